@@ -32,16 +32,15 @@ use nokhwa::{
     pixel_format::{MJPEG, NV12, YUYV},
     query,
     utils::{CameraFormat, CameraIndex, RequestedFormat, RequestedFormatType, Resolution},
-    CallbackCamera, Camera,
+    CallbackCamera, Camera, SubscriptionMode,
 };
 use std::fs::File;
 use std::io::Write;
 use std::str::FromStr;
-use std::sync::Arc;
 use std::time::Duration;
 
 struct CaptureState {
-    receiver: Arc<Receiver<FrameBuffer>>,
+    receiver: Receiver<FrameBuffer>,
     format: CameraFormat,
 }
 
@@ -187,7 +186,7 @@ impl RequestedCliFormat {
 
                 let resolution = Resolution::new(x, y);
                 let camera_format = CameraFormat::new(resolution, fourcc, fps);
-                Some(RequestedFormat::new(RequestedFormatType::Closest(
+                Some(RequestedFormat::new(RequestedFormatType::Exact(
                     camera_format,
                 )))
             }
@@ -324,14 +323,9 @@ fn nokhwa_main() {
             };
 
             if display {
-                let (sender, receiver) = flume::unbounded();
-                let (sender, receiver) = (Arc::new(sender), Arc::new(receiver));
-                let sender_clone = sender.clone();
-
-                let mut camera = CallbackCamera::new(index, requested, move |buf| {
-                    sender_clone.send(buf).expect("Error sending frame!!!!");
-                })
-                .unwrap();
+                let mut camera = CallbackCamera::new(index, requested, |_| {}).unwrap();
+                // only the newest frame is worth drawing
+                let receiver = camera.subscribe(SubscriptionMode::LatestOnly).unwrap();
 
                 let camera_info = camera.info().clone();
                 let format = camera.camera_format().unwrap();
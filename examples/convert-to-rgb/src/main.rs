@@ -23,6 +23,7 @@ static mut EGUI_TEXTURE_TIMES: Vec<Duration> = Vec::new();
 #[derive(Default)]
 struct MyApp {
     camera: Option<CallbackCamera>,
+    last_stats: Option<Instant>,
 }
 
 impl MyApp {
@@ -91,7 +92,6 @@ impl MyApp {
                     EGUI_TEXTURE_TIMES.clear();
 
                     LAST_STATS = Lazy::new(|| Instant::now());
-                    println!("Average frame rate: {:?}", FRAME_COUNTER as f32 / 10.0);
                 }
             }
         })
@@ -136,6 +136,13 @@ impl MyApp {
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.request_repaint();
+        if let Some(camera) = &self.camera {
+            let last_stats = self.last_stats.get_or_insert_with(Instant::now);
+            if last_stats.elapsed() >= Duration::from_secs(STATS_INTERVAL_SECS) {
+                println!("{}", camera.stats().unwrap());
+                *last_stats = Instant::now();
+            }
+        }
         egui::CentralPanel::default().show(ctx, |ui| match unsafe { TEXTURE.as_ref() } {
             None => {
                 ui.label("Loading...");
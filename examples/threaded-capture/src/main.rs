@@ -14,6 +14,8 @@
  * limitations under the License.
  */
 
+use std::time::{Duration, Instant};
+
 use nokhwa::{
     query,
     utils::{ApiBackend, CameraIndex, RequestedFormat, RequestedFormatType},
@@ -39,9 +41,15 @@ fn main() {
     })
     .unwrap();
     threaded.open_stream().unwrap();
-    #[allow(clippy::empty_loop)] // keep it running
-    loop {
-        let frame = threaded.poll_frame().unwrap();
+    // run for ten seconds, waking up regularly even if the camera stalls
+    let stop_at = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < stop_at {
+        let Some(frame) = threaded
+            .poll_frame_timeout(Duration::from_millis(100))
+            .unwrap()
+        else {
+            continue;
+        };
         println!(
             "{}x{} {} frame",
             frame.resolution().width(),
@@ -17,34 +17,73 @@
 mod internal {
     use four_cc::FourCC;
     use nokhwa_core::{
-        buffer::FrameBuffer,
+        buffer::{
+            BufferHandle, BufferPool, ExternalBuffer, FrameBuffer, FrameMetadata, PlaneLayout,
+            PooledBuffer,
+        },
+        conversion::{ColorSpace, HardwareDecoder, YuvMatrix, YuvRange},
         error::NokhwaError,
-        traits::CaptureBackendTrait,
+        pixel_format::{H264, HEVC, I420, NV12, YUYV},
+        traits::{CaptureDevice, VirtualBackendTrait},
         types::{
-            ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo,
-            ControlValueDescription, ControlValueSetter, KnownCameraControl,
-            KnownCameraControlFlag, RequestedFormat, RequestedFormatType, Resolution,
+            ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, CameraPosition,
+            ControlValueDescription, ControlValueSetter, Exposure, Focus, FrameRate,
+            KnownCameraControl, KnownCameraControlFlag, NodeKind, PtzCapabilities, PtzRange, Rect,
+            RequestedFormat, RequestedFormatType, Resolution, Rotation, SessionEvent, StreamStats,
+            ThreadPriority, UsbDevice, WhiteBalance, XuQuery,
         },
     };
     use std::{
+        alloc::{self, Layout},
+        any::Any,
         borrow::Cow,
-        collections::HashMap,
+        collections::{BTreeMap, HashMap},
+        ffi::OsStr,
+        fs,
         io::{self, ErrorKind},
+        marker::PhantomData,
+        mem,
+        ops::Range,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+            unix::fs::MetadataExt,
+        },
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc,
+        },
+        thread::JoinHandle,
+        time::{Duration, Instant},
     };
     use v4l::{
+        buffer::Type as BufferType,
+        capability::Flags as CapabilityFlags,
         control::{Control, Flags, Type, Value},
+        device::Handle,
+        format::{Colorspace, Quantization},
         frameinterval::FrameIntervalEnum,
         framesize::FrameSizeEnum,
-        io::traits::CaptureStream,
-        prelude::MmapStream,
-        video::{capture::Parameters, Capture},
-        Device, Format, FourCC as v4l2_FourCC,
+        memory::Memory,
+        v4l2::{self, vidioc::_IOC_TYPE},
+        video::{capture::Parameters, output::Parameters as OutputParameters, Capture},
+        Device, Format, FourCC as v4l2_FourCC, Fraction,
     };
     use v4l2_sys_mit::{
+        v4l2_buffer, v4l2_event, v4l2_event_subscription, v4l2_exportbuffer, v4l2_ext_control,
+        v4l2_ext_controls, v4l2_fmtdesc, v4l2_format, v4l2_pix_format, v4l2_pix_format_mplane,
+        v4l2_plane, v4l2_rect, v4l2_requestbuffers, v4l2_selection, v4l2_streamparm,
+        v4l2_streamparm__bindgen_ty_1, V4L2_CID_AUTO_WHITE_BALANCE,
         V4L2_CID_BACKLIGHT_COMPENSATION, V4L2_CID_BRIGHTNESS, V4L2_CID_CONTRAST, V4L2_CID_EXPOSURE,
-        V4L2_CID_FOCUS_RELATIVE, V4L2_CID_GAIN, V4L2_CID_GAMMA, V4L2_CID_HUE,
-        V4L2_CID_IRIS_RELATIVE, V4L2_CID_PAN_RELATIVE, V4L2_CID_SATURATION, V4L2_CID_SHARPNESS,
-        V4L2_CID_TILT_RELATIVE, V4L2_CID_WHITE_BALANCE_TEMPERATURE, V4L2_CID_ZOOM_RELATIVE,
+        V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO, V4L2_CID_FLASH_LED_MODE,
+        V4L2_CID_FOCUS_ABSOLUTE, V4L2_CID_FOCUS_AUTO, V4L2_CID_FOCUS_RELATIVE, V4L2_CID_GAIN,
+        V4L2_CID_GAMMA, V4L2_CID_HFLIP, V4L2_CID_HUE, V4L2_CID_IRIS_RELATIVE,
+        V4L2_CID_MIN_BUFFERS_FOR_CAPTURE, V4L2_CID_PAN_ABSOLUTE, V4L2_CID_PAN_RELATIVE,
+        V4L2_CID_POWER_LINE_FREQUENCY, V4L2_CID_SATURATION, V4L2_CID_SHARPNESS,
+        V4L2_CID_TILT_ABSOLUTE, V4L2_CID_TILT_RELATIVE, V4L2_CID_WHITE_BALANCE_TEMPERATURE,
+        V4L2_CID_ZOOM_ABSOLUTE, V4L2_CID_ZOOM_RELATIVE, V4L2_EVENT_CTRL, V4L2_EVENT_CTRL_CH_VALUE,
+        V4L2_EVENT_SOURCE_CHANGE, V4L2_EVENT_SRC_CH_RESOLUTION, V4L2_SEL_TGT_CROP,
+        V4L2_SEL_TGT_CROP_DEFAULT, VIDEO_MAX_PLANES,
     };
 
     /// Attempts to convert a [`KnownCameraControl`] into a V4L2 Control ID.
@@ -67,6 +106,8 @@ mod internal {
             KnownCameraControl::Exposure => V4L2_CID_EXPOSURE,
             KnownCameraControl::Iris => V4L2_CID_IRIS_RELATIVE,
             KnownCameraControl::Focus => V4L2_CID_FOCUS_RELATIVE,
+            KnownCameraControl::Torch => V4L2_CID_FLASH_LED_MODE,
+            KnownCameraControl::PowerlineFrequency => V4L2_CID_POWER_LINE_FREQUENCY,
             KnownCameraControl::Other(id) => id as u32,
         }
     }
@@ -91,74 +132,552 @@ mod internal {
             V4L2_CID_EXPOSURE => KnownCameraControl::Exposure,
             V4L2_CID_IRIS_RELATIVE => KnownCameraControl::Iris,
             V4L2_CID_FOCUS_RELATIVE => KnownCameraControl::Focus,
+            V4L2_CID_FLASH_LED_MODE => KnownCameraControl::Torch,
+            V4L2_CID_POWER_LINE_FREQUENCY => KnownCameraControl::PowerlineFrequency,
             id => KnownCameraControl::Other(id as u128),
         }
     }
 
+    /// Gets an ID for the device node at `path` that stays the same across reboots and re-plugging.
+    ///
+    /// This is the `/dev/v4l/by-id` link (based on the USB serial number) if there is one, otherwise the `/dev/v4l/by-path`
+    /// link (based on the port it is plugged into), otherwise the node path itself.
+    pub fn stable_unique_id(path: &Path) -> String {
+        let Ok(node) = fs::canonicalize(path) else {
+            return path.to_string_lossy().to_string();
+        };
+        ["/dev/v4l/by-id", "/dev/v4l/by-path"]
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .map(|entry| entry.path())
+            .find(|link| fs::canonicalize(link).is_ok_and(|target| target == node))
+            .unwrap_or(node)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // _IOWR('V', 94 / 95, struct v4l2_selection), which the v4l crate does not define
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    const VIDIOC_G_SELECTION: _IOC_TYPE = 0xc040_565e_u32 as _IOC_TYPE;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    const VIDIOC_S_SELECTION: _IOC_TYPE = 0xc040_565f_u32 as _IOC_TYPE;
+    // _IOR('V', 89, struct v4l2_event) and _IOW('V', 90, struct v4l2_event_subscription), which the v4l crate does not
+    // define either. `struct v4l2_event` holds a `timespec`, so its size depends on the platform.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    const VIDIOC_DQEVENT: _IOC_TYPE =
+        ((2 << 30) | (mem::size_of::<v4l2_event>() << 16) | ((b'V' as usize) << 8) | 89)
+            as _IOC_TYPE;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    const VIDIOC_SUBSCRIBE_EVENT: _IOC_TYPE = ((1 << 30)
+        | (mem::size_of::<v4l2_event_subscription>() << 16)
+        | ((b'V' as usize) << 8)
+        | 90) as _IOC_TYPE;
+
+    // V4L2_CID_CAMERA_CLASS_BASE + 34 and + 35, not present in older kernel headers
+    const V4L2_CID_CAMERA_ORIENTATION: u32 = 0x009a_0922;
+    const V4L2_CID_CAMERA_SENSOR_ROTATION: u32 = 0x009a_0923;
+
+    /// Reads the sensor mounting rotation of `device`, which is only reported for sensors the device tree describes.
+    fn sensor_orientation(device: &Device) -> Rotation {
+        match device
+            .control(V4L2_CID_CAMERA_SENSOR_ROTATION)
+            .map(|ctrl| ctrl.value)
+        {
+            Ok(Value::Integer(degrees)) => i32::try_from(degrees)
+                .ok()
+                .and_then(Rotation::from_degrees)
+                .unwrap_or_default(),
+            _ => Rotation::Deg0,
+        }
+    }
+
+    /// Reads where `device` is mounted, which like the rotation is only reported for sensors the device tree describes.
+    fn camera_position(device: &Device) -> CameraPosition {
+        match device
+            .control(V4L2_CID_CAMERA_ORIENTATION)
+            .map(|ctrl| ctrl.value)
+        {
+            Ok(Value::Integer(0)) => CameraPosition::Front,
+            Ok(Value::Integer(1)) => CameraPosition::Back,
+            Ok(Value::Integer(2)) => CameraPosition::External,
+            _ => CameraPosition::Unknown,
+        }
+    }
+
+    /// Maps the colorspace and quantization of `format` to a [`ColorSpace`], following the defaults of the kernel for fields the
+    /// driver left alone. Returns `None` if the driver did not set a colorspace, or one with a matrix the converters lack.
+    fn color_space(format: &Format) -> Option<ColorSpace> {
+        let matrix = match format.colorspace {
+            Colorspace::SMPTE170M
+            | Colorspace::NTSC
+            | Colorspace::EBUTech3212
+            | Colorspace::JPEG
+            | Colorspace::SRGB
+            | Colorspace::OPRGB => YuvMatrix::Bt601,
+            Colorspace::Rec709 | Colorspace::DCIP3 => YuvMatrix::Bt709,
+            _ => return None,
+        };
+        let range = match format.quantization {
+            Quantization::FullRange => YuvRange::Full,
+            Quantization::LimitedRange => YuvRange::Limited,
+            Quantization::Default if matches!(format.colorspace, Colorspace::JPEG) => {
+                YuvRange::Full
+            }
+            Quantization::Default => YuvRange::Limited,
+        };
+        Some(ColorSpace::new(matrix, range))
+    }
+
+    /// Gets the queue `device` captures on, which is the multi-planar one for devices that only have that.
+    fn capture_buffer_type(device: &Device) -> io::Result<BufferType> {
+        let capabilities = device.query_caps()?.capabilities;
+        Ok(
+            if !capabilities.contains(CapabilityFlags::VIDEO_CAPTURE)
+                && capabilities.contains(CapabilityFlags::VIDEO_CAPTURE_MPLANE)
+            {
+                BufferType::VideoCaptureMplane
+            } else {
+                BufferType::VideoCapture
+            },
+        )
+    }
+
+    /// Lists the [`FourCC`](v4l2_FourCC)s the `buffer_type` queue of `device` takes.
+    fn capture_fourccs(device: &Device, buffer_type: BufferType) -> io::Result<Vec<v4l2_FourCC>> {
+        if matches!(buffer_type, BufferType::VideoCapture) {
+            return Ok(Capture::enum_formats(device)?
+                .into_iter()
+                .map(|description| description.fourcc)
+                .collect());
+        }
+        let mut fourccs = vec![];
+        for index in 0.. {
+            let mut description = v4l2_fmtdesc {
+                index,
+                type_: buffer_type as u32,
+                ..unsafe { mem::zeroed() }
+            };
+            // the driver errors once `index` is past the last format
+            if unsafe {
+                v4l2::ioctl(
+                    device.handle().fd(),
+                    v4l2::vidioc::VIDIOC_ENUM_FMT,
+                    std::ptr::addr_of_mut!(description).cast(),
+                )
+            }
+            .is_err()
+            {
+                break;
+            }
+            fourccs.push(v4l2_FourCC::from(description.pixelformat));
+        }
+        Ok(fourccs)
+    }
+
+    /// Reads the format of the `buffer_type` queue of `device`, with the bytes per line of each of its planes.
+    /// Single-planar formats have one.
+    fn capture_format(device: &Device, buffer_type: BufferType) -> io::Result<(Format, Vec<u32>)> {
+        if !matches!(
+            buffer_type,
+            BufferType::VideoCaptureMplane | BufferType::VideoOutputMplane
+        ) {
+            let format = if matches!(buffer_type, BufferType::VideoOutput) {
+                v4l::video::Output::format(device)?
+            } else {
+                Capture::format(device)?
+            };
+            return Ok((format, vec![format.stride]));
+        }
+        let mut format = v4l2_format {
+            type_: buffer_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                device.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_FMT,
+                std::ptr::addr_of_mut!(format).cast(),
+            )?;
+        }
+        let multi_planar: v4l2_pix_format_mplane = unsafe { format.fmt.pix_mp };
+        let plane_formats = multi_planar.plane_fmt;
+        let plane_formats =
+            &plane_formats[..usize::from(multi_planar.num_planes).min(plane_formats.len())];
+        // the v4l crate only knows single-planar formats, whose size covers every plane
+        let single_planar = v4l2_pix_format {
+            width: multi_planar.width,
+            height: multi_planar.height,
+            pixelformat: multi_planar.pixelformat,
+            field: multi_planar.field,
+            bytesperline: plane_formats.first().map_or(0, |plane| plane.bytesperline),
+            sizeimage: plane_formats.iter().map(|plane| plane.sizeimage).sum(),
+            colorspace: multi_planar.colorspace,
+            quantization: u32::from(multi_planar.quantization),
+            xfer_func: u32::from(multi_planar.xfer_func),
+            ..unsafe { mem::zeroed() }
+        };
+        Ok((
+            Format::from(single_planar),
+            plane_formats
+                .iter()
+                .map(|plane| plane.bytesperline)
+                .collect(),
+        ))
+    }
+
+    /// Sets the resolution and [`FourCC`](v4l2_FourCC) of `format` on the `buffer_type` queue of `device`.
+    fn set_capture_format(
+        device: &Device,
+        buffer_type: BufferType,
+        format: &Format,
+    ) -> io::Result<()> {
+        if matches!(buffer_type, BufferType::VideoOutput) {
+            return v4l::video::Output::set_format(device, format).map(|_| ());
+        }
+        if !matches!(
+            buffer_type,
+            BufferType::VideoCaptureMplane | BufferType::VideoOutputMplane
+        ) {
+            return Capture::set_format(device, format).map(|_| ());
+        }
+        let mut v4l2_format = v4l2_format {
+            type_: buffer_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        v4l2_format.fmt.pix_mp = v4l2_pix_format_mplane {
+            width: format.width,
+            height: format.height,
+            pixelformat: format.fourcc.into(),
+            field: format.field_order as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                device.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_FMT,
+                std::ptr::addr_of_mut!(v4l2_format).cast(),
+            )
+        }
+    }
+
+    /// The frame rate of a V4L2 frame interval, which is the time between frames in seconds.
+    fn interval_frame_rate(interval: Fraction) -> FrameRate {
+        FrameRate::new(interval.denominator, interval.numerator)
+    }
+
+    /// The V4L2 frame interval of `frame_rate`.
+    fn frame_interval(frame_rate: FrameRate) -> Fraction {
+        Fraction::new(frame_rate.denominator(), frame_rate.numerator())
+    }
+
+    /// Reads the streaming parameters of the `buffer_type` queue of `device`.
+    fn capture_params(device: &Device, buffer_type: BufferType) -> io::Result<Parameters> {
+        if !matches!(buffer_type, BufferType::VideoCaptureMplane) {
+            return Capture::params(device);
+        }
+        let mut params = v4l2_streamparm {
+            type_: buffer_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                device.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_PARM,
+                std::ptr::addr_of_mut!(params).cast(),
+            )?;
+            Ok(Parameters::from(params.parm.capture))
+        }
+    }
+
+    /// Sets the streaming parameters of the `buffer_type` queue of `device`.
+    fn set_capture_params(
+        device: &Device,
+        buffer_type: BufferType,
+        params: &Parameters,
+    ) -> io::Result<()> {
+        if !matches!(buffer_type, BufferType::VideoCaptureMplane) {
+            return Capture::set_params(device, params).map(|_| ());
+        }
+        let mut v4l2_params = v4l2_streamparm {
+            type_: buffer_type as u32,
+            parm: v4l2_streamparm__bindgen_ty_1 {
+                capture: (*params).into(),
+            },
+        };
+        unsafe {
+            v4l2::ioctl(
+                device.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_PARM,
+                std::ptr::addr_of_mut!(v4l2_params).cast(),
+            )
+        }
+    }
+
+    fn buffer_timestamp(buffer: &v4l2_buffer) -> Duration {
+        Duration::new(
+            buffer.timestamp.tv_sec as u64,
+            (buffer.timestamp.tv_usec * 1000) as u32,
+        )
+    }
+
+    /// Gets what a node with the device capabilities `capabilities` delivers, or `None` if it is not a camera at all,
+    /// such as a memory-to-memory decoder or an output-only node.
+    fn node_kind(capabilities: CapabilityFlags) -> Option<NodeKind> {
+        if capabilities
+            .intersects(CapabilityFlags::VIDEO_CAPTURE | CapabilityFlags::VIDEO_CAPTURE_MPLANE)
+        {
+            Some(NodeKind::Capture)
+        } else if capabilities.contains(CapabilityFlags::META_CAPTURE) {
+            Some(NodeKind::Metadata)
+        } else {
+            None
+        }
+    }
+
+    /// Adds what `udev` knows about the device node at `path` to `info`: the IDs of the USB device it belongs to, and its
+    /// `ID_PATH`, falling back to `bus_info` from the driver.
+    #[cfg(feature = "udev")]
+    fn with_device_details(
+        mut info: CameraInfo,
+        path: &Path,
+        bus_info: Option<&str>,
+    ) -> CameraInfo {
+        let text = |value: Option<&OsStr>| {
+            value
+                .map(|value| value.to_string_lossy().trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        // `path` may be one of the `/dev/v4l` links
+        let node = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let device = node
+            .file_name()
+            .map(|name| Path::new("/sys/class/video4linux").join(name))
+            .and_then(|syspath| udev::Device::from_syspath(&syspath).ok());
+
+        let usb_device = device
+            .as_ref()
+            .and_then(|device| {
+                device
+                    .parent_with_subsystem_devtype("usb", "usb_device")
+                    .ok()
+            })
+            .flatten()
+            .and_then(|usb| {
+                let id = |attribute: &str| {
+                    text(usb.attribute_value(attribute))
+                        .and_then(|id| u16::from_str_radix(&id, 16).ok())
+                };
+                Some(UsbDevice::new(
+                    id("idVendor")?,
+                    id("idProduct")?,
+                    text(usb.attribute_value("serial")),
+                ))
+            });
+        if let Some(usb_device) = usb_device {
+            info = info.with_usb_device(usb_device);
+        }
+        let bus_path = device
+            .as_ref()
+            .and_then(|device| text(device.property_value("ID_PATH")))
+            .or_else(|| bus_info.map(str::to_string).filter(|bus| !bus.is_empty()));
+        match bus_path {
+            Some(bus_path) => info.with_bus_path(&bus_path),
+            None => info,
+        }
+    }
+
+    /// Adds the IDs of the USB device the node at `path` belongs to to `info`, read straight from sysfs, and `bus_info`
+    /// from the driver as its bus path. This is what is used without the `udev` feature.
+    #[cfg(not(feature = "udev"))]
+    fn with_device_details(
+        mut info: CameraInfo,
+        path: &Path,
+        bus_info: Option<&str>,
+    ) -> CameraInfo {
+        // `path` may be one of the `/dev/v4l` links
+        let node = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        // the `device` link points at the USB interface, whose parent is the USB device with the IDs
+        let usb_device = node
+            .file_name()
+            .and_then(|name| {
+                fs::canonicalize(
+                    Path::new("/sys/class/video4linux")
+                        .join(name)
+                        .join("device"),
+                )
+                .ok()
+            })
+            .and_then(|device| {
+                device
+                    .ancestors()
+                    .find(|ancestor| ancestor.join("idVendor").is_file())
+                    .map(Path::to_path_buf)
+            })
+            .and_then(|usb| {
+                let text = |attribute: &str| {
+                    fs::read_to_string(usb.join(attribute))
+                        .ok()
+                        .map(|value| value.trim().to_string())
+                        .filter(|value| !value.is_empty())
+                };
+                let id = |attribute: &str| {
+                    text(attribute).and_then(|id| u16::from_str_radix(&id, 16).ok())
+                };
+                Some(UsbDevice::new(
+                    id("idVendor")?,
+                    id("idProduct")?,
+                    text("serial"),
+                ))
+            });
+        if let Some(usb_device) = usb_device {
+            info = info.with_usb_device(usb_device);
+        }
+        match bus_info.filter(|bus| !bus.is_empty()) {
+            Some(bus_path) => info.with_bus_path(bus_path),
+            None => info,
+        }
+    }
+
     /// query v4l2 cameras
+    ///
+    /// Only nodes that capture frames are listed, the second node UVC cameras have for their metadata is left out, as
+    /// are memory-to-memory devices. See [`query_all_nodes()`] for those.
+    /// # Errors
+    /// This does not currently error.
     #[allow(clippy::unnecessary_wraps)]
-    #[allow(clippy::cast_possible_truncation)]
     pub fn query() -> Result<Vec<CameraInfo>, NokhwaError> {
-        Ok({
-            let camera_info: Vec<CameraInfo> = v4l::context::enum_devices()
-                .iter()
-                .map(|node| {
-                    CameraInfo::new(
-                        &node
-                            .name()
-                            .unwrap_or(format!("{}", node.path().to_string_lossy())),
-                        &format!("Video4Linux Device @ {}", node.path().to_string_lossy()),
-                        "",
-                        &node
-                            .name()
-                            .unwrap_or(format!("{}", node.path().to_string_lossy())),
-                        "Video4Linux Device",
-                        "",
-                    )
-                })
-                .collect();
-            camera_info
-        })
+        Ok(query_all_nodes()?
+            .into_iter()
+            .filter(|info| info.node_kind() == NodeKind::Capture)
+            .collect())
+    }
+
+    /// Lists every V4L2 node that captures frames or metadata, with [`CameraInfo::node_kind()`] telling them apart.
+    ///
+    /// Nodes that cannot be opened, e.g. for lack of permissions, are listed as [`NodeKind::Capture`].
+    /// # Errors
+    /// This does not currently error.
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn query_all_nodes() -> Result<Vec<CameraInfo>, NokhwaError> {
+        let mut nodes = v4l::context::enum_devices();
+        // the kernel lists nodes in no particular order
+        nodes.sort_by_key(v4l::context::Node::index);
+        Ok(nodes
+            .iter()
+            .filter_map(|node| {
+                let name = node
+                    .name()
+                    .unwrap_or(format!("{}", node.path().to_string_lossy()));
+                let device = Device::with_path(node.path()).ok();
+                let caps = device.as_ref().and_then(|device| device.query_caps().ok());
+                let kind = match &caps {
+                    Some(caps) => node_kind(caps.capabilities)?,
+                    None => NodeKind::Capture,
+                };
+                let orientation = device.as_ref().map(sensor_orientation).unwrap_or_default();
+                let position = device.as_ref().map(camera_position).unwrap_or_default();
+                let info = CameraInfo::new(
+                    &stable_unique_id(node.path()),
+                    &name,
+                    "",
+                    &name,
+                    "Video4Linux Device",
+                    "",
+                )
+                .with_sensor_orientation(orientation)
+                .with_camera_position(position)
+                .with_node_kind(kind);
+                Some(with_device_details(
+                    info,
+                    node.path(),
+                    caps.as_ref().map(|caps| caps.bus.as_str()),
+                ))
+            })
+            .collect())
     }
 
     /// The backend struct that interfaces with V4L2.
-    /// To see what this does, please see [`CaptureBackendTrait`].
+    /// To see what this does, please see [`CaptureDevice`].
     /// # Quirks
-    /// - Calling [`set_resolution()`](CaptureBackendTrait::set_resolution), [`set_frame_rate()`](CaptureBackendTrait::set_frame_rate), or [`set_frame_format()`](CaptureBackendTrait::set_frame_format) each internally calls [`set_camera_format()`](CaptureBackendTrait::set_camera_format).
+    /// - Calling [`set_resolution()`](CaptureDevice::set_resolution), [`set_frame_rate()`](CaptureDevice::set_frame_rate), or [`set_frame_format()`](CaptureDevice::set_frame_format) each internally calls [`set_camera_format()`](CaptureDevice::set_camera_format).
+    /// - Frames are copied out of the driver's buffers, unless they are exported as DMA-BUFs, see
+    ///   [`set_dmabuf_export()`](V4LCaptureDevice::set_dmabuf_export).
+    /// - Devices that only have the multi-planar API (`VIDEO_CAPTURE_MPLANE`, common on SoCs) are captured through it. Formats
+    ///   with each plane in its own buffer, such as `NM12` ([`NV12M`](nokhwa_core::pixel_format::NV12M)), are copied
+    ///   into one buffer with [`FrameBuffer::plane_layouts()`] saying where each plane is. Their frames cannot be
+    ///   exported as DMA-BUFs.
+    /// - While the stream is open, controls changed by anything else (e.g. another process) and resolution changes of
+    ///   the source are sent to [`events()`](V4LCaptureDevice::events), if the driver reports them.
+    /// - Cameras that encode H.264 or HEVC themselves hand out the compressed stream, unless it is decoded on a V4L2
+    ///   memory-to-memory decoder, see [`set_decode_compressed()`](V4LCaptureDevice::set_decode_compressed).
+    /// - 4 buffers are requested from the driver and mapped into memory, which [`set_buffer_count()`](V4LCaptureDevice::set_buffer_count)
+    ///   and [`set_buffer_memory()`](V4LCaptureDevice::set_buffer_memory) change.
+    /// - Waiting for a frame polls the device, and can be cut short from another thread with a [`WakeHandle`]. The device
+    ///   is also readable through [`AsRawFd`] while a frame is ready, for event loops.
+    /// - Capture nodes fed by a media controller pipeline (sensor, ISP, ...) are used as the pipeline is set up, unless
+    ///   [`set_configure_pipeline()`](V4LCaptureDevice::set_configure_pipeline) is on.
     pub struct V4LCaptureDevice<'a> {
         camera_format: CameraFormat,
         camera_info: CameraInfo,
+        path: PathBuf,
         device: Device,
-        stream_handle: Option<MmapStream<'a>>,
+        buffer_type: BufferType,
+        stream: Option<BufferStream>,
+        dmabuf_export: bool,
+        dmabuf_stream: Option<DmaBufStream>,
+        decode_compressed: bool,
+        decoder: Option<V4L2VideoDecoder>,
+        buffer_count: u32,
+        buffer_memory: BufferMemory,
+        configure_pipeline: bool,
+        last_sequence: Option<u32>,
+        frames_dropped: u64,
+        pool: BufferPool,
+        color_space: Option<ColorSpace>,
+        event_sender: mpsc::Sender<SessionEvent>,
+        event_receiver: mpsc::Receiver<SessionEvent>,
+        event_watcher: Option<EventWatcher>,
+        wake_handle: WakeHandle,
+        _lifetime: PhantomData<&'a ()>,
     }
 
     impl<'a> V4LCaptureDevice<'a> {
         /// Creates a new capture device using the `V4L2` backend. Indexes are gives to devices by the OS, and usually numbered by order of discovery.
+        ///
+        /// A [`CameraIndex::String`] is either a number, or a path to the device node such as a [`stable_unique_id`].
         /// # Errors
         /// This function will error if the camera is currently busy or if `V4L2` can't read device information.
         #[allow(clippy::too_many_lines)]
         pub fn new(index: &CameraIndex, cam_fmt: RequestedFormat) -> Result<Self, NokhwaError> {
             let index = index.clone();
-            let device = match Device::new(index.as_index()? as usize) {
+            let path = match index.as_index() {
+                Ok(number) => PathBuf::from(format!("/dev/video{number}")),
+                Err(_) => PathBuf::from(index.as_string()),
+            };
+            let device = match Device::with_path(&path) {
                 Ok(dev) => dev,
                 Err(why) => {
                     return Err(NokhwaError::OpenDeviceError(
                         index.to_string(),
                         format!("V4L2 Error: {}", why),
-                    ))
+                    )
+                    .with_io_error(&why))
                 }
             };
 
+            let buffer_type =
+                capture_buffer_type(&device).map_err(|why| NokhwaError::GetPropertyError {
+                    property: "Device Capabilities".to_string(),
+                    error: why.to_string(),
+                })?;
+
             // get all formats
             // get all fcc
             let mut camera_formats = vec![];
 
-            let frame_formats = match device.enum_formats() {
-                Ok(formats) => {
-                    let mut frame_format_vec = vec![];
-                    formats
-                        .iter()
-                        .for_each(|fmt| frame_format_vec.push(fmt.fourcc));
+            let frame_formats = match capture_fourccs(&device, buffer_type) {
+                Ok(mut frame_format_vec) => {
                     frame_format_vec.dedup();
                     Ok(frame_format_vec)
                 }
@@ -198,15 +717,11 @@ mod internal {
                             .into_iter()
                             .flat_map(|x| match x.interval {
                                 FrameIntervalEnum::Discrete(dis) => {
-                                    if dis.numerator == 1 {
-                                        vec![CameraFormat::new(
-                                            Resolution::new(x.width, x.height),
-                                            framefmt,
-                                            dis.denominator,
-                                        )]
-                                    } else {
-                                        vec![]
-                                    }
+                                    vec![CameraFormat::new_exact(
+                                        Resolution::new(x.width, x.height),
+                                        framefmt,
+                                        interval_frame_rate(dis),
+                                    )]
                                 }
                                 FrameIntervalEnum::Stepwise(step) => {
                                     let mut intvec = vec![];
@@ -236,21 +751,29 @@ mod internal {
                     error: "Failed to Fufill".to_string(),
                 })?;
 
-            if let Err(why) = device.set_format(&Format::new(
-                format.width(),
-                format.height(),
-                v4l2_FourCC::new(&format.format().0),
-            )) {
+            if let Err(why) = set_capture_format(
+                &device,
+                buffer_type,
+                &Format::new(
+                    format.width(),
+                    format.height(),
+                    v4l2_FourCC::new(&format.format().0),
+                ),
+            ) {
                 return Err(NokhwaError::SetPropertyError {
                     property: "Resolution, FourCC".to_string(),
                     value: format.to_string(),
                     error: why.to_string(),
                 });
             }
-            if let Err(why) = device.set_params(&Parameters::with_fps(format.frame_rate())) {
+            if let Err(why) = set_capture_params(
+                &device,
+                buffer_type,
+                &Parameters::new(frame_interval(format.exact_frame_rate())),
+            ) {
                 return Err(NokhwaError::SetPropertyError {
                     property: "Frame rate".to_string(),
-                    value: format.frame_rate().to_string(),
+                    value: format.exact_frame_rate().to_string(),
                     error: why.to_string(),
                 });
             }
@@ -262,18 +785,44 @@ mod internal {
                     error: why.to_string(),
                 })?;
 
+            let camera_info = CameraInfo::new(
+                &stable_unique_id(&path),
+                &device_caps.card,
+                &device_caps.driver,
+                &format!("{} {:?}", device_caps.bus, device_caps.version),
+                "Video4Linux Device",
+                "",
+            )
+            .with_sensor_orientation(sensor_orientation(&device))
+            .with_camera_position(camera_position(&device));
+
+            let (event_sender, event_receiver) = mpsc::channel();
+            let wake_handle = WakeHandle::new().map_err(|why| {
+                NokhwaError::OpenDeviceError(path.display().to_string(), why.to_string())
+            })?;
             let mut v4l2 = V4LCaptureDevice {
                 camera_format: format,
-                camera_info: CameraInfo::new(
-                    &device_caps.card,
-                    &device_caps.driver,
-                    &format!("{} {:?}", device_caps.bus, device_caps.version),
-                    &device_caps.driver,
-                    &device_caps.bus,
-                    "Front",
-                ),
+                camera_info: with_device_details(camera_info, &path, Some(&device_caps.bus)),
+                path,
                 device,
-                stream_handle: None,
+                buffer_type,
+                stream: None,
+                dmabuf_export: false,
+                dmabuf_stream: None,
+                decode_compressed: false,
+                decoder: None,
+                buffer_count: STREAM_BUFFER_COUNT,
+                buffer_memory: BufferMemory::Mmap,
+                configure_pipeline: false,
+                last_sequence: None,
+                frames_dropped: 0,
+                pool: BufferPool::default(),
+                color_space: None,
+                event_sender,
+                event_receiver,
+                event_watcher: None,
+                wake_handle,
+                _lifetime: PhantomData,
             };
 
             v4l2.force_refresh_camera_format()?;
@@ -339,30 +888,12 @@ mod internal {
         /// # Errors
         /// If the internal representation in the driver is invalid, this will error.
         pub fn force_refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
-            match self.device.format() {
-                Ok(format) => {
+            match capture_format(&self.device, self.buffer_type) {
+                Ok((format, _)) => {
                     let frame_format = FourCC(format.fourcc.repr);
 
-                    let fps = match self.device.params() {
-                        Ok(params) => {
-                            if params.interval.numerator != 1
-                                || params.interval.denominator % params.interval.numerator != 0
-                            {
-                                return Err(NokhwaError::GetPropertyError {
-                                    property: "V4L2 FrameRate".to_string(),
-                                    error: format!(
-                                        "Framerate not whole number: {} / {}",
-                                        params.interval.denominator, params.interval.numerator
-                                    ),
-                                });
-                            }
-
-                            if params.interval.numerator == 1 {
-                                params.interval.denominator
-                            } else {
-                                params.interval.denominator / params.interval.numerator
-                            }
-                        }
+                    let frame_rate = match capture_params(&self.device, self.buffer_type) {
+                        Ok(params) => interval_frame_rate(params.interval),
                         Err(why) => {
                             return Err(NokhwaError::GetPropertyError {
                                 property: "V4L2 FrameRate".to_string(),
@@ -371,11 +902,12 @@ mod internal {
                         }
                     };
 
-                    self.camera_format = CameraFormat::new(
+                    self.camera_format = CameraFormat::new_exact(
                         Resolution::new(format.width, format.height),
                         frame_format,
-                        fps,
+                        frame_rate,
                     );
+                    self.color_space = color_space(&format);
                     Ok(())
                 }
                 Err(why) => Err(NokhwaError::GetPropertyError {
@@ -384,212 +916,414 @@ mod internal {
                 }),
             }
         }
-    }
 
-    impl<'a> CaptureBackendTrait for V4LCaptureDevice<'a> {
-        fn backend(&self) -> ApiBackend {
-            ApiBackend::Video4Linux
+        /// Reads the exposure and gain the driver is using right now. Controls the device does not have are left as `None`.
+        #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+        fn frame_metadata(&self) -> FrameMetadata {
+            let integer_control = |id| match self.device.control(id).map(|ctrl| ctrl.value) {
+                Ok(Value::Integer(value)) => Some(value),
+                _ => None,
+            };
+            // V4L2_CID_EXPOSURE_ABSOLUTE is in units of 100µs
+            let exposure = integer_control(V4L2_CID_EXPOSURE_ABSOLUTE)
+                .map(|value| Duration::from_micros(value.max(0) as u64 * 100));
+            let gain = integer_control(V4L2_CID_GAIN).map(|value| value as f32);
+            FrameMetadata::new(exposure, None, gain, None)
         }
 
-        fn camera_info(&self) -> &CameraInfo {
-            &self.camera_info
+        /// Hands frames out in the driver's buffers instead of copying them, with each buffer exported as a DMA-BUF
+        /// (`VIDIOC_EXPBUF`). [`FrameBuffer::handle()`] then gives the file descriptor to import into EGL, Vulkan or
+        /// `wgpu`, and the data can still be read on the CPU.
+        ///
+        /// A buffer goes back to the driver once every clone of its frame has been dropped. There are only a few of
+        /// them, so holding on to frames makes the driver drop new ones, and the stream cannot be reopened until
+        /// all frames of the previous one are gone.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_dmabuf_export(&mut self, export: bool) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "DMA-BUF export".to_string(),
+                    value: export.to_string(),
+                    error: "Cannot change the buffer export while the stream is open".to_string(),
+                });
+            }
+            self.dmabuf_export = export;
+            Ok(())
         }
 
-        fn refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
-            self.force_refresh_camera_format()
+        /// Whether frames are exported as DMA-BUFs, see [`set_dmabuf_export()`](V4LCaptureDevice::set_dmabuf_export).
+        #[must_use]
+        pub fn dmabuf_export(&self) -> bool {
+            self.dmabuf_export
         }
 
-        fn camera_format(&self) -> CameraFormat {
-            self.camera_format
+        /// Decodes the frames of cameras that capture in [`H264`] or [`HEVC`] on a V4L2 memory-to-memory decoder (see
+        /// [`V4L2VideoDecoder`]), so [`frame()`](CaptureDevice::frame) hands out [`NV12`] or [`I420`] frames
+        /// instead of the compressed stream. [`camera_format()`](CaptureDevice::camera_format) still reports
+        /// the format the camera captures in. Other formats are not affected.
+        ///
+        /// Opening the stream fails if the system has no decoder for the format, there is no software fallback.
+        /// Decoded frames cannot be exported as DMA-BUFs.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_decode_compressed(&mut self, decode: bool) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Decode compressed".to_string(),
+                    value: decode.to_string(),
+                    error: "Cannot change decoding while the stream is open".to_string(),
+                });
+            }
+            self.decode_compressed = decode;
+            Ok(())
         }
 
-        fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
-            let prev_format = match Capture::format(&self.device) {
-                Ok(fmt) => fmt,
-                Err(why) => {
-                    return Err(NokhwaError::GetPropertyError {
-                        property: "Resolution, FourCC".to_string(),
-                        error: why.to_string(),
-                    })
-                }
-            };
-            let prev_fps = match Capture::params(&self.device) {
-                Ok(fps) => fps,
-                Err(why) => {
-                    return Err(NokhwaError::GetPropertyError {
-                        property: "Frame rate".to_string(),
-                        error: why.to_string(),
-                    })
-                }
-            };
-
-            let v4l_fcc = v4l2_FourCC::new(&new_fmt.format().0);
-
-            let format = Format::new(new_fmt.width(), new_fmt.height(), v4l_fcc);
-            let frame_rate = Parameters::with_fps(new_fmt.frame_rate());
+        /// Whether H.264 and HEVC frames are decoded, see [`set_decode_compressed()`](V4LCaptureDevice::set_decode_compressed).
+        #[must_use]
+        pub fn decode_compressed(&self) -> bool {
+            self.decode_compressed
+        }
 
-            if let Err(why) = Capture::set_format(&self.device, &format) {
-                return Err(NokhwaError::SetPropertyError {
-                    property: "Resolution, FourCC".to_string(),
-                    value: format.to_string(),
-                    error: why.to_string(),
-                });
+        /// Sets how many buffers the driver is given to capture into, 4 by default.
+        ///
+        /// Frames are handed out oldest first, so with more buffers a slow reader gets frames that are further behind,
+        /// but the driver drops fewer of them. With fewer buffers frames stay fresh, but the driver drops frames as soon
+        /// as one is not read in time. The driver may use more buffers than asked for.
+        /// # Errors
+        /// This errors if the stream is open, or `count` is 0.
+        pub fn set_buffer_count(&mut self, count: u32) -> Result<(), NokhwaError> {
+            let error = |error: &str| NokhwaError::SetPropertyError {
+                property: "Buffer count".to_string(),
+                value: count.to_string(),
+                error: error.to_string(),
+            };
+            if self.is_stream_open() {
+                return Err(error(
+                    "Cannot change the buffer count while the stream is open",
+                ));
             }
-            if let Err(why) = Capture::set_params(&self.device, &frame_rate) {
-                return Err(NokhwaError::SetPropertyError {
-                    property: "Frame rate".to_string(),
-                    value: frame_rate.to_string(),
-                    error: why.to_string(),
-                });
+            if count == 0 {
+                return Err(error("At least one buffer is needed"));
             }
+            self.buffer_count = count;
+            Ok(())
+        }
 
-            if self.stream_handle.is_some() {
-                return match self.open_stream() {
-                    Ok(_) => Ok(()),
-                    Err(why) => {
-                        // undo
-                        if let Err(why) = Capture::set_format(&self.device, &prev_format) {
-                            return Err(NokhwaError::SetPropertyError {
-                                property: format!("Attempt undo due to stream acquisition failure with error {}. Resolution, FourCC", why),
-                                value: prev_format.to_string(),
-                                error: why.to_string(),
-                            });
-                        }
-                        if let Err(why) = Capture::set_params(&self.device, &prev_fps) {
-                            return Err(NokhwaError::SetPropertyError {
-                                property:
-                                format!("Attempt undo due to stream acquisition failure with error {}. Frame rate", why),
-                                value: prev_fps.to_string(),
-                                error: why.to_string(),
-                            });
-                        }
-                        Err(why)
-                    }
-                };
-            }
-            self.camera_format = new_fmt;
+        /// How many buffers are requested from the driver, see [`set_buffer_count()`](V4LCaptureDevice::set_buffer_count).
+        #[must_use]
+        pub fn buffer_count(&self) -> u32 {
+            self.buffer_count
+        }
 
-            self.force_refresh_camera_format()?;
-            if self.camera_format != new_fmt {
+        /// Sets how the buffers frames are captured into are allocated, [`BufferMemory::Mmap`] by default.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_buffer_memory(&mut self, memory: BufferMemory) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
                 return Err(NokhwaError::SetPropertyError {
-                    property: "CameraFormat".to_string(),
-                    value: new_fmt.to_string(),
-                    error: "Rejected".to_string(),
+                    property: "Buffer memory".to_string(),
+                    value: format!("{memory:?}"),
+                    error: "Cannot change the buffer memory while the stream is open".to_string(),
                 });
             }
-
+            self.buffer_memory = memory;
             Ok(())
         }
 
-        fn compatible_list_by_resolution(
-            &mut self,
-            format: FourCC,
-        ) -> Result<HashMap<Resolution, Vec<u32>>, NokhwaError> {
-            let resolutions = self.get_resolution_list(format)?;
-            let mut res_map = HashMap::new();
-            for res in resolutions {
-                let mut compatible_fps = vec![];
-                match self.device.enum_frameintervals(
-                    v4l2_FourCC::new(&format.0),
-                    res.width(),
-                    res.height(),
-                ) {
-                    Ok(intervals) => {
-                        for interval in intervals {
-                            match interval.interval {
-                                FrameIntervalEnum::Discrete(dis) => {
-                                    compatible_fps.push(dis.denominator);
-                                }
-                                FrameIntervalEnum::Stepwise(step) => {
-                                    for fstep in (step.min.numerator..step.max.numerator)
-                                        .step_by(step.step.numerator as usize)
-                                    {
-                                        if step.max.denominator != 1 || step.min.denominator != 1 {
-                                            compatible_fps.push(fstep);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(why) => {
-                        return Err(NokhwaError::GetPropertyError {
-                            property: "Frame rate".to_string(),
-                            error: why.to_string(),
-                        })
-                    }
-                }
-                res_map.insert(res, compatible_fps);
-            }
-            Ok(res_map)
+        /// How the buffers are allocated, see [`set_buffer_memory()`](V4LCaptureDevice::set_buffer_memory).
+        #[must_use]
+        pub fn buffer_memory(&self) -> BufferMemory {
+            self.buffer_memory
         }
 
-        fn compatible_fourcc(&mut self) -> Result<Vec<FourCC>, NokhwaError> {
-            match self.device.enum_formats() {
-                Ok(formats) => {
-                    let mut frame_format_vec = vec![];
-                    for format in formats {
-                        frame_format_vec.push(FourCC(format.fourcc.repr));
-                    }
-                    frame_format_vec.sort();
-                    frame_format_vec.dedup();
-                    Ok(frame_format_vec)
-                }
-                Err(why) => Err(NokhwaError::GetPropertyError {
-                    property: "FourCC".to_string(),
-                    error: why.to_string(),
-                }),
+        /// Sets whether opening the stream sets up the [`MediaPipeline`] feeding the device: enabling the links from the
+        /// sensor to the device and setting the sensor and every sub-device to the resolution of the camera format. Off
+        /// by default, which leaves the pipeline as it is, e.g. set up with `media-ctl`.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_configure_pipeline(&mut self, configure: bool) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Configure media pipeline".to_string(),
+                    value: configure.to_string(),
+                    error: "Cannot change the media pipeline while the stream is open".to_string(),
+                });
             }
+            self.configure_pipeline = configure;
+            Ok(())
         }
 
-        fn resolution(&self) -> Resolution {
-            self.camera_format.resolution()
+        /// Whether opening the stream sets up the media pipeline, see
+        /// [`set_configure_pipeline()`](V4LCaptureDevice::set_configure_pipeline).
+        #[must_use]
+        pub fn configure_pipeline(&self) -> bool {
+            self.configure_pipeline
         }
 
-        fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
-            let mut new_fmt = self.camera_format;
-            new_fmt.set_resolution(new_res);
-            self.set_camera_format(new_fmt)
+        /// Finds the [`MediaPipeline`] feeding the device. Returns `None` if the device is not part of a media controller graph.
+        /// # Errors
+        /// This errors if the media device cannot be enumerated.
+        pub fn media_pipeline(&self) -> Result<Option<MediaPipeline>, NokhwaError> {
+            MediaPipeline::discover(&self.path)
         }
 
-        fn frame_rate(&self) -> u32 {
-            self.camera_format.frame_rate()
+        /// The channel [`SessionEvent`]s are delivered on while the stream is open: [`SessionEvent::ControlChanged`]
+        /// when another process (or the driver itself) changes a control, and [`SessionEvent::FormatChanged`] when the
+        /// source of e.g. an HDMI capture device switches resolution. Drivers that do not report these send nothing.
+        ///
+        /// Only available on Linux.
+        #[must_use]
+        pub fn events(&self) -> &mpsc::Receiver<SessionEvent> {
+            &self.event_receiver
         }
 
-        fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
-            let mut new_fmt = self.camera_format;
-            new_fmt.set_frame_rate(new_fps);
-            self.set_camera_format(new_fmt)
+        /// Gets a handle that wakes up a thread waiting for a frame of this device, e.g. to shut the stream down.
+        #[must_use]
+        pub fn wake_handle(&self) -> WakeHandle {
+            self.wake_handle.clone()
         }
 
-        fn frame_format(&self) -> FourCC {
-            self.camera_format.format()
+        // the driver counts every frame it captured, so gaps are frames it dropped
+        fn count_sequence(&mut self, sequence: u32) {
+            if let Some(last) = self.last_sequence {
+                self.frames_dropped += u64::from(sequence.wrapping_sub(last).saturating_sub(1));
+            }
+            self.last_sequence = Some(sequence);
         }
 
-        fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
-            let mut new_fmt = self.camera_format;
-            new_fmt.set_format(fourcc);
-            self.set_camera_format(new_fmt)
+        fn finish_frame(&self, frame: FrameBuffer) -> FrameBuffer {
+            let frame = frame.with_metadata(self.frame_metadata());
+            match self.color_space {
+                Some(color_space) => frame.with_color_space(color_space),
+                None => frame,
+            }
         }
 
-        fn camera_control(
-            &self,
-            control: KnownCameraControl,
-        ) -> Result<CameraControl, NokhwaError> {
-            let controls = self.camera_controls()?;
-            for supported_control in controls {
-                if supported_control.control() == control {
-                    return Ok(supported_control);
+        fn open_capture_stream(&mut self) -> Result<(), NokhwaError> {
+            self.decoder = None;
+            let format = self.camera_format.format();
+            if self.decode_compressed && matches!(format, H264 | HEVC) {
+                if self.dmabuf_export {
+                    return Err(NokhwaError::OpenStreamError(
+                        "DMA-BUF export cannot be combined with decoding".to_string(),
+                    ));
                 }
+                let decoder = V4L2VideoDecoder::new(format)
+                    .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?;
+                self.decoder = Some(decoder);
             }
-            Err(NokhwaError::GetPropertyError {
-                property: control.to_string(),
-                error: "not found/not supported".to_string(),
-            })
+            if self.buffer_memory == BufferMemory::UserPtr
+                && (self.dmabuf_export
+                    || matches!(self.buffer_type, BufferType::VideoCaptureMplane))
+            {
+                return Err(NokhwaError::OpenStreamError(
+                    "USERPTR buffers are only supported on single-planar devices, without DMA-BUF export"
+                        .to_string(),
+                ));
+            }
+            if self.configure_pipeline {
+                // links and formats cannot change while the device holds on to its buffers
+                self.stream = None;
+                self.dmabuf_stream = None;
+                self.configure_media_pipeline()?;
+            }
+            if self.dmabuf_export {
+                if matches!(self.buffer_type, BufferType::VideoCaptureMplane) {
+                    return Err(NokhwaError::OpenStreamError(
+                        "DMA-BUF export is not supported on multi-planar devices".to_string(),
+                    ));
+                }
+                // the buffers of the previous stream have to be released before new ones can be requested
+                self.stream = None;
+                self.dmabuf_stream = None;
+                let stream = DmaBufStream::new(&self.device, self.buffer_count).map_err(|why| {
+                    NokhwaError::OpenStreamError(why.to_string()).with_io_error(&why)
+                })?;
+                self.dmabuf_stream = Some(stream);
+                self.last_sequence = None;
+                self.frames_dropped = 0;
+                return Ok(());
+            }
+            // the buffers of the previous stream have to be released before new ones can be requested
+            self.stream = None;
+            let stream = capture_format(&self.device, self.buffer_type)
+                .and_then(|(_, strides)| {
+                    BufferStream::new(
+                        &self.device,
+                        self.buffer_type,
+                        strides,
+                        self.buffer_count,
+                        self.buffer_memory,
+                    )
+                })
+                .map_err(|why| NokhwaError::OpenStreamError(why.to_string()).with_io_error(&why))?;
+            self.stream = Some(stream);
+            self.last_sequence = None;
+            self.frames_dropped = 0;
+            Ok(())
         }
 
-        #[allow(clippy::cast_possible_wrap)]
-        fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+        fn configure_media_pipeline(&self) -> Result<(), NokhwaError> {
+            let Some(pipeline) = self.media_pipeline()? else {
+                return Ok(());
+            };
+            let resolution = self.camera_format.resolution();
+            let reached = pipeline.configure(resolution)?;
+            if reached != resolution {
+                return Err(NokhwaError::OpenStreamError(format!(
+                    "The media pipeline produces {reached} frames, not {resolution}"
+                )));
+            }
+            Ok(())
+        }
+
+        /// Waits up to `timeout` (or forever if it is `None`) for the next frame, decoding it if that is on. Returns
+        /// `None` if it timed out or the [`WakeHandle`] woke it up.
+        fn next_frame(
+            &mut self,
+            timeout: Option<Duration>,
+        ) -> Result<Option<FrameBuffer>, NokhwaError> {
+            if self.decoder.is_some() {
+                return self.decoded_frame(timeout);
+            }
+            self.capture_frame(timeout)
+        }
+
+        fn capture_frame(
+            &mut self,
+            timeout: Option<Duration>,
+        ) -> Result<Option<FrameBuffer>, NokhwaError> {
+            if self.dmabuf_stream.is_some() {
+                return self.dmabuf_frame(timeout);
+            }
+            let Some(stream) = &mut self.stream else {
+                return Err(NokhwaError::ReadFrameError(
+                    "Stream Not Started".to_string(),
+                ));
+            };
+            let next = match stream.next(&self.pool, poll_timeout(timeout), &self.wake_handle) {
+                Ok(Some(next)) => next,
+                Ok(None) => return Ok(None),
+                Err(why) if why.kind() == ErrorKind::Interrupted => return Ok(None),
+                Err(why) => {
+                    return Err(NokhwaError::ReadFrameError(why.to_string()).with_io_error(&why))
+                }
+            };
+            let (data, layouts, buffer) = next;
+            self.count_sequence(buffer.sequence);
+            let mut frame = FrameBuffer::from_pooled(
+                self.camera_format.resolution(),
+                data,
+                self.camera_format.format(),
+                Instant::now(),
+            )
+            .with_capture_timestamp(buffer_timestamp(&buffer), monotonic_now());
+            // formats kept in one plane are laid out as on single-planar devices, with the planes after the first
+            // following it. Compressed formats have a stride of 0.
+            if layouts.len() > 1 {
+                frame = frame.with_plane_layouts(layouts);
+            } else if let Some(layout) = layouts.first().filter(|layout| layout.stride() > 0) {
+                frame = frame.with_row_stride(layout.stride());
+            }
+            Ok(Some(self.finish_frame(frame)))
+        }
+
+        /// Reads frames from the camera until the decoder hands one out, or `timeout` runs out.
+        fn decoded_frame(
+            &mut self,
+            timeout: Option<Duration>,
+        ) -> Result<Option<FrameBuffer>, NokhwaError> {
+            let deadline = timeout.map(|timeout| Instant::now() + timeout);
+            loop {
+                let remaining =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                let Some(frame) = self.capture_frame(remaining)? else {
+                    return Ok(None);
+                };
+                let Some(decoder) = &mut self.decoder else {
+                    return Ok(Some(frame));
+                };
+                let Some((fourcc, data)) = decoder.decode(frame.resolution(), frame.buffer())?
+                else {
+                    continue;
+                };
+                let mut decoded = FrameBuffer::from_pooled(
+                    frame.resolution(),
+                    self.pool.copy_from_slice(&data),
+                    fourcc,
+                    Instant::now(),
+                );
+                // decoders hold frames back, so this is the time of the newest frame that went in
+                if let Some(capture_timestamp) = frame.capture_timestamp() {
+                    decoded = decoded.with_capture_timestamp(capture_timestamp, monotonic_now());
+                }
+                if let Some(metadata) = frame.metadata() {
+                    decoded = decoded.with_metadata(metadata);
+                }
+                return Ok(Some(decoded));
+            }
+        }
+
+        fn dmabuf_frame(
+            &mut self,
+            timeout: Option<Duration>,
+        ) -> Result<Option<FrameBuffer>, NokhwaError> {
+            let Some(stream) = &self.dmabuf_stream else {
+                return Err(NokhwaError::ReadFrameError(
+                    "Stream Not Started".to_string(),
+                ));
+            };
+            let (external, buffer) = match stream.next(poll_timeout(timeout), &self.wake_handle) {
+                Ok(Some(next)) => next,
+                Ok(None) => return Ok(None),
+                Err(why) if why.kind() == ErrorKind::Interrupted => return Ok(None),
+                Err(why) => {
+                    return Err(NokhwaError::ReadFrameError(why.to_string()).with_io_error(&why))
+                }
+            };
+            let stride = stream.stride;
+            let capture_timestamp = buffer_timestamp(&buffer);
+            self.count_sequence(buffer.sequence);
+            let mut frame = FrameBuffer::from_pooled(
+                self.camera_format.resolution(),
+                PooledBuffer::external(external),
+                self.camera_format.format(),
+                Instant::now(),
+            )
+            .with_capture_timestamp(capture_timestamp, monotonic_now());
+            // compressed formats have a stride of 0
+            if stride > 0 {
+                frame = frame.with_row_stride(stride);
+            }
+            Ok(Some(self.finish_frame(frame)))
+        }
+
+        /// Sets pan, tilt or zoom controls in one call, each given as `(id, value, scale)` where the control is set to `value * scale`.
+        #[allow(clippy::cast_possible_truncation)]
+        fn set_ptz_controls(
+            &self,
+            property: &str,
+            controls: &[(u32, f64, f64)],
+        ) -> Result<(), NokhwaError> {
+            let values = controls
+                .iter()
+                .map(|(id, value, scale)| Control {
+                    id: *id,
+                    value: Value::Integer((value * scale).round() as i64),
+                })
+                .collect();
+            self.device
+                .set_controls(values)
+                .map_err(|why| NokhwaError::SetPropertyError {
+                    property: property.to_string(),
+                    value: format!("{controls:?}"),
+                    error: why.to_string(),
+                })
+        }
+
+        /// Gets the range of the control `id`, or an [`UnsupportedOperationError`](NokhwaError::UnsupportedOperationError) if the device does not have it.
+        fn control_range(&self, id: u32) -> Result<(i64, i64), NokhwaError> {
             self.device
                 .query_controls()
                 .map_err(|why| NokhwaError::GetPropertyError {
@@ -597,176 +1331,2937 @@ mod internal {
                     error: why.to_string(),
                 })?
                 .into_iter()
-                .map(|desc| {
-                    let id_as_kcc = id_to_known_camera_control(desc.id);
-                    let ctrl_current = self.device.control(desc.id)?.value;
+                .find(|desc| desc.id == id && !desc.flags.intersects(Flags::DISABLED))
+                .map(|desc| (desc.minimum, desc.maximum))
+                .ok_or(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::Video4Linux,
+                ))
+        }
 
-                    let ctrl_value_desc = match (desc.typ, ctrl_current) {
-                        (
-                            Type::Integer
-                            | Type::Integer64
-                            | Type::Menu
-                            | Type::U8
-                            | Type::U16
-                            | Type::U32
-                            | Type::IntegerMenu,
-                            Value::Integer(current),
-                        ) => ControlValueDescription::IntegerRange {
-                            min: desc.minimum as isize,
-                            max: desc.maximum as isize,
-                            value: current as isize,
-                            step: desc.step as isize,
-                            default: desc.default as isize,
-                        },
-                        (Type::Boolean, Value::Boolean(current)) => {
-                            ControlValueDescription::Boolean {
-                                value: current,
-                                default: desc.default != 0,
-                            }
-                        }
+        fn set_integer_control(
+            &self,
+            property: &str,
+            id: u32,
+            value: i64,
+        ) -> Result<(), NokhwaError> {
+            self.device
+                .set_control(Control {
+                    id,
+                    value: Value::Integer(value),
+                })
+                .map_err(|why| NokhwaError::SetPropertyError {
+                    property: property.to_string(),
+                    value: value.to_string(),
+                    error: why.to_string(),
+                })
+        }
 
-                        (Type::String, Value::String(current)) => ControlValueDescription::String {
-                            value: current,
-                            default: None,
-                        },
-                        _ => {
-                            return Err(io::Error::new(
-                                ErrorKind::Unsupported,
-                                "what is this?????? todo: support ig",
-                            ))
-                        }
-                    };
+        /// Sets the control `id` to `fraction` of the way between its minimum and maximum.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+        fn set_fraction_control(
+            &self,
+            property: &str,
+            id: u32,
+            fraction: f64,
+        ) -> Result<(), NokhwaError> {
+            let (min, max) = self.control_range(id)?;
+            let value = min + ((max - min) as f64 * fraction.clamp(0.0, 1.0)).round() as i64;
+            self.set_integer_control(property, id, value)
+        }
 
-                    let is_readonly = desc
-                        .flags
-                        .intersects(Flags::READ_ONLY)
-                        .then_some(KnownCameraControlFlag::ReadOnly);
-                    let is_writeonly = desc
-                        .flags
-                        .intersects(Flags::WRITE_ONLY)
-                        .then_some(KnownCameraControlFlag::WriteOnly);
-                    let is_disabled = desc
-                        .flags
-                        .intersects(Flags::DISABLED)
-                        .then_some(KnownCameraControlFlag::Disabled);
-                    let is_volatile = desc
-                        .flags
-                        .intersects(Flags::VOLATILE)
-                        .then_some(KnownCameraControlFlag::Volatile);
-                    let is_inactive = desc
-                        .flags
-                        .intersects(Flags::INACTIVE)
-                        .then_some(KnownCameraControlFlag::Disabled);
-                    let flags_vec = vec![
-                        is_inactive,
-                        is_readonly,
-                        is_volatile,
-                        is_disabled,
-                        is_writeonly,
-                    ]
-                    .into_iter()
-                    .filter(Option::is_some)
-                    .collect::<Option<Vec<KnownCameraControlFlag>>>()
-                    .unwrap_or_default();
+        /// Sets the UVC region of interest to `roi`, and turns `auto_control` on for it in
+        /// `V4L2_CID_UVC_REGION_OF_INTEREST_AUTO`, keeping on what already is.
+        fn set_region_of_interest(&self, roi: Rect, auto_control: i32) -> Result<(), NokhwaError> {
+            let error = |why: String| NokhwaError::SetPropertyError {
+                property: "Region of Interest".to_string(),
+                value: roi.to_string(),
+                error: why,
+            };
+            let resolution = self.camera_format.resolution();
+            if !roi.fits_in(resolution) {
+                return Err(error(format!("Not inside the {resolution} frame")));
+            }
+            // the v4l crate cannot read bitmask controls
+            let mut auto = v4l2_ext_control {
+                id: V4L2_CID_UVC_REGION_OF_INTEREST_AUTO,
+                ..unsafe { mem::zeroed() }
+            };
+            let mut controls = v4l2_ext_controls {
+                count: 1,
+                controls: &mut auto,
+                ..unsafe { mem::zeroed() }
+            };
+            if unsafe {
+                v4l2::ioctl(
+                    self.device.handle().fd(),
+                    v4l2::vidioc::VIDIOC_G_EXT_CTRLS,
+                    std::ptr::addr_of_mut!(controls).cast(),
+                )
+            }
+            .is_err()
+            {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::Video4Linux,
+                ));
+            }
+            let auto = unsafe { auto.__bindgen_anon_1.value } | auto_control;
 
-                    Ok(CameraControl::new(
-                        id_as_kcc,
-                        desc.name,
-                        ctrl_value_desc,
-                        flags_vec,
-                        !desc.flags.intersects(Flags::INACTIVE),
-                    ))
-                })
-                .filter(Result::is_ok)
-                .collect::<Result<Vec<CameraControl>, io::Error>>()
-                .map_err(|x| NokhwaError::GetPropertyError {
-                    property: "www".to_string(),
-                    error: x.to_string(),
+            // the bytes of a struct v4l2_rect, whose left and top are signed but never negative here
+            let rect = [roi.x(), roi.y(), roi.width(), roi.height()]
+                .into_iter()
+                .flat_map(u32::to_ne_bytes)
+                .collect();
+            // both are camera class controls, so they are set together
+            self.device
+                .set_controls(vec![
+                    Control {
+                        id: V4L2_CID_UVC_REGION_OF_INTEREST_RECT,
+                        value: Value::CompoundU8(rect),
+                    },
+                    Control {
+                        id: V4L2_CID_UVC_REGION_OF_INTEREST_AUTO,
+                        value: Value::Integer(i64::from(auto)),
+                    },
+                ])
+                .map_err(|why| error(why.to_string()))
+        }
+    }
+
+    /// V4L2 pan and tilt controls are in arc seconds.
+    const ARC_SECONDS_PER_DEGREE: f64 = 3600.0;
+
+    // the menu items of V4L2_CID_EXPOSURE_AUTO, from enum v4l2_exposure_auto_type
+    const V4L2_EXPOSURE_AUTO: i64 = 0;
+    const V4L2_EXPOSURE_MANUAL: i64 = 1;
+    const V4L2_EXPOSURE_APERTURE_PRIORITY: i64 = 3;
+
+    // V4L2_CID_CAMERA_UVC_BASE + 1 and + 2, not present in kernel headers before 6.3
+    const V4L2_CID_UVC_REGION_OF_INTEREST_RECT: u32 = 0x009a_1901;
+    const V4L2_CID_UVC_REGION_OF_INTEREST_AUTO: u32 = 0x009a_1902;
+    const V4L2_UVC_REGION_OF_INTEREST_AUTO_EXPOSURE: i32 = 1 << 0;
+    const V4L2_UVC_REGION_OF_INTEREST_AUTO_FOCUS: i32 = 1 << 3;
+
+    /// `struct uvc_xu_control_query` from `linux/uvcvideo.h`.
+    #[repr(C)]
+    struct UvcXuControlQuery {
+        unit: u8,
+        selector: u8,
+        query: u8,
+        size: u16,
+        data: *mut u8,
+    }
+
+    const UVCIOC_CTRL_QUERY: _IOC_TYPE = iowr::<UvcXuControlQuery>(b'u', 0x21);
+
+    /// Converts `value` into the V4L2 control of `id`, also returning the value the control should read back as.
+    fn to_v4l2_control(
+        id: KnownCameraControl,
+        value: ControlValueSetter,
+    ) -> Result<(Control, ControlValueSetter), NokhwaError> {
+        // the flash LED mode is a menu (0 => none, 1 => flash, 2 => torch), so the torch can also be turned on and off
+        let value = match (id, value) {
+            (KnownCameraControl::Torch, ControlValueSetter::Boolean(on)) => {
+                ControlValueSetter::Integer(if on { 2 } else { 0 })
+            }
+            (_, value) => value,
+        };
+        let conv_value = match value.clone() {
+            ControlValueSetter::None => Value::None,
+            ControlValueSetter::Integer(i) | ControlValueSetter::EnumValue(i) => {
+                Value::Integer(i as i64)
+            }
+            ControlValueSetter::Boolean(b) => Value::Boolean(b),
+            ControlValueSetter::String(s) => Value::String(s),
+            ControlValueSetter::Bytes(b) => Value::CompoundU8(b),
+            v => {
+                return Err(NokhwaError::SetPropertyError {
+                    property: id.to_string(),
+                    value: v.to_string(),
+                    error: "not supported".to_string(),
                 })
+            }
+        };
+        Ok((
+            Control {
+                id: known_camera_control_to_id(id),
+                value: conv_value,
+            },
+            value,
+        ))
+    }
+
+    /// Checks that `control` reads back as `value`, as drivers can reject a value without returning an error.
+    fn verify_control(
+        id: KnownCameraControl,
+        value: &ControlValueSetter,
+        control: &CameraControl,
+    ) -> Result<(), NokhwaError> {
+        // menus read back as enum values, but may be set with either an integer or an enum value
+        let matches = match (control.value(), value) {
+            (ControlValueSetter::EnumValue(read), ControlValueSetter::Integer(set)) => read == *set,
+            (read, set) => read == *set,
+        };
+        if !matches {
+            return Err(NokhwaError::SetPropertyError {
+                property: id.to_string(),
+                value: format!("{:?}", value),
+                error: "Rejected".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    impl AsRawFd for V4LCaptureDevice<'_> {
+        /// The device node, which polls readable (`POLLIN`) while a frame is ready to be read with
+        /// [`frame_timeout()`](CaptureDevice::frame_timeout) and a zero timeout.
+        fn as_raw_fd(&self) -> RawFd {
+            self.device.handle().fd()
         }
+    }
 
-        fn set_camera_control(
-            &mut self,
-            id: KnownCameraControl,
-            value: ControlValueSetter,
-        ) -> Result<(), NokhwaError> {
-            let conv_value = match value.clone() {
-                ControlValueSetter::None => Value::None,
-                ControlValueSetter::Integer(i) => Value::Integer(i as i64),
-                ControlValueSetter::Boolean(b) => Value::Boolean(b),
-                ControlValueSetter::String(s) => Value::String(s),
-                ControlValueSetter::Bytes(b) => Value::CompoundU8(b),
-                v => {
-                    return Err(NokhwaError::SetPropertyError {
-                        property: id.to_string(),
-                        value: v.to_string(),
-                        error: "not supported".to_string(),
+    impl<'a> CaptureDevice for V4LCaptureDevice<'a> {
+        fn backend(&self) -> ApiBackend {
+            ApiBackend::Video4Linux
+        }
+
+        fn camera_info(&self) -> &CameraInfo {
+            &self.camera_info
+        }
+
+        fn refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
+            self.force_refresh_camera_format()
+        }
+
+        fn camera_format(&self) -> CameraFormat {
+            self.camera_format
+        }
+
+        fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+            let prev_format = match capture_format(&self.device, self.buffer_type) {
+                Ok((fmt, _)) => fmt,
+                Err(why) => {
+                    return Err(NokhwaError::GetPropertyError {
+                        property: "Resolution, FourCC".to_string(),
+                        error: why.to_string(),
                     })
                 }
             };
-            self.device
-                .set_control(Control {
-                    id: known_camera_control_to_id(id),
-                    value: conv_value,
+            let prev_fps = match capture_params(&self.device, self.buffer_type) {
+                Ok(fps) => fps,
+                Err(why) => {
+                    return Err(NokhwaError::GetPropertyError {
+                        property: "Frame rate".to_string(),
+                        error: why.to_string(),
+                    })
+                }
+            };
+
+            let v4l_fcc = v4l2_FourCC::new(&new_fmt.format().0);
+
+            let format = Format::new(new_fmt.width(), new_fmt.height(), v4l_fcc);
+            let frame_rate = Parameters::new(frame_interval(new_fmt.exact_frame_rate()));
+
+            if let Err(why) = set_capture_format(&self.device, self.buffer_type, &format) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Resolution, FourCC".to_string(),
+                    value: format.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = set_capture_params(&self.device, self.buffer_type, &frame_rate) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Frame rate".to_string(),
+                    value: frame_rate.to_string(),
+                    error: why.to_string(),
+                });
+            }
+
+            if self.is_stream_open() {
+                return match self.open_stream() {
+                    Ok(_) => Ok(()),
+                    Err(why) => {
+                        // undo
+                        if let Err(why) =
+                            set_capture_format(&self.device, self.buffer_type, &prev_format)
+                        {
+                            return Err(NokhwaError::SetPropertyError {
+                                property: format!("Attempt undo due to stream acquisition failure with error {}. Resolution, FourCC", why),
+                                value: prev_format.to_string(),
+                                error: why.to_string(),
+                            });
+                        }
+                        if let Err(why) =
+                            set_capture_params(&self.device, self.buffer_type, &prev_fps)
+                        {
+                            return Err(NokhwaError::SetPropertyError {
+                                property:
+                                format!("Attempt undo due to stream acquisition failure with error {}. Frame rate", why),
+                                value: prev_fps.to_string(),
+                                error: why.to_string(),
+                            });
+                        }
+                        Err(why)
+                    }
+                };
+            }
+            self.camera_format = new_fmt;
+
+            self.force_refresh_camera_format()?;
+            if self.camera_format != new_fmt {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "CameraFormat".to_string(),
+                    value: new_fmt.to_string(),
+                    error: "Rejected".to_string(),
+                });
+            }
+
+            Ok(())
+        }
+
+        fn compatible_list_by_resolution(
+            &mut self,
+            format: FourCC,
+        ) -> Result<HashMap<Resolution, Vec<u32>>, NokhwaError> {
+            let resolutions = self.get_resolution_list(format)?;
+            let mut res_map = HashMap::new();
+            for res in resolutions {
+                let mut compatible_fps = vec![];
+                match self.device.enum_frameintervals(
+                    v4l2_FourCC::new(&format.0),
+                    res.width(),
+                    res.height(),
+                ) {
+                    Ok(intervals) => {
+                        for interval in intervals {
+                            match interval.interval {
+                                FrameIntervalEnum::Discrete(dis) => {
+                                    compatible_fps.push(interval_frame_rate(dis).fps());
+                                }
+                                FrameIntervalEnum::Stepwise(step) => {
+                                    for fstep in (step.min.numerator..step.max.numerator)
+                                        .step_by(step.step.numerator as usize)
+                                    {
+                                        if step.max.denominator != 1 || step.min.denominator != 1 {
+                                            compatible_fps.push(fstep);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Err(why) => {
+                        return Err(NokhwaError::GetPropertyError {
+                            property: "Frame rate".to_string(),
+                            error: why.to_string(),
+                        })
+                    }
+                }
+                res_map.insert(res, compatible_fps);
+            }
+            Ok(res_map)
+        }
+
+        fn compatible_fourcc(&mut self) -> Result<Vec<FourCC>, NokhwaError> {
+            match capture_fourccs(&self.device, self.buffer_type) {
+                Ok(fourccs) => {
+                    let mut frame_format_vec = vec![];
+                    for fourcc in fourccs {
+                        frame_format_vec.push(FourCC(fourcc.repr));
+                    }
+                    frame_format_vec.sort();
+                    frame_format_vec.dedup();
+                    Ok(frame_format_vec)
+                }
+                Err(why) => Err(NokhwaError::GetPropertyError {
+                    property: "FourCC".to_string(),
+                    error: why.to_string(),
+                }),
+            }
+        }
+
+        fn resolution(&self) -> Resolution {
+            self.camera_format.resolution()
+        }
+
+        fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
+            let mut new_fmt = self.camera_format;
+            new_fmt.set_resolution(new_res);
+            self.set_camera_format(new_fmt)
+        }
+
+        fn frame_rate(&self) -> u32 {
+            self.camera_format.frame_rate()
+        }
+
+        fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
+            let mut new_fmt = self.camera_format;
+            new_fmt.set_frame_rate(new_fps);
+            self.set_camera_format(new_fmt)
+        }
+
+        fn frame_format(&self) -> FourCC {
+            self.camera_format.format()
+        }
+
+        fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
+            let mut new_fmt = self.camera_format;
+            new_fmt.set_format(fourcc);
+            self.set_camera_format(new_fmt)
+        }
+
+        fn camera_control(
+            &self,
+            control: KnownCameraControl,
+        ) -> Result<CameraControl, NokhwaError> {
+            let controls = self.camera_controls()?;
+            for supported_control in controls {
+                if supported_control.control() == control {
+                    return Ok(supported_control);
+                }
+            }
+            Err(NokhwaError::GetPropertyError {
+                property: control.to_string(),
+                error: "not found/not supported".to_string(),
+            })
+        }
+
+        #[allow(clippy::cast_possible_wrap)]
+        fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+            self.device
+                .query_controls()
+                .map_err(|why| NokhwaError::GetPropertyError {
+                    property: "V4L2 Controls".to_string(),
+                    error: why.to_string(),
+                })?
+                .into_iter()
+                .map(|desc| {
+                    let id_as_kcc = id_to_known_camera_control(desc.id);
+                    let ctrl_current = self.device.control(desc.id)?.value;
+
+                    let ctrl_value_desc = match (desc.typ, ctrl_current, desc.items.as_ref()) {
+                        (Type::Menu | Type::IntegerMenu, Value::Integer(current), Some(items)) => {
+                            ControlValueDescription::Enum {
+                                value: current as isize,
+                                possible: items.iter().map(|(index, _)| *index as isize).collect(),
+                                default: desc.default as isize,
+                                labels: items.iter().map(|(_, item)| item.to_string()).collect(),
+                            }
+                        }
+                        (
+                            Type::Integer
+                            | Type::Integer64
+                            | Type::Menu
+                            | Type::U8
+                            | Type::U16
+                            | Type::U32
+                            | Type::IntegerMenu,
+                            Value::Integer(current),
+                            _,
+                        ) => ControlValueDescription::IntegerRange {
+                            min: desc.minimum as isize,
+                            max: desc.maximum as isize,
+                            value: current as isize,
+                            step: desc.step as isize,
+                            default: desc.default as isize,
+                        },
+                        (Type::Boolean, Value::Boolean(current), _) => {
+                            ControlValueDescription::Boolean {
+                                value: current,
+                                default: desc.default != 0,
+                            }
+                        }
+
+                        (Type::String, Value::String(current), _) => {
+                            ControlValueDescription::String {
+                                value: current,
+                                default: None,
+                            }
+                        }
+                        _ => {
+                            return Err(io::Error::new(
+                                ErrorKind::Unsupported,
+                                "what is this?????? todo: support ig",
+                            ))
+                        }
+                    };
+
+                    let is_readonly = desc
+                        .flags
+                        .intersects(Flags::READ_ONLY)
+                        .then_some(KnownCameraControlFlag::ReadOnly);
+                    let is_writeonly = desc
+                        .flags
+                        .intersects(Flags::WRITE_ONLY)
+                        .then_some(KnownCameraControlFlag::WriteOnly);
+                    let is_disabled = desc
+                        .flags
+                        .intersects(Flags::DISABLED)
+                        .then_some(KnownCameraControlFlag::Disabled);
+                    let is_volatile = desc
+                        .flags
+                        .intersects(Flags::VOLATILE)
+                        .then_some(KnownCameraControlFlag::Volatile);
+                    let is_inactive = desc
+                        .flags
+                        .intersects(Flags::INACTIVE)
+                        .then_some(KnownCameraControlFlag::Disabled);
+                    let flags_vec = vec![
+                        is_inactive,
+                        is_readonly,
+                        is_volatile,
+                        is_disabled,
+                        is_writeonly,
+                    ]
+                    .into_iter()
+                    .filter(Option::is_some)
+                    .collect::<Option<Vec<KnownCameraControlFlag>>>()
+                    .unwrap_or_default();
+
+                    Ok(CameraControl::new(
+                        id_as_kcc,
+                        desc.name,
+                        ctrl_value_desc,
+                        flags_vec,
+                        !desc.flags.intersects(Flags::INACTIVE),
+                    ))
+                })
+                .filter(Result::is_ok)
+                .collect::<Result<Vec<CameraControl>, io::Error>>()
+                .map_err(|x| NokhwaError::GetPropertyError {
+                    property: "www".to_string(),
+                    error: x.to_string(),
+                })
+        }
+
+        fn set_camera_control(
+            &mut self,
+            id: KnownCameraControl,
+            value: ControlValueSetter,
+        ) -> Result<(), NokhwaError> {
+            let (control, value) = to_v4l2_control(id, value)?;
+            self.device
+                .set_control(control)
+                .map_err(|why| NokhwaError::SetPropertyError {
+                    property: id.to_string(),
+                    value: format!("{:?}", value),
+                    error: why.to_string(),
+                })?;
+            verify_control(id, &value, &self.camera_control(id)?)
+        }
+
+        fn set_camera_controls(
+            &mut self,
+            controls: &[(KnownCameraControl, ControlValueSetter)],
+        ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+            let mut results = vec![Ok(()); controls.len()];
+            let mut expected = Vec::with_capacity(controls.len());
+            // VIDIOC_S_EXT_CTRLS only takes controls of one class, which it then sets atomically
+            let mut classes: BTreeMap<u32, Vec<(usize, Control)>> = BTreeMap::new();
+            for (index, (id, value)) in controls.iter().enumerate() {
+                match to_v4l2_control(*id, value.clone()) {
+                    Ok((control, value)) => {
+                        classes
+                            .entry(control.id & 0xFFFF_0000)
+                            .or_default()
+                            .push((index, control));
+                        expected.push((index, *id, value));
+                    }
+                    Err(why) => results[index] = Err(why),
+                }
+            }
+            for class in classes.into_values() {
+                let (indices, class): (Vec<usize>, Vec<Control>) = class.into_iter().unzip();
+                if let Err(why) = self.device.set_controls(class) {
+                    for index in indices {
+                        let (id, value) = &controls[index];
+                        results[index] = Err(NokhwaError::SetPropertyError {
+                            property: id.to_string(),
+                            value: format!("{:?}", value),
+                            error: why.to_string(),
+                        });
+                    }
+                }
+            }
+
+            // read back once for all of them, instead of once per control
+            let current = self.camera_controls()?;
+            for (index, id, value) in expected {
+                if results[index].is_err() {
+                    continue;
+                }
+                results[index] = match current.iter().find(|control| control.control() == id) {
+                    Some(control) => verify_control(id, &value, control),
+                    None => Err(NokhwaError::GetPropertyError {
+                        property: id.to_string(),
+                        error: "not found/not supported".to_string(),
+                    }),
+                };
+            }
+            Ok(results)
+        }
+
+        fn open_stream(&mut self) -> Result<(), NokhwaError> {
+            self.open_capture_stream()?;
+            if self.event_watcher.is_none() {
+                let control_ids: Vec<u32> = self
+                    .device
+                    .query_controls()
+                    .map(|descriptions| descriptions.into_iter().map(|desc| desc.id).collect())
+                    .unwrap_or_default();
+                self.event_watcher = Some(EventWatcher::new(
+                    &self.device,
+                    &control_ids,
+                    self.event_sender.clone(),
+                ));
+            }
+            Ok(())
+        }
+
+        fn is_stream_open(&self) -> bool {
+            self.stream.is_some() || self.dmabuf_stream.is_some()
+        }
+
+        fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
+            // without a timeout, only the wake handle ends the wait without a frame
+            self.next_frame(None)?.ok_or_else(|| {
+                NokhwaError::ReadFrameError("Woken up before a frame arrived".to_string())
+            })
+        }
+
+        fn frame_timeout(&mut self, timeout: Duration) -> Result<Option<FrameBuffer>, NokhwaError> {
+            if !self.is_stream_open() {
+                return Err(NokhwaError::ReadFrameError(
+                    "Stream Not Started".to_string(),
+                ));
+            }
+            self.next_frame(Some(timeout))
+        }
+
+        fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+            self.frame().map(|frame| Cow::Owned(frame.into_owned()))
+        }
+
+        fn stream_stats(&self) -> StreamStats {
+            StreamStats::new(0, self.frames_dropped, 0)
+        }
+
+        fn pause_stream(&mut self) -> Result<(), NokhwaError> {
+            // stopping takes back the buffers frames are still using
+            if self.dmabuf_stream.is_some() {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::Video4Linux,
+                ));
+            }
+            // VIDIOC_STREAMOFF keeps the buffers mapped, the next frame re-queues them and turns streaming back on
+            if let Some(stream) = &mut self.stream {
+                stream
+                    .stop()
+                    .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?;
+            }
+            self.last_sequence = None;
+            Ok(())
+        }
+
+        fn resume_stream(&mut self) -> Result<(), NokhwaError> {
+            if !self.is_stream_open() {
+                return Err(NokhwaError::OpenStreamError(
+                    "Stream Not Started".to_string(),
+                ));
+            }
+            // started here rather than on the next frame, so the device polls readable again
+            if let Some(stream) = &mut self.stream {
+                stream.start().map_err(|why| {
+                    NokhwaError::OpenStreamError(why.to_string()).with_io_error(&why)
+                })?;
+            }
+            Ok(())
+        }
+
+        fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+            let crop_error = |error: String| NokhwaError::SetPropertyError {
+                property: "Crop".to_string(),
+                value: format!("{crop:?}"),
+                error,
+            };
+            if self.is_stream_open() {
+                return Err(crop_error(
+                    "Cannot change the crop while the stream is open".to_string(),
+                ));
+            }
+            let unsupported_or = |why: io::Error| match why.raw_os_error() {
+                Some(libc::ENOTTY | libc::EINVAL | libc::ENODATA) => {
+                    NokhwaError::UnsupportedOperationError(ApiBackend::Video4Linux)
+                }
+                _ => crop_error(why.to_string()),
+            };
+
+            let fd = self.device.handle().fd();
+            let mut selection: v4l2_selection = unsafe { mem::zeroed() };
+            selection.type_ = BufferType::VideoCapture as u32;
+            selection.r = match crop {
+                Some(rect) => v4l2_rect {
+                    left: i32::try_from(rect.x()).unwrap_or(i32::MAX),
+                    top: i32::try_from(rect.y()).unwrap_or(i32::MAX),
+                    width: rect.width(),
+                    height: rect.height(),
+                },
+                None => {
+                    selection.target = V4L2_SEL_TGT_CROP_DEFAULT;
+                    unsafe {
+                        v4l2::ioctl(
+                            fd,
+                            VIDIOC_G_SELECTION,
+                            std::ptr::addr_of_mut!(selection).cast(),
+                        )
+                    }
+                    .map_err(unsupported_or)?;
+                    selection.r
+                }
+            };
+            selection.target = V4L2_SEL_TGT_CROP;
+            unsafe {
+                v4l2::ioctl(
+                    fd,
+                    VIDIOC_S_SELECTION,
+                    std::ptr::addr_of_mut!(selection).cast(),
+                )
+            }
+            .map_err(unsupported_or)?;
+            // drivers without a scaler shrink the format to the crop rectangle
+            self.force_refresh_camera_format()
+        }
+
+        fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+            // most UVC cameras do not have a flip control, only some sensors do
+            if self.device.control(V4L2_CID_HFLIP).is_err() {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::Video4Linux,
+                ));
+            }
+            self.device
+                .set_control(Control {
+                    id: V4L2_CID_HFLIP,
+                    value: Value::Boolean(mirrored),
+                })
+                .map_err(|why| NokhwaError::SetPropertyError {
+                    property: "V4L2_CID_HFLIP".to_string(),
+                    value: mirrored.to_string(),
+                    error: why.to_string(),
+                })
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+            let descriptions =
+                self.device
+                    .query_controls()
+                    .map_err(|why| NokhwaError::GetPropertyError {
+                        property: "V4L2 Controls".to_string(),
+                        error: why.to_string(),
+                    })?;
+            let usable = |id: u32| {
+                descriptions
+                    .iter()
+                    .find(|desc| desc.id == id && !desc.flags.intersects(Flags::DISABLED))
+            };
+            let range = |id: u32, scale: f64| {
+                usable(id).map(|desc| {
+                    PtzRange::new(
+                        desc.minimum as f64 / scale,
+                        desc.maximum as f64 / scale,
+                        desc.step as f64 / scale,
+                        desc.default as f64 / scale,
+                    )
+                })
+            };
+            Ok(PtzCapabilities::new(
+                range(V4L2_CID_PAN_ABSOLUTE, ARC_SECONDS_PER_DEGREE),
+                range(V4L2_CID_TILT_ABSOLUTE, ARC_SECONDS_PER_DEGREE),
+                range(V4L2_CID_ZOOM_ABSOLUTE, 1.0),
+            )
+            .with_relative_pan_tilt(
+                usable(V4L2_CID_PAN_RELATIVE).is_some() || usable(V4L2_CID_TILT_RELATIVE).is_some(),
+            ))
+        }
+
+        fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+            self.set_ptz_controls("Zoom", &[(V4L2_CID_ZOOM_ABSOLUTE, zoom, 1.0)])
+        }
+
+        fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+            self.set_ptz_controls(
+                "Pan/Tilt",
+                &[
+                    (V4L2_CID_PAN_ABSOLUTE, pan, ARC_SECONDS_PER_DEGREE),
+                    (V4L2_CID_TILT_ABSOLUTE, tilt, ARC_SECONDS_PER_DEGREE),
+                ],
+            )
+        }
+
+        fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+            // cameras often only have one of the relative controls, so axes that do not move are left out
+            let controls = [
+                (V4L2_CID_PAN_RELATIVE, pan, ARC_SECONDS_PER_DEGREE),
+                (V4L2_CID_TILT_RELATIVE, tilt, ARC_SECONDS_PER_DEGREE),
+            ]
+            .into_iter()
+            .filter(|(_, offset, _)| *offset != 0.0)
+            .collect::<Vec<_>>();
+            if controls.is_empty() {
+                return Ok(());
+            }
+            self.set_ptz_controls("Relative Pan/Tilt", &controls)
+        }
+
+        fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+            match exposure {
+                // UVC cameras usually only have manual and aperture priority, which is their automatic exposure
+                Exposure::Auto => self
+                    .set_integer_control(
+                        "Exposure",
+                        V4L2_CID_EXPOSURE_AUTO,
+                        V4L2_EXPOSURE_APERTURE_PRIORITY,
+                    )
+                    .or_else(|_| {
+                        self.set_integer_control(
+                            "Exposure",
+                            V4L2_CID_EXPOSURE_AUTO,
+                            V4L2_EXPOSURE_AUTO,
+                        )
+                    }),
+                Exposure::Manual(duration) => {
+                    let (min, max) = self.control_range(V4L2_CID_EXPOSURE_ABSOLUTE)?;
+                    self.set_integer_control(
+                        "Exposure",
+                        V4L2_CID_EXPOSURE_AUTO,
+                        V4L2_EXPOSURE_MANUAL,
+                    )?;
+                    // V4L2_CID_EXPOSURE_ABSOLUTE is in units of 100µs
+                    let value = i64::try_from((duration.as_micros() + 50) / 100)
+                        .unwrap_or(i64::MAX)
+                        .clamp(min, max);
+                    self.set_integer_control("Exposure", V4L2_CID_EXPOSURE_ABSOLUTE, value)
+                }
+            }
+        }
+
+        fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+            match white_balance {
+                WhiteBalance::Auto => {
+                    self.set_integer_control("White Balance", V4L2_CID_AUTO_WHITE_BALANCE, 1)
+                }
+                WhiteBalance::Manual(kelvin) => {
+                    let (min, max) = self.control_range(V4L2_CID_WHITE_BALANCE_TEMPERATURE)?;
+                    self.set_integer_control("White Balance", V4L2_CID_AUTO_WHITE_BALANCE, 0)?;
+                    self.set_integer_control(
+                        "White Balance",
+                        V4L2_CID_WHITE_BALANCE_TEMPERATURE,
+                        i64::from(kelvin).clamp(min, max),
+                    )
+                }
+            }
+        }
+
+        fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+            match focus {
+                Focus::Auto => self.set_integer_control("Focus", V4L2_CID_FOCUS_AUTO, 1),
+                Focus::Manual(position) => {
+                    // checked first, so autofocus is not turned off on a camera that cannot focus manually
+                    self.control_range(V4L2_CID_FOCUS_ABSOLUTE)?;
+                    self.set_integer_control("Focus", V4L2_CID_FOCUS_AUTO, 0)?;
+                    self.set_fraction_control("Focus", V4L2_CID_FOCUS_ABSOLUTE, position)
+                }
+            }
+        }
+
+        fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+            self.set_fraction_control("Brightness", V4L2_CID_BRIGHTNESS, brightness)
+        }
+
+        fn set_focus_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+            self.set_region_of_interest(roi, V4L2_UVC_REGION_OF_INTEREST_AUTO_FOCUS)
+        }
+
+        fn set_exposure_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+            self.set_region_of_interest(roi, V4L2_UVC_REGION_OF_INTEREST_AUTO_EXPOSURE)
+        }
+
+        fn xu_query(
+            &mut self,
+            unit: u8,
+            selector: u8,
+            query: XuQuery,
+            data: &mut [u8],
+        ) -> Result<(), NokhwaError> {
+            let property = format!("Extension Unit {unit} Selector {selector}");
+            let written = format!("{data:?}");
+            let error = |why: String| match query {
+                XuQuery::SetCur => NokhwaError::SetPropertyError {
+                    property: property.clone(),
+                    value: written.clone(),
+                    error: why,
+                },
+                _ => NokhwaError::GetPropertyError {
+                    property: format!("{property} {query}"),
+                    error: why,
+                },
+            };
+            let Ok(size) = u16::try_from(data.len()) else {
+                return Err(error("Controls are at most 65535 bytes".to_string()));
+            };
+            let mut request = UvcXuControlQuery {
+                unit,
+                selector,
+                query: query as u8,
+                size,
+                data: data.as_mut_ptr(),
+            };
+            unsafe {
+                v4l2::ioctl(
+                    self.device.handle().fd(),
+                    UVCIOC_CTRL_QUERY,
+                    std::ptr::addr_of_mut!(request).cast(),
+                )
+            }
+            .map_err(|why| match why.raw_os_error() {
+                // not a UVC device
+                Some(libc::ENOTTY) => {
+                    NokhwaError::UnsupportedOperationError(ApiBackend::Video4Linux)
+                }
+                _ => error(why.to_string()),
+            })
+        }
+
+        fn poll_event(&mut self) -> Option<SessionEvent> {
+            self.event_receiver.try_recv().ok()
+        }
+
+        fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+            self.stream = None;
+            self.dmabuf_stream = None;
+            self.decoder = None;
+            self.event_watcher = None;
+            Ok(())
+        }
+    }
+
+    /// Subscribes `device` to the events [`EventWatcher`] forwards. Drivers that do not have an event fail to subscribe
+    /// to it, which is fine, they just never send it.
+    fn subscribe_events(device: &Device, control_ids: &[u32]) {
+        let subscriptions = control_ids
+            .iter()
+            .map(|id| (V4L2_EVENT_CTRL, *id))
+            .chain([(V4L2_EVENT_SOURCE_CHANGE, 0)]);
+        for (type_, id) in subscriptions {
+            let mut subscription = v4l2_event_subscription {
+                type_,
+                id,
+                // without V4L2_EVENT_SUB_FL_ALLOW_FEEDBACK, controls set through this handle are not reported
+                flags: 0,
+                ..unsafe { mem::zeroed() }
+            };
+            unsafe {
+                let _ = v4l2::ioctl(
+                    device.handle().fd(),
+                    VIDIOC_SUBSCRIBE_EVENT,
+                    std::ptr::addr_of_mut!(subscription).cast(),
+                );
+            }
+        }
+    }
+
+    /// Maps a V4L2 event to the [`SessionEvent`] it is sent as, if any.
+    fn session_event(event: &v4l2_event) -> Option<SessionEvent> {
+        match event.type_ {
+            V4L2_EVENT_CTRL if unsafe { event.u.ctrl.changes } & V4L2_EVENT_CTRL_CH_VALUE != 0 => {
+                Some(SessionEvent::ControlChanged(id_to_known_camera_control(
+                    event.id,
+                )))
+            }
+            V4L2_EVENT_SOURCE_CHANGE
+                if unsafe { event.u.src_change.changes } & V4L2_EVENT_SRC_CH_RESOLUTION != 0 =>
+            {
+                Some(SessionEvent::FormatChanged)
+            }
+            _ => None,
+        }
+    }
+
+    /// Forwards the events of a device as [`SessionEvent`]s from its own thread, until it is dropped.
+    struct EventWatcher {
+        die_bool: Arc<AtomicBool>,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    impl EventWatcher {
+        fn new(device: &Device, control_ids: &[u32], sender: mpsc::Sender<SessionEvent>) -> Self {
+            subscribe_events(device, control_ids);
+            let die_bool = Arc::new(AtomicBool::new(false));
+            let die_bool_clone = die_bool.clone();
+            let device_handle = device.handle();
+            let handle = std::thread::spawn(move || {
+                event_loop(&device_handle, &sender, &die_bool_clone);
+            });
+            EventWatcher {
+                die_bool,
+                handle: Some(handle),
+            }
+        }
+    }
+
+    impl Drop for EventWatcher {
+        fn drop(&mut self) {
+            self.die_bool.store(true, Ordering::SeqCst);
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    fn event_loop(handle: &Handle, sender: &mpsc::Sender<SessionEvent>, die_bool: &AtomicBool) {
+        // only asking for POLLPRI, as the buffer queue reports POLLERR while nothing is queued
+        let mut poll_fd = libc::pollfd {
+            fd: handle.fd(),
+            events: libc::POLLPRI,
+            revents: 0,
+        };
+        while !die_bool.load(Ordering::SeqCst) {
+            // wake up regularly to check if we have been dropped
+            if unsafe { libc::poll(&raw mut poll_fd, 1, 250) } <= 0 {
+                continue;
+            }
+            if poll_fd.revents & libc::POLLPRI == 0 {
+                // the device went away, and would wake us up right away from now on
+                return;
+            }
+            loop {
+                let mut event: v4l2_event = unsafe { mem::zeroed() };
+                let dequeued = unsafe {
+                    v4l2::ioctl(
+                        handle.fd(),
+                        VIDIOC_DQEVENT,
+                        std::ptr::addr_of_mut!(event).cast(),
+                    )
+                };
+                if dequeued.is_err() {
+                    break;
+                }
+                if let Some(session_event) = session_event(&event) {
+                    if sender.send(session_event).is_err() {
+                        return;
+                    }
+                }
+                if event.pending == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The current time on `CLOCK_MONOTONIC`, which V4L2 drivers stamp buffers with.
+    fn monotonic_now() -> Duration {
+        let mut now = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe {
+            libc::clock_gettime(libc::CLOCK_MONOTONIC, &raw mut now);
+        }
+        Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+    }
+
+    /// A virtual camera sink that writes frames into a [`v4l2loopback`](https://github.com/umlaeute/v4l2loopback) device.
+    /// To see what this does, please see [`VirtualBackendTrait`].
+    /// # Quirks
+    /// - Frames are pushed with `write()`, so no conversion is done: the [`FrameBuffer`] must already be in the advertised [`FourCC`].
+    /// - Some consumers (e.g. browsers) only see the device if the module was loaded with `exclusive_caps=1`.
+    pub struct V4L2LoopbackDevice {
+        camera_format: CameraFormat,
+        device: Device,
+    }
+
+    impl V4L2LoopbackDevice {
+        /// Opens a `v4l2loopback` device and makes it advertise `format`. A [`CameraIndex::Index`] opens `/dev/videoN`,
+        /// while a [`CameraIndex::String`] is used as the path to the device node.
+        /// # Errors
+        /// This function will error if the device cannot be opened or it rejects the [`CameraFormat`].
+        pub fn new(index: &CameraIndex, format: CameraFormat) -> Result<Self, NokhwaError> {
+            let device = match index {
+                CameraIndex::Index(idx) => Device::new(*idx as usize),
+                CameraIndex::String(path) => Device::with_path(path),
+            }
+            .map_err(|why| {
+                NokhwaError::OpenDeviceError(index.to_string(), format!("V4L2 Error: {}", why))
+                    .with_io_error(&why)
+            })?;
+
+            let mut loopback = V4L2LoopbackDevice {
+                camera_format: format,
+                device,
+            };
+            loopback.set_camera_format(format)?;
+            Ok(loopback)
+        }
+    }
+
+    impl VirtualBackendTrait for V4L2LoopbackDevice {
+        fn backend(&self) -> ApiBackend {
+            ApiBackend::Video4Linux
+        }
+
+        fn camera_format(&self) -> CameraFormat {
+            self.camera_format
+        }
+
+        fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+            let format = Format::new(
+                new_fmt.width(),
+                new_fmt.height(),
+                v4l2_FourCC::new(&new_fmt.format().0),
+            );
+            if let Err(why) = v4l::video::Output::set_format(&self.device, &format) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Resolution, FourCC".to_string(),
+                    value: format.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            let frame_rate = OutputParameters::new(frame_interval(new_fmt.exact_frame_rate()));
+            if let Err(why) = v4l::video::Output::set_params(&self.device, &frame_rate) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Frame rate".to_string(),
+                    value: frame_rate.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            self.camera_format = new_fmt;
+            Ok(())
+        }
+
+        fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError> {
+            if frame.resolution() != self.camera_format.resolution()
+                || frame.source_frame_format() != self.camera_format.format()
+            {
+                return Err(NokhwaError::WriteFrameError(format!(
+                    "Frame {} {} does not match device format {}",
+                    frame.resolution(),
+                    frame.source_frame_format(),
+                    self.camera_format
+                )));
+            }
+            io::Write::write_all(&mut self.device, frame.buffer())
+                .map_err(|why| NokhwaError::WriteFrameError(why.to_string()))
+        }
+    }
+
+    /// Decodes MJPEG on a V4L2 memory-to-memory JPEG decoder, such as the ones in Raspberry Pi, Rockchip and i.MX SoCs.
+    /// Register it with [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+    /// # Quirks
+    /// - Only single-planar devices are supported.
+    /// - Frames are decoded one at a time, the device is not kept busy with a queue of frames.
+    pub struct V4L2JpegDecoder {
+        // the buffers are released before the device is closed, since fields drop in order
+        output: Option<MappedBuffers>,
+        capture: Option<MappedBuffers>,
+        device: Device,
+        name: String,
+        input_format: v4l2_FourCC,
+        configured: Option<(Resolution, FourCC, Format)>,
+    }
+
+    impl V4L2JpegDecoder {
+        /// Finds the first memory-to-memory device that decodes MJPEG.
+        /// # Errors
+        /// If there is no such device, this will error.
+        pub fn new() -> Result<Self, NokhwaError> {
+            let mut nodes = v4l::context::enum_devices();
+            nodes.sort_by_key(v4l::context::Node::index);
+            nodes
+                .iter()
+                .find_map(|node| Self::with_path(node.path()).ok())
+                .ok_or_else(|| {
+                    NokhwaError::NotImplementedError(
+                        "No V4L2 memory-to-memory JPEG decoder found".to_string(),
+                    )
+                })
+        }
+
+        /// Opens the memory-to-memory device at `path`.
+        /// # Errors
+        /// If the device cannot be opened, or it is not a single-planar decoder that takes MJPEG, this will error.
+        pub fn with_path(path: impl AsRef<Path>) -> Result<Self, NokhwaError> {
+            let path = path.as_ref();
+            let device = Device::with_path(path).map_err(|why| {
+                NokhwaError::OpenDeviceError(path.display().to_string(), why.to_string())
+                    .with_io_error(&why)
+            })?;
+            let caps = device
+                .query_caps()
+                .map_err(|why| NokhwaError::GetPropertyError {
+                    property: "Capabilities".to_string(),
+                    error: why.to_string(),
+                })?;
+            if !caps
+                .capabilities
+                .contains(v4l::capability::Flags::VIDEO_M2M)
+            {
+                return Err(NokhwaError::OpenDeviceError(
+                    path.display().to_string(),
+                    "Not a single-planar memory-to-memory device".to_string(),
+                ));
+            }
+            let input_format = v4l::video::Output::enum_formats(&device)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|description| description.fourcc)
+                .find(|fourcc| [*b"MJPG", *b"JPEG"].contains(&fourcc.repr))
+                .ok_or_else(|| {
+                    NokhwaError::OpenDeviceError(
+                        path.display().to_string(),
+                        "Does not decode MJPEG".to_string(),
+                    )
+                })?;
+            Ok(V4L2JpegDecoder {
+                output: None,
+                capture: None,
+                device,
+                name: format!("V4L2 {} ({})", caps.card, path.display()),
+                input_format,
+                configured: None,
+            })
+        }
+
+        /// Creates a decoder for [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+        /// # Errors
+        /// If there is no decoder, this will error.
+        pub fn create() -> Result<Box<dyn HardwareDecoder>, NokhwaError> {
+            Ok(Box::new(Self::new()?))
+        }
+
+        fn configure(&mut self, resolution: Resolution) -> Result<(), NokhwaError> {
+            let error = |why: io::Error| {
+                NokhwaError::SetPropertyError {
+                    property: "Decoder format".to_string(),
+                    value: resolution.to_string(),
+                    error: why.to_string(),
+                }
+                .with_io_error(&why)
+            };
+
+            // buffers have to be released before the formats can change
+            self.output = None;
+            self.capture = None;
+            self.configured = None;
+
+            let input = Format::new(resolution.width(), resolution.height(), self.input_format);
+            v4l::video::Output::set_format(&self.device, &input).map_err(error)?;
+            // the decoded format is only known once the input format is set
+            let available = v4l::video::Capture::enum_formats(&self.device)
+                .map_err(error)?
+                .into_iter()
+                .map(|description| description.fourcc.repr)
+                .collect::<Vec<_>>();
+            let fourcc = [NV12, I420, YUYV]
+                .into_iter()
+                .find(|fourcc| {
+                    let repr = if *fourcc == I420 { *b"YU12" } else { fourcc.0 };
+                    available.contains(&repr)
+                })
+                .ok_or_else(|| NokhwaError::SetPropertyError {
+                    property: "Decoder format".to_string(),
+                    value: resolution.to_string(),
+                    error: "The decoder outputs no format that can be converted".to_string(),
+                })?;
+            let repr = if fourcc == I420 { *b"YU12" } else { fourcc.0 };
+            let decoded = v4l::video::Capture::set_format(
+                &self.device,
+                &Format::new(
+                    resolution.width(),
+                    resolution.height(),
+                    v4l2_FourCC::new(&repr),
+                ),
+            )
+            .map_err(error)?;
+
+            let output =
+                MappedBuffers::new(&self.device, BufferType::VideoOutput, 1).map_err(error)?;
+            let capture =
+                MappedBuffers::new(&self.device, BufferType::VideoCapture, 2).map_err(error)?;
+            for index in 0..capture.len() {
+                capture.queue(index, 0).map_err(error)?;
+            }
+            output.stream_on().map_err(error)?;
+            capture.stream_on().map_err(error)?;
+            self.output = Some(output);
+            self.capture = Some(capture);
+            self.configured = Some((resolution, fourcc, decoded));
+            Ok(())
+        }
+    }
+
+    impl HardwareDecoder for V4L2JpegDecoder {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        fn decode(
+            &mut self,
+            resolution: Resolution,
+            data: &[u8],
+        ) -> Result<(FourCC, Vec<u8>), NokhwaError> {
+            if self.configured.map(|(configured, _, _)| configured) != Some(resolution) {
+                self.configure(resolution)?;
+            }
+            let (Some((_, fourcc, decoded)), Some(output), Some(capture)) =
+                (self.configured, &self.output, &self.capture)
+            else {
+                return Err(NokhwaError::ReadFrameError(
+                    "The decoder is not configured".to_string(),
+                ));
+            };
+            let error = |why: io::Error| {
+                NokhwaError::ReadFrameError(format!("V4L2 decoder: {why}")).with_io_error(&why)
+            };
+
+            let input = output.buffer(0);
+            if input.len() < data.len() {
+                return Err(NokhwaError::ReadFrameError(format!(
+                    "Frame of {} bytes does not fit into the decoder's {} byte buffer",
+                    data.len(),
+                    input.len()
+                )));
+            }
+            input[..data.len()].copy_from_slice(data);
+            let bytes_used = u32::try_from(data.len())
+                .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?;
+            output.queue(0, bytes_used).map_err(error)?;
+
+            let (index, _) = capture.dequeue(DECODE_TIMEOUT_MS).map_err(error)?;
+            let frame = unpad(capture.buffer(index), &decoded, fourcc, resolution);
+            capture.queue(index, 0).map_err(error)?;
+            // the input buffer is handed back once it has been decoded
+            output.dequeue(DECODE_TIMEOUT_MS).map_err(error)?;
+            Ok((fourcc, frame))
+        }
+    }
+
+    const DECODE_TIMEOUT_MS: i32 = 1000;
+
+    /// Copies a decoded frame out of a buffer that may have padding after each row, or extra rows.
+    fn unpad(buffer: &[u8], decoded: &Format, fourcc: FourCC, resolution: Resolution) -> Vec<u8> {
+        let width = resolution.width() as usize;
+        let height = resolution.height() as usize;
+        let stride = decoded.stride as usize;
+        let padded_height = decoded.height as usize;
+        // (bytes per row, rows, stride) of each plane, chroma rows of 4:2:0 are half as long but NV12 interleaves two
+        let planes: Vec<(usize, usize, usize)> = if fourcc == YUYV {
+            vec![(width * 2, height, stride)]
+        } else if fourcc == NV12 {
+            vec![
+                (width, height, stride),
+                (width.div_ceil(2) * 2, height.div_ceil(2), stride),
+            ]
+        } else {
+            vec![
+                (width, height, stride),
+                (width.div_ceil(2), height.div_ceil(2), stride / 2),
+                (width.div_ceil(2), height.div_ceil(2), stride / 2),
+            ]
+        };
+
+        let mut frame = Vec::with_capacity(planes.iter().map(|(len, rows, _)| len * rows).sum());
+        let mut plane_start = 0;
+        for (index, (row_len, rows, plane_stride)) in planes.into_iter().enumerate() {
+            for row in 0..rows {
+                let start = plane_start + row * plane_stride;
+                if let Some(row) = buffer.get(start..start + row_len) {
+                    frame.extend_from_slice(row);
+                }
+            }
+            // the next plane starts after all rows of this one the driver allocated
+            let allocated_rows = if index == 0 {
+                padded_height
+            } else {
+                padded_height.div_ceil(2)
+            };
+            plane_start += allocated_rows * plane_stride;
+        }
+        frame
+    }
+
+    /// Decodes H.264 and HEVC on a V4L2 stateful memory-to-memory video decoder, such as the ones in Raspberry Pi,
+    /// Qualcomm, MediaTek and Amlogic SoCs. Frames come out as [`NV12`] or [`I420`].
+    ///
+    /// [`V4LCaptureDevice`] uses one for cameras that encode on the device, see
+    /// [`set_decode_compressed()`](V4LCaptureDevice::set_decode_compressed).
+    /// # Quirks
+    /// - Decoders hold on to a few frames before handing out the first one, so [`decode()`](V4L2VideoDecoder::decode)
+    ///   does not return a frame for every frame it is given.
+    /// - Stateless decoders (which need the request API, such as Hantro and Cedrus) are not supported.
+    pub struct V4L2VideoDecoder {
+        // the buffers are released before the device is closed, since fields drop in order
+        output: Option<MappedBuffers>,
+        capture: Option<MappedBuffers>,
+        device: Device,
+        name: String,
+        codec: FourCC,
+        output_type: BufferType,
+        capture_type: BufferType,
+        // output buffers the decoder is not holding on to
+        free_outputs: Vec<u32>,
+        resolution: Option<Resolution>,
+        // the format of decoded frames and the bytes per line of each of their planes, known once the decoder has
+        // parsed the headers of the stream
+        decoded: Option<(FourCC, Format, Vec<u32>)>,
+    }
+
+    impl V4L2VideoDecoder {
+        /// Finds the first memory-to-memory device that decodes `codec`, which is [`H264`] or [`HEVC`].
+        /// # Errors
+        /// If there is no such device, this will error.
+        pub fn new(codec: FourCC) -> Result<Self, NokhwaError> {
+            let mut nodes = v4l::context::enum_devices();
+            nodes.sort_by_key(v4l::context::Node::index);
+            nodes
+                .iter()
+                .find_map(|node| Self::with_path(node.path(), codec).ok())
+                .ok_or_else(|| {
+                    NokhwaError::NotImplementedError(format!(
+                        "No V4L2 memory-to-memory {codec} decoder found"
+                    ))
+                })
+        }
+
+        /// Opens the memory-to-memory device at `path`.
+        /// # Errors
+        /// If the device cannot be opened, or it is not a decoder that takes `codec`, this will error.
+        pub fn with_path(path: impl AsRef<Path>, codec: FourCC) -> Result<Self, NokhwaError> {
+            let path = path.as_ref();
+            let device = Device::with_path(path).map_err(|why| {
+                NokhwaError::OpenDeviceError(path.display().to_string(), why.to_string())
+                    .with_io_error(&why)
+            })?;
+            let caps = device
+                .query_caps()
+                .map_err(|why| NokhwaError::GetPropertyError {
+                    property: "Capabilities".to_string(),
+                    error: why.to_string(),
+                })?;
+            let (output_type, capture_type) = if caps
+                .capabilities
+                .contains(CapabilityFlags::VIDEO_M2M_MPLANE)
+            {
+                (
+                    BufferType::VideoOutputMplane,
+                    BufferType::VideoCaptureMplane,
+                )
+            } else if caps.capabilities.contains(CapabilityFlags::VIDEO_M2M) {
+                (BufferType::VideoOutput, BufferType::VideoCapture)
+            } else {
+                return Err(NokhwaError::OpenDeviceError(
+                    path.display().to_string(),
+                    "Not a memory-to-memory device".to_string(),
+                ));
+            };
+            if !capture_fourccs(&device, output_type)
+                .unwrap_or_default()
+                .iter()
+                .any(|fourcc| fourcc.repr == codec.0)
+            {
+                return Err(NokhwaError::OpenDeviceError(
+                    path.display().to_string(),
+                    format!("Does not decode {codec}"),
+                ));
+            }
+            Ok(V4L2VideoDecoder {
+                output: None,
+                capture: None,
+                device,
+                name: format!("V4L2 {} ({})", caps.card, path.display()),
+                codec,
+                output_type,
+                capture_type,
+                free_outputs: vec![],
+                resolution: None,
+                decoded: None,
+            })
+        }
+
+        /// Gets a name for the decoder, e.g. for logging.
+        #[must_use]
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Gets the format the decoder takes.
+        #[must_use]
+        pub fn codec(&self) -> FourCC {
+            self.codec
+        }
+
+        /// Feeds one frame of the stream of a `resolution` camera to the decoder, returning the next decoded frame and
+        /// its format if one is ready.
+        /// # Errors
+        /// If the decoder cannot be set up, the frame does not fit into its buffers, or decoding fails, this will error.
+        pub fn decode(
+            &mut self,
+            resolution: Resolution,
+            data: &[u8],
+        ) -> Result<Option<(FourCC, Vec<u8>)>, NokhwaError> {
+            if self.resolution != Some(resolution) {
+                self.configure(resolution)?;
+            }
+            let error = |why: io::Error| {
+                NokhwaError::ReadFrameError(format!("V4L2 decoder: {why}")).with_io_error(&why)
+            };
+
+            let index = self.free_output().map_err(error)?;
+            let Some(output) = &self.output else {
+                return Err(NokhwaError::ReadFrameError(
+                    "The decoder is not configured".to_string(),
+                ));
+            };
+            let input = output.buffer(index);
+            let queued = if input.len() < data.len() {
+                Err(NokhwaError::ReadFrameError(format!(
+                    "Frame of {} bytes does not fit into the decoder's {} byte buffer",
+                    data.len(),
+                    input.len()
+                )))
+            } else {
+                input[..data.len()].copy_from_slice(data);
+                u32::try_from(data.len())
+                    .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))
+                    .and_then(|bytes_used| output.queue(index, bytes_used).map_err(error))
+            };
+            if let Err(why) = queued {
+                self.free_outputs.push(index);
+                return Err(why);
+            }
+
+            if self.source_changed() {
+                self.configure_capture()?;
+            }
+            let (Some((fourcc, format, strides)), Some(capture)) = (&self.decoded, &self.capture)
+            else {
+                return Ok(None);
+            };
+            match capture.dequeue_buffer(0) {
+                Ok((buffer, _)) => {
+                    let frame =
+                        copy_decoded(capture, buffer.index, *fourcc, format, strides, resolution);
+                    capture.queue(buffer.index, 0).map_err(error)?;
+                    Ok(Some((*fourcc, frame)))
+                }
+                Err(why) if why.kind() == ErrorKind::TimedOut => Ok(None),
+                Err(why) => Err(error(why)),
+            }
+        }
+
+        /// Sets the decoder up for a stream of `resolution`. The decoded format is only known once the decoder has seen
+        /// the headers of the stream, so the capture queue is set up later, by [`configure_capture()`](Self::configure_capture).
+        fn configure(&mut self, resolution: Resolution) -> Result<(), NokhwaError> {
+            let error = |why: io::Error| {
+                NokhwaError::SetPropertyError {
+                    property: "Decoder format".to_string(),
+                    value: resolution.to_string(),
+                    error: why.to_string(),
+                }
+                .with_io_error(&why)
+            };
+
+            // buffers have to be released before the formats can change
+            self.output = None;
+            self.capture = None;
+            self.free_outputs.clear();
+            self.resolution = None;
+            self.decoded = None;
+
+            let input = Format::new(
+                resolution.width(),
+                resolution.height(),
+                v4l2_FourCC::new(&self.codec.0),
+            );
+            set_capture_format(&self.device, self.output_type, &input).map_err(error)?;
+            // only the source change event is subscribed to
+            subscribe_events(&self.device, &[]);
+            let output = MappedBuffers::new(&self.device, self.output_type, STREAM_BUFFER_COUNT)
+                .map_err(error)?;
+            output.stream_on().map_err(error)?;
+            self.free_outputs = (0..output.len()).collect();
+            self.output = Some(output);
+            self.resolution = Some(resolution);
+            Ok(())
+        }
+
+        /// Sets up the capture queue in the format the decoder says the stream decodes to, switching to one the
+        /// converters take if it is not one already.
+        fn configure_capture(&mut self) -> Result<(), NokhwaError> {
+            let codec = self.codec;
+            let error = |why: io::Error| {
+                NokhwaError::SetPropertyError {
+                    property: "Decoded format".to_string(),
+                    value: codec.to_string(),
+                    error: why.to_string(),
+                }
+                .with_io_error(&why)
+            };
+
+            self.capture = None;
+            self.decoded = None;
+
+            let (format, _) = capture_format(&self.device, self.capture_type).map_err(error)?;
+            if decoded_fourcc(format.fourcc.repr).is_none() {
+                let available = capture_fourccs(&self.device, self.capture_type).map_err(error)?;
+                let repr = [*b"NV12", *b"NM12", *b"YU12", *b"YM12"]
+                    .into_iter()
+                    .find(|repr| available.iter().any(|fourcc| fourcc.repr == *repr))
+                    .ok_or_else(|| NokhwaError::SetPropertyError {
+                        property: "Decoded format".to_string(),
+                        value: codec.to_string(),
+                        error: "The decoder outputs no format that can be converted".to_string(),
+                    })?;
+                let wanted = Format::new(format.width, format.height, v4l2_FourCC::new(&repr));
+                set_capture_format(&self.device, self.capture_type, &wanted).map_err(error)?;
+            }
+            let (format, strides) =
+                capture_format(&self.device, self.capture_type).map_err(error)?;
+            let Some(fourcc) = decoded_fourcc(format.fourcc.repr) else {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Decoded format".to_string(),
+                    value: codec.to_string(),
+                    error: format!("The decoder switched to {} instead", format.fourcc),
+                });
+            };
+            if let Some(resolution) = self.resolution {
+                if format.width < resolution.width() || format.height < resolution.height() {
+                    return Err(NokhwaError::SetPropertyError {
+                        property: "Decoded format".to_string(),
+                        value: resolution.to_string(),
+                        error: format!("The stream decodes to {}x{}", format.width, format.height),
+                    });
+                }
+            }
+
+            // on top of the buffers the decoder keeps reference frames in, so it is not starved while we copy one out
+            let reference_frames = match self
+                .device
+                .control(V4L2_CID_MIN_BUFFERS_FOR_CAPTURE)
+                .map(|ctrl| ctrl.value)
+            {
+                Ok(Value::Integer(count)) => u32::try_from(count).unwrap_or(0),
+                _ => STREAM_BUFFER_COUNT,
+            };
+            let capture = MappedBuffers::new(&self.device, self.capture_type, reference_frames + 2)
+                .map_err(error)?;
+            for index in 0..capture.len() {
+                capture.queue(index, 0).map_err(error)?;
+            }
+            capture.stream_on().map_err(error)?;
+            self.capture = Some(capture);
+            self.decoded = Some((fourcc, format, strides));
+            Ok(())
+        }
+
+        /// Takes an output buffer the decoder is not holding on to, waiting for one if it holds all of them.
+        fn free_output(&mut self) -> io::Result<u32> {
+            let Some(output) = &self.output else {
+                return Err(io::Error::new(
+                    ErrorKind::NotConnected,
+                    "The decoder is not configured",
+                ));
+            };
+            while let Ok((index, _)) = output.dequeue(0) {
+                self.free_outputs.push(index);
+            }
+            match self.free_outputs.pop() {
+                Some(index) => Ok(index),
+                None => output.dequeue(DECODE_TIMEOUT_MS).map(|(index, _)| index),
+            }
+        }
+
+        /// Whether the decoder sent a source change event, which it does once it has parsed the headers of the stream,
+        /// and again whenever the decoded format changes.
+        fn source_changed(&self) -> bool {
+            let mut changed = false;
+            loop {
+                let mut event: v4l2_event = unsafe { mem::zeroed() };
+                let dequeued = unsafe {
+                    v4l2::ioctl(
+                        self.device.handle().fd(),
+                        VIDIOC_DQEVENT,
+                        std::ptr::addr_of_mut!(event).cast(),
+                    )
+                };
+                if dequeued.is_err() {
+                    return changed;
+                }
+                changed |= event.type_ == V4L2_EVENT_SOURCE_CHANGE;
+                if event.pending == 0 {
+                    return changed;
+                }
+            }
+        }
+    }
+
+    /// Gets the [`FourCC`] frames in the V4L2 format `repr` are handed out as, if the converters take it.
+    fn decoded_fourcc(repr: [u8; 4]) -> Option<FourCC> {
+        match &repr {
+            b"NV12" | b"NM12" => Some(NV12),
+            b"YU12" | b"YM12" => Some(I420),
+            _ => None,
+        }
+    }
+
+    /// Copies a decoded frame out of capture buffer `index`, dropping the padding after each row and plane.
+    fn copy_decoded(
+        capture: &MappedBuffers,
+        index: u32,
+        fourcc: FourCC,
+        format: &Format,
+        strides: &[u32],
+        resolution: Resolution,
+    ) -> Vec<u8> {
+        if strides.len() <= 1 {
+            return unpad(capture.buffer(index), format, fourcc, resolution);
+        }
+        // each plane is in its own buffer
+        let width = resolution.width() as usize;
+        let height = resolution.height() as usize;
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        let planes = if fourcc == NV12 {
+            vec![(width, height), (chroma_width * 2, chroma_height)]
+        } else {
+            vec![
+                (width, height),
+                (chroma_width, chroma_height),
+                (chroma_width, chroma_height),
+            ]
+        };
+        let mut frame = Vec::with_capacity(planes.iter().map(|(len, rows)| len * rows).sum());
+        for (plane, ((row_len, rows), stride)) in planes.into_iter().zip(strides).enumerate() {
+            let buffer = capture.plane(index, plane);
+            for row in 0..rows {
+                let start = row * *stride as usize;
+                if let Some(row) = buffer.get(start..start + row_len) {
+                    frame.extend_from_slice(row);
+                }
+            }
+        }
+        frame
+    }
+
+    /// The planes a multi-planar buffer descriptor points to.
+    type PlaneDescriptors = [v4l2_plane; VIDEO_MAX_PLANES as usize];
+
+    /// Buffers of one queue of a device, mapped into memory or allocated by us. They are unmapped or freed, and
+    /// released, on drop.
+    struct MappedBuffers {
+        fd: std::os::raw::c_int,
+        // keeps `fd` open for as long as the buffers, which may outlive the device when frames hold on to them
+        _handle: Arc<Handle>,
+        buffer_type: BufferType,
+        memory: BufferMemory,
+        // the memory of each plane of each buffer, buffers of single-planar queues have one
+        buffers: Vec<Vec<(*mut std::os::raw::c_void, usize)>>,
+    }
+
+    // the mappings are only accessed through `&self`/`&mut self`, and each buffer only by whoever dequeued it
+    unsafe impl Send for MappedBuffers {}
+    unsafe impl Sync for MappedBuffers {}
+
+    impl MappedBuffers {
+        fn new(device: &Device, buffer_type: BufferType, count: u32) -> io::Result<Self> {
+            Self::with_memory(device, buffer_type, count, BufferMemory::Mmap)
+        }
+
+        /// Requests `count` buffers of `memory`. [`BufferMemory::UserPtr`] buffers are allocated here, sized for the
+        /// current format, and only work on single-planar queues.
+        fn with_memory(
+            device: &Device,
+            buffer_type: BufferType,
+            count: u32,
+            memory: BufferMemory,
+        ) -> io::Result<Self> {
+            let fd = device.handle().fd();
+            let mut request = v4l2_requestbuffers {
+                count,
+                type_: buffer_type as u32,
+                memory: memory.raw(),
+                ..unsafe { mem::zeroed() }
+            };
+            unsafe {
+                v4l2::ioctl(
+                    fd,
+                    v4l2::vidioc::VIDIOC_REQBUFS,
+                    std::ptr::addr_of_mut!(request).cast(),
+                )?;
+            }
+            let mut mapped = MappedBuffers {
+                fd,
+                _handle: device.handle(),
+                buffer_type,
+                memory,
+                buffers: Vec::with_capacity(request.count as usize),
+            };
+            if memory == BufferMemory::UserPtr {
+                let length = capture_format(device, buffer_type)?.0.size as usize;
+                let layout = userptr_layout(length)?;
+                for _ in 0..request.count {
+                    let pointer = unsafe { alloc::alloc_zeroed(layout) };
+                    if pointer.is_null() {
+                        return Err(io::Error::from(ErrorKind::OutOfMemory));
+                    }
+                    mapped.buffers.push(vec![(pointer.cast(), length)]);
+                }
+                return Ok(mapped);
+            }
+            for index in 0..request.count {
+                let mut planes: PlaneDescriptors = unsafe { mem::zeroed() };
+                let mut buffer = mapped.descriptor(index, &mut planes);
+                unsafe {
+                    v4l2::ioctl(
+                        fd,
+                        v4l2::vidioc::VIDIOC_QUERYBUF,
+                        std::ptr::addr_of_mut!(buffer).cast(),
+                    )?;
+                }
+                // (offset to map at, length) of each plane
+                let locations = if mapped.is_multi_planar() {
+                    planes[..(buffer.length as usize).min(planes.len())]
+                        .iter()
+                        .map(|plane| (unsafe { plane.m.mem_offset }, plane.length))
+                        .collect()
+                } else {
+                    vec![(unsafe { buffer.m.offset }, buffer.length)]
+                };
+                // pushed before mapping, so the planes mapped so far are unmapped on drop if one fails
+                mapped.buffers.push(Vec::with_capacity(locations.len()));
+                for (offset, length) in locations {
+                    let pointer = unsafe {
+                        v4l2::mmap(
+                            std::ptr::null_mut(),
+                            length as usize,
+                            libc::PROT_READ | libc::PROT_WRITE,
+                            libc::MAP_SHARED,
+                            fd,
+                            libc::off_t::try_from(offset)
+                                .map_err(|why| io::Error::new(ErrorKind::InvalidData, why))?,
+                        )?
+                    };
+                    if let Some(buffer) = mapped.buffers.last_mut() {
+                        buffer.push((pointer, length as usize));
+                    }
+                }
+            }
+            Ok(mapped)
+        }
+
+        fn len(&self) -> u32 {
+            u32::try_from(self.buffers.len()).unwrap_or(u32::MAX)
+        }
+
+        fn is_multi_planar(&self) -> bool {
+            matches!(
+                self.buffer_type,
+                BufferType::VideoCaptureMplane | BufferType::VideoOutputMplane
+            )
+        }
+
+        /// Describes buffer `index` for an ioctl. Multi-planar buffers point to `planes`, which has to outlive the ioctl.
+        fn descriptor(&self, index: u32, planes: &mut PlaneDescriptors) -> v4l2_buffer {
+            let mut buffer = v4l2_buffer {
+                index,
+                type_: self.buffer_type as u32,
+                memory: self.memory.raw(),
+                ..unsafe { mem::zeroed() }
+            };
+            if self.is_multi_planar() {
+                buffer.m.planes = planes.as_mut_ptr();
+                buffer.length = VIDEO_MAX_PLANES;
+            }
+            buffer
+        }
+
+        fn buffer(&self, index: u32) -> &mut [u8] {
+            self.plane(index, 0)
+        }
+
+        #[allow(clippy::mut_from_ref)]
+        fn plane(&self, index: u32, plane: usize) -> &mut [u8] {
+            let (pointer, length) = self.buffers[index as usize][plane];
+            // SAFETY: the mapping lives as long as `self`, and the device only touches buffers while they are queued
+            unsafe { std::slice::from_raw_parts_mut(pointer.cast(), length) }
+        }
+
+        /// Queues buffer `index`, with `bytes_used` bytes of its first plane filled in for output queues.
+        fn queue(&self, index: u32, bytes_used: u32) -> io::Result<()> {
+            let mut planes: PlaneDescriptors = unsafe { mem::zeroed() };
+            let mut buffer = self.descriptor(index, &mut planes);
+            if self.is_multi_planar() {
+                planes[0].bytesused = bytes_used;
+            } else {
+                buffer.bytesused = bytes_used;
+            }
+            if self.memory == BufferMemory::UserPtr {
+                let (pointer, length) = self.buffers[index as usize][0];
+                buffer.m.userptr = pointer as std::os::raw::c_ulong;
+                buffer.length = u32::try_from(length).unwrap_or(u32::MAX);
+            }
+            unsafe {
+                v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_QBUF,
+                    std::ptr::addr_of_mut!(buffer).cast(),
+                )
+            }
+        }
+
+        /// Waits up to `timeout` milliseconds for a single-planar buffer, returning its index and how many bytes of it are used.
+        fn dequeue(&self, timeout: i32) -> io::Result<(u32, u32)> {
+            self.dequeue_buffer(timeout)
+                .map(|(buffer, _)| (buffer.index, buffer.bytesused))
+        }
+
+        /// Waits up to `timeout` milliseconds (or forever if it is negative) for a buffer, returning it and the range of
+        /// each of its planes that holds data.
+        fn dequeue_buffer(&self, timeout: i32) -> io::Result<(v4l2_buffer, Vec<Range<usize>>)> {
+            if !self.wait(timeout, None)? {
+                return Err(io::Error::new(
+                    ErrorKind::TimedOut,
+                    "Timed out waiting for the decoder",
+                ));
+            }
+            self.dequeue_ready()
+        }
+
+        /// Waits up to `timeout` milliseconds (or forever if it is negative) until a buffer can be dequeued, returning
+        /// `false` if it timed out. Waking `wake` ends the wait with an [`ErrorKind::Interrupted`] error.
+        fn wait(&self, timeout: i32, wake: Option<&WakeHandle>) -> io::Result<bool> {
+            let events = if matches!(
+                self.buffer_type,
+                BufferType::VideoOutput | BufferType::VideoOutputMplane
+            ) {
+                libc::POLLOUT
+            } else {
+                libc::POLLIN
+            };
+            // poll skips negative descriptors, so without a wake handle only the device is waited on
+            let mut poll_fds = [
+                libc::pollfd {
+                    fd: self.fd,
+                    events,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: wake.map_or(-1, |wake| wake.event_fd.as_raw_fd()),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let ready = unsafe { libc::poll(poll_fds.as_mut_ptr(), 2, timeout) };
+            if ready < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if let Some(wake) = wake.filter(|_| poll_fds[1].revents & libc::POLLIN != 0) {
+                wake.reset();
+                return Err(io::Error::new(
+                    ErrorKind::Interrupted,
+                    "Woken up while waiting for a frame",
+                ));
+            }
+            Ok(ready > 0)
+        }
+
+        /// Dequeues a buffer that [`wait()`](MappedBuffers::wait) said is ready, see
+        /// [`dequeue_buffer()`](MappedBuffers::dequeue_buffer).
+        fn dequeue_ready(&self) -> io::Result<(v4l2_buffer, Vec<Range<usize>>)> {
+            let mut planes: PlaneDescriptors = unsafe { mem::zeroed() };
+            let mut buffer = self.descriptor(0, &mut planes);
+            unsafe {
+                v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_DQBUF,
+                    std::ptr::addr_of_mut!(buffer).cast(),
+                )?;
+            }
+            let used = if self.is_multi_planar() {
+                // `planes` does not outlive this call
+                buffer.m.planes = std::ptr::null_mut();
+                planes[..(buffer.length as usize).min(planes.len())]
+                    .iter()
+                    .map(|plane| plane.data_offset as usize..plane.bytesused as usize)
+                    .collect()
+            } else {
+                vec![0..buffer.bytesused as usize]
+            };
+            Ok((buffer, used))
+        }
+
+        /// Exports buffer `index` as a DMA-BUF.
+        fn export(&self, index: u32) -> io::Result<OwnedFd> {
+            let mut export = v4l2_exportbuffer {
+                type_: self.buffer_type as u32,
+                index,
+                flags: (libc::O_RDONLY | libc::O_CLOEXEC) as u32,
+                ..unsafe { mem::zeroed() }
+            };
+            unsafe {
+                v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_EXPBUF,
+                    std::ptr::addr_of_mut!(export).cast(),
+                )?;
+                Ok(OwnedFd::from_raw_fd(export.fd))
+            }
+        }
+
+        fn stream_on(&self) -> io::Result<()> {
+            let mut buffer_type = self.buffer_type as std::os::raw::c_int;
+            unsafe {
+                v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_STREAMON,
+                    std::ptr::addr_of_mut!(buffer_type).cast(),
+                )?;
+            }
+            Ok(())
+        }
+
+        /// Stops streaming, which takes every buffer off the queue.
+        fn stream_off(&self) -> io::Result<()> {
+            let mut buffer_type = self.buffer_type as std::os::raw::c_int;
+            unsafe {
+                v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_STREAMOFF,
+                    std::ptr::addr_of_mut!(buffer_type).cast(),
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for MappedBuffers {
+        fn drop(&mut self) {
+            let _ = self.stream_off();
+            let mut release = v4l2_requestbuffers {
+                count: 0,
+                type_: self.buffer_type as u32,
+                memory: self.memory.raw(),
+                ..unsafe { mem::zeroed() }
+            };
+            unsafe {
+                // mappings have to go before the buffers can be released, our own memory only after the driver let go of it
+                if self.memory == BufferMemory::Mmap {
+                    for (pointer, length) in self.buffers.drain(..).flatten() {
+                        let _ = v4l2::munmap(pointer, length);
+                    }
+                }
+                let _ = v4l2::ioctl(
+                    self.fd,
+                    v4l2::vidioc::VIDIOC_REQBUFS,
+                    std::ptr::addr_of_mut!(release).cast(),
+                );
+                for (pointer, length) in self.buffers.drain(..).flatten() {
+                    if let Ok(layout) = userptr_layout(length) {
+                        alloc::dealloc(pointer.cast(), layout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// How [`BufferMemory::UserPtr`] buffers of `length` bytes are allocated. Page aligned, which some drivers need.
+    fn userptr_layout(length: usize) -> io::Result<Layout> {
+        Layout::from_size_align(length.max(1), 4096)
+            .map_err(|why| io::Error::new(ErrorKind::InvalidInput, why))
+    }
+
+    /// How many buffers the streams of a [`V4LCaptureDevice`] request from the driver, unless
+    /// [`set_buffer_count()`](V4LCaptureDevice::set_buffer_count) says otherwise.
+    const STREAM_BUFFER_COUNT: u32 = 4;
+
+    /// How the buffers of a [`V4LCaptureDevice`] are allocated, see [`set_buffer_memory()`](V4LCaptureDevice::set_buffer_memory).
+    #[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+    pub enum BufferMemory {
+        /// The driver allocates the buffers, which are mapped into memory (`V4L2_MEMORY_MMAP`). Every driver supports this.
+        #[default]
+        Mmap,
+        /// The buffers are allocated in memory of the process and handed to the driver (`V4L2_MEMORY_USERPTR`). Not every
+        /// driver supports this, and it is only available on single-planar devices without DMA-BUF export.
+        UserPtr,
+    }
+
+    impl BufferMemory {
+        fn raw(self) -> u32 {
+            match self {
+                BufferMemory::Mmap => Memory::Mmap as u32,
+                BufferMemory::UserPtr => Memory::UserPtr as u32,
+            }
+        }
+    }
+
+    /// Wakes up a thread waiting for a frame of a [`V4LCaptureDevice`], see
+    /// [`wake_handle()`](V4LCaptureDevice::wake_handle).
+    ///
+    /// A waiting [`frame()`](CaptureDevice::frame) returns an error, a waiting
+    /// [`frame_timeout()`](CaptureDevice::frame_timeout) returns `None`. If nothing is waiting, the next wait
+    /// ends right away.
+    #[derive(Clone, Debug)]
+    pub struct WakeHandle {
+        event_fd: Arc<OwnedFd>,
+    }
+
+    impl WakeHandle {
+        fn new() -> io::Result<Self> {
+            let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(WakeHandle {
+                event_fd: Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+            })
+        }
+
+        /// Wakes up the thread waiting for a frame.
+        pub fn wake(&self) {
+            // only fails if the counter is about to overflow, in which case a wake up is pending anyway
+            let _ = unsafe { libc::eventfd_write(self.event_fd.as_raw_fd(), 1) };
+        }
+
+        /// Takes back pending wake ups, once one ended a wait.
+        fn reset(&self) {
+            let mut value: libc::eventfd_t = 0;
+            let _ = unsafe { libc::eventfd_read(self.event_fd.as_raw_fd(), &raw mut value) };
+        }
+    }
+
+    /// Converts a wait of `timeout`, or forever if it is `None`, to milliseconds for `poll`.
+    fn poll_timeout(timeout: Option<Duration>) -> i32 {
+        timeout.map_or(-1, |timeout| {
+            i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX)
+        })
+    }
+
+    /// The capture buffers of a stream whose frames are copied out, plane by plane on multi-planar devices.
+    struct BufferStream {
+        buffers: MappedBuffers,
+        // bytes per line of each plane
+        strides: Vec<u32>,
+        streaming: bool,
+    }
+
+    impl BufferStream {
+        /// Requests the buffers and starts streaming, so the device polls readable once a frame is captured.
+        fn new(
+            device: &Device,
+            buffer_type: BufferType,
+            strides: Vec<u32>,
+            count: u32,
+            memory: BufferMemory,
+        ) -> io::Result<Self> {
+            let mut stream = BufferStream {
+                buffers: MappedBuffers::with_memory(device, buffer_type, count, memory)?,
+                strides,
+                streaming: false,
+            };
+            stream.start()?;
+            Ok(stream)
+        }
+
+        /// Queues every buffer and turns streaming on, unless it already is.
+        fn start(&mut self) -> io::Result<()> {
+            if !self.streaming {
+                for index in 0..self.buffers.len() {
+                    self.buffers.queue(index, 0)?;
+                }
+                self.buffers.stream_on()?;
+                self.streaming = true;
+            }
+            Ok(())
+        }
+
+        /// Waits up to `timeout` milliseconds (or forever if it is negative) for the next frame, returning its planes
+        /// copied one after the other into a buffer of `pool`, where each plane is in that buffer, and the buffer the
+        /// frame came in. Returns `None` if it timed out.
+        fn next(
+            &mut self,
+            pool: &BufferPool,
+            timeout: i32,
+            wake: &WakeHandle,
+        ) -> io::Result<Option<(PooledBuffer, Vec<PlaneLayout>, v4l2_buffer)>> {
+            // after pausing, the next frame starts streaming again
+            self.start()?;
+            if !self.buffers.wait(timeout, Some(wake))? {
+                return Ok(None);
+            }
+            let (buffer, used) = self.buffers.dequeue_ready()?;
+            let planes = used
+                .into_iter()
+                .enumerate()
+                .map(|(plane, range)| {
+                    let data = self.buffers.plane(buffer.index, plane);
+                    let end = range.end.min(data.len());
+                    &data[range.start.min(end)..end]
+                })
+                .collect::<Vec<&[u8]>>();
+            let mut offset = 0;
+            let layouts = planes
+                .iter()
+                .zip(&self.strides)
+                .map(|(plane, stride)| {
+                    let layout = PlaneLayout::new(offset, *stride as usize);
+                    offset += plane.len();
+                    layout
+                })
+                .collect();
+            let data = pool.copy_from_slices(&planes);
+            self.buffers.queue(buffer.index, 0)?;
+            Ok(Some((data, layouts, buffer)))
+        }
+
+        fn stop(&mut self) -> io::Result<()> {
+            self.buffers.stream_off()?;
+            self.streaming = false;
+            Ok(())
+        }
+    }
+
+    /// The capture buffers of a stream whose frames are handed out without copying, with the DMA-BUF each one is
+    /// exported as.
+    struct DmaBufStream {
+        buffers: Arc<MappedBuffers>,
+        exported: Arc<Vec<OwnedFd>>,
+        stride: usize,
+    }
+
+    impl DmaBufStream {
+        fn new(device: &Device, count: u32) -> io::Result<Self> {
+            let stride = device.format()?.stride as usize;
+            let buffers = MappedBuffers::new(device, BufferType::VideoCapture, count)?;
+            let exported = (0..buffers.len())
+                .map(|index| buffers.export(index))
+                .collect::<io::Result<Vec<OwnedFd>>>()?;
+            for index in 0..buffers.len() {
+                buffers.queue(index, 0)?;
+            }
+            buffers.stream_on()?;
+            Ok(DmaBufStream {
+                buffers: Arc::new(buffers),
+                exported: Arc::new(exported),
+                stride,
+            })
+        }
+
+        /// Waits up to `timeout` milliseconds (or forever if it is negative) for the next frame, returning it and the
+        /// buffer it came in. Returns `None` if it timed out.
+        fn next(
+            &self,
+            timeout: i32,
+            wake: &WakeHandle,
+        ) -> io::Result<Option<(DmaBufFrame, v4l2_buffer)>> {
+            if !self.buffers.wait(timeout, Some(wake))? {
+                return Ok(None);
+            }
+            let (buffer, _) = self.buffers.dequeue_ready()?;
+            let frame = DmaBufFrame {
+                buffers: self.buffers.clone(),
+                exported: self.exported.clone(),
+                index: buffer.index,
+                bytes_used: buffer.bytesused as usize,
+                stride: self.stride,
+            };
+            Ok(Some((frame, buffer)))
+        }
+    }
+
+    /// A frame in a buffer of a [`DmaBufStream`], which is queued back once the frame is dropped.
+    struct DmaBufFrame {
+        buffers: Arc<MappedBuffers>,
+        exported: Arc<Vec<OwnedFd>>,
+        index: u32,
+        bytes_used: usize,
+        stride: usize,
+    }
+
+    impl ExternalBuffer for DmaBufFrame {
+        fn data(&self) -> &[u8] {
+            let buffer = self.buffers.buffer(self.index);
+            &buffer[..self.bytes_used.min(buffer.len())]
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn handle(&self) -> Option<BufferHandle> {
+            Some(BufferHandle::DmaBuf {
+                fd: self.exported[self.index as usize].as_raw_fd(),
+                offset: 0,
+                stride: self.stride,
+                length: self.bytes_used,
+            })
+        }
+    }
+
+    impl Drop for DmaBufFrame {
+        fn drop(&mut self) {
+            let _ = self.buffers.queue(self.index, 0);
+        }
+    }
+
+    // `struct media_entity_desc` of `linux/media.h`, which the v4l crates do not bind. Of the union at the end only
+    // `dev`, the major and minor number of the device node of the entity, is read.
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct MediaEntityDesc {
+        id: u32,
+        name: [u8; 32],
+        function: u32,
+        revision: u32,
+        flags: u32,
+        group_id: u32,
+        pads: u16,
+        links: u16,
+        reserved: [u32; 4],
+        dev: [u32; 46],
+    }
+
+    // `struct media_pad_desc`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct MediaPadDesc {
+        entity: u32,
+        index: u16,
+        flags: u32,
+        reserved: [u32; 2],
+    }
+
+    // `struct media_link_desc`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct MediaLinkDesc {
+        source: MediaPadDesc,
+        sink: MediaPadDesc,
+        flags: u32,
+        reserved: [u32; 2],
+    }
+
+    // `struct media_links_enum`
+    #[repr(C)]
+    struct MediaLinksEnum {
+        entity: u32,
+        pads: *mut MediaPadDesc,
+        links: *mut MediaLinkDesc,
+        reserved: [u32; 4],
+    }
+
+    // `struct v4l2_mbus_framefmt` of `linux/v4l2-mediabus.h`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct MbusFrameFormat {
+        width: u32,
+        height: u32,
+        code: u32,
+        field: u32,
+        colorspace: u32,
+        ycbcr_enc: u16,
+        quantization: u16,
+        xfer_func: u16,
+        flags: u16,
+        reserved: [u16; 10],
+    }
+
+    // `struct v4l2_subdev_format` of `linux/v4l2-subdev.h`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct SubdevFormat {
+        which: u32,
+        pad: u32,
+        format: MbusFrameFormat,
+        reserved: [u32; 8],
+    }
+
+    // _IOWR(kind, number, T)
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    const fn iowr<T>(kind: u8, number: usize) -> _IOC_TYPE {
+        ((3 << 30) | (mem::size_of::<T>() << 16) | ((kind as usize) << 8) | number) as _IOC_TYPE
+    }
+
+    const MEDIA_IOC_ENUM_ENTITIES: _IOC_TYPE = iowr::<MediaEntityDesc>(b'|', 1);
+    const MEDIA_IOC_ENUM_LINKS: _IOC_TYPE = iowr::<MediaLinksEnum>(b'|', 2);
+    const MEDIA_IOC_SETUP_LINK: _IOC_TYPE = iowr::<MediaLinkDesc>(b'|', 3);
+    const VIDIOC_SUBDEV_G_FMT: _IOC_TYPE = iowr::<SubdevFormat>(b'V', 4);
+    const VIDIOC_SUBDEV_S_FMT: _IOC_TYPE = iowr::<SubdevFormat>(b'V', 5);
+    const MEDIA_ENT_ID_FLAG_NEXT: u32 = 1 << 31;
+    const MEDIA_ENT_F_CAM_SENSOR: u32 = 0x0002_0001;
+    const MEDIA_LNK_FL_ENABLED: u32 = 1;
+    const MEDIA_LNK_FL_IMMUTABLE: u32 = 1 << 1;
+    // data links have a type of 0
+    const MEDIA_LNK_FL_LINK_TYPE: u32 = 0xf << 28;
+    const V4L2_SUBDEV_FORMAT_ACTIVE: u32 = 1;
+
+    /// An entity of a [`MediaPipeline`]: a sensor, a processing block such as an ISP or a scaler, or the capture node.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct MediaEntity {
+        id: u32,
+        name: String,
+        function: u32,
+        device_node: Option<PathBuf>,
+    }
+
+    impl MediaEntity {
+        fn from_desc(entity: &MediaEntityDesc) -> Self {
+            let name_len = entity
+                .name
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(entity.name.len());
+            // entities without a device node have a major number of 0
+            let device_node = (entity.dev[0] != 0)
+                .then(|| {
+                    fs::read_link(format!("/sys/dev/char/{}:{}", entity.dev[0], entity.dev[1]))
+                })
+                .and_then(Result::ok)
+                .and_then(|link| link.file_name().map(|name| Path::new("/dev").join(name)));
+            MediaEntity {
+                id: entity.id,
+                name: String::from_utf8_lossy(&entity.name[..name_len]).to_string(),
+                function: entity.function,
+                device_node,
+            }
+        }
+
+        /// The name the driver gave the entity.
+        #[must_use]
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// What the entity does, one of the `MEDIA_ENT_F_*` values of `linux/media.h`.
+        #[must_use]
+        pub fn function(&self) -> u32 {
+            self.function
+        }
+
+        /// Whether the entity is a camera sensor.
+        #[must_use]
+        pub fn is_sensor(&self) -> bool {
+            self.function == MEDIA_ENT_F_CAM_SENSOR
+        }
+
+        /// The device node of the entity, e.g. `/dev/v4l-subdev0`. Entities the driver does not expose have none.
+        #[must_use]
+        pub fn device_node(&self) -> Option<&Path> {
+            self.device_node.as_deref()
+        }
+    }
+
+    /// A data link of a media graph, as the (entity, pad) it goes from and to.
+    #[derive(Copy, Clone, Debug)]
+    struct MediaLink {
+        source: (u32, u16),
+        sink: (u32, u16),
+        flags: u32,
+    }
+
+    /// The chain of entities of a media controller graph that feeds a capture node, from the sensor to the node.
+    ///
+    /// On many ARM boards the sensor, the ISP and the capture node are separate entities of a media device
+    /// (`/dev/mediaN`), which have to be linked and set to matching formats before the capture node produces frames.
+    /// Streaming from an unconfigured pipeline fails with `EPIPE`.
+    #[derive(Clone, Debug)]
+    pub struct MediaPipeline {
+        media_device: PathBuf,
+        entities: Vec<MediaEntity>,
+        // `links[i]` goes from `entities[i]` to `entities[i + 1]`
+        links: Vec<MediaLink>,
+    }
+
+    impl MediaPipeline {
+        /// Finds the pipeline feeding `video_node`, e.g. `/dev/video0`. Returns `None` if no media device has the node.
+        /// # Errors
+        /// This errors if `video_node` cannot be read, or the media device that has it cannot be enumerated.
+        pub fn discover(video_node: impl AsRef<Path>) -> Result<Option<Self>, NokhwaError> {
+            let error = |why: io::Error| {
+                NokhwaError::GetPropertyError {
+                    property: "Media pipeline".to_string(),
+                    error: why.to_string(),
+                }
+                .with_io_error(&why)
+            };
+            let device_number = fs::metadata(video_node.as_ref()).map_err(error)?.rdev();
+            let node = (libc::major(device_number), libc::minor(device_number));
+            let Ok(entries) = fs::read_dir("/dev") else {
+                return Ok(None);
+            };
+            let mut media_devices = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(OsStr::to_str)
+                        .is_some_and(|name| name.starts_with("media"))
+                })
+                .collect::<Vec<PathBuf>>();
+            media_devices.sort();
+            for media_device in media_devices {
+                // media devices of other users, or that went away, cannot have the node anyway
+                let Ok(media) = fs::OpenOptions::new().read(true).open(&media_device) else {
+                    continue;
+                };
+                let entities = media_entities(media.as_raw_fd()).map_err(error)?;
+                let Some(capture) = entities
+                    .iter()
+                    .find(|entity| (entity.dev[0], entity.dev[1]) == node)
+                else {
+                    continue;
+                };
+                let links = media_links(media.as_raw_fd(), &entities).map_err(error)?;
+                return Ok(Some(Self::walk(
+                    media_device,
+                    &entities,
+                    &links,
+                    capture.id,
+                )));
+            }
+            Ok(None)
+        }
+
+        /// Follows the links into `capture` back to an entity nothing links into, usually the sensor.
+        fn walk(
+            media_device: PathBuf,
+            entities: &[MediaEntityDesc],
+            links: &[MediaLink],
+            capture: u32,
+        ) -> Self {
+            let mut chain = vec![capture];
+            let mut chain_links = Vec::new();
+            while let Some(current) = chain.last().copied() {
+                let incoming = links
+                    .iter()
+                    .filter(|link| link.sink.0 == current && !chain.contains(&link.source.0))
+                    .collect::<Vec<&MediaLink>>();
+                // the link the driver enabled, otherwise the first one there is
+                let Some(link) = incoming
+                    .iter()
+                    .find(|link| link.flags & MEDIA_LNK_FL_ENABLED != 0)
+                    .or_else(|| incoming.first())
+                    .map(|link| **link)
+                else {
+                    break;
+                };
+                chain_links.push(link);
+                chain.push(link.source.0);
+            }
+            chain.reverse();
+            chain_links.reverse();
+            MediaPipeline {
+                media_device,
+                entities: chain
+                    .iter()
+                    .filter_map(|id| entities.iter().find(|entity| entity.id == *id))
+                    .map(MediaEntity::from_desc)
+                    .collect(),
+                links: chain_links,
+            }
+        }
+
+        /// The media device the pipeline is part of, e.g. `/dev/media0`.
+        #[must_use]
+        pub fn media_device(&self) -> &Path {
+            &self.media_device
+        }
+
+        /// The entities of the pipeline, from the sensor to the capture node.
+        #[must_use]
+        pub fn entities(&self) -> &[MediaEntity] {
+            &self.entities
+        }
+
+        /// Enables the links of the pipeline, and sets the sensor to `resolution` and every sub-device after it to the
+        /// format coming in. Returns the resolution that reaches the capture node, as the sensor and sub-devices may
+        /// pick a different one.
+        ///
+        /// Sub-devices keep the media bus code of their output, so an ISP that turns Bayer into YUV keeps doing that.
+        /// # Errors
+        /// This errors if a link cannot be enabled or a format cannot be set, e.g. while the capture node streams.
+        pub fn configure(&self, resolution: Resolution) -> Result<Resolution, NokhwaError> {
+            self.setup_links()
+                .and_then(|()| self.propagate_format(resolution))
+                .map_err(|why| {
+                    NokhwaError::SetPropertyError {
+                        property: "Media pipeline".to_string(),
+                        value: resolution.to_string(),
+                        error: why.to_string(),
+                    }
+                    .with_io_error(&why)
                 })
-                .map_err(|why| NokhwaError::SetPropertyError {
-                    property: id.to_string(),
-                    value: format!("{:?}", value),
-                    error: why.to_string(),
-                })?;
-            // verify
+        }
 
-            let control = self.camera_control(id)?;
-            if control.value() != value {
-                return Err(NokhwaError::SetPropertyError {
-                    property: id.to_string(),
-                    value: format!("{:?}", value),
-                    error: "Rejected".to_string(),
-                });
+        fn setup_links(&self) -> io::Result<()> {
+            let media = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.media_device)?;
+            let fd = media.as_raw_fd();
+            let current = media_entities(fd).and_then(|entities| media_links(fd, &entities))?;
+            for link in &self.links {
+                if link.flags & MEDIA_LNK_FL_IMMUTABLE != 0 {
+                    continue;
+                }
+                // sink pads usually take one enabled link at a time
+                for other in current.iter().filter(|other| {
+                    other.sink == link.sink
+                        && other.source != link.source
+                        && other.flags & (MEDIA_LNK_FL_ENABLED | MEDIA_LNK_FL_IMMUTABLE)
+                            == MEDIA_LNK_FL_ENABLED
+                }) {
+                    setup_link(fd, other, false)?;
+                }
+                setup_link(fd, link, true)?;
             }
             Ok(())
         }
 
-        fn open_stream(&mut self) -> Result<(), NokhwaError> {
-            let stream = match MmapStream::new(&self.device, v4l::buffer::Type::VideoCapture) {
-                Ok(s) => s,
-                Err(why) => return Err(NokhwaError::OpenStreamError(why.to_string())),
+        fn propagate_format(&self, resolution: Resolution) -> io::Result<Resolution> {
+            // the format going out of the last sub-device that was set
+            let mut outgoing: Option<MbusFrameFormat> = None;
+            for (index, link) in self.links.iter().enumerate() {
+                let Some(node) = self.entities[index].device_node() else {
+                    continue;
+                };
+                let subdev = fs::OpenOptions::new().read(true).write(true).open(node)?;
+                let fd = subdev.as_raw_fd();
+                let size = match (outgoing, index.checked_sub(1)) {
+                    (Some(incoming), Some(previous)) => {
+                        // drivers pass the sink format on to the source, so the source is read after
+                        let sink = set_subdev_format(fd, self.links[previous].sink.1, incoming)?;
+                        (sink.width, sink.height)
+                    }
+                    _ => (resolution.width(), resolution.height()),
+                };
+                let mut source = subdev_format(fd, link.source.1)?;
+                (source.width, source.height) = size;
+                outgoing = Some(set_subdev_format(fd, link.source.1, source)?);
+            }
+            Ok(outgoing.map_or(resolution, |format| {
+                Resolution::new(format.width, format.height)
+            }))
+        }
+    }
+
+    /// Enumerates the entities of the media device `fd`.
+    fn media_entities(fd: std::os::raw::c_int) -> io::Result<Vec<MediaEntityDesc>> {
+        let mut entities = Vec::new();
+        let mut id = 0;
+        loop {
+            let mut entity = MediaEntityDesc {
+                id: id | MEDIA_ENT_ID_FLAG_NEXT,
+                ..unsafe { mem::zeroed() }
             };
-            self.stream_handle = Some(stream);
-            Ok(())
+            match unsafe {
+                v4l2::ioctl(
+                    fd,
+                    MEDIA_IOC_ENUM_ENTITIES,
+                    std::ptr::addr_of_mut!(entity).cast(),
+                )
+            } {
+                Ok(()) => {}
+                // there is no entity after the last one
+                Err(why) if why.raw_os_error() == Some(libc::EINVAL) => return Ok(entities),
+                Err(why) => return Err(why),
+            }
+            id = entity.id;
+            entities.push(entity);
         }
+    }
 
-        fn is_stream_open(&self) -> bool {
-            self.stream_handle.is_some()
+    /// Enumerates the data links going out of `entities` of the media device `fd`.
+    fn media_links(
+        fd: std::os::raw::c_int,
+        entities: &[MediaEntityDesc],
+    ) -> io::Result<Vec<MediaLink>> {
+        let mut links = Vec::new();
+        for entity in entities {
+            let mut pads = vec![unsafe { mem::zeroed::<MediaPadDesc>() }; usize::from(entity.pads)];
+            let mut entity_links =
+                vec![unsafe { mem::zeroed::<MediaLinkDesc>() }; usize::from(entity.links)];
+            let mut request = MediaLinksEnum {
+                entity: entity.id,
+                pads: pads.as_mut_ptr(),
+                links: entity_links.as_mut_ptr(),
+                reserved: [0; 4],
+            };
+            unsafe {
+                v4l2::ioctl(
+                    fd,
+                    MEDIA_IOC_ENUM_LINKS,
+                    std::ptr::addr_of_mut!(request).cast(),
+                )?;
+            }
+            links.extend(
+                entity_links
+                    .iter()
+                    .filter(|link| link.flags & MEDIA_LNK_FL_LINK_TYPE == 0)
+                    .map(|link| MediaLink {
+                        source: (link.source.entity, link.source.index),
+                        sink: (link.sink.entity, link.sink.index),
+                        flags: link.flags,
+                    }),
+            );
+        }
+        Ok(links)
+    }
+
+    fn setup_link(fd: std::os::raw::c_int, link: &MediaLink, enabled: bool) -> io::Result<()> {
+        let pad = |(entity, index): (u32, u16)| MediaPadDesc {
+            entity,
+            index,
+            flags: 0,
+            reserved: [0; 2],
+        };
+        let mut desc = MediaLinkDesc {
+            source: pad(link.source),
+            sink: pad(link.sink),
+            flags: if enabled {
+                link.flags | MEDIA_LNK_FL_ENABLED
+            } else {
+                link.flags & !MEDIA_LNK_FL_ENABLED
+            },
+            reserved: [0; 2],
+        };
+        unsafe {
+            v4l2::ioctl(
+                fd,
+                MEDIA_IOC_SETUP_LINK,
+                std::ptr::addr_of_mut!(desc).cast(),
+            )
         }
+    }
 
-        fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
-            let cam_fmt = self.camera_format;
-            let raw_frame = self.frame_raw()?;
-            Ok(FrameBuffer::new(
-                cam_fmt.resolution(),
-                &raw_frame,
-                cam_fmt.format(),
-            ))
+    fn subdev_format(fd: std::os::raw::c_int, pad: u16) -> io::Result<MbusFrameFormat> {
+        let mut format = SubdevFormat {
+            which: V4L2_SUBDEV_FORMAT_ACTIVE,
+            pad: u32::from(pad),
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe {
+            v4l2::ioctl(
+                fd,
+                VIDIOC_SUBDEV_G_FMT,
+                std::ptr::addr_of_mut!(format).cast(),
+            )?;
         }
+        Ok(format.format)
+    }
 
-        fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
-            match &mut self.stream_handle {
-                Some(sh) => match sh.next() {
-                    Ok((data, _)) => Ok(Cow::Borrowed(data)),
-                    Err(why) => Err(NokhwaError::ReadFrameError(why.to_string())),
-                },
-                None => Err(NokhwaError::ReadFrameError(
-                    "Stream Not Started".to_string(),
-                )),
+    /// Sets the format of `pad`, returning the one the driver picked.
+    fn set_subdev_format(
+        fd: std::os::raw::c_int,
+        pad: u16,
+        mbus_format: MbusFrameFormat,
+    ) -> io::Result<MbusFrameFormat> {
+        let mut format = SubdevFormat {
+            which: V4L2_SUBDEV_FORMAT_ACTIVE,
+            pad: u32::from(pad),
+            format: mbus_format,
+            reserved: [0; 8],
+        };
+        unsafe {
+            v4l2::ioctl(
+                fd,
+                VIDIOC_SUBDEV_S_FMT,
+                std::ptr::addr_of_mut!(format).cast(),
+            )?;
+        }
+        Ok(format.format)
+    }
+
+    /// Watches `udev` for `video4linux` devices being added or removed, calling `on_change` for every event.
+    /// The watch runs on its own thread, and stops when this is dropped. Needs the `udev` feature.
+    #[cfg(feature = "udev")]
+    pub struct DeviceNotifier {
+        die_bool: Arc<AtomicBool>,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    #[cfg(feature = "udev")]
+    impl DeviceNotifier {
+        /// Starts watching for device changes.
+        /// # Errors
+        /// If the `udev` monitor could not be created, this will error.
+        pub fn new(on_change: impl Fn() + Send + 'static) -> Result<Self, NokhwaError> {
+            let die_bool = Arc::new(AtomicBool::new(false));
+            let die_bool_clone = die_bool.clone();
+            let (ready_send, ready_recv) = mpsc::channel();
+            // the udev socket is not `Send`, so it has to be created on the thread that uses it
+            let handle = std::thread::spawn(move || {
+                let socket = match udev::MonitorBuilder::new()
+                    .and_then(|builder| builder.match_subsystem("video4linux"))
+                    .and_then(udev::MonitorBuilder::listen)
+                {
+                    Ok(socket) => {
+                        let _ = ready_send.send(Ok(()));
+                        socket
+                    }
+                    Err(why) => {
+                        let _ = ready_send.send(Err(why));
+                        return;
+                    }
+                };
+                udev_monitor_loop(&socket, &on_change, &die_bool_clone);
+            });
+
+            match ready_recv.recv() {
+                Ok(Ok(())) => Ok(DeviceNotifier {
+                    die_bool,
+                    handle: Some(handle),
+                }),
+                Ok(Err(why)) => Err(NokhwaError::GeneralError(format!(
+                    "Failed to create udev monitor: {why}"
+                ))),
+                Err(why) => Err(NokhwaError::GeneralError(why.to_string())),
             }
         }
+    }
 
-        fn stop_stream(&mut self) -> Result<(), NokhwaError> {
-            if self.stream_handle.is_some() {
-                self.stream_handle = None;
+    #[cfg(feature = "udev")]
+    impl Drop for DeviceNotifier {
+        fn drop(&mut self) {
+            self.die_bool.store(true, Ordering::SeqCst);
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
             }
-            Ok(())
+        }
+    }
+
+    #[cfg(feature = "udev")]
+    fn udev_monitor_loop(
+        socket: &udev::MonitorSocket,
+        on_change: &dyn Fn(),
+        die_bool: &AtomicBool,
+    ) {
+        let mut poll_fd = libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        while !die_bool.load(Ordering::SeqCst) {
+            // wake up regularly to check if we have been dropped
+            let ready = unsafe { libc::poll(&raw mut poll_fd, 1, 250) };
+            if ready > 0 && socket.iter().count() > 0 {
+                on_change();
+            }
+        }
+    }
+
+    /// Sets the [`ThreadPriority`] of the calling thread.
+    /// # Errors
+    /// If the thread is not allowed to raise its priority (see [`ThreadPriority`]), this will error.
+    pub fn set_current_thread_priority(priority: ThreadPriority) -> Result<(), NokhwaError> {
+        let error = |why: io::Error| NokhwaError::SetPropertyError {
+            property: "Thread priority".to_string(),
+            value: priority.to_string(),
+            error: why.to_string(),
+        };
+        let (policy, nice) = match priority {
+            ThreadPriority::Normal => (libc::SCHED_OTHER, 0),
+            ThreadPriority::High => (libc::SCHED_OTHER, -10),
+            ThreadPriority::RealTime => (libc::SCHED_FIFO, 0),
+        };
+        let param = libc::sched_param {
+            sched_priority: if policy == libc::SCHED_FIFO {
+                unsafe { libc::sched_get_priority_min(policy) }
+            } else {
+                0
+            },
+        };
+        let result =
+            unsafe { libc::pthread_setschedparam(libc::pthread_self(), policy, &raw const param) };
+        if result != 0 {
+            return Err(error(io::Error::from_raw_os_error(result)));
+        }
+        if policy == libc::SCHED_OTHER {
+            // the nice value is per thread on Linux, despite `PRIO_PROCESS`
+            let thread_id = unsafe { libc::gettid() } as libc::id_t;
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, thread_id, nice) } != 0 {
+                return Err(error(io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pins the calling thread to the CPU cores with the indices in `cores`, or lets it run on all of them if `cores` is
+    /// empty.
+    /// # Errors
+    /// If a core does not exist, this will error.
+    pub fn set_current_thread_affinity(cores: &[usize]) -> Result<(), NokhwaError> {
+        let error = |why: String| NokhwaError::SetPropertyError {
+            property: "Thread affinity".to_string(),
+            value: format!("{cores:?}"),
+            error: why,
+        };
+        let max_cores = mem::size_of::<libc::cpu_set_t>() * 8;
+        let mut set = unsafe { mem::zeroed::<libc::cpu_set_t>() };
+        if cores.is_empty() {
+            // every configured core, not just the ones the thread may run on right now
+            let configured = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+            let configured = usize::try_from(configured).unwrap_or(1).min(max_cores);
+            (0..configured).for_each(|core| unsafe { libc::CPU_SET(core, &mut set) });
+        }
+        for core in cores {
+            if *core >= max_cores {
+                return Err(error(format!("There is no core {core}")));
+            }
+            unsafe { libc::CPU_SET(*core, &mut set) };
+        }
+        if unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &raw const set) }
+            != 0
+        {
+            return Err(error(io::Error::last_os_error().to_string()));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn frame_intervals_map_to_exact_frame_rates() {
+            assert_eq!(
+                interval_frame_rate(Fraction::new(1, 30)),
+                FrameRate::from_fps(30)
+            );
+            // 29.97 FPS
+            let ntsc = interval_frame_rate(Fraction::new(1001, 30000));
+            assert_eq!(ntsc, FrameRate::new(30000, 1001));
+            assert!(!ntsc.is_integer());
+            assert_eq!(ntsc.fps(), 30);
+            // drivers often report 30 FPS as 333333 / 10000000 seconds
+            let rounded = interval_frame_rate(Fraction::new(333_333, 10_000_000));
+            assert_eq!(rounded.fps(), 30);
+            // one frame every two seconds
+            assert_eq!(
+                interval_frame_rate(Fraction::new(2, 1)),
+                FrameRate::new(1, 2)
+            );
+        }
+
+        #[test]
+        fn frame_rates_round_trip_through_intervals() {
+            for frame_rate in [
+                FrameRate::from_fps(60),
+                FrameRate::new(30000, 1001),
+                FrameRate::new(1, 2),
+            ] {
+                let interval = frame_interval(frame_rate);
+                assert_eq!(interval_frame_rate(interval), frame_rate);
+            }
+            let interval = frame_interval(FrameRate::new(60000, 1001));
+            assert_eq!((interval.numerator, interval.denominator), (1001, 60000));
         }
     }
 }
@@ -775,11 +4270,12 @@ mod internal {
 mod internal {
     use four_cc::FourCC;
     use nokhwa_core::buffer::FrameBuffer;
+    use nokhwa_core::conversion::HardwareDecoder;
     use nokhwa_core::error::NokhwaError;
-    use nokhwa_core::traits::CaptureBackendTrait;
+    use nokhwa_core::traits::{CaptureDevice, VirtualBackendTrait};
     use nokhwa_core::types::{
         ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
-        KnownCameraControl, RequestedFormat, Resolution,
+        KnownCameraControl, RequestedFormat, Resolution, ThreadPriority,
     };
     use std::borrow::Cow;
     use std::collections::HashMap;
@@ -800,9 +4296,9 @@ mod internal {
     }
 
     /// The backend struct that interfaces with V4L2.
-    /// To see what this does, please see [`CaptureBackendTrait`].
+    /// To see what this does, please see [`CaptureDevice`].
     /// # Quirks
-    /// - Calling [`set_resolution()`](CaptureBackendTrait::set_resolution), [`set_frame_rate()`](CaptureBackendTrait::set_frame_rate), or [`set_frame_format()`](CaptureBackendTrait::set_frame_format) each internally calls [`set_camera_format()`](CaptureBackendTrait::set_camera_format).
+    /// - Calling [`set_resolution()`](CaptureDevice::set_resolution), [`set_frame_rate()`](CaptureDevice::set_frame_rate), or [`set_frame_format()`](CaptureDevice::set_frame_format) each internally calls [`set_camera_format()`](CaptureDevice::set_camera_format).
     pub struct V4LCaptureDevice<'a> {
         __holder: PhantomData<&'a str>,
     }
@@ -843,10 +4339,102 @@ mod internal {
                 "V4L2 only on Linux".to_string(),
             ))
         }
+
+        /// Hands frames out as DMA-BUFs instead of copying them.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_dmabuf_export(&mut self, export: bool) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Whether frames are exported as DMA-BUFs.
+        #[must_use]
+        pub fn dmabuf_export(&self) -> bool {
+            false
+        }
+
+        /// Decodes H.264 and HEVC frames on a V4L2 memory-to-memory decoder.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_decode_compressed(&mut self, decode: bool) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Whether H.264 and HEVC frames are decoded.
+        #[must_use]
+        pub fn decode_compressed(&self) -> bool {
+            false
+        }
+
+        /// Sets how many buffers the driver is given to capture into.
+        /// # Errors
+        /// This errors if the stream is open, or `count` is 0.
+        pub fn set_buffer_count(&mut self, count: u32) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// How many buffers are requested from the driver.
+        #[must_use]
+        pub fn buffer_count(&self) -> u32 {
+            0
+        }
+
+        /// Sets how the buffers frames are captured into are allocated.
+        /// # Errors
+        /// This errors if the stream is open.
+        pub fn set_buffer_memory(&mut self, memory: BufferMemory) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// How the buffers are allocated.
+        #[must_use]
+        pub fn buffer_memory(&self) -> BufferMemory {
+            BufferMemory::Mmap
+        }
+
+        /// Gets a handle that wakes up a thread waiting for a frame of this device. Waking it does nothing.
+        #[must_use]
+        pub fn wake_handle(&self) -> WakeHandle {
+            WakeHandle {
+                __holder: PhantomData,
+            }
+        }
+
+        /// Sets whether opening the stream sets up the media pipeline feeding the device.
+        /// # Errors
+        /// This always errors, V4L2 is only available on Linux.
+        pub fn set_configure_pipeline(&mut self, configure: bool) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Whether opening the stream sets up the media pipeline.
+        #[must_use]
+        pub fn configure_pipeline(&self) -> bool {
+            false
+        }
+
+        /// Finds the [`MediaPipeline`] feeding the device.
+        /// # Errors
+        /// This always errors, V4L2 is only available on Linux.
+        pub fn media_pipeline(&self) -> Result<Option<MediaPipeline>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
     }
 
     #[allow(unused_variables)]
-    impl<'a> CaptureBackendTrait for V4LCaptureDevice<'a> {
+    impl<'a> CaptureDevice for V4LCaptureDevice<'a> {
         fn backend(&self) -> ApiBackend {
             ApiBackend::Video4Linux
         }
@@ -941,6 +4529,265 @@ mod internal {
             todo!()
         }
     }
+
+    /// A virtual camera sink that writes frames into a [`v4l2loopback`](https://github.com/umlaeute/v4l2loopback) device.
+    /// To see what this does, please see [`VirtualBackendTrait`].
+    pub struct V4L2LoopbackDevice {
+        __holder: PhantomData<()>,
+    }
+
+    #[allow(unused_variables)]
+    impl V4L2LoopbackDevice {
+        /// Opens a `v4l2loopback` device and makes it advertise `format`.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn new(index: &CameraIndex, format: CameraFormat) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+    }
+
+    #[allow(unused_variables)]
+    impl VirtualBackendTrait for V4L2LoopbackDevice {
+        fn backend(&self) -> ApiBackend {
+            ApiBackend::Video4Linux
+        }
+
+        fn camera_format(&self) -> CameraFormat {
+            todo!()
+        }
+
+        fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+            todo!()
+        }
+
+        fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError> {
+            todo!()
+        }
+    }
+
+    /// Decodes MJPEG on a V4L2 memory-to-memory JPEG decoder, such as the ones in Raspberry Pi, Rockchip and i.MX SoCs.
+    /// Register it with [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+    pub struct V4L2JpegDecoder {
+        __holder: PhantomData<()>,
+    }
+
+    #[allow(unused_variables)]
+    impl V4L2JpegDecoder {
+        /// Finds the first memory-to-memory device that decodes MJPEG.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn new() -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Opens the memory-to-memory device at `path`.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn with_path(path: impl AsRef<std::path::Path>) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Creates a decoder for [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn create() -> Result<Box<dyn HardwareDecoder>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+    }
+
+    /// How the buffers of a [`V4LCaptureDevice`] are allocated.
+    #[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+    pub enum BufferMemory {
+        /// The driver allocates the buffers, which are mapped into memory (`V4L2_MEMORY_MMAP`).
+        #[default]
+        Mmap,
+        /// The buffers are allocated in memory of the process and handed to the driver (`V4L2_MEMORY_USERPTR`).
+        UserPtr,
+    }
+
+    /// Wakes up a thread waiting for a frame of a [`V4LCaptureDevice`].
+    #[derive(Clone, Debug)]
+    pub struct WakeHandle {
+        __holder: PhantomData<()>,
+    }
+
+    impl WakeHandle {
+        /// Wakes up the thread waiting for a frame.
+        pub fn wake(&self) {}
+    }
+
+    /// An entity of a [`MediaPipeline`].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct MediaEntity {
+        __holder: PhantomData<()>,
+    }
+
+    impl MediaEntity {
+        /// The name the driver gave the entity.
+        #[must_use]
+        pub fn name(&self) -> &str {
+            ""
+        }
+
+        /// What the entity does, one of the `MEDIA_ENT_F_*` values of `linux/media.h`.
+        #[must_use]
+        pub fn function(&self) -> u32 {
+            0
+        }
+
+        /// Whether the entity is a camera sensor.
+        #[must_use]
+        pub fn is_sensor(&self) -> bool {
+            false
+        }
+
+        /// The device node of the entity.
+        #[must_use]
+        pub fn device_node(&self) -> Option<&std::path::Path> {
+            None
+        }
+    }
+
+    /// The chain of entities of a media controller graph that feeds a capture node.
+    #[derive(Clone, Debug)]
+    pub struct MediaPipeline {
+        __holder: PhantomData<()>,
+    }
+
+    #[allow(unused_variables)]
+    impl MediaPipeline {
+        /// Finds the pipeline feeding `video_node`.
+        /// # Errors
+        /// This always errors, V4L2 is only available on Linux.
+        pub fn discover(
+            video_node: impl AsRef<std::path::Path>,
+        ) -> Result<Option<Self>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// The media device the pipeline is part of.
+        #[must_use]
+        pub fn media_device(&self) -> &std::path::Path {
+            std::path::Path::new("")
+        }
+
+        /// The entities of the pipeline, from the sensor to the capture node.
+        #[must_use]
+        pub fn entities(&self) -> &[MediaEntity] {
+            &[]
+        }
+
+        /// Enables the links of the pipeline and sets its formats for `resolution`.
+        /// # Errors
+        /// This always errors, V4L2 is only available on Linux.
+        pub fn configure(&self, resolution: Resolution) -> Result<Resolution, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+    }
+
+    /// Decodes H.264 and HEVC on a V4L2 stateful memory-to-memory video decoder.
+    pub struct V4L2VideoDecoder {
+        __holder: PhantomData<()>,
+    }
+
+    #[allow(unused_variables)]
+    impl V4L2VideoDecoder {
+        /// Finds the first memory-to-memory device that decodes `codec`.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn new(codec: FourCC) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Opens the memory-to-memory device at `path`.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn with_path(
+            path: impl AsRef<std::path::Path>,
+            codec: FourCC,
+        ) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+
+        /// Gets a name for the decoder, e.g. for logging.
+        #[must_use]
+        pub fn name(&self) -> &str {
+            ""
+        }
+
+        /// Gets the format the decoder takes.
+        #[must_use]
+        pub fn codec(&self) -> FourCC {
+            FourCC(*b"H264")
+        }
+
+        /// Feeds one frame of the stream to the decoder, returning the next decoded frame if one is ready.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn decode(
+            &mut self,
+            resolution: Resolution,
+            data: &[u8],
+        ) -> Result<Option<(FourCC, Vec<u8>)>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+    }
+
+    /// Watches `udev` for `video4linux` devices being added or removed, calling `on_change` for every event.
+    /// The watch runs on its own thread, and stops when this is dropped. Needs the `udev` feature.
+    #[cfg(feature = "udev")]
+    pub struct DeviceNotifier {
+        __holder: PhantomData<()>,
+    }
+
+    #[cfg(feature = "udev")]
+    #[allow(unused_variables)]
+    impl DeviceNotifier {
+        /// Starts watching for device changes.
+        /// # Errors
+        /// This function will always error on non-Linux platforms.
+        pub fn new(on_change: impl Fn() + Send + 'static) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "V4L2 only on Linux".to_string(),
+            ))
+        }
+    }
+
+    /// Sets the [`ThreadPriority`] of the calling thread.
+    /// # Errors
+    /// This function will always error on non-Linux platforms.
+    pub fn set_current_thread_priority(_priority: ThreadPriority) -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "V4L2 only on Linux".to_string(),
+        ))
+    }
+
+    /// Pins the calling thread to the CPU cores with the indices in `cores`.
+    /// # Errors
+    /// This function will always error on non-Linux platforms.
+    pub fn set_current_thread_affinity(_cores: &[usize]) -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "V4L2 only on Linux".to_string(),
+        ))
+    }
 }
 
 pub use internal::*;
@@ -17,7 +17,15 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 mod internal {
-    use std::{ffi::c_void, sync::Arc, time::Instant};
+    use std::{
+        ffi::c_void,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Mutex, MutexGuard,
+        },
+        thread::ThreadId,
+        time::{Duration, Instant},
+    };
 
     #[cfg(target_os = "ios")]
     use av_foundation::capture_device::{
@@ -33,34 +41,58 @@ mod internal {
             AVCaptureFocusModeContinuousAutoFocus, AVCaptureFocusModeLocked,
         },
         capture_device_discovery_session::AVCaptureDeviceDiscoverySession,
+        capture_input::AVCaptureDeviceInput,
         capture_output_base::AVCaptureOutput,
-        capture_session::AVCaptureConnection,
-        capture_video_data_output::AVCaptureVideoDataOutputSampleBufferDelegate,
+        capture_session::{AVCaptureConnection, AVCaptureSession},
+        capture_video_data_output::{
+            AVCaptureVideoDataOutput, AVCaptureVideoDataOutputSampleBufferDelegate,
+        },
         media_format::AVMediaTypeVideo,
     };
-    use core_foundation::base::TCFType;
+    use block::ConcreteBlock;
+    use core_foundation::{
+        array::{CFArray, CFArrayRef},
+        base::{CFType, CFTypeRef, TCFType},
+        boolean::CFBoolean,
+        dictionary::{CFDictionary, CFDictionaryRef},
+        number::CFNumber,
+        string::{CFString, CFStringRef},
+    };
     use core_media::{
         sample_buffer::{CMSampleBuffer, CMSampleBufferRef},
         time::CMTime,
         OSType,
     };
     use core_video::pixel_buffer::CVPixelBuffer;
+    use dispatch2::{Queue, QueueAttribute};
     use flume::Sender;
     use four_cc::FourCC;
     use nokhwa_core::{
-        buffer::FrameBuffer,
-        error::NokhwaError,
+        buffer::{
+            AudioBuffer, AudioSampleFormat, BufferPool, ExternalBuffer, FrameBuffer, FrameMetadata,
+            PooledBuffer,
+        },
+        conversion::{ColorSpace, HardwareDecoder, YuvMatrix, YuvRange},
+        error::{ErrorKind, NokhwaError, PlatformError},
+        pixel_format::{NV12, YUV420},
+        traits::VirtualBackendTrait,
         types::{
-            ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo,
-            ControlValueDescription, ControlValueSetter, KnownCameraControl, Resolution,
+            ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, CameraPosition,
+            ContinuityCamera, ControlValueDescription, ControlValueSetter, FlashMode,
+            InterruptionReason, KnownCameraControl, KnownCameraControlFlag, PermissionStatus,
+            PhotoCodec, PhotoSettings, PtzCapabilities, PtzRange, Resolution, Rotation,
+            SessionEvent, SystemPressureLevel, ThreadPriority,
         },
     };
     use objc2::{
-        declare_class, extern_methods, msg_send, msg_send_id, mutability,
+        class, declare_class,
+        encode::{Encode, Encoding},
+        extern_methods, msg_send, msg_send_id, mutability,
         rc::{Allocated, Id, Retained},
-        ClassType, DeclaredClass,
+        runtime::{AnyClass, AnyObject, Bool, MessageReceiver, ProtocolObject, Sel},
+        sel, ClassType, DeclaredClass,
     };
-    use objc2_foundation::{NSArray, NSObject, NSObjectProtocol, NSString};
+    use objc2_foundation::{CGPoint, NSArray, NSInteger, NSObject, NSObjectProtocol, NSString};
 
     #[allow(non_upper_case_globals)]
     fn raw_fcc_to_fourcc(raw: OSType) -> FourCC {
@@ -69,8 +101,248 @@ mod internal {
 
     pub type SenderType = Sender<FrameBuffer>;
 
+    #[link(name = "CoreMedia", kind = "framework")]
+    extern "C" {
+        fn CMSampleBufferGetPresentationTimeStamp(sbuf: CMSampleBufferRef) -> CMTime;
+        fn CMClockGetHostTimeClock() -> *const c_void;
+        fn CMClockGetTime(clock: *const c_void) -> CMTime;
+        fn CMGetAttachment(
+            target: CFTypeRef,
+            key: CFStringRef,
+            attachment_mode_out: *mut u32,
+        ) -> CFTypeRef;
+        fn CMSampleBufferGetFormatDescription(sbuf: CMSampleBufferRef) -> *const c_void;
+        fn CMAudioFormatDescriptionGetStreamBasicDescription(
+            desc: *const c_void,
+        ) -> *const AudioStreamBasicDescription;
+        fn CMSampleBufferGetDataBuffer(sbuf: CMSampleBufferRef) -> *const c_void;
+        fn CMBlockBufferGetDataLength(buffer: *const c_void) -> usize;
+        fn CMBlockBufferCopyDataBytes(
+            buffer: *const c_void,
+            offset: usize,
+            length: usize,
+            destination: *mut c_void,
+        ) -> i32;
+    }
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct AudioStreamBasicDescription {
+        mSampleRate: f64,
+        mFormatID: u32,
+        mFormatFlags: u32,
+        mBytesPerPacket: u32,
+        mFramesPerPacket: u32,
+        mBytesPerFrame: u32,
+        mChannelsPerFrame: u32,
+        mBitsPerChannel: u32,
+        mReserved: u32,
+    }
+
+    /// `kAudioFormatLinearPCM`
+    const AUDIO_FORMAT_LINEAR_PCM: u32 = u32::from_be_bytes(*b"lpcm");
+    /// `kAudioFormatFlagIsFloat`
+    const AUDIO_FORMAT_FLAG_IS_FLOAT: u32 = 1 << 0;
+    /// `kAudioFormatFlagIsNonInterleaved`
+    const AUDIO_FORMAT_FLAG_IS_NON_INTERLEAVED: u32 = 1 << 5;
+
+    fn cmtime_to_duration(time: CMTime) -> Option<Duration> {
+        // kCMTimeFlags_Valid
+        if time.flags & 1 == 0 || time.timescale <= 0 {
+            return None;
+        }
+        let value = u128::try_from(time.value).ok()?;
+        let nanos = value * 1_000_000_000 / time.timescale as u128;
+        Some(Duration::from_nanos(u64::try_from(nanos).ok()?))
+    }
+
+    /// The presentation timestamp of a sample buffer, along with the current time on the host time clock it is stamped with.
+    fn sample_buffer_presentation_time(
+        sample_buffer: &CMSampleBuffer,
+    ) -> Option<(Duration, Duration)> {
+        let presentation_time = cmtime_to_duration(unsafe {
+            CMSampleBufferGetPresentationTimeStamp(sample_buffer.as_concrete_TypeRef())
+        })?;
+        let host_now = cmtime_to_duration(unsafe { CMClockGetTime(CMClockGetHostTimeClock()) })?;
+        Some((presentation_time, host_now))
+    }
+
+    /// Reads the exposure time and ISO from the `{Exif}` attachment of a sample buffer.
+    fn sample_buffer_metadata(sample_buffer: &CMSampleBuffer) -> Option<FrameMetadata> {
+        let exif_key = CFString::from_static_string("{Exif}");
+        let exif = unsafe {
+            CMGetAttachment(
+                sample_buffer.as_CFTypeRef(),
+                exif_key.as_concrete_TypeRef(),
+                std::ptr::null_mut(),
+            )
+        };
+        if exif.is_null() {
+            return None;
+        }
+        let exif: CFDictionary<CFString, CFType> =
+            unsafe { CFDictionary::wrap_under_get_rule(exif as CFDictionaryRef) };
+
+        let exposure_duration = exif
+            .find(&CFString::from_static_string("ExposureTime"))
+            .and_then(|value| value.downcast::<CFNumber>())
+            .and_then(|seconds| seconds.to_f64())
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+        // ISOSpeedRatings is an array, but cameras only ever report one value
+        let iso = exif
+            .find(&CFString::from_static_string("ISOSpeedRatings"))
+            .filter(|value| value.type_of() == CFArray::<CFNumber>::type_id())
+            .and_then(|value| {
+                let ratings: CFArray<CFNumber> =
+                    unsafe { CFArray::wrap_under_get_rule(value.as_CFTypeRef() as CFArrayRef) };
+                ratings.get(0).and_then(|rating| rating.to_f64())
+            })
+            .map(|rating| rating as f32);
+
+        Some(FrameMetadata::new(exposure_duration, iso, None, None))
+    }
+
+    /// Reads the YCbCr matrix a pixel buffer is tagged with. Of the formats `AVFoundation` delivers, only `420f` is full range.
+    fn pixel_buffer_color_space(
+        pixel_buffer: &CVPixelBuffer,
+        pixel_format: OSType,
+    ) -> Option<ColorSpace> {
+        let matrix_key = CFString::from_static_string("CVImageBufferYCbCrMatrix");
+        let matrix = unsafe {
+            CMGetAttachment(
+                pixel_buffer.as_CFTypeRef(),
+                matrix_key.as_concrete_TypeRef(),
+                std::ptr::null_mut(),
+            )
+        };
+        if matrix.is_null() {
+            return None;
+        }
+        let matrix = unsafe { CFType::wrap_under_get_rule(matrix) }.downcast::<CFString>()?;
+        let matrix = match matrix.to_string().as_str() {
+            "ITU_R_601_4" => YuvMatrix::Bt601,
+            "ITU_R_709_2" => YuvMatrix::Bt709,
+            _ => return None,
+        };
+        let range = if raw_fcc_to_fourcc(pixel_format) == FourCC(*b"420f") {
+            YuvRange::Full
+        } else {
+            YuvRange::Limited
+        };
+        Some(ColorSpace::new(matrix, range))
+    }
+
+    /// Copies the PCM samples of an audio sample buffer, interleaving them if they are not.
+    fn sample_buffer_audio(sample_buffer: &CMSampleBuffer) -> Option<AudioBuffer> {
+        let sample_buffer_ref = sample_buffer.as_concrete_TypeRef();
+        let description = unsafe {
+            CMAudioFormatDescriptionGetStreamBasicDescription(CMSampleBufferGetFormatDescription(
+                sample_buffer_ref,
+            ))
+            .as_ref()?
+        };
+        if description.mFormatID != AUDIO_FORMAT_LINEAR_PCM {
+            return None;
+        }
+        let is_float = description.mFormatFlags & AUDIO_FORMAT_FLAG_IS_FLOAT != 0;
+        let sample_format = match (is_float, description.mBitsPerChannel) {
+            (true, 32) => AudioSampleFormat::F32,
+            (false, 16) => AudioSampleFormat::I16,
+            (false, 32) => AudioSampleFormat::I32,
+            _ => return None,
+        };
+        let channels = u16::try_from(description.mChannelsPerFrame).ok()?;
+
+        let block_buffer = unsafe { CMSampleBufferGetDataBuffer(sample_buffer_ref) };
+        if block_buffer.is_null() {
+            return None;
+        }
+        let length = unsafe { CMBlockBufferGetDataLength(block_buffer) };
+        let mut data = vec![0_u8; length];
+        let status = unsafe {
+            CMBlockBufferCopyDataBytes(block_buffer, 0, length, data.as_mut_ptr().cast())
+        };
+        if status != 0 {
+            return None;
+        }
+
+        // non-interleaved buffers hold one plane per channel, one after another
+        if description.mFormatFlags & AUDIO_FORMAT_FLAG_IS_NON_INTERLEAVED != 0 && channels > 1 {
+            let sample_size = sample_format.sample_size();
+            let plane_len = length / usize::from(channels);
+            let mut interleaved = Vec::with_capacity(length);
+            for offset in (0..plane_len).step_by(sample_size) {
+                for channel in 0..usize::from(channels) {
+                    let start = channel * plane_len + offset;
+                    interleaved.extend_from_slice(&data[start..start + sample_size]);
+                }
+            }
+            data = interleaved;
+        }
+
+        let audio = AudioBuffer::new(
+            description.mSampleRate as u32,
+            channels,
+            sample_format,
+            data,
+        );
+        Some(match sample_buffer_presentation_time(sample_buffer) {
+            Some((presentation_time, host_now)) => {
+                audio.with_capture_timestamp(presentation_time, host_now)
+            }
+            None => audio,
+        })
+    }
+
+    pub type AudioSenderType = Sender<AudioBuffer>;
+
+    pub struct AudioCaptureDelegateIvars {
+        sender: AudioSenderType,
+    }
+
+    declare_class!(
+        pub struct AudioCaptureDelegate;
+
+        unsafe impl ClassType for AudioCaptureDelegate {
+            type Super = NSObject;
+            type Mutability = mutability::InteriorMutable;
+            const NAME: &'static str = "NokhwaAudioCaptureDelegate";
+        }
+
+        impl DeclaredClass for AudioCaptureDelegate {
+            type Ivars = AudioCaptureDelegateIvars;
+        }
+
+        unsafe impl NSObjectProtocol for AudioCaptureDelegate {}
+
+        unsafe impl AudioCaptureDelegate {
+            #[method(captureOutput:didOutputSampleBuffer:fromConnection:)]
+            unsafe fn capture_output_did_output_sample_buffer(
+                &self,
+                _capture_output: &AnyObject,
+                sample_buffer: CMSampleBufferRef,
+                _connection: &AnyObject,
+            ) {
+                let sample_buffer = CMSampleBuffer::wrap_under_get_rule(sample_buffer);
+                if let Some(audio) = sample_buffer_audio(&sample_buffer) {
+                    let _ = self.ivars().sender.send(audio);
+                }
+            }
+        }
+    );
+
+    impl AudioCaptureDelegate {
+        fn new(sender: AudioSenderType) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(AudioCaptureDelegateIvars { sender });
+            unsafe { msg_send_id![super(this), init] }
+        }
+    }
+
     pub struct DelegateIvars {
         sender: *const c_void,
+        dropped_frames: AtomicU64,
+        pool: BufferPool,
+        zero_copy: AtomicBool,
     }
 
     declare_class!(
@@ -99,27 +371,44 @@ mod internal {
                 let sample_buffer = CMSampleBuffer::wrap_under_get_rule(sample_buffer);
                 if let Some(image_buffer) = sample_buffer.get_image_buffer() {
                     if let Some(pixel_buffer) = image_buffer.downcast::<CVPixelBuffer>() {
-                        pixel_buffer.lock_base_address(0);
                         let width = pixel_buffer.get_width();
                         let height = pixel_buffer.get_height();
-                        let base_address = pixel_buffer.get_base_address();
                         let pixel_format = pixel_buffer.get_pixel_format();
-                        let buffer_length = pixel_buffer.get_data_size();
 
-                        // Capture the bytes from the buffer
-                        let buffer_as_vec = unsafe {
-                            std::slice::from_raw_parts_mut(base_address as *mut u8, buffer_length as usize)
-                                .to_vec()
-                        };
+                        let buffer = if self.ivars().zero_copy.load(Ordering::Relaxed) {
+                            PooledBuffer::external(PixelBuffer::new(pixel_buffer.clone()))
+                        } else {
+                            pixel_buffer.lock_base_address(0);
+                            let base_address = pixel_buffer.get_base_address();
+                            let buffer_length = pixel_buffer.get_data_size();
+
+                            // Capture the bytes from the buffer, reusing an allocation from a previous frame
+                            let buffer = self.ivars().pool.copy_from_slice(unsafe {
+                                std::slice::from_raw_parts(base_address as *const u8, buffer_length as usize)
+                            });
 
-                        pixel_buffer.unlock_base_address(0);
+                            pixel_buffer.unlock_base_address(0);
+                            buffer
+                        };
 
                         let sender_raw = self.ivars().sender;
                         let sender: Arc<SenderType> = unsafe {
                                     let ptr = sender_raw.cast::<SenderType>();
                                     Arc::from_raw(ptr)
                                 };
-                        let framebuffer = FrameBuffer::new(Resolution::new(width as u32, height as u32), &buffer_as_vec, raw_fcc_to_fourcc(pixel_format), Instant::now());
+                        let framebuffer = FrameBuffer::from_pooled(Resolution::new(width as u32, height as u32), buffer, raw_fcc_to_fourcc(pixel_format), Instant::now());
+                        let framebuffer = match sample_buffer_presentation_time(&sample_buffer) {
+                            Some((presentation_time, host_now)) => framebuffer.with_capture_timestamp(presentation_time, host_now),
+                            None => framebuffer,
+                        };
+                        let framebuffer = match sample_buffer_metadata(&sample_buffer) {
+                            Some(metadata) => framebuffer.with_metadata(metadata),
+                            None => framebuffer,
+                        };
+                        let framebuffer = match pixel_buffer_color_space(&pixel_buffer, pixel_format) {
+                            Some(color_space) => framebuffer.with_color_space(color_space),
+                            None => framebuffer,
+                        };
                         if let Err(_) = sender.send(framebuffer) {
                             return;
                         }
@@ -134,7 +423,9 @@ mod internal {
             _capture_output: &AVCaptureOutput,
             _sample_buffer: CMSampleBufferRef,
             _connection: &AVCaptureConnection,
-            ) {}
+            ) {
+                self.ivars().dropped_frames.fetch_add(1, Ordering::Relaxed);
+            }
         }
 
         unsafe impl AVCaptureDelegate {
@@ -142,6 +433,9 @@ mod internal {
             fn init(this: Allocated<Self>) -> Option<Id<Self>> {
                 let this = this.set_ivars(DelegateIvars {
                     sender: std::ptr::null(),
+                    dropped_frames: AtomicU64::new(0),
+                    pool: BufferPool::default(),
+                    zero_copy: AtomicBool::new(false),
                 });
                 unsafe { msg_send_id![super(this), init] }
             }
@@ -162,12 +456,1862 @@ mod internal {
     );
 
     impl AVCaptureDelegate {
+        /// The number of sample buffers `AVFoundation` reported as dropped to this delegate.
+        pub fn dropped_frames(&self) -> u64 {
+            self.ivars().dropped_frames.load(Ordering::Relaxed)
+        }
+
+        /// Makes the delegate hand out the `CVPixelBuffer`s `AVFoundation` delivers as [`PixelBuffer`]s, instead of
+        /// copying each frame.
+        pub fn set_zero_copy(&self, zero_copy: bool) {
+            self.ivars().zero_copy.store(zero_copy, Ordering::Relaxed);
+        }
+
+        /// Checks if frames are handed out without being copied, see [`set_zero_copy()`](AVCaptureDelegate::set_zero_copy).
+        pub fn zero_copy(&self) -> bool {
+            self.ivars().zero_copy.load(Ordering::Relaxed)
+        }
+
         pub fn set_sender(&mut self, sender: Arc<SenderType>) -> bool {
             let raw_sender = Arc::into_raw(sender) as *const c_void;
             return unsafe { msg_send![self, setSender: raw_sender] };
         }
     }
 
+    pub struct DeviceChangeObserverIvars {
+        on_change: Box<dyn Fn() + Send>,
+    }
+
+    declare_class!(
+        pub struct DeviceChangeObserver;
+
+        unsafe impl ClassType for DeviceChangeObserver {
+            type Super = NSObject;
+            type Mutability = mutability::InteriorMutable;
+            const NAME: &'static str = "NokhwaDeviceChangeObserver";
+        }
+
+        impl DeclaredClass for DeviceChangeObserver {
+            type Ivars = DeviceChangeObserverIvars;
+        }
+
+        unsafe impl NSObjectProtocol for DeviceChangeObserver {}
+
+        unsafe impl DeviceChangeObserver {
+            #[method(deviceChanged:)]
+            fn device_changed(&self, _notification: &AnyObject) {
+                (self.ivars().on_change)();
+            }
+        }
+    );
+
+    impl DeviceChangeObserver {
+        fn new(on_change: Box<dyn Fn() + Send>) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(DeviceChangeObserverIvars { on_change });
+            unsafe { msg_send_id![super(this), init] }
+        }
+    }
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVCaptureDeviceWasConnectedNotification: &'static NSString;
+        static AVCaptureDeviceWasDisconnectedNotification: &'static NSString;
+        static AVCaptureSessionRuntimeErrorNotification: &'static NSString;
+        static AVCaptureSessionErrorKey: &'static NSString;
+        static AVCaptureSessionWasInterruptedNotification: &'static NSString;
+        static AVCaptureSessionInterruptionEndedNotification: &'static NSString;
+        static AVVideoCodecKey: &'static NSString;
+        static AVVideoCodecTypeJPEG: &'static NSString;
+        static AVVideoCodecTypeHEVC: &'static NSString;
+        static AVVideoCompressionPropertiesKey: &'static NSString;
+        static AVVideoQualityKey: &'static NSString;
+        static AVMediaTypeAudio: &'static NSString;
+        static AVFormatIDKey: &'static NSString;
+        static AVLinearPCMIsFloatKey: &'static NSString;
+        static AVLinearPCMBitDepthKey: &'static NSString;
+        static AVLinearPCMIsNonInterleaved: &'static NSString;
+    }
+
+    /// Observes `AVCaptureDeviceWasConnectedNotification` and `AVCaptureDeviceWasDisconnectedNotification`,
+    /// calling `on_change` for each. The observer is removed when this is dropped.
+    ///
+    /// `AVFoundation` posts these on the main thread, so the main run loop must be running for them to arrive.
+    pub struct DeviceNotifier {
+        observer: Retained<DeviceChangeObserver>,
+    }
+
+    // SAFETY: the observer only holds a `Send` callback, and `NSNotificationCenter` is thread safe.
+    unsafe impl Send for DeviceNotifier {}
+
+    impl DeviceNotifier {
+        pub fn new(on_change: impl Fn() + Send + 'static) -> Result<Self, NokhwaError> {
+            let observer = DeviceChangeObserver::new(Box::new(on_change));
+            unsafe {
+                let center: *mut AnyObject = msg_send![class!(NSNotificationCenter), defaultCenter];
+                for name in [
+                    AVCaptureDeviceWasConnectedNotification,
+                    AVCaptureDeviceWasDisconnectedNotification,
+                ] {
+                    let _: () = msg_send![
+                        center,
+                        addObserver: &*observer,
+                        selector: sel!(deviceChanged:),
+                        name: name,
+                        object: std::ptr::null::<AnyObject>()
+                    ];
+                }
+            }
+            Ok(DeviceNotifier { observer })
+        }
+    }
+
+    impl Drop for DeviceNotifier {
+        fn drop(&mut self) {
+            unsafe {
+                let center: *mut AnyObject = msg_send![class!(NSNotificationCenter), defaultCenter];
+                let _: () = msg_send![center, removeObserver: &*self.observer];
+            }
+        }
+    }
+
+    #[cfg(target_os = "ios")]
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVCaptureSessionInterruptionReasonKey: &'static NSString;
+    }
+
+    /// The `AVError` code of a runtime error after which the session has to be restarted.
+    const AV_ERROR_MEDIA_SERVICES_WERE_RESET: NSInteger = -11819;
+
+    type SessionEventCallback = Arc<dyn Fn(SessionEvent) + Send + Sync>;
+
+    /// The controls of a device as last read, or `None` if they have to be read again.
+    type ControlCache = Arc<Mutex<Option<Vec<CameraControl>>>>;
+
+    pub struct SessionObserverIvars {
+        unique_id: String,
+        device: Retained<AVCaptureDevice>,
+        session: Retained<AVCaptureSession>,
+        on_event: SessionEventCallback,
+        // the cached controls of the device, dropped when they are changed elsewhere
+        controls: ControlCache,
+        throttle: AtomicBool,
+        // the frame durations from before the frame rate was lowered
+        throttled_from: Mutex<Option<(CMTime, CMTime)>>,
+        // the thread changing controls through `without_control_events()`, whose changes are not reported
+        changing_thread: Mutex<Option<ThreadId>>,
+    }
+
+    /// The key path of `AVCaptureDevice` that is observed for system pressure changes.
+    const SYSTEM_PRESSURE_KEY_PATH: &str = "systemPressureState";
+
+    /// The key paths of `AVCaptureDevice` that are observed for [`SessionEvent::ControlChanged`], with the control each
+    /// is reported as. Only those the device responds to are observed.
+    const CONTROL_KEY_PATHS: [(&str, KnownCameraControl); 7] = [
+        ("videoZoomFactor", KnownCameraControl::Zoom),
+        ("focusMode", KnownCameraControl::Focus),
+        ("lensPosition", KnownCameraControl::Focus),
+        ("exposureMode", KnownCameraControl::Exposure),
+        ("whiteBalanceMode", KnownCameraControl::WhiteBalance),
+        ("torchMode", KnownCameraControl::Torch),
+        ("lowLightBoostEnabled", KnownCameraControl::BacklightComp),
+    ];
+
+    /// A session handed to the thread that restarts it.
+    struct SendSession(Retained<AVCaptureSession>);
+
+    // SAFETY: `AVCaptureSession` can be started from any thread, Apple recommends doing so off the main thread.
+    unsafe impl Send for SendSession {}
+
+    declare_class!(
+        pub struct SessionObserver;
+
+        unsafe impl ClassType for SessionObserver {
+            type Super = NSObject;
+            type Mutability = mutability::InteriorMutable;
+            const NAME: &'static str = "NokhwaSessionObserver";
+        }
+
+        impl DeclaredClass for SessionObserver {
+            type Ivars = SessionObserverIvars;
+        }
+
+        unsafe impl NSObjectProtocol for SessionObserver {}
+
+        unsafe impl SessionObserver {
+            #[method(deviceConnected:)]
+            fn device_connected(&self, notification: &AnyObject) {
+                if self.is_own_device(notification) {
+                    (self.ivars().on_event)(SessionEvent::Reconnected);
+                }
+            }
+
+            #[method(deviceDisconnected:)]
+            fn device_disconnected(&self, notification: &AnyObject) {
+                if self.is_own_device(notification) {
+                    (self.ivars().on_event)(SessionEvent::Disconnected);
+                }
+            }
+
+            #[method(sessionRuntimeError:)]
+            fn session_runtime_error(&self, notification: &AnyObject) {
+                let error = unsafe {
+                    let user_info: *mut AnyObject = msg_send![notification, userInfo];
+                    if user_info.is_null() {
+                        None
+                    } else {
+                        let error: *mut AnyObject =
+                            msg_send![user_info, objectForKey: AVCaptureSessionErrorKey];
+                        error.as_ref()
+                    }
+                };
+                let (why, code) = match error {
+                    Some(error) => {
+                        let code: NSInteger = unsafe { msg_send![error, code] };
+                        (format!("{} ({code})", ns_error_description(error)), code)
+                    }
+                    None => ("Unknown error".to_string(), 0),
+                };
+                (self.ivars().on_event)(SessionEvent::RuntimeError(why));
+                if code == AV_ERROR_MEDIA_SERVICES_WERE_RESET {
+                    self.restart();
+                }
+            }
+
+            #[method(sessionWasInterrupted:)]
+            fn session_was_interrupted(&self, notification: &AnyObject) {
+                (self.ivars().on_event)(SessionEvent::Interrupted(interruption_reason(notification)));
+            }
+
+            #[method(sessionInterruptionEnded:)]
+            fn session_interruption_ended(&self, _notification: &AnyObject) {
+                // the session resumes on its own once the interruption ends
+                (self.ivars().on_event)(SessionEvent::Resumed);
+            }
+
+            #[method(observeValueForKeyPath:ofObject:change:context:)]
+            fn observe_value(
+                &self,
+                key_path: Option<&AnyObject>,
+                _object: Option<&AnyObject>,
+                _change: Option<&AnyObject>,
+                _context: *mut c_void,
+            ) {
+                let key_path = key_path.map(|path| {
+                    let path: &NSString = unsafe { &*std::ptr::from_ref(path).cast() };
+                    path.to_string()
+                });
+                if let Some((_, control)) = CONTROL_KEY_PATHS
+                    .iter()
+                    .find(|(path, _)| key_path.as_deref() == Some(*path))
+                {
+                    if !self.is_changing_thread() {
+                        *self
+                            .ivars()
+                            .controls
+                            .lock()
+                            .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+                        (self.ivars().on_event)(SessionEvent::ControlChanged(*control));
+                    }
+                    return;
+                }
+                if let Some(level) = self.system_pressure_level() {
+                    self.apply_system_pressure(level);
+                    (self.ivars().on_event)(SessionEvent::SystemPressure(level));
+                }
+            }
+        }
+    );
+
+    #[cfg(target_os = "ios")]
+    fn interruption_reason(notification: &AnyObject) -> InterruptionReason {
+        let reason: NSInteger = unsafe {
+            let user_info: *mut AnyObject = msg_send![notification, userInfo];
+            if user_info.is_null() {
+                return InterruptionReason::Unknown;
+            }
+            let reason: *mut AnyObject =
+                msg_send![user_info, objectForKey: AVCaptureSessionInterruptionReasonKey];
+            if reason.is_null() {
+                return InterruptionReason::Unknown;
+            }
+            msg_send![reason, integerValue]
+        };
+        match reason {
+            1 => InterruptionReason::Backgrounded,
+            2 => InterruptionReason::AudioDeviceInUse,
+            3 => InterruptionReason::VideoDeviceInUse,
+            4 => InterruptionReason::MultipleForegroundApps,
+            5 => InterruptionReason::SystemPressure,
+            _ => InterruptionReason::Unknown,
+        }
+    }
+
+    /// macOS does not say why a session was interrupted.
+    #[cfg(target_os = "macos")]
+    fn interruption_reason(_notification: &AnyObject) -> InterruptionReason {
+        InterruptionReason::Unknown
+    }
+
+    impl SessionObserver {
+        fn new(
+            unique_id: String,
+            device: Retained<AVCaptureDevice>,
+            session: Retained<AVCaptureSession>,
+            on_event: SessionEventCallback,
+            controls: ControlCache,
+        ) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(SessionObserverIvars {
+                unique_id,
+                device,
+                session,
+                on_event,
+                controls,
+                throttle: AtomicBool::new(false),
+                throttled_from: Mutex::new(None),
+                changing_thread: Mutex::new(None),
+            });
+            unsafe { msg_send_id![super(this), init] }
+        }
+
+        /// Reads the `systemPressureState` of the device. Only iOS reports it.
+        fn system_pressure_level(&self) -> Option<SystemPressureLevel> {
+            let level: Option<Retained<NSString>> = unsafe {
+                let state: *mut AnyObject = msg_send![&*self.ivars().device, systemPressureState];
+                if state.is_null() {
+                    return None;
+                }
+                msg_send_id![state, level]
+            };
+            // the values of the `AVCaptureSystemPressureLevel` constants, which are not available on macOS to link against
+            match level?.to_string().as_str() {
+                "nominal" => Some(SystemPressureLevel::Nominal),
+                "fair" => Some(SystemPressureLevel::Fair),
+                "serious" => Some(SystemPressureLevel::Serious),
+                "critical" => Some(SystemPressureLevel::Critical),
+                "shutdown" => Some(SystemPressureLevel::Shutdown),
+                _ => None,
+            }
+        }
+
+        /// Halves the frame rate (down to the lowest the active format supports) once the pressure is critical, if
+        /// throttling is on, and restores it once the pressure is back to nominal or fair.
+        fn apply_system_pressure(&self, level: SystemPressureLevel) {
+            let device = &*self.ivars().device;
+            let mut throttled_from = self
+                .ivars()
+                .throttled_from
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let throttle = self.ivars().throttle.load(Ordering::Relaxed);
+
+            let durations = match *throttled_from {
+                None if throttle && level >= SystemPressureLevel::Critical => {
+                    let (min, max): (CMTime, CMTime) = unsafe {
+                        (
+                            msg_send![device, activeVideoMinFrameDuration],
+                            msg_send![device, activeVideoMaxFrameDuration],
+                        )
+                    };
+                    if min.value <= 0 || min.timescale <= 0 {
+                        return;
+                    }
+                    let fps = f64::from(min.timescale) / min.value as f64;
+                    let lowest = device
+                        .get_active_format()
+                        .video_supported_frame_rate_ranges()
+                        .into_iter()
+                        .map(|range| range.min_frame_rate())
+                        .fold(fps, f64::min);
+                    let target = (fps / 2.0).max(lowest);
+                    if target >= fps {
+                        return;
+                    }
+                    *throttled_from = Some((min, max));
+                    (frame_duration(target), frame_duration(target))
+                }
+                Some(durations) if !throttle || level <= SystemPressureLevel::Fair => {
+                    *throttled_from = None;
+                    durations
+                }
+                _ => return,
+            };
+            unsafe {
+                let locked: bool = msg_send![
+                    device,
+                    lockForConfiguration: std::ptr::null_mut::<*mut AnyObject>()
+                ];
+                if locked {
+                    let _: () = msg_send![device, setActiveVideoMinFrameDuration: durations.0];
+                    let _: () = msg_send![device, setActiveVideoMaxFrameDuration: durations.1];
+                    let _: () = msg_send![device, unlockForConfiguration];
+                }
+            }
+        }
+
+        /// Starts the session again on a thread of its own, as starting blocks and notifications arrive on the main
+        /// thread.
+        fn restart(&self) {
+            let session = SendSession(self.ivars().session.clone());
+            let on_event = self.ivars().on_event.clone();
+            std::thread::spawn(move || {
+                let session = session;
+                session.0.start_running();
+                if session.0.is_running() {
+                    on_event(SessionEvent::Resumed);
+                }
+            });
+        }
+
+        /// Checks if the current thread is changing controls through
+        /// [`without_control_events()`](SessionNotifier::without_control_events).
+        fn is_changing_thread(&self) -> bool {
+            *self
+                .ivars()
+                .changing_thread
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                == Some(std::thread::current().id())
+        }
+
+        /// Checks if the device of a connection notification is the observed one. A device that is plugged back in is a
+        /// new `AVCaptureDevice`, so this compares unique IDs rather than objects.
+        fn is_own_device(&self, notification: &AnyObject) -> bool {
+            let unique_id: Option<Retained<NSString>> = unsafe {
+                let device: *mut AnyObject = msg_send![notification, object];
+                if device.is_null() {
+                    return false;
+                }
+                msg_send_id![device, uniqueID]
+            };
+            unique_id.is_some_and(|unique_id| unique_id.to_string() == self.ivars().unique_id)
+        }
+    }
+
+    /// Observes `AVCaptureDeviceWasConnectedNotification` and `AVCaptureDeviceWasDisconnectedNotification` for one device,
+    /// and the runtime error and interruption notifications of one session, calling `on_event` with a [`SessionEvent`] for
+    /// each. The observer is removed when this is dropped.
+    ///
+    /// The session resumes by itself after an interruption (e.g. a phone call or going to the background on iOS). After
+    /// the media services of the system were reset, it is restarted, which is reported as [`SessionEvent::Resumed`].
+    ///
+    /// On iOS, changes of the thermal and power pressure on the device are reported as well, see
+    /// [`set_throttle_on_pressure()`](SessionNotifier::set_throttle_on_pressure).
+    ///
+    /// Controls of the device changing, e.g. the zoom, focus or exposure mode, are reported as
+    /// [`SessionEvent::ControlChanged`] through key-value observing, on the thread that changed them.
+    ///
+    /// `AVFoundation` posts the device and interruption notifications on the main thread, so the main run loop must be
+    /// running for them to arrive. Runtime errors are posted on the thread of the session.
+    pub struct SessionNotifier {
+        observer: Retained<SessionObserver>,
+        observes_pressure: bool,
+        observed_controls: Vec<&'static str>,
+    }
+
+    // SAFETY: the observer only holds a `Send` callback besides the device and session, which can be configured from any
+    // thread, and `NSNotificationCenter` and key-value observing are thread safe.
+    unsafe impl Send for SessionNotifier {}
+
+    impl SessionNotifier {
+        pub fn new(
+            device: &AVCaptureDeviceWrapper,
+            session: &AVCaptureSession,
+            on_event: impl Fn(SessionEvent) + Send + Sync + 'static,
+        ) -> Self {
+            let observer = SessionObserver::new(
+                device.info().unique_id(),
+                device.raw_device().retain(),
+                session.retain(),
+                Arc::new(on_event),
+                device.controls.clone(),
+            );
+            let observes_pressure = responds_to(device.raw_device(), sel!(systemPressureState));
+            let observed_controls = CONTROL_KEY_PATHS
+                .iter()
+                .map(|(path, _)| *path)
+                .filter(|path| responds_to(device.raw_device(), Sel::register(path)))
+                .collect::<Vec<_>>();
+            unsafe {
+                let center: *mut AnyObject = msg_send![class!(NSNotificationCenter), defaultCenter];
+                for (selector, name) in [
+                    (
+                        sel!(deviceConnected:),
+                        AVCaptureDeviceWasConnectedNotification,
+                    ),
+                    (
+                        sel!(deviceDisconnected:),
+                        AVCaptureDeviceWasDisconnectedNotification,
+                    ),
+                ] {
+                    let _: () = msg_send![
+                        center,
+                        addObserver: &*observer,
+                        selector: selector,
+                        name: name,
+                        object: std::ptr::null::<AnyObject>()
+                    ];
+                }
+                for (selector, name) in [
+                    (
+                        sel!(sessionRuntimeError:),
+                        AVCaptureSessionRuntimeErrorNotification,
+                    ),
+                    (
+                        sel!(sessionWasInterrupted:),
+                        AVCaptureSessionWasInterruptedNotification,
+                    ),
+                    (
+                        sel!(sessionInterruptionEnded:),
+                        AVCaptureSessionInterruptionEndedNotification,
+                    ),
+                ] {
+                    let _: () = msg_send![
+                        center,
+                        addObserver: &*observer,
+                        selector: selector,
+                        name: name,
+                        object: session
+                    ];
+                }
+                if observes_pressure {
+                    // NSKeyValueObservingOptionNew | NSKeyValueObservingOptionInitial, to report the current level
+                    let options: usize = 0x01 | 0x04;
+                    let _: () = msg_send![
+                        device.raw_device(),
+                        addObserver: &*observer,
+                        forKeyPath: &*NSString::from_str(SYSTEM_PRESSURE_KEY_PATH),
+                        options: options,
+                        context: std::ptr::null_mut::<c_void>()
+                    ];
+                }
+                for path in &observed_controls {
+                    // NSKeyValueObservingOptionNew
+                    let options: usize = 0x01;
+                    let _: () = msg_send![
+                        device.raw_device(),
+                        addObserver: &*observer,
+                        forKeyPath: &*NSString::from_str(path),
+                        options: options,
+                        context: std::ptr::null_mut::<c_void>()
+                    ];
+                }
+            }
+            SessionNotifier {
+                observer,
+                observes_pressure,
+                observed_controls,
+            }
+        }
+
+        /// Runs `change` without reporting the controls it changes as [`SessionEvent::ControlChanged`], so that only
+        /// changes made elsewhere are. Changes made by other threads meanwhile are still reported.
+        pub fn without_control_events<T>(&self, change: impl FnOnce() -> T) -> T {
+            let changing_thread = &self.observer.ivars().changing_thread;
+            let set = |thread: Option<ThreadId>| {
+                *changing_thread
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner) = thread;
+            };
+            set(Some(std::thread::current().id()));
+            let result = change();
+            set(None);
+            result
+        }
+
+        /// Makes the frame rate drop to half (or the lowest the format supports) while the system pressure is
+        /// [`Critical`](SystemPressureLevel::Critical) or worse, and go back once it is down to
+        /// [`Fair`](SystemPressureLevel::Fair), so that the system does not have to stop the session. This is off by
+        /// default. Turning it off restores a lowered frame rate right away.
+        pub fn set_throttle_on_pressure(&self, throttle: bool) {
+            self.observer
+                .ivars()
+                .throttle
+                .store(throttle, Ordering::Relaxed);
+            if let Some(level) = self.observer.system_pressure_level() {
+                self.observer.apply_system_pressure(level);
+            }
+        }
+    }
+
+    impl Drop for SessionNotifier {
+        fn drop(&mut self) {
+            unsafe {
+                let center: *mut AnyObject = msg_send![class!(NSNotificationCenter), defaultCenter];
+                let _: () = msg_send![center, removeObserver: &*self.observer];
+                if self.observes_pressure {
+                    let _: () = msg_send![
+                        &*self.observer.ivars().device,
+                        removeObserver: &*self.observer,
+                        forKeyPath: &*NSString::from_str(SYSTEM_PRESSURE_KEY_PATH)
+                    ];
+                }
+                for path in &self.observed_controls {
+                    let _: () = msg_send![
+                        &*self.observer.ivars().device,
+                        removeObserver: &*self.observer,
+                        forKeyPath: &*NSString::from_str(path)
+                    ];
+                }
+            }
+        }
+    }
+
+    /// Enables or disables the video connection of `output`. A disabled connection stops delivering sample buffers
+    /// to the delegate, while the session keeps running.
+    pub fn set_output_enabled(output: &AVCaptureVideoDataOutput, enabled: bool) {
+        unsafe {
+            let connection: *mut AnyObject =
+                msg_send![output, connectionWithMediaType: AVMediaTypeVideo];
+            if !connection.is_null() {
+                let _: () = msg_send![connection, setEnabled: enabled];
+            }
+        }
+    }
+
+    /// Detaches the sample buffer delegate of `output`, so that it is released and no more frames are sent to it.
+    pub fn clear_sample_buffer_delegate(output: &AVCaptureVideoDataOutput) {
+        unsafe {
+            let _: () = msg_send![
+                output,
+                setSampleBufferDelegate: std::ptr::null::<AnyObject>(),
+                queue: std::ptr::null::<AnyObject>()
+            ];
+        }
+    }
+
+    /// Mirrors the video connection of `output` horizontally, turning off automatic mirroring.
+    /// Returns `false` if `output` has no video connection yet, or the connection does not support mirroring.
+    pub fn set_output_mirrored(output: &AVCaptureVideoDataOutput, mirrored: bool) -> bool {
+        unsafe {
+            let connection: *mut AnyObject =
+                msg_send![output, connectionWithMediaType: AVMediaTypeVideo];
+            if connection.is_null() {
+                return false;
+            }
+            let supported: bool = msg_send![connection, isVideoMirroringSupported];
+            if !supported {
+                return false;
+            }
+            let _: () = msg_send![connection, setAutomaticallyAdjustsVideoMirroring: false];
+            let _: () = msg_send![connection, setVideoMirrored: mirrored];
+        }
+        true
+    }
+
+    /// Get the pixel formats `output` can deliver sample buffers in, the most efficient one first.
+    pub fn output_pixel_formats(output: &AVCaptureVideoDataOutput) -> Vec<FourCC> {
+        unsafe {
+            let types: *mut AnyObject = msg_send![output, availableVideoCVPixelFormatTypes];
+            if types.is_null() {
+                return Vec::new();
+            }
+            let count: usize = msg_send![types, count];
+            (0..count)
+                .map(|index| {
+                    let number: *mut AnyObject = msg_send![types, objectAtIndex: index];
+                    let raw: u32 = msg_send![number, unsignedIntValue];
+                    raw_fcc_to_fourcc(raw)
+                })
+                .collect()
+        }
+    }
+
+    /// Makes `output` deliver sample buffers in `pixel_format` (e.g. `BGRA`), so that they do not have to be converted on
+    /// the CPU, or in the format of the device again with `None`. [`NV12`] is taken to mean `420v`, which is what CoreVideo
+    /// calls it.
+    ///
+    /// Returns `false` if `output` cannot deliver `pixel_format`, see [`output_pixel_formats()`].
+    pub fn set_output_pixel_format(
+        output: &AVCaptureVideoDataOutput,
+        pixel_format: Option<FourCC>,
+    ) -> bool {
+        let Some(pixel_format) = pixel_format else {
+            unsafe {
+                let _: () = msg_send![output, setVideoSettings: std::ptr::null::<AnyObject>()];
+            }
+            return true;
+        };
+        let pixel_format = if pixel_format == NV12 {
+            YUV420
+        } else {
+            pixel_format
+        };
+        if !output_pixel_formats(output).contains(&pixel_format) {
+            return false;
+        }
+
+        let settings = unsafe {
+            CFDictionary::from_CFType_pairs(&[(
+                CFString::wrap_under_get_rule(kCVPixelBufferPixelFormatTypeKey).as_CFType(),
+                CFNumber::from(i64::from(u32::from(pixel_format))).as_CFType(),
+            )])
+        };
+        unsafe {
+            let _: () = msg_send![
+                output,
+                setVideoSettings: settings.as_concrete_TypeRef().cast::<AnyObject>()
+            ];
+        }
+        true
+    }
+
+    /// A `CVPixelBuffer` a frame was delivered in, kept locked for reading so that its data can be read without copying.
+    ///
+    /// Most capture formats are backed by an `IOSurface`, which Metal and Core Image can use directly, see
+    /// [`io_surface()`](PixelBuffer::io_surface). Get it from a frame with [`FrameBuffer::external_buffer()`] and
+    /// [`ExternalBuffer::as_any()`].
+    pub struct PixelBuffer {
+        buffer: CVPixelBuffer,
+        data: *const u8,
+        len: usize,
+    }
+
+    // SAFETY: a `CVPixelBuffer` can be retained and released from any thread, and this one is only ever read.
+    unsafe impl Send for PixelBuffer {}
+    unsafe impl Sync for PixelBuffer {}
+
+    impl PixelBuffer {
+        fn new(buffer: CVPixelBuffer) -> Self {
+            let (data, len) = unsafe {
+                let raw = buffer.as_concrete_TypeRef().cast::<c_void>();
+                if CVPixelBufferLockBaseAddress(raw, CV_PIXEL_BUFFER_LOCK_READ_ONLY) == 0 {
+                    (
+                        buffer.get_base_address().cast::<u8>().cast_const(),
+                        buffer.get_data_size(),
+                    )
+                } else {
+                    (std::ptr::null(), 0)
+                }
+            };
+            PixelBuffer { buffer, data, len }
+        }
+
+        /// Get the `CVPixelBuffer`.
+        pub fn pixel_buffer(&self) -> &CVPixelBuffer {
+            &self.buffer
+        }
+
+        /// Get the `IOSurfaceRef` backing the pixel buffer, or null if it is not backed by one.
+        pub fn io_surface(&self) -> *mut c_void {
+            unsafe { CVPixelBufferGetIOSurface(self.raw()) }
+        }
+
+        fn raw(&self) -> *mut c_void {
+            self.buffer.as_concrete_TypeRef().cast()
+        }
+    }
+
+    impl ExternalBuffer for PixelBuffer {
+        fn data(&self) -> &[u8] {
+            if self.data.is_null() {
+                return &[];
+            }
+            unsafe { std::slice::from_raw_parts(self.data, self.len) }
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    impl Drop for PixelBuffer {
+        fn drop(&mut self) {
+            if !self.data.is_null() {
+                unsafe {
+                    CVPixelBufferUnlockBaseAddress(self.raw(), CV_PIXEL_BUFFER_LOCK_READ_ONLY);
+                }
+            }
+        }
+    }
+
+    const CV_PIXEL_BUFFER_LOCK_READ_ONLY: u64 = 1;
+
+    #[cfg(feature = "metal")]
+    type CVMetalTextureCacheRef = *mut c_void;
+    #[cfg(feature = "metal")]
+    type CVMetalTextureRef = *mut c_void;
+
+    #[cfg(feature = "metal")]
+    #[link(name = "CoreVideo", kind = "framework")]
+    extern "C" {
+        fn CVMetalTextureCacheCreate(
+            allocator: *const c_void,
+            cache_attributes: CFDictionaryRef,
+            metal_device: *mut c_void,
+            texture_attributes: CFDictionaryRef,
+            cache_out: *mut CVMetalTextureCacheRef,
+        ) -> i32;
+        fn CVMetalTextureCacheCreateTextureFromImage(
+            allocator: *const c_void,
+            texture_cache: CVMetalTextureCacheRef,
+            source_image: *mut c_void,
+            texture_attributes: CFDictionaryRef,
+            pixel_format: u64,
+            width: usize,
+            height: usize,
+            plane_index: usize,
+            texture_out: *mut CVMetalTextureRef,
+        ) -> i32;
+        fn CVMetalTextureGetTexture(image: CVMetalTextureRef) -> *mut c_void;
+        fn CVMetalTextureCacheFlush(texture_cache: CVMetalTextureCacheRef, options: u64);
+        fn CVPixelBufferGetWidthOfPlane(pixel_buffer: *mut c_void, plane: usize) -> usize;
+        fn CVPixelBufferGetHeightOfPlane(pixel_buffer: *mut c_void, plane: usize) -> usize;
+        fn CVPixelBufferGetPlaneCount(pixel_buffer: *mut c_void) -> usize;
+    }
+
+    #[cfg(feature = "metal")]
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// Turns the [`PixelBuffer`]s of frames into Metal textures through a `CVMetalTextureCache`, sharing their
+    /// `IOSurface` with the GPU so that no pixel is copied on the CPU.
+    ///
+    /// Frames have to be delivered without copying for this, see [`AVCaptureDelegate::set_zero_copy()`].
+    #[cfg(feature = "metal")]
+    pub struct MetalTextureCache {
+        cache: CVMetalTextureCacheRef,
+    }
+
+    // SAFETY: `CVMetalTextureCache` is thread safe.
+    #[cfg(feature = "metal")]
+    unsafe impl Send for MetalTextureCache {}
+    #[cfg(feature = "metal")]
+    unsafe impl Sync for MetalTextureCache {}
+
+    #[cfg(feature = "metal")]
+    impl MetalTextureCache {
+        /// Creates a texture cache for `device`.
+        /// # Errors
+        /// If Core Video cannot create the cache, this will error.
+        pub fn new(device: &metal::DeviceRef) -> Result<Self, NokhwaError> {
+            use metal::foreign_types::ForeignTypeRef;
+
+            let mut cache = std::ptr::null_mut();
+            let status = unsafe {
+                CVMetalTextureCacheCreate(
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    device.as_ptr().cast(),
+                    std::ptr::null(),
+                    &mut cache,
+                )
+            };
+            if status != 0 || cache.is_null() {
+                return Err(NokhwaError::InitializeError {
+                    backend: ApiBackend::AVFoundation,
+                    error: format!("Failed to create the Metal texture cache: CVReturn {status}"),
+                });
+            }
+            Ok(MetalTextureCache { cache })
+        }
+
+        /// Gets one texture per plane of the pixel buffer `frame` was delivered in: one `BGRA8Unorm` texture for BGRA
+        /// frames, or an `R8Unorm` luma and an `RG8Unorm` chroma texture for NV12 frames.
+        /// # Errors
+        /// If the frame was copied rather than delivered in its pixel buffer, is in another format, or Core Video cannot
+        /// create the textures, this will error.
+        pub fn textures(&self, frame: &FrameBuffer) -> Result<Vec<MetalTexture>, NokhwaError> {
+            let error = |why: String| NokhwaError::ProcessFrameError {
+                src: frame.source_frame_format(),
+                destination: "Metal texture".to_string(),
+                error: why,
+            };
+            let pixel_buffer = frame
+                .external_buffer()
+                .and_then(|buffer| buffer.as_any().downcast_ref::<PixelBuffer>())
+                .ok_or_else(|| {
+                    error("The frame was copied, turn on zero copy delivery".to_string())
+                })?;
+            let formats: &[metal::MTLPixelFormat] = match frame.source_frame_format() {
+                nokhwa_core::pixel_format::BGRA => &[metal::MTLPixelFormat::BGRA8Unorm],
+                NV12 | YUV420 => &[
+                    metal::MTLPixelFormat::R8Unorm,
+                    metal::MTLPixelFormat::RG8Unorm,
+                ],
+                other => return Err(error(format!("{other} has no matching Metal pixel format"))),
+            };
+
+            let raw = pixel_buffer.raw();
+            let planar = unsafe { CVPixelBufferGetPlaneCount(raw) } > 0;
+            formats
+                .iter()
+                .enumerate()
+                .map(|(plane, format)| {
+                    let (width, height) = if planar {
+                        unsafe {
+                            (
+                                CVPixelBufferGetWidthOfPlane(raw, plane),
+                                CVPixelBufferGetHeightOfPlane(raw, plane),
+                            )
+                        }
+                    } else {
+                        (
+                            pixel_buffer.pixel_buffer().get_width(),
+                            pixel_buffer.pixel_buffer().get_height(),
+                        )
+                    };
+                    let mut texture = std::ptr::null_mut();
+                    let status = unsafe {
+                        CVMetalTextureCacheCreateTextureFromImage(
+                            std::ptr::null(),
+                            self.cache,
+                            raw,
+                            std::ptr::null(),
+                            *format as u64,
+                            width,
+                            height,
+                            plane,
+                            &mut texture,
+                        )
+                    };
+                    if status != 0 || texture.is_null() {
+                        return Err(error(format!(
+                            "Failed to create the texture of plane {plane}: CVReturn {status}"
+                        )));
+                    }
+                    Ok(MetalTexture { texture })
+                })
+                .collect()
+        }
+
+        /// Releases the textures the cache keeps around that are no longer in use. Call this now and then, e.g. once per
+        /// rendered frame.
+        pub fn flush(&self) {
+            unsafe { CVMetalTextureCacheFlush(self.cache, 0) };
+        }
+    }
+
+    #[cfg(feature = "metal")]
+    impl Drop for MetalTextureCache {
+        fn drop(&mut self) {
+            unsafe { CFRelease(self.cache.cast_const()) };
+        }
+    }
+
+    /// A Metal texture sharing the memory of a frame, made by [`MetalTextureCache::textures()`]. It must be kept alive
+    /// until the GPU is done with the texture.
+    #[cfg(feature = "metal")]
+    pub struct MetalTexture {
+        texture: CVMetalTextureRef,
+    }
+
+    // SAFETY: `CVMetalTexture` can be used and released from any thread.
+    #[cfg(feature = "metal")]
+    unsafe impl Send for MetalTexture {}
+    #[cfg(feature = "metal")]
+    unsafe impl Sync for MetalTexture {}
+
+    #[cfg(feature = "metal")]
+    impl MetalTexture {
+        /// Get the Metal texture.
+        pub fn texture(&self) -> &metal::TextureRef {
+            use metal::foreign_types::ForeignTypeRef;
+
+            unsafe { metal::TextureRef::from_ptr(CVMetalTextureGetTexture(self.texture).cast()) }
+        }
+    }
+
+    #[cfg(feature = "metal")]
+    impl Drop for MetalTexture {
+        fn drop(&mut self) {
+            unsafe { CFRelease(self.texture.cast_const()) };
+        }
+    }
+
+    pub struct PhotoCaptureDelegateIvars {
+        sender: Sender<Result<Vec<u8>, String>>,
+    }
+
+    declare_class!(
+        pub struct PhotoCaptureDelegate;
+
+        unsafe impl ClassType for PhotoCaptureDelegate {
+            type Super = NSObject;
+            type Mutability = mutability::InteriorMutable;
+            const NAME: &'static str = "NokhwaPhotoCaptureDelegate";
+        }
+
+        impl DeclaredClass for PhotoCaptureDelegate {
+            type Ivars = PhotoCaptureDelegateIvars;
+        }
+
+        unsafe impl NSObjectProtocol for PhotoCaptureDelegate {}
+
+        unsafe impl PhotoCaptureDelegate {
+            #[method(captureOutput:didFinishProcessingPhoto:error:)]
+            fn did_finish_processing_photo(
+                &self,
+                _output: &AnyObject,
+                photo: &AnyObject,
+                error: Option<&AnyObject>,
+            ) {
+                let result = match error {
+                    Some(error) => Err(ns_error_description(error)),
+                    None => unsafe { photo_file_data(photo) },
+                };
+                let _ = self.ivars().sender.try_send(result);
+            }
+        }
+    );
+
+    impl PhotoCaptureDelegate {
+        fn new(sender: Sender<Result<Vec<u8>, String>>) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(PhotoCaptureDelegateIvars { sender });
+            unsafe { msg_send_id![super(this), init] }
+        }
+    }
+
+    fn ns_error_description(error: &AnyObject) -> String {
+        let description: Option<Retained<NSString>> =
+            unsafe { msg_send_id![error, localizedDescription] };
+        description.map_or_else(
+            || "Unknown error".to_string(),
+            |description| description.to_string(),
+        )
+    }
+
+    /// Copies the encoded file of an `AVCapturePhoto`.
+    unsafe fn photo_file_data(photo: &AnyObject) -> Result<Vec<u8>, String> {
+        let data: *mut AnyObject = msg_send![photo, fileDataRepresentation];
+        if data.is_null() {
+            return Err("The photo could not be encoded".to_string());
+        }
+        let bytes: *const c_void = msg_send![data, bytes];
+        let length: usize = msg_send![data, length];
+        if bytes.is_null() || length == 0 {
+            return Err("The photo is empty".to_string());
+        }
+        Ok(std::slice::from_raw_parts(bytes.cast::<u8>(), length).to_vec())
+    }
+
+    /// Reads an `NSArray` of `NSNumber`s as integers.
+    unsafe fn ns_number_array(array: *mut AnyObject) -> Vec<NSInteger> {
+        if array.is_null() {
+            return Vec::new();
+        }
+        let count: usize = msg_send![array, count];
+        (0..count)
+            .map(|index| {
+                let number: *mut AnyObject = msg_send![array, objectAtIndex: index];
+                msg_send![number, integerValue]
+            })
+            .collect()
+    }
+
+    /// An `AVCapturePhotoOutput`, which takes still photos at the full resolution of the device while the video output of
+    /// the same session keeps streaming.
+    pub struct PhotoOutput {
+        output: Retained<AnyObject>,
+    }
+
+    impl PhotoOutput {
+        /// Waits this long for a photo, which includes focusing, exposing and firing the flash.
+        const TIMEOUT: Duration = Duration::from_secs(10);
+
+        fn new() -> Self {
+            PhotoOutput {
+                output: unsafe { msg_send_id![class!(AVCapturePhotoOutput), new] },
+            }
+        }
+
+        /// Takes a photo with `settings`, blocking until it is encoded.
+        /// # Errors
+        /// If the output does not support the codec or flash mode of `settings`, or the photo could not be taken, this
+        /// will error.
+        pub fn capture(&self, settings: PhotoSettings) -> Result<Vec<u8>, NokhwaError> {
+            let error = |error: String| {
+                NokhwaError::ReadFrameError(format!("Cannot take a photo: {error}"))
+            };
+            let output = &*self.output;
+
+            let codec = unsafe {
+                match settings.codec() {
+                    PhotoCodec::Jpeg => AVVideoCodecTypeJPEG,
+                    PhotoCodec::Heif => AVVideoCodecTypeHEVC,
+                }
+            };
+            let available: *mut AnyObject = unsafe { msg_send![output, availablePhotoCodecTypes] };
+            let supported: bool =
+                !available.is_null() && unsafe { msg_send![available, containsObject: codec] };
+            if !supported {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "PhotoCodec".to_string(),
+                    value: settings.codec().to_string(),
+                    error: "Not supported by the device".to_string(),
+                }
+                .with_kind(ErrorKind::FormatUnsupported));
+            }
+
+            let ns_key = |key: &NSString| unsafe {
+                CFString::wrap_under_get_rule((key as *const NSString).cast())
+            };
+            let mut format = vec![(
+                unsafe { ns_key(AVVideoCodecKey) }.as_CFType(),
+                ns_key(codec).as_CFType(),
+            )];
+            if let Some(quality) = settings.quality() {
+                let compression = CFDictionary::from_CFType_pairs(&[(
+                    unsafe { ns_key(AVVideoQualityKey) }.as_CFType(),
+                    CFNumber::from(f64::from(quality) / 100.0).as_CFType(),
+                )]);
+                format.push((
+                    unsafe { ns_key(AVVideoCompressionPropertiesKey) }.as_CFType(),
+                    compression.as_CFType(),
+                ));
+            }
+            let format = CFDictionary::from_CFType_pairs(&format);
+            let photo_settings: Option<Retained<AnyObject>> = unsafe {
+                msg_send_id![
+                    class!(AVCapturePhotoSettings),
+                    photoSettingsWithFormat: format.as_concrete_TypeRef().cast::<AnyObject>()
+                ]
+            };
+            let photo_settings = photo_settings
+                .ok_or_else(|| error("The photo settings were rejected".to_string()))?;
+
+            let flash_mode: NSInteger = match settings.flash() {
+                FlashMode::Off => 0,
+                FlashMode::On => 1,
+                FlashMode::Auto => 2,
+            };
+            let flash_modes = unsafe { ns_number_array(msg_send![output, supportedFlashModes]) };
+            if !flash_modes.contains(&flash_mode) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "FlashMode".to_string(),
+                    value: settings.flash().to_string(),
+                    error: "Not supported by the device".to_string(),
+                });
+            }
+            unsafe {
+                let _: () = msg_send![&*photo_settings, setFlashMode: flash_mode];
+                let high_resolution: bool = msg_send![output, isHighResolutionCaptureEnabled];
+                let _: () =
+                    msg_send![&*photo_settings, setHighResolutionPhotoEnabled: high_resolution];
+            }
+
+            let (sender, receiver) = flume::bounded(1);
+            let delegate = PhotoCaptureDelegate::new(sender);
+            unsafe {
+                let _: () = msg_send![output, capturePhotoWithSettings: &*photo_settings, delegate: &*delegate];
+            }
+            match receiver.recv_timeout(Self::TIMEOUT) {
+                Ok(Ok(photo)) => Ok(photo),
+                Ok(Err(why)) => Err(error(why)),
+                Err(_) => Err(error("Timed out waiting for the photo".to_string())),
+            }
+        }
+    }
+
+    /// An `AVCaptureSession` that streams one device into an [`AVCaptureDelegate`], which sends its frames to a channel.
+    ///
+    /// Creating it attaches the device input and a video data output, which delivers sample buffers on a serial dispatch
+    /// queue of its own. Nothing is captured until [`start()`](CaptureSession::start) is called. Dropping it stops the
+    /// session and detaches the delegate, output and input again.
+    pub struct CaptureSession {
+        session: Retained<AVCaptureSession>,
+        input: Retained<AVCaptureDeviceInput>,
+        output: Retained<AVCaptureVideoDataOutput>,
+        delegate: Retained<AVCaptureDelegate>,
+        photo_output: Option<PhotoOutput>,
+        audio: Option<AudioStream>,
+        // the output retains the queue as well, this keeps it alive for as long as the delegate is attached
+        _queue: Queue,
+    }
+
+    /// The microphone input and audio data output of a [`CaptureSession`].
+    struct AudioStream {
+        input: Retained<AnyObject>,
+        output: Retained<AnyObject>,
+        _delegate: Retained<AudioCaptureDelegate>,
+        _queue: Queue,
+    }
+
+    impl CaptureSession {
+        /// Creates a session that streams `device` in `format` to `sender`. `queue_name` labels the dispatch queue the frames
+        /// are delivered on.
+        /// # Errors
+        /// If the device cannot be opened (e.g. permission was not given or it is in use), the session does not accept its
+        /// input or the video output, or `format` cannot be set, this will error.
+        pub fn new(
+            device: &mut AVCaptureDeviceWrapper,
+            format: CameraFormat,
+            queue_name: &str,
+            sender: Arc<SenderType>,
+        ) -> Result<Self, NokhwaError> {
+            let input = AVCaptureDeviceInput::from_device(device.raw_device()).map_err(|why| {
+                NokhwaError::OpenDeviceError("Cannot open device".to_string(), why.to_string())
+                    .with_os_error(PlatformError::NSError {
+                        domain: why.domain().to_string(),
+                        code: why.code(),
+                    })
+            })?;
+
+            let session = AVCaptureSession::new();
+            session.begin_configuration();
+            let can_add_input: bool = unsafe { msg_send![&*session, canAddInput: &*input] };
+            if !can_add_input {
+                session.commit_configuration();
+                return Err(NokhwaError::OpenDeviceError(
+                    "Cannot open device".to_string(),
+                    "The capture session does not accept the device input".to_string(),
+                ));
+            }
+            session.add_input(&input);
+            // adding the input resets the active format of the device
+            if let Err(why) = device.set_all(format) {
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(why);
+            }
+
+            let output = AVCaptureVideoDataOutput::new();
+            let mut delegate = AVCaptureDelegate::new();
+            delegate.set_sender(sender);
+            let queue = Queue::new(queue_name, QueueAttribute::Serial);
+            let protocol: &ProtocolObject<dyn AVCaptureVideoDataOutputSampleBufferDelegate> =
+                ProtocolObject::from_ref(&*delegate);
+            output.set_sample_buffer_delegate(protocol, &queue);
+            output.set_always_discards_late_video_frames(true);
+            let can_add_output: bool = unsafe { msg_send![&*session, canAddOutput: &*output] };
+            if !can_add_output {
+                clear_sample_buffer_delegate(&output);
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(NokhwaError::OpenDeviceError(
+                    "Cannot open device".to_string(),
+                    "The capture session does not accept the video data output".to_string(),
+                ));
+            }
+            session.add_output(&output);
+            session.commit_configuration();
+
+            Ok(CaptureSession {
+                session,
+                input,
+                output,
+                delegate,
+                photo_output: None,
+                audio: None,
+                _queue: queue,
+            })
+        }
+
+        /// Starts capturing. This blocks until the session is running, or has failed to start.
+        pub fn start(&self) {
+            self.session.start_running();
+        }
+
+        /// Checks if the session is capturing. On iOS, an interrupted session (e.g. by a phone call) is not capturing.
+        pub fn is_running(&self) -> bool {
+            #[cfg(target_os = "ios")]
+            if self.session.is_interrupted() {
+                return false;
+            }
+            self.session.is_running()
+        }
+
+        /// Enables or disables the video connection, see [`set_output_enabled()`].
+        pub fn set_enabled(&self, enabled: bool) {
+            set_output_enabled(&self.output, enabled);
+        }
+
+        /// Mirrors the video connection, see [`set_output_mirrored()`].
+        pub fn set_mirrored(&self, mirrored: bool) -> bool {
+            set_output_mirrored(&self.output, mirrored)
+        }
+
+        /// Makes the output deliver frames in `pixel_format`, see [`set_output_pixel_format()`].
+        pub fn set_pixel_format(&self, pixel_format: Option<FourCC>) -> bool {
+            set_output_pixel_format(&self.output, pixel_format)
+        }
+
+        /// Get the pixel formats the output can deliver frames in, see [`output_pixel_formats()`].
+        pub fn pixel_formats(&self) -> Vec<FourCC> {
+            output_pixel_formats(&self.output)
+        }
+
+        /// Hands out frames without copying them, see [`AVCaptureDelegate::set_zero_copy()`].
+        pub fn set_zero_copy(&self, zero_copy: bool) {
+            self.delegate.set_zero_copy(zero_copy);
+        }
+
+        /// Takes a still photo at the full resolution of the device, while the video stream continues. The photo output is
+        /// added to the session the first time, which may drop a few video frames.
+        /// # Errors
+        /// If the session does not accept a photo output, or the photo could not be taken, see [`PhotoOutput::capture()`],
+        /// this will error.
+        pub fn capture_photo(&mut self, settings: PhotoSettings) -> Result<Vec<u8>, NokhwaError> {
+            if self.photo_output.is_none() {
+                let photo_output = PhotoOutput::new();
+                let session = &*self.session;
+                unsafe {
+                    let can_add: bool = msg_send![session, canAddOutput: &*photo_output.output];
+                    if !can_add {
+                        return Err(NokhwaError::UnsupportedOperationError(
+                            ApiBackend::AVFoundation,
+                        ));
+                    }
+                    session.begin_configuration();
+                    let _: () = msg_send![session, addOutput: &*photo_output.output];
+                    session.commit_configuration();
+                    // this can only be turned on once the output is part of a session
+                    let _: () =
+                        msg_send![&*photo_output.output, setHighResolutionCaptureEnabled: true];
+                }
+                self.photo_output = Some(photo_output);
+            }
+            match &self.photo_output {
+                Some(photo_output) => photo_output.capture(settings),
+                None => Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::AVFoundation,
+                )),
+            }
+        }
+
+        /// Captures the default microphone in the same session as the video, sending its samples to `sender`. The samples
+        /// are timestamped on the clock of the session, like the video frames. On macOS they are 32 bit floats, on iOS
+        /// whatever the microphone delivers.
+        ///
+        /// If the user has not been asked for microphone access yet, this shows the system prompt and waits for their
+        /// answer. The application needs an `NSMicrophoneUsageDescription` entry in its `Info.plist`.
+        /// # Errors
+        /// If microphone access is denied, there is no microphone, or the session does not accept it, this will error.
+        pub fn add_audio(&mut self, sender: AudioSenderType) -> Result<(), NokhwaError> {
+            self.remove_audio();
+            ensure_authorized(unsafe { AVMediaTypeAudio }, "Microphone")?;
+            let error = |why: String| NokhwaError::OpenDeviceError("Microphone".to_string(), why);
+
+            let device: Option<Retained<AnyObject>> = unsafe {
+                msg_send_id![class!(AVCaptureDevice), defaultDeviceWithMediaType: AVMediaTypeAudio]
+            };
+            let device = device.ok_or_else(|| error("There is no microphone".to_string()))?;
+            let mut ns_error: *mut AnyObject = std::ptr::null_mut();
+            let input: Option<Retained<AnyObject>> = unsafe {
+                msg_send_id![
+                    class!(AVCaptureDeviceInput),
+                    deviceInputWithDevice: &*device,
+                    error: &mut ns_error
+                ]
+            };
+            let input = input.ok_or_else(|| {
+                error(match unsafe { ns_error.as_ref() } {
+                    Some(ns_error) => ns_error_description(ns_error),
+                    None => "Cannot open the microphone".to_string(),
+                })
+            })?;
+
+            let output: Retained<AnyObject> =
+                unsafe { msg_send_id![class!(AVCaptureAudioDataOutput), new] };
+            // only macOS lets the output convert the samples
+            #[cfg(target_os = "macos")]
+            unsafe {
+                let ns_key =
+                    |key: &NSString| CFString::wrap_under_get_rule((key as *const NSString).cast());
+                let settings = CFDictionary::from_CFType_pairs(&[
+                    (
+                        ns_key(AVFormatIDKey).as_CFType(),
+                        CFNumber::from(AUDIO_FORMAT_LINEAR_PCM as i64).as_CFType(),
+                    ),
+                    (
+                        ns_key(AVLinearPCMIsFloatKey).as_CFType(),
+                        CFBoolean::true_value().as_CFType(),
+                    ),
+                    (
+                        ns_key(AVLinearPCMBitDepthKey).as_CFType(),
+                        CFNumber::from(32_i32).as_CFType(),
+                    ),
+                    (
+                        ns_key(AVLinearPCMIsNonInterleaved).as_CFType(),
+                        CFBoolean::false_value().as_CFType(),
+                    ),
+                ]);
+                let _: () = msg_send![
+                    &*output,
+                    setAudioSettings: settings.as_concrete_TypeRef().cast::<AnyObject>()
+                ];
+            }
+            let delegate = AudioCaptureDelegate::new(sender);
+            let queue = Queue::new("nokhwa_audio", QueueAttribute::Serial);
+            unsafe {
+                let _: () = msg_send![
+                    &*output,
+                    setSampleBufferDelegate: &*delegate,
+                    queue: QueuePtr(queue.as_raw().cast())
+                ];
+            }
+
+            let session = &*self.session;
+            session.begin_configuration();
+            let accepted: bool = unsafe {
+                let can_add_input: bool = msg_send![session, canAddInput: &*input];
+                can_add_input && {
+                    let _: () = msg_send![session, addInput: &*input];
+                    let can_add_output: bool = msg_send![session, canAddOutput: &*output];
+                    if can_add_output {
+                        let _: () = msg_send![session, addOutput: &*output];
+                    } else {
+                        let _: () = msg_send![session, removeInput: &*input];
+                    }
+                    can_add_output
+                }
+            };
+            session.commit_configuration();
+            if !accepted {
+                unsafe {
+                    let _: () = msg_send![
+                        &*output,
+                        setSampleBufferDelegate: std::ptr::null::<AnyObject>(),
+                        queue: QueuePtr(std::ptr::null_mut())
+                    ];
+                }
+                return Err(error(
+                    "The capture session does not accept the microphone".to_string(),
+                ));
+            }
+
+            self.audio = Some(AudioStream {
+                input,
+                output,
+                _delegate: delegate,
+                _queue: queue,
+            });
+            Ok(())
+        }
+
+        /// Stops capturing audio, see [`add_audio()`](CaptureSession::add_audio).
+        pub fn remove_audio(&mut self) {
+            let Some(audio) = self.audio.take() else {
+                return;
+            };
+            let session = &*self.session;
+            unsafe {
+                let _: () = msg_send![
+                    &*audio.output,
+                    setSampleBufferDelegate: std::ptr::null::<AnyObject>(),
+                    queue: QueuePtr(std::ptr::null_mut())
+                ];
+                session.begin_configuration();
+                let _: () = msg_send![session, removeOutput: &*audio.output];
+                let _: () = msg_send![session, removeInput: &*audio.input];
+                session.commit_configuration();
+            }
+        }
+
+        /// Checks if the session captures audio, see [`add_audio()`](CaptureSession::add_audio).
+        pub fn has_audio(&self) -> bool {
+            self.audio.is_some()
+        }
+
+        /// Get the `AVCaptureSession`.
+        pub fn session(&self) -> &AVCaptureSession {
+            &self.session
+        }
+
+        /// Get the video data output frames are captured from.
+        pub fn output(&self) -> &AVCaptureVideoDataOutput {
+            &self.output
+        }
+
+        /// Get the delegate the output delivers sample buffers to.
+        pub fn delegate(&self) -> &AVCaptureDelegate {
+            &self.delegate
+        }
+    }
+
+    impl Drop for CaptureSession {
+        fn drop(&mut self) {
+            // detached first, so that no frame is delivered while the session is being torn down
+            clear_sample_buffer_delegate(&self.output);
+            self.remove_audio();
+            self.session.stop_running();
+            self.session.begin_configuration();
+            if let Some(photo_output) = &self.photo_output {
+                let _: () =
+                    unsafe { msg_send![&*self.session, removeOutput: &*photo_output.output] };
+            }
+            self.session.remove_output(&self.output);
+            self.session.remove_input(&self.input);
+            self.session.commit_configuration();
+        }
+    }
+
+    struct MultiCamStream {
+        input: Retained<AVCaptureDeviceInput>,
+        output: Retained<AVCaptureVideoDataOutput>,
+        connection: Retained<AnyObject>,
+        delegate: Retained<AVCaptureDelegate>,
+        _queue: Queue,
+    }
+
+    /// One capture session that streams several devices at once, each into an [`AVCaptureDelegate`] of its own.
+    ///
+    /// On iOS this is an `AVCaptureMultiCamSession`, which only some devices support, see
+    /// [`is_supported()`](MultiCamSession::is_supported). On macOS a regular `AVCaptureSession` takes several inputs, so
+    /// this works with any combination of devices the hardware can drive at once. Every device gets its own input, video
+    /// data output and dispatch queue, connected explicitly so that frames of one device never reach the output of another.
+    pub struct MultiCamSession {
+        session: Retained<AVCaptureSession>,
+        multi_cam: bool,
+        streams: Vec<MultiCamStream>,
+    }
+
+    impl MultiCamSession {
+        /// Checks if this device can stream several cameras at once. This is always the case on macOS.
+        pub fn is_supported() -> bool {
+            match AnyClass::get("AVCaptureMultiCamSession") {
+                Some(class) => unsafe { msg_send![class, isMultiCamSupported] },
+                None => cfg!(target_os = "macos"),
+            }
+        }
+
+        /// Creates an empty session. Add the devices with [`add_device()`](MultiCamSession::add_device).
+        /// # Errors
+        /// If this device cannot stream several cameras at once, see [`is_supported()`](MultiCamSession::is_supported),
+        /// this will error.
+        pub fn new() -> Result<Self, NokhwaError> {
+            let multi_cam_class = AnyClass::get("AVCaptureMultiCamSession");
+            let session = match multi_cam_class {
+                Some(class) => {
+                    let supported: bool = unsafe { msg_send![class, isMultiCamSupported] };
+                    if !supported {
+                        return Err(NokhwaError::UnsupportedOperationError(
+                            ApiBackend::AVFoundation,
+                        ));
+                    }
+                    let session: Retained<AnyObject> = unsafe { msg_send_id![class, new] };
+                    // `AVCaptureMultiCamSession` is a subclass of `AVCaptureSession`
+                    unsafe { Retained::cast::<AVCaptureSession>(session) }
+                }
+                None if cfg!(target_os = "macos") => AVCaptureSession::new(),
+                None => {
+                    return Err(NokhwaError::UnsupportedOperationError(
+                        ApiBackend::AVFoundation,
+                    ))
+                }
+            };
+            Ok(MultiCamSession {
+                session,
+                multi_cam: multi_cam_class.is_some(),
+                streams: Vec::new(),
+            })
+        }
+
+        /// Adds `device`, streaming in `format` to `sender`. `queue_name` labels the dispatch queue its frames are
+        /// delivered on. Returns the index of the stream, which is the order the devices were added in.
+        ///
+        /// Devices can also be added to a running session, which may briefly interrupt the other streams.
+        /// # Errors
+        /// If the device cannot be opened, the session does not accept it, `format` cannot be set, or the hardware cannot
+        /// stream it together with the devices already added, this will error and the session stays the same.
+        pub fn add_device(
+            &mut self,
+            device: &mut AVCaptureDeviceWrapper,
+            format: CameraFormat,
+            queue_name: &str,
+            sender: Arc<SenderType>,
+        ) -> Result<usize, NokhwaError> {
+            let name = device.info().name();
+            let rejected = |why: &str| NokhwaError::OpenDeviceError(name.clone(), why.to_string());
+            let input = AVCaptureDeviceInput::from_device(device.raw_device()).map_err(|why| {
+                NokhwaError::OpenDeviceError("Cannot open device".to_string(), why.to_string())
+                    .with_os_error(PlatformError::NSError {
+                        domain: why.domain().to_string(),
+                        code: why.code(),
+                    })
+            })?;
+            let output = AVCaptureVideoDataOutput::new();
+            let session = &*self.session;
+
+            session.begin_configuration();
+            let can_add_input: bool = unsafe { msg_send![session, canAddInput: &*input] };
+            if !can_add_input {
+                session.commit_configuration();
+                return Err(rejected(
+                    "The capture session does not accept the device input",
+                ));
+            }
+            unsafe {
+                let _: () = msg_send![session, addInputWithNoConnections: &*input];
+            }
+            // adding the input resets the active format of the device
+            if let Err(why) = device.set_all(format) {
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(why);
+            }
+            let can_add_output: bool = unsafe { msg_send![session, canAddOutput: &*output] };
+            if !can_add_output {
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(rejected(
+                    "The capture session does not accept another video data output",
+                ));
+            }
+            unsafe {
+                let _: () = msg_send![session, addOutputWithNoConnections: &*output];
+            }
+            let Some(connection) = (unsafe { video_connection(&input, &output) }) else {
+                session.remove_output(&output);
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(rejected(
+                    "The device input cannot be connected to the video data output",
+                ));
+            };
+            let can_add_connection: bool =
+                unsafe { msg_send![session, canAddConnection: &*connection] };
+            if !can_add_connection {
+                session.remove_output(&output);
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(rejected(
+                    "The capture session does not accept the connection",
+                ));
+            }
+            unsafe {
+                let _: () = msg_send![session, addConnection: &*connection];
+            }
+            session.commit_configuration();
+
+            if self.hardware_cost() > 1.0 {
+                session.begin_configuration();
+                unsafe {
+                    let _: () = msg_send![session, removeConnection: &*connection];
+                }
+                session.remove_output(&output);
+                session.remove_input(&input);
+                session.commit_configuration();
+                return Err(rejected(
+                    "The hardware cannot stream this device together with the others, try a smaller format",
+                ));
+            }
+
+            let mut delegate = AVCaptureDelegate::new();
+            delegate.set_sender(sender);
+            let queue = Queue::new(queue_name, QueueAttribute::Serial);
+            let protocol: &ProtocolObject<dyn AVCaptureVideoDataOutputSampleBufferDelegate> =
+                ProtocolObject::from_ref(&*delegate);
+            output.set_sample_buffer_delegate(protocol, &queue);
+            output.set_always_discards_late_video_frames(true);
+
+            self.streams.push(MultiCamStream {
+                input,
+                output,
+                connection,
+                delegate,
+                _queue: queue,
+            });
+            Ok(self.streams.len() - 1)
+        }
+
+        /// Gets how much of the capture hardware the devices use, where anything above `1.0` cannot be streamed. This is
+        /// always `0.0` on macOS.
+        pub fn hardware_cost(&self) -> f64 {
+            if self.multi_cam {
+                unsafe { msg_send![&*self.session, hardwareCost] }
+            } else {
+                0.0
+            }
+        }
+
+        /// Starts capturing on all devices. This blocks until the session is running, or has failed to start.
+        pub fn start(&self) {
+            self.session.start_running();
+        }
+
+        /// Checks if the session is capturing.
+        pub fn is_running(&self) -> bool {
+            #[cfg(target_os = "ios")]
+            if self.session.is_interrupted() {
+                return false;
+            }
+            self.session.is_running()
+        }
+
+        /// Gets the number of devices in the session.
+        pub fn len(&self) -> usize {
+            self.streams.len()
+        }
+
+        /// Checks if no device has been added yet.
+        pub fn is_empty(&self) -> bool {
+            self.streams.is_empty()
+        }
+
+        /// Mirrors the stream at `index`, see [`set_output_mirrored()`]. Returns `false` if there is no such stream.
+        pub fn set_mirrored(&self, index: usize, mirrored: bool) -> bool {
+            self.streams
+                .get(index)
+                .is_some_and(|stream| set_output_mirrored(&stream.output, mirrored))
+        }
+
+        /// Get the delegate of the stream at `index`.
+        pub fn delegate(&self, index: usize) -> Option<&AVCaptureDelegate> {
+            self.streams.get(index).map(|stream| &*stream.delegate)
+        }
+
+        /// Get the `AVCaptureSession`.
+        pub fn session(&self) -> &AVCaptureSession {
+            &self.session
+        }
+    }
+
+    impl Drop for MultiCamSession {
+        fn drop(&mut self) {
+            for stream in &self.streams {
+                clear_sample_buffer_delegate(&stream.output);
+            }
+            self.session.stop_running();
+            self.session.begin_configuration();
+            for stream in &self.streams {
+                unsafe {
+                    let _: () = msg_send![&*self.session, removeConnection: &*stream.connection];
+                }
+                self.session.remove_output(&stream.output);
+                self.session.remove_input(&stream.input);
+            }
+            self.session.commit_configuration();
+        }
+    }
+
+    /// Creates a connection from the video port of `input` to `output`.
+    unsafe fn video_connection(
+        input: &AVCaptureDeviceInput,
+        output: &AVCaptureVideoDataOutput,
+    ) -> Option<Retained<AnyObject>> {
+        let ports: *mut AnyObject = msg_send![input, ports];
+        if ports.is_null() {
+            return None;
+        }
+        let count: usize = msg_send![ports, count];
+        let port = (0..count)
+            .map(|index| -> *mut AnyObject { msg_send![ports, objectAtIndex: index] })
+            .find(|port| {
+                let media_type: *mut AnyObject = msg_send![*port, mediaType];
+                !media_type.is_null() && msg_send![media_type, isEqualToString: AVMediaTypeVideo]
+            })?;
+        let ports: Retained<AnyObject> = msg_send_id![class!(NSArray), arrayWithObject: port];
+        msg_send_id![
+            class!(AVCaptureConnection),
+            connectionWithInputPorts: &*ports,
+            output: output
+        ]
+    }
+
+    /// Gets the duration of one frame at `fps` frames per second, exact to a thousandth of a frame per second, e.g. for
+    /// 29.97.
+    fn frame_duration(fps: f64) -> CMTime {
+        CMTime {
+            value: 1000,
+            timescale: (fps * 1000.0).round() as i32,
+            // kCMTimeFlags_Valid
+            flags: 1,
+            epoch: 0,
+        }
+    }
+
+    /// Checks if `object` (an instance or a class) implements `selector`, as methods added in newer OS versions are
+    /// missing on older ones.
+    fn responds_to(object: impl MessageReceiver, selector: Sel) -> bool {
+        unsafe { msg_send![object, respondsToSelector: selector] }
+    }
+
+    /// An Objective-C block pointer, passed as a message argument.
+    #[repr(transparent)]
+    struct BlockPtr(*const c_void);
+
+    unsafe impl Encode for BlockPtr {
+        const ENCODING: Encoding = Encoding::Block;
+    }
+
+    /// A `dispatch_queue_t`, passed as a message argument. Dispatch queues are Objective-C objects.
+    #[repr(transparent)]
+    struct QueuePtr(*mut c_void);
+
+    unsafe impl Encode for QueuePtr {
+        const ENCODING: Encoding = Encoding::Object;
+    }
+
+    /// Gets the camera authorization status of the application from `AVCaptureDevice`.
+    pub fn authorization_status() -> PermissionStatus {
+        media_authorization_status(unsafe { AVMediaTypeVideo })
+    }
+
+    /// Asks the user for camera access, showing the system prompt if they have not been asked before.
+    /// `callback` is called from an arbitrary dispatch queue once the user has answered, with whether access was granted.
+    pub fn request_authorization<F>(callback: F)
+    where
+        F: FnOnce(bool) + Send + 'static,
+    {
+        request_media_authorization(unsafe { AVMediaTypeVideo }, callback);
+    }
+
+    fn media_authorization_status(media_type: &NSString) -> PermissionStatus {
+        let status: NSInteger = unsafe {
+            msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: media_type]
+        };
+        // AVAuthorizationStatus
+        match status {
+            0 => PermissionStatus::NotDetermined,
+            1 => PermissionStatus::Restricted,
+            2 => PermissionStatus::Denied,
+            _ => PermissionStatus::Authorized,
+        }
+    }
+
+    fn request_media_authorization<F>(media_type: &NSString, callback: F)
+    where
+        F: FnOnce(bool) + Send + 'static,
+    {
+        let callback = Mutex::new(Some(callback));
+        let block = ConcreteBlock::new(move |granted: Bool| {
+            if let Some(callback) = callback.lock().ok().and_then(|mut cb| cb.take()) {
+                callback(granted.as_bool());
+            }
+        })
+        .copy();
+        // AVFoundation copies the block, so it is fine to drop ours once the message returns.
+        unsafe {
+            let _: () = msg_send![
+                class!(AVCaptureDevice),
+                requestAccessForMediaType: media_type,
+                completionHandler: BlockPtr(std::ptr::from_ref(&*block).cast())
+            ];
+        }
+    }
+
+    /// Makes sure the application may use the camera (or microphone, depending on `media_type`), showing the system
+    /// prompt and blocking until the user has answered if they have not been asked yet. `device` is the device being
+    /// opened, for the error.
+    fn ensure_authorized(media_type: &NSString, device: &str) -> Result<(), NokhwaError> {
+        let status = match media_authorization_status(media_type) {
+            PermissionStatus::NotDetermined => {
+                let (sender, receiver) = std::sync::mpsc::channel();
+                request_media_authorization(media_type, move |granted| {
+                    let _ = sender.send(granted);
+                });
+                // if the block is dropped without being called, the user did not grant access
+                if receiver.recv().unwrap_or(false) {
+                    PermissionStatus::Authorized
+                } else {
+                    media_authorization_status(media_type)
+                }
+            }
+            status => status,
+        };
+        match status {
+            PermissionStatus::Authorized => Ok(()),
+            status => Err(NokhwaError::OpenDeviceError(
+                device.to_string(),
+                format!("Access is {status}"),
+            )
+            .with_kind(ErrorKind::PermissionDenied)),
+        }
+    }
+
     pub fn query_avfoundation() -> Result<Vec<CameraInfo>, NokhwaError> {
         #[cfg(any(target_os = "macos"))]
         let device_types: Vec<&AVCaptureDeviceType> = unsafe {
@@ -202,15 +2346,88 @@ mod internal {
             )
         };
         let devices = discovery_session.devices();
-        let cameras = devices
+        let mut cameras = devices
             .into_iter()
             .map(|device| get_camera_info(device.as_ref()))
-            .collect();
+            .collect::<Vec<_>>();
+
+        // a Desk View camera is not always discovered on its own, so list the ones of Continuity Cameras too
+        let desk_view_ids = cameras
+            .iter()
+            .filter_map(|camera| {
+                camera
+                    .continuity_camera()?
+                    .desk_view_id()
+                    .map(str::to_string)
+            })
+            .collect::<Vec<_>>();
+        for desk_view_id in desk_view_ids {
+            if cameras
+                .iter()
+                .any(|camera| camera.unique_id() == desk_view_id)
+            {
+                continue;
+            }
+            let id = NSString::from_str(&desk_view_id);
+            if let Some(device) = AVCaptureDevice::device_with_unique_id(&id) {
+                cameras.push(get_camera_info(&device));
+            }
+        }
         Ok(cameras)
     }
 
+    /// Gets the default camera of the system, from `defaultDeviceWithMediaType:`. Returns `None` if there is no camera.
+    pub fn default_avfoundation_device() -> Option<CameraInfo> {
+        let device: Option<Retained<AVCaptureDevice>> = unsafe {
+            msg_send_id![class!(AVCaptureDevice), defaultDeviceWithMediaType: AVMediaTypeVideo]
+        };
+        device.map(|device| get_camera_info(&device))
+    }
+
+    /// Gets the details of `device` if it is an iPhone used as a Continuity Camera. Only macOS 13 and later know about them.
+    fn continuity_camera(device: &AVCaptureDevice) -> Option<ContinuityCamera> {
+        if !cfg!(target_os = "macos") || !responds_to(device, sel!(isContinuityCamera)) {
+            return None;
+        }
+        let is_continuity_camera: bool = unsafe { msg_send![device, isContinuityCamera] };
+        if !is_continuity_camera {
+            return None;
+        }
+        let connected: bool = unsafe { msg_send![device, isConnected] };
+        let desk_view_id = if responds_to(device, sel!(companionDeskViewCamera)) {
+            let desk_view: *mut AnyObject = unsafe { msg_send![device, companionDeskViewCamera] };
+            unsafe { desk_view.as_ref() }.and_then(|desk_view| {
+                let unique_id: Option<Retained<NSString>> =
+                    unsafe { msg_send_id![desk_view, uniqueID] };
+                unique_id.map(|unique_id| unique_id.to_string())
+            })
+        } else {
+            None
+        };
+        Some(ContinuityCamera::new(connected, desk_view_id))
+    }
+
+    /// Gets where `device` is from its `position`, which external cameras leave unspecified, so their device type is
+    /// checked as well (`AVCaptureDeviceTypeExternal`, or `AVCaptureDeviceTypeExternalUnknown` before macOS 14).
+    fn camera_position(device: &AVCaptureDevice) -> CameraPosition {
+        // AVCaptureDevicePositionBack and AVCaptureDevicePositionFront
+        let position: NSInteger = unsafe { msg_send![device, position] };
+        match position {
+            1 => CameraPosition::Back,
+            2 => CameraPosition::Front,
+            _ if device
+                .device_type()
+                .to_string()
+                .starts_with("AVCaptureDeviceTypeExternal") =>
+            {
+                CameraPosition::External
+            }
+            _ => CameraPosition::Unknown,
+        }
+    }
+
     pub fn get_camera_info(device: &AVCaptureDevice) -> CameraInfo {
-        CameraInfo::new(
+        let info = CameraInfo::new(
             device.unique_id().to_string().as_str(),
             device.localized_name().to_string().as_str(),
             device.manufacturer().to_string().as_str(),
@@ -218,16 +2435,53 @@ mod internal {
             device.device_type().to_string().as_str(),
             device.position().to_string().as_str(),
         )
+        // iOS cameras are mounted for landscape, and the video data output does not rotate buffers by default
+        .with_sensor_orientation(if cfg!(target_os = "ios") {
+            Rotation::Deg90
+        } else {
+            Rotation::Deg0
+        })
+        .with_camera_position(camera_position(device));
+        match continuity_camera(device) {
+            Some(continuity_camera) => info.with_continuity_camera(continuity_camera),
+            None => info,
+        }
+    }
+
+    /// Names each of the `possible` AVFoundation modes, where `names` is indexed by the mode's raw value.
+    fn mode_labels(possible: &[isize], names: &[&str]) -> Vec<String> {
+        possible
+            .iter()
+            .map(|mode| {
+                usize::try_from(*mode)
+                    .ok()
+                    .and_then(|index| names.get(index))
+                    .map_or_else(|| mode.to_string(), |name| (*name).to_string())
+            })
+            .collect()
     }
 
     pub struct AVCaptureDeviceWrapper {
         inner: Retained<AVCaptureDevice>,
         device: CameraInfo,
         locked: bool,
+        controls: ControlCache,
+    }
+
+    impl Drop for AVCaptureDeviceWrapper {
+        fn drop(&mut self) {
+            // a configuration lock that is never released keeps the device from being reconfigured by anyone else
+            self.unlock();
+        }
     }
 
     impl AVCaptureDeviceWrapper {
+        /// Opens the camera at `index`. If the user has not been asked for camera access yet, this shows the system prompt
+        /// and waits for their answer first.
+        /// # Errors
+        /// If camera access is denied or restricted, or the camera does not exist, this will error.
         pub fn new(index: &CameraIndex) -> Result<Self, NokhwaError> {
+            ensure_authorized(unsafe { AVMediaTypeVideo }, &index.to_string())?;
             match &index {
                 CameraIndex::Index(idx) => {
                     let devices = query_avfoundation()?;
@@ -246,7 +2500,11 @@ mod internal {
             }
         }
 
+        /// Opens the camera with `unique_id`, asking for camera access first like [`new()`](AVCaptureDeviceWrapper::new).
+        /// # Errors
+        /// If camera access is denied or restricted, or the camera does not exist, this will error.
         pub fn from_unique_id(unique_id: &str) -> Result<Self, NokhwaError> {
+            ensure_authorized(unsafe { AVMediaTypeVideo }, unique_id)?;
             let binding = NSString::from_str(&unique_id.to_string());
             let nsstr_id = binding.as_ref();
             let device_option =
@@ -265,6 +2523,7 @@ mod internal {
                 inner: device,
                 device: camera_info,
                 locked: false,
+                controls: Arc::new(Mutex::new(None)),
             })
         }
 
@@ -303,7 +2562,7 @@ mod internal {
                 .collect())
         }
 
-        pub fn lock(&self) -> Result<(), NokhwaError> {
+        pub fn lock(&mut self) -> Result<(), NokhwaError> {
             if self.locked {
                 return Ok(());
             }
@@ -311,12 +2570,14 @@ mod internal {
                 return Err(NokhwaError::InitializeError {
                     backend: ApiBackend::AVFoundation,
                     error: "Already in use".to_string(),
-                });
+                }
+                .with_kind(ErrorKind::DeviceBusy));
             }
             let result = self.inner.lock_for_configuration();
             match result {
                 Ok(accepted) => {
                     if accepted {
+                        self.locked = true;
                         return Ok(());
                     } else {
                         return Err(NokhwaError::SetPropertyError {
@@ -359,14 +2620,20 @@ mod internal {
                     && dimensions.width == descriptor.resolution().width() as i32
                 {
                     selected_format = Some(format);
+                    let fps = f64::from(descriptor.frame_rate());
                     for range in format.video_supported_frame_rate_ranges() {
                         let max_fps: f64 = range.max_frame_rate();
 
-                        if (f64::from(descriptor.frame_rate()) - max_fps).abs() < 0.01 {
+                        if (fps - max_fps).abs() < 0.01 {
                             min_frame_duration = Some(range.min_frame_duration());
                             max_frame_duration = Some(range.max_frame_duration());
                             break;
                         }
+                        // a rate inside a range, e.g. 24 in 1-30, is streamed at exactly that rate
+                        if range.min_frame_rate() <= fps && fps <= max_fps {
+                            min_frame_duration = Some(frame_duration(fps));
+                            max_frame_duration = Some(frame_duration(fps));
+                        }
                     }
                 }
             }
@@ -375,11 +2642,13 @@ mod internal {
                 || max_frame_duration.is_none()
                 || selected_format.is_none()
             {
+                self.unlock();
                 return Err(NokhwaError::SetPropertyError {
                     property: "CameraFormat".to_string(),
                     value: descriptor.to_string(),
                     error: "Not Found/Rejected/Unsupported".to_string(),
-                });
+                }
+                .with_kind(ErrorKind::FormatUnsupported));
             }
 
             self.inner
@@ -391,9 +2660,254 @@ mod internal {
                 max_frame_duration.expect("max_frame_duration not set"),
             );
             self.unlock();
+            // the ranges of the zoom, ISO and exposure duration depend on the format
+            self.invalidate_controls();
+            Ok(())
+        }
+
+        /// Streams the active format at exactly `fps` frames per second, e.g. 24 from a format that supports 1 to 30,
+        /// by setting both its minimum and maximum frame duration. Returns the frame rate the device reports afterwards,
+        /// see [`frame_rate_exact()`](AVCaptureDeviceWrapper::frame_rate_exact).
+        /// # Errors
+        /// If no frame rate range of the active format contains `fps`, or the device cannot be locked, this will error.
+        pub fn set_frame_rate_exact(&mut self, fps: f64) -> Result<f64, NokhwaError> {
+            let supported = fps.is_finite()
+                && fps > 0.0
+                && self
+                    .inner
+                    .get_active_format()
+                    .video_supported_frame_rate_ranges()
+                    .into_iter()
+                    .any(|range| {
+                        range.min_frame_rate() - 0.01 <= fps && fps <= range.max_frame_rate() + 0.01
+                    });
+            if !supported {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "FrameRate".to_string(),
+                    value: fps.to_string(),
+                    error: "Outside of the frame rate ranges of the active format".to_string(),
+                }
+                .with_kind(ErrorKind::FormatUnsupported));
+            }
+
+            self.lock()?;
+            self.inner
+                .set_active_video_min_frame_duration(frame_duration(fps));
+            self.inner
+                .set_active_video_max_frame_duration(frame_duration(fps));
+            self.unlock();
+            self.invalidate_controls();
+            Ok(self.frame_rate_exact())
+        }
+
+        /// Gets the frame rate the device streams at, from its `activeVideoMinFrameDuration`. Returns `0.0` if the
+        /// device does not report one.
+        pub fn frame_rate_exact(&self) -> f64 {
+            let duration: CMTime = unsafe { msg_send![&*self.inner, activeVideoMinFrameDuration] };
+            if duration.value <= 0 || duration.timescale <= 0 {
+                return 0.0;
+            }
+            f64::from(duration.timescale) / duration.value as f64
+        }
+
+        /// Gets the zoom range of the device, as `videoZoomFactor`s. `AVFoundation` has no pan and tilt.
+        pub fn ptz_capabilities(&self) -> PtzCapabilities {
+            let device = &*self.inner;
+            // the available zoom factors are only there on macOS 14 and later
+            let zoom = responds_to(device, sel!(maxAvailableVideoZoomFactor))
+                .then(|| {
+                    let (min, max): (f64, f64) = unsafe {
+                        (
+                            msg_send![device, minAvailableVideoZoomFactor],
+                            msg_send![device, maxAvailableVideoZoomFactor],
+                        )
+                    };
+                    PtzRange::new(min, max, 0.0, 1.0)
+                })
+                .filter(|range| range.max() > range.min());
+            PtzCapabilities::new(None, None, zoom).with_zoom_ramp(zoom.is_some())
+        }
+
+        /// Sets the `videoZoomFactor` of the device, or ramps to it at `rate` (in powers of two per second) with
+        /// `rampToVideoZoomFactor:withRate:`.
+        /// # Errors
+        /// If the device cannot zoom, `zoom` is out of range, or the device cannot be locked, this will error.
+        pub fn set_zoom(&mut self, zoom: f64, rate: Option<f64>) -> Result<(), NokhwaError> {
+            let Some(range) = self.ptz_capabilities().zoom() else {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::AVFoundation,
+                ));
+            };
+            // setting a zoom factor out of range raises an exception
+            if !(range.min()..=range.max()).contains(&zoom) {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "videoZoomFactor".to_string(),
+                    value: zoom.to_string(),
+                    error: format!("Out of range {range}"),
+                });
+            }
+            self.lock()?;
+            let device = &*self.inner;
+            unsafe {
+                match rate {
+                    Some(rate) => {
+                        let _: () =
+                            msg_send![device, rampToVideoZoomFactor: zoom withRate: rate as f32];
+                    }
+                    None => {
+                        let _: () = msg_send![device, setVideoZoomFactor: zoom];
+                    }
+                }
+            }
+            self.unlock();
+            Ok(())
+        }
+
+        /// Switches [`KnownCameraControl::Focus`], [`KnownCameraControl::Exposure`] or
+        /// [`KnownCameraControl::WhiteBalance`] to its continuous automatic mode. Setting a lens position, exposure
+        /// duration or white balance gains is only possible on iOS, so on macOS only the automatic modes can be set.
+        /// # Errors
+        /// If the device does not have the automatic mode, or cannot be locked, this will error.
+        pub fn set_continuous_auto(
+            &mut self,
+            control: KnownCameraControl,
+        ) -> Result<(), NokhwaError> {
+            // AVCaptureFocusModeContinuousAutoFocus, AVCaptureExposureModeContinuousAutoExposure and
+            // AVCaptureWhiteBalanceModeContinuousAutoWhiteBalance are all 2
+            const CONTINUOUS_AUTO: NSInteger = 2;
+            let device = &*self.inner;
+            let supported: bool = unsafe {
+                match control {
+                    KnownCameraControl::Focus => {
+                        msg_send![device, isFocusModeSupported: CONTINUOUS_AUTO]
+                    }
+                    KnownCameraControl::Exposure => {
+                        msg_send![device, isExposureModeSupported: CONTINUOUS_AUTO]
+                    }
+                    KnownCameraControl::WhiteBalance => {
+                        msg_send![device, isWhiteBalanceModeSupported: CONTINUOUS_AUTO]
+                    }
+                    _ => false,
+                }
+            };
+            if !supported {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::AVFoundation,
+                ));
+            }
+            self.lock()?;
+            let device = &*self.inner;
+            unsafe {
+                match control {
+                    KnownCameraControl::Focus => {
+                        let _: () = msg_send![device, setFocusMode: CONTINUOUS_AUTO];
+                    }
+                    KnownCameraControl::Exposure => {
+                        let _: () = msg_send![device, setExposureMode: CONTINUOUS_AUTO];
+                    }
+                    _ => {
+                        let _: () = msg_send![device, setWhiteBalanceMode: CONTINUOUS_AUTO];
+                    }
+                }
+            }
+            self.unlock();
+            self.invalidate_controls();
+            Ok(())
+        }
+
+        /// Sets the point of interest of [`KnownCameraControl::Focus`] or [`KnownCameraControl::Exposure`] to `point`,
+        /// from `(0.0, 0.0)` at the top left of the frame to `(1.0, 1.0)` at the bottom right, and switches to the
+        /// continuous (or else one-shot) automatic mode, which is what makes the device use the point.
+        /// # Errors
+        /// If the device has no point of interest for `control`, or cannot be locked, this will error.
+        pub fn set_point_of_interest(
+            &mut self,
+            control: KnownCameraControl,
+            point: (f64, f64),
+        ) -> Result<(), NokhwaError> {
+            let device = &*self.inner;
+            let unsupported = || NokhwaError::UnsupportedOperationError(ApiBackend::AVFoundation);
+            let supported: bool = unsafe {
+                match control {
+                    KnownCameraControl::Focus => msg_send![device, isFocusPointOfInterestSupported],
+                    KnownCameraControl::Exposure => {
+                        msg_send![device, isExposurePointOfInterestSupported]
+                    }
+                    _ => false,
+                }
+            };
+            if !supported {
+                return Err(unsupported());
+            }
+            // 2 => continuous, 1 => once
+            let mode = [2, 1]
+                .into_iter()
+                .find(|mode: &NSInteger| unsafe {
+                    match control {
+                        KnownCameraControl::Focus => msg_send![device, isFocusModeSupported: *mode],
+                        _ => msg_send![device, isExposureModeSupported: *mode],
+                    }
+                })
+                .ok_or_else(unsupported)?;
+            let point = CGPoint::new(point.0, point.1);
+
+            self.lock()?;
+            let device = &*self.inner;
+            unsafe {
+                match control {
+                    KnownCameraControl::Focus => {
+                        let _: () = msg_send![device, setFocusPointOfInterest: point];
+                        let _: () = msg_send![device, setFocusMode: mode];
+                    }
+                    _ => {
+                        let _: () = msg_send![device, setExposurePointOfInterest: point];
+                        let _: () = msg_send![device, setExposureMode: mode];
+                    }
+                }
+            }
+            self.unlock();
+            self.invalidate_controls();
             Ok(())
         }
 
+        /// Reads all controls of the device, and caches them for [`set_control()`](AVCaptureDeviceWrapper::set_control)
+        /// to check values against.
+        /// # Errors
+        /// If the controls cannot be read, this will error.
+        pub fn get_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+            let controls = self.read_controls()?;
+            *self.control_cache() = Some(controls.clone());
+            Ok(controls)
+        }
+
+        /// Reads the controls of the device again for the cache. The cache is dropped by itself when the format
+        /// changes, a mode is set, or a [`SessionNotifier`] reports a control changed elsewhere, so this is only needed
+        /// after changing the device some other way.
+        /// # Errors
+        /// If the controls cannot be read, this will error.
+        pub fn refresh_controls(&self) -> Result<(), NokhwaError> {
+            self.get_controls().map(|_| ())
+        }
+
+        /// Gets the cached controls, only reading them from the device if they are not cached, so that setting controls
+        /// in quick succession (e.g. from a slider) does not read all of them every time.
+        fn cached_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+            if let Some(controls) = self.control_cache().as_ref() {
+                return Ok(controls.clone());
+            }
+            self.get_controls()
+        }
+
+        fn invalidate_controls(&self) {
+            *self.control_cache() = None;
+        }
+
+        fn control_cache(&self) -> MutexGuard<'_, Option<Vec<CameraControl>>> {
+            self.controls
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+
         // 0 => Focus POI
         // 1 => Focus Manual Setting
         // 2 => Exposure POI
@@ -401,7 +2915,12 @@ mod internal {
         // 4 => Exposure Target Bias
         // 5 => Exposure ISO
         // 6 => Exposure Duration
-        pub fn get_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+        // 7 => Center Stage
+        // 8 => Center Stage Control Mode
+        // 9 => Portrait Effect
+        // 10 => Studio Light
+        // 11 => Reaction Effects
+        fn read_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
             let mut controls = vec![];
 
             let focus_current = self.inner.focus_mode();
@@ -431,6 +2950,10 @@ mod internal {
                     "FocusMode".to_string(),
                     ControlValueDescription::Enum {
                         value: focus_current,
+                        labels: mode_labels(
+                            &supported_focus_values,
+                            &["Locked", "Auto", "Continuous"],
+                        ),
                         possible: supported_focus_values,
                         default: focus_current,
                     },
@@ -532,6 +3055,10 @@ mod internal {
                             "ExposureMode".to_string(),
                             ControlValueDescription::Enum {
                                 value: exposure_current,
+                                labels: mode_labels(
+                                    &supported_exposure_values,
+                                    &["Locked", "Auto", "Continuous", "Custom"],
+                                ),
                                 possible: supported_exposure_values,
                                 default: exposure_current,
                             },
@@ -690,6 +3217,7 @@ mod internal {
                             "WhiteBalanceMode".to_string(),
                             ControlValueDescription::Enum {
                                 value: white_balance_mode,
+                                labels: mode_labels(&possible, &["Locked", "Auto", "Continuous"]),
                                 possible,
                                 default: 0,
                             },
@@ -742,48 +3270,6 @@ mod internal {
                         white_balance_gain_supported == YES,
                     ));
 
-                    let has_torch: BOOL = unsafe { msg_send![&self.inner, isTorchAvailable] };
-                    let torch_active: BOOL = unsafe { msg_send![&self.inner, isTorchActive] };
-                    let torch_off: BOOL =
-                        unsafe { msg_send![&self.inner, isTorchModeSupported:NSInteger::from(0)] };
-                    let torch_on: BOOL =
-                        unsafe { msg_send![&self.inner, isTorchModeSupported:NSInteger::from(1)] };
-                    let torch_auto: BOOL =
-                        unsafe { msg_send![&self.inner, isTorchModeSupported:NSInteger::from(2)] };
-
-                    {
-                        let mut possible = vec![];
-
-                        if torch_off == YES {
-                            possible.push(0);
-                        }
-                        if torch_on == YES {
-                            possible.push(1);
-                        }
-                        if torch_auto == YES {
-                            possible.push(2);
-                        }
-
-                        controls.push(CameraControl::new(
-                            KnownCameraControl::Other(5),
-                            "TorchMode".to_string(),
-                            ControlValueDescription::Enum {
-                                value: (torch_active == YES) as isize,
-                                possible,
-                                default: 0,
-                            },
-                            if has_torch == YES {
-                                vec![
-                                    KnownCameraControlFlag::Disabled,
-                                    KnownCameraControlFlag::ReadOnly,
-                                ]
-                            } else {
-                                vec![]
-                            },
-                            has_torch == YES,
-                        ));
-                    }
-
                     controls.push(CameraControl::new(
                         KnownCameraControl::BacklightComp,
                         "LowLightCompensation".to_string(),
@@ -802,51 +3288,445 @@ mod internal {
                         self.inner.is_(),
                     ));
 
-                    controls.push(CameraControl::new(
-                        KnownCameraControl::Zoom,
-                        "Zoom".to_string(),
-                        ControlValueDescription::FloatRange {
-                            min: zoom_min as f64,
-                            max: zoom_max as f64,
-                            value: zoom_current as f64,
-                            step: f32::MIN_POSITIVE as f64,
-                            default: 1.0,
-                        },
-                        vec![],
-                        true,
-                    ));
+                    controls.push(CameraControl::new(
+                        KnownCameraControl::Zoom,
+                        "Zoom".to_string(),
+                        ControlValueDescription::FloatRange {
+                            min: zoom_min as f64,
+                            max: zoom_max as f64,
+                            value: zoom_current as f64,
+                            step: f32::MIN_POSITIVE as f64,
+                            default: 1.0,
+                        },
+                        vec![],
+                        true,
+                    ));
+
+                    controls.push(CameraControl::new(
+                        KnownCameraControl::Other(6),
+                        "DistortionCorrection".to_string(),
+                        ControlValueDescription::Boolean {
+                            value: distortion_correction_current_value == YES,
+                            default: false,
+                        },
+                        if distortion_correction_supported == YES {
+                            vec![
+                                KnownCameraControlFlag::ReadOnly,
+                                KnownCameraControlFlag::Disabled,
+                            ]
+                        } else {
+                            vec![]
+                        },
+                        distortion_correction_supported == YES,
+                    ));
+                }
+            }
+
+            controls.extend(self.torch_control());
+            #[cfg(target_os = "macos")]
+            controls.extend(self.macos_controls());
+            controls.extend(self.system_effect_controls());
+
+            Ok(controls)
+        }
+
+        /// The controls macOS has for external and Continuity cameras: white balance mode and zoom. Each is only
+        /// reported if the device supports it, most webcams only have [`KnownCameraControl::Focus`] on top of these.
+        #[cfg(target_os = "macos")]
+        fn macos_controls(&self) -> Vec<CameraControl> {
+            let device = &*self.inner;
+            let mut controls = vec![];
+
+            // 0 => locked, 1 => auto, 2 => continuous
+            let white_balance_modes = (0..=2)
+                .filter(|mode: &NSInteger| unsafe {
+                    msg_send![device, isWhiteBalanceModeSupported: *mode]
+                })
+                .collect::<Vec<_>>();
+            if !white_balance_modes.is_empty() {
+                let white_balance_mode: NSInteger = unsafe { msg_send![device, whiteBalanceMode] };
+                controls.push(CameraControl::new(
+                    KnownCameraControl::WhiteBalance,
+                    "WhiteBalanceMode".to_string(),
+                    ControlValueDescription::Enum {
+                        value: white_balance_mode,
+                        labels: mode_labels(
+                            &white_balance_modes,
+                            &["Locked", "Auto", "Continuous"],
+                        ),
+                        possible: white_balance_modes,
+                        default: 2,
+                    },
+                    vec![],
+                    true,
+                ));
+            }
+
+            // zoom is only available on macOS 14 and later, for Continuity Camera
+            if responds_to(device, sel!(maxAvailableVideoZoomFactor)) {
+                let (zoom, min, max): (f64, f64, f64) = unsafe {
+                    (
+                        msg_send![device, videoZoomFactor],
+                        msg_send![device, minAvailableVideoZoomFactor],
+                        msg_send![device, maxAvailableVideoZoomFactor],
+                    )
+                };
+                if max > min {
+                    controls.push(CameraControl::new(
+                        KnownCameraControl::Zoom,
+                        "Zoom".to_string(),
+                        ControlValueDescription::FloatRange {
+                            min,
+                            max,
+                            value: zoom,
+                            step: 0.0,
+                            default: 1.0,
+                        },
+                        vec![],
+                        true,
+                    ));
+                }
+            }
+
+            controls
+        }
+
+        /// Sets one of the controls macOS supports, see [`macos_controls()`](AVCaptureDeviceWrapper::macos_controls), or
+        /// [`KnownCameraControl::Focus`]. Returns `None` for any other control. The device has to be locked.
+        #[cfg(target_os = "macos")]
+        fn set_macos_control(
+            &self,
+            id: KnownCameraControl,
+            value: &ControlValueSetter,
+        ) -> Option<Result<(), NokhwaError>> {
+            let error = |why: &str| NokhwaError::SetPropertyError {
+                property: id.to_string(),
+                value: value.to_string(),
+                error: why.to_string(),
+            };
+            match id {
+                KnownCameraControl::Focus
+                | KnownCameraControl::WhiteBalance
+                | KnownCameraControl::Zoom => {}
+                _ => return None,
+            }
+            let controls = match self.cached_controls() {
+                Ok(controls) => controls,
+                Err(why) => return Some(Err(why)),
+            };
+            let Some(control) = controls.iter().find(|control| control.control() == id) else {
+                return Some(Err(error("Control does not exist")));
+            };
+            if control.flag().contains(&KnownCameraControlFlag::ReadOnly) {
+                return Some(Err(error("Read Only")));
+            }
+            if control.flag().contains(&KnownCameraControlFlag::Disabled) {
+                return Some(Err(error("Disabled")));
+            }
+            if !control.description().verify_setter(value) {
+                return Some(Err(error("Failed to verify value")));
+            }
+
+            let device = &*self.inner;
+            if id == KnownCameraControl::Zoom {
+                let Some(zoom) = value.as_float() else {
+                    return Some(Err(error("Expected float")));
+                };
+                if let ControlValueDescription::FloatRange { min, max, .. } = control.description()
+                {
+                    // setting a zoom factor out of range raises an exception
+                    if !(min..=max).contains(&zoom) {
+                        return Some(Err(error("Out of range")));
+                    }
+                }
+                unsafe {
+                    let _: () = msg_send![device, setVideoZoomFactor: *zoom];
+                }
+                return Some(Ok(()));
+            }
+            let Some(mode) = value.as_enum().map(|mode| *mode as NSInteger) else {
+                return Some(Err(error("Expected Enum")));
+            };
+            if let ControlValueDescription::Enum { possible, .. } = control.description() {
+                if !possible.contains(&mode) {
+                    return Some(Err(error("Not supported by the device")));
+                }
+            }
+            unsafe {
+                match id {
+                    KnownCameraControl::Focus => {
+                        let _: () = msg_send![device, setFocusMode: mode];
+                    }
+                    _ => {
+                        let _: () = msg_send![device, setWhiteBalanceMode: mode];
+                    }
+                }
+            }
+            Some(Ok(()))
+        }
+
+        /// Reports the torch of this device as [`KnownCameraControl::Torch`], if it has one. The value is the torch mode
+        /// (0 => off, 1 => on, 2 => auto); it is disabled while the torch is not available, e.g. because it overheated.
+        fn torch_control(&self) -> Option<CameraControl> {
+            let device = &*self.inner;
+            let has_torch: bool = unsafe { msg_send![device, hasTorch] };
+            if !has_torch {
+                return None;
+            }
+            let (available, mode): (bool, NSInteger) = unsafe {
+                (
+                    msg_send![device, isTorchAvailable],
+                    msg_send![device, torchMode],
+                )
+            };
+            let possible = (0..=2)
+                .filter(|mode: &NSInteger| unsafe {
+                    msg_send![device, isTorchModeSupported: *mode]
+                })
+                .collect();
+            Some(CameraControl::new(
+                KnownCameraControl::Torch,
+                "Torch".to_string(),
+                ControlValueDescription::Enum {
+                    value: mode,
+                    labels: mode_labels(&possible, &["Off", "On", "Auto"]),
+                    possible,
+                    default: 0,
+                },
+                if available {
+                    vec![]
+                } else {
+                    vec![KnownCameraControlFlag::Disabled]
+                },
+                available,
+            ))
+        }
+
+        /// Sets the torch, see [`torch_control()`](AVCaptureDeviceWrapper::torch_control). Besides a mode, this takes a
+        /// boolean to turn it on or off, or a level from 0.0 (off) to 1.0 (full brightness). The device has to be locked.
+        fn set_torch(&self, value: &ControlValueSetter) -> Result<(), NokhwaError> {
+            let error = |why: &str| NokhwaError::SetPropertyError {
+                property: KnownCameraControl::Torch.to_string(),
+                value: value.to_string(),
+                error: why.to_string(),
+            };
+            let Some(control) = self.torch_control() else {
+                return Err(error("Control does not exist"));
+            };
+            if control.flag().contains(&KnownCameraControlFlag::Disabled) {
+                return Err(error("Disabled"));
+            }
+
+            let device = &*self.inner;
+            let mode = match value {
+                ControlValueSetter::Float(level) => {
+                    if !(0.0..=1.0).contains(level) {
+                        return Err(error("Out of range"));
+                    }
+                    if *level > 0.0 {
+                        let mut ns_error: *mut AnyObject = std::ptr::null_mut();
+                        let accepted: bool = unsafe {
+                            msg_send![device, setTorchModeOnWithLevel: *level as f32 error: &mut ns_error]
+                        };
+                        if accepted {
+                            return Ok(());
+                        }
+                        return Err(error(&match unsafe { ns_error.as_ref() } {
+                            Some(ns_error) => ns_error_description(ns_error),
+                            None => "Rejected".to_string(),
+                        }));
+                    }
+                    0
+                }
+                ControlValueSetter::Boolean(on) => NSInteger::from(*on),
+                ControlValueSetter::EnumValue(mode) => *mode as NSInteger,
+                _ => return Err(error("Expected Enum, Boolean or Float")),
+            };
+            if let ControlValueDescription::Enum { possible, .. } = control.description() {
+                if !possible.contains(&mode) {
+                    return Err(error("Not supported by the device"));
+                }
+            }
+            unsafe {
+                let _: () = msg_send![device, setTorchMode: mode];
+            }
+            Ok(())
+        }
+
+        /// Reports the video effects the system applies to this device (Center Stage, Portrait, Studio Light and
+        /// reactions), which the user turns on in Control Center. Effects the OS does not know about are left out.
+        ///
+        /// Center Stage can be turned on and off by the application if its control mode (0 => user, 1 => app,
+        /// 2 => cooperative) is not the user. The other effects are read only.
+        fn system_effect_controls(&self) -> Vec<CameraControl> {
+            let device = &*self.inner;
+            let device_class = class!(AVCaptureDevice);
+            let mut controls = vec![];
+
+            if responds_to(device_class, sel!(isCenterStageEnabled)) {
+                let (enabled, active, mode, supported): (bool, bool, NSInteger, bool) = unsafe {
+                    let format: *mut AnyObject = msg_send![device, activeFormat];
+                    (
+                        msg_send![device_class, isCenterStageEnabled],
+                        msg_send![device, isCenterStageActive],
+                        msg_send![device_class, centerStageControlMode],
+                        !format.is_null() && msg_send![format, isCenterStageSupported],
+                    )
+                };
+                let user_controlled = mode == 0;
+                controls.push(CameraControl::new(
+                    KnownCameraControl::Other(7),
+                    "CenterStage".to_string(),
+                    ControlValueDescription::Boolean {
+                        value: enabled,
+                        default: false,
+                    },
+                    match (supported, user_controlled) {
+                        (false, _) => vec![
+                            KnownCameraControlFlag::Disabled,
+                            KnownCameraControlFlag::ReadOnly,
+                        ],
+                        (true, true) => vec![KnownCameraControlFlag::ReadOnly],
+                        (true, false) => vec![],
+                    },
+                    active,
+                ));
+                controls.push(CameraControl::new(
+                    KnownCameraControl::Other(8),
+                    "CenterStageControlMode".to_string(),
+                    ControlValueDescription::Enum {
+                        value: mode,
+                        possible: vec![0, 1, 2],
+                        default: 0,
+                        labels: mode_labels(&[0, 1, 2], &["User", "App", "Cooperative"]),
+                    },
+                    vec![],
+                    true,
+                ));
+            }
+
+            let read_only_effect = |id: u128, name: &str, enabled: bool, active: bool| {
+                CameraControl::new(
+                    KnownCameraControl::Other(id),
+                    name.to_string(),
+                    ControlValueDescription::Boolean {
+                        value: enabled,
+                        default: false,
+                    },
+                    vec![KnownCameraControlFlag::ReadOnly],
+                    active,
+                )
+            };
+            if responds_to(device_class, sel!(isPortraitEffectEnabled)) {
+                let (enabled, active): (bool, bool) = unsafe {
+                    (
+                        msg_send![device_class, isPortraitEffectEnabled],
+                        msg_send![device, isPortraitEffectActive],
+                    )
+                };
+                controls.push(read_only_effect(9, "PortraitEffect", enabled, active));
+            }
+            if responds_to(device_class, sel!(isStudioLightEnabled)) {
+                let (enabled, active): (bool, bool) = unsafe {
+                    (
+                        msg_send![device_class, isStudioLightEnabled],
+                        msg_send![device, isStudioLightActive],
+                    )
+                };
+                controls.push(read_only_effect(10, "StudioLight", enabled, active));
+            }
+            if responds_to(device_class, sel!(reactionEffectsEnabled)) {
+                let (enabled, active): (bool, bool) = unsafe {
+                    (
+                        msg_send![device_class, reactionEffectsEnabled],
+                        msg_send![device, canPerformReactionEffects],
+                    )
+                };
+                controls.push(read_only_effect(11, "ReactionEffects", enabled, active));
+            }
 
-                    controls.push(CameraControl::new(
-                        KnownCameraControl::Other(6),
-                        "DistortionCorrection".to_string(),
-                        ControlValueDescription::Boolean {
-                            value: distortion_correction_current_value == YES,
-                            default: false,
-                        },
-                        if distortion_correction_supported == YES {
-                            vec![
-                                KnownCameraControlFlag::ReadOnly,
-                                KnownCameraControlFlag::Disabled,
-                            ]
-                        } else {
-                            vec![]
-                        },
-                        distortion_correction_supported == YES,
-                    ));
-                }
+            controls
+        }
+
+        /// Sets a control of [`system_effect_controls()`](AVCaptureDeviceWrapper::system_effect_controls), or returns
+        /// `None` if `id` is not one of them.
+        fn set_system_effect_control(
+            &self,
+            id: KnownCameraControl,
+            value: &ControlValueSetter,
+        ) -> Option<Result<(), NokhwaError>> {
+            let error = |why: &str| NokhwaError::SetPropertyError {
+                property: id.to_string(),
+                value: value.to_string(),
+                error: why.to_string(),
+            };
+            let device_class = class!(AVCaptureDevice);
+            match id {
+                KnownCameraControl::Other(7 | 8) => {}
+                KnownCameraControl::Other(9..=11) => return Some(Err(error("Read Only"))),
+                _ => return None,
+            }
+            if !responds_to(device_class, sel!(setCenterStageControlMode:)) {
+                return Some(Err(error("Control does not exist")));
             }
 
-            Ok(controls)
+            if id == KnownCameraControl::Other(8) {
+                return Some(match value.as_enum() {
+                    Some(mode @ 0..=2) => {
+                        unsafe {
+                            let _: () = msg_send![device_class, setCenterStageControlMode: *mode];
+                        }
+                        Ok(())
+                    }
+                    Some(_) => Err(error("Failed to verify value")),
+                    None => Err(error("Expected Enum")),
+                });
+            }
+            let Some(enabled) = value.as_boolean() else {
+                return Some(Err(error("Expected Boolean")));
+            };
+            let mode: NSInteger = unsafe { msg_send![device_class, centerStageControlMode] };
+            if mode == 0 {
+                return Some(Err(error(
+                    "Center Stage is controlled by the user, set the control mode first",
+                )));
+            }
+            unsafe {
+                let _: () = msg_send![device_class, setCenterStageEnabled: *enabled];
+            }
+            Some(Ok(()))
         }
 
+        /// Sets the control `id` to `value`, checking it against the cached controls, see
+        /// [`get_controls()`](AVCaptureDeviceWrapper::get_controls).
+        /// # Errors
+        /// If the control does not exist, `value` is invalid for it, or the device rejects it, this will error.
         pub fn set_control(
             &mut self,
             id: KnownCameraControl,
             value: ControlValueSetter,
         ) -> Result<(), NokhwaError> {
+            // a new mode can change which other controls are writable, e.g. the ISO can only be set in the custom exposure mode
+            let sets_mode = matches!(value, ControlValueSetter::EnumValue(_));
+            let result = self.apply_control(id, value);
+            if sets_mode && result.is_ok() {
+                self.invalidate_controls();
+            }
+            result
+        }
+
+        fn apply_control(
+            &mut self,
+            id: KnownCameraControl,
+            value: ControlValueSetter,
+        ) -> Result<(), NokhwaError> {
+            if id == KnownCameraControl::Torch {
+                return self.set_torch(&value);
+            }
+
             #[cfg(target_os = "ios")]
             {
-                let rc = self.get_controls()?;
+                let rc = self.cached_controls()?;
                 let controls = rc
                     .iter()
                     .map(|cc| (cc.control(), cc))
@@ -1233,6 +4113,14 @@ mod internal {
                 }
             }
 
+            #[cfg(target_os = "macos")]
+            if let Some(result) = self.set_macos_control(id, &value) {
+                return result;
+            }
+            if let Some(result) = self.set_system_effect_control(id, &value) {
+                return result;
+            }
+
             return Err(NokhwaError::SetPropertyError {
                 property: id.to_string(),
                 value: value.to_string(),
@@ -1258,6 +4146,16 @@ mod internal {
                 .collect::<Vec<_>>();
             a.sort_by(|a, b| a.frame_rate().cmp(&b.frame_rate()));
 
+            // report the rate the device actually streams at, rather than the top of its range
+            let fps = self.frame_rate_exact();
+            if fps > 0.0 {
+                return Ok(CameraFormat::new(
+                    Resolution::new(resolution.width as u32, resolution.height as u32),
+                    fourcc,
+                    fps.round() as u32,
+                ));
+            }
+
             if a.len() != 0 {
                 Ok(a[a.len() - 1])
             } else {
@@ -1268,6 +4166,980 @@ mod internal {
             }
         }
     }
+
+    type OSStatus = i32;
+
+    #[repr(C)]
+    struct VTDecompressionOutputCallbackRecord {
+        callback: extern "C" fn(
+            refcon: *mut c_void,
+            source_frame_refcon: *mut c_void,
+            status: OSStatus,
+            info_flags: u32,
+            image_buffer: *mut c_void,
+            presentation_time: CMTime,
+            presentation_duration: CMTime,
+        ),
+        refcon: *mut c_void,
+    }
+
+    #[link(name = "CoreMedia", kind = "framework")]
+    extern "C" {
+        fn CMVideoFormatDescriptionCreate(
+            allocator: *const c_void,
+            codec_type: u32,
+            width: i32,
+            height: i32,
+            extensions: CFDictionaryRef,
+            format_description_out: *mut *mut c_void,
+        ) -> OSStatus;
+        fn CMBlockBufferCreateWithMemoryBlock(
+            allocator: *const c_void,
+            memory_block: *mut c_void,
+            block_length: usize,
+            block_allocator: *const c_void,
+            custom_block_source: *const c_void,
+            offset_to_data: usize,
+            data_length: usize,
+            flags: u32,
+            block_buffer_out: *mut *mut c_void,
+        ) -> OSStatus;
+        fn CMBlockBufferReplaceDataBytes(
+            source_bytes: *const c_void,
+            destination_buffer: *mut c_void,
+            offset_into_destination: usize,
+            data_length: usize,
+        ) -> OSStatus;
+        fn CMSampleBufferCreateReady(
+            allocator: *const c_void,
+            data_buffer: *mut c_void,
+            format_description: *mut c_void,
+            num_samples: isize,
+            num_sample_timing_entries: isize,
+            sample_timing_array: *const c_void,
+            num_sample_size_entries: isize,
+            sample_size_array: *const usize,
+            sample_buffer_out: *mut *mut c_void,
+        ) -> OSStatus;
+    }
+
+    #[link(name = "VideoToolbox", kind = "framework")]
+    extern "C" {
+        static kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder: CFStringRef;
+        fn VTDecompressionSessionCreate(
+            allocator: *const c_void,
+            video_format_description: *mut c_void,
+            video_decoder_specification: CFDictionaryRef,
+            destination_image_buffer_attributes: CFDictionaryRef,
+            output_callback: *const VTDecompressionOutputCallbackRecord,
+            decompression_session_out: *mut *mut c_void,
+        ) -> OSStatus;
+        fn VTDecompressionSessionDecodeFrame(
+            session: *mut c_void,
+            sample_buffer: *mut c_void,
+            decode_flags: u32,
+            source_frame_refcon: *mut c_void,
+            info_flags_out: *mut u32,
+        ) -> OSStatus;
+        fn VTDecompressionSessionWaitForAsynchronousFrames(session: *mut c_void) -> OSStatus;
+        fn VTDecompressionSessionInvalidate(session: *mut c_void);
+    }
+
+    #[link(name = "CoreVideo", kind = "framework")]
+    extern "C" {
+        static kCVPixelBufferPixelFormatTypeKey: CFStringRef;
+        fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut c_void, lock_flags: u64) -> i32;
+        fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut c_void, unlock_flags: u64) -> i32;
+        fn CVPixelBufferGetIOSurface(pixel_buffer: *mut c_void) -> *mut c_void;
+        fn CVPixelBufferGetBaseAddressOfPlane(pixel_buffer: *mut c_void, plane: usize)
+            -> *const u8;
+        fn CVPixelBufferGetBytesPerRowOfPlane(pixel_buffer: *mut c_void, plane: usize) -> usize;
+        fn CVPixelBufferGetHeightOfPlane(pixel_buffer: *mut c_void, plane: usize) -> usize;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    // 'jpeg', which is what CoreMedia calls (M)JPEG
+    const CODEC_TYPE_JPEG: u32 = u32::from_be_bytes(*b"jpeg");
+    // kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange, which is NV12
+    const PIXEL_FORMAT_NV12: i32 = i32::from_be_bytes(*b"420v");
+    const LOCK_READ_ONLY: u64 = 1;
+    const BLOCK_BUFFER_ASSURE_MEMORY_NOW: u32 = 1;
+
+    /// Decodes MJPEG with a `VideoToolbox` decompression session, which uses the hardware JPEG decoder where the
+    /// Mac has one. Register it with [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+    pub struct VideoToolboxJpegDecoder {
+        session: *mut c_void,
+        format_description: *mut c_void,
+        resolution: Option<Resolution>,
+    }
+
+    // the session is only ever used by the thread holding the decoder
+    unsafe impl Send for VideoToolboxJpegDecoder {}
+
+    impl VideoToolboxJpegDecoder {
+        /// Creates a decoder. The decompression session is only created once the resolution of the frames is known.
+        #[must_use]
+        pub fn new() -> Self {
+            VideoToolboxJpegDecoder {
+                session: std::ptr::null_mut(),
+                format_description: std::ptr::null_mut(),
+                resolution: None,
+            }
+        }
+
+        /// Creates a decoder for [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+        /// # Errors
+        /// This does not error, it returns a [`Result`] to be a [`HardwareDecoderFactory`](nokhwa_core::conversion::HardwareDecoderFactory).
+        pub fn create() -> Result<Box<dyn HardwareDecoder>, NokhwaError> {
+            Ok(Box::new(Self::new()))
+        }
+
+        fn release(&mut self) {
+            unsafe {
+                if !self.session.is_null() {
+                    VTDecompressionSessionInvalidate(self.session);
+                    CFRelease(self.session);
+                }
+                if !self.format_description.is_null() {
+                    CFRelease(self.format_description);
+                }
+            }
+            self.session = std::ptr::null_mut();
+            self.format_description = std::ptr::null_mut();
+            self.resolution = None;
+        }
+
+        fn configure(&mut self, resolution: Resolution) -> Result<(), NokhwaError> {
+            self.release();
+            let error = |function: &str, status: OSStatus| {
+                NokhwaError::OpenStreamError(format!("{function} failed: OSStatus {status}"))
+            };
+            let width = i32::try_from(resolution.width())
+                .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?;
+            let height = i32::try_from(resolution.height())
+                .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?;
+
+            let status = unsafe {
+                CMVideoFormatDescriptionCreate(
+                    std::ptr::null(),
+                    CODEC_TYPE_JPEG,
+                    width,
+                    height,
+                    std::ptr::null(),
+                    &mut self.format_description,
+                )
+            };
+            if status != 0 {
+                return Err(error("CMVideoFormatDescriptionCreate", status));
+            }
+
+            let decoder_specification = unsafe {
+                CFDictionary::from_CFType_pairs(&[(
+                    CFString::wrap_under_get_rule(
+                        kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder,
+                    )
+                    .as_CFType(),
+                    CFBoolean::true_value().as_CFType(),
+                )])
+            };
+            let image_buffer_attributes = unsafe {
+                CFDictionary::from_CFType_pairs(&[(
+                    CFString::wrap_under_get_rule(kCVPixelBufferPixelFormatTypeKey).as_CFType(),
+                    CFNumber::from(PIXEL_FORMAT_NV12).as_CFType(),
+                )])
+            };
+            let callback = VTDecompressionOutputCallbackRecord {
+                callback: decompression_output,
+                refcon: std::ptr::null_mut(),
+            };
+            let status = unsafe {
+                VTDecompressionSessionCreate(
+                    std::ptr::null(),
+                    self.format_description,
+                    decoder_specification.as_concrete_TypeRef(),
+                    image_buffer_attributes.as_concrete_TypeRef(),
+                    &callback,
+                    &mut self.session,
+                )
+            };
+            if status != 0 {
+                self.release();
+                return Err(error("VTDecompressionSessionCreate", status));
+            }
+            self.resolution = Some(resolution);
+            Ok(())
+        }
+
+        fn sample_buffer(&self, data: &[u8]) -> Result<*mut c_void, NokhwaError> {
+            let error = |function: &str, status: OSStatus| {
+                NokhwaError::ReadFrameError(format!("{function} failed: OSStatus {status}"))
+            };
+            let mut block_buffer = std::ptr::null_mut();
+            unsafe {
+                // let CoreMedia allocate the block, the frame is copied into it
+                let status = CMBlockBufferCreateWithMemoryBlock(
+                    std::ptr::null(),
+                    std::ptr::null_mut(),
+                    data.len(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    0,
+                    data.len(),
+                    BLOCK_BUFFER_ASSURE_MEMORY_NOW,
+                    &mut block_buffer,
+                );
+                if status != 0 {
+                    return Err(error("CMBlockBufferCreateWithMemoryBlock", status));
+                }
+                let status = CMBlockBufferReplaceDataBytes(
+                    data.as_ptr().cast(),
+                    block_buffer,
+                    0,
+                    data.len(),
+                );
+                if status != 0 {
+                    CFRelease(block_buffer);
+                    return Err(error("CMBlockBufferReplaceDataBytes", status));
+                }
+
+                let mut sample_buffer = std::ptr::null_mut();
+                let sample_size = data.len();
+                let status = CMSampleBufferCreateReady(
+                    std::ptr::null(),
+                    block_buffer,
+                    self.format_description,
+                    1,
+                    0,
+                    std::ptr::null(),
+                    1,
+                    &sample_size,
+                    &mut sample_buffer,
+                );
+                // the sample buffer holds its own reference to the block
+                CFRelease(block_buffer);
+                if status != 0 {
+                    return Err(error("CMSampleBufferCreateReady", status));
+                }
+                Ok(sample_buffer)
+            }
+        }
+    }
+
+    impl Default for VideoToolboxJpegDecoder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Drop for VideoToolboxJpegDecoder {
+        fn drop(&mut self) {
+            self.release();
+        }
+    }
+
+    /// Where the output callback puts the decoded frame of one [`VTDecompressionSessionDecodeFrame`] call.
+    struct DecodedFrame {
+        resolution: Resolution,
+        frame: Option<Result<Vec<u8>, OSStatus>>,
+    }
+
+    extern "C" fn decompression_output(
+        _refcon: *mut c_void,
+        source_frame_refcon: *mut c_void,
+        status: OSStatus,
+        _info_flags: u32,
+        image_buffer: *mut c_void,
+        _presentation_time: CMTime,
+        _presentation_duration: CMTime,
+    ) {
+        let Some(decoded) = (unsafe { source_frame_refcon.cast::<DecodedFrame>().as_mut() }) else {
+            return;
+        };
+        if status != 0 || image_buffer.is_null() {
+            decoded.frame = Some(Err(status));
+            return;
+        }
+
+        let width = decoded.resolution.width() as usize;
+        let height = decoded.resolution.height() as usize;
+        // the luma plane, then the interleaved chroma plane, without the padding CoreVideo puts after each row
+        let planes = [(width, height), (width.div_ceil(2) * 2, height.div_ceil(2))];
+        let mut frame = Vec::with_capacity(planes.iter().map(|(len, rows)| len * rows).sum());
+        unsafe {
+            CVPixelBufferLockBaseAddress(image_buffer, LOCK_READ_ONLY);
+            for (plane, (row_len, rows)) in planes.into_iter().enumerate() {
+                let base_address = CVPixelBufferGetBaseAddressOfPlane(image_buffer, plane);
+                let stride = CVPixelBufferGetBytesPerRowOfPlane(image_buffer, plane);
+                let plane_rows = CVPixelBufferGetHeightOfPlane(image_buffer, plane);
+                if base_address.is_null() || stride < row_len {
+                    continue;
+                }
+                for row in 0..rows.min(plane_rows) {
+                    frame.extend_from_slice(std::slice::from_raw_parts(
+                        base_address.add(row * stride),
+                        row_len,
+                    ));
+                }
+            }
+            CVPixelBufferUnlockBaseAddress(image_buffer, LOCK_READ_ONLY);
+        }
+        decoded.frame = Some(Ok(frame));
+    }
+
+    impl HardwareDecoder for VideoToolboxJpegDecoder {
+        fn name(&self) -> String {
+            "VideoToolbox".to_string()
+        }
+
+        fn decode(
+            &mut self,
+            resolution: Resolution,
+            data: &[u8],
+        ) -> Result<(FourCC, Vec<u8>), NokhwaError> {
+            if self.resolution != Some(resolution) {
+                self.configure(resolution)?;
+            }
+
+            let sample_buffer = self.sample_buffer(data)?;
+            let mut decoded = DecodedFrame {
+                resolution,
+                frame: None,
+            };
+            let status = unsafe {
+                // without kVTDecodeFrame_EnableAsynchronousDecompression, the callback runs before this returns
+                let status = VTDecompressionSessionDecodeFrame(
+                    self.session,
+                    sample_buffer,
+                    0,
+                    (&raw mut decoded).cast(),
+                    std::ptr::null_mut(),
+                );
+                VTDecompressionSessionWaitForAsynchronousFrames(self.session);
+                CFRelease(sample_buffer);
+                status
+            };
+            if status != 0 {
+                return Err(NokhwaError::ReadFrameError(format!(
+                    "VTDecompressionSessionDecodeFrame failed: OSStatus {status}"
+                )));
+            }
+
+            match decoded.frame {
+                Some(Ok(frame)) => Ok((NV12, frame)),
+                Some(Err(status)) => Err(NokhwaError::ReadFrameError(format!(
+                    "VideoToolbox could not decode the frame: OSStatus {status}"
+                ))),
+                None => Err(NokhwaError::ReadFrameError(
+                    "VideoToolbox did not output a frame".to_string(),
+                )),
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    pub use cmio::CoreMediaIOVirtualCamera;
+
+    /// Feeding the sink stream of a `CoreMediaIO` camera extension, which is macOS only.
+    #[cfg(target_os = "macos")]
+    mod cmio {
+        use super::*;
+        use nokhwa_core::types::FrameRate;
+
+        #[repr(C)]
+        struct CMIOObjectPropertyAddress {
+            selector: u32,
+            scope: u32,
+            element: u32,
+        }
+
+        #[repr(C)]
+        struct CMSampleTimingInfo {
+            duration: CMTime,
+            presentation_time_stamp: CMTime,
+            decode_time_stamp: CMTime,
+        }
+
+        #[repr(C)]
+        struct CMVideoDimensions {
+            width: i32,
+            height: i32,
+        }
+
+        #[link(name = "CoreMediaIO", kind = "framework")]
+        extern "C" {
+            fn CMIOObjectGetPropertyDataSize(
+                object_id: u32,
+                address: *const CMIOObjectPropertyAddress,
+                qualifier_data_size: u32,
+                qualifier_data: *const c_void,
+                data_size: *mut u32,
+            ) -> OSStatus;
+            fn CMIOObjectGetPropertyData(
+                object_id: u32,
+                address: *const CMIOObjectPropertyAddress,
+                qualifier_data_size: u32,
+                qualifier_data: *const c_void,
+                data_size: u32,
+                data_used: *mut u32,
+                data: *mut c_void,
+            ) -> OSStatus;
+            fn CMIOStreamCopyBufferQueue(
+                stream_id: u32,
+                queue_altered_proc: Option<extern "C" fn(u32, *mut c_void, *mut c_void)>,
+                queue_altered_ref_con: *mut c_void,
+                queue: *mut *mut c_void,
+            ) -> OSStatus;
+            fn CMIODeviceStartStream(device_id: u32, stream_id: u32) -> OSStatus;
+            fn CMIODeviceStopStream(device_id: u32, stream_id: u32) -> OSStatus;
+        }
+
+        #[link(name = "CoreMedia", kind = "framework")]
+        extern "C" {
+            fn CMSimpleQueueEnqueue(queue: *mut c_void, element: *const c_void) -> OSStatus;
+            fn CMSimpleQueueGetCount(queue: *mut c_void) -> i32;
+            fn CMSimpleQueueGetCapacity(queue: *mut c_void) -> i32;
+            fn CMFormatDescriptionGetMediaSubType(description: *const c_void) -> u32;
+            fn CMVideoFormatDescriptionGetDimensions(
+                description: *const c_void,
+            ) -> CMVideoDimensions;
+            fn CMVideoFormatDescriptionCreateForImageBuffer(
+                allocator: *const c_void,
+                image_buffer: *mut c_void,
+                format_description_out: *mut *mut c_void,
+            ) -> OSStatus;
+            fn CMSampleBufferCreateReadyWithImageBuffer(
+                allocator: *const c_void,
+                image_buffer: *mut c_void,
+                format_description: *mut c_void,
+                sample_timing: *const CMSampleTimingInfo,
+                sample_buffer_out: *mut *mut c_void,
+            ) -> OSStatus;
+            fn CMClockGetHostTimeClock() -> *mut c_void;
+            fn CMClockGetTime(clock: *mut c_void) -> CMTime;
+        }
+
+        #[link(name = "CoreVideo", kind = "framework")]
+        extern "C" {
+            static kCVPixelBufferIOSurfacePropertiesKey: CFStringRef;
+            fn CVPixelBufferCreate(
+                allocator: *const c_void,
+                width: usize,
+                height: usize,
+                pixel_format_type: u32,
+                attributes: CFDictionaryRef,
+                pixel_buffer_out: *mut *mut c_void,
+            ) -> i32;
+            fn CVPixelBufferGetPlaneCount(pixel_buffer: *mut c_void) -> usize;
+            fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut c_void) -> *mut u8;
+            fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut c_void) -> usize;
+        }
+
+        // kCMIOObjectSystemObject
+        const SYSTEM_OBJECT: u32 = 1;
+        // kCMIOObjectPropertyScopeGlobal and kCMIOObjectPropertyElementMain
+        const SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+        const ELEMENT_MAIN: u32 = 0;
+        // kCMIOHardwarePropertyDevices
+        const HARDWARE_DEVICES: u32 = u32::from_be_bytes(*b"dev#");
+        // kCMIOObjectPropertyName
+        const OBJECT_NAME: u32 = u32::from_be_bytes(*b"lnam");
+        // kCMIODevicePropertyDeviceUID
+        const DEVICE_UID: u32 = u32::from_be_bytes(*b"uid ");
+        // kCMIODevicePropertyStreams
+        const DEVICE_STREAMS: u32 = u32::from_be_bytes(*b"stm#");
+        // kCMIOStreamPropertyDirection, which is 1 for streams that take frames in
+        const STREAM_DIRECTION: u32 = u32::from_be_bytes(*b"sdir");
+        const DIRECTION_SINK: u32 = 1;
+        // kCMIOStreamPropertyFormatDescription
+        const STREAM_FORMAT_DESCRIPTION: u32 = u32::from_be_bytes(*b"pft ");
+
+        /// Reads the property `selector` of a `CoreMediaIO` object as an array of `T`.
+        fn property<T>(object: u32, selector: u32) -> Result<Vec<T>, OSStatus> {
+            let address = CMIOObjectPropertyAddress {
+                selector,
+                scope: SCOPE_GLOBAL,
+                element: ELEMENT_MAIN,
+            };
+            let mut size = 0;
+            let status = unsafe {
+                CMIOObjectGetPropertyDataSize(object, &address, 0, std::ptr::null(), &mut size)
+            };
+            if status != 0 {
+                return Err(status);
+            }
+            let mut values = Vec::<T>::with_capacity(size as usize / std::mem::size_of::<T>());
+            let mut used = 0;
+            let status = unsafe {
+                CMIOObjectGetPropertyData(
+                    object,
+                    &address,
+                    0,
+                    std::ptr::null(),
+                    size,
+                    &mut used,
+                    values.as_mut_ptr().cast(),
+                )
+            };
+            if status != 0 {
+                return Err(status);
+            }
+            unsafe { values.set_len(used as usize / std::mem::size_of::<T>()) };
+            Ok(values)
+        }
+
+        /// Reads a string property of a `CoreMediaIO` object, or an empty string if it has none.
+        fn string_property(object: u32, selector: u32) -> String {
+            property::<CFStringRef>(object, selector)
+                .ok()
+                .and_then(|strings| strings.first().copied())
+                .filter(|string| !string.is_null())
+                .map(|string| unsafe { CFString::wrap_under_create_rule(string) }.to_string())
+                .unwrap_or_default()
+        }
+
+        /// A `CoreMediaIO` device with a sink stream: (device, UID, name, sink stream).
+        fn sink_devices() -> Result<Vec<(u32, String, String, u32)>, OSStatus> {
+            Ok(property::<u32>(SYSTEM_OBJECT, HARDWARE_DEVICES)?
+                .into_iter()
+                .filter_map(|device| {
+                    let sink = property::<u32>(device, DEVICE_STREAMS)
+                        .ok()?
+                        .into_iter()
+                        .find(|stream| {
+                            property::<u32>(*stream, STREAM_DIRECTION)
+                                .is_ok_and(|direction| direction.first() == Some(&DIRECTION_SINK))
+                        })?;
+                    Some((
+                        device,
+                        string_property(device, DEVICE_UID),
+                        string_property(device, OBJECT_NAME),
+                        sink,
+                    ))
+                })
+                .collect())
+        }
+
+        /// The [`CameraFormat`] a sink stream takes frames in, with `frame_rate`.
+        fn stream_format(stream: u32, frame_rate: FrameRate) -> Result<CameraFormat, OSStatus> {
+            let description = property::<*const c_void>(stream, STREAM_FORMAT_DESCRIPTION)?
+                .first()
+                .copied()
+                .filter(|description| !description.is_null())
+                .ok_or(-1)?;
+            let (subtype, dimensions) = unsafe {
+                let subtype = CMFormatDescriptionGetMediaSubType(description);
+                let dimensions = CMVideoFormatDescriptionGetDimensions(description);
+                CFRelease(description);
+                (subtype, dimensions)
+            };
+            Ok(CameraFormat::new_exact(
+                Resolution::new(dimensions.width as u32, dimensions.height as u32),
+                raw_fcc_to_fourcc(subtype),
+                frame_rate,
+            ))
+        }
+
+        /// Whether frames in `frame` can be put into a stream taking `stream`, which differ for `NV12`.
+        fn same_fourcc(frame: FourCC, stream: FourCC) -> bool {
+            let normalize = |fourcc| if fourcc == NV12 { YUV420 } else { fourcc };
+            normalize(frame) == normalize(stream)
+        }
+
+        /// A virtual camera sink that feeds the sink stream of a `CoreMediaIO` camera extension, such as the one of
+        /// [OBS Virtual Camera](https://obsproject.com/kb/virtual-camera-guide), which applications then open like any
+        /// other camera.
+        /// To see what this does, please see [`VirtualBackendTrait`].
+        /// # Quirks
+        /// - The camera extension has to be installed and approved in System Settings. For OBS, start and stop its virtual camera once.
+        /// - The extension decides the resolution and [`FourCC`] (usually `BGRA` or [`NV12`]) the frames have to be in, see [`camera_format()`](VirtualBackendTrait::camera_format). Only the frame rate can be changed.
+        /// - Frames are dropped if the extension falls behind.
+        pub struct CoreMediaIOVirtualCamera {
+            camera_format: CameraFormat,
+            device: u32,
+            stream: u32,
+            queue: *mut c_void,
+            pixel_format: u32,
+        }
+
+        // the queue is only ever written to by the thread holding the sink
+        unsafe impl Send for CoreMediaIOVirtualCamera {}
+
+        impl CoreMediaIOVirtualCamera {
+            /// Starts feeding the camera extension at `index` with frames in `format`. A [`CameraIndex::Index`] refers
+            /// to the order of the `CoreMediaIO` devices that have a sink stream, while a [`CameraIndex::String`] is the
+            /// UID or name of the device, e.g. `OBS Virtual Camera`.
+            /// # Errors
+            /// This function will error if there is no such camera extension, the resolution or [`FourCC`] of `format`
+            /// is not the one the extension takes, or the stream cannot be started.
+            pub fn new(index: &CameraIndex, format: CameraFormat) -> Result<Self, NokhwaError> {
+                let error = |error: String| NokhwaError::OpenDeviceError(index.to_string(), error);
+                let status_error = |function: &str, status: OSStatus| {
+                    error(format!("{function} failed: OSStatus {status}"))
+                };
+                let sinks = sink_devices()
+                    .map_err(|status| status_error("CMIOObjectGetPropertyData", status))?;
+                let (device, _, _, stream) = match index {
+                    CameraIndex::Index(idx) => sinks.into_iter().nth(*idx as usize),
+                    CameraIndex::String(id) => sinks
+                        .into_iter()
+                        .find(|(_, uid, name, _)| uid == id || name == id),
+                }
+                .ok_or_else(|| error("No camera extension with a sink stream found".to_string()))?;
+
+                let stream_format =
+                    stream_format(stream, format.exact_frame_rate()).map_err(|status| {
+                        status_error("kCMIOStreamPropertyFormatDescription", status)
+                    })?;
+                if format.resolution() != stream_format.resolution()
+                    || !same_fourcc(format.format(), stream_format.format())
+                {
+                    return Err(error(format!(
+                        "The camera extension takes {stream_format}, not {format}"
+                    )));
+                }
+
+                let mut queue = std::ptr::null_mut();
+                let status = unsafe {
+                    CMIOStreamCopyBufferQueue(stream, None, std::ptr::null_mut(), &mut queue)
+                };
+                if status != 0 {
+                    return Err(status_error("CMIOStreamCopyBufferQueue", status));
+                }
+                let status = unsafe { CMIODeviceStartStream(device, stream) };
+                if status != 0 {
+                    unsafe { CFRelease(queue) };
+                    return Err(status_error("CMIODeviceStartStream", status));
+                }
+                Ok(CoreMediaIOVirtualCamera {
+                    camera_format: stream_format,
+                    device,
+                    stream,
+                    queue,
+                    pixel_format: u32::from(stream_format.format()),
+                })
+            }
+
+            /// Copies `frame` into a new `CVPixelBuffer` in the format of the stream.
+            fn pixel_buffer(&self, frame: &FrameBuffer) -> Result<*mut c_void, NokhwaError> {
+                let error = |function: &str, status: i32| {
+                    NokhwaError::WriteFrameError(format!("{function} failed: OSStatus {status}"))
+                };
+                let planes = frame.planes()?;
+                let attributes = unsafe {
+                    CFDictionary::from_CFType_pairs(&[(
+                        CFString::wrap_under_get_rule(kCVPixelBufferIOSurfacePropertiesKey)
+                            .as_CFType(),
+                        CFDictionary::<CFType, CFType>::from_CFType_pairs(&[]).as_CFType(),
+                    )])
+                };
+                let mut pixel_buffer = std::ptr::null_mut();
+                unsafe {
+                    let status = CVPixelBufferCreate(
+                        std::ptr::null(),
+                        frame.width() as usize,
+                        frame.height() as usize,
+                        self.pixel_format,
+                        attributes.as_concrete_TypeRef(),
+                        &mut pixel_buffer,
+                    );
+                    if status != 0 {
+                        return Err(error("CVPixelBufferCreate", status));
+                    }
+                    let status = CVPixelBufferLockBaseAddress(pixel_buffer, 0);
+                    if status != 0 {
+                        CFRelease(pixel_buffer);
+                        return Err(error("CVPixelBufferLockBaseAddress", status));
+                    }
+                    let plane_count = CVPixelBufferGetPlaneCount(pixel_buffer);
+                    for (index, plane) in planes.iter().enumerate() {
+                        let (base, stride) = if plane_count == 0 {
+                            (
+                                CVPixelBufferGetBaseAddress(pixel_buffer),
+                                CVPixelBufferGetBytesPerRow(pixel_buffer),
+                            )
+                        } else if index < plane_count {
+                            (
+                                CVPixelBufferGetBaseAddressOfPlane(pixel_buffer, index).cast_mut(),
+                                CVPixelBufferGetBytesPerRowOfPlane(pixel_buffer, index),
+                            )
+                        } else {
+                            break;
+                        };
+                        if base.is_null() {
+                            continue;
+                        }
+                        for (row_index, row) in (0..plane.height())
+                            .filter_map(|row_index| plane.row(row_index))
+                            .enumerate()
+                        {
+                            std::ptr::copy_nonoverlapping(
+                                row.as_ptr(),
+                                base.add(row_index * stride),
+                                row.len().min(stride),
+                            );
+                        }
+                        if plane_count == 0 {
+                            break;
+                        }
+                    }
+                    CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+                }
+                Ok(pixel_buffer)
+            }
+        }
+
+        impl VirtualBackendTrait for CoreMediaIOVirtualCamera {
+            fn backend(&self) -> ApiBackend {
+                ApiBackend::AVFoundation
+            }
+
+            fn camera_format(&self) -> CameraFormat {
+                self.camera_format
+            }
+
+            fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+                if new_fmt.resolution() != self.camera_format.resolution()
+                    || !same_fourcc(new_fmt.format(), self.camera_format.format())
+                {
+                    return Err(NokhwaError::SetPropertyError {
+                        property: "CameraFormat".to_string(),
+                        value: new_fmt.to_string(),
+                        error: format!("The camera extension takes {}", self.camera_format),
+                    });
+                }
+                self.camera_format = CameraFormat::new_exact(
+                    self.camera_format.resolution(),
+                    self.camera_format.format(),
+                    new_fmt.exact_frame_rate(),
+                );
+                Ok(())
+            }
+
+            fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError> {
+                if frame.resolution() != self.camera_format.resolution()
+                    || !same_fourcc(frame.source_frame_format(), self.camera_format.format())
+                {
+                    return Err(NokhwaError::WriteFrameError(format!(
+                        "Frame {} {} does not match device format {}",
+                        frame.resolution(),
+                        frame.source_frame_format(),
+                        self.camera_format
+                    )));
+                }
+                if unsafe {
+                    CMSimpleQueueGetCount(self.queue) >= CMSimpleQueueGetCapacity(self.queue)
+                } {
+                    return Ok(());
+                }
+                let error = |function: &str, status: OSStatus| {
+                    NokhwaError::WriteFrameError(format!("{function} failed: OSStatus {status}"))
+                };
+
+                let pixel_buffer = self.pixel_buffer(frame)?;
+                let frame_rate = self.camera_format.exact_frame_rate();
+                unsafe {
+                    let mut format_description = std::ptr::null_mut();
+                    let status = CMVideoFormatDescriptionCreateForImageBuffer(
+                        std::ptr::null(),
+                        pixel_buffer,
+                        &mut format_description,
+                    );
+                    if status != 0 {
+                        CFRelease(pixel_buffer);
+                        return Err(error(
+                            "CMVideoFormatDescriptionCreateForImageBuffer",
+                            status,
+                        ));
+                    }
+                    let timing = CMSampleTimingInfo {
+                        duration: CMTime {
+                            value: i64::from(frame_rate.denominator()),
+                            timescale: frame_rate.numerator() as i32,
+                            // kCMTimeFlags_Valid
+                            flags: 1,
+                            epoch: 0,
+                        },
+                        presentation_time_stamp: CMClockGetTime(CMClockGetHostTimeClock()),
+                        // kCMTimeInvalid
+                        decode_time_stamp: CMTime {
+                            value: 0,
+                            timescale: 0,
+                            flags: 0,
+                            epoch: 0,
+                        },
+                    };
+                    let mut sample_buffer = std::ptr::null_mut();
+                    let status = CMSampleBufferCreateReadyWithImageBuffer(
+                        std::ptr::null(),
+                        pixel_buffer,
+                        format_description,
+                        &timing,
+                        &mut sample_buffer,
+                    );
+                    // the sample buffer holds its own references to both
+                    CFRelease(format_description);
+                    CFRelease(pixel_buffer);
+                    if status != 0 {
+                        return Err(error("CMSampleBufferCreateReadyWithImageBuffer", status));
+                    }
+                    // the extension releases the sample buffer once it has dequeued it
+                    let status = CMSimpleQueueEnqueue(self.queue, sample_buffer);
+                    if status != 0 {
+                        CFRelease(sample_buffer);
+                        return Err(error("CMSimpleQueueEnqueue", status));
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        impl Drop for CoreMediaIOVirtualCamera {
+            fn drop(&mut self) {
+                unsafe {
+                    CMIODeviceStopStream(self.device, self.stream);
+                    CFRelease(self.queue);
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn nv12_fits_video_range_streams() {
+                assert!(same_fourcc(NV12, YUV420));
+                assert!(same_fourcc(YUV420, NV12));
+                assert!(same_fourcc(NV12, NV12));
+                assert!(same_fourcc(FourCC(*b"BGRA"), FourCC(*b"BGRA")));
+                assert!(!same_fourcc(NV12, FourCC(*b"BGRA")));
+                assert!(!same_fourcc(NV12, FourCC(*b"420f")));
+            }
+        }
+    }
+
+    extern "C" {
+        fn pthread_set_qos_class_self_np(qos_class: u32, relative_priority: i32) -> i32;
+    }
+
+    // from `sys/qos.h`
+    const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+    const QOS_CLASS_USER_INITIATED: u32 = 0x19;
+    const QOS_CLASS_DEFAULT: u32 = 0x15;
+
+    /// Sets the quality of service class of the calling thread from a [`ThreadPriority`].
+    /// # Errors
+    /// If the system refuses the class, this will error.
+    pub fn set_current_thread_priority(priority: ThreadPriority) -> Result<(), NokhwaError> {
+        let qos_class = match priority {
+            ThreadPriority::Normal => QOS_CLASS_DEFAULT,
+            ThreadPriority::High => QOS_CLASS_USER_INITIATED,
+            ThreadPriority::RealTime => QOS_CLASS_USER_INTERACTIVE,
+        };
+        match unsafe { pthread_set_qos_class_self_np(qos_class, 0) } {
+            0 => Ok(()),
+            errno => Err(NokhwaError::SetPropertyError {
+                property: "Thread priority".to_string(),
+                value: priority.to_string(),
+                error: std::io::Error::from_raw_os_error(errno).to_string(),
+            }
+            .with_os_error(PlatformError::Errno(errno))),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn pixel_format_types_map_to_fourccs() {
+            assert_eq!(raw_fcc_to_fourcc(u32::from_be_bytes(*b"420v")), YUV420);
+            assert_eq!(
+                raw_fcc_to_fourcc(u32::from_be_bytes(*b"yuvs")),
+                FourCC(*b"yuvs")
+            );
+            assert_eq!(
+                raw_fcc_to_fourcc(u32::from_be_bytes(*b"BGRA")),
+                FourCC(*b"BGRA")
+            );
+            assert_eq!(
+                raw_fcc_to_fourcc(OSType::from_be_bytes(PIXEL_FORMAT_NV12.to_be_bytes())),
+                YUV420
+            );
+        }
+
+        #[test]
+        fn frame_durations_keep_fractional_frame_rates() {
+            let duration = frame_duration(29.97);
+            assert_eq!((duration.value, duration.timescale), (1000, 29970));
+            assert_eq!(
+                cmtime_to_duration(duration),
+                Some(Duration::from_nanos(33_366_700))
+            );
+            let duration = frame_duration(30.0);
+            assert_eq!((duration.value, duration.timescale), (1000, 30000));
+            assert_eq!(
+                cmtime_to_duration(duration),
+                Some(Duration::from_nanos(33_333_333))
+            );
+        }
+
+        #[test]
+        fn invalid_times_have_no_duration() {
+            let mut time = frame_duration(30.0);
+            time.flags = 0;
+            assert_eq!(cmtime_to_duration(time), None);
+            let mut time = frame_duration(30.0);
+            time.timescale = 0;
+            assert_eq!(cmtime_to_duration(time), None);
+            let mut time = frame_duration(30.0);
+            time.value = -1;
+            assert_eq!(cmtime_to_duration(time), None);
+        }
+
+        #[test]
+        fn modes_are_labelled_by_their_raw_value() {
+            assert_eq!(
+                mode_labels(&[0, 2], &["Locked", "Auto", "Continuous"]),
+                ["Locked", "Continuous"]
+            );
+            // modes newer than the names fall back to their number
+            assert_eq!(mode_labels(&[1, 3, -1], &["Off", "On"]), ["On", "3", "-1"]);
+            assert!(mode_labels(&[], &["Off"]).is_empty());
+        }
+
+        #[test]
+        fn observed_key_paths_map_to_controls() {
+            let control = |key_path: &str| {
+                CONTROL_KEY_PATHS
+                    .iter()
+                    .find(|(path, _)| *path == key_path)
+                    .map(|(_, control)| *control)
+            };
+            assert_eq!(control("videoZoomFactor"), Some(KnownCameraControl::Zoom));
+            assert_eq!(control("focusMode"), Some(KnownCameraControl::Focus));
+            assert_eq!(control("lensPosition"), Some(KnownCameraControl::Focus));
+            assert_eq!(control("exposureMode"), Some(KnownCameraControl::Exposure));
+            assert_eq!(
+                control("whiteBalanceMode"),
+                Some(KnownCameraControl::WhiteBalance)
+            );
+            assert_eq!(control("torchMode"), Some(KnownCameraControl::Torch));
+            assert_eq!(
+                control("lowLightBoostEnabled"),
+                Some(KnownCameraControl::BacklightComp)
+            );
+            for (index, (key_path, _)) in CONTROL_KEY_PATHS.iter().enumerate() {
+                assert!(CONTROL_KEY_PATHS[index + 1..]
+                    .iter()
+                    .all(|(other, _)| other != key_path));
+            }
+        }
+    }
 }
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
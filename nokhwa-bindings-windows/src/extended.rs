@@ -0,0 +1,154 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Extended camera controls, set through `KSPROPERTYSETID_ExtendedCameraControl`. These are the controls of
+//! Windows Studio Effects and similar driver features, which `IAMCameraControl` and `IAMVideoProcAmp` do not cover.
+
+use nokhwa_core::types::KnownCameraControl;
+
+/// The upper 96 bits of `KSPROPERTYSETID_ExtendedCameraControl`. The [`KnownCameraControl::Other`] of an extended
+/// control is this with the property ID in the lower 32 bits.
+pub const EXTENDED_CONTROL_SET: u128 = 0x1CB7_9112_C0D2_4213_9CA6_CD4F_0000_0000;
+
+pub(crate) const EYE_GAZE_CORRECTION_ID: u32 = 40;
+pub(crate) const BACKGROUND_SEGMENTATION_ID: u32 = 41;
+pub(crate) const DIGITAL_WINDOW_ID: u32 = 43;
+
+/// `KSPROPERTY_CAMERACONTROL_EXTENDED_EYEGAZECORRECTION`, see [`EyeGazeCorrection`].
+pub const EYE_GAZE_CORRECTION: KnownCameraControl = extended_control(EYE_GAZE_CORRECTION_ID);
+/// `KSPROPERTY_CAMERACONTROL_EXTENDED_BACKGROUNDSEGMENTATION`, see [`BackgroundSegmentation`].
+pub const BACKGROUND_SEGMENTATION: KnownCameraControl =
+    extended_control(BACKGROUND_SEGMENTATION_ID);
+/// `KSPROPERTY_CAMERACONTROL_EXTENDED_DIGITALWINDOW`, see [`DigitalWindow`]. As a [`KnownCameraControl`], its value
+/// is only the mode: 0 for [`DigitalWindow::Manual`] and 1 for [`DigitalWindow::AutoFaceFraming`].
+pub const DIGITAL_WINDOW: KnownCameraControl = extended_control(DIGITAL_WINDOW_ID);
+
+// u128::from is not const
+#[allow(clippy::cast_lossless)]
+const fn extended_control(id: u32) -> KnownCameraControl {
+    KnownCameraControl::Other(EXTENDED_CONTROL_SET | id as u128)
+}
+
+/// All extended controls `nokhwa` knows about.
+#[must_use]
+pub const fn extended_camera_controls() -> [KnownCameraControl; 3] {
+    [EYE_GAZE_CORRECTION, BACKGROUND_SEGMENTATION, DIGITAL_WINDOW]
+}
+
+/// Gets the property ID of an extended control, or `None` if `control` is not one.
+#[must_use]
+pub fn extended_property_id(control: KnownCameraControl) -> Option<u32> {
+    match control {
+        KnownCameraControl::Other(id) if id & !u128::from(u32::MAX) == EXTENDED_CONTROL_SET => {
+            Some(id as u32)
+        }
+        _ => None,
+    }
+}
+
+/// Eye gaze correction, which makes the user look like they are looking at the camera.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EyeGazeCorrection {
+    #[default]
+    Off,
+    On,
+    /// Stronger correction, for when the user is reading, e.g. from a script.
+    Stare,
+}
+
+// only used by the Windows bindings
+#[cfg_attr(any(not(windows), feature = "docs-only"), allow(dead_code))]
+impl EyeGazeCorrection {
+    pub(crate) fn flags(self) -> u64 {
+        match self {
+            EyeGazeCorrection::Off => 0,
+            EyeGazeCorrection::On => 1,
+            EyeGazeCorrection::Stare => 1 | 2,
+        }
+    }
+
+    pub(crate) fn from_flags(flags: u64) -> Self {
+        if flags & 2 != 0 {
+            EyeGazeCorrection::Stare
+        } else if flags & 1 != 0 {
+            EyeGazeCorrection::On
+        } else {
+            EyeGazeCorrection::Off
+        }
+    }
+}
+
+/// What the camera does with the background behind the user.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BackgroundSegmentation {
+    #[default]
+    Off,
+    /// Blurs the background.
+    Blur,
+    /// Blurs the background less, like a lens with a shallow depth of field would.
+    ShallowFocus,
+    /// Leaves the frame as it is, and attaches a mask of the background to it as metadata.
+    Mask,
+}
+
+// only used by the Windows bindings
+#[cfg_attr(any(not(windows), feature = "docs-only"), allow(dead_code))]
+impl BackgroundSegmentation {
+    pub(crate) fn flags(self) -> u64 {
+        match self {
+            BackgroundSegmentation::Off => 0,
+            BackgroundSegmentation::Blur => 1,
+            BackgroundSegmentation::ShallowFocus => 1 | 4,
+            BackgroundSegmentation::Mask => 2,
+        }
+    }
+
+    pub(crate) fn from_flags(flags: u64) -> Self {
+        if flags & 4 != 0 {
+            BackgroundSegmentation::ShallowFocus
+        } else if flags & 1 != 0 {
+            BackgroundSegmentation::Blur
+        } else if flags & 2 != 0 {
+            BackgroundSegmentation::Mask
+        } else {
+            BackgroundSegmentation::Off
+        }
+    }
+}
+
+/// The part of the sensor the camera streams, as a digital pan, tilt and zoom.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum DigitalWindow {
+    /// A fixed window. The origin is its top left corner, from -1.0 to 1.0 across the full frame of the sensor, and
+    /// the size is its width and height, with 1.0 being the full frame.
+    Manual {
+        origin_x: f64,
+        origin_y: f64,
+        size: f64,
+    },
+    /// The camera moves the window to keep the faces in the frame.
+    AutoFaceFraming,
+}
+
+impl Default for DigitalWindow {
+    fn default() -> Self {
+        DigitalWindow::Manual {
+            origin_x: -1.0,
+            origin_y: -1.0,
+            size: 1.0,
+        }
+    }
+}
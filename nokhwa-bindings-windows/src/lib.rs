@@ -27,34 +27,59 @@
 //!
 //! No support or API stability will be given. Subject to change at any time.
 
+pub mod extended;
+
 #[cfg(all(windows, not(feature = "docs-only")))]
 pub mod wmf {
+    pub use crate::extended::*;
     use four_cc::FourCC;
-    use nokhwa_core::error::NokhwaError;
+    use nokhwa_core::buffer::FrameBuffer;
+    use nokhwa_core::conversion::HardwareDecoder;
+    use nokhwa_core::error::{ErrorKind, NokhwaError, PlatformError};
     use nokhwa_core::pixel_format;
+    use nokhwa_core::traits::VirtualBackendTrait;
     use nokhwa_core::types::{
-        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueDescription,
-        ControlValueSetter, KnownCameraControl, KnownCameraControlFlag, Resolution,
+        all_known_camera_controls, ApiBackend, CameraAvailability, CameraControl, CameraFormat,
+        CameraIndex, CameraInfo, CameraPosition, ControlValueDescription, ControlValueSetter,
+        Exposure, Focus, FrameRate, KnownCameraControl, KnownCameraControlFlag, PermissionStatus,
+        PtzCapabilities, PtzRange, Rect, Resolution, Rotation, SensorType, SessionEvent,
+        ThreadPriority, WhiteBalance,
     };
     use once_cell::sync::Lazy;
     use std::ffi::c_void;
     use std::{
         borrow::Cow,
-        cell::Cell,
+        cell::{Cell, RefCell},
         mem::MaybeUninit,
         slice::from_raw_parts,
         sync::{
-            atomic::{AtomicBool, AtomicUsize, Ordering},
-            Arc,
+            atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+            mpsc, Arc,
         },
+        thread::JoinHandle,
+        time::{Duration, Instant},
     };
     use windows::Win32::Media::DirectShow::{CameraControl_Flags_Auto, CameraControl_Flags_Manual};
     use windows::Win32::Media::MediaFoundation::{
-        IMFMediaType, MFCreateSample, MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+        IMFMediaEventGenerator, IMFMediaType, METransformHaveOutput, METransformNeedInput,
+        MFCreateMemoryBuffer, MFCreateSample, MFTEnumEx, MFT_FRIENDLY_NAME_Attribute,
+        MFT_CATEGORY_VIDEO_DECODER, MFT_ENUM_FLAG_HARDWARE, MFT_ENUM_FLAG_SORTANDFILTER,
+        MFT_ENUM_FLAG_SYNCMFT, MFT_MESSAGE_COMMAND_FLUSH, MFT_MESSAGE_NOTIFY_BEGIN_STREAMING,
+        MFT_MESSAGE_NOTIFY_END_STREAMING, MFT_MESSAGE_NOTIFY_START_OF_STREAM,
+        MFT_OUTPUT_DATA_BUFFER, MFT_OUTPUT_STREAM_PROVIDES_SAMPLES, MFT_REGISTER_TYPE_INFO,
+        MF_EVENT_FLAG_NONE, MF_EVENT_TYPE, MF_E_TRANSFORM_STREAM_CHANGE, MF_MT_DEFAULT_STRIDE,
+        MF_TRANSFORM_ASYNC, MF_TRANSFORM_ASYNC_UNLOCK,
     };
     use windows::{
-        core::{Interface, GUID, PWSTR},
+        core::{Interface, GUID, HSTRING, PCWSTR, PWSTR},
+        w,
+        Devices::Enumeration::{DeviceInformation, EnclosureLocation, Panel},
+        Security::Authorization::AppCapabilityAccess::{AppCapability, AppCapabilityAccessStatus},
         Win32::{
+            Foundation::{
+                GetLastError, BOOL, ERROR_ALREADY_EXISTS, ERROR_SUCCESS, HANDLE, HWND,
+                INVALID_HANDLE_VALUE, LPARAM, LRESULT, RECT, WPARAM,
+            },
             Media::{
                 DirectShow::{
                     CameraControl_Exposure, CameraControl_Focus, CameraControl_Iris,
@@ -64,21 +89,73 @@ pub mod wmf {
                     VideoProcAmp_Gamma, VideoProcAmp_Hue, VideoProcAmp_Saturation,
                     VideoProcAmp_Sharpness, VideoProcAmp_WhiteBalance,
                 },
-                KernelStreaming::GUID_NULL,
+                KernelStreaming::{IKsControl, GUID_NULL},
                 MediaFoundation::{
-                    IMFActivate, IMFAttributes, IMFMediaSource, IMFSample, IMFSourceReader,
-                    MFCreateAttributes, MFCreateMediaType, MFCreateSourceReaderFromMediaSource,
-                    MFEnumDeviceSources, MFMediaType_Video, MFShutdown, MFStartup,
-                    MFSTARTUP_NOSOCKET, MF_API_VERSION, MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME,
-                    MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
+                    IMF2DBuffer, IMFActivate, IMFAttributes, IMFDXGIDeviceManager, IMFMediaSource,
+                    IMFSample, IMFSourceReader, IMFSourceReaderEx, IMFStreamDescriptor,
+                    IMFTransform, IMFVideoProcessorControl, MFCreateAttributes, MFCreateMediaType,
+                    MFCreateSourceReaderFromMediaSource, MFEnumDeviceSources, MFGetSystemTime,
+                    MFMediaType_Video, MFShutdown, MFStartup, MFSTARTUP_NOSOCKET, MF_API_VERSION,
+                    MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME, MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
                     MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
                     MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK, MF_MT_FRAME_RATE,
                     MF_MT_FRAME_RATE_RANGE_MAX, MF_MT_FRAME_RATE_RANGE_MIN, MF_MT_FRAME_SIZE,
                     MF_MT_MAJOR_TYPE, MF_MT_SUBTYPE, MF_READWRITE_DISABLE_CONVERTERS,
+                    MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS, MF_SOURCE_READERF_ENDOFSTREAM,
+                    MF_SOURCE_READERF_ERROR, MF_SOURCE_READER_D3D_MANAGER,
+                    MF_SOURCE_READER_DISCONNECT_MEDIASOURCE_ON_SHUTDOWN,
+                    MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING, MIRROR_HORIZONTAL,
+                    MIRROR_NONE,
+                },
+            },
+            System::{
+                Com::{CoInitializeEx, CoTaskMemFree, CoUninitialize, COINIT},
+                LibraryLoader::GetModuleHandleW,
+                Memory::{
+                    CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+                    PAGE_READWRITE,
+                },
+                Power::DEVICE_NOTIFY_WINDOW_HANDLE,
+                Registry::{
+                    RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY,
+                    HKEY_CURRENT_USER, KEY_READ, RRF_RT_REG_QWORD,
+                },
+                SystemServices::{
+                    UnregisterDeviceNotification, DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE,
+                    DBT_DEVTYP_DEVICEINTERFACE, DEV_BROADCAST_DEVICEINTERFACE_W,
+                },
+                Threading::{
+                    GetCurrentThread, GetCurrentThreadId, SetThreadAffinityMask, SetThreadPriority,
+                    THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_NORMAL,
+                    THREAD_PRIORITY_TIME_CRITICAL,
                 },
             },
-            System::Com::{CoInitializeEx, CoUninitialize, COINIT},
+            UI::WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+                PostThreadMessageW, RegisterClassW, RegisterDeviceNotificationW, HMENU,
+                HWND_MESSAGE, MSG, WINDOW_EX_STYLE, WINDOW_STYLE, WM_DEVICECHANGE, WM_QUIT,
+                WNDCLASSW,
+            },
+        },
+    };
+
+    use windows::Win32::Foundation::CloseHandle;
+    #[cfg(feature = "d3d11")]
+    use windows::Win32::{
+        Foundation::HINSTANCE,
+        Graphics::{
+            Direct3D::D3D_DRIVER_TYPE_HARDWARE,
+            Direct3D11::{
+                D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Multithread,
+                ID3D11Query, ID3D11Texture2D, D3D11_BIND_SHADER_RESOURCE,
+                D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_CREATE_DEVICE_VIDEO_SUPPORT,
+                D3D11_QUERY_DESC, D3D11_QUERY_EVENT, D3D11_RESOURCE_MISC_SHARED,
+                D3D11_RESOURCE_MISC_SHARED_NTHANDLE, D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC,
+                D3D11_USAGE_DEFAULT,
+            },
+            Dxgi::{Common::DXGI_SAMPLE_DESC, IDXGIResource1, DXGI_SHARED_RESOURCE_READ},
         },
+        Media::MediaFoundation::{IMFDXGIBuffer, MFCreateDXGIDeviceManager},
     };
 
     static INITIALIZED: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
@@ -113,14 +190,83 @@ pub mod wmf {
         0x0010,
         [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
     );
+    const MF_VIDEO_FORMAT_I420: GUID = GUID::from_values(
+        0x3032_3449,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    const MF_VIDEO_FORMAT_YV12: GUID = GUID::from_values(
+        0x3231_5659,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
     const MF_VIDEO_FORMAT_RGB24: GUID = GUID::from_values(
         0x0000_0014,
         0x0000,
         0x0010,
         [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
     );
+    // byte order B, G, R, X, which is what BGRA is in memory
+    const MF_VIDEO_FORMAT_RGB32: GUID = GUID::from_values(
+        0x0000_0016,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    const MF_VIDEO_FORMAT_P010: GUID = GUID::from_values(
+        0x3031_3050,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    const MF_VIDEO_FORMAT_Y210: GUID = GUID::from_values(
+        0x3031_3259,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+
+    const MF_VIDEO_FORMAT_L8: GUID = GUID::from_values(
+        0x0000_0032,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    const MF_VIDEO_FORMAT_L16: GUID = GUID::from_values(
+        0x0000_0051,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    const MF_VIDEO_FORMAT_D16: GUID = GUID::from_values(
+        0x0000_0050,
+        0x0000,
+        0x0010,
+        [0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71],
+    );
+    // subtypes that are D3DFORMATs rather than FourCCs
+    const D3D_FORMATS: [(GUID, FourCC); 5] = [
+        (MF_VIDEO_FORMAT_RGB24, pixel_format::RAWRGB),
+        (MF_VIDEO_FORMAT_RGB32, pixel_format::BGRA),
+        (MF_VIDEO_FORMAT_L8, pixel_format::GRAY),
+        (MF_VIDEO_FORMAT_L16, pixel_format::GRAY16),
+        (MF_VIDEO_FORMAT_D16, pixel_format::DEPTH16),
+    ];
+
+    // Windows Hello and other infrared or depth cameras are only enumerated in this category
+    const KSCATEGORY_SENSOR_CAMERA: GUID =
+        GUID::from_u128(0x24e5_52d7_6523_47f7_a647_d346_5bf1_f5ca);
+    const MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_CATEGORY: GUID =
+        GUID::from_u128(0x77f0_ae69_c3bd_4509_941d_467e_4d24_899e);
+    const MF_DEVICESTREAM_ATTRIBUTE_FRAMESOURCE_TYPES: GUID =
+        GUID::from_u128(0x1714_5fd1_1b2b_423c_8001_2b68_33ed_3588);
+    const MF_FRAME_SOURCE_TYPES_INFRARED: u32 = 0x0002;
+    const MF_FRAME_SOURCE_TYPES_DEPTH: u32 = 0x0004;
 
     const MEDIA_FOUNDATION_FIRST_VIDEO_STREAM: u32 = 0xFFFF_FFFC;
+    const MF_SOURCE_READER_ALL_STREAMS: u32 = 0xFFFF_FFFE;
     const MF_SOURCE_READER_MEDIASOURCE: u32 = 0xFFFF_FFFF;
 
     // const CAM_CTRL_AUTO: i32 = 0x0001;
@@ -164,7 +310,19 @@ pub mod wmf {
     //     };
     // }
 
+    // an MFRatio is packed into a u64, with the numerator in the upper 32 bits
+    fn frame_rate_from_ratio(ratio: u64) -> FrameRate {
+        FrameRate::new((ratio >> 32) as u32, ratio as u32)
+    }
+
+    fn frame_rate_to_ratio(frame_rate: FrameRate) -> u64 {
+        (u64::from(frame_rate.numerator()) << 32) | u64::from(frame_rate.denominator())
+    }
+
     fn guid_to_fourcc(guid: GUID) -> FourCC {
+        if let Some((_, fourcc)) = D3D_FORMATS.iter().find(|(format, _)| *format == guid) {
+            return *fourcc;
+        }
         let data1 = guid.data1;
         FourCC::from(&data1.to_le_bytes())
     }
@@ -174,8 +332,15 @@ pub mod wmf {
             pixel_format::MJPEG => Some(MF_VIDEO_FORMAT_MJPEG),
             pixel_format::YUYV => Some(MF_VIDEO_FORMAT_YUY2),
             pixel_format::NV12 => Some(MF_VIDEO_FORMAT_NV12),
+            pixel_format::I420 | pixel_format::YU12 => Some(MF_VIDEO_FORMAT_I420),
+            pixel_format::YV12 => Some(MF_VIDEO_FORMAT_YV12),
             pixel_format::GRAY => Some(MF_VIDEO_FORMAT_GRAY),
+            pixel_format::GRAY16 => Some(MF_VIDEO_FORMAT_L16),
+            pixel_format::DEPTH16 => Some(MF_VIDEO_FORMAT_D16),
             pixel_format::RAWRGB => Some(MF_VIDEO_FORMAT_RGB24),
+            pixel_format::BGRA => Some(MF_VIDEO_FORMAT_RGB32),
+            pixel_format::P010 => Some(MF_VIDEO_FORMAT_P010),
+            pixel_format::Y210 => Some(MF_VIDEO_FORMAT_Y210),
             _ => None,
         }
     }
@@ -221,11 +386,11 @@ pub mod wmf {
         Ok(())
     }
 
-    fn query_activate_pointers() -> Result<Vec<IMFActivate>, NokhwaError> {
+    fn query_activate_pointers(category: Option<GUID>) -> Result<Vec<IMFActivate>, NokhwaError> {
         initialize_mf()?;
 
         let mut attributes: Option<IMFAttributes> = None;
-        if let Err(why) = unsafe { MFCreateAttributes(&mut attributes, 1) } {
+        if let Err(why) = unsafe { MFCreateAttributes(&mut attributes, 2) } {
             return Err(NokhwaError::GetPropertyError {
                 property: "IMFAttributes".to_string(),
                 error: why.to_string(),
@@ -246,6 +411,21 @@ pub mod wmf {
                         error: why.to_string(),
                     });
                 }
+                if let Some(category) = category {
+                    if let Err(why) = unsafe {
+                        attr.SetGUID(
+                            &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_CATEGORY,
+                            &category,
+                        )
+                    } {
+                        return Err(NokhwaError::SetPropertyError {
+                            property: "GUID MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_CATEGORY"
+                                .to_string(),
+                            value: format!("{category:?}"),
+                            error: why.to_string(),
+                        });
+                    }
+                }
                 attr
             }
             None => {
@@ -283,10 +463,8 @@ pub mod wmf {
         Ok(device_list)
     }
 
-    fn activate_to_descriptors(
-        index: CameraIndex,
-        imf_activate: &IMFActivate,
-    ) -> Result<CameraInfo, NokhwaError> {
+    /// The symbolic link is used as the unique ID, as it is derived from the device instance path and stays the same across reboots.
+    fn activate_to_descriptors(imf_activate: &IMFActivate) -> Result<CameraInfo, NokhwaError> {
         let mut pwstr_name = PWSTR(&mut 0_u16);
         let mut len_pwstrname = 0;
         let mut pwstr_symlink = PWSTR(&mut 0_u16);
@@ -348,24 +526,310 @@ pub mod wmf {
                 })?
         };
 
-        Ok(CameraInfo::new(
-            &name,
-            "MediaFoundation Camera",
-            &symlink,
-            index,
-        ))
+        let location = enclosure_location(&symlink);
+        Ok(
+            CameraInfo::new(&symlink, &name, "", "", "MediaFoundation Camera", "")
+                .with_sensor_orientation(
+                    location
+                        .as_ref()
+                        .map(enclosure_rotation)
+                        .unwrap_or_default(),
+                )
+                .with_camera_position(
+                    location
+                        .as_ref()
+                        .map_or(CameraPosition::External, enclosure_position),
+                ),
+        )
+    }
+
+    // Built in cameras of tablets and phones report how they are mounted through the enclosure location of the device
+    // interface. External cameras have no enclosure location.
+    fn enclosure_location(symlink: &str) -> Option<EnclosureLocation> {
+        DeviceInformation::CreateFromIdAsync(&HSTRING::from(symlink))
+            .and_then(|operation| operation.get())
+            .and_then(|information| information.EnclosureLocation())
+            .ok()
+    }
+
+    fn enclosure_rotation(location: &EnclosureLocation) -> Rotation {
+        location
+            .RotationAngleInDegreesClockwise()
+            .ok()
+            .and_then(|degrees| i32::try_from(degrees).ok())
+            .and_then(Rotation::from_degrees)
+            .unwrap_or_default()
+    }
+
+    fn enclosure_position(location: &EnclosureLocation) -> CameraPosition {
+        match location.Panel() {
+            Ok(Panel::Front) => CameraPosition::Front,
+            Ok(Panel::Back) => CameraPosition::Back,
+            _ => CameraPosition::Unknown,
+        }
+    }
+
+    fn webcam_capability() -> Result<AppCapability, NokhwaError> {
+        AppCapability::Create(&HSTRING::from("webcam")).map_err(|why| {
+            NokhwaError::GeneralError(format!("Failed to create webcam AppCapability: {why}"))
+        })
+    }
+
+    fn capability_status_to_permission(status: AppCapabilityAccessStatus) -> PermissionStatus {
+        match status {
+            AppCapabilityAccessStatus::Allowed => PermissionStatus::Authorized,
+            AppCapabilityAccessStatus::DeniedByUser => PermissionStatus::Denied,
+            AppCapabilityAccessStatus::UserPromptRequired => PermissionStatus::NotDetermined,
+            _ => PermissionStatus::Restricted,
+        }
+    }
+
+    /// Checks the `webcam` app capability, which reflects the camera privacy settings of the system.
+    pub fn webcam_capability_status() -> Result<PermissionStatus, NokhwaError> {
+        webcam_capability()?
+            .CheckAccess()
+            .map(capability_status_to_permission)
+            .map_err(|why| {
+                NokhwaError::GeneralError(format!("Failed to check webcam capability: {why}"))
+            })
+    }
+
+    /// Requests the `webcam` app capability, prompting the user if needed. This blocks until the user answers.
+    pub fn request_webcam_capability() -> Result<PermissionStatus, NokhwaError> {
+        webcam_capability()?
+            .RequestAccessAsync()
+            .and_then(|operation| operation.get())
+            .map(capability_status_to_permission)
+            .map_err(|why| {
+                NokhwaError::GeneralError(format!("Failed to request webcam capability: {why}"))
+            })
+    }
+
+    /// A stream that is listed as a camera: the video stream of a regular camera, or the infrared or depth stream of a
+    /// sensor camera.
+    struct VideoSource {
+        activate: IMFActivate,
+        info: CameraInfo,
+        stream: u32,
+    }
+
+    fn query_video_sources() -> Result<Vec<VideoSource>, NokhwaError> {
+        let mut sources = vec![];
+        for activate in query_activate_pointers(None)? {
+            sources.push(VideoSource {
+                info: activate_to_descriptors(&activate)?,
+                activate,
+                stream: MEDIA_FOUNDATION_FIRST_VIDEO_STREAM,
+            });
+        }
+
+        // the color streams of sensor cameras are also regular cameras, so only the others are listed
+        let Ok(sensor_cameras) = query_activate_pointers(Some(KSCATEGORY_SENSOR_CAMERA)) else {
+            return Ok(sources);
+        };
+        for activate in sensor_cameras {
+            let info = activate_to_descriptors(&activate)?;
+            for (stream, sensor_type) in sensor_streams(&activate) {
+                // symbolic links are full of '#', but never have a '|'
+                let unique_id = format!("{}|{stream}", info.unique_id());
+                if sources
+                    .iter()
+                    .any(|source| source.info.unique_id() == unique_id)
+                {
+                    continue;
+                }
+                sources.push(VideoSource {
+                    info: CameraInfo::new(
+                        &unique_id,
+                        &format!("{} ({sensor_type})", info.name()),
+                        "",
+                        "",
+                        "MediaFoundation Sensor Camera",
+                        "",
+                    )
+                    .with_sensor_orientation(info.sensor_orientation())
+                    .with_camera_position(info.camera_position())
+                    .with_sensor_type(sensor_type),
+                    activate: activate.clone(),
+                    stream,
+                });
+            }
+        }
+        Ok(sources)
+    }
+
+    // The infrared and depth streams of a sensor camera, by their index in the source reader.
+    fn sensor_streams(activate: &IMFActivate) -> Vec<(u32, SensorType)> {
+        let Ok(media_source) = (unsafe { activate.ActivateObject::<IMFMediaSource>() }) else {
+            return vec![];
+        };
+        let mut streams = vec![];
+        if let Ok(presentation) = unsafe { media_source.CreatePresentationDescriptor() } {
+            let count = unsafe { presentation.GetStreamDescriptorCount() }.unwrap_or_default();
+            for index in 0..count {
+                let mut selected = BOOL::default();
+                let mut descriptor: Option<IMFStreamDescriptor> = None;
+                if unsafe {
+                    presentation.GetStreamDescriptorByIndex(index, &mut selected, &mut descriptor)
+                }
+                .is_err()
+                {
+                    continue;
+                }
+                let source_types = descriptor
+                    .and_then(|descriptor| unsafe {
+                        descriptor
+                            .GetUINT32(&MF_DEVICESTREAM_ATTRIBUTE_FRAMESOURCE_TYPES)
+                            .ok()
+                    })
+                    .unwrap_or_default();
+                if source_types & MF_FRAME_SOURCE_TYPES_DEPTH != 0 {
+                    streams.push((index, SensorType::Depth));
+                } else if source_types & MF_FRAME_SOURCE_TYPES_INFRARED != 0 {
+                    streams.push((index, SensorType::Infrared));
+                }
+            }
+        }
+        // detached, so the camera can be activated again when it is opened
+        unsafe {
+            let _ = activate.DetachObject();
+            let _ = media_source.Shutdown();
+        }
+        streams
     }
 
     pub fn query_media_foundation_descriptors() -> Result<Vec<CameraInfo>, NokhwaError> {
-        let mut device_list = vec![];
+        let availability = camera_availability();
+        Ok(query_video_sources()?
+            .into_iter()
+            .map(|source| source.info.with_availability(availability))
+            .collect())
+    }
+
+    const CONSENT_STORE_WEBCAM: PCWSTR = w!(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\webcam"
+    );
 
-        for (index, activate_ptr) in query_activate_pointers()?.into_iter().enumerate() {
-            device_list.push(activate_to_descriptors(
-                CameraIndex::Index(index as u32),
-                &activate_ptr,
-            )?);
+    struct RegistryKey(HKEY);
+
+    impl RegistryKey {
+        fn open(parent: HKEY, path: PCWSTR) -> Option<Self> {
+            let mut key = HKEY::default();
+            (unsafe { RegOpenKeyExW(parent, path, 0, KEY_READ, &mut key) } == ERROR_SUCCESS)
+                .then_some(RegistryKey(key))
+        }
+
+        fn subkeys(&self) -> Vec<String> {
+            let mut subkeys = vec![];
+            let mut name = [0_u16; 512];
+            for index in 0.. {
+                let mut len = name.len() as u32;
+                if unsafe {
+                    RegEnumKeyExW(
+                        self.0,
+                        index,
+                        PWSTR(name.as_mut_ptr()),
+                        &mut len,
+                        None,
+                        PWSTR::null(),
+                        None,
+                        None,
+                    )
+                } != ERROR_SUCCESS
+                {
+                    break;
+                }
+                subkeys.push(String::from_utf16_lossy(&name[..len as usize]));
+            }
+            subkeys
+        }
+
+        fn qword(&self, subkey: &str, value: PCWSTR) -> Option<u64> {
+            let subkey = subkey.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+            let mut data = 0_u64;
+            let mut size = std::mem::size_of::<u64>() as u32;
+            (unsafe {
+                RegGetValueW(
+                    self.0,
+                    PCWSTR(subkey.as_ptr()),
+                    value,
+                    RRF_RT_REG_QWORD,
+                    None,
+                    Some(std::ptr::addr_of_mut!(data).cast()),
+                    Some(&mut size),
+                )
+            } == ERROR_SUCCESS)
+                .then_some(data)
+        }
+    }
+
+    impl Drop for RegistryKey {
+        fn drop(&mut self) {
+            unsafe {
+                RegCloseKey(self.0);
+            }
+        }
+    }
+
+    // The consent store of the privacy settings records when each app started and stopped using a camera, with a
+    // stop time of 0 while it still is. Desktop apps are under `NonPackaged`, by their path with `#` for `\`.
+    fn camera_in_use_by_other_app() -> bool {
+        let Some(webcam) = RegistryKey::open(HKEY_CURRENT_USER, CONSENT_STORE_WEBCAM) else {
+            return false;
+        };
+        let in_use = |key: &RegistryKey, app: &str| {
+            key.qword(app, w!("LastUsedTimeStart")).unwrap_or(0) != 0
+                && key.qword(app, w!("LastUsedTimeStop")) == Some(0)
+        };
+        let this_app = std::env::current_exe()
+            .map(|path| path.to_string_lossy().replace('\\', "#"))
+            .unwrap_or_default();
+
+        let packaged = webcam
+            .subkeys()
+            .iter()
+            .filter(|app| *app != "NonPackaged")
+            .any(|app| in_use(&webcam, app));
+        packaged
+            || RegistryKey::open(webcam.0, w!("NonPackaged")).map_or(false, |non_packaged| {
+                non_packaged
+                    .subkeys()
+                    .iter()
+                    .filter(|app| !app.eq_ignore_ascii_case(&this_app))
+                    .any(|app| in_use(&non_packaged, app))
+            })
+    }
+
+    /// Checks whether cameras can be opened right now: not if the camera privacy settings block this application,
+    /// and maybe not while another application is using a camera. Windows does not say which camera is in use.
+    #[must_use]
+    pub fn camera_availability() -> CameraAvailability {
+        if matches!(
+            webcam_capability_status(),
+            Ok(PermissionStatus::Denied | PermissionStatus::Restricted)
+        ) {
+            return CameraAvailability::PrivacyBlocked;
+        }
+        if camera_in_use_by_other_app() {
+            return CameraAvailability::InUse;
+        }
+        CameraAvailability::Available
+    }
+
+    // E_ACCESSDENIED and sharing violations do not say why, so this says what the user can do about them
+    fn describe_access_error(why: &windows::core::Error) -> String {
+        match PlatformError::HResult(why.code().0).kind() {
+            ErrorKind::PermissionDenied
+                if matches!(
+                    webcam_capability_status(),
+                    Ok(PermissionStatus::Denied | PermissionStatus::Restricted)
+                ) =>
+            {
+                format!("Camera access is turned off in the Windows privacy settings: {why}")
+            }
+            ErrorKind::DeviceBusy => format!("The camera is in use by another application: {why}"),
+            _ => why.to_string(),
         }
-        Ok(device_list)
     }
 
     #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Eq)]
@@ -374,9 +838,96 @@ pub mod wmf {
         ProcAmpRange(i32),
         CCValue(i32),
         CCRange(i32),
+        Extended(u32),
+    }
+
+    const KSPROPERTYSETID_EXTENDED_CAMERA_CONTROL: GUID =
+        GUID::from_u128(0x1cb7_9112_c0d2_4213_9ca6_cd4f_db92_7972);
+    const KSPROPERTY_TYPE_GET: u32 = 0x0000_0001;
+    const KSPROPERTY_TYPE_SET: u32 = 0x0000_0002;
+    const KSCAMERA_EXTENDEDPROP_VERSION: u32 = 1;
+    const KSCAMERA_EXTENDEDPROP_FILTERSCOPE: u32 = 0xFFFF_FFFF;
+
+    // KSPROPERTY
+    #[repr(C)]
+    struct KsProperty {
+        set: GUID,
+        id: u32,
+        flags: u32,
+    }
+
+    // KSCAMERA_EXTENDEDPROP_HEADER
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct ExtendedPropertyHeader {
+        version: u32,
+        pin_id: u32,
+        size: u32,
+        result: u32,
+        flags: u64,
+        capability: u64,
+    }
+
+    // KSCAMERA_EXTENDEDPROP_DIGITALWINDOW_SETTING
+    #[repr(C)]
+    #[derive(Copy, Clone, Default)]
+    struct DigitalWindowSetting {
+        origin_x: f64,
+        origin_y: f64,
+        window_size: f64,
+        reserved: u32,
+    }
+
+    // the header is followed by the value of the control, which is a KSCAMERA_EXTENDEDPROP_VALUE for most
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct ExtendedProperty<T> {
+        header: ExtendedPropertyHeader,
+        value: T,
+    }
+
+    impl<T: Default> Default for ExtendedProperty<T> {
+        fn default() -> Self {
+            ExtendedProperty {
+                header: ExtendedPropertyHeader {
+                    version: KSCAMERA_EXTENDEDPROP_VERSION,
+                    pin_id: KSCAMERA_EXTENDEDPROP_FILTERSCOPE,
+                    size: std::mem::size_of::<Self>() as u32,
+                    result: 0,
+                    flags: 0,
+                    capability: 0,
+                },
+                value: T::default(),
+            }
+        }
+    }
+
+    /// The value `fraction` of the way from `min` to `max`.
+    fn fraction_of_range(min: i32, max: i32, fraction: f64) -> f64 {
+        f64::from(min) + f64::from(max - min) * fraction.clamp(0.0, 1.0)
     }
 
     #[allow(clippy::cast_sign_loss)]
+    /// Sets `property` of `camera_control` to `value`, rounded to the integer the device takes.
+    fn set_camera_control_value(
+        camera_control: &IAMCameraControl,
+        property: i32,
+        name: &str,
+        value: f64,
+    ) -> Result<(), NokhwaError> {
+        let rounded = value.round() as i32;
+        unsafe { camera_control.Set(property, rounded, CameraControl_Flags_Manual.0) }.map_err(
+            |why| NokhwaError::SetPropertyError {
+                property: name.to_string(),
+                value: rounded.to_string(),
+                error: why.to_string(),
+            },
+        )
+    }
+
+    // IAMVideoProcAmp passes the property on to the driver, so it also takes the KS properties it has no name for
+    const KSPROPERTY_VIDEOPROCAMP_POWERLINE_FREQUENCY: i32 = 13;
+
     fn kcc_to_i32(kcc: KnownCameraControl) -> Option<MFControlId> {
         let control_id = match kcc {
             KnownCameraControl::Brightness => MFControlId::ProcAmpRange(VideoProcAmp_Brightness.0),
@@ -398,11 +949,16 @@ pub mod wmf {
             KnownCameraControl::Exposure => MFControlId::CCValue(CameraControl_Exposure.0),
             KnownCameraControl::Iris => MFControlId::CCValue(CameraControl_Iris.0),
             KnownCameraControl::Focus => MFControlId::CCValue(CameraControl_Focus.0),
+            // Media Foundation has no torch control for webcams
+            KnownCameraControl::Torch => return None,
+            KnownCameraControl::PowerlineFrequency => {
+                MFControlId::ProcAmpRange(KSPROPERTY_VIDEOPROCAMP_POWERLINE_FREQUENCY)
+            }
             KnownCameraControl::Other(o) => {
                 if o == VideoProcAmp_ColorEnable.0 as u128 {
                     MFControlId::ProcAmpRange(o as i32)
                 } else {
-                    return None;
+                    return extended_property_id(kcc).map(MFControlId::Extended);
                 }
             }
         };
@@ -412,9 +968,103 @@ pub mod wmf {
 
     pub struct MediaFoundationDevice {
         is_open: Cell<bool>,
+        index: CameraIndex,
         device_specifier: CameraInfo,
         device_format: CameraFormat,
         source_reader: IMFSourceReader,
+        media_source: IMFMediaSource,
+        last_sample_time: Option<i64>,
+        last_row_stride: Option<usize>,
+        uncropped_resolution: Option<Resolution>,
+        decoded_output: Option<FourCC>,
+        disconnected: Arc<AtomicBool>,
+        connection_watch: Option<DeviceNotifier>,
+        control_watch: Option<ControlWatch>,
+        // the stream of the source reader, which is not the first video stream for infrared and depth streams
+        stream: u32,
+        #[cfg(feature = "d3d11")]
+        d3d11: Option<D3D11Output>,
+    }
+
+    /// Creates the source reader of a camera. With a `decoded_output`, the reader may insert its decoders and the
+    /// video processor (hardware ones where there are), so the camera can send e.g. MJPEG while frames are read as
+    /// `decoded_output`. Without one, frames are read as the camera sends them.
+    ///
+    /// With a `d3d_manager`, the decoders and the video processor output Direct3D textures on its device.
+    fn create_source_reader(
+        media_source: &IMFMediaSource,
+        decoded_output: Option<FourCC>,
+        d3d_manager: Option<&IMFDXGIDeviceManager>,
+    ) -> Result<IMFSourceReader, NokhwaError> {
+        let attr = {
+            let mut attr: Option<IMFAttributes> = None;
+
+            if let Err(why) = unsafe { MFCreateAttributes(&mut attr, 4) } {
+                return Err(NokhwaError::StructureError {
+                    structure: "MFCreateAttributes".to_string(),
+                    error: why.to_string(),
+                });
+            }
+            match attr {
+                Some(imf_attr) => imf_attr,
+                None => {
+                    return Err(NokhwaError::StructureError {
+                        structure: "MFCreateAttributes".to_string(),
+                        error: "Attributee Alloc Failure".to_string(),
+                    });
+                }
+            }
+        };
+
+        let decode = decoded_output.is_some();
+        for (property, key, value) in [
+            (
+                "MF_READWRITE_DISABLE_CONVERTERS",
+                &MF_READWRITE_DISABLE_CONVERTERS,
+                !decode,
+            ),
+            (
+                "MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS",
+                &MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS,
+                decode,
+            ),
+            (
+                "MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING",
+                &MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING,
+                decode,
+            ),
+            // the reader is replaced when the decoded output changes, which must not shut the camera down
+            (
+                "MF_SOURCE_READER_DISCONNECT_MEDIASOURCE_ON_SHUTDOWN",
+                &MF_SOURCE_READER_DISCONNECT_MEDIASOURCE_ON_SHUTDOWN,
+                true,
+            ),
+        ] {
+            if let Err(why) = unsafe { attr.SetUINT32(key, u32::from(value)) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: property.to_string(),
+                    value: u32::from(value).to_string(),
+                    error: why.to_string(),
+                });
+            }
+        }
+        if let Some(manager) = d3d_manager {
+            if let Err(why) = unsafe { attr.SetUnknown(&MF_SOURCE_READER_D3D_MANAGER, manager) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_SOURCE_READER_D3D_MANAGER".to_string(),
+                    value: "IMFDXGIDeviceManager".to_string(),
+                    error: why.to_string(),
+                });
+            }
+        }
+
+        match unsafe { MFCreateSourceReaderFromMediaSource(media_source, &attr) } {
+            Ok(sr) => Ok(sr),
+            Err(why) => Err(NokhwaError::StructureError {
+                structure: "MFCreateSourceReaderFromMediaSource".to_string(),
+                error: why.to_string(),
+            }),
+        }
     }
 
     impl MediaFoundationDevice {
@@ -422,18 +1072,18 @@ pub mod wmf {
             initialize_mf()?;
             match index {
                 CameraIndex::Index(i) => {
-                    let (media_source, device_descriptor) =
-                        match query_activate_pointers()?.into_iter().nth(i as usize) {
-                            Some(activate) => {
-                                match unsafe { activate.ActivateObject::<IMFMediaSource>() } {
-                                    Ok(media_source) => {
-                                        (media_source, activate_to_descriptors(index, &activate)?)
-                                    }
+                    let (media_source, device_descriptor, stream) =
+                        match query_video_sources()?.into_iter().nth(i as usize) {
+                            Some(source) => {
+                                match unsafe { source.activate.ActivateObject::<IMFMediaSource>() }
+                                {
+                                    Ok(media_source) => (media_source, source.info, source.stream),
                                     Err(why) => {
                                         return Err(NokhwaError::OpenDeviceError(
                                             index.to_string(),
-                                            why.to_string(),
-                                        ))
+                                            describe_access_error(&why),
+                                        )
+                                        .with_os_error(PlatformError::HResult(why.code().0)))
                                     }
                                 }
                             }
@@ -445,76 +1095,38 @@ pub mod wmf {
                             }
                         };
 
-                    let source_reader_attr = {
-                        let attr = match {
-                            let mut attr: Option<IMFAttributes> = None;
-
-                            if let Err(why) = unsafe { MFCreateAttributes(&mut attr, 3) } {
-                                return Err(NokhwaError::StructureError {
-                                    structure: "MFCreateAttributes".to_string(),
-                                    error: why.to_string(),
-                                });
-                            }
-                            attr
-                        } {
-                            Some(imf_attr) => imf_attr,
-                            None => {
-                                return Err(NokhwaError::StructureError {
-                                    structure: "MFCreateAttributes".to_string(),
-                                    error: "Attributee Alloc Failure".to_string(),
-                                });
-                            }
-                        };
-
-                        if let Err(why) = unsafe {
-                            attr.SetUINT32(&MF_READWRITE_DISABLE_CONVERTERS, u32::from(true))
-                        } {
-                            return Err(NokhwaError::SetPropertyError {
-                                property: "MF_READWRITE_DISABLE_CONVERTERS".to_string(),
-                                value: u32::from(true).to_string(),
-                                error: why.to_string(),
-                            });
-                        }
-
-                        attr
-                    };
-
-                    let source_reader = match unsafe {
-                        MFCreateSourceReaderFromMediaSource(&media_source, &source_reader_attr)
-                    } {
-                        Ok(sr) => sr,
-                        Err(why) => {
-                            return Err(NokhwaError::StructureError {
-                                structure: "MFCreateSourceReaderFromMediaSource".to_string(),
-                                error: why.to_string(),
-                            })
-                        }
-                    };
+                    let source_reader = create_source_reader(&media_source, None, None)?;
 
                     // increment refcnt
                     CAMERA_REFCNT.store(CAMERA_REFCNT.load(Ordering::SeqCst) + 1, Ordering::SeqCst);
 
                     Ok(MediaFoundationDevice {
                         is_open: Cell::new(false),
+                        index: CameraIndex::Index(i),
                         device_specifier: device_descriptor,
                         device_format: CameraFormat::default(),
                         source_reader,
+                        media_source,
+                        last_sample_time: None,
+                        last_row_stride: None,
+                        uncropped_resolution: None,
+                        decoded_output: None,
+                        disconnected: Arc::new(AtomicBool::new(false)),
+                        connection_watch: None,
+                        control_watch: None,
+                        stream,
+                        #[cfg(feature = "d3d11")]
+                        d3d11: None,
                     })
                 }
                 CameraIndex::String(s) => {
-                    let devicelist = query_media_foundation_descriptors()?;
-                    let mut id_eq = None;
-
-                    for mfdev in devicelist {
-                        if mfdev.misc() == s {
-                            id_eq = Some(mfdev.index().as_index()?);
-                            break;
-                        }
-                    }
+                    let position = query_media_foundation_descriptors()?
+                        .iter()
+                        .position(|mfdev| mfdev.unique_id() == s);
 
-                    match id_eq {
-                        Some(index) => Self::new(CameraIndex::Index(index)),
-                        None => Err(NokhwaError::OpenDeviceError(s, "Not Found".to_string())),
+                    match position.map(u32::try_from) {
+                        Some(Ok(index)) => Self::new(CameraIndex::Index(index)),
+                        _ => Err(NokhwaError::OpenDeviceError(s, "Not Found".to_string())),
                     }
                 }
             }
@@ -547,7 +1159,7 @@ pub mod wmf {
         // }
 
         pub fn index(&self) -> &CameraIndex {
-            self.device_specifier.index()
+            &self.index
         }
 
         pub fn name(&self) -> String {
@@ -555,17 +1167,16 @@ pub mod wmf {
         }
 
         pub fn symlink(&self) -> String {
-            self.device_specifier.misc()
+            self.device_specifier.unique_id()
         }
 
         pub fn compatible_format_list(&mut self) -> Result<Vec<CameraFormat>, NokhwaError> {
             let mut camera_format_list = vec![];
             let mut index = 0;
 
-            while let Ok(media_type) = unsafe {
-                self.source_reader
-                    .GetNativeMediaType(MEDIA_FOUNDATION_FIRST_VIDEO_STREAM, index)
-            } {
+            while let Ok(media_type) =
+                unsafe { self.source_reader.GetNativeMediaType(self.stream, index) }
+            {
                 let guid = match unsafe { media_type.GetGUID(&MF_MT_SUBTYPE) } {
                     Ok(fcc) => fcc,
                     Err(why) => {
@@ -592,58 +1203,25 @@ pub mod wmf {
                     }
                 };
 
-                // MFRatio is represented as 2 u32s in memory. This means we cann convert it to 2
-                let framerate_list = {
-                    let mut framerates = vec![0_u32; 3];
-                    if let Ok(fraction_u64) =
-                        unsafe { media_type.GetUINT64(&MF_MT_FRAME_RATE_RANGE_MAX) }
-                    {
-                        let mut numerator = (fraction_u64 >> 32) as u32;
-                        let denominator = fraction_u64 as u32;
-                        if denominator != 1 {
-                            numerator = 0;
-                        }
-                        framerates.push(numerator);
-                    };
-                    if let Ok(fraction_u64) =
-                        unsafe { media_type.GetUINT64(&MF_MT_FRAME_RATE_RANGE_MAX) }
-                    {
-                        let mut numerator = (fraction_u64 >> 32) as u32;
-                        let denominator = fraction_u64 as u32;
-                        if denominator != 1 {
-                            numerator = 0;
-                        }
-                        framerates.push(numerator);
-                    };
-                    if let Ok(fraction_u64) = unsafe { media_type.GetUINT64(&MF_MT_FRAME_RATE) } {
-                        let mut numerator = (fraction_u64 >> 32) as u32;
-                        let denominator = fraction_u64 as u32;
-                        if denominator != 1 {
-                            numerator = 0;
-                        }
-                        framerates.push(numerator);
-                    };
-                    if let Ok(fraction_u64) =
-                        unsafe { media_type.GetUINT64(&MF_MT_FRAME_RATE_RANGE_MIN) }
-                    {
-                        let mut numerator = (fraction_u64 >> 32) as u32;
-                        let denominator = fraction_u64 as u32;
-                        if denominator != 1 {
-                            numerator = 0;
-                        }
-                        framerates.push(numerator);
-                    };
-                    framerates
-                };
+                let mut frame_rates = [
+                    &MF_MT_FRAME_RATE_RANGE_MAX,
+                    &MF_MT_FRAME_RATE,
+                    &MF_MT_FRAME_RATE_RANGE_MIN,
+                ]
+                .into_iter()
+                .filter_map(|key| unsafe { media_type.GetUINT64(key) }.ok())
+                .map(frame_rate_from_ratio)
+                .filter(|frame_rate| frame_rate.numerator() != 0)
+                .collect::<Vec<FrameRate>>();
+                frame_rates.sort_unstable();
+                frame_rates.dedup();
 
-                for frame_rate in framerate_list {
-                    if frame_rate != 0 {
-                        camera_format_list.push(CameraFormat::new(
-                            Resolution::new(width, height),
-                            fourcc,
-                            frame_rate,
-                        ));
-                    }
+                for frame_rate in frame_rates {
+                    camera_format_list.push(CameraFormat::new_exact(
+                        Resolution::new(width, height),
+                        fourcc,
+                        frame_rate,
+                    ));
                 }
 
                 index += 1;
@@ -652,6 +1230,9 @@ pub mod wmf {
         }
 
         pub fn control(&self, control: KnownCameraControl) -> Result<CameraControl, NokhwaError> {
+            if let Some(MFControlId::Extended(id)) = kcc_to_i32(control) {
+                return self.extended_control(control, id);
+            }
             let camera_control = unsafe {
                 let mut receiver: MaybeUninit<IAMCameraControl> = MaybeUninit::uninit();
                 let ptr_receiver = receiver.as_mut_ptr();
@@ -836,7 +1417,9 @@ pub mod wmf {
             control: KnownCameraControl,
             value: ControlValueSetter,
         ) -> Result<(), NokhwaError> {
-            let current_value = self.control(control)?;
+            if let Some(MFControlId::Extended(id)) = kcc_to_i32(control) {
+                return self.set_extended_control(control, id, &value);
+            }
 
             let camera_control = unsafe {
                 let mut receiver: MaybeUninit<IAMCameraControl> = MaybeUninit::uninit();
@@ -874,7 +1457,43 @@ pub mod wmf {
                 }
                 receiver.assume_init()
             };
+            self.set_control_with(&camera_control, &video_proc_amp, control, value)
+        }
+
+        /// Sets several controls, getting the `IAMCameraControl` and `IAMVideoProcAmp` of the source once for all of them
+        /// instead of for each. Media Foundation has no way to set them atomically, they are set one after another.
+        /// # Errors
+        /// If the source has no `IAMCameraControl` or `IAMVideoProcAmp`, this will error.
+        pub fn set_controls(
+            &mut self,
+            controls: &[(KnownCameraControl, ControlValueSetter)],
+        ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+            let camera_control = self.camera_control_interface()?;
+            let video_proc_amp = self.video_proc_amp_interface()?;
+            Ok(controls
+                .iter()
+                .map(|(control, value)| match kcc_to_i32(*control) {
+                    Some(MFControlId::Extended(id)) => {
+                        self.set_extended_control(*control, id, value)
+                    }
+                    _ => self.set_control_with(
+                        &camera_control,
+                        &video_proc_amp,
+                        *control,
+                        value.clone(),
+                    ),
+                })
+                .collect())
+        }
 
+        fn set_control_with(
+            &mut self,
+            camera_control: &IAMCameraControl,
+            video_proc_amp: &IAMVideoProcAmp,
+            control: KnownCameraControl,
+            value: ControlValueSetter,
+        ) -> Result<(), NokhwaError> {
+            let current_value = self.control(control)?;
             let control_id = kcc_to_i32(control).ok_or(NokhwaError::SetPropertyError {
                 property: "CameraControl".to_string(),
                 value: control.to_string(),
@@ -926,26 +1545,260 @@ pub mod wmf {
                         });
                     }
                 },
+                // handled above
+                MFControlId::Extended(_) => {}
             }
 
+            self.remember_control(control);
             Ok(())
         }
 
-        #[allow(clippy::cast_sign_loss)]
-        pub fn format_refreshed(&mut self) -> Result<CameraFormat, NokhwaError> {
-            match unsafe {
-                self.source_reader
-                    .GetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32)
-            } {
-                Ok(media_type) => {
-                    let resolution = match unsafe { media_type.GetUINT64(&MF_MT_FRAME_SIZE) } {
-                        Ok(res) => {
-                            let width = (res >> 32) as u32;
-                            let height = ((res << 32) >> 32) as u32;
-
-                            Resolution {
-                                width_x: width,
-                                height_y: height,
+        fn extended_property<T: Copy + Default>(
+            &self,
+            id: u32,
+        ) -> Result<ExtendedProperty<T>, NokhwaError> {
+            let error = |why: String| NokhwaError::GetPropertyError {
+                property: format!("KSPROPERTY_CAMERACONTROL_EXTENDED {id}"),
+                error: why,
+            };
+            let ks_control = self
+                .media_source
+                .cast::<IKsControl>()
+                .map_err(|why| error(why.to_string()))?;
+            let property = KsProperty {
+                set: KSPROPERTYSETID_EXTENDED_CAMERA_CONTROL,
+                id,
+                flags: KSPROPERTY_TYPE_GET,
+            };
+            let mut data = ExtendedProperty::<T>::default();
+            let mut returned = 0;
+            unsafe {
+                ks_control.KsProperty(
+                    (&raw const property).cast(),
+                    std::mem::size_of::<KsProperty>() as u32,
+                    (&raw mut data).cast(),
+                    std::mem::size_of::<ExtendedProperty<T>>() as u32,
+                    &mut returned,
+                )
+            }
+            .map_err(|why| {
+                error(why.to_string()).with_os_error(PlatformError::HResult(why.code().0))
+            })?;
+            Ok(data)
+        }
+
+        fn set_extended_property<T: Copy + Default>(
+            &self,
+            id: u32,
+            flags: u64,
+            value: T,
+        ) -> Result<(), NokhwaError> {
+            let error = |why: String| NokhwaError::SetPropertyError {
+                property: format!("KSPROPERTY_CAMERACONTROL_EXTENDED {id}"),
+                value: flags.to_string(),
+                error: why,
+            };
+            let current = self.extended_property::<T>(id)?;
+            // a flag the camera does not have would fail anyway, but with a less helpful error
+            if flags & !current.header.capability != 0 {
+                return Err(error(format!(
+                    "The camera only supports the flags {:#x}",
+                    current.header.capability
+                )));
+            }
+            let ks_control = self
+                .media_source
+                .cast::<IKsControl>()
+                .map_err(|why| error(why.to_string()))?;
+            let property = KsProperty {
+                set: KSPROPERTYSETID_EXTENDED_CAMERA_CONTROL,
+                id,
+                flags: KSPROPERTY_TYPE_SET,
+            };
+            let mut data = ExtendedProperty {
+                header: ExtendedPropertyHeader {
+                    flags,
+                    ..current.header
+                },
+                value,
+            };
+            let mut returned = 0;
+            unsafe {
+                ks_control.KsProperty(
+                    (&raw const property).cast(),
+                    std::mem::size_of::<KsProperty>() as u32,
+                    (&raw mut data).cast(),
+                    std::mem::size_of::<ExtendedProperty<T>>() as u32,
+                    &mut returned,
+                )
+            }
+            .map_err(|why| {
+                error(why.to_string()).with_os_error(PlatformError::HResult(why.code().0))
+            })
+        }
+
+        #[allow(clippy::cast_possible_wrap)]
+        fn extended_control(
+            &self,
+            control: KnownCameraControl,
+            id: u32,
+        ) -> Result<CameraControl, NokhwaError> {
+            let header = if id == DIGITAL_WINDOW_ID {
+                self.extended_property::<DigitalWindowSetting>(id)?.header
+            } else {
+                self.extended_property::<u64>(id)?.header
+            };
+            // every flag the camera has is a mode on its own, except shallow focus, which is on top of blur
+            let mut possible = vec![0];
+            if id == DIGITAL_WINDOW_ID {
+                possible.extend((header.capability & 1 != 0).then_some(1));
+            } else {
+                for bit in (0..64).map(|bit| 1_u64 << bit) {
+                    if header.capability & bit != 0 {
+                        let flags = if id == BACKGROUND_SEGMENTATION_ID && bit == 4 {
+                            BackgroundSegmentation::ShallowFocus.flags()
+                        } else {
+                            bit
+                        };
+                        possible.push(flags as isize);
+                    }
+                }
+            }
+            Ok(CameraControl::new(
+                control,
+                match id {
+                    EYE_GAZE_CORRECTION_ID => "Eye Gaze Correction".to_string(),
+                    BACKGROUND_SEGMENTATION_ID => "Background Segmentation".to_string(),
+                    _ => "Digital Window".to_string(),
+                },
+                ControlValueDescription::Enum {
+                    value: header.flags as isize,
+                    possible,
+                    default: 0,
+                    labels: vec![],
+                },
+                vec![KnownCameraControlFlag::Manual],
+                true,
+            ))
+        }
+
+        #[allow(clippy::cast_sign_loss)]
+        fn set_extended_control(
+            &self,
+            control: KnownCameraControl,
+            id: u32,
+            value: &ControlValueSetter,
+        ) -> Result<(), NokhwaError> {
+            let flags = match value {
+                ControlValueSetter::EnumValue(v) | ControlValueSetter::Integer(v) => *v as u64,
+                ControlValueSetter::Boolean(b) => u64::from(*b),
+                v => {
+                    return Err(NokhwaError::SetPropertyError {
+                        property: control.to_string(),
+                        value: v.to_string(),
+                        error: "Extended controls take an EnumValue".to_string(),
+                    })
+                }
+            };
+            if id == DIGITAL_WINDOW_ID {
+                let current = self.extended_property::<DigitalWindowSetting>(id)?;
+                self.set_extended_property(id, flags, current.value)
+            } else {
+                self.set_extended_property(id, flags, 0_u64)
+            }
+        }
+
+        /// Gets the eye gaze correction of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control.
+        pub fn eye_gaze_correction(&self) -> Result<EyeGazeCorrection, NokhwaError> {
+            let property = self.extended_property::<u64>(EYE_GAZE_CORRECTION_ID)?;
+            Ok(EyeGazeCorrection::from_flags(property.header.flags))
+        }
+
+        /// Sets the eye gaze correction of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control, or does not support `correction`.
+        pub fn set_eye_gaze_correction(
+            &self,
+            correction: EyeGazeCorrection,
+        ) -> Result<(), NokhwaError> {
+            self.set_extended_property(EYE_GAZE_CORRECTION_ID, correction.flags(), 0_u64)
+        }
+
+        /// Gets the background segmentation of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control.
+        pub fn background_segmentation(&self) -> Result<BackgroundSegmentation, NokhwaError> {
+            let property = self.extended_property::<u64>(BACKGROUND_SEGMENTATION_ID)?;
+            Ok(BackgroundSegmentation::from_flags(property.header.flags))
+        }
+
+        /// Sets the background segmentation of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control, or does not support `segmentation`.
+        pub fn set_background_segmentation(
+            &self,
+            segmentation: BackgroundSegmentation,
+        ) -> Result<(), NokhwaError> {
+            self.set_extended_property(BACKGROUND_SEGMENTATION_ID, segmentation.flags(), 0_u64)
+        }
+
+        /// Gets the digital window of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control.
+        pub fn digital_window(&self) -> Result<DigitalWindow, NokhwaError> {
+            let property = self.extended_property::<DigitalWindowSetting>(DIGITAL_WINDOW_ID)?;
+            Ok(if property.header.flags & 1 == 0 {
+                DigitalWindow::Manual {
+                    origin_x: property.value.origin_x,
+                    origin_y: property.value.origin_y,
+                    size: property.value.window_size,
+                }
+            } else {
+                DigitalWindow::AutoFaceFraming
+            })
+        }
+
+        /// Sets the digital window of the camera.
+        /// # Errors
+        /// This errors if the camera does not have the control, or does not support `window`.
+        pub fn set_digital_window(&self, window: DigitalWindow) -> Result<(), NokhwaError> {
+            let (flags, setting) = match window {
+                DigitalWindow::Manual {
+                    origin_x,
+                    origin_y,
+                    size,
+                } => (
+                    0,
+                    DigitalWindowSetting {
+                        origin_x,
+                        origin_y,
+                        window_size: size,
+                        reserved: 0,
+                    },
+                ),
+                DigitalWindow::AutoFaceFraming => (
+                    1,
+                    self.extended_property::<DigitalWindowSetting>(DIGITAL_WINDOW_ID)?
+                        .value,
+                ),
+            };
+            self.set_extended_property(DIGITAL_WINDOW_ID, flags, setting)
+        }
+
+        #[allow(clippy::cast_sign_loss)]
+        pub fn format_refreshed(&mut self) -> Result<CameraFormat, NokhwaError> {
+            match unsafe { self.source_reader.GetCurrentMediaType(self.stream) } {
+                Ok(media_type) => {
+                    let resolution = match unsafe { media_type.GetUINT64(&MF_MT_FRAME_SIZE) } {
+                        Ok(res) => {
+                            let width = (res >> 32) as u32;
+                            let height = ((res << 32) >> 32) as u32;
+
+                            Resolution {
+                                width_x: width,
+                                height_y: height,
                             }
                         }
                         Err(why) => {
@@ -957,7 +1810,7 @@ pub mod wmf {
                     };
 
                     let frame_rate = match unsafe { media_type.GetUINT64(&MF_MT_FRAME_RATE) } {
-                        Ok(fps) => fps as u32,
+                        Ok(ratio) => frame_rate_from_ratio(ratio),
                         Err(why) => {
                             return Err(NokhwaError::GetPropertyError {
                                 property: "MF_MT_FRAME_RATE".to_string(),
@@ -966,23 +1819,27 @@ pub mod wmf {
                         }
                     };
 
-                    let format = match unsafe { media_type.GetGUID(&MF_MT_SUBTYPE) } {
-                        Ok(fcc) => guid_to_fourcc(fcc),
-                        Err(why) => {
-                            return Err(NokhwaError::GetPropertyError {
-                                property: "MF_MT_SUBTYPE".to_string(),
-                                error: why.to_string(),
-                            })
-                        }
+                    // while decoding, the current type is the decoded one, not what the camera sends
+                    let format = match self.decoded_output {
+                        Some(_) => self.device_format.format(),
+                        None => match unsafe { media_type.GetGUID(&MF_MT_SUBTYPE) } {
+                            Ok(fcc) => guid_to_fourcc(fcc),
+                            Err(why) => {
+                                return Err(NokhwaError::GetPropertyError {
+                                    property: "MF_MT_SUBTYPE".to_string(),
+                                    error: why.to_string(),
+                                })
+                            }
+                        },
                     };
 
-                    let cfmt = CameraFormat::new(resolution, format, frame_rate);
+                    let cfmt = CameraFormat::new_exact(resolution, format, frame_rate);
                     self.device_format = cfmt;
 
                     Ok(cfmt)
                 }
                 Err(why) => Err(NokhwaError::GetPropertyError {
-                    property: "MF_SOURCE_READER_FIRST_VIDEO_STREAM".to_string(),
+                    property: "MF_SOURCE_READER_CURRENT_TYPE".to_string(),
                     error: why.to_string(),
                 }),
             }
@@ -1007,14 +1864,10 @@ pub mod wmf {
             // set relevant things
             let resolution = (u64::from(format.resolution().width_x) << 32_u64)
                 + u64::from(format.resolution().height_y);
-            let fps = {
-                let frame_rate_u64 = 0_u64;
-                let mut bytes: [u8; 8] = frame_rate_u64.to_le_bytes();
-                bytes[7] = format.frame_rate() as u8;
-                bytes[3] = 0x01;
-                u64::from_le_bytes(bytes)
-            };
-            let guid = fourcc_to_guid(format.format());
+            let fps = frame_rate_to_ratio(format.exact_frame_rate());
+            let guid = self
+                .native_subtype(format.format())
+                .or_else(|| fourcc_to_guid(format.format()));
             if guid.is_none() {
                 return Err(NokhwaError::SetPropertyError {
                     property: "MF_MT_SUBTYPE".to_string(),
@@ -1068,40 +1921,294 @@ pub mod wmf {
             }
 
             if let Err(why) = unsafe {
-                self.source_reader.SetCurrentMediaType(
-                    MEDIA_FOUNDATION_FIRST_VIDEO_STREAM,
-                    None,
-                    &media_type,
-                )
+                self.source_reader
+                    .SetCurrentMediaType(self.stream, None, &media_type)
             } {
                 return Err(NokhwaError::SetPropertyError {
-                    property: "MEDIA_FOUNDATION_FIRST_VIDEO_STREAM".to_string(),
+                    property: "MF_SOURCE_READER_CURRENT_TYPE".to_string(),
                     value: format!("{media_type:?}"),
                     error: why.to_string(),
-                });
+                }
+                .with_os_error(PlatformError::HResult(why.code().0)));
+            }
+            if let Some(output) = self.decoded_output {
+                self.set_output_type(output, resolution)?;
             }
             self.device_format = format;
             self.format_refreshed()?;
             Ok(())
         }
 
+        // asks the reader to decode the native type set above into `output`
+        fn set_output_type(&self, output: FourCC, frame_size: u64) -> Result<(), NokhwaError> {
+            let Some(subtype) = fourcc_to_guid(output) else {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_SUBTYPE".to_string(),
+                    value: output.to_string(),
+                    error: "Unknown MF_MT_SUBTYPE".to_string(),
+                });
+            };
+            let media_type: IMFMediaType = match unsafe { MFCreateMediaType() } {
+                Ok(mt) => mt,
+                Err(why) => {
+                    return Err(NokhwaError::StructureError {
+                        structure: "IMFMediaType".to_string(),
+                        error: why.to_string(),
+                    })
+                }
+            };
+            if let Err(why) = unsafe { media_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_MAJOR_TYPE".to_string(),
+                    value: "MFMediaType_Video".to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe { media_type.SetGUID(&MF_MT_SUBTYPE, &subtype) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_SUBTYPE".to_string(),
+                    value: output.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe { media_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_FRAME_SIZE".to_string(),
+                    value: frame_size.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe {
+                self.source_reader
+                    .SetCurrentMediaType(self.stream, None, &media_type)
+            } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_SOURCE_READER_CURRENT_TYPE".to_string(),
+                    value: output.to_string(),
+                    error: why.to_string(),
+                }
+                .with_kind(ErrorKind::FormatUnsupported)
+                .with_os_error(PlatformError::HResult(why.code().0)));
+            }
+            Ok(())
+        }
+
+        /// The format frames are read as, which is [`decoded_output()`](MediaFoundationDevice::decoded_output) if
+        /// the reader decodes, otherwise the format of the camera.
+        #[must_use]
+        pub fn output_format(&self) -> FourCC {
+            self.decoded_output
+                .unwrap_or_else(|| self.device_format.format())
+        }
+
+        /// The format the source reader decodes frames into, if it does.
+        #[must_use]
+        pub fn decoded_output(&self) -> Option<FourCC> {
+            self.decoded_output
+        }
+
+        /// Lets the source reader decode frames into `output`, which can be [`NV12`](pixel_format::NV12) or
+        /// [`BGRA`](pixel_format::BGRA), or back to reading them as the camera sends them with `None`.
+        ///
+        /// The reader uses Media Foundation's decoders, so MJPEG and H.264 are decoded on the GPU where there
+        /// is a hardware transform for them. This also enables the video processor needed by
+        /// [`set_mirrored()`](MediaFoundationDevice::set_mirrored) and [`set_crop()`](MediaFoundationDevice::set_crop).
+        /// # Errors
+        /// This errors if the stream is open, `output` is not a format the reader decodes into, or the reader
+        /// cannot decode the current format of the camera into it.
+        pub fn set_decoded_output(&mut self, output: Option<FourCC>) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_READWRITE_DISABLE_CONVERTERS".to_string(),
+                    value: format!("{output:?}"),
+                    error: "Cannot change the decoded output while the stream is open".to_string(),
+                });
+            }
+            if let Some(format) =
+                output.filter(|f| ![pixel_format::NV12, pixel_format::BGRA].contains(f))
+            {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_SUBTYPE".to_string(),
+                    value: format.to_string(),
+                    error: "The source reader only decodes into NV12 or BGRA".to_string(),
+                }
+                .with_kind(ErrorKind::FormatUnsupported));
+            }
+            if output == self.decoded_output {
+                return Ok(());
+            }
+            if output.is_none() && self.d3d_manager().is_some() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_READWRITE_DISABLE_CONVERTERS".to_string(),
+                    value: "None".to_string(),
+                    error: "Direct3D 11 output needs the source reader to decode".to_string(),
+                });
+            }
+            self.recreate_source_reader(output)
+        }
+
+        // replaces the source reader, e.g. to decode into another format, and puts the old one back if the format
+        // cannot be set on the new one
+        fn recreate_source_reader(&mut self, output: Option<FourCC>) -> Result<(), NokhwaError> {
+            let source_reader =
+                create_source_reader(&self.media_source, output, self.d3d_manager())?;
+            let previous = std::mem::replace(&mut self.source_reader, source_reader);
+            let previous_output = std::mem::replace(&mut self.decoded_output, output);
+            self.uncropped_resolution = None;
+            if self.device_format == CameraFormat::default() {
+                return Ok(());
+            }
+            if let Err(why) = self.set_format(self.device_format) {
+                self.source_reader = previous;
+                self.decoded_output = previous_output;
+                return Err(why);
+            }
+            Ok(())
+        }
+
+        #[cfg(feature = "d3d11")]
+        fn d3d_manager(&self) -> Option<&IMFDXGIDeviceManager> {
+            self.d3d11.as_ref().map(|d3d11| &d3d11.manager)
+        }
+
+        #[cfg(not(feature = "d3d11"))]
+        #[allow(clippy::unused_self)]
+        fn d3d_manager(&self) -> Option<&IMFDXGIDeviceManager> {
+            None
+        }
+
+        /// Whether frames are decoded on a Direct3D 11 device, see
+        /// [`set_d3d11_output()`](MediaFoundationDevice::set_d3d11_output).
+        #[cfg(feature = "d3d11")]
+        #[must_use]
+        pub fn d3d11_output(&self) -> bool {
+            self.d3d11.is_some()
+        }
+
+        /// Lets the source reader decode frames on a Direct3D 11 device of its own, so they can be read as textures
+        /// with [`read_texture()`](MediaFoundationDevice::read_texture) without going through system memory.
+        ///
+        /// The reader has to decode for this, so the decoded output becomes [`NV12`](pixel_format::NV12) if it was
+        /// not set, and stays that when this is turned off again. [`raw_bytes()`](MediaFoundationDevice::raw_bytes)
+        /// still works, copying each texture back from the GPU.
+        /// # Errors
+        /// This errors if the stream is open, there is no hardware Direct3D 11 device, or the reader cannot decode
+        /// the current format of the camera on it.
+        #[cfg(feature = "d3d11")]
+        pub fn set_d3d11_output(&mut self, enabled: bool) -> Result<(), NokhwaError> {
+            if self.is_stream_open() {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_SOURCE_READER_D3D_MANAGER".to_string(),
+                    value: enabled.to_string(),
+                    error: "Cannot change the Direct3D 11 output while the stream is open"
+                        .to_string(),
+                });
+            }
+            if enabled == self.d3d11.is_some() {
+                return Ok(());
+            }
+
+            let d3d11 = if enabled {
+                Some(D3D11Output::new()?)
+            } else {
+                None
+            };
+            let output = self.decoded_output.or(Some(pixel_format::NV12));
+            let previous = std::mem::replace(&mut self.d3d11, d3d11);
+            if let Err(why) = self.recreate_source_reader(output) {
+                self.d3d11 = previous;
+                return Err(why);
+            }
+            Ok(())
+        }
+
+        /// Reads the next frame as a Direct3D 11 texture, see [`D3D11Texture`].
+        ///
+        /// The frame is copied on the GPU into one of a few shareable textures, which are reused in turn, so a
+        /// [`D3D11Texture`] is overwritten once [`SHARED_TEXTURE_COUNT`] more frames have been read.
+        /// # Errors
+        /// This errors if [`set_d3d11_output()`](MediaFoundationDevice::set_d3d11_output) is off, reading the
+        /// frame fails, or the frame is not a texture.
+        #[cfg(feature = "d3d11")]
+        pub fn read_texture(&mut self) -> Result<D3D11Texture, NokhwaError> {
+            if self.d3d11.is_none() {
+                return Err(NokhwaError::ReadFrameError(
+                    "Direct3D 11 output is off".to_string(),
+                ));
+            }
+            let sample = self.read_sample()?;
+            let read_error = |why: windows::core::Error| {
+                NokhwaError::ReadFrameError(why.to_string())
+                    .with_os_error(PlatformError::HResult(why.code().0))
+            };
+
+            let dxgi_buffer = unsafe { sample.GetBufferByIndex(0) }
+                .and_then(|buffer| buffer.cast::<IMFDXGIBuffer>())
+                .map_err(read_error)?;
+            let source = unsafe {
+                let mut receiver: MaybeUninit<ID3D11Texture2D> = MaybeUninit::uninit();
+                let ptr_receiver = receiver.as_mut_ptr();
+                dxgi_buffer
+                    .GetResource(
+                        &ID3D11Texture2D::IID,
+                        ptr_receiver.cast::<ID3D11Texture2D>().cast::<*mut c_void>(),
+                    )
+                    .map_err(read_error)?;
+                receiver.assume_init()
+            };
+            let subresource = unsafe { dxgi_buffer.GetSubresourceIndex() }.map_err(read_error)?;
+
+            let format = self.output_format();
+            let sample_time = self.last_sample_time();
+            match self.d3d11.as_mut() {
+                Some(d3d11) => d3d11.copy_to_shared(&source, subresource, format, sample_time),
+                None => Err(NokhwaError::ReadFrameError(
+                    "Direct3D 11 output is off".to_string(),
+                )),
+            }
+        }
+
+        // Several subtypes map to the same FourCC, e.g. Y800 and L8 are both GRAY, so look for the one the camera has.
+        fn native_subtype(&self, fourcc: FourCC) -> Option<GUID> {
+            (0..)
+                .map_while(|index| unsafe {
+                    self.source_reader
+                        .GetNativeMediaType(self.stream, index)
+                        .ok()
+                })
+                .filter_map(|media_type| unsafe { media_type.GetGUID(&MF_MT_SUBTYPE).ok() })
+                .find(|subtype| guid_to_fourcc(*subtype) == fourcc)
+        }
+
         pub fn is_stream_open(&self) -> bool {
             self.is_open.get()
         }
 
         pub fn start_stream(&mut self) -> Result<(), NokhwaError> {
-            if let Err(why) = unsafe {
-                self.source_reader
-                    .SetStreamSelection(MEDIA_FOUNDATION_FIRST_VIDEO_STREAM, true)
-            } {
-                return Err(NokhwaError::OpenStreamError(why.to_string()));
+            // the reader starts with the first video stream selected
+            if self.stream != MEDIA_FOUNDATION_FIRST_VIDEO_STREAM {
+                if let Err(why) = unsafe {
+                    self.source_reader
+                        .SetStreamSelection(MF_SOURCE_READER_ALL_STREAMS, false)
+                } {
+                    return Err(NokhwaError::OpenStreamError(describe_access_error(&why))
+                        .with_os_error(PlatformError::HResult(why.code().0)));
+                }
+            }
+            if let Err(why) = unsafe { self.source_reader.SetStreamSelection(self.stream, true) } {
+                return Err(NokhwaError::OpenStreamError(describe_access_error(&why))
+                    .with_os_error(PlatformError::HResult(why.code().0)));
             }
 
             self.is_open.set(true);
             Ok(())
         }
 
-        pub fn raw_bytes(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+        fn read_sample(&mut self) -> Result<IMFSample, NokhwaError> {
+            if self.is_disconnected() {
+                return Err(disconnected_error());
+            }
+            self.poll_controls();
             let mut imf_sample: Option<IMFSample> = match unsafe { MFCreateSample() } {
                 Ok(sample) => Some(sample),
                 Err(why) => {
@@ -1113,7 +2220,7 @@ pub mod wmf {
                 loop {
                     if let Err(why) = unsafe {
                         self.source_reader.ReadSample(
-                            MEDIA_FOUNDATION_FIRST_VIDEO_STREAM,
+                            self.stream,
                             0,
                             None,
                             Some(&mut stream_flags),
@@ -1121,12 +2228,26 @@ pub mod wmf {
                             Some(&mut imf_sample),
                         )
                     } {
-                        return Err(NokhwaError::ReadFrameError(why.to_string()));
+                        let error = NokhwaError::ReadFrameError(describe_access_error(&why))
+                            .with_os_error(PlatformError::HResult(why.code().0));
+                        if error.kind() == ErrorKind::Disconnected {
+                            self.disconnected.store(true, Ordering::Relaxed);
+                        }
+                        return Err(error);
                     }
 
                     if imf_sample.is_some() {
                         break;
                     }
+
+                    // no sample will ever come after these, so waiting for one would hang
+                    #[allow(clippy::cast_sign_loss)]
+                    let stopped =
+                        (MF_SOURCE_READERF_ERROR.0 | MF_SOURCE_READERF_ENDOFSTREAM.0) as u32;
+                    if stream_flags & stopped != 0 {
+                        self.disconnected.store(true, Ordering::Relaxed);
+                        return Err(disconnected_error());
+                    }
                 }
             }
 
@@ -1138,6 +2259,13 @@ pub mod wmf {
                 }
             };
 
+            self.last_sample_time = unsafe { imf_sample.GetSampleTime() }.ok();
+            Ok(imf_sample)
+        }
+
+        pub fn raw_bytes(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+            let imf_sample = self.read_sample()?;
+
             let buffer = match unsafe { imf_sample.ConvertToContiguousBuffer() } {
                 Ok(buf) => buf,
                 Err(why) => return Err(NokhwaError::ReadFrameError(why.to_string())),
@@ -1172,59 +2300,1652 @@ pub mod wmf {
                 ) as &[u8]);
             }
 
+            // `Lock` packs the rows of 2D buffers, other buffers keep the padding of the media type
+            self.last_row_stride = if buffer.cast::<IMF2DBuffer>().is_ok() {
+                None
+            } else {
+                self.default_stride()
+            };
+
             Ok(Cow::from(data_slice))
         }
 
-        pub fn stop_stream(&mut self) {
-            self.is_open.set(false);
+        /// The distance in bytes between the starts of two rows of the last frame read by
+        /// [`raw_bytes()`](MediaFoundationDevice::raw_bytes), if the camera said how far apart they are.
+        #[must_use]
+        pub fn last_row_stride(&self) -> Option<usize> {
+            self.last_row_stride
         }
-    }
 
-    impl Drop for MediaFoundationDevice {
-        fn drop(&mut self) {
-            // swallow errors
-            unsafe {
-                if self
-                    .source_reader
-                    .Flush(MEDIA_FOUNDATION_FIRST_VIDEO_STREAM)
-                    .is_ok()
-                {}
+        /// The `MF_MT_DEFAULT_STRIDE` of the frames read, unless they are bottom-up.
+        fn default_stride(&self) -> Option<usize> {
+            let media_type = unsafe { self.source_reader.GetCurrentMediaType(self.stream) }.ok()?;
+            // a negative default stride means the frame is bottom-up
+            unsafe { media_type.GetUINT32(&MF_MT_DEFAULT_STRIDE) }
+                .ok()
+                .and_then(|stride| i32::try_from(stride).ok())
+                .and_then(|stride| usize::try_from(stride).ok())
+                .filter(|stride| *stride > 0)
+        }
 
-                // decrement refcnt
-                if CAMERA_REFCNT.load(Ordering::SeqCst) > 0 {
-                    CAMERA_REFCNT.store(CAMERA_REFCNT.load(Ordering::SeqCst) - 1, Ordering::SeqCst);
+        /// The sample time of the last frame read by [`raw_bytes()`](MediaFoundationDevice::raw_bytes), on the [`system_time()`] clock.
+        #[must_use]
+        pub fn last_sample_time(&self) -> Option<Duration> {
+            self.last_sample_time
+                .and_then(|time| u64::try_from(time).ok())
+                .map(|time| Duration::from_nanos(time.saturating_mul(100)))
+        }
+
+        /// Mirrors frames horizontally with the video processor of the source reader, if it has one.
+        /// # Errors
+        /// This returns [`NokhwaError::UnsupportedOperationError`] if the source reader has no video processor, which is
+        /// the case while converters are disabled.
+        // the video processor is only inserted when the reader is allowed to convert formats
+        fn video_processor(&self) -> Result<IMFVideoProcessorControl, NokhwaError> {
+            let unsupported =
+                || NokhwaError::UnsupportedOperationError(ApiBackend::MediaFoundation);
+            let reader = self
+                .source_reader
+                .cast::<IMFSourceReaderEx>()
+                .map_err(|_| unsupported())?;
+
+            let mut index = 0;
+            loop {
+                let mut transform: Option<IMFTransform> = None;
+                // this fails with MF_E_INVALIDINDEX once every transform has been looked at
+                if unsafe { reader.GetTransformForStream(self.stream, index, None, &mut transform) }
+                    .is_err()
+                {
+                    return Err(unsupported());
                 }
-                if CAMERA_REFCNT.load(Ordering::SeqCst) == 0 {
-                    #[allow(clippy::let_underscore_drop)]
-                    let _ = de_initialize_mf();
+                if let Some(control) = transform
+                    .and_then(|transform| transform.cast::<IMFVideoProcessorControl>().ok())
+                {
+                    return Ok(control);
                 }
+                index += 1;
             }
         }
-    }
-}
 
-#[cfg(any(not(windows), feature = "docs-only"))]
-#[allow(clippy::missing_errors_doc)]
-#[allow(clippy::unused_self)]
-#[allow(clippy::needless_pass_by_value)]
-#[allow(clippy::must_use_candidate)]
-pub mod wmf {
-    use nokhwa_core::error::NokhwaError;
-    use nokhwa_core::types::{
-        CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
-        KnownCameraControl,
-    };
-    use std::borrow::Cow;
+        pub fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+            let mirror = if mirrored {
+                MIRROR_HORIZONTAL
+            } else {
+                MIRROR_NONE
+            };
+            unsafe { self.video_processor()?.SetMirror(mirror) }.map_err(|why| {
+                NokhwaError::SetPropertyError {
+                    property: "MF_VIDEO_PROCESSOR_MIRROR".to_string(),
+                    value: mirrored.to_string(),
+                    error: why.to_string(),
+                }
+            })
+        }
 
-    pub fn initialize_mf() -> Result<(), NokhwaError> {
-        Err(NokhwaError::NotImplementedError(
-            "Not on windows".to_string(),
-        ))
-    }
+        pub fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+            let processor = self.video_processor()?;
+            let uncropped = self
+                .uncropped_resolution
+                .unwrap_or_else(|| self.device_format.resolution());
+            let source = crop.map(|rect| RECT {
+                left: i32::try_from(rect.x()).unwrap_or(i32::MAX),
+                top: i32::try_from(rect.y()).unwrap_or(i32::MAX),
+                right: i32::try_from(rect.x().saturating_add(rect.width())).unwrap_or(i32::MAX),
+                bottom: i32::try_from(rect.y().saturating_add(rect.height())).unwrap_or(i32::MAX),
+            });
+            unsafe { processor.SetSourceRectangle(source.as_ref().map(std::ptr::from_ref)) }
+                .map_err(|why| NokhwaError::SetPropertyError {
+                    property: "MF_VIDEO_PROCESSOR_SOURCE_RECTANGLE".to_string(),
+                    value: format!("{crop:?}"),
+                    error: why.to_string(),
+                })?;
 
-    pub fn de_initialize_mf() -> Result<(), NokhwaError> {
-        Err(NokhwaError::NotImplementedError(
-            "Not on windows".to_string(),
+            // the processor scales the source rectangle to the output type, so shrink that too
+            let mut format = self.device_format;
+            format.set_resolution(crop.map_or(uncropped, |rect| rect.resolution()));
+            self.set_format(format)?;
+            self.uncropped_resolution = crop.map(|_| uncropped);
+            Ok(())
+        }
+
+        /// The `IAMCameraControl` of the source, which pan, tilt and zoom go through.
+        fn camera_control_interface(&self) -> Result<IAMCameraControl, NokhwaError> {
+            let mut receiver: MaybeUninit<IAMCameraControl> = MaybeUninit::uninit();
+            let ptr_receiver = receiver.as_mut_ptr();
+            unsafe {
+                if let Err(why) = self.source_reader.GetServiceForStream(
+                    MF_SOURCE_READER_MEDIASOURCE,
+                    &GUID_NULL,
+                    &IAMCameraControl::IID,
+                    ptr_receiver
+                        .cast::<IAMCameraControl>()
+                        .cast::<*mut c_void>(),
+                ) {
+                    return Err(NokhwaError::GetPropertyError {
+                        property: "IAMCameraControl".to_string(),
+                        error: why.to_string(),
+                    });
+                }
+                Ok(receiver.assume_init())
+            }
+        }
+
+        /// The `IAMVideoProcAmp` of the source, which brightness and white balance go through.
+        fn video_proc_amp_interface(&self) -> Result<IAMVideoProcAmp, NokhwaError> {
+            let mut receiver: MaybeUninit<IAMVideoProcAmp> = MaybeUninit::uninit();
+            let ptr_receiver = receiver.as_mut_ptr();
+            unsafe {
+                if let Err(why) = self.source_reader.GetServiceForStream(
+                    MF_SOURCE_READER_MEDIASOURCE,
+                    &GUID_NULL,
+                    &IAMVideoProcAmp::IID,
+                    ptr_receiver.cast::<IAMVideoProcAmp>().cast::<*mut c_void>(),
+                ) {
+                    return Err(NokhwaError::GetPropertyError {
+                        property: "IAMVideoProcAmp".to_string(),
+                        error: why.to_string(),
+                    });
+                }
+                Ok(receiver.assume_init())
+            }
+        }
+
+        /// Sets `control` to automatic, or with `manual` to manual at the value it returns for the `(min, max)` range
+        /// of the control, clamped to that range. Automatic keeps the current value, the driver takes over from there.
+        #[allow(clippy::cast_possible_truncation)]
+        fn set_control_mode(
+            &mut self,
+            control: KnownCameraControl,
+            manual: Option<&dyn Fn(i32, i32) -> f64>,
+        ) -> Result<(), NokhwaError> {
+            let (mut min, mut max, mut step, mut default, mut capabilities) = (0, 0, 0, 0, 0);
+            let (mut value, mut flags) = (0, 0);
+            let error = |why: windows::core::Error| NokhwaError::SetPropertyError {
+                property: control.to_string(),
+                value: if manual.is_some() {
+                    "Manual"
+                } else {
+                    "Automatic"
+                }
+                .to_string(),
+                error: why.to_string(),
+            };
+            let flag = if manual.is_some() {
+                CameraControl_Flags_Manual.0
+            } else {
+                CameraControl_Flags_Auto.0
+            };
+            let target = |min: i32, max: i32, value: i32| {
+                manual.map_or(value, |manual| {
+                    (manual(min, max).round() as i32).clamp(min, max)
+                })
+            };
+            match kcc_to_i32(control) {
+                Some(MFControlId::ProcAmpRange(id)) => {
+                    let video_proc_amp = self.video_proc_amp_interface()?;
+                    unsafe {
+                        video_proc_amp
+                            .GetRange(
+                                id,
+                                &mut min,
+                                &mut max,
+                                &mut step,
+                                &mut default,
+                                &mut capabilities,
+                            )
+                            .map_err(error)?;
+                        video_proc_amp
+                            .Get(id, &mut value, &mut flags)
+                            .map_err(error)?;
+                        video_proc_amp
+                            .Set(id, target(min, max, value), flag)
+                            .map_err(error)?;
+                    }
+                }
+                Some(MFControlId::CCValue(id) | MFControlId::CCRange(id)) => {
+                    let camera_control = self.camera_control_interface()?;
+                    unsafe {
+                        camera_control
+                            .GetRange(
+                                id,
+                                &mut min,
+                                &mut max,
+                                &mut step,
+                                &mut default,
+                                &mut capabilities,
+                            )
+                            .map_err(error)?;
+                        camera_control
+                            .Get(id, &mut value, &mut flags)
+                            .map_err(error)?;
+                        camera_control
+                            .Set(id, target(min, max, value), flag)
+                            .map_err(error)?;
+                    }
+                }
+                _ => {
+                    return Err(NokhwaError::UnsupportedOperationError(
+                        ApiBackend::MediaFoundation,
+                    ))
+                }
+            }
+            self.remember_control(control);
+            Ok(())
+        }
+
+        pub fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+            let camera_control = self.camera_control_interface()?;
+            let range = |property: i32| {
+                let (mut min, mut max, mut step, mut default, mut flag) = (0, 0, 0, 0, 0);
+                unsafe {
+                    camera_control.GetRange(
+                        property,
+                        &mut min,
+                        &mut max,
+                        &mut step,
+                        &mut default,
+                        &mut flag,
+                    )
+                }
+                .ok()?;
+                Some(PtzRange::new(
+                    f64::from(min),
+                    f64::from(max),
+                    f64::from(step),
+                    f64::from(default),
+                ))
+            };
+            let pan = range(CameraControl_Pan.0);
+            let tilt = range(CameraControl_Tilt.0);
+            Ok(PtzCapabilities::new(pan, tilt, range(CameraControl_Zoom.0))
+                .with_relative_pan_tilt(pan.is_some() || tilt.is_some()))
+        }
+
+        pub fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+            let camera_control = self.camera_control_interface()?;
+            set_camera_control_value(&camera_control, CameraControl_Zoom.0, "Zoom", zoom)
+        }
+
+        pub fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+            let camera_control = self.camera_control_interface()?;
+            set_camera_control_value(&camera_control, CameraControl_Pan.0, "Pan", pan)?;
+            set_camera_control_value(&camera_control, CameraControl_Tilt.0, "Tilt", tilt)
+        }
+
+        pub fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+            // UVC cameras only expose absolute pan and tilt through IAMCameraControl, so the offset is added to where
+            // the camera points now
+            let camera_control = self.camera_control_interface()?;
+            for (property, name, offset) in [
+                (CameraControl_Pan.0, "Pan", pan),
+                (CameraControl_Tilt.0, "Tilt", tilt),
+            ] {
+                if offset == 0.0 {
+                    continue;
+                }
+                let (mut value, mut flag) = (0, 0);
+                unsafe { camera_control.Get(property, &mut value, &mut flag) }.map_err(|why| {
+                    NokhwaError::GetPropertyError {
+                        property: name.to_string(),
+                        error: why.to_string(),
+                    }
+                })?;
+                set_camera_control_value(
+                    &camera_control,
+                    property,
+                    name,
+                    f64::from(value) + offset,
+                )?;
+            }
+            Ok(())
+        }
+
+        pub fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+            match exposure {
+                Exposure::Auto => self.set_control_mode(KnownCameraControl::Exposure, None),
+                // CameraControl_Exposure is in log2 seconds, so -5 is 1/32s
+                Exposure::Manual(duration) => self.set_control_mode(
+                    KnownCameraControl::Exposure,
+                    Some(&|_, _| duration.as_secs_f64().log2()),
+                ),
+            }
+        }
+
+        pub fn set_white_balance(
+            &mut self,
+            white_balance: WhiteBalance,
+        ) -> Result<(), NokhwaError> {
+            match white_balance {
+                WhiteBalance::Auto => self.set_control_mode(KnownCameraControl::WhiteBalance, None),
+                WhiteBalance::Manual(kelvin) => self.set_control_mode(
+                    KnownCameraControl::WhiteBalance,
+                    Some(&|_, _| f64::from(kelvin)),
+                ),
+            }
+        }
+
+        pub fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+            match focus {
+                Focus::Auto => self.set_control_mode(KnownCameraControl::Focus, None),
+                Focus::Manual(position) => self.set_control_mode(
+                    KnownCameraControl::Focus,
+                    Some(&|min, max| fraction_of_range(min, max, position)),
+                ),
+            }
+        }
+
+        pub fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+            self.set_control_mode(
+                KnownCameraControl::Brightness,
+                Some(&|min, max| fraction_of_range(min, max, brightness)),
+            )
+        }
+
+        /// Whether the camera went away, found either by [`raw_bytes()`](MediaFoundationDevice::raw_bytes) or by
+        /// [`watch_connection()`](MediaFoundationDevice::watch_connection). A disconnected device stays that way, even
+        /// if it is plugged back in, and has to be opened again.
+        #[must_use]
+        pub fn is_disconnected(&self) -> bool {
+            self.disconnected.load(Ordering::Relaxed)
+        }
+
+        /// Calls `on_event` with [`SessionEvent::Disconnected`] when this camera is unplugged, and
+        /// [`SessionEvent::Reconnected`] when it comes back, until [`stop_stream()`](MediaFoundationDevice::stop_stream).
+        /// Once it is unplugged, [`raw_bytes()`](MediaFoundationDevice::raw_bytes) returns an
+        /// [`ErrorKind::Disconnected`] error.
+        ///
+        /// `on_event` is called on the thread of a [`DeviceNotifier`]. This replaces the previous watch.
+        /// # Errors
+        /// If the notifier could not be created, this will error.
+        pub fn watch_connection(
+            &mut self,
+            on_event: impl Fn(SessionEvent) + Send + 'static,
+        ) -> Result<(), NokhwaError> {
+            self.connection_watch = None;
+            // the symbolic link, without the stream of a sensor camera
+            let symlink = self
+                .device_specifier
+                .unique_id()
+                .split('|')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            let disconnected = self.disconnected.clone();
+            let present = AtomicBool::new(true);
+            self.connection_watch = Some(DeviceNotifier::new(move || {
+                // the notification does not say which camera changed, so look for this one. The streams of sensor
+                // cameras are not looked at, as that needs the camera, which is in use
+                let Ok(devices) = query_activate_pointers(None).and_then(|mut devices| {
+                    devices.extend(query_activate_pointers(Some(KSCATEGORY_SENSOR_CAMERA))?);
+                    Ok(devices)
+                }) else {
+                    return;
+                };
+                let found = devices.iter().any(|device| {
+                    activate_to_descriptors(device).is_ok_and(|info| info.unique_id() == symlink)
+                });
+                if found == present.swap(found, Ordering::Relaxed) {
+                    return;
+                }
+                if found {
+                    on_event(SessionEvent::Reconnected);
+                } else {
+                    disconnected.store(true, Ordering::Relaxed);
+                    on_event(SessionEvent::Disconnected);
+                }
+            })?);
+            Ok(())
+        }
+
+        /// Calls `on_event` with [`SessionEvent::ControlChanged`] when a control is changed by something other than
+        /// [`set_control()`](MediaFoundationDevice::set_control), such as the automatic exposure of the driver or
+        /// another application, until [`stop_stream()`](MediaFoundationDevice::stop_stream).
+        ///
+        /// Media Foundation does not notify about UVC controls changing, so they are read again every
+        /// [`CONTROL_POLL_INTERVAL`] while frames are read, and `on_event` is called on the thread reading them.
+        /// This replaces the previous watch.
+        pub fn watch_controls(&mut self, on_event: impl Fn(SessionEvent) + Send + 'static) {
+            self.control_watch = Some(ControlWatch {
+                on_event: Box::new(on_event),
+                values: self.control_values(),
+                last_poll: Instant::now(),
+            });
+        }
+
+        fn control_values(&self) -> Vec<(KnownCameraControl, ControlValueSetter)> {
+            all_known_camera_controls()
+                .into_iter()
+                .filter_map(|control| {
+                    self.control(control)
+                        .ok()
+                        .map(|current| (control, current.value()))
+                })
+                .collect()
+        }
+
+        fn poll_controls(&mut self) {
+            let due = self
+                .control_watch
+                .as_ref()
+                .is_some_and(|watch| watch.last_poll.elapsed() >= CONTROL_POLL_INTERVAL);
+            if !due {
+                return;
+            }
+            let values = self.control_values();
+            if let Some(watch) = &mut self.control_watch {
+                for (control, value) in &values {
+                    let changed = watch
+                        .values
+                        .iter()
+                        .any(|(previous, old)| previous == control && old != value);
+                    if changed {
+                        (watch.on_event)(SessionEvent::ControlChanged(*control));
+                    }
+                }
+                watch.values = values;
+                watch.last_poll = Instant::now();
+            }
+        }
+
+        // so a control set through this device is not reported as changed
+        fn remember_control(&mut self, control: KnownCameraControl) {
+            if self.control_watch.is_none() {
+                return;
+            }
+            let Ok(current) = self.control(control) else {
+                return;
+            };
+            if let Some(watch) = &mut self.control_watch {
+                match watch.values.iter_mut().find(|(id, _)| *id == control) {
+                    Some((_, value)) => *value = current.value(),
+                    None => watch.values.push((control, current.value())),
+                }
+            }
+        }
+
+        pub fn stop_stream(&mut self) {
+            self.connection_watch = None;
+            self.control_watch = None;
+            self.is_open.set(false);
+        }
+    }
+
+    /// How often [`watch_controls()`](MediaFoundationDevice::watch_controls) reads the controls again.
+    pub const CONTROL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// The controls [`watch_controls()`](MediaFoundationDevice::watch_controls) last read, and when.
+    struct ControlWatch {
+        on_event: Box<dyn Fn(SessionEvent) + Send>,
+        values: Vec<(KnownCameraControl, ControlValueSetter)>,
+        last_poll: Instant,
+    }
+
+    fn disconnected_error() -> NokhwaError {
+        NokhwaError::ReadFrameError("The camera was disconnected".to_string())
+            .with_kind(ErrorKind::Disconnected)
+    }
+
+    /// The current `Media Foundation` system time, which capture sources stamp their samples with.
+    #[must_use]
+    pub fn system_time() -> Duration {
+        let time = unsafe { MFGetSystemTime() };
+        Duration::from_nanos(u64::try_from(time).unwrap_or_default().saturating_mul(100))
+    }
+
+    /// Sets the [`ThreadPriority`] of the calling thread.
+    /// # Errors
+    /// If Windows refuses to change the priority, this will error.
+    pub fn set_current_thread_priority(priority: ThreadPriority) -> Result<(), NokhwaError> {
+        let level = match priority {
+            ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+            ThreadPriority::High => THREAD_PRIORITY_ABOVE_NORMAL,
+            ThreadPriority::RealTime => THREAD_PRIORITY_TIME_CRITICAL,
+        };
+        unsafe { SetThreadPriority(GetCurrentThread(), level) }
+            .ok()
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: "Thread priority".to_string(),
+                value: priority.to_string(),
+                error: why.to_string(),
+            })
+    }
+
+    /// Pins the calling thread to the CPU cores with the indices in `cores`, or lets it run on all of them if `cores` is
+    /// empty. Only the cores of the first processor group can be used.
+    /// # Errors
+    /// If a core does not exist, this will error.
+    pub fn set_current_thread_affinity(cores: &[usize]) -> Result<(), NokhwaError> {
+        let error = |why: String| NokhwaError::SetPropertyError {
+            property: "Thread affinity".to_string(),
+            value: format!("{cores:?}"),
+            error: why,
+        };
+        let mask = if cores.is_empty() {
+            let available = std::thread::available_parallelism()
+                .map_or(1, usize::from)
+                .min(usize::BITS as usize);
+            usize::MAX >> (usize::BITS as usize - available)
+        } else {
+            cores.iter().try_fold(0_usize, |mask, core| {
+                1_usize
+                    .checked_shl(u32::try_from(*core).unwrap_or(u32::MAX))
+                    .map(|bit| mask | bit)
+                    .ok_or_else(|| error(format!("There is no core {core}")))
+            })?
+        };
+        if unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } == 0 {
+            return Err(error(windows::core::Error::from_win32().to_string()));
+        }
+        Ok(())
+    }
+
+    /// How many shareable textures [`MediaFoundationDevice::read_texture()`] copies frames into, in turn.
+    #[cfg(feature = "d3d11")]
+    pub const SHARED_TEXTURE_COUNT: usize = 3;
+
+    #[cfg(feature = "d3d11")]
+    fn d3d11_error(structure: &str, why: &windows::core::Error) -> NokhwaError {
+        NokhwaError::StructureError {
+            structure: structure.to_string(),
+            error: why.to_string(),
+        }
+        .with_os_error(PlatformError::HResult(why.code().0))
+    }
+
+    // an NT handle of a shared texture, closed once the last texture using it is dropped
+    #[cfg(feature = "d3d11")]
+    struct SharedHandle(HANDLE);
+
+    #[cfg(feature = "d3d11")]
+    impl Drop for SharedHandle {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    /// A frame read as a Direct3D 11 texture by [`MediaFoundationDevice::read_texture()`].
+    ///
+    /// The texture is on the device of the camera, and shared with an NT handle so that other devices can open it,
+    /// e.g. with `ID3D11Device1::OpenSharedResource1` or `ID3D12Device::OpenSharedHandle`. For `wgpu`, open it on
+    /// the `ID3D12Device` of its DX12 backend and wrap the resource with `wgpu::hal::dx12::Device::texture_from_raw`.
+    ///
+    /// Its format is [`format()`](D3D11Texture::format): `DXGI_FORMAT_NV12` for [`NV12`](pixel_format::NV12),
+    /// `DXGI_FORMAT_B8G8R8A8_UNORM` for [`BGRA`](pixel_format::BGRA).
+    #[cfg(feature = "d3d11")]
+    #[derive(Clone)]
+    pub struct D3D11Texture {
+        texture: ID3D11Texture2D,
+        handle: Arc<SharedHandle>,
+        resolution: Resolution,
+        format: FourCC,
+        sample_time: Option<Duration>,
+    }
+
+    // SAFETY: Direct3D 11 resources are free threaded, and the handle is only closed on drop.
+    #[cfg(feature = "d3d11")]
+    unsafe impl Send for D3D11Texture {}
+    #[cfg(feature = "d3d11")]
+    unsafe impl Sync for D3D11Texture {}
+
+    #[cfg(feature = "d3d11")]
+    impl D3D11Texture {
+        /// The texture, on the Direct3D 11 device of the camera.
+        #[must_use]
+        pub fn texture(&self) -> &ID3D11Texture2D {
+            &self.texture
+        }
+
+        /// The NT handle the texture is shared with. It stays valid as long as this, or a clone of it, is alive.
+        #[must_use]
+        pub fn shared_handle(&self) -> HANDLE {
+            self.handle.0
+        }
+
+        #[must_use]
+        pub fn resolution(&self) -> Resolution {
+            self.resolution
+        }
+
+        #[must_use]
+        pub fn format(&self) -> FourCC {
+            self.format
+        }
+
+        /// The sample time of the frame, on the [`system_time()`] clock.
+        #[must_use]
+        pub fn sample_time(&self) -> Option<Duration> {
+            self.sample_time
+        }
+    }
+
+    #[cfg(feature = "d3d11")]
+    struct D3D11Output {
+        device: ID3D11Device,
+        context: ID3D11DeviceContext,
+        manager: IMFDXGIDeviceManager,
+        copy_done: ID3D11Query,
+        shared: Vec<(ID3D11Texture2D, Arc<SharedHandle>)>,
+        next_shared: usize,
+    }
+
+    #[cfg(feature = "d3d11")]
+    impl D3D11Output {
+        fn new() -> Result<Self, NokhwaError> {
+            let mut device = None;
+            let mut context = None;
+            if let Err(why) = unsafe {
+                D3D11CreateDevice(
+                    None,
+                    D3D_DRIVER_TYPE_HARDWARE,
+                    HINSTANCE::default(),
+                    D3D11_CREATE_DEVICE_VIDEO_SUPPORT | D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                    None,
+                    D3D11_SDK_VERSION,
+                    Some(&mut device),
+                    None,
+                    Some(&mut context),
+                )
+            } {
+                return Err(d3d11_error("D3D11CreateDevice", &why));
+            }
+            let (Some(device), Some(context)) = (device, context) else {
+                return Err(NokhwaError::StructureError {
+                    structure: "D3D11CreateDevice".to_string(),
+                    error: "No device".to_string(),
+                });
+            };
+
+            // the source reader uses the device from its own threads
+            let multithread = device
+                .cast::<ID3D11Multithread>()
+                .map_err(|why| d3d11_error("ID3D11Multithread", &why))?;
+            unsafe {
+                multithread.SetMultithreadProtected(true);
+            }
+
+            let mut reset_token = 0;
+            let mut manager = None;
+            if let Err(why) = unsafe { MFCreateDXGIDeviceManager(&mut reset_token, &mut manager) } {
+                return Err(d3d11_error("MFCreateDXGIDeviceManager", &why));
+            }
+            let Some(manager) = manager else {
+                return Err(NokhwaError::StructureError {
+                    structure: "MFCreateDXGIDeviceManager".to_string(),
+                    error: "No device manager".to_string(),
+                });
+            };
+            if let Err(why) = unsafe { manager.ResetDevice(&device, reset_token) } {
+                return Err(d3d11_error("IMFDXGIDeviceManager::ResetDevice", &why));
+            }
+
+            let copy_done = unsafe {
+                device.CreateQuery(&D3D11_QUERY_DESC {
+                    Query: D3D11_QUERY_EVENT,
+                    MiscFlags: 0,
+                })
+            }
+            .map_err(|why| d3d11_error("ID3D11Device::CreateQuery", &why))?;
+
+            Ok(D3D11Output {
+                device,
+                context,
+                manager,
+                copy_done,
+                shared: Vec::with_capacity(SHARED_TEXTURE_COUNT),
+                next_shared: 0,
+            })
+        }
+
+        fn create_shared(
+            &self,
+            desc: &D3D11_TEXTURE2D_DESC,
+        ) -> Result<(ID3D11Texture2D, Arc<SharedHandle>), NokhwaError> {
+            let shared_desc = D3D11_TEXTURE2D_DESC {
+                Width: desc.Width,
+                Height: desc.Height,
+                MipLevels: 1,
+                ArraySize: 1,
+                Format: desc.Format,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Usage: D3D11_USAGE_DEFAULT,
+                BindFlags: D3D11_BIND_SHADER_RESOURCE,
+                MiscFlags: D3D11_RESOURCE_MISC_SHARED | D3D11_RESOURCE_MISC_SHARED_NTHANDLE,
+                ..D3D11_TEXTURE2D_DESC::default()
+            };
+            let texture = unsafe { self.device.CreateTexture2D(&shared_desc, None) }
+                .map_err(|why| d3d11_error("ID3D11Device::CreateTexture2D", &why))?;
+            let handle = texture
+                .cast::<IDXGIResource1>()
+                .and_then(|resource| unsafe {
+                    resource.CreateSharedHandle(None, DXGI_SHARED_RESOURCE_READ, PCWSTR::null())
+                })
+                .map_err(|why| d3d11_error("IDXGIResource1::CreateSharedHandle", &why))?;
+            Ok((texture, Arc::new(SharedHandle(handle))))
+        }
+
+        fn copy_to_shared(
+            &mut self,
+            source: &ID3D11Texture2D,
+            subresource: u32,
+            format: FourCC,
+            sample_time: Option<Duration>,
+        ) -> Result<D3D11Texture, NokhwaError> {
+            let mut desc = D3D11_TEXTURE2D_DESC::default();
+            unsafe { source.GetDesc(&mut desc) };
+
+            // the frames changed size or format, so the textures of the old ones are of no use
+            let matches = self.shared.first().map_or(true, |(texture, _)| {
+                let mut shared_desc = D3D11_TEXTURE2D_DESC::default();
+                unsafe { texture.GetDesc(&mut shared_desc) };
+                (shared_desc.Width, shared_desc.Height, shared_desc.Format)
+                    == (desc.Width, desc.Height, desc.Format)
+            });
+            if !matches {
+                self.shared.clear();
+                self.next_shared = 0;
+            }
+            if self.shared.len() < SHARED_TEXTURE_COUNT {
+                let shared = self.create_shared(&desc)?;
+                self.shared.push(shared);
+            }
+            let index = self.next_shared % self.shared.len();
+            self.next_shared = (index + 1) % SHARED_TEXTURE_COUNT;
+            let (texture, handle) = self.shared[index].clone();
+
+            unsafe {
+                self.context
+                    .CopySubresourceRegion(&texture, 0, 0, 0, 0, source, subresource, None);
+                self.context.End(&self.copy_done);
+                // other devices do not wait for this one, so the copy has to be done before handing the texture out
+                let mut done = BOOL(0);
+                while self
+                    .context
+                    .GetData(
+                        &self.copy_done,
+                        std::ptr::addr_of_mut!(done).cast(),
+                        std::mem::size_of::<BOOL>() as u32,
+                        0,
+                    )
+                    .is_ok()
+                    && !done.as_bool()
+                {
+                    std::thread::yield_now();
+                }
+            }
+
+            Ok(D3D11Texture {
+                texture,
+                handle,
+                resolution: Resolution::new(desc.Width, desc.Height),
+                format,
+                sample_time,
+            })
+        }
+    }
+
+    impl Drop for MediaFoundationDevice {
+        fn drop(&mut self) {
+            // swallow errors
+            unsafe {
+                if self.source_reader.Flush(self.stream).is_ok() {}
+                // the camera, and its privacy LED, stay active until the source is shut down
+                let _shutdown_err = self.media_source.Shutdown();
+
+                // decrement refcnt
+                if CAMERA_REFCNT.load(Ordering::SeqCst) > 0 {
+                    CAMERA_REFCNT.store(CAMERA_REFCNT.load(Ordering::SeqCst) - 1, Ordering::SeqCst);
+                }
+                if CAMERA_REFCNT.load(Ordering::SeqCst) == 0 {
+                    #[allow(clippy::let_underscore_drop)]
+                    let _ = de_initialize_mf();
+                }
+            }
+        }
+    }
+
+    /// Decodes MJPEG with a Media Foundation transform, preferring hardware (GPU) decoders over the software one
+    /// Windows ships with. Register it with [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+    /// # Quirks
+    /// - Asynchronous (hardware) transforms are driven with blocking event waits, one frame at a time.
+    /// - Transforms that output into Direct3D surfaces rather than system memory are not supported.
+    pub struct MediaFoundationJpegDecoder {
+        transform: IMFTransform,
+        events: Option<IMFMediaEventGenerator>,
+        name: String,
+        configured: Option<(Resolution, FourCC, DecodedLayout)>,
+    }
+
+    /// The stride and allocated rows of the frames a transform outputs.
+    #[derive(Copy, Clone)]
+    struct DecodedLayout {
+        stride: usize,
+        rows: usize,
+    }
+
+    // the transform is only ever used by the thread holding the decoder
+    unsafe impl Send for MediaFoundationJpegDecoder {}
+
+    impl MediaFoundationJpegDecoder {
+        /// Finds the best MJPEG decoding transform, hardware ones first.
+        /// # Errors
+        /// If Media Foundation cannot be initialized or there is no such transform, this will error.
+        pub fn new() -> Result<Self, NokhwaError> {
+            initialize_mf()?;
+
+            let input = MFT_REGISTER_TYPE_INFO {
+                guidMajorType: MFMediaType_Video,
+                guidSubtype: MF_VIDEO_FORMAT_MJPEG,
+            };
+            let mut count: u32 = 0;
+            let mut activates: MaybeUninit<*mut Option<IMFActivate>> = MaybeUninit::uninit();
+            if let Err(why) = unsafe {
+                MFTEnumEx(
+                    MFT_CATEGORY_VIDEO_DECODER,
+                    MFT_ENUM_FLAG_HARDWARE | MFT_ENUM_FLAG_SYNCMFT | MFT_ENUM_FLAG_SORTANDFILTER,
+                    Some(&raw const input),
+                    None,
+                    activates.as_mut_ptr(),
+                    &mut count,
+                )
+            } {
+                return Err(NokhwaError::StructureError {
+                    structure: "MFTEnumEx".to_string(),
+                    error: why.to_string(),
+                });
+            }
+
+            let activates = unsafe { activates.assume_init() };
+            if activates.is_null() {
+                return Err(NokhwaError::NotImplementedError(
+                    "No Media Foundation MJPEG decoder found".to_string(),
+                ));
+            }
+            let candidates = unsafe { std::slice::from_raw_parts_mut(activates, count as usize) };
+            let decoder = candidates
+                .iter()
+                .flatten()
+                .find_map(|activate| Self::from_activate(activate).ok());
+            unsafe {
+                // release our references to every candidate, then the array itself
+                std::ptr::drop_in_place(candidates);
+                CoTaskMemFree(Some(activates.cast_const().cast::<c_void>()));
+            }
+            decoder.ok_or_else(|| {
+                NokhwaError::NotImplementedError(
+                    "No Media Foundation MJPEG decoder found".to_string(),
+                )
+            })
+        }
+
+        /// Creates a decoder for [`register_hardware_decoder()`](nokhwa_core::conversion::register_hardware_decoder).
+        /// # Errors
+        /// If there is no decoder, this will error.
+        pub fn create() -> Result<Box<dyn HardwareDecoder>, NokhwaError> {
+            Ok(Box::new(Self::new()?))
+        }
+
+        fn from_activate(activate: &IMFActivate) -> Result<Self, NokhwaError> {
+            let mut pwstr_name = PWSTR(std::ptr::null_mut());
+            let mut len_pwstrname = 0;
+            let name = match unsafe {
+                activate.GetAllocatedString(
+                    &MFT_FRIENDLY_NAME_Attribute,
+                    &mut pwstr_name,
+                    &mut len_pwstrname,
+                )
+            } {
+                Ok(()) if !pwstr_name.is_null() => unsafe {
+                    let name = pwstr_name.to_string().unwrap_or_default();
+                    CoTaskMemFree(Some(pwstr_name.0.cast_const().cast::<c_void>()));
+                    name
+                },
+                _ => "Unknown transform".to_string(),
+            };
+
+            let transform =
+                unsafe { activate.ActivateObject::<IMFTransform>() }.map_err(|why| {
+                    NokhwaError::OpenDeviceError(name.clone(), why.to_string())
+                        .with_os_error(PlatformError::HResult(why.code().0))
+                })?;
+
+            // asynchronous transforms refuse to work until they are unlocked, and then report progress as events
+            let attributes = unsafe { transform.GetAttributes() }.ok();
+            let is_async = attributes
+                .as_ref()
+                .and_then(|attributes| unsafe { attributes.GetUINT32(&MF_TRANSFORM_ASYNC) }.ok())
+                .unwrap_or_default()
+                != 0;
+            let events = if is_async {
+                if let Some(attributes) = &attributes {
+                    if let Err(why) = unsafe { attributes.SetUINT32(&MF_TRANSFORM_ASYNC_UNLOCK, 1) }
+                    {
+                        return Err(NokhwaError::SetPropertyError {
+                            property: "MF_TRANSFORM_ASYNC_UNLOCK".to_string(),
+                            value: "1".to_string(),
+                            error: why.to_string(),
+                        });
+                    }
+                }
+                Some(
+                    transform.cast::<IMFMediaEventGenerator>().map_err(|why| {
+                        NokhwaError::OpenDeviceError(name.clone(), why.to_string())
+                    })?,
+                )
+            } else {
+                None
+            };
+
+            Ok(MediaFoundationJpegDecoder {
+                transform,
+                events,
+                name: format!("Media Foundation {name}"),
+                configured: None,
+            })
+        }
+
+        fn configure(&mut self, resolution: Resolution) -> Result<(), NokhwaError> {
+            self.configured = None;
+            let frame_size = (u64::from(resolution.width()) << 32) + u64::from(resolution.height());
+
+            let input_type =
+                unsafe { MFCreateMediaType() }.map_err(|why| NokhwaError::StructureError {
+                    structure: "IMFMediaType".to_string(),
+                    error: why.to_string(),
+                })?;
+            if let Err(why) = unsafe { input_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_MAJOR_TYPE".to_string(),
+                    value: "MFMediaType_Video".to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe { input_type.SetGUID(&MF_MT_SUBTYPE, &MF_VIDEO_FORMAT_MJPEG) }
+            {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_SUBTYPE".to_string(),
+                    value: "MJPG".to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe { input_type.SetUINT64(&MF_MT_FRAME_SIZE, frame_size) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "MF_MT_FRAME_SIZE".to_string(),
+                    value: resolution.to_string(),
+                    error: why.to_string(),
+                });
+            }
+            if let Err(why) = unsafe { self.transform.SetInputType(0, &input_type, 0) } {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Input Type".to_string(),
+                    value: "MJPG".to_string(),
+                    error: why.to_string(),
+                });
+            }
+
+            let (fourcc, layout) = self.set_output_type(resolution)?;
+
+            unsafe {
+                let _flush = self.transform.ProcessMessage(MFT_MESSAGE_COMMAND_FLUSH, 0);
+                if let Err(why) = self
+                    .transform
+                    .ProcessMessage(MFT_MESSAGE_NOTIFY_BEGIN_STREAMING, 0)
+                    .and_then(|()| {
+                        self.transform
+                            .ProcessMessage(MFT_MESSAGE_NOTIFY_START_OF_STREAM, 0)
+                    })
+                {
+                    return Err(NokhwaError::OpenStreamError(why.to_string()));
+                }
+            }
+            self.configured = Some((resolution, fourcc, layout));
+            Ok(())
+        }
+
+        // picks the first output the transform offers that nokhwa can convert, in the transform's order of preference
+        fn set_output_type(
+            &self,
+            resolution: Resolution,
+        ) -> Result<(FourCC, DecodedLayout), NokhwaError> {
+            let mut index = 0;
+            while let Ok(output_type) = unsafe { self.transform.GetOutputAvailableType(0, index) } {
+                index += 1;
+                let Ok(subtype) = (unsafe { output_type.GetGUID(&MF_MT_SUBTYPE) }) else {
+                    continue;
+                };
+                let fourcc = if subtype == MF_VIDEO_FORMAT_NV12 {
+                    pixel_format::NV12
+                } else if subtype == MF_VIDEO_FORMAT_I420 {
+                    pixel_format::I420
+                } else if subtype == MF_VIDEO_FORMAT_YUY2 {
+                    pixel_format::YUYV
+                } else {
+                    continue;
+                };
+                if unsafe { self.transform.SetOutputType(0, &output_type, 0) }.is_err() {
+                    continue;
+                }
+
+                let width = resolution.width() as usize;
+                let packed_stride = if fourcc == pixel_format::YUYV {
+                    width * 2
+                } else {
+                    width
+                };
+                // a negative default stride means the frame is bottom-up, which decoders do not do for YUV
+                let stride = unsafe { output_type.GetUINT32(&MF_MT_DEFAULT_STRIDE) }
+                    .ok()
+                    .and_then(|stride| i32::try_from(stride).ok())
+                    .and_then(|stride| usize::try_from(stride).ok())
+                    .filter(|stride| *stride >= packed_stride)
+                    .unwrap_or(packed_stride);
+                let rows = unsafe { output_type.GetUINT64(&MF_MT_FRAME_SIZE) }
+                    .ok()
+                    .and_then(|frame_size| usize::try_from(frame_size & 0xFFFF_FFFF).ok())
+                    .filter(|rows| *rows >= resolution.height() as usize)
+                    .unwrap_or(resolution.height() as usize);
+                return Ok((fourcc, DecodedLayout { stride, rows }));
+            }
+
+            Err(NokhwaError::SetPropertyError {
+                property: "Output Type".to_string(),
+                value: "NV12/I420/YUY2".to_string(),
+                error: "The transform offers no supported output format".to_string(),
+            })
+        }
+
+        fn input_sample(data: &[u8]) -> Result<IMFSample, NokhwaError> {
+            let error = |why: windows::core::Error| {
+                NokhwaError::ReadFrameError(why.to_string())
+                    .with_os_error(PlatformError::HResult(why.code().0))
+            };
+            let length = u32::try_from(data.len())
+                .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?;
+            unsafe {
+                let buffer = MFCreateMemoryBuffer(length).map_err(error)?;
+                let mut buffer_start_ptr = std::ptr::null_mut::<u8>();
+                buffer
+                    .Lock(&mut buffer_start_ptr, None, None)
+                    .map_err(error)?;
+                std::ptr::copy_nonoverlapping(data.as_ptr(), buffer_start_ptr, data.len());
+                buffer.Unlock().map_err(error)?;
+                buffer.SetCurrentLength(length).map_err(error)?;
+
+                let sample = MFCreateSample().map_err(error)?;
+                sample.AddBuffer(&buffer).map_err(error)?;
+                Ok(sample)
+            }
+        }
+
+        fn wait_for(&self, event: MF_EVENT_TYPE) -> Result<(), NokhwaError> {
+            let Some(events) = &self.events else {
+                return Ok(());
+            };
+            loop {
+                let received = unsafe { events.GetEvent(MF_EVENT_FLAG_NONE) }
+                    .and_then(|received| unsafe { received.GetType() })
+                    .map_err(|why| {
+                        NokhwaError::ReadFrameError(why.to_string())
+                            .with_os_error(PlatformError::HResult(why.code().0))
+                    })?;
+                if u32::try_from(event.0).ok() == Some(received) {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn process_output(&mut self) -> Result<Vec<u8>, NokhwaError> {
+            let error = |why: windows::core::Error| {
+                NokhwaError::ReadFrameError(why.to_string())
+                    .with_os_error(PlatformError::HResult(why.code().0))
+            };
+            let info = unsafe { self.transform.GetOutputStreamInfo(0) }.map_err(error)?;
+            let provides_samples =
+                info.dwFlags & MFT_OUTPUT_STREAM_PROVIDES_SAMPLES.0.unsigned_abs() != 0;
+
+            loop {
+                let sample = if provides_samples {
+                    None
+                } else {
+                    unsafe {
+                        let sample = MFCreateSample().map_err(error)?;
+                        let buffer = MFCreateMemoryBuffer(info.cbSize).map_err(error)?;
+                        sample.AddBuffer(&buffer).map_err(error)?;
+                        Some(sample)
+                    }
+                };
+                let mut output = [MFT_OUTPUT_DATA_BUFFER {
+                    dwStreamID: 0,
+                    pSample: sample,
+                    dwStatus: 0,
+                    pEvents: None,
+                }];
+                let mut status = 0;
+                let result = unsafe { self.transform.ProcessOutput(0, &mut output, &mut status) };
+                let [MFT_OUTPUT_DATA_BUFFER {
+                    pSample: sample, ..
+                }] = output;
+
+                match result {
+                    Ok(()) => {
+                        let Some(sample) = sample else {
+                            return Err(NokhwaError::ReadFrameError("No sample".to_string()));
+                        };
+                        return Self::sample_bytes(&sample);
+                    }
+                    // the decoder only learns the real layout from the first frame, so take the type it now offers
+                    Err(why) if why.code() == MF_E_TRANSFORM_STREAM_CHANGE => {
+                        if let Some((resolution, _, _)) = self.configured {
+                            let (fourcc, layout) = self.set_output_type(resolution)?;
+                            self.configured = Some((resolution, fourcc, layout));
+                        }
+                    }
+                    Err(why) => return Err(error(why)),
+                }
+            }
+        }
+
+        fn sample_bytes(sample: &IMFSample) -> Result<Vec<u8>, NokhwaError> {
+            let buffer = unsafe { sample.ConvertToContiguousBuffer() }
+                .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?;
+            let mut buffer_valid_length = 0;
+            let mut buffer_start_ptr = std::ptr::null_mut::<u8>();
+            if let Err(why) =
+                unsafe { buffer.Lock(&mut buffer_start_ptr, None, Some(&mut buffer_valid_length)) }
+            {
+                return Err(NokhwaError::ReadFrameError(why.to_string()));
+            }
+            if buffer_start_ptr.is_null() {
+                return Err(NokhwaError::ReadFrameError(
+                    "Buffer Pointer Null".to_string(),
+                ));
+            }
+            let data =
+                unsafe { from_raw_parts(buffer_start_ptr, buffer_valid_length as usize).to_vec() };
+            let _unlock = unsafe { buffer.Unlock() };
+            Ok(data)
+        }
+    }
+
+    impl HardwareDecoder for MediaFoundationJpegDecoder {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        fn decode(
+            &mut self,
+            resolution: Resolution,
+            data: &[u8],
+        ) -> Result<(FourCC, Vec<u8>), NokhwaError> {
+            if self.configured.map(|(configured, _, _)| configured) != Some(resolution) {
+                self.configure(resolution)?;
+            }
+
+            let sample = Self::input_sample(data)?;
+            self.wait_for(METransformNeedInput)?;
+            if let Err(why) = unsafe { self.transform.ProcessInput(0, &sample, 0) } {
+                return Err(NokhwaError::ReadFrameError(why.to_string())
+                    .with_os_error(PlatformError::HResult(why.code().0)));
+            }
+            self.wait_for(METransformHaveOutput)?;
+            let decoded = self.process_output()?;
+
+            let Some((_, fourcc, layout)) = self.configured else {
+                return Err(NokhwaError::ReadFrameError(
+                    "The decoder is not configured".to_string(),
+                ));
+            };
+            Ok((fourcc, unpad(&decoded, layout, fourcc, resolution)))
+        }
+    }
+
+    impl Drop for MediaFoundationJpegDecoder {
+        fn drop(&mut self) {
+            // swallow errors
+            unsafe {
+                let _end = self
+                    .transform
+                    .ProcessMessage(MFT_MESSAGE_NOTIFY_END_STREAMING, 0);
+            }
+        }
+    }
+
+    /// Copies a decoded frame out of a buffer that may have padding after each row, or extra rows.
+    fn unpad(
+        buffer: &[u8],
+        layout: DecodedLayout,
+        fourcc: FourCC,
+        resolution: Resolution,
+    ) -> Vec<u8> {
+        let width = resolution.width() as usize;
+        let height = resolution.height() as usize;
+        let DecodedLayout { stride, rows } = layout;
+        // (bytes per row, rows, stride) of each plane, chroma rows of 4:2:0 are half as long but NV12 interleaves two
+        let planes: Vec<(usize, usize, usize)> = if fourcc == pixel_format::YUYV {
+            vec![(width * 2, height, stride)]
+        } else if fourcc == pixel_format::NV12 {
+            vec![
+                (width, height, stride),
+                (width.div_ceil(2) * 2, height.div_ceil(2), stride),
+            ]
+        } else {
+            vec![
+                (width, height, stride),
+                (width.div_ceil(2), height.div_ceil(2), stride / 2),
+                (width.div_ceil(2), height.div_ceil(2), stride / 2),
+            ]
+        };
+
+        let mut frame = Vec::with_capacity(planes.iter().map(|(len, rows, _)| len * rows).sum());
+        let mut plane_start = 0;
+        for (index, (row_len, plane_rows, plane_stride)) in planes.into_iter().enumerate() {
+            for row in 0..plane_rows {
+                let start = plane_start + row * plane_stride;
+                if let Some(row) = buffer.get(start..start + row_len) {
+                    frame.extend_from_slice(row);
+                }
+            }
+            // the next plane starts after all rows of this one the transform allocated
+            let allocated_rows = if index == 0 { rows } else { rows.div_ceil(2) };
+            plane_start += allocated_rows * plane_stride;
+        }
+        frame
+    }
+
+    // See: https://learn.microsoft.com/en-us/windows-hardware/drivers/install/kscategory-video-camera
+    const KSCATEGORY_VIDEO_CAMERA: GUID =
+        GUID::from_u128(0xe532_3777_f976_4f5b_9b55_b946_99c4_6e44);
+
+    thread_local! {
+        // the window procedure runs on the notifier thread, so the callback only needs to live there
+        static DEVICE_CHANGE_CALLBACK: RefCell<Option<Box<dyn Fn()>>> = RefCell::new(None);
+    }
+
+    /// Listens for `WM_DEVICECHANGE` on a message-only window, calling `on_change` whenever a video camera
+    /// is plugged in or removed. The window lives on its own thread, and is destroyed when this is dropped.
+    pub struct DeviceNotifier {
+        thread_id: u32,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    impl DeviceNotifier {
+        /// Starts watching for device changes.
+        /// # Errors
+        /// If the notification window could not be created, this will error.
+        pub fn new(on_change: impl Fn() + Send + 'static) -> Result<Self, NokhwaError> {
+            let (ready_send, ready_recv) = mpsc::channel();
+            let handle = std::thread::spawn(move || {
+                DEVICE_CHANGE_CALLBACK.with(|callback| {
+                    *callback.borrow_mut() = Some(Box::new(on_change));
+                });
+                match unsafe { create_notification_window() } {
+                    Ok((hwnd, notify)) => {
+                        let _ = ready_send.send(Ok(unsafe { GetCurrentThreadId() }));
+                        unsafe {
+                            let mut msg = MSG::default();
+                            while GetMessageW(&mut msg, HWND::default(), 0, 0).as_bool() {
+                                DispatchMessageW(&msg);
+                            }
+                            UnregisterDeviceNotification(notify);
+                            DestroyWindow(hwnd);
+                        }
+                    }
+                    Err(why) => {
+                        let _ = ready_send.send(Err(why));
+                    }
+                }
+            });
+
+            match ready_recv.recv() {
+                Ok(Ok(thread_id)) => Ok(DeviceNotifier {
+                    thread_id,
+                    handle: Some(handle),
+                }),
+                Ok(Err(why)) => Err(why),
+                Err(why) => Err(NokhwaError::GeneralError(why.to_string())),
+            }
+        }
+    }
+
+    impl Drop for DeviceNotifier {
+        fn drop(&mut self) {
+            unsafe {
+                PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    unsafe fn create_notification_window() -> Result<(HWND, *mut c_void), NokhwaError> {
+        let instance = GetModuleHandleW(PCWSTR::null())
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?;
+        let class_name = w!("NokhwaDeviceNotifier");
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(device_change_window_proc),
+            hInstance: instance,
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+        // this fails if another notifier already registered the class, which is fine
+        RegisterClassW(&class);
+
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class_name,
+            w!(""),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            HMENU::default(),
+            instance,
+            None,
+        );
+        if hwnd.0 == 0 {
+            return Err(NokhwaError::GeneralError(
+                windows::core::Error::from_win32().to_string(),
+            ));
+        }
+
+        let filter = DEV_BROADCAST_DEVICEINTERFACE_W {
+            dbcc_size: std::mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32,
+            dbcc_devicetype: DBT_DEVTYP_DEVICEINTERFACE.0,
+            dbcc_classguid: KSCATEGORY_VIDEO_CAMERA,
+            ..Default::default()
+        };
+        let notify = RegisterDeviceNotificationW(
+            HANDLE(hwnd.0),
+            (&raw const filter).cast(),
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        );
+        if notify.is_null() {
+            let why = windows::core::Error::from_win32();
+            DestroyWindow(hwnd);
+            return Err(NokhwaError::GeneralError(why.to_string()));
+        }
+        Ok((hwnd, notify))
+    }
+
+    extern "system" fn device_change_window_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_DEVICECHANGE
+            && matches!(
+                wparam.0 as u32,
+                DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE
+            )
+        {
+            DEVICE_CHANGE_CALLBACK.with(|callback| {
+                if let Some(callback) = callback.borrow().as_ref() {
+                    callback();
+                }
+            });
+        }
+        unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+    }
+
+    // the states of `QueueHeader::state`
+    const QUEUE_STATE_STARTING: u32 = 1;
+    const QUEUE_STATE_READY: u32 = 2;
+    const QUEUE_STATE_STOPPING: u32 = 3;
+    // the frames of the queue, each preceded by its timestamp padded to `QUEUE_FRAME_HEADER_SIZE`
+    const QUEUE_FRAME_COUNT: usize = 3;
+    const QUEUE_FRAME_HEADER_SIZE: usize = 32;
+
+    /// The header of the shared memory video queue of OBS (`plugins/win-dshow/shared-memory-queue.c`), which its
+    /// DirectShow filter reads frames from.
+    #[repr(C)]
+    struct QueueHeader {
+        write_idx: AtomicU32,
+        read_idx: AtomicU32,
+        state: AtomicU32,
+        offsets: [u32; QUEUE_FRAME_COUNT],
+        queue_type: u32,
+        cx: u32,
+        cy: u32,
+        // in 100ns units, like a `REFERENCE_TIME`
+        interval: u64,
+        reserved: [u32; 8],
+    }
+
+    const fn align_32(size: usize) -> usize {
+        (size + 31) & !31
+    }
+
+    /// A virtual camera sink that feeds the DirectShow filter of [OBS Virtual Camera](https://obsproject.com/kb/virtual-camera-guide),
+    /// which applications then open like any other webcam.
+    /// To see what this does, please see [`VirtualBackendTrait`].
+    /// # Quirks
+    /// - OBS Studio (27 or newer) has to be installed, as it registers the filter. OBS itself cannot have its virtual camera running at the same time.
+    /// - Frames have to be [`NV12`](nokhwa_core::pixel_format::NV12) with an even width and height. The filter scales them to the resolution it was set up with in OBS.
+    /// - Changing the [`CameraFormat`] recreates the queue, so consumers may show a blank frame for a moment.
+    pub struct OBSVirtualCamera {
+        camera_format: CameraFormat,
+        mapping: HANDLE,
+        header: *mut QueueHeader,
+        frame_size: usize,
+        started: Instant,
+    }
+
+    // the mapped view is only ever written by the thread holding the sink
+    unsafe impl Send for OBSVirtualCamera {}
+
+    impl OBSVirtualCamera {
+        /// Starts feeding OBS Virtual Camera with frames in `format`.
+        /// # Errors
+        /// This function will error if `format` is not `NV12` with an even resolution, or if the queue already exists
+        /// because OBS (or another sink) is running its virtual camera.
+        pub fn new(format: CameraFormat) -> Result<Self, NokhwaError> {
+            let mut sink = OBSVirtualCamera {
+                camera_format: format,
+                mapping: HANDLE::default(),
+                header: std::ptr::null_mut(),
+                frame_size: 0,
+                started: Instant::now(),
+            };
+            sink.set_camera_format(format)?;
+            Ok(sink)
+        }
+
+        fn open_queue(&mut self, format: CameraFormat) -> Result<(), NokhwaError> {
+            let error = |error: String| {
+                NokhwaError::OpenDeviceError("OBS Virtual Camera".to_string(), error)
+            };
+            if format.format() != pixel_format::NV12 {
+                return Err(error(format!("{} is not NV12", format.format())));
+            }
+            if format.width() % 2 != 0 || format.height() % 2 != 0 {
+                return Err(error(format!(
+                    "{} is not an even resolution",
+                    format.resolution()
+                )));
+            }
+
+            let frame_size = format.width() as usize * format.height() as usize * 3 / 2;
+            let mut offsets = [0; QUEUE_FRAME_COUNT];
+            let mut size = align_32(std::mem::size_of::<QueueHeader>());
+            for offset in &mut offsets {
+                *offset = size as u32;
+                size = align_32(size + QUEUE_FRAME_HEADER_SIZE + frame_size);
+            }
+            let frame_rate = format.exact_frame_rate();
+            let interval = if frame_rate.numerator() == 0 {
+                0
+            } else {
+                u64::from(frame_rate.denominator()) * 10_000_000 / u64::from(frame_rate.numerator())
+            };
+
+            unsafe {
+                let mapping = CreateFileMappingW(
+                    INVALID_HANDLE_VALUE,
+                    None,
+                    PAGE_READWRITE,
+                    0,
+                    u32::try_from(size).map_err(|why| error(why.to_string()))?,
+                    w!("OBSVirtualCamVideo"),
+                )
+                .map_err(|why| error(why.to_string()))?;
+                if GetLastError() == ERROR_ALREADY_EXISTS {
+                    CloseHandle(mapping);
+                    return Err(error(
+                        "The virtual camera is already running, e.g. in OBS".to_string(),
+                    ));
+                }
+                let header =
+                    MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, 0).cast::<QueueHeader>();
+                if header.is_null() {
+                    let why = windows::core::Error::from_win32();
+                    CloseHandle(mapping);
+                    return Err(error(why.to_string()));
+                }
+                header.write(QueueHeader {
+                    write_idx: AtomicU32::new(0),
+                    read_idx: AtomicU32::new(0),
+                    state: AtomicU32::new(QUEUE_STATE_STARTING),
+                    offsets,
+                    queue_type: 0,
+                    cx: format.width(),
+                    cy: format.height(),
+                    interval,
+                    reserved: [0; 8],
+                });
+                self.mapping = mapping;
+                self.header = header;
+            }
+            self.frame_size = frame_size;
+            self.camera_format = format;
+            Ok(())
+        }
+
+        fn close_queue(&mut self) {
+            if self.header.is_null() {
+                return;
+            }
+            unsafe {
+                (*self.header)
+                    .state
+                    .store(QUEUE_STATE_STOPPING, Ordering::SeqCst);
+                UnmapViewOfFile(self.header.cast());
+                CloseHandle(self.mapping);
+            }
+            self.header = std::ptr::null_mut();
+            self.mapping = HANDLE::default();
+        }
+    }
+
+    impl VirtualBackendTrait for OBSVirtualCamera {
+        fn backend(&self) -> ApiBackend {
+            ApiBackend::MediaFoundation
+        }
+
+        fn camera_format(&self) -> CameraFormat {
+            self.camera_format
+        }
+
+        fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+            self.close_queue();
+            self.open_queue(new_fmt)
+        }
+
+        fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError> {
+            if frame.resolution() != self.camera_format.resolution()
+                || frame.source_frame_format() != self.camera_format.format()
+            {
+                return Err(NokhwaError::WriteFrameError(format!(
+                    "Frame {} {} does not match device format {}",
+                    frame.resolution(),
+                    frame.source_frame_format(),
+                    self.camera_format
+                )));
+            }
+            if self.header.is_null() {
+                return Err(NokhwaError::WriteFrameError("Queue is closed".to_string()));
+            }
+            let planes = frame.planes()?;
+            let timestamp = u64::try_from(self.started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+
+            unsafe {
+                let header = &*self.header;
+                let index = header
+                    .write_idx
+                    .fetch_add(1, Ordering::SeqCst)
+                    .wrapping_add(1);
+                let slot = self
+                    .header
+                    .cast::<u8>()
+                    .add(header.offsets[index as usize % QUEUE_FRAME_COUNT] as usize);
+                let data = std::slice::from_raw_parts_mut(
+                    slot.add(QUEUE_FRAME_HEADER_SIZE),
+                    self.frame_size,
+                );
+                let mut offset = 0;
+                for plane in &planes {
+                    for row in (0..plane.height()).filter_map(|index| plane.row(index)) {
+                        let Some(dst) = data.get_mut(offset..offset + row.len()) else {
+                            return Err(NokhwaError::WriteFrameError(
+                                "Frame is larger than the queue".to_string(),
+                            ));
+                        };
+                        dst.copy_from_slice(row);
+                        offset += row.len();
+                    }
+                }
+                slot.cast::<u64>().write_unaligned(timestamp);
+                header.read_idx.store(index, Ordering::SeqCst);
+                header.state.store(QUEUE_STATE_READY, Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for OBSVirtualCamera {
+        fn drop(&mut self) {
+            self.close_queue();
+        }
+    }
+}
+
+#[cfg(any(not(windows), feature = "docs-only"))]
+#[allow(clippy::missing_errors_doc)]
+#[allow(clippy::unused_self)]
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::must_use_candidate)]
+pub mod wmf {
+    pub use crate::extended::*;
+    use four_cc::FourCC;
+    use nokhwa_core::buffer::FrameBuffer;
+    use nokhwa_core::conversion::HardwareDecoder;
+    use nokhwa_core::error::NokhwaError;
+    use nokhwa_core::traits::VirtualBackendTrait;
+    #[cfg(feature = "d3d11")]
+    use nokhwa_core::types::Resolution;
+    use nokhwa_core::types::{
+        ApiBackend, CameraAvailability, CameraControl, CameraFormat, CameraIndex, CameraInfo,
+        ControlValueSetter, Exposure, Focus, KnownCameraControl, PermissionStatus, PtzCapabilities,
+        Rect, SessionEvent, ThreadPriority, WhiteBalance,
+    };
+    use std::{borrow::Cow, time::Duration};
+
+    pub fn initialize_mf() -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
+        ))
+    }
+
+    pub fn de_initialize_mf() -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
         ))
     }
 
@@ -1234,6 +3955,34 @@ pub mod wmf {
         ))
     }
 
+    pub fn set_current_thread_priority(_priority: ThreadPriority) -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
+        ))
+    }
+
+    pub fn set_current_thread_affinity(_cores: &[usize]) -> Result<(), NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
+        ))
+    }
+
+    pub fn webcam_capability_status() -> Result<PermissionStatus, NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
+        ))
+    }
+
+    pub fn request_webcam_capability() -> Result<PermissionStatus, NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "Not on windows".to_string(),
+        ))
+    }
+
+    pub fn camera_availability() -> CameraAvailability {
+        CameraAvailability::Unknown
+    }
+
     pub struct MediaFoundationDevice {
         camera: CameraIndex,
     }
@@ -1279,6 +4028,15 @@ pub mod wmf {
             ))
         }
 
+        pub fn set_controls(
+            &mut self,
+            _controls: &[(KnownCameraControl, ControlValueSetter)],
+        ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
         pub fn format_refreshed(&mut self) -> Result<CameraFormat, NokhwaError> {
             Err(NokhwaError::NotImplementedError(
                 "Only on Windows".to_string(),
@@ -1311,10 +4069,257 @@ pub mod wmf {
             ))
         }
 
+        pub fn last_sample_time(&self) -> Option<Duration> {
+            None
+        }
+
+        pub fn set_mirrored(&mut self, _mirrored: bool) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_crop(&mut self, _crop: Option<Rect>) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_zoom(&mut self, _zoom: f64) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_pan_tilt_absolute(&mut self, _pan: f64, _tilt: f64) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_pan_tilt_relative(&mut self, _pan: f64, _tilt: f64) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_exposure(&mut self, _exposure: Exposure) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_white_balance(
+            &mut self,
+            _white_balance: WhiteBalance,
+        ) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_focus(&mut self, _focus: Focus) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_brightness(&mut self, _brightness: f64) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn output_format(&self) -> FourCC {
+            CameraFormat::default().format()
+        }
+
+        pub fn last_row_stride(&self) -> Option<usize> {
+            None
+        }
+
+        pub fn decoded_output(&self) -> Option<FourCC> {
+            None
+        }
+
+        pub fn set_decoded_output(&mut self, _output: Option<FourCC>) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn is_disconnected(&self) -> bool {
+            false
+        }
+
+        #[cfg(feature = "d3d11")]
+        pub fn d3d11_output(&self) -> bool {
+            false
+        }
+
+        #[cfg(feature = "d3d11")]
+        pub fn set_d3d11_output(&mut self, _enabled: bool) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        #[cfg(feature = "d3d11")]
+        pub fn read_texture(&mut self) -> Result<D3D11Texture, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn eye_gaze_correction(&self) -> Result<EyeGazeCorrection, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_eye_gaze_correction(
+            &self,
+            _correction: EyeGazeCorrection,
+        ) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn background_segmentation(&self) -> Result<BackgroundSegmentation, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_background_segmentation(
+            &self,
+            _segmentation: BackgroundSegmentation,
+        ) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn digital_window(&self) -> Result<DigitalWindow, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn set_digital_window(&self, _window: DigitalWindow) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn watch_connection(
+            &mut self,
+            _on_event: impl Fn(SessionEvent) + Send + 'static,
+        ) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn watch_controls(&mut self, _on_event: impl Fn(SessionEvent) + Send + 'static) {}
+
         pub fn stop_stream(&mut self) {}
     }
 
     impl Drop for MediaFoundationDevice {
         fn drop(&mut self) {}
     }
+
+    pub fn system_time() -> Duration {
+        Duration::ZERO
+    }
+
+    pub const CONTROL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    #[cfg(feature = "d3d11")]
+    pub const SHARED_TEXTURE_COUNT: usize = 3;
+
+    #[cfg(feature = "d3d11")]
+    #[derive(Clone)]
+    pub struct D3D11Texture {}
+
+    #[cfg(feature = "d3d11")]
+    impl D3D11Texture {
+        pub fn resolution(&self) -> Resolution {
+            Resolution::default()
+        }
+
+        pub fn format(&self) -> FourCC {
+            CameraFormat::default().format()
+        }
+
+        pub fn sample_time(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    pub struct MediaFoundationJpegDecoder {}
+
+    impl MediaFoundationJpegDecoder {
+        pub fn new() -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        pub fn create() -> Result<Box<dyn HardwareDecoder>, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+    }
+
+    pub struct DeviceNotifier {}
+
+    impl DeviceNotifier {
+        pub fn new(_on_change: impl Fn() + Send + 'static) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+    }
+
+    pub struct OBSVirtualCamera {}
+
+    impl OBSVirtualCamera {
+        pub fn new(_format: CameraFormat) -> Result<Self, NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+    }
+
+    impl VirtualBackendTrait for OBSVirtualCamera {
+        fn backend(&self) -> ApiBackend {
+            ApiBackend::MediaFoundation
+        }
+
+        fn camera_format(&self) -> CameraFormat {
+            CameraFormat::default()
+        }
+
+        fn set_camera_format(&mut self, _new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+
+        fn write_frame(&mut self, _frame: &FrameBuffer) -> Result<(), NokhwaError> {
+            Err(NokhwaError::NotImplementedError(
+                "Only on Windows".to_string(),
+            ))
+        }
+    }
 }
@@ -14,9 +14,25 @@
  * limitations under the License.
  */
 
-use std::time;
+use std::{
+    any::Any,
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{Debug, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex, Weak},
+    time,
+};
 
-use crate::types::Resolution;
+#[cfg(any(feature = "output-image", feature = "output-opencv"))]
+use crate::conversion::{convert_into, RgbDestination, RgbFormat};
+use crate::{
+    conversion::ColorSpace,
+    error::NokhwaError,
+    pixel_format::KnownFrameFormat,
+    types::{Rect, Resolution, Rotation},
+};
 use bytes::Bytes;
 use four_cc::FourCC;
 
@@ -24,28 +40,269 @@ use four_cc::FourCC;
 /// Contains information of Resolution, the buffer's [`FrameFormat`], and the buffer.
 ///
 /// Note that decoding on the main thread **will** decrease your performance and lead to dropped frames.
+///
+/// The data is reference counted, so cloning a [`FrameBuffer`] does not copy the frame.
 #[derive(Clone, Debug, Hash, PartialOrd, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
 pub struct FrameBuffer {
     timestamp: time::Instant,
+    capture_timestamp: Option<time::Duration>,
+    sequence: u64,
+    metadata: Option<FrameMetadata>,
+    color_space: Option<ColorSpace>,
+    rotation: Rotation,
+    mirror_pending: bool,
     resolution: Resolution,
-    buffer: Bytes,
+    buffer: Arc<PooledBuffer>,
+    plane_layouts: Option<Arc<[PlaneLayout]>>,
+    row_stride: Option<usize>,
     source_frame_format: FourCC,
 }
 
 impl FrameBuffer {
-    /// Creates a new buffer with a [`&[u8]`].
+    /// Creates a new buffer with a [`&[u8]`]. This allocates a new buffer for every frame, use
+    /// [`from_pooled()`](FrameBuffer::from_pooled) to reuse allocations instead.
+    #[must_use]
+    #[inline]
+    pub fn new(
+        resolution: Resolution,
+        buffer: &[u8],
+        source_frame_format: FourCC,
+        timestamp: time::Instant,
+    ) -> Self {
+        Self::from_pooled(
+            resolution,
+            PooledBuffer::unpooled(buffer.to_vec()),
+            source_frame_format,
+            timestamp,
+        )
+    }
+
+    /// Creates a new buffer from a [`PooledBuffer`], which goes back to its [`BufferPool`] once every clone of this
+    /// [`FrameBuffer`] has been dropped.
     #[must_use]
     #[inline]
-    pub fn new(resolution: Resolution, buffer: &[u8], source_frame_format: FourCC, timestamp: time::Instant) -> Self {
+    pub fn from_pooled(
+        resolution: Resolution,
+        buffer: PooledBuffer,
+        source_frame_format: FourCC,
+        timestamp: time::Instant,
+    ) -> Self {
         Self {
             timestamp,
-            resolution: resolution,
-            buffer: Bytes::copy_from_slice(buffer),
+            capture_timestamp: None,
+            sequence: 0,
+            metadata: None,
+            color_space: None,
+            rotation: Rotation::Deg0,
+            mirror_pending: false,
+            resolution,
+            buffer: Arc::new(buffer),
+            plane_layouts: None,
+            row_stride: None,
             source_frame_format,
         }
     }
 
+    /// Sets where each plane of the frame starts in the buffer and the stride of its rows, for frames whose planes are not
+    /// packed one after the other, such as the planes of a multi-planar `V4L2` buffer. There has to be one
+    /// [`PlaneLayout`] per plane of the format, in the order [`planes()`](FrameBuffer::planes) gives them.
+    #[must_use]
+    pub fn with_plane_layouts(mut self, layouts: Vec<PlaneLayout>) -> Self {
+        self.plane_layouts = Some(layouts.into());
+        self
+    }
+
+    /// Get the layout set with [`with_plane_layouts()`](FrameBuffer::with_plane_layouts), if any. Without one, the
+    /// planes are packed one after the other without padding.
+    #[must_use]
+    pub fn plane_layouts(&self) -> Option<&[PlaneLayout]> {
+        self.plane_layouts.as_deref()
+    }
+
+    /// Sets the distance in bytes between the starts of two rows of the first plane, for drivers that pad their rows (e.g.
+    /// 1280×720 `YUYV` with rows 2560+ bytes apart). The planes after it follow one after the other, with their rows
+    /// padded the same way: the chroma planes of `I420` have half the stride, the interleaved UV plane of `NV12` the same.
+    ///
+    /// [`Plane layouts`](FrameBuffer::with_plane_layouts) take precedence over this.
+    #[must_use]
+    pub fn with_row_stride(mut self, row_stride: usize) -> Self {
+        self.row_stride = Some(row_stride);
+        self
+    }
+
+    /// Get the distance in bytes between the starts of two rows of the first plane, which is more than a row of pixels if
+    /// the rows are padded. Returns `None` if the frame is compressed or has no known layout.
+    #[must_use]
+    pub fn row_stride(&self) -> Option<usize> {
+        let shapes = self.plane_shapes()?;
+        self.plane_spans(&shapes)
+            .ok()?
+            .first()
+            .map(|&(_, stride)| stride)
+    }
+
+    /// Whether the rows of the frame are padded, or its planes are not stored one right after the other. Converters take
+    /// frames packed tightly, see [`unpadded_buffer()`](FrameBuffer::unpadded_buffer).
+    #[must_use]
+    pub fn is_padded(&self) -> bool {
+        let Some(shapes) = self.plane_shapes() else {
+            return false;
+        };
+        let Ok(spans) = self.plane_spans(&shapes) else {
+            return false;
+        };
+        let mut packed_offset = 0;
+        shapes
+            .iter()
+            .zip(spans)
+            .any(|(&(height, _, _, packed_stride), (offset, stride))| {
+                let padded = offset != packed_offset || stride != packed_stride;
+                packed_offset += height * packed_stride;
+                padded
+            })
+    }
+
+    /// Get the data of the frame with its rows and planes packed tightly, which is what the converters in
+    /// [`conversion`](crate::conversion) take. This only copies the frame if it [is padded](FrameBuffer::is_padded).
+    /// # Errors
+    /// If the frame is padded and its planes cannot be found, see [`planes()`](FrameBuffer::planes), this will error.
+    pub fn unpadded_buffer(&self) -> Result<Cow<'_, [u8]>, NokhwaError> {
+        let Some(shapes) = self.plane_shapes().filter(|_| self.is_padded()) else {
+            return Ok(Cow::Borrowed(&self.buffer));
+        };
+        let mut data = Vec::with_capacity(
+            shapes
+                .iter()
+                .map(|&(height, _, _, packed_stride)| height * packed_stride)
+                .sum(),
+        );
+        for (plane, (height, _, _, packed_stride)) in self.planes()?.into_iter().zip(shapes) {
+            for row in 0..height {
+                let start = row * plane.stride();
+                let end = (start + packed_stride).min(plane.data().len());
+                data.extend_from_slice(&plane.data()[start.min(end)..end]);
+            }
+        }
+        Ok(Cow::Owned(data))
+    }
+
+    /// Attaches the presentation timestamp the driver gave this frame.
+    ///
+    /// `clock_now` is the current time on the same (monotonic) clock as `capture_timestamp`. It is used to map the
+    /// driver timestamp onto the host clock, replacing this buffer's [`timestamp()`](FrameBuffer::timestamp).
+    #[must_use]
+    pub fn with_capture_timestamp(
+        mut self,
+        capture_timestamp: time::Duration,
+        clock_now: time::Duration,
+    ) -> Self {
+        let now = time::Instant::now();
+        self.timestamp = now
+            .checked_sub(clock_now.saturating_sub(capture_timestamp))
+            .unwrap_or(now);
+        self.capture_timestamp = Some(capture_timestamp);
+        self
+    }
+
+    /// Get the host-clock time this frame was captured at.
+    ///
+    /// If the backend provides a [`capture_timestamp()`](FrameBuffer::capture_timestamp), this is derived from it. Otherwise it is
+    /// the time the frame was received by `nokhwa`.
+    #[must_use]
+    pub fn timestamp(&self) -> time::Instant {
+        self.timestamp
+    }
+
+    /// Get the presentation timestamp the driver gave this frame, on the driver's monotonic clock.
+    /// # Quirks
+    /// - `Video4Linux`: The V4L2 buffer timestamp (`CLOCK_MONOTONIC`).
+    /// - `Media Foundation`: The sample time, on the `MFGetSystemTime` clock.
+    /// - `AVFoundation`: The sample buffer presentation timestamp, on the host time clock.
+    #[must_use]
+    pub fn capture_timestamp(&self) -> Option<time::Duration> {
+        self.capture_timestamp
+    }
+
+    /// Sets the sequence number of this frame.
+    #[must_use]
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Get the sequence number of this frame, counting up from `0` since the stream was opened.
+    /// A gap between two frames means frames were dropped in between.
+    #[must_use]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Attaches the [`FrameMetadata`] of this frame.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: FrameMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Get the capture settings that were in effect for this frame, if the backend reports them.
+    /// # Quirks
+    /// - `Video4Linux`: Read from the exposure and gain controls right after the frame is dequeued.
+    /// - `AVFoundation`: Read from the `Exif` attachment of the sample buffer. White balance gains are not available.
+    #[must_use]
+    pub fn metadata(&self) -> Option<FrameMetadata> {
+        self.metadata
+    }
+
+    /// Attaches the color space the backend found the YUV samples of this frame to be in.
+    #[must_use]
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    /// Get the color space of the frame's YUV samples, if the backend reports it. The converters fall back to
+    /// [`ColorSpace::BT601_LIMITED`] otherwise.
+    /// # Quirks
+    /// - `Video4Linux`: Derived from the colorspace and quantization of the format. Drivers that leave them at their defaults
+    ///   report nothing.
+    /// - `AVFoundation`: Read from the YCbCr matrix attachment of the pixel buffer. Only `420f` frames are full range.
+    #[must_use]
+    pub fn color_space(&self) -> Option<ColorSpace> {
+        self.color_space
+    }
+
+    /// Records that the backend has already rotated this frame clockwise by `rotation`.
+    #[must_use]
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Get the clockwise rotation the backend already applied to this frame, relative to the
+    /// [`sensor_orientation()`](crate::types::CameraInfo::sensor_orientation) of the camera.
+    /// The [`resolution()`](FrameBuffer::resolution) is of the rotated frame.
+    ///
+    /// Subtract this from the [`suggested_rotation()`](crate::types::CameraInfo::suggested_rotation) to get what is left to rotate.
+    #[must_use]
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Marks this frame as needing a horizontal flip, which the conversion functions apply to their output.
+    #[must_use]
+    pub fn with_pending_mirror(mut self, mirror_pending: bool) -> Self {
+        self.mirror_pending = mirror_pending;
+        self
+    }
+
+    /// Checks if this frame still has to be flipped horizontally. The raw [`buffer()`](FrameBuffer::buffer) is never flipped,
+    /// this is set when mirroring was requested but the backend could not do it.
+    #[must_use]
+    pub fn mirror_pending(&self) -> bool {
+        self.mirror_pending
+    }
+
     /// Get the [`Resolution`] of this buffer.
     #[must_use]
     pub fn resolution(&self) -> Resolution {
@@ -70,10 +327,38 @@ impl FrameBuffer {
         &self.buffer
     }
 
-    /// Get a owned version of this buffer.
+    /// Get a owned version of this buffer, without copying the data. The [`Bytes`] shares the allocation with the frame,
+    /// which only goes back to its [`BufferPool`] once the [`Bytes`] is dropped as well.
     #[must_use]
     pub fn buffer_bytes(&self) -> Bytes {
-        self.buffer.clone()
+        Bytes::from_owner(SharedBuffer(Arc::clone(&self.buffer)))
+    }
+
+    /// Consumes the [`FrameBuffer`] and returns its data.
+    ///
+    /// If this is the only clone of the frame the allocation is taken over (and not returned to its [`BufferPool`]),
+    /// otherwise the data is copied. Data held in an [`ExternalBuffer`] is always copied.
+    #[must_use]
+    pub fn into_owned(self) -> Vec<u8> {
+        match Arc::try_unwrap(self.buffer) {
+            Ok(buffer) if buffer.external.is_some() => buffer.to_vec(),
+            Ok(mut buffer) => std::mem::take(&mut buffer.data),
+            Err(shared) => shared.to_vec(),
+        }
+    }
+
+    /// Get the platform buffer this frame was delivered in without being copied, if any. See [`ExternalBuffer`].
+    #[must_use]
+    pub fn external_buffer(&self) -> Option<&dyn ExternalBuffer> {
+        self.buffer.external.as_deref()
+    }
+
+    /// Get a handle to share the memory of this frame without copying it, if the backend delivered it in one.
+    /// Together with the [`resolution()`](FrameBuffer::resolution) and
+    /// [`source_frame_format()`](FrameBuffer::source_frame_format), this is what e.g. EGL needs to import it.
+    #[must_use]
+    pub fn handle(&self) -> Option<BufferHandle> {
+        self.external_buffer().and_then(ExternalBuffer::handle)
     }
 
     /// Get the [`FourCC`] of this buffer.
@@ -81,4 +366,1350 @@ impl FrameBuffer {
     pub fn source_frame_format(&self) -> FourCC {
         self.source_frame_format
     }
+
+    /// Get the [`FourCC`] of this buffer as a [`KnownFrameFormat`].
+    #[must_use]
+    pub fn known_frame_format(&self) -> KnownFrameFormat {
+        KnownFrameFormat::from(self.source_frame_format)
+    }
+
+    /// Get the amount of bits each sample of the frame holds, see [`KnownFrameFormat::bit_depth()`]. 10-bit frames convert
+    /// into RGB48 with e.g. [`p010_to_rgb48()`](crate::conversion::p010_to_rgb48).
+    #[must_use]
+    pub fn bit_depth(&self) -> Option<u32> {
+        self.known_frame_format().bit_depth()
+    }
+
+    /// Get the amount of planes the format of the frame is stored in, such as 2 for `NV12` and 3 for `I420`. Packed
+    /// formats have 1, and compressed formats or ones without a known layout have none.
+    #[must_use]
+    pub fn plane_count(&self) -> usize {
+        self.plane_shapes().map_or(0, |shapes| shapes.len())
+    }
+
+    /// Get plane `index` of the frame, in the order the planes are stored, see [`planes()`](FrameBuffer::planes). Returns
+    /// `None` if there is no such plane, or the buffer is too small for it.
+    #[must_use]
+    pub fn plane(&self, index: usize) -> Option<Plane<'_>> {
+        self.planes().ok()?.into_iter().nth(index)
+    }
+
+    /// Get each plane of the frame, in the order they are stored, so it can be used (e.g. uploaded to a GPU or handed to an
+    /// encoder) without copying it or working out the layout of the format.
+    ///
+    /// `NV12` gives the Y plane and the interleaved UV plane at half the resolution, `I420` and `YV12` the Y, and then the
+    /// U and V or V and U planes. `P010` is laid out like `NV12` with 2 bytes per sample. Packed formats give one plane.
+    ///
+    /// The planes are found with the [`plane_layouts()`](FrameBuffer::plane_layouts) if the frame has them, otherwise
+    /// they are expected one after the other, with the [`row_stride()`](FrameBuffer::row_stride) if it was set or without
+    /// padding.
+    /// # Errors
+    /// If the frame is compressed, has no known layout (this includes `V210`, which does not have a whole amount of bytes
+    /// per pixel), its plane layouts do not match its format, or the buffer is too small for its resolution, this will error.
+    pub fn planes(&self) -> Result<Vec<Plane<'_>>, NokhwaError> {
+        let error = |error: &str| NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: "Planes".to_string(),
+            error: error.to_string(),
+        };
+        let shapes = self
+            .plane_shapes()
+            .ok_or_else(|| error("The frame is compressed or has no known layout"))?;
+
+        let spans = self.plane_spans(&shapes)?;
+
+        let mut planes = Vec::with_capacity(shapes.len());
+        for ((height, width, bytes_per_pixel, packed_stride), (offset, stride)) in
+            shapes.into_iter().zip(spans)
+        {
+            if stride < width * bytes_per_pixel {
+                return Err(error("A plane stride is shorter than its rows"));
+            }
+            // the padding of the last row may be missing from the end of the buffer
+            let len = match height {
+                0 => 0,
+                height => (height - 1) * stride + packed_stride.min(stride),
+            };
+            let data = self
+                .buffer
+                .get(offset..offset + len)
+                .ok_or_else(|| error("The buffer is too small for the resolution"))?;
+            planes.push(Plane {
+                data,
+                width,
+                height,
+                bytes_per_pixel,
+                stride,
+            });
+        }
+        Ok(planes)
+    }
+
+    /// The (offset, stride) of each plane of `shapes`, see [`planes()`](FrameBuffer::planes).
+    fn plane_spans(
+        &self,
+        shapes: &[(usize, usize, usize, usize)],
+    ) -> Result<Vec<(usize, usize)>, NokhwaError> {
+        if let Some(layouts) = self.plane_layouts.as_deref() {
+            if layouts.len() != shapes.len() {
+                return Err(NokhwaError::ProcessFrameError {
+                    src: self.source_frame_format,
+                    destination: "Planes".to_string(),
+                    error: "The plane layouts do not match the format".to_string(),
+                });
+            }
+            return Ok(layouts
+                .iter()
+                .map(|layout| (layout.offset, layout.stride))
+                .collect());
+        }
+        let Some(&(_, width, luma_bytes_per_pixel, _)) = shapes.first() else {
+            return Ok(Vec::new());
+        };
+        let mut offset = 0;
+        let mut spans = Vec::with_capacity(shapes.len());
+        for &(height, plane_width, bytes_per_pixel, packed_stride) in shapes {
+            let stride = match self.row_stride {
+                // subsampled planes have as many bytes per row as the first plane if they interleave two samples per
+                // pixel, like the UV plane of `NV12`, and half as many otherwise, like the U and V planes of `I420`
+                Some(row_stride) if plane_width == width => {
+                    row_stride * bytes_per_pixel / luma_bytes_per_pixel
+                }
+                Some(row_stride) => row_stride * bytes_per_pixel / (2 * luma_bytes_per_pixel),
+                None => packed_stride,
+            };
+            spans.push((offset, stride));
+            offset += height * stride;
+        }
+        Ok(spans)
+    }
+
+    /// The (height, width, bytes per pixel, unpadded row stride in bytes) of each plane, in the order they are stored.
+    fn plane_shapes(&self) -> Option<Vec<(usize, usize, usize, usize)>> {
+        let width = self.resolution.width() as usize;
+        let height = self.resolution.height() as usize;
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        let layout = match self.known_frame_format() {
+            KnownFrameFormat::Gray8
+            | KnownFrameFormat::BayerBggr8
+            | KnownFrameFormat::BayerGbrg8
+            | KnownFrameFormat::BayerGrbg8
+            | KnownFrameFormat::BayerRggb8 => vec![(height, width, 1, width)],
+            KnownFrameFormat::Gray16 | KnownFrameFormat::Depth16 => {
+                vec![(height, width, 2, width * 2)]
+            }
+            KnownFrameFormat::Rgb24 => vec![(height, width, 3, width * 3)],
+            KnownFrameFormat::Bgra => vec![(height, width, 4, width * 4)],
+            // an odd width still stores a whole group of 4 bytes for the last pixel
+            KnownFrameFormat::Yuyv | KnownFrameFormat::Uyvy => {
+                vec![(height, width, 2, chroma_width * 4)]
+            }
+            KnownFrameFormat::Y210 => vec![(height, width, 4, chroma_width * 8)],
+            KnownFrameFormat::Nv12 | KnownFrameFormat::Yuv420 => vec![
+                (height, width, 1, width),
+                (chroma_height, chroma_width, 2, chroma_width * 2),
+            ],
+            KnownFrameFormat::P010 => vec![
+                (height, width, 2, width * 2),
+                (chroma_height, chroma_width, 4, chroma_width * 4),
+            ],
+            KnownFrameFormat::I420 | KnownFrameFormat::Yv12 => vec![
+                (height, width, 1, width),
+                (chroma_height, chroma_width, 1, chroma_width),
+                (chroma_height, chroma_width, 1, chroma_width),
+            ],
+            KnownFrameFormat::Mjpeg
+            | KnownFrameFormat::H264
+            | KnownFrameFormat::Hevc
+            | KnownFrameFormat::V210
+            | KnownFrameFormat::Unknown(_) => return None,
+        };
+        Some(layout)
+    }
+
+    /// Crops the frame to `rect`, copying the pixels inside it into a new [`FrameBuffer`]. Everything else about the frame is kept.
+    ///
+    /// `rect` is shrunk with [`Rect::aligned()`] to the chroma or Bayer alignment of the format first, so the
+    /// [`resolution()`](FrameBuffer::resolution) of the result may be slightly smaller than `rect`.
+    /// # Errors
+    /// This will error if the format is compressed or has no known layout, `rect` does not fit in the frame, or the
+    /// buffer is smaller than an unpadded frame.
+    pub fn cropped(&self, rect: Rect) -> Result<FrameBuffer, NokhwaError> {
+        let format = self.known_frame_format();
+        let error = |error: &str| NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: format!("Crop {rect}"),
+            error: error.to_string(),
+        };
+
+        // (horizontal alignment, vertical alignment)
+        let (align_x, align_y) = match format {
+            KnownFrameFormat::Gray8
+            | KnownFrameFormat::Gray16
+            | KnownFrameFormat::Depth16
+            | KnownFrameFormat::Rgb24
+            | KnownFrameFormat::Bgra => (1, 1),
+            KnownFrameFormat::Yuyv | KnownFrameFormat::Uyvy | KnownFrameFormat::Y210 => (2, 1),
+            KnownFrameFormat::BayerBggr8
+            | KnownFrameFormat::BayerGbrg8
+            | KnownFrameFormat::BayerGrbg8
+            | KnownFrameFormat::BayerRggb8
+            | KnownFrameFormat::Yuv420
+            | KnownFrameFormat::Nv12
+            | KnownFrameFormat::P010
+            | KnownFrameFormat::I420
+            | KnownFrameFormat::Yv12 => (2, 2),
+            KnownFrameFormat::Mjpeg
+            | KnownFrameFormat::H264
+            | KnownFrameFormat::Hevc
+            | KnownFrameFormat::V210
+            | KnownFrameFormat::Unknown(_) => return Err(error("Format cannot be cropped")),
+        };
+        if !rect.fits_in(self.resolution) {
+            return Err(error("Rectangle does not fit in the frame"));
+        }
+        let rect = rect.aligned(align_x, align_y);
+        if rect.width() == 0 || rect.height() == 0 {
+            return Err(error("Rectangle is empty once aligned"));
+        }
+        let shapes = self
+            .plane_shapes()
+            .ok_or_else(|| error("Format cannot be cropped"))?;
+        let buffer = self.unpadded_buffer()?;
+        let frame_size: usize = shapes
+            .iter()
+            .map(|&(height, _, _, packed_stride)| height * packed_stride)
+            .sum();
+        if buffer.len() < frame_size {
+            return Err(error("Buffer is smaller than an unpadded frame"));
+        }
+
+        let (x, y) = (rect.x() as usize, rect.y() as usize);
+        let (crop_width, crop_height) = (rect.width() as usize, rect.height() as usize);
+        let mut data = Vec::with_capacity(
+            format
+                .frame_size(rect.width(), rect.height())
+                .unwrap_or_default(),
+        );
+        let mut offset = 0;
+        for (plane, (height, _, bytes_per_pixel, packed_stride)) in shapes.into_iter().enumerate() {
+            // the chroma planes after the first have a sample for every two pixels and rows, rounded up for odd sizes
+            let (columns, rows) = if plane == 0 {
+                (x..x + crop_width, y..y + crop_height)
+            } else {
+                (
+                    x / 2..x / 2 + crop_width / 2,
+                    y / 2..y / 2 + crop_height / 2,
+                )
+            };
+            copy_rows(
+                &mut data,
+                &buffer[offset..offset + height * packed_stride],
+                packed_stride,
+                columns.start * bytes_per_pixel..columns.end * bytes_per_pixel,
+                rows,
+            );
+            offset += height * packed_stride;
+        }
+
+        let mut cropped = self.clone();
+        cropped.resolution = rect.resolution();
+        cropped.buffer = Arc::new(PooledBuffer::unpooled(data));
+        cropped.plane_layouts = None;
+        cropped.row_stride = None;
+        Ok(cropped)
+    }
+
+    /// Converts the frame into `format` with [`convert()`](crate::conversion::convert), e.g. into
+    /// [`RGBA`](crate::pixel_format::RGBA) whatever format the camera captures in. Everything else about the frame is kept.
+    ///
+    /// A [pending mirror](FrameBuffer::mirror_pending) is not applied.
+    /// # Errors
+    /// If there is no converter from the format of the frame into `format`, or it fails, this will error.
+    pub fn converted(&self, format: FourCC) -> Result<FrameBuffer, NokhwaError> {
+        let data = crate::conversion::convert(
+            self.resolution,
+            self.source_frame_format,
+            format,
+            self.color_space.unwrap_or_default(),
+            &self.unpadded_buffer()?,
+        )?;
+        Ok(self.with_pixels(self.resolution, data, format))
+    }
+
+    /// Copies everything about the frame but its data, which is replaced by `data` of `resolution` in `format`.
+    pub(crate) fn with_pixels(
+        &self,
+        resolution: Resolution,
+        data: Vec<u8>,
+        format: FourCC,
+    ) -> FrameBuffer {
+        let mut frame = self.clone();
+        frame.resolution = resolution;
+        frame.buffer = Arc::new(PooledBuffer::unpooled(data));
+        frame.plane_layouts = None;
+        frame.row_stride = None;
+        frame.source_frame_format = format;
+        frame
+    }
+
+    /// Converts the frame into an [`RgbImage`](image::RgbImage), e.g. to save it. Needs the `output-image` feature.
+    ///
+    /// Frames are converted with [`convert_into()`], [`RAWRGB`](crate::pixel_format::RAWRGB) frames are copied.
+    /// # Errors
+    /// If the format of the frame cannot be converted, or the buffer is too small for its resolution, this will error.
+    #[cfg(feature = "output-image")]
+    pub fn as_image_rgb(&self) -> Result<image::RgbImage, NokhwaError> {
+        self.to_image(RgbFormat::Rgb24)
+    }
+
+    /// Converts the frame into an [`RgbaImage`](image::RgbaImage), see [`as_image_rgb()`](FrameBuffer::as_image_rgb). The alpha
+    /// is opaque unless the frame has one of its own.
+    /// # Errors
+    /// If the format of the frame cannot be converted, or the buffer is too small for its resolution, this will error.
+    #[cfg(feature = "output-image")]
+    pub fn as_image_rgba(&self) -> Result<image::RgbaImage, NokhwaError> {
+        self.to_image(RgbFormat::Rgba)
+    }
+
+    /// Consumes the frame and converts it into an [`RgbImage`](image::RgbImage), see [`as_image_rgb()`](FrameBuffer::as_image_rgb).
+    ///
+    /// A [`RAWRGB`](crate::pixel_format::RAWRGB) frame takes over the allocation if this is the only clone of it, like
+    /// [`into_owned()`](FrameBuffer::into_owned).
+    /// # Errors
+    /// If the format of the frame cannot be converted, or the buffer is too small for its resolution, this will error.
+    #[cfg(feature = "output-image")]
+    pub fn into_image(self) -> Result<image::RgbImage, NokhwaError> {
+        if self.known_frame_format() != KnownFrameFormat::Rgb24 || self.is_padded() {
+            return self.as_image_rgb();
+        }
+        let (resolution, src) = (self.resolution, self.source_frame_format);
+        let mut data = self.into_owned();
+        let size = resolution.width() as usize * resolution.height() as usize * 3;
+        if data.len() < size {
+            return Err(NokhwaError::ProcessFrameError {
+                src,
+                destination: RgbFormat::Rgb24.to_string(),
+                error: format!("Expected at least {size} bytes, got {}", data.len()),
+            });
+        }
+        data.truncate(size);
+        image::RgbImage::from_raw(resolution.width(), resolution.height(), data).ok_or_else(|| {
+            NokhwaError::ProcessFrameError {
+                src,
+                destination: RgbFormat::Rgb24.to_string(),
+                error: "The image does not fit the data".to_string(),
+            }
+        })
+    }
+
+    #[cfg(feature = "output-image")]
+    fn to_image<P>(&self, format: RgbFormat) -> Result<image::ImageBuffer<P, Vec<u8>>, NokhwaError>
+    where
+        P: image::Pixel<Subpixel = u8>,
+    {
+        let error = |error: String| NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: format.to_string(),
+            error,
+        };
+
+        let pixels = self.resolution.width() as usize * self.resolution.height() as usize;
+        let mut data = vec![0; pixels * format.pixel_size()];
+        let buffer = self.unpadded_buffer()?;
+        if self.known_frame_format() == KnownFrameFormat::Rgb24 {
+            let source = buffer.get(..pixels * 3).ok_or_else(|| {
+                error(format!(
+                    "Expected at least {} bytes, got {}",
+                    pixels * 3,
+                    buffer.len()
+                ))
+            })?;
+            for (pixel, rgb) in data
+                .chunks_exact_mut(format.pixel_size())
+                .zip(source.chunks_exact(3))
+            {
+                pixel[..3].copy_from_slice(rgb);
+                if let Some(alpha) = pixel.get_mut(3) {
+                    *alpha = u8::MAX;
+                }
+            }
+        } else {
+            convert_into(
+                self.resolution,
+                self.source_frame_format,
+                self.color_space.unwrap_or_default(),
+                &buffer,
+                RgbDestination::new(&mut data, format),
+            )?;
+        }
+        image::ImageBuffer::from_raw(self.resolution.width(), self.resolution.height(), data)
+            .ok_or_else(|| error("The image does not fit the data".to_string()))
+    }
+
+    /// Encodes the frame as a JPEG, e.g. to save a snapshot. `quality` goes from 1 to 100, and is clamped to that. Needs the
+    /// `output-jpeg` feature.
+    ///
+    /// The frame is converted like [`as_image_rgb()`](FrameBuffer::as_image_rgb) first, also if it already is `MJPEG`.
+    /// # Errors
+    /// If the format of the frame cannot be converted, the buffer is too small for its resolution, or encoding fails, this
+    /// will error.
+    #[cfg(feature = "output-jpeg")]
+    pub fn encode_jpeg(&self, quality: u8) -> Result<Vec<u8>, NokhwaError> {
+        let mut encoded = Vec::new();
+        self.as_image_rgb()?
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut encoded,
+                quality.clamp(1, 100),
+            ))
+            .map_err(|why| self.encode_error("JPEG", &why))?;
+        Ok(encoded)
+    }
+
+    /// Encodes the frame as a PNG, e.g. to save a snapshot. Needs the `output-png` feature.
+    ///
+    /// The frame is converted like [`as_image_rgb()`](FrameBuffer::as_image_rgb) first. [`BGRA`](crate::pixel_format::BGRA)
+    /// frames keep their alpha.
+    /// # Errors
+    /// If the format of the frame cannot be converted, the buffer is too small for its resolution, or encoding fails, this
+    /// will error.
+    #[cfg(feature = "output-png")]
+    pub fn encode_png(&self) -> Result<Vec<u8>, NokhwaError> {
+        let mut encoded = Vec::new();
+        let encoder = image::codecs::png::PngEncoder::new(&mut encoded);
+        let written = if self.known_frame_format() == KnownFrameFormat::Bgra {
+            self.as_image_rgba()?.write_with_encoder(encoder)
+        } else {
+            self.as_image_rgb()?.write_with_encoder(encoder)
+        };
+        written.map_err(|why| self.encode_error("PNG", &why))?;
+        Ok(encoded)
+    }
+
+    #[cfg(any(feature = "output-jpeg", feature = "output-png"))]
+    fn encode_error(&self, format: &str, error: &image::ImageError) -> NokhwaError {
+        NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: format.to_string(),
+            error: error.to_string(),
+        }
+    }
+
+    /// Gets a view of a packed 8-bit frame as a height × width × channels array, without copying it. Needs the
+    /// `output-ndarray` feature.
+    ///
+    /// [`RAWRGB`](crate::pixel_format::RAWRGB) has 3 channels and [`BGRA`](crate::pixel_format::BGRA) 4. Greyscale and Bayer
+    /// frames have 1, and `YUYV`/`UYVY` have 2, since every pixel holds a luma and one of the two chroma samples.
+    /// # Errors
+    /// If the frame is planar, compressed, not 8-bit, or the buffer is too small for its resolution, this will error. Planar
+    /// frames can be viewed with [`as_ndarray_planes()`](FrameBuffer::as_ndarray_planes).
+    #[cfg(feature = "output-ndarray")]
+    pub fn as_ndarray(&self) -> Result<ndarray::ArrayView3<'_, u8>, NokhwaError> {
+        let mut planes = self.as_ndarray_planes()?;
+        match planes.pop() {
+            Some(packed) if planes.is_empty() => Ok(packed),
+            _ => Err(self.ndarray_error("The frame is planar, view it with `as_ndarray_planes()`")),
+        }
+    }
+
+    /// Gets views of each plane of an 8-bit frame as height × width × channels arrays, without copying it. Needs the
+    /// `output-ndarray` feature.
+    ///
+    /// `NV12` gives the Y plane and the interleaved UV plane, with 2 channels at half the resolution. `I420` and `YV12` give
+    /// the three planes in the order they are stored. Packed frames give one plane, the same as [`as_ndarray()`](FrameBuffer::as_ndarray).
+    /// # Errors
+    /// If the frame is compressed, not 8-bit, or the buffer is too small for its resolution, this will error.
+    #[cfg(feature = "output-ndarray")]
+    pub fn as_ndarray_planes(&self) -> Result<Vec<ndarray::ArrayView3<'_, u8>>, NokhwaError> {
+        use ndarray::ShapeBuilder;
+
+        if self.bit_depth() != Some(8) {
+            return Err(
+                self.ndarray_error("The frame is compressed, not 8-bit, or has no known layout")
+            );
+        }
+        self.planes()?
+            .into_iter()
+            .map(|plane| {
+                let channels = plane.bytes_per_pixel();
+                let shape = (plane.height(), plane.width(), channels).strides((
+                    plane.stride(),
+                    channels,
+                    1,
+                ));
+                ndarray::ArrayView3::from_shape(shape, plane.data())
+                    .map_err(|why| self.ndarray_error(&why.to_string()))
+            })
+            .collect()
+    }
+
+    /// Converts the frame into an OpenCV [`Mat`](opencv::core::Mat). Needs the `output-opencv` feature.
+    ///
+    /// Greyscale frames become a `CV_8UC1` and [`BGRA`](crate::pixel_format::BGRA) frames a `CV_8UC4` matrix, everything
+    /// else is converted with [`convert_into()`](crate::conversion::convert_into) into a `CV_8UC3` matrix in OpenCV's
+    /// BGR order.
+    /// # Errors
+    /// If the format of the frame cannot be converted, the buffer is too small for its resolution, or OpenCV fails to
+    /// allocate the matrix, this will error.
+    #[cfg(feature = "output-opencv")]
+    pub fn to_mat(&self) -> Result<opencv::core::Mat, NokhwaError> {
+        use opencv::{
+            core::{Mat, Scalar, CV_8UC1, CV_8UC3, CV_8UC4},
+            prelude::*,
+        };
+
+        let format = self.known_frame_format();
+        let (typ, pixel_size) = match format {
+            KnownFrameFormat::Gray8 => (CV_8UC1, 1),
+            KnownFrameFormat::Bgra => (CV_8UC4, 4),
+            _ => (CV_8UC3, 3),
+        };
+        let dimension = |value: u32| {
+            i32::try_from(value).map_err(|_| self.opencv_error(format!("{value} is too large")))
+        };
+        let mut mat = Mat::new_rows_cols_with_default(
+            dimension(self.height())?,
+            dimension(self.width())?,
+            typ,
+            Scalar::all(0.0),
+        )
+        .map_err(|why| self.opencv_error(why.to_string()))?;
+        // a newly allocated matrix has no padding between its rows
+        let data = mat
+            .data_bytes_mut()
+            .map_err(|why| self.opencv_error(why.to_string()))?;
+        let buffer = self.unpadded_buffer()?;
+
+        match format {
+            KnownFrameFormat::Gray8 | KnownFrameFormat::Bgra => {
+                let source = buffer.get(..data.len()).ok_or_else(|| {
+                    self.opencv_error(format!(
+                        "Expected at least {} bytes, got {}",
+                        data.len(),
+                        buffer.len()
+                    ))
+                })?;
+                data.copy_from_slice(source);
+            }
+            KnownFrameFormat::Rgb24 => {
+                let source = buffer.get(..data.len()).ok_or_else(|| {
+                    self.opencv_error(format!(
+                        "Expected at least {} bytes, got {}",
+                        data.len(),
+                        buffer.len()
+                    ))
+                })?;
+                for (bgr, rgb) in data
+                    .chunks_exact_mut(pixel_size)
+                    .zip(source.chunks_exact(3))
+                {
+                    bgr.copy_from_slice(&[rgb[2], rgb[1], rgb[0]]);
+                }
+            }
+            _ => {
+                convert_into(
+                    self.resolution,
+                    self.source_frame_format,
+                    self.color_space.unwrap_or_default(),
+                    &buffer,
+                    RgbDestination::new(data, RgbFormat::Rgb24),
+                )?;
+                for pixel in data.chunks_exact_mut(pixel_size) {
+                    pixel.swap(0, 2);
+                }
+            }
+        }
+        Ok(mat)
+    }
+
+    /// Creates a frame from an OpenCV [`Mat`](opencv::core::Mat), e.g. to hand the output of a pipeline back to code taking
+    /// [`FrameBuffer`]s. Needs the `output-opencv` feature.
+    ///
+    /// A `CV_8UC1` matrix becomes a [`GRAY`](crate::pixel_format::GRAY) frame, `CV_8UC3` is taken to be BGR and becomes a
+    /// [`RAWRGB`](crate::pixel_format::RAWRGB) frame, and `CV_8UC4` a [`BGRA`](crate::pixel_format::BGRA) frame. Padding at
+    /// the end of the rows of the matrix is dropped. The frame is timestamped now.
+    /// # Errors
+    /// If the matrix has any other type or is not two dimensional, this will error.
+    #[cfg(feature = "output-opencv")]
+    pub fn from_mat(mat: &opencv::core::Mat) -> Result<Self, NokhwaError> {
+        use crate::pixel_format::{BGRA, GRAY, RAWRGB};
+        use opencv::{
+            core::{CV_8UC1, CV_8UC3, CV_8UC4},
+            prelude::*,
+        };
+
+        let error = |error: String| NokhwaError::StructureError {
+            structure: "OpenCV Mat".to_string(),
+            error,
+        };
+        let typ = mat.typ();
+        let (format, pixel_size) = match typ {
+            CV_8UC1 => (GRAY, 1),
+            CV_8UC3 => (RAWRGB, 3),
+            CV_8UC4 => (BGRA, 4),
+            _ => return Err(error(format!("Matrices of type {typ} are not supported"))),
+        };
+        if mat.dims() != 2 {
+            return Err(error(format!(
+                "Expected a two dimensional matrix, got {} dimensions",
+                mat.dims()
+            )));
+        }
+        let width = u32::try_from(mat.cols()).map_err(|why| error(why.to_string()))?;
+        let height = u32::try_from(mat.rows()).map_err(|why| error(why.to_string()))?;
+
+        let row_len = width as usize * pixel_size;
+        let mut data = Vec::with_capacity(row_len * height as usize);
+        for row in 0..mat.rows() {
+            let pointer = mat.ptr(row).map_err(|why| error(why.to_string()))?;
+            // SAFETY: each row of a two dimensional `Mat` holds `cols` pixels of the size of its type, and it outlives this loop
+            let row = unsafe { std::slice::from_raw_parts(pointer, row_len) };
+            if format == RAWRGB {
+                data.extend(row.chunks_exact(3).flat_map(|bgr| [bgr[2], bgr[1], bgr[0]]));
+            } else {
+                data.extend_from_slice(row);
+            }
+        }
+        Ok(FrameBuffer::from_pooled(
+            Resolution::new(width, height),
+            PooledBuffer::unpooled(data),
+            format,
+            time::Instant::now(),
+        ))
+    }
+
+    #[cfg(feature = "output-opencv")]
+    fn opencv_error(&self, error: String) -> NokhwaError {
+        NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: "OpenCV Mat".to_string(),
+            error,
+        }
+    }
+
+    #[cfg(feature = "output-ndarray")]
+    fn ndarray_error(&self, error: &str) -> NokhwaError {
+        NokhwaError::ProcessFrameError {
+            src: self.source_frame_format,
+            destination: "ndarray".to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Appends `columns` of each of `rows` of a plane whose rows are `stride` bytes apart to `dst`.
+fn copy_rows(
+    dst: &mut Vec<u8>,
+    plane: &[u8],
+    stride: usize,
+    columns: std::ops::Range<usize>,
+    rows: std::ops::Range<usize>,
+) {
+    for row in plane.chunks_exact(stride).skip(rows.start).take(rows.len()) {
+        dst.extend_from_slice(&row[columns.clone()]);
+    }
+}
+
+/// Where a plane of a [`FrameBuffer`] is in its buffer, see [`FrameBuffer::with_plane_layouts()`].
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PlaneLayout {
+    offset: usize,
+    stride: usize,
+}
+
+impl PlaneLayout {
+    /// Creates a layout for a plane starting `offset` bytes into the buffer, with rows `stride` bytes apart.
+    #[must_use]
+    pub fn new(offset: usize, stride: usize) -> Self {
+        Self { offset, stride }
+    }
+
+    /// Get where the plane starts in the buffer, in bytes.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Get the distance in bytes from the start of one row of the plane to the start of the next.
+    #[must_use]
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// One plane of a [`FrameBuffer`], see [`FrameBuffer::planes()`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Plane<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    /// Get the data of the plane, which is [`height()`](Plane::height) rows of [`stride()`](Plane::stride) bytes. The
+    /// padding of the last row may be missing.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the width of the plane in pixels. Subsampled chroma planes are narrower than the frame.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the plane in rows. Subsampled chroma planes are shorter than the frame.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the amount of bytes each pixel of the plane takes, e.g. 2 for the interleaved UV plane of `NV12`. `YUYV` has 2,
+    /// since every pixel holds a luma and one of the two chroma samples.
+    #[must_use]
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bytes_per_pixel
+    }
+
+    /// Get the distance in bytes from the start of one row to the start of the next, which may be more than
+    /// [`width()`](Plane::width) pixels.
+    #[must_use]
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Get row `index` of the plane, without any padding at its end. Returns `None` if there is no such row.
+    #[must_use]
+    pub fn row(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.height {
+            return None;
+        }
+        let start = index * self.stride;
+        self.data
+            .get(start..start + (self.width * self.bytes_per_pixel).min(self.stride))
+    }
+}
+
+/// The capture settings a camera actually used for a frame. Any value the backend cannot report is `None`.
+///
+/// Useful for HDR fusion or calibration, where the requested settings are not good enough.
+///
+/// The floats are compared by their bits and ordered by [`f32::total_cmp`], so that [`FrameBuffer`] can stay `Eq` and `Hash`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameMetadata {
+    exposure_duration: Option<time::Duration>,
+    iso: Option<f32>,
+    gain: Option<f32>,
+    white_balance_gains: Option<[f32; 3]>,
+}
+
+impl FrameMetadata {
+    /// Create a new [`FrameMetadata`]. `white_balance_gains` are in red, green, blue order.
+    #[must_use]
+    pub fn new(
+        exposure_duration: Option<time::Duration>,
+        iso: Option<f32>,
+        gain: Option<f32>,
+        white_balance_gains: Option<[f32; 3]>,
+    ) -> Self {
+        Self {
+            exposure_duration,
+            iso,
+            gain,
+            white_balance_gains,
+        }
+    }
+
+    /// Get how long the sensor was exposed for.
+    #[must_use]
+    pub fn exposure_duration(&self) -> Option<time::Duration> {
+        self.exposure_duration
+    }
+
+    /// Get the ISO sensitivity.
+    #[must_use]
+    pub fn iso(&self) -> Option<f32> {
+        self.iso
+    }
+
+    /// Get the sensor gain, in the backend's own units.
+    #[must_use]
+    pub fn gain(&self) -> Option<f32> {
+        self.gain
+    }
+
+    /// Get the white balance gains, in red, green, blue order.
+    #[must_use]
+    pub fn white_balance_gains(&self) -> Option<[f32; 3]> {
+        self.white_balance_gains
+    }
+}
+
+fn total_cmp_gains(a: &[f32], b: &[f32]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.total_cmp(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn total_cmp_option<T>(a: Option<T>, b: Option<T>, cmp: impl FnOnce(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(a, b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+impl PartialEq for FrameMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FrameMetadata {}
+
+impl PartialOrd for FrameMetadata {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FrameMetadata {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.exposure_duration
+            .cmp(&other.exposure_duration)
+            .then_with(|| total_cmp_option(self.iso, other.iso, |a, b| a.total_cmp(&b)))
+            .then_with(|| total_cmp_option(self.gain, other.gain, |a, b| a.total_cmp(&b)))
+            .then_with(|| {
+                total_cmp_option(
+                    self.white_balance_gains,
+                    other.white_balance_gains,
+                    |a, b| total_cmp_gains(&a, &b),
+                )
+            })
+    }
+}
+
+impl Hash for FrameMetadata {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.exposure_duration.hash(state);
+        self.iso.map(f32::to_bits).hash(state);
+        self.gain.map(f32::to_bits).hash(state);
+        self.white_balance_gains
+            .map(|gains| gains.map(f32::to_bits))
+            .hash(state);
+    }
+}
+
+/// The sample format of an [`AudioBuffer`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AudioSampleFormat {
+    /// Signed 16 bit integers.
+    I16,
+    /// Signed 32 bit integers.
+    I32,
+    /// 32 bit floats, from -1.0 to 1.0.
+    F32,
+}
+
+impl AudioSampleFormat {
+    /// Get the size of one sample in bytes.
+    #[must_use]
+    pub fn sample_size(self) -> usize {
+        match self {
+            AudioSampleFormat::I16 => 2,
+            AudioSampleFormat::I32 | AudioSampleFormat::F32 => 4,
+        }
+    }
+}
+
+/// A buffer of PCM audio, captured by the same session as the video frames of a camera.
+///
+/// Samples are interleaved by channel and in native byte order. The timestamps are on the same clock as those of
+/// [`FrameBuffer`]s from the same session, so audio and video can be lined up without drifting.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct AudioBuffer {
+    timestamp: time::Instant,
+    capture_timestamp: Option<time::Duration>,
+    sample_rate: u32,
+    channels: u16,
+    sample_format: AudioSampleFormat,
+    data: Vec<u8>,
+}
+
+impl AudioBuffer {
+    /// Creates a new buffer of interleaved samples, received now.
+    #[must_use]
+    pub fn new(
+        sample_rate: u32,
+        channels: u16,
+        sample_format: AudioSampleFormat,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            timestamp: time::Instant::now(),
+            capture_timestamp: None,
+            sample_rate,
+            channels,
+            sample_format,
+            data,
+        }
+    }
+
+    /// Attaches the presentation timestamp of the first sample, see [`FrameBuffer::with_capture_timestamp()`].
+    #[must_use]
+    pub fn with_capture_timestamp(
+        mut self,
+        capture_timestamp: time::Duration,
+        clock_now: time::Duration,
+    ) -> Self {
+        let now = time::Instant::now();
+        self.timestamp = now
+            .checked_sub(clock_now.saturating_sub(capture_timestamp))
+            .unwrap_or(now);
+        self.capture_timestamp = Some(capture_timestamp);
+        self
+    }
+
+    /// Get the host-clock time the first sample was captured at, see [`FrameBuffer::timestamp()`].
+    #[must_use]
+    pub fn timestamp(&self) -> time::Instant {
+        self.timestamp
+    }
+
+    /// Get the presentation timestamp of the first sample, on the same clock as [`FrameBuffer::capture_timestamp()`].
+    #[must_use]
+    pub fn capture_timestamp(&self) -> Option<time::Duration> {
+        self.capture_timestamp
+    }
+
+    /// Get the number of samples per second, per channel.
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Get the number of interleaved channels.
+    #[must_use]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Get the format of each sample.
+    #[must_use]
+    pub fn sample_format(&self) -> AudioSampleFormat {
+        self.sample_format
+    }
+
+    /// Get the number of samples per channel.
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        self.data.len() / (self.sample_format.sample_size() * usize::from(self.channels.max(1)))
+    }
+
+    /// Get how long the buffer plays for.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn duration(&self) -> time::Duration {
+        if self.sample_rate == 0 {
+            return time::Duration::ZERO;
+        }
+        time::Duration::from_secs_f64(self.frames() as f64 / f64::from(self.sample_rate))
+    }
+
+    /// Get the raw interleaved samples.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Takes the raw interleaved samples out of the buffer.
+    #[must_use]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+type FreeList = Mutex<Vec<Vec<u8>>>;
+
+/// A pool of reusable frame allocations.
+///
+/// Backends keep one of these per stream so that each frame is copied into an already allocated buffer, rather than
+/// allocating (potentially megabytes) every frame. Cloning a [`BufferPool`] gives another handle to the same pool.
+#[derive(Clone)]
+pub struct BufferPool {
+    free: Arc<FreeList>,
+    max_idle: usize,
+}
+
+impl BufferPool {
+    /// The amount of idle buffers a [`BufferPool::default()`] keeps around.
+    pub const DEFAULT_MAX_IDLE: usize = 4;
+
+    /// Creates a new, empty pool that keeps at most `max_idle` unused buffers around.
+    #[must_use]
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            free: Arc::new(Mutex::new(Vec::with_capacity(max_idle))),
+            max_idle,
+        }
+    }
+
+    /// Copies `data` into a buffer taken from the pool, allocating a new one if there are no idle buffers.
+    #[must_use]
+    pub fn copy_from_slice(&self, data: &[u8]) -> PooledBuffer {
+        self.copy_from_slices(&[data])
+    }
+
+    /// Copies `parts` one after the other into a buffer taken from the pool, e.g. the planes of a frame the driver
+    /// delivered in separate buffers.
+    #[must_use]
+    pub fn copy_from_slices(&self, parts: &[&[u8]]) -> PooledBuffer {
+        let mut buffer = self
+            .free
+            .lock()
+            .ok()
+            .and_then(|mut free| free.pop())
+            .unwrap_or_default();
+        buffer.clear();
+        for part in parts {
+            buffer.extend_from_slice(part);
+        }
+        PooledBuffer {
+            data: buffer,
+            external: None,
+            pool: Arc::downgrade(&self.free),
+            max_idle: self.max_idle,
+        }
+    }
+
+    /// Get the amount of idle buffers in the pool.
+    #[must_use]
+    pub fn idle(&self) -> usize {
+        self.free.lock().map(|free| free.len()).unwrap_or_default()
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_IDLE)
+    }
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.max_idle)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Frame data owned by the platform, such as a `CVPixelBuffer` on macOS, which a [`FrameBuffer`] holds on to instead of
+/// copying it. The platform buffer is released once every clone of the frame has been dropped.
+///
+/// Platform buffers usually come from a small pool of the driver, so holding on to many frames makes it drop frames.
+pub trait ExternalBuffer: Send + Sync {
+    /// Get the frame data. This must not change for as long as the buffer lives.
+    fn data(&self) -> &[u8];
+
+    /// Get the buffer as [`Any`], to downcast it to the type of the platform.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Get a handle the frame memory can be shared with, e.g. with the GPU, if the platform gives one.
+    fn handle(&self) -> Option<BufferHandle> {
+        None
+    }
+}
+
+/// A handle to the memory of a frame, which other APIs can import without copying the frame. See
+/// [`FrameBuffer::handle()`].
+///
+/// The handle is owned by the frame, and only valid while it is alive. Duplicate it to keep it for longer.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BufferHandle {
+    /// A Linux DMA-BUF file descriptor, which can be imported into EGL (`EGL_EXT_image_dma_buf_import`), Vulkan
+    /// (`VK_EXT_external_memory_dma_buf`) or `wgpu` through its Vulkan backend.
+    /// - `offset` - Where the first plane starts in the buffer.
+    /// - `stride` - The bytes per row of the first plane. Further planes follow it, as laid out by
+    ///   [`FrameBuffer::planes()`] but with this stride.
+    /// - `length` - The bytes of the buffer the frame uses.
+    DmaBuf {
+        fd: i32,
+        offset: usize,
+        stride: usize,
+        length: usize,
+    },
+}
+
+/// Frame data taken from a [`BufferPool`]. The allocation is handed back to the pool when this is dropped.
+pub struct PooledBuffer {
+    data: Vec<u8>,
+    external: Option<Box<dyn ExternalBuffer>>,
+    pool: Weak<FreeList>,
+    max_idle: usize,
+}
+
+impl PooledBuffer {
+    /// Wraps a [`Vec<u8>`] that does not belong to any pool.
+    #[must_use]
+    pub fn unpooled(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            external: None,
+            pool: Weak::new(),
+            max_idle: 0,
+        }
+    }
+
+    /// Wraps a buffer of the platform without copying it, see [`ExternalBuffer`].
+    #[must_use]
+    pub fn external(buffer: impl ExternalBuffer + 'static) -> Self {
+        Self {
+            data: Vec::new(),
+            external: Some(Box::new(buffer)),
+            pool: Weak::new(),
+            max_idle: 0,
+        }
+    }
+}
+
+/// Lets a [`Bytes`] hold on to the buffer of a [`FrameBuffer`], see [`FrameBuffer::buffer_bytes()`].
+struct SharedBuffer(Arc<PooledBuffer>);
+
+impl AsRef<[u8]> for SharedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match &self.external {
+            Some(external) => external.data(),
+            None => &self.data,
+        }
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if self.data.capacity() == 0 {
+            return;
+        }
+        if let Some(pool) = self.pool.upgrade() {
+            if let Ok(mut free) = pool.lock() {
+                if free.len() < self.max_idle {
+                    free.push(std::mem::take(&mut self.data));
+                }
+            }
+        }
+    }
+}
+
+impl Debug for PooledBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl PartialEq for PooledBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for PooledBuffer {}
+
+impl PartialOrd for PooledBuffer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PooledBuffer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl Hash for PooledBuffer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_format::{GRAY, I420, NV12, YUYV};
+
+    fn pooled_frame(
+        pool: &BufferPool,
+        resolution: Resolution,
+        data: &[u8],
+        fourcc: FourCC,
+    ) -> FrameBuffer {
+        FrameBuffer::from_pooled(
+            resolution,
+            pool.copy_from_slice(data),
+            fourcc,
+            time::Instant::now(),
+        )
+    }
+
+    #[test]
+    fn buffer_bytes_shares_the_frame() {
+        let pool = BufferPool::new(2);
+        let frame = pooled_frame(&pool, Resolution::new(4, 1), &[1, 2, 3, 4], GRAY);
+        let bytes = frame.buffer_bytes();
+        assert_eq!(bytes.as_ptr(), frame.buffer().as_ptr());
+        assert_eq!(&bytes[..], &[1, 2, 3, 4]);
+
+        drop(frame);
+        assert_eq!(pool.idle(), 0);
+        drop(bytes);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    fn frame(resolution: Resolution, data: &[u8], fourcc: FourCC) -> FrameBuffer {
+        FrameBuffer::new(resolution, data, fourcc, time::Instant::now())
+    }
+
+    /// `len` bytes counting up from `start`.
+    fn bytes(start: u8, len: u8) -> Vec<u8> {
+        (start..start + len).collect()
+    }
+
+    #[test]
+    fn crops_nv12_with_an_odd_width() {
+        // 5x4 luma, then 2 rows of 3 UV pairs
+        let data = [bytes(0, 20), bytes(100, 12)].concat();
+        let cropped = frame(Resolution::new(5, 4), &data, NV12)
+            .cropped(Rect::new(2, 2, 2, 2))
+            .unwrap();
+        assert_eq!(cropped.resolution(), Resolution::new(2, 2));
+        assert_eq!(cropped.buffer(), &[12, 13, 17, 18, 108, 109]);
+    }
+
+    #[test]
+    fn crops_i420_with_an_odd_width() {
+        // 5x4 luma, then U and V planes of 3x2
+        let data = [bytes(0, 20), bytes(100, 6), bytes(200, 6)].concat();
+        let cropped = frame(Resolution::new(5, 4), &data, I420)
+            .cropped(Rect::new(2, 0, 2, 4))
+            .unwrap();
+        assert_eq!(cropped.resolution(), Resolution::new(2, 4));
+        assert_eq!(
+            cropped.buffer(),
+            &[2, 3, 7, 8, 12, 13, 17, 18, 101, 104, 201, 204]
+        );
+    }
+
+    #[test]
+    fn crops_yuyv_with_an_odd_width() {
+        // the odd pixel at the end of each row still takes a whole Y0 U Y1 V group
+        let data = bytes(0, 16);
+        let source = frame(Resolution::new(3, 2), &data, YUYV);
+        let cropped = source.cropped(Rect::new(0, 1, 2, 1)).unwrap();
+        assert_eq!(cropped.buffer(), &[8, 9, 10, 11]);
+        // aligned to a width of 0
+        assert!(source.cropped(Rect::new(1, 0, 2, 1)).is_err());
+    }
+
+    #[test]
+    fn cropping_rejects_short_buffers() {
+        let data = bytes(0, 20);
+        assert!(frame(Resolution::new(5, 4), &data, NV12)
+            .cropped(Rect::new(0, 0, 2, 2))
+            .is_err());
+    }
+
+    #[test]
+    fn planes_of_nv12_with_an_odd_width() {
+        let data = [bytes(0, 20), bytes(100, 12)].concat();
+        let frame = frame(Resolution::new(5, 4), &data, NV12);
+        assert_eq!(frame.plane_count(), 2);
+        assert!(!frame.is_padded());
+        let chroma = frame.plane(1).unwrap();
+        assert_eq!((chroma.width(), chroma.height()), (3, 2));
+        assert_eq!((chroma.bytes_per_pixel(), chroma.stride()), (2, 6));
+        assert_eq!(chroma.row(1), Some(&bytes(106, 6)[..]));
+        assert_eq!(chroma.row(2), None);
+        assert!(frame.plane(2).is_none());
+    }
+
+    #[test]
+    fn padded_rows_are_removed() {
+        // 4x2 NV12 with 2 bytes of padding after every row
+        let data = [
+            bytes(0, 4),
+            vec![0; 2],
+            bytes(4, 4),
+            vec![0; 2],
+            bytes(100, 4),
+            vec![0; 2],
+        ]
+        .concat();
+        let frame = frame(Resolution::new(4, 2), &data, NV12).with_row_stride(6);
+        assert!(frame.is_padded());
+        assert_eq!(frame.row_stride(), Some(6));
+        assert_eq!(frame.plane(1).unwrap().stride(), 6);
+        assert_eq!(
+            frame.unpadded_buffer().unwrap().as_ref(),
+            &[bytes(0, 8), bytes(100, 4)].concat()[..]
+        );
+    }
+
+    #[test]
+    fn plane_layouts_take_precedence() {
+        // 2x2 NV12, with the UV plane stored before the luma plane
+        let data = [bytes(100, 2), bytes(0, 4)].concat();
+        let frame = frame(Resolution::new(2, 2), &data, NV12)
+            .with_plane_layouts(vec![PlaneLayout::new(2, 2), PlaneLayout::new(0, 2)]);
+        assert!(frame.is_padded());
+        assert_eq!(frame.plane(0).unwrap().data(), &[0, 1, 2, 3]);
+        assert_eq!(frame.plane(1).unwrap().data(), &[100, 101]);
+        assert_eq!(
+            frame.unpadded_buffer().unwrap().as_ref(),
+            &[0, 1, 2, 3, 100, 101]
+        );
+        // one layout too few
+        let frame = frame.with_plane_layouts(vec![PlaneLayout::new(0, 2)]);
+        assert!(frame.planes().is_err());
+    }
+
+    #[test]
+    fn pool_reuses_allocations() {
+        let pool = BufferPool::new(2);
+        let buffer = pool.copy_from_slice(&[1, 2, 3, 4]);
+        let allocation = buffer.as_ptr();
+        drop(buffer);
+        assert_eq!(pool.idle(), 1);
+
+        let buffer = pool.copy_from_slices(&[&[5, 6], &[7]]);
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(buffer.as_ptr(), allocation);
+        assert_eq!(&buffer[..], &[5, 6, 7]);
+    }
+
+    #[test]
+    fn pool_keeps_at_most_max_idle_buffers() {
+        let pool = BufferPool::new(1);
+        let buffers = [pool.copy_from_slice(&[1]), pool.copy_from_slice(&[2])];
+        drop(buffers);
+        assert_eq!(pool.idle(), 1);
+
+        // buffers outliving their pool are just freed
+        let buffer = pool.copy_from_slice(&[3]);
+        drop(pool);
+        drop(buffer);
+        drop(PooledBuffer::unpooled(vec![4]));
+    }
 }
@@ -0,0 +1,1367 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Pure Rust conversions of raw frame data into RGB, which work without linking any native libraries.
+//!
+//! The `buf_` variants write into memory provided by the caller, so that it can be reused between frames. [`convert_into()`]
+//! also writes BGRA, and into rows with padding at their end, so frames can go straight into e.g. a mapped texture.
+//!
+//! `YUYV` and `NV12` use SSE2/AVX2 on x86 and NEON on aarch64 when the CPU supports it, and fall back to plain Rust otherwise.
+//! With the `conversion-libyuv` or `conversion-dcv` features, the converters can instead be handed to libyuv or
+//! dcv-color-primitives, see [`set_conversion_engine()`]. With `parallel-conversion`, the native converters use multiple threads
+//! for large frames.
+//!
+//! YUV is taken to be BT.601 limited range, unless a [`ColorSpace`] is given to [`convert_into()`].
+//!
+//! Bayer mosaics are demosaiced with bilinear interpolation, or Malvar-He-Cutler, see [`set_demosaic()`].
+//!
+//! MJPEG is decoded on the platform's hardware decoder where there is one, or with mozjpeg, see [`set_mjpeg_decoder()`].
+//!
+//! [`convert()`] converts between any two formats there is a converter for, looked up by their [`FourCC`]s. Custom
+//! converters can be added with [`register_converter()`].
+//!
+//! The 10-bit formats [`P010`](crate::pixel_format::P010) and [`Y210`](crate::pixel_format::Y210) convert into RGB48 or
+//! floating point RGB instead, so that HDR pipelines keep their precision.
+
+mod bayer;
+#[cfg(feature = "conversion-dcv")]
+mod dcv;
+#[cfg(feature = "conversion-libyuv")]
+mod libyuv;
+mod mjpeg;
+mod registry;
+mod simd;
+mod ten_bit;
+
+pub use bayer::{bayer_to_rgb, buf_bayer_to_rgb, demosaic, set_demosaic, BayerPattern, Demosaic};
+pub use mjpeg::{
+    buf_mjpeg_to_rgb, hardware_decoder_name, mjpeg_decoder, mjpeg_to_rgb,
+    register_default_hardware_decoder, register_hardware_decoder, set_mjpeg_decoder, Decoder,
+    HardwareDecoder, HardwareDecoderFactory,
+};
+pub use registry::{
+    conversion_targets, convert, converter, register_converter, unregister_converter, ConverterFn,
+};
+pub use ten_bit::{
+    buf_p010_to_rgb48, buf_y210_to_rgb48, p010_to_rgb48, p010_to_rgb_f32, rgb48_to_f32,
+    y210_to_rgb48, y210_to_rgb_f32,
+};
+
+use crate::{
+    error::NokhwaError,
+    pixel_format::{KnownFrameFormat, BGRA, I420, NV12, YUYV, YV12},
+    types::Resolution,
+};
+use four_cc::FourCC;
+use std::{
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// The size in pixels from which the native converters spread a frame's rows over multiple threads. Needs the
+/// `parallel-conversion` feature.
+///
+/// Below this, handing the rows to other threads costs more than it saves.
+#[cfg(feature = "parallel-conversion")]
+pub const PARALLEL_CONVERSION_THRESHOLD: usize = 1280 * 720;
+
+static CONVERSION_ENGINE: AtomicU8 = AtomicU8::new(ConversionEngine::Native as u8);
+
+/// The implementation behind the converters in this module.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum ConversionEngine {
+    /// The converters in this module. This is always available.
+    #[default]
+    Native,
+    /// [libyuv](https://chromium.googlesource.com/libyuv/libyuv/), linked as the system library `yuv`. Needs the
+    /// `conversion-libyuv` feature.
+    Libyuv,
+    /// [dcv-color-primitives](https://github.com/aws/dcv-color-primitives). Needs the `conversion-dcv` feature.
+    ///
+    /// It cannot convert `YUYV`, or `BGRA` into RGBA, so those still use the native converters.
+    DcvColorPrimitives,
+}
+
+impl ConversionEngine {
+    /// Whether support for this engine was compiled in.
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        match self {
+            ConversionEngine::Native => true,
+            ConversionEngine::Libyuv => cfg!(feature = "conversion-libyuv"),
+            ConversionEngine::DcvColorPrimitives => cfg!(feature = "conversion-dcv"),
+        }
+    }
+}
+
+impl Display for ConversionEngine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionEngine::Native => write!(f, "Native"),
+            ConversionEngine::Libyuv => write!(f, "libyuv"),
+            ConversionEngine::DcvColorPrimitives => write!(f, "dcv-color-primitives"),
+        }
+    }
+}
+
+/// Sets the engine every converter in this module uses from now on, for all threads. The default is [`ConversionEngine::Native`].
+/// # Errors
+/// If support for the `engine` was not compiled in, this will error and the engine stays the same.
+pub fn set_conversion_engine(engine: ConversionEngine) -> Result<(), NokhwaError> {
+    if !engine.is_available() {
+        return Err(NokhwaError::NotImplementedError(format!(
+            "The {engine} conversion engine was not compiled in"
+        )));
+    }
+    CONVERSION_ENGINE.store(engine as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Gets the engine the converters in this module use, see [`set_conversion_engine()`].
+#[must_use]
+pub fn conversion_engine() -> ConversionEngine {
+    match CONVERSION_ENGINE.load(Ordering::Relaxed) {
+        1 => ConversionEngine::Libyuv,
+        2 => ConversionEngine::DcvColorPrimitives,
+        _ => ConversionEngine::Native,
+    }
+}
+
+/// The matrix that mixes the Y, Cb and Cr samples of a frame into RGB.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601, used by SD video and most webcams.
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, used by HD video.
+    Bt709,
+}
+
+/// The range of values the samples of a frame span.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum YuvRange {
+    /// Y from 16 to 235, Cb and Cr from 16 to 240. Also called video or TV range.
+    #[default]
+    Limited,
+    /// Y, Cb and Cr from 0 to 255. Also called PC range, this is what JPEG uses.
+    Full,
+}
+
+/// How the YUV samples of a frame map to RGB. Frames a backend could not find out about are converted as
+/// [`ColorSpace::BT601_LIMITED`].
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ColorSpace {
+    matrix: YuvMatrix,
+    range: YuvRange,
+}
+
+impl ColorSpace {
+    /// BT.601 in limited range, the default.
+    pub const BT601_LIMITED: ColorSpace = ColorSpace::new(YuvMatrix::Bt601, YuvRange::Limited);
+    /// BT.601 in full range.
+    pub const BT601_FULL: ColorSpace = ColorSpace::new(YuvMatrix::Bt601, YuvRange::Full);
+    /// BT.709 in limited range.
+    pub const BT709_LIMITED: ColorSpace = ColorSpace::new(YuvMatrix::Bt709, YuvRange::Limited);
+    /// BT.709 in full range.
+    pub const BT709_FULL: ColorSpace = ColorSpace::new(YuvMatrix::Bt709, YuvRange::Full);
+
+    /// Creates a color space from its `matrix` and `range`.
+    #[must_use]
+    pub const fn new(matrix: YuvMatrix, range: YuvRange) -> Self {
+        ColorSpace { matrix, range }
+    }
+
+    /// Gets the matrix.
+    #[must_use]
+    pub fn matrix(self) -> YuvMatrix {
+        self.matrix
+    }
+
+    /// Gets the range.
+    #[must_use]
+    pub fn range(self) -> YuvRange {
+        self.range
+    }
+
+    fn coefficients(self) -> Coefficients {
+        // the factors of the standards times 64, the luma scale times 128
+        let (luma_offset, luma_scale) = match self.range {
+            YuvRange::Limited => (16, 149),
+            YuvRange::Full => (0, 128),
+        };
+        let factors = match (self.matrix, self.range) {
+            (YuvMatrix::Bt601, YuvRange::Limited) => [102, 25, 52, 129],
+            (YuvMatrix::Bt601, YuvRange::Full) => [90, 22, 46, 113],
+            (YuvMatrix::Bt709, YuvRange::Limited) => [115, 14, 34, 135],
+            (YuvMatrix::Bt709, YuvRange::Full) => [101, 12, 30, 119],
+        };
+        Coefficients {
+            luma_offset,
+            luma_scale,
+            cr_red: factors[0],
+            cb_green: factors[1],
+            cr_green: factors[2],
+            cb_blue: factors[3],
+        }
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let matrix = match self.matrix {
+            YuvMatrix::Bt601 => "BT.601",
+            YuvMatrix::Bt709 => "BT.709",
+        };
+        let range = match self.range {
+            YuvRange::Limited => "limited",
+            YuvRange::Full => "full",
+        };
+        write!(f, "{matrix} {range} range")
+    }
+}
+
+/// The fixed point factors [`yuv_to_rgb()`] uses for a [`ColorSpace`].
+#[derive(Copy, Clone)]
+struct Coefficients {
+    luma_offset: i32,
+    luma_scale: i32,
+    cr_red: i32,
+    cb_green: i32,
+    cr_green: i32,
+    cb_blue: i32,
+}
+
+/// The pixel layout the converters write.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RgbFormat {
+    /// Packed RGB, 3 bytes per pixel.
+    #[default]
+    Rgb24,
+    /// Packed RGBA with an opaque alpha, 4 bytes per pixel.
+    Rgba,
+    /// Packed BGRA with an opaque alpha, 4 bytes per pixel. This is what Direct3D and many GUI toolkits upload.
+    Bgra,
+}
+
+impl RgbFormat {
+    /// Gets the amount of bytes one pixel takes up.
+    #[must_use]
+    pub fn pixel_size(self) -> usize {
+        match self {
+            RgbFormat::Rgb24 => 3,
+            RgbFormat::Rgba | RgbFormat::Bgra => 4,
+        }
+    }
+
+    fn from_rgba(rgba: bool) -> Self {
+        if rgba {
+            RgbFormat::Rgba
+        } else {
+            RgbFormat::Rgb24
+        }
+    }
+}
+
+impl Display for RgbFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RgbFormat::Rgb24 => write!(f, "RGB24"),
+            RgbFormat::Rgba => write!(f, "RGBA"),
+            RgbFormat::Bgra => write!(f, "BGRA"),
+        }
+    }
+}
+
+/// Where [`convert_into()`] writes a frame: memory provided by the caller, such as a mapped texture, whose rows may be further
+/// apart than they are long.
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub struct RgbDestination<'a> {
+    data: &'a mut [u8],
+    format: RgbFormat,
+    stride: Option<usize>,
+}
+
+impl<'a> RgbDestination<'a> {
+    /// Creates a destination that packs the rows of the frame tightly into `data`.
+    #[must_use]
+    pub fn new(data: &'a mut [u8], format: RgbFormat) -> Self {
+        RgbDestination {
+            data,
+            format,
+            stride: None,
+        }
+    }
+
+    /// Starts each row `stride` bytes after the previous one instead. The padding at the end of the rows is left alone.
+    #[must_use]
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = Some(stride);
+        self
+    }
+
+    /// Gets the pixel layout.
+    #[must_use]
+    pub fn format(&self) -> RgbFormat {
+        self.format
+    }
+
+    /// Gets the distance in bytes between the starts of two rows, or `None` if they are packed tightly.
+    #[must_use]
+    pub fn stride(&self) -> Option<usize> {
+        self.stride
+    }
+
+    fn reborrow(&mut self) -> RgbDestination<'_> {
+        RgbDestination {
+            data: &mut *self.data,
+            format: self.format,
+            stride: self.stride,
+        }
+    }
+
+    /// Checks that a frame of `resolution` fits, and returns the part of the data it goes into along with the stride.
+    fn rows(
+        &mut self,
+        resolution: Resolution,
+        src: FourCC,
+    ) -> Result<(&mut [u8], usize), NokhwaError> {
+        let row_len = resolution.width() as usize * self.format.pixel_size();
+        let stride = self.stride.unwrap_or(row_len);
+        if stride < row_len {
+            return Err(conversion_error(
+                src,
+                self.format,
+                format!("A stride of {stride} bytes is shorter than a row of {row_len}"),
+            ));
+        }
+        let dest_size = match resolution.height() as usize {
+            0 => 0,
+            height => stride * (height - 1) + row_len,
+        };
+        if self.data.len() < dest_size {
+            return Err(conversion_error(
+                src,
+                self.format,
+                format!(
+                    "Destination needs {dest_size} bytes, has {}",
+                    self.data.len()
+                ),
+            ));
+        }
+        Ok((&mut self.data[..dest_size], stride))
+    }
+}
+
+/// Converts a frame into `dest`, which can be `BGRA` and have padding at the end of its rows, so that it can e.g. be uploaded to
+/// a texture without repacking it first.
+///
+/// YUV frames are converted as `color_space`, usually [`FrameBuffer::color_space()`](crate::buffer::FrameBuffer::color_space).
+/// The SIMD paths and the engines only do [`ColorSpace::BT601_LIMITED`], any other color space uses the plain Rust converters.
+///
+/// `src` can be anything the converters in this module turn into RGB: [`NV12`], [`YUV420`](crate::pixel_format::YUV420),
+/// [`I420`], [`YV12`], [`YUYV`], [`BGRA`], [`MJPEG`](crate::pixel_format::MJPEG), or one of the 8-bit Bayer formats.
+/// # Errors
+/// If `src` is not one of those, `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this
+/// will error.
+pub fn convert_into(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+    dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    match KnownFrameFormat::from(src) {
+        KnownFrameFormat::Yuv420 | KnownFrameFormat::Nv12 => {
+            nv12_into(resolution, data, dest, color_space)
+        }
+        KnownFrameFormat::I420 => {
+            let [luma, first, second] = split_planar_420(resolution, data, I420, dest.format)?;
+            convert_planar_420(resolution, luma, first, second, dest, I420, color_space)
+        }
+        KnownFrameFormat::Yv12 => {
+            let [luma, first, second] = split_planar_420(resolution, data, YV12, dest.format)?;
+            convert_planar_420(resolution, luma, second, first, dest, YV12, color_space)
+        }
+        KnownFrameFormat::Yuyv => yuyv422_into(resolution, data, dest, color_space),
+        KnownFrameFormat::Bgra => bgra_into(resolution, data, dest),
+        KnownFrameFormat::Mjpeg => mjpeg::mjpeg_into(resolution, data, dest),
+        format => match BayerPattern::from_fourcc(src) {
+            Some(pattern) => bayer::bayer_into(resolution, data, dest, pattern),
+            None => Err(conversion_error(
+                src,
+                dest.format,
+                format!("{format} cannot be converted"),
+            )),
+        },
+    }
+}
+
+/// Converts a bi-planar 4:2:0 frame ([`NV12`](crate::pixel_format::NV12), or Apple's [`YUV420`](crate::pixel_format::YUV420))
+/// into packed RGB24, or RGBA with an opaque alpha if `rgba` is set.
+///
+/// The full-resolution Y plane is followed by an interleaved UV plane at half the resolution. BT.601 limited range is assumed.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn nv12_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_nv12_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Converts a bi-planar 4:2:0 frame into `dest`, see [`nv12_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_nv12_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    nv12_into(
+        resolution,
+        data,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+        ColorSpace::BT601_LIMITED,
+    )
+}
+
+fn nv12_into(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+    color_space: ColorSpace,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let error = |error: String| conversion_error(NV12, format, error);
+
+    let width = resolution.width() as usize;
+    let height = resolution.height() as usize;
+    // odd sizes still get a chroma sample for the last column and row
+    let chroma_stride = width.div_ceil(2) * 2;
+    let luma_size = width * height;
+    let source_size = luma_size + chroma_stride * height.div_ceil(2);
+    if data.len() < source_size {
+        return Err(error(format!(
+            "Expected at least {source_size} bytes, got {}",
+            data.len()
+        )));
+    }
+    let (dest, stride) = dest.rows(resolution, NV12)?;
+    if width == 0 {
+        return Ok(());
+    }
+
+    let (luma, chroma) = data.split_at(luma_size);
+    let chroma = &chroma[..source_size - luma_size];
+    #[cfg(feature = "conversion-libyuv")]
+    if conversion_engine() == ConversionEngine::Libyuv && color_space == ColorSpace::BT601_LIMITED {
+        if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+            Some(libyuv::nv12(resolution, luma, chroma, dest, rgba))
+        }) {
+            return result.map_err(error);
+        }
+    }
+    #[cfg(feature = "conversion-dcv")]
+    if conversion_engine() == ConversionEngine::DcvColorPrimitives
+        && color_space == ColorSpace::BT601_LIMITED
+    {
+        if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+            dcv::nv12(resolution, luma, chroma, dest, rgba)
+        }) {
+            return result.map_err(error);
+        }
+    }
+    let rgba = format != RgbFormat::Rgb24;
+    let pixel_size = format.pixel_size();
+    let coefficients = color_space.coefficients();
+    let simd = color_space == ColorSpace::BT601_LIMITED;
+    for_each_rgb_row(dest, width, stride, format, |row, dest_row| {
+        let luma_row = &luma[row * width..][..width];
+        let chroma_row = &chroma[(row / 2) * chroma_stride..][..chroma_stride];
+        // the SIMD paths always stop on an even pixel, so the chroma pairs line up
+        let done = if simd {
+            simd::nv12_row(luma_row, chroma_row, dest_row, rgba)
+        } else {
+            0
+        };
+        for ((luma_pair, uv), dest_pair) in luma_row[done..]
+            .chunks(2)
+            .zip(chroma_row[done..].chunks_exact(2))
+            .zip(dest_row[done * pixel_size..].chunks_mut(2 * pixel_size))
+        {
+            for (&luma, pixel) in luma_pair.iter().zip(dest_pair.chunks_exact_mut(pixel_size)) {
+                pixel[..3].copy_from_slice(&yuv_to_rgb(luma, uv[0], uv[1], coefficients));
+                if rgba {
+                    pixel[3] = u8::MAX;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Converts a packed 4:2:2 frame ([`YUYV`](crate::pixel_format::YUYV)) into packed RGB24, or RGBA with an opaque alpha if `rgba` is set.
+///
+/// Every two pixels are stored as `Y0 U Y1 V`. BT.601 limited range is assumed.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn yuyv422_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_yuyv422_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Converts a packed 4:2:2 frame into `dest`, see [`yuyv422_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_yuyv422_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    yuyv422_into(
+        resolution,
+        data,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+        ColorSpace::BT601_LIMITED,
+    )
+}
+
+fn yuyv422_into(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+    color_space: ColorSpace,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let error = |error: String| conversion_error(YUYV, format, error);
+
+    let width = resolution.width() as usize;
+    let height = resolution.height() as usize;
+    // an odd width still stores a whole `Y0 U Y1 V` group for the last pixel
+    let src_stride = width.div_ceil(2) * 4;
+    let source_size = src_stride * height;
+    if data.len() < source_size {
+        return Err(error(format!(
+            "Expected at least {source_size} bytes, got {}",
+            data.len()
+        )));
+    }
+    let (dest, stride) = dest.rows(resolution, YUYV)?;
+    if width == 0 {
+        return Ok(());
+    }
+
+    #[cfg(feature = "conversion-libyuv")]
+    if conversion_engine() == ConversionEngine::Libyuv && color_space == ColorSpace::BT601_LIMITED {
+        if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+            Some(libyuv::yuyv(resolution, &data[..source_size], dest, rgba))
+        }) {
+            return result.map_err(error);
+        }
+    }
+    let rgba = format != RgbFormat::Rgb24;
+    let pixel_size = format.pixel_size();
+    let coefficients = color_space.coefficients();
+    let simd = color_space == ColorSpace::BT601_LIMITED;
+    for_each_rgb_row(dest, width, stride, format, |row, dest_row| {
+        let src_row = &data[row * src_stride..][..src_stride];
+        let done = if simd {
+            simd::yuyv_row(&src_row[..width * 2], dest_row, rgba)
+        } else {
+            0
+        };
+        for (group, dest_pair) in src_row[done * 2..]
+            .chunks_exact(4)
+            .zip(dest_row[done * pixel_size..].chunks_mut(2 * pixel_size))
+        {
+            for (&luma, pixel) in [group[0], group[2]]
+                .iter()
+                .zip(dest_pair.chunks_exact_mut(pixel_size))
+            {
+                pixel[..3].copy_from_slice(&yuv_to_rgb(luma, group[1], group[3], coefficients));
+                if rgba {
+                    pixel[3] = u8::MAX;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Converts a packed [`BGRA`](crate::pixel_format::BGRA) frame into packed RGB24, or RGBA if `rgba` is set.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn bgra_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_bgra_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Converts a packed `BGRA` frame into `dest`, see [`bgra_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_bgra_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    bgra_into(
+        resolution,
+        data,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+    )
+}
+
+fn bgra_into(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let error = |error: String| conversion_error(BGRA, format, error);
+
+    let width = resolution.width() as usize;
+    let source_size = width * resolution.height() as usize * 4;
+    if data.len() < source_size {
+        return Err(error(format!(
+            "Expected at least {source_size} bytes, got {}",
+            data.len()
+        )));
+    }
+    let (dest, stride) = dest.rows(resolution, BGRA)?;
+    if width == 0 {
+        return Ok(());
+    }
+    let data = &data[..source_size];
+    // copying BGRA as it is beats any engine
+    #[cfg(feature = "conversion-libyuv")]
+    if conversion_engine() == ConversionEngine::Libyuv && format != RgbFormat::Bgra {
+        if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+            Some(libyuv::bgra(resolution, data, dest, rgba))
+        }) {
+            return result.map_err(error);
+        }
+    }
+    #[cfg(feature = "conversion-dcv")]
+    if conversion_engine() == ConversionEngine::DcvColorPrimitives && format != RgbFormat::Bgra {
+        if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+            dcv::bgra(resolution, data, dest, rgba)
+        }) {
+            return result.map_err(error);
+        }
+    }
+
+    let row_len = width * format.pixel_size();
+    for (src_row, dest_row) in data.chunks_exact(width * 4).zip(dest.chunks_mut(stride)) {
+        let dest_row = &mut dest_row[..row_len];
+        match format {
+            RgbFormat::Rgb24 => {
+                for (pixel, bgra) in dest_row.chunks_exact_mut(3).zip(src_row.chunks_exact(4)) {
+                    pixel.copy_from_slice(&[bgra[2], bgra[1], bgra[0]]);
+                }
+            }
+            RgbFormat::Rgba => {
+                dest_row.copy_from_slice(src_row);
+                swap_red_blue(dest_row);
+            }
+            RgbFormat::Bgra => dest_row.copy_from_slice(src_row),
+        }
+    }
+    Ok(())
+}
+
+/// One plane of a planar frame, for the stride-aware converters.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Plane<'a> {
+    data: &'a [u8],
+    stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    /// Creates a plane from its `data`, where each row starts `stride` bytes after the previous one.
+    #[must_use]
+    pub fn new(data: &'a [u8], stride: usize) -> Self {
+        Plane { data, stride }
+    }
+
+    /// Gets the data of the plane.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Gets the distance in bytes between the starts of two rows.
+    #[must_use]
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    fn row(&self, row: usize, len: usize) -> Option<&'a [u8]> {
+        self.data.get(row.checked_mul(self.stride)?..)?.get(..len)
+    }
+}
+
+/// Converts a planar 4:2:0 frame ([`I420`](crate::pixel_format::I420)) into packed RGB24, or RGBA if `rgba` is set.
+///
+/// The full-resolution Y plane is followed by the U and then the V plane, both at half the resolution. BT.601 limited range is assumed.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn i420_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_i420_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Converts a planar 4:2:0 frame into `dest`, see [`i420_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_i420_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    let format = RgbFormat::from_rgba(rgba);
+    let [luma, first, second] = split_planar_420(resolution, data, I420, format)?;
+    convert_planar_420(
+        resolution,
+        luma,
+        first,
+        second,
+        RgbDestination::new(dest, format),
+        I420,
+        ColorSpace::BT601_LIMITED,
+    )
+}
+
+/// Converts a planar 4:2:0 frame with the V plane first ([`YV12`](crate::pixel_format::YV12)) into packed RGB24, or RGBA if `rgba` is set.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn yv12_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_yv12_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Converts a planar 4:2:0 frame with the V plane first into `dest`, see [`yv12_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_yv12_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    let format = RgbFormat::from_rgba(rgba);
+    let [luma, first, second] = split_planar_420(resolution, data, YV12, format)?;
+    convert_planar_420(
+        resolution,
+        luma,
+        second,
+        first,
+        RgbDestination::new(dest, format),
+        YV12,
+        ColorSpace::BT601_LIMITED,
+    )
+}
+
+/// Converts a planar 4:2:0 frame whose planes are stored separately, or have padding at the end of their rows, into `dest`.
+/// This is what e.g. `VideoFrame`s and `libcamera` hand out.
+///
+/// `u` and `v` have half the width and height of `y`, rounded up. BT.601 limited range is assumed.
+/// # Errors
+/// If a plane is too small for the `resolution` and its stride, or `dest` is too small for the converted frame, this will error.
+pub fn buf_yuv420p_to_rgb(
+    resolution: Resolution,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    buf_yuv420p_into(
+        resolution,
+        ColorSpace::BT601_LIMITED,
+        y,
+        u,
+        v,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+    )
+}
+
+/// Converts a planar 4:2:0 frame whose planes are stored separately into `dest`, like [`buf_yuv420p_to_rgb()`], but into any
+/// [`RgbDestination`] and as `color_space`.
+/// # Errors
+/// If a plane is too small for the `resolution` and its stride, or `dest` is too small for the converted frame, this will error.
+pub fn buf_yuv420p_into(
+    resolution: Resolution,
+    color_space: ColorSpace,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+    dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    convert_planar_420(resolution, y, u, v, dest, I420, color_space)
+}
+
+fn rgb_size(resolution: Resolution, rgba: bool) -> usize {
+    resolution.width() as usize
+        * resolution.height() as usize
+        * RgbFormat::from_rgba(rgba).pixel_size()
+}
+
+/// Swaps the first and third byte of every 4 byte pixel, turning BGRA into RGBA and back.
+fn swap_red_blue(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+fn conversion_error(src: FourCC, format: RgbFormat, error: String) -> NokhwaError {
+    NokhwaError::ProcessFrameError {
+        src,
+        destination: format.to_string(),
+        error,
+    }
+}
+
+/// Splits a tightly packed planar 4:2:0 frame into its three planes, in the order they are stored.
+fn split_planar_420(
+    resolution: Resolution,
+    data: &[u8],
+    src: FourCC,
+    format: RgbFormat,
+) -> Result<[Plane<'_>; 3], NokhwaError> {
+    let width = resolution.width() as usize;
+    let luma_size = width * resolution.height() as usize;
+    let chroma_width = width.div_ceil(2);
+    let chroma_size = chroma_width * (resolution.height() as usize).div_ceil(2);
+    let source_size = luma_size + 2 * chroma_size;
+    if data.len() < source_size {
+        return Err(conversion_error(
+            src,
+            format,
+            format!("Expected at least {source_size} bytes, got {}", data.len()),
+        ));
+    }
+    let (luma, chroma) = data.split_at(luma_size);
+    let (first, second) = chroma.split_at(chroma_size);
+    Ok([
+        Plane::new(luma, width),
+        Plane::new(first, chroma_width),
+        Plane::new(&second[..chroma_size], chroma_width),
+    ])
+}
+
+fn convert_planar_420(
+    resolution: Resolution,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+    mut dest: RgbDestination,
+    src: FourCC,
+    color_space: ColorSpace,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let width = resolution.width() as usize;
+    let height = resolution.height() as usize;
+    let pixel_size = format.pixel_size();
+    let (dest, stride) = dest.rows(resolution, src)?;
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    let chroma_width = width.div_ceil(2);
+    let missing_row = |plane: &str, row: usize| {
+        conversion_error(
+            src,
+            format,
+            format!("The {plane} plane is too small for row {row}"),
+        )
+    };
+    #[cfg(any(feature = "conversion-libyuv", feature = "conversion-dcv"))]
+    {
+        // the engines need every row to be there up front
+        let chroma_height = height.div_ceil(2);
+        y.row(height - 1, width)
+            .ok_or_else(|| missing_row("Y", height - 1))?;
+        u.row(chroma_height - 1, chroma_width)
+            .ok_or_else(|| missing_row("U", chroma_height - 1))?;
+        v.row(chroma_height - 1, chroma_width)
+            .ok_or_else(|| missing_row("V", chroma_height - 1))?;
+        let error = |error| conversion_error(src, format, error);
+        #[cfg(feature = "conversion-libyuv")]
+        if conversion_engine() == ConversionEngine::Libyuv
+            && color_space == ColorSpace::BT601_LIMITED
+        {
+            if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+                Some(libyuv::yuv420p(resolution, y, u, v, dest, rgba))
+            }) {
+                return result.map_err(error);
+            }
+        }
+        #[cfg(feature = "conversion-dcv")]
+        if conversion_engine() == ConversionEngine::DcvColorPrimitives
+            && color_space == ColorSpace::BT601_LIMITED
+        {
+            if let Some(result) = with_engine(dest, stride, width, format, |dest, rgba| {
+                dcv::yuv420p(resolution, y, u, v, dest, rgba)
+            }) {
+                return result.map_err(error);
+            }
+        }
+    }
+    let rgba = format != RgbFormat::Rgb24;
+    let coefficients = color_space.coefficients();
+    for_each_rgb_row(dest, width, stride, format, |row, dest_row| {
+        let luma_row = y.row(row, width).ok_or_else(|| missing_row("Y", row))?;
+        let u_row = u
+            .row(row / 2, chroma_width)
+            .ok_or_else(|| missing_row("U", row / 2))?;
+        let v_row = v
+            .row(row / 2, chroma_width)
+            .ok_or_else(|| missing_row("V", row / 2))?;
+        for (((luma_pair, &cb), &cr), dest_pair) in luma_row
+            .chunks(2)
+            .zip(u_row)
+            .zip(v_row)
+            .zip(dest_row.chunks_mut(2 * pixel_size))
+        {
+            for (&luma, pixel) in luma_pair.iter().zip(dest_pair.chunks_exact_mut(pixel_size)) {
+                pixel[..3].copy_from_slice(&yuv_to_rgb(luma, cb, cr, coefficients));
+                if rgba {
+                    pixel[3] = u8::MAX;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Hands a packed destination to an engine, which writes RGB24, or RGBA if the `bool` is set, and returns `None` if it cannot
+/// do the conversion. `BGRA` is swapped into place afterwards. Destinations with padding are left to the native converters.
+#[cfg(any(feature = "conversion-libyuv", feature = "conversion-dcv"))]
+fn with_engine(
+    dest: &mut [u8],
+    stride: usize,
+    width: usize,
+    format: RgbFormat,
+    convert: impl FnOnce(&mut [u8], bool) -> Option<Result<(), String>>,
+) -> Option<Result<(), String>> {
+    if stride != width * format.pixel_size() {
+        return None;
+    }
+    let result = convert(dest, format != RgbFormat::Rgb24)?;
+    if result.is_ok() && format == RgbFormat::Bgra {
+        swap_red_blue(dest);
+    }
+    Some(result)
+}
+
+/// Runs `convert` on every row of an 8-bit destination of `format`, see [`for_each_row()`]. `convert` always writes RGB24 or
+/// RGBA, `BGRA` rows are swapped into place afterwards.
+fn for_each_rgb_row<F>(
+    dest: &mut [u8],
+    width: usize,
+    stride: usize,
+    format: RgbFormat,
+    convert: F,
+) -> Result<(), NokhwaError>
+where
+    F: Fn(usize, &mut [u8]) -> Result<(), NokhwaError> + Send + Sync,
+{
+    for_each_row(dest, width, format.pixel_size(), stride, |row, dest_row| {
+        convert(row, dest_row)?;
+        if format == RgbFormat::Bgra {
+            swap_red_blue(dest_row);
+        }
+        Ok(())
+    })
+}
+
+/// Runs `convert` on every `width` pixel row of `dest`, along with its index. Rows start `stride` elements apart, and the
+/// padding after them is not handed to `convert`. `pixel_size` and `stride` count components, not bytes.
+///
+/// With the `parallel-conversion` feature, frames of at least `PARALLEL_CONVERSION_THRESHOLD` pixels are split across rayon's
+/// thread pool.
+fn for_each_row<T, F>(
+    dest: &mut [T],
+    width: usize,
+    pixel_size: usize,
+    stride: usize,
+    convert: F,
+) -> Result<(), NokhwaError>
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) -> Result<(), NokhwaError> + Send + Sync,
+{
+    let row_len = width * pixel_size;
+    #[cfg(feature = "parallel-conversion")]
+    if dest.len().div_ceil(stride) * width >= PARALLEL_CONVERSION_THRESHOLD {
+        use rayon::{
+            iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut,
+        };
+
+        return dest
+            .par_chunks_mut(stride)
+            .enumerate()
+            .try_for_each(|(row, dest_row)| convert(row, &mut dest_row[..row_len]));
+    }
+    dest.chunks_mut(stride)
+        .enumerate()
+        .try_for_each(|(row, dest_row)| convert(row, &mut dest_row[..row_len]))
+}
+
+/// Converts one YUV sample to RGB, using 6 bits of fixed point precision.
+///
+/// This is as much as fits into the 16 bit lanes of the SIMD paths, which give the exact same results for BT.601 limited range.
+#[inline]
+fn yuv_to_rgb(luma: u8, cb: u8, cr: u8, coefficients: Coefficients) -> [u8; 3] {
+    // in limited range 1.164 * 64 is 74.5, which is why the luma scale has one more bit
+    let luma = i32::from(luma) - coefficients.luma_offset;
+    let luma = ((luma * coefficients.luma_scale) >> 1) + 32;
+    let cb = i32::from(cb) - 128;
+    let cr = i32::from(cr) - 128;
+    [
+        clamp_to_u8((luma + coefficients.cr_red * cr) >> 6),
+        clamp_to_u8((luma - coefficients.cb_green * cb - coefficients.cr_green * cr) >> 6),
+        clamp_to_u8((luma + coefficients.cb_blue * cb) >> 6),
+    ]
+}
+
+#[inline]
+fn clamp_to_u8(value: i32) -> u8 {
+    u8::try_from(value.clamp(0, 255)).unwrap_or(u8::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repeatable frame of noise, so that every sample value and chroma pairing shows up.
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nv12_black_white_and_alpha() {
+        // a 3x3 frame: luma 16 in the left column, 235 elsewhere, and neutral chroma
+        let mut data = vec![235; 9];
+        for row in 0..3 {
+            data[row * 3] = 16;
+        }
+        data.extend([128; 8]);
+        let rgb = nv12_to_rgb(Resolution::new(3, 3), &data, false).unwrap();
+        for pixel in rgb.chunks_exact(3).step_by(3) {
+            assert_eq!(pixel, [0, 0, 0]);
+        }
+        assert_eq!(&rgb[3..9], &[255; 6]);
+
+        let rgba = nv12_to_rgb(Resolution::new(3, 3), &data, true).unwrap();
+        assert_eq!(rgba.len(), 36);
+        assert!(rgba.chunks_exact(4).all(|pixel| pixel[3] == u8::MAX));
+    }
+
+    #[test]
+    fn nv12_shares_chroma_between_2x2_blocks() {
+        let resolution = Resolution::new(5, 3);
+        let mut data = vec![128; 15];
+        data.extend(noise(12, 7));
+        let rgb = nv12_to_rgb(resolution, &data, false).unwrap();
+        let pixel = |x: usize, y: usize| &rgb[(y * 5 + x) * 3..][..3];
+        for y in 0..3 {
+            for x in 0..5 {
+                assert_eq!(pixel(x, y), pixel(x & !1, y & !1));
+            }
+        }
+    }
+
+    #[test]
+    fn nv12_rejects_short_buffers() {
+        let resolution = Resolution::new(4, 4);
+        assert!(nv12_to_rgb(resolution, &[0; 23], false).is_err());
+        assert!(buf_nv12_to_rgb(resolution, &[0; 24], &mut [0; 47], false).is_err());
+        assert!(buf_nv12_to_rgb(resolution, &[0; 24], &mut [0; 48], false).is_ok());
+    }
+
+    /// Splits a tightly packed I420 frame into its Y, U and V planes.
+    fn i420_planes(resolution: Resolution, data: &[u8]) -> (&[u8], &[u8], &[u8]) {
+        let luma_size = (resolution.width() * resolution.height()) as usize;
+        let chroma_size =
+            (resolution.width().div_ceil(2) * resolution.height().div_ceil(2)) as usize;
+        let (luma, chroma) = data.split_at(luma_size);
+        let (u, v) = chroma.split_at(chroma_size);
+        (luma, u, &v[..chroma_size])
+    }
+
+    #[test]
+    fn i420_and_yv12_match_nv12() {
+        for (width, height) in [(1, 1), (2, 2), (5, 3), (16, 9), (33, 7)] {
+            let resolution = Resolution::new(width, height);
+            let chroma_size = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+            let i420 = noise((width * height) as usize + 2 * chroma_size, width ^ height);
+            let (luma, u, v) = i420_planes(resolution, &i420);
+
+            let mut nv12 = luma.to_vec();
+            nv12.extend(u.iter().zip(v).flat_map(|(&u, &v)| [u, v]));
+            let mut yv12 = luma.to_vec();
+            yv12.extend(v);
+            yv12.extend(u);
+
+            for rgba in [false, true] {
+                let expected = nv12_to_rgb(resolution, &nv12, rgba).unwrap();
+                assert_eq!(i420_to_rgb(resolution, &i420, rgba).unwrap(), expected);
+                assert_eq!(yv12_to_rgb(resolution, &yv12, rgba).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn yuv420p_honours_plane_strides() {
+        let resolution = Resolution::new(7, 5);
+        let i420 = noise(35 + 2 * 12, 3);
+        let (luma, u, v) = i420_planes(resolution, &i420);
+        let pad = |plane: &[u8], width: usize, stride: usize| {
+            let mut padded = vec![0; plane.len() / width * stride];
+            for (row, padded_row) in plane.chunks(width).zip(padded.chunks_mut(stride)) {
+                padded_row[..width].copy_from_slice(row);
+            }
+            padded
+        };
+        let (luma, u, v) = (pad(luma, 7, 16), pad(u, 4, 8), pad(v, 4, 12));
+
+        let mut dest = vec![0; 7 * 5 * 3];
+        buf_yuv420p_to_rgb(
+            resolution,
+            Plane::new(&luma, 16),
+            Plane::new(&u, 8),
+            Plane::new(&v, 12),
+            &mut dest,
+            false,
+        )
+        .unwrap();
+        assert_eq!(dest, i420_to_rgb(resolution, &i420, false).unwrap());
+
+        assert!(buf_yuv420p_to_rgb(
+            resolution,
+            Plane::new(&luma, 16),
+            Plane::new(&u[..8 * 2], 8),
+            Plane::new(&v, 12),
+            &mut dest,
+            false,
+        )
+        .is_err());
+    }
+
+    /// Converts pixels one at a time, the way the scalar path does.
+    fn scalar_pixels(samples: impl Iterator<Item = [u8; 3]>, rgba: bool) -> Vec<u8> {
+        let coefficients = ColorSpace::BT601_LIMITED.coefficients();
+        samples
+            .flat_map(|[luma, cb, cr]| {
+                let [red, green, blue] = yuv_to_rgb(luma, cb, cr, coefficients);
+                [red, green, blue, u8::MAX]
+                    .into_iter()
+                    .take(RgbFormat::from_rgba(rgba).pixel_size())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn simd_nv12_rows_match_scalar() {
+        for seed in 0..100 {
+            let width = seed as usize;
+            let luma = noise(width, 11 + seed);
+            let chroma = noise(width.div_ceil(2) * 2, 13 + seed);
+            for rgba in [false, true] {
+                let pixel_size = RgbFormat::from_rgba(rgba).pixel_size();
+                let mut dest = vec![0; width * pixel_size];
+                let done = simd::nv12_row(&luma, &chroma, &mut dest, rgba);
+                assert!(done <= width);
+                assert_eq!(done % 2, 0);
+                let expected = scalar_pixels(
+                    (0..done).map(|x| [luma[x], chroma[x & !1], chroma[x | 1]]),
+                    rgba,
+                );
+                assert_eq!(&dest[..done * pixel_size], expected, "width {width}");
+            }
+        }
+    }
+
+    #[test]
+    fn simd_yuyv_rows_match_scalar() {
+        for seed in 0..100 {
+            let width = seed as usize;
+            let src = noise(width.div_ceil(2) * 4, 17 + seed);
+            for rgba in [false, true] {
+                let pixel_size = RgbFormat::from_rgba(rgba).pixel_size();
+                let mut dest = vec![0; width * pixel_size];
+                let done = simd::yuyv_row(&src[..width * 2], &mut dest, rgba);
+                assert!(done <= width);
+                assert_eq!(done % 2, 0);
+                let expected = scalar_pixels(
+                    (0..done).map(|x| [src[x * 2], src[(x & !1) * 2 + 1], src[(x & !1) * 2 + 3]]),
+                    rgba,
+                );
+                assert_eq!(&dest[..done * pixel_size], expected, "width {width}");
+            }
+        }
+    }
+
+    #[test]
+    fn simd_rows_stop_at_the_end_of_dest() {
+        let luma = noise(64, 19);
+        let chroma = noise(64, 23);
+        let mut dest = vec![0; 20 * 3 + 2];
+        let done = simd::nv12_row(&luma, &chroma, &mut dest, false);
+        assert!(done <= 20);
+        assert_eq!(&dest[done * 3..], vec![0; dest.len() - done * 3]);
+    }
+
+    #[test]
+    fn yuyv_frames_match_scalar() {
+        for (width, height) in [(1_u32, 1_u32), (7, 3), (32, 2), (45, 4)] {
+            let resolution = Resolution::new(width, height);
+            let stride = width.div_ceil(2) as usize * 4;
+            let data = noise(stride * height as usize, width);
+            for rgba in [false, true] {
+                let expected = scalar_pixels(
+                    (0..(width * height) as usize).map(|pixel| {
+                        let (x, y) = (pixel % width as usize, pixel / width as usize);
+                        let group = &data[y * stride + (x / 2) * 4..][..4];
+                        [group[(x % 2) * 2], group[1], group[3]]
+                    }),
+                    rgba,
+                );
+                assert_eq!(yuyv422_to_rgb(resolution, &data, rgba).unwrap(), expected);
+            }
+        }
+    }
+
+    const COLOR_SPACES: [ColorSpace; 4] = [
+        ColorSpace::BT601_LIMITED,
+        ColorSpace::BT601_FULL,
+        ColorSpace::BT709_LIMITED,
+        ColorSpace::BT709_FULL,
+    ];
+
+    #[test]
+    fn color_spaces_keep_grey_grey_and_span_their_range() {
+        for color_space in COLOR_SPACES {
+            let coefficients = color_space.coefficients();
+            let (black, white) = match color_space.range() {
+                YuvRange::Limited => (16, 235),
+                YuvRange::Full => (0, 255),
+            };
+            assert_eq!(yuv_to_rgb(black, 128, 128, coefficients), [0; 3]);
+            assert_eq!(yuv_to_rgb(white, 128, 128, coefficients), [255; 3]);
+            let [red, green, blue] = yuv_to_rgb(100, 128, 128, coefficients);
+            assert!(red == green && green == blue, "{color_space}");
+        }
+    }
+
+    #[test]
+    fn matrices_and_ranges_differ() {
+        let colors =
+            COLOR_SPACES.map(|color_space| yuv_to_rgb(128, 90, 200, color_space.coefficients()));
+        for (index, color) in colors.iter().enumerate() {
+            assert!(
+                !colors[index + 1..].contains(color),
+                "{}",
+                COLOR_SPACES[index]
+            );
+        }
+    }
+
+    #[test]
+    fn convert_into_uses_the_color_space() {
+        let resolution = Resolution::new(33, 5);
+        let nv12 = noise(33 * 5 + 34 * 3, 29);
+        for color_space in COLOR_SPACES {
+            let coefficients = color_space.coefficients();
+            let expected = (0..33 * 5)
+                .flat_map(|pixel| {
+                    let (x, y) = (pixel % 33, pixel / 33);
+                    let uv = &nv12[33 * 5 + (y / 2) * 34 + (x & !1)..][..2];
+                    yuv_to_rgb(nv12[pixel], uv[0], uv[1], coefficients)
+                })
+                .collect::<Vec<_>>();
+            let mut dest = vec![0; expected.len()];
+            convert_into(
+                resolution,
+                NV12,
+                color_space,
+                &nv12,
+                RgbDestination::new(&mut dest, RgbFormat::Rgb24),
+            )
+            .unwrap();
+            assert_eq!(dest, expected, "{color_space}");
+        }
+    }
+
+    #[test]
+    fn convert_into_bgra_leaves_the_padding_alone() {
+        let resolution = Resolution::new(9, 3);
+        let yuyv = noise(20 * 3, 31);
+        let rgba = yuyv422_to_rgb(resolution, &yuyv, true).unwrap();
+        let mut dest = vec![0xAA; 40 * 2 + 36];
+        convert_into(
+            resolution,
+            YUYV,
+            ColorSpace::BT601_LIMITED,
+            &yuyv,
+            RgbDestination::new(&mut dest, RgbFormat::Bgra).with_stride(40),
+        )
+        .unwrap();
+        for (row, expected) in dest.chunks(40).zip(rgba.chunks_exact(36)) {
+            for (pixel, expected) in row[..36].chunks_exact(4).zip(expected.chunks_exact(4)) {
+                assert_eq!(pixel, [expected[2], expected[1], expected[0], expected[3]]);
+            }
+            assert!(row[36..].iter().all(|&byte| byte == 0xAA));
+        }
+    }
+}
@@ -0,0 +1,387 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Demosaicing of 8-bit Bayer mosaics into RGB.
+
+use super::{clamp_to_u8, conversion_error, for_each_rgb_row, rgb_size, RgbDestination, RgbFormat};
+use crate::{
+    error::NokhwaError,
+    pixel_format::{BAYER_BGGR8, BAYER_GBRG8, BAYER_GRBG8, BAYER_RGGB8},
+    types::Resolution,
+};
+use four_cc::FourCC;
+use std::{
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+static DEMOSAIC: AtomicU8 = AtomicU8::new(Demosaic::Bilinear as u8);
+
+/// The order of the colors in the first 2x2 block of a Bayer mosaic, which repeats over the whole frame.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BayerPattern {
+    /// `BG/GR` ([`BAYER_BGGR8`]).
+    Bggr,
+    /// `GB/RG` ([`BAYER_GBRG8`]).
+    Gbrg,
+    /// `GR/BG` ([`BAYER_GRBG8`]).
+    Grbg,
+    /// `RG/GB` ([`BAYER_RGGB8`]).
+    Rggb,
+}
+
+impl BayerPattern {
+    /// Gets the pattern of a Bayer [`FourCC`], or `None` if it is not one.
+    #[must_use]
+    pub fn from_fourcc(fourcc: FourCC) -> Option<Self> {
+        match fourcc {
+            BAYER_BGGR8 => Some(BayerPattern::Bggr),
+            BAYER_GBRG8 => Some(BayerPattern::Gbrg),
+            BAYER_GRBG8 => Some(BayerPattern::Grbg),
+            BAYER_RGGB8 => Some(BayerPattern::Rggb),
+            _ => None,
+        }
+    }
+
+    /// Gets the [`FourCC`] of 8-bit mosaics with this pattern.
+    #[must_use]
+    pub fn fourcc(self) -> FourCC {
+        match self {
+            BayerPattern::Bggr => BAYER_BGGR8,
+            BayerPattern::Gbrg => BAYER_GBRG8,
+            BayerPattern::Grbg => BAYER_GRBG8,
+            BayerPattern::Rggb => BAYER_RGGB8,
+        }
+    }
+
+    // the column and row of the red sample in each 2x2 block, blue is diagonally opposite
+    fn red_site(self) -> (usize, usize) {
+        match self {
+            BayerPattern::Rggb => (0, 0),
+            BayerPattern::Grbg => (1, 0),
+            BayerPattern::Gbrg => (0, 1),
+            BayerPattern::Bggr => (1, 1),
+        }
+    }
+}
+
+impl Display for BayerPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BayerPattern::Bggr => write!(f, "BGGR"),
+            BayerPattern::Gbrg => write!(f, "GBRG"),
+            BayerPattern::Grbg => write!(f, "GRBG"),
+            BayerPattern::Rggb => write!(f, "RGGB"),
+        }
+    }
+}
+
+/// How [`bayer_to_rgb()`] fills in the two colors each pixel of a mosaic is missing.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Demosaic {
+    /// Averages the nearest samples of each color. Fast, but edges get colored fringes.
+    #[default]
+    Bilinear,
+    /// Malvar-He-Cutler, which corrects the averages with the gradient of the pixel's own color. Sharper, with fewer
+    /// fringes, for about twice the work.
+    Malvar,
+}
+
+impl Display for Demosaic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Demosaic::Bilinear => write!(f, "Bilinear"),
+            Demosaic::Malvar => write!(f, "Malvar-He-Cutler"),
+        }
+    }
+}
+
+/// Sets how Bayer mosaics are demosaiced from now on, for all threads. The default is [`Demosaic::Bilinear`].
+pub fn set_demosaic(demosaic: Demosaic) {
+    DEMOSAIC.store(demosaic as u8, Ordering::Relaxed);
+}
+
+/// Gets how Bayer mosaics are demosaiced, see [`set_demosaic()`].
+#[must_use]
+pub fn demosaic() -> Demosaic {
+    match DEMOSAIC.load(Ordering::Relaxed) {
+        1 => Demosaic::Malvar,
+        _ => Demosaic::Bilinear,
+    }
+}
+
+/// Demosaics an 8-bit Bayer mosaic with the given `pattern` into packed RGB24, or RGBA with an opaque alpha if `rgba` is set.
+///
+/// Every pixel holds one 8-bit sample, of the color the `pattern` puts there. The other two colors are interpolated as set
+/// with [`set_demosaic()`]. Samples past the edges of the frame are mirrored back in.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn bayer_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    pattern: BayerPattern,
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_bayer_to_rgb(resolution, data, &mut dest, pattern, rgba)?;
+    Ok(dest)
+}
+
+/// Demosaics an 8-bit Bayer mosaic into `dest`, see [`bayer_to_rgb()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_bayer_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    pattern: BayerPattern,
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    bayer_into(
+        resolution,
+        data,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+        pattern,
+    )
+}
+
+pub(super) fn bayer_into(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+    pattern: BayerPattern,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let width = resolution.width() as usize;
+    let height = resolution.height() as usize;
+    let source_size = width * height;
+    if data.len() < source_size {
+        return Err(conversion_error(
+            pattern.fourcc(),
+            format,
+            format!("Expected at least {source_size} bytes, got {}", data.len()),
+        ));
+    }
+    let (dest, stride) = dest.rows(resolution, pattern.fourcc())?;
+    if width == 0 {
+        return Ok(());
+    }
+
+    let mosaic = Mosaic {
+        data: &data[..source_size],
+        width,
+        height,
+    };
+    let demosaic = demosaic();
+    let (red_x, red_y) = pattern.red_site();
+    let rgba = format != RgbFormat::Rgb24;
+    let pixel_size = format.pixel_size();
+    for_each_rgb_row(dest, width, stride, format, |y, dest_row| {
+        for (x, pixel) in dest_row.chunks_exact_mut(pixel_size).enumerate() {
+            let site = match (x % 2 == red_x, y % 2 == red_y) {
+                (true, true) => Site::Red,
+                (false, false) => Site::Blue,
+                (false, true) => Site::GreenRedRow,
+                (true, false) => Site::GreenBlueRow,
+            };
+            let around = |dx: isize, dy: isize| mosaic.get(x, y, dx, dy);
+            pixel[..3].copy_from_slice(&match demosaic {
+                Demosaic::Bilinear => bilinear(site, around),
+                Demosaic::Malvar => malvar(site, around),
+            });
+            if rgba {
+                pixel[3] = u8::MAX;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The color a pixel of the mosaic has a sample of. Green samples are told apart by whether red or blue is next to them
+/// in the same row.
+#[derive(Copy, Clone)]
+enum Site {
+    Red,
+    Blue,
+    GreenRedRow,
+    GreenBlueRow,
+}
+
+struct Mosaic<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Mosaic<'_> {
+    /// Gets the sample `dx` columns and `dy` rows away from `x`, `y`. Positions past an edge are mirrored, which keeps
+    /// the color of the sample the same.
+    #[inline]
+    fn get(&self, x: usize, y: usize, dx: isize, dy: isize) -> i32 {
+        let column = mirror(x, dx, self.width);
+        let row = mirror(y, dy, self.height);
+        i32::from(self.data[row * self.width + column])
+    }
+}
+
+#[inline]
+fn mirror(position: usize, offset: isize, len: usize) -> usize {
+    // mirroring repeats every 2 * (len - 1) samples, an even period, so even frames too narrow for a single reflection
+    // keep the color of the sample
+    let period = 2 * (len - 1);
+    if period == 0 {
+        return 0;
+    }
+    let moved = position
+        .checked_add_signed(offset)
+        .unwrap_or_else(|| offset.unsigned_abs() - position)
+        % period;
+    if moved >= len {
+        // mirroring one past the end lands one before it
+        period - moved
+    } else {
+        moved
+    }
+}
+
+fn bilinear(site: Site, at: impl Fn(isize, isize) -> i32) -> [u8; 3] {
+    let center = at(0, 0);
+    let cross = (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1) + 2) / 4;
+    let diagonal = (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1) + 2) / 4;
+    let horizontal = (at(-1, 0) + at(1, 0) + 1) / 2;
+    let vertical = (at(0, -1) + at(0, 1) + 1) / 2;
+    let [red, green, blue] = match site {
+        Site::Red => [center, cross, diagonal],
+        Site::Blue => [diagonal, cross, center],
+        Site::GreenRedRow => [horizontal, center, vertical],
+        Site::GreenBlueRow => [vertical, center, horizontal],
+    };
+    [red, green, blue].map(clamp_to_u8)
+}
+
+// See: Malvar, He and Cutler, "High-quality linear interpolation for demosaicing of Bayer-patterned color images", 2004.
+// The kernels are scaled by 16 rather than 8 so that their half weights stay integers.
+fn malvar(site: Site, at: impl Fn(isize, isize) -> i32) -> [u8; 3] {
+    let center = at(0, 0);
+    let cross = at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1);
+    let far_cross = at(-2, 0) + at(2, 0) + at(0, -2) + at(0, 2);
+    let diagonal = at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1);
+    let horizontal = at(-1, 0) + at(1, 0);
+    let vertical = at(0, -1) + at(0, 1);
+    let far_horizontal = at(-2, 0) + at(2, 0);
+    let far_vertical = at(0, -2) + at(0, 2);
+
+    // green at a red or blue sample
+    let green = || (8 * center + 4 * cross - 2 * far_cross + 8) / 16;
+    // red or blue at the opposite color's sample
+    let opposite = || (12 * center + 4 * diagonal - 3 * far_cross + 8) / 16;
+    // red or blue at a green sample, from the neighbours in the same row
+    let along_row = || {
+        (10 * center + 8 * horizontal - 2 * far_horizontal - 2 * diagonal + far_vertical + 8) / 16
+    };
+    // red or blue at a green sample, from the neighbours in the same column
+    let along_column =
+        || (10 * center + 8 * vertical - 2 * far_vertical - 2 * diagonal + far_horizontal + 8) / 16;
+
+    let [red, green, blue] = match site {
+        Site::Red => [center, green(), opposite()],
+        Site::Blue => [opposite(), green(), center],
+        Site::GreenRedRow => [along_row(), center, along_column()],
+        Site::GreenBlueRow => [along_column(), center, along_row()],
+    };
+    [red, green, blue].map(clamp_to_u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATTERNS: [BayerPattern; 4] = [
+        BayerPattern::Bggr,
+        BayerPattern::Gbrg,
+        BayerPattern::Grbg,
+        BayerPattern::Rggb,
+    ];
+
+    /// A mosaic of a single color, as a sensor with `pattern` would capture it.
+    fn flat_mosaic(pattern: BayerPattern, width: usize, height: usize, color: [u8; 3]) -> Vec<u8> {
+        let (red_x, red_y) = pattern.red_site();
+        (0..width * height)
+            .map(
+                |pixel| match ((pixel % width) % 2 == red_x, (pixel / width) % 2 == red_y) {
+                    (true, true) => color[0],
+                    (false, false) => color[2],
+                    _ => color[1],
+                },
+            )
+            .collect()
+    }
+
+    #[test]
+    fn patterns_round_trip_through_fourcc() {
+        for pattern in PATTERNS {
+            assert_eq!(BayerPattern::from_fourcc(pattern.fourcc()), Some(pattern));
+        }
+        assert_eq!(BayerPattern::from_fourcc(crate::pixel_format::NV12), None);
+    }
+
+    #[test]
+    fn flat_colors_survive_both_demosaics() {
+        // this is the only test that changes the demosaic, the others use frames both give the same result for
+        let color = [200, 100, 30];
+        for method in [Demosaic::Malvar, Demosaic::Bilinear] {
+            set_demosaic(method);
+            assert_eq!(demosaic(), method);
+            for pattern in PATTERNS {
+                for (width, height) in [(2_u32, 2_u32), (3, 2), (5, 3), (8, 7)] {
+                    let mosaic = flat_mosaic(pattern, width as usize, height as usize, color);
+                    let resolution = Resolution::new(width, height);
+                    let rgb = bayer_to_rgb(resolution, &mosaic, pattern, false).unwrap();
+                    assert!(
+                        rgb.chunks_exact(3).all(|pixel| pixel == color),
+                        "{method} {pattern} {width}x{height}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rgba_is_opaque() {
+        let mosaic = flat_mosaic(BayerPattern::Rggb, 4, 4, [10, 20, 30]);
+        let rgba = bayer_to_rgb(Resolution::new(4, 4), &mosaic, BayerPattern::Rggb, true).unwrap();
+        assert!(rgba
+            .chunks_exact(4)
+            .all(|pixel| pixel == [10, 20, 30, u8::MAX]));
+    }
+
+    #[test]
+    fn mirroring_stays_inside_the_frame() {
+        assert_eq!(mirror(0, -1, 4), 1);
+        assert_eq!(mirror(0, -2, 4), 2);
+        assert_eq!(mirror(3, 1, 4), 2);
+        assert_eq!(mirror(3, 2, 4), 1);
+        assert_eq!(mirror(0, -2, 1), 0);
+        assert_eq!(mirror(1, 2, 2), 1);
+        assert_eq!(mirror(0, 2, 2), 0);
+    }
+
+    #[test]
+    fn short_mosaics_are_rejected() {
+        assert!(bayer_to_rgb(Resolution::new(4, 4), &[0; 15], BayerPattern::Bggr, false).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions through [dcv-color-primitives](https://github.com/aws/dcv-color-primitives).
+//!
+//! It has no packed 4:2:2 support, and only converts `BGRA` to RGB, so those return `None` and are done natively.
+
+use super::{swap_red_blue, Plane};
+use crate::types::Resolution;
+use dcv_color_primitives::{convert_image, ColorSpace, ImageFormat, PixelFormat};
+
+/// Converts `sources` to RGB, or RGBA by converting to BGRA and swapping the channels in place.
+fn convert(
+    resolution: Resolution,
+    source: ImageFormat,
+    strides: Option<&[usize]>,
+    sources: &[&[u8]],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let destination = ImageFormat {
+        pixel_format: if rgba {
+            PixelFormat::Bgra
+        } else {
+            PixelFormat::Rgb
+        },
+        color_space: ColorSpace::Rgb,
+        num_planes: 1,
+    };
+    convert_image(
+        resolution.width(),
+        resolution.height(),
+        &source,
+        strides,
+        sources,
+        &destination,
+        None,
+        &mut [&mut *dest],
+    )
+    .map_err(|why| why.to_string())?;
+    if rgba {
+        swap_red_blue(dest);
+    }
+    Ok(())
+}
+
+pub(super) fn nv12(
+    resolution: Resolution,
+    luma: &[u8],
+    chroma: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Option<Result<(), String>> {
+    let source = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+    let width = resolution.width() as usize;
+    Some(convert(
+        resolution,
+        source,
+        Some(&[width, width.div_ceil(2) * 2]),
+        &[luma, chroma],
+        dest,
+        rgba,
+    ))
+}
+
+pub(super) fn yuv420p(
+    resolution: Resolution,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+    dest: &mut [u8],
+    rgba: bool,
+) -> Option<Result<(), String>> {
+    let source = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+    Some(convert(
+        resolution,
+        source,
+        Some(&[y.stride(), u.stride(), v.stride()]),
+        &[y.data(), u.data(), v.data()],
+        dest,
+        rgba,
+    ))
+}
+
+pub(super) fn bgra(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Option<Result<(), String>> {
+    if rgba {
+        return None;
+    }
+    let source = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Rgb,
+        num_planes: 1,
+    };
+    Some(convert(resolution, source, None, &[data], dest, rgba))
+}
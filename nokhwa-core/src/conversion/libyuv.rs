@@ -0,0 +1,265 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions through [libyuv](https://chromium.googlesource.com/libyuv/libyuv/), linked as the system library `yuv`.
+//!
+//! The callers have already checked that the buffers are large enough. libyuv's `ARGB` is `BGRA` in memory, `ABGR` is `RGBA`,
+//! and `RAW` is `RGB`.
+
+use super::Plane;
+use crate::types::Resolution;
+use std::os::raw::c_int;
+
+#[link(name = "yuv")]
+extern "C" {
+    fn YUY2ToARGB(
+        src_yuy2: *const u8,
+        src_stride_yuy2: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn ARGBToRAW(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn ARGBToABGR(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_abgr: *mut u8,
+        dst_stride_abgr: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn NV12ToRAW(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_uv: *const u8,
+        src_stride_uv: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn NV12ToABGR(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_uv: *const u8,
+        src_stride_uv: c_int,
+        dst_abgr: *mut u8,
+        dst_stride_abgr: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn I420ToRAW(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+    fn I420ToABGR(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_abgr: *mut u8,
+        dst_stride_abgr: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+}
+
+type ArgbConverter = unsafe extern "C" fn(*const u8, c_int, *mut u8, c_int, c_int, c_int) -> c_int;
+type Nv12Converter =
+    unsafe extern "C" fn(*const u8, c_int, *const u8, c_int, *mut u8, c_int, c_int, c_int) -> c_int;
+type I420Converter = unsafe extern "C" fn(
+    *const u8,
+    c_int,
+    *const u8,
+    c_int,
+    *const u8,
+    c_int,
+    *mut u8,
+    c_int,
+    c_int,
+    c_int,
+) -> c_int;
+
+fn to_int(value: usize) -> Result<c_int, String> {
+    c_int::try_from(value).map_err(|_| format!("{value} is too large for libyuv"))
+}
+
+fn check(function: &str, result: c_int) -> Result<(), String> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(format!("{function} failed with {result}"))
+    }
+}
+
+/// Gets the width, height and destination stride as libyuv takes them.
+fn dimensions(resolution: Resolution, rgba: bool) -> Result<[c_int; 3], String> {
+    let width = resolution.width() as usize;
+    Ok([
+        to_int(width)?,
+        to_int(resolution.height() as usize)?,
+        to_int(width * if rgba { 4 } else { 3 })?,
+    ])
+}
+
+/// Converts packed `BGRA` (libyuv's `ARGB`) with the given row stride to RGB or RGBA.
+fn from_argb(
+    resolution: Resolution,
+    data: &[u8],
+    stride: c_int,
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let [width, height, dest_stride] = dimensions(resolution, rgba)?;
+    let (function, name): (ArgbConverter, _) = if rgba {
+        (ARGBToABGR, "ARGBToABGR")
+    } else {
+        (ARGBToRAW, "ARGBToRAW")
+    };
+    // SAFETY: the caller checked that both buffers hold `height` rows of their stride
+    let result = unsafe {
+        function(
+            data.as_ptr(),
+            stride,
+            dest.as_mut_ptr(),
+            dest_stride,
+            width,
+            height,
+        )
+    };
+    check(name, result)
+}
+
+pub(super) fn yuyv(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let [width, height, _] = dimensions(resolution, rgba)?;
+    let stride = to_int((resolution.width() as usize).div_ceil(2) * 4)?;
+    // there is no direct YUY2 to RAW or ABGR, so it goes through ARGB
+    let argb_stride = to_int(resolution.width() as usize * 4)?;
+    let mut argb = vec![0; resolution.width() as usize * resolution.height() as usize * 4];
+    // SAFETY: the caller checked that `data` holds `height` rows of `stride`, `argb` was just sized for the frame
+    let result = unsafe {
+        YUY2ToARGB(
+            data.as_ptr(),
+            stride,
+            argb.as_mut_ptr(),
+            argb_stride,
+            width,
+            height,
+        )
+    };
+    check("YUY2ToARGB", result)?;
+    from_argb(resolution, &argb, argb_stride, dest, rgba)
+}
+
+pub(super) fn nv12(
+    resolution: Resolution,
+    luma: &[u8],
+    chroma: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let [width, height, dest_stride] = dimensions(resolution, rgba)?;
+    let chroma_stride = to_int((resolution.width() as usize).div_ceil(2) * 2)?;
+    let (function, name): (Nv12Converter, _) = if rgba {
+        (NV12ToABGR, "NV12ToABGR")
+    } else {
+        (NV12ToRAW, "NV12ToRAW")
+    };
+    // SAFETY: the caller checked the sizes of all planes and the destination
+    let result = unsafe {
+        function(
+            luma.as_ptr(),
+            width,
+            chroma.as_ptr(),
+            chroma_stride,
+            dest.as_mut_ptr(),
+            dest_stride,
+            width,
+            height,
+        )
+    };
+    check(name, result)
+}
+
+pub(super) fn yuv420p(
+    resolution: Resolution,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let [width, height, dest_stride] = dimensions(resolution, rgba)?;
+    let (function, name): (I420Converter, _) = if rgba {
+        (I420ToABGR, "I420ToABGR")
+    } else {
+        (I420ToRAW, "I420ToRAW")
+    };
+    let [y_stride, u_stride, v_stride] = [
+        to_int(y.stride())?,
+        to_int(u.stride())?,
+        to_int(v.stride())?,
+    ];
+    // SAFETY: the caller checked that every plane holds all of its rows
+    let result = unsafe {
+        function(
+            y.data().as_ptr(),
+            y_stride,
+            u.data().as_ptr(),
+            u_stride,
+            v.data().as_ptr(),
+            v_stride,
+            dest.as_mut_ptr(),
+            dest_stride,
+            width,
+            height,
+        )
+    };
+    check(name, result)
+}
+
+pub(super) fn bgra(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), String> {
+    let stride = to_int(resolution.width() as usize * 4)?;
+    from_argb(resolution, data, stride, dest, rgba)
+}
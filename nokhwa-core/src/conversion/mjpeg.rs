@@ -0,0 +1,321 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! MJPEG decoding, on a platform's hardware decoder or in software with mozjpeg.
+
+use super::{
+    bgra_into, conversion_error, convert_planar_420, nv12_into, rgb_size, split_planar_420,
+    yuyv422_into, ColorSpace, RgbDestination, RgbFormat,
+};
+use crate::{
+    error::NokhwaError,
+    pixel_format::{BGRA, I420, MJPEG, NV12, YUYV},
+    types::Resolution,
+};
+use four_cc::FourCC;
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+static DECODER: AtomicU8 = AtomicU8::new(Decoder::Auto as u8);
+static HARDWARE_DECODER: Mutex<HardwareDecoderSlot> = Mutex::new(HardwareDecoderSlot {
+    factory: None,
+    decoder: None,
+    failed: false,
+});
+
+/// Where [`mjpeg_to_rgb()`] decodes frames.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Decoder {
+    /// The hardware decoder if there is one and it works, otherwise software.
+    #[default]
+    Auto,
+    /// Only the hardware decoder, see [`register_hardware_decoder()`]. Decoding errors if there is none.
+    Hardware,
+    /// Only mozjpeg. Needs the `conversion-mozjpeg` feature.
+    Software,
+}
+
+impl Display for Decoder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decoder::Auto => write!(f, "Auto"),
+            Decoder::Hardware => write!(f, "Hardware"),
+            Decoder::Software => write!(f, "Software"),
+        }
+    }
+}
+
+/// Sets where MJPEG frames are decoded from now on, for all threads. The default is [`Decoder::Auto`].
+/// # Errors
+/// If `decoder` is [`Decoder::Software`] and the `conversion-mozjpeg` feature is not enabled, this will error and the decoder stays the same.
+pub fn set_mjpeg_decoder(decoder: Decoder) -> Result<(), NokhwaError> {
+    if decoder == Decoder::Software && !cfg!(feature = "conversion-mozjpeg") {
+        return Err(NokhwaError::NotImplementedError(
+            "Software MJPEG decoding needs the `conversion-mozjpeg` feature".to_string(),
+        ));
+    }
+    DECODER.store(decoder as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Gets where MJPEG frames are decoded, see [`set_mjpeg_decoder()`].
+#[must_use]
+pub fn mjpeg_decoder() -> Decoder {
+    match DECODER.load(Ordering::Relaxed) {
+        1 => Decoder::Hardware,
+        2 => Decoder::Software,
+        _ => Decoder::Auto,
+    }
+}
+
+/// A platform's MJPEG decoder, such as `VideoToolbox`, a Media Foundation transform or a V4L2 memory-to-memory device.
+///
+/// The decoder is created on first use and then kept, so it can hold on to its session between frames.
+pub trait HardwareDecoder: Send {
+    /// Gets a name for the decoder, e.g. for logging.
+    fn name(&self) -> String;
+
+    /// Decodes one MJPEG frame of `resolution`, returning the decoded frame and its format.
+    ///
+    /// The format has to be one the converters in this module take: [`NV12`], [`I420`], [`YUYV`] or [`BGRA`].
+    /// # Errors
+    /// If the frame could not be decoded, this will error.
+    fn decode(
+        &mut self,
+        resolution: Resolution,
+        data: &[u8],
+    ) -> Result<(FourCC, Vec<u8>), NokhwaError>;
+}
+
+/// Creates a [`HardwareDecoder`], or errors if the system does not have one.
+pub type HardwareDecoderFactory = fn() -> Result<Box<dyn HardwareDecoder>, NokhwaError>;
+
+struct HardwareDecoderSlot {
+    factory: Option<HardwareDecoderFactory>,
+    decoder: Option<Box<dyn HardwareDecoder>>,
+    // creating the decoder is only tried once, so a system without one does not pay for it on every frame
+    failed: bool,
+}
+
+/// Registers the hardware decoder [`mjpeg_to_rgb()`] uses. `nokhwa` registers the one of the platform when a camera is created,
+/// so this is only needed for custom decoders, or when using the converters on their own.
+///
+/// Registering a decoder replaces the previous one.
+pub fn register_hardware_decoder(factory: HardwareDecoderFactory) {
+    let mut slot = HARDWARE_DECODER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    *slot = HardwareDecoderSlot {
+        factory: Some(factory),
+        decoder: None,
+        failed: false,
+    };
+}
+
+/// Registers `factory` like [`register_hardware_decoder()`], unless a decoder has already been registered.
+/// This is how `nokhwa` registers the decoder of the platform, so it does not replace a custom one.
+pub fn register_default_hardware_decoder(factory: HardwareDecoderFactory) {
+    let mut slot = HARDWARE_DECODER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if slot.factory.is_none() {
+        slot.factory = Some(factory);
+    }
+}
+
+/// Gets the name of the hardware decoder, creating it if that has not happened yet. Returns `None` if there is none.
+#[must_use]
+pub fn hardware_decoder_name() -> Option<String> {
+    let mut slot = HARDWARE_DECODER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    hardware_decoder(&mut slot)
+        .ok()
+        .map(|decoder| decoder.name())
+}
+
+fn hardware_decoder(slot: &mut HardwareDecoderSlot) -> Result<&mut dyn HardwareDecoder, String> {
+    if slot.decoder.is_none() {
+        let Some(factory) = slot.factory.filter(|_| !slot.failed) else {
+            return Err("No hardware decoder is available".to_string());
+        };
+        match factory() {
+            Ok(decoder) => slot.decoder = Some(decoder),
+            Err(why) => {
+                slot.failed = true;
+                return Err(format!("Failed to create the hardware decoder: {why}"));
+            }
+        }
+    }
+    match slot.decoder.as_deref_mut() {
+        Some(decoder) => Ok(decoder),
+        None => Err("No hardware decoder is available".to_string()),
+    }
+}
+
+/// Decodes an MJPEG frame into packed RGB24, or RGBA with an opaque alpha if `rgba` is set.
+///
+/// Where it is decoded depends on [`mjpeg_decoder()`].
+/// # Errors
+/// If the frame could not be decoded, or is not of `resolution`, this will error.
+pub fn mjpeg_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    rgba: bool,
+) -> Result<Vec<u8>, NokhwaError> {
+    let mut dest = vec![0; rgb_size(resolution, rgba)];
+    buf_mjpeg_to_rgb(resolution, data, &mut dest, rgba)?;
+    Ok(dest)
+}
+
+/// Decodes an MJPEG frame into `dest`, see [`mjpeg_to_rgb()`].
+/// # Errors
+/// If the frame could not be decoded, is not of `resolution`, or `dest` is too small for the decoded frame, this will error.
+pub fn buf_mjpeg_to_rgb(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u8],
+    rgba: bool,
+) -> Result<(), NokhwaError> {
+    mjpeg_into(
+        resolution,
+        data,
+        RgbDestination::new(dest, RgbFormat::from_rgba(rgba)),
+    )
+}
+
+pub(super) fn mjpeg_into(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    // checked up front, so a destination that is too small does not count as a failure of the hardware decoder
+    dest.rows(resolution, MJPEG)?;
+
+    let decoder = mjpeg_decoder();
+    if decoder != Decoder::Software {
+        match decode_hardware(resolution, data, dest.reborrow()) {
+            Ok(()) => return Ok(()),
+            Err(why) if decoder == Decoder::Hardware => return Err(why),
+            Err(_) => {}
+        }
+    }
+    decode_software(resolution, data, dest)
+}
+
+fn decode_hardware(
+    resolution: Resolution,
+    data: &[u8],
+    dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    let (format, decoded) = {
+        let mut slot = HARDWARE_DECODER
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        hardware_decoder(&mut slot)
+            .and_then(|decoder| {
+                decoder
+                    .decode(resolution, data)
+                    .map_err(|why| why.to_string())
+            })
+            .map_err(|why| conversion_error(MJPEG, dest.format, why))?
+    };
+    match format {
+        NV12 => nv12_into(resolution, &decoded, dest, ColorSpace::BT601_LIMITED),
+        I420 => {
+            let [luma, u, v] = split_planar_420(resolution, &decoded, I420, dest.format)?;
+            convert_planar_420(
+                resolution,
+                luma,
+                u,
+                v,
+                dest,
+                I420,
+                ColorSpace::BT601_LIMITED,
+            )
+        }
+        YUYV => yuyv422_into(resolution, &decoded, dest, ColorSpace::BT601_LIMITED),
+        BGRA => bgra_into(resolution, &decoded, dest),
+        other => Err(conversion_error(
+            MJPEG,
+            dest.format,
+            format!("The hardware decoder returned {other}, which cannot be converted"),
+        )),
+    }
+}
+
+#[cfg(feature = "conversion-mozjpeg")]
+fn decode_software(
+    resolution: Resolution,
+    data: &[u8],
+    mut dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    let format = dest.format;
+    let error = |why: std::io::Error| conversion_error(MJPEG, format, why.to_string());
+
+    let decompress = mozjpeg::Decompress::new_mem(data).map_err(error)?;
+    let mut image = match format {
+        RgbFormat::Rgb24 => decompress.rgb(),
+        RgbFormat::Rgba => decompress.rgba(),
+        RgbFormat::Bgra => decompress.to_colorspace(mozjpeg::ColorSpace::JCS_EXT_BGRA),
+    }
+    .map_err(error)?;
+    let decoded = (image.width(), image.height());
+    let expected = (resolution.width() as usize, resolution.height() as usize);
+    if decoded != expected {
+        return Err(conversion_error(
+            MJPEG,
+            format,
+            format!(
+                "Expected a {resolution} frame, got {}x{}",
+                decoded.0, decoded.1
+            ),
+        ));
+    }
+    let (rows, stride) = dest.rows(resolution, MJPEG)?;
+    let row_len = decoded.0 * format.pixel_size();
+    if stride == row_len {
+        image.read_scanlines_into::<u8>(rows).map_err(error)?;
+    } else {
+        for row in rows.chunks_mut(stride) {
+            image
+                .read_scanlines_into::<u8>(&mut row[..row_len])
+                .map_err(error)?;
+        }
+    }
+    image.finish().map_err(error)
+}
+
+#[cfg(not(feature = "conversion-mozjpeg"))]
+#[allow(clippy::unnecessary_wraps, clippy::needless_pass_by_value)]
+fn decode_software(
+    _resolution: Resolution,
+    _data: &[u8],
+    dest: RgbDestination,
+) -> Result<(), NokhwaError> {
+    Err(conversion_error(
+        MJPEG,
+        dest.format,
+        "No hardware decoder is available, and software decoding needs the `conversion-mozjpeg` feature"
+            .to_string(),
+    ))
+}
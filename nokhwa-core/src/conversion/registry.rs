@@ -0,0 +1,312 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Converters between frame formats, looked up by the [`FourCC`] they convert from and into.
+
+use super::{convert_into, swap_red_blue, ColorSpace, RgbDestination, RgbFormat};
+use crate::{
+    error::NokhwaError,
+    pixel_format::{KnownFrameFormat, BGRA, GRAY, RAWRGB, RGBA},
+    types::Resolution,
+};
+use four_cc::FourCC;
+use std::sync::Mutex;
+
+static CONVERTERS: Mutex<Vec<Registration>> = Mutex::new(Vec::new());
+
+/// Converts a frame of `resolution` in the `src` format into a new buffer, in the format it was registered for.
+///
+/// `src` is passed along so that one function can be registered for several formats.
+pub type ConverterFn = fn(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError>;
+
+struct Registration {
+    src: FourCC,
+    dst: FourCC,
+    converter: ConverterFn,
+}
+
+/// Registers `converter` for converting `src` frames into `dst`, replacing the one registered for them before.
+///
+/// Registered converters take precedence over the built-in ones, which convert everything [`convert_into()`] can into
+/// [`RAWRGB`], [`RGBA`] and [`BGRA`], [`GRAY`], [`GRAY16`](crate::pixel_format::GRAY16),
+/// [`DEPTH16`](crate::pixel_format::DEPTH16) and [`RAWRGB`] into those too, and YUV, 16-bit greyscale and depth, [`RAWRGB`] and
+/// [`BGRA`] into [`GRAY`]. 16-bit samples are cut down to their most significant byte.
+/// Aliases of a format, such as [`YU12`](crate::pixel_format::YU12) for [`I420`](crate::pixel_format::I420), share their converters.
+pub fn register_converter(src: FourCC, dst: FourCC, converter: ConverterFn) {
+    let (src, dst) = (canonical(src), canonical(dst));
+    let mut converters = CONVERTERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    converters.retain(|registration| (registration.src, registration.dst) != (src, dst));
+    converters.push(Registration {
+        src,
+        dst,
+        converter,
+    });
+}
+
+/// Removes the converter registered for converting `src` frames into `dst`, returning `true` if there was one. The
+/// built-in converter is used again, if there is one.
+pub fn unregister_converter(src: FourCC, dst: FourCC) -> bool {
+    let (src, dst) = (canonical(src), canonical(dst));
+    let mut converters = CONVERTERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let registered = converters.len();
+    converters.retain(|registration| (registration.src, registration.dst) != (src, dst));
+    converters.len() != registered
+}
+
+/// Gets the converter from `src` into `dst`, either a registered one or a built-in one. Returns `None` if there is neither.
+#[must_use]
+pub fn converter(src: FourCC, dst: FourCC) -> Option<ConverterFn> {
+    let (src, dst) = (canonical(src), canonical(dst));
+    CONVERTERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .iter()
+        .find(|registration| (registration.src, registration.dst) == (src, dst))
+        .map(|registration| registration.converter)
+        .or_else(|| built_in(src, dst))
+}
+
+/// Gets every format `src` frames can be converted into, see [`converter()`].
+#[must_use]
+pub fn conversion_targets(src: FourCC) -> Vec<FourCC> {
+    let src = canonical(src);
+    let mut targets: Vec<FourCC> = [RAWRGB, RGBA, BGRA, GRAY]
+        .into_iter()
+        .filter(|dst| built_in(src, *dst).is_some())
+        .collect();
+    for registration in CONVERTERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .iter()
+        .filter(|registration| registration.src == src)
+    {
+        if !targets.contains(&registration.dst) {
+            targets.push(registration.dst);
+        }
+    }
+    targets
+}
+
+/// Converts a frame of `resolution` from `src` into `dst`, with the converter [`converter()`] finds. Frames that are
+/// already in `dst` are copied.
+/// # Errors
+/// If there is no converter from `src` into `dst`, or it fails, this will error.
+pub fn convert(
+    resolution: Resolution,
+    src: FourCC,
+    dst: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    let Some(converter) = converter(src, dst) else {
+        return Err(NokhwaError::ProcessFrameError {
+            src,
+            destination: dst.to_string(),
+            error: "No converter is registered".to_string(),
+        });
+    };
+    converter(resolution, src, color_space, data)
+}
+
+/// Folds aliases into the [`FourCC`] [`KnownFrameFormat`] converts back into.
+fn canonical(fourcc: FourCC) -> FourCC {
+    FourCC::from(KnownFrameFormat::from(fourcc))
+}
+
+fn built_in(src: FourCC, dst: FourCC) -> Option<ConverterFn> {
+    if src == dst {
+        return Some(copy);
+    }
+    let format = KnownFrameFormat::from(src);
+    let to_rgb = matches!(
+        format,
+        KnownFrameFormat::Yuv420
+            | KnownFrameFormat::Nv12
+            | KnownFrameFormat::I420
+            | KnownFrameFormat::Yv12
+            | KnownFrameFormat::Yuyv
+            | KnownFrameFormat::Bgra
+            | KnownFrameFormat::Mjpeg
+            | KnownFrameFormat::Gray8
+            | KnownFrameFormat::Gray16
+            | KnownFrameFormat::Depth16
+            | KnownFrameFormat::Rgb24
+            | KnownFrameFormat::BayerBggr8
+            | KnownFrameFormat::BayerGbrg8
+            | KnownFrameFormat::BayerGrbg8
+            | KnownFrameFormat::BayerRggb8
+    );
+    let to_gray = matches!(
+        format,
+        KnownFrameFormat::Yuv420
+            | KnownFrameFormat::Nv12
+            | KnownFrameFormat::I420
+            | KnownFrameFormat::Yv12
+            | KnownFrameFormat::Yuyv
+            | KnownFrameFormat::Uyvy
+            | KnownFrameFormat::Gray16
+            | KnownFrameFormat::Depth16
+            | KnownFrameFormat::Rgb24
+            | KnownFrameFormat::Bgra
+    );
+    match dst {
+        RAWRGB if to_rgb => Some(to_rgb24),
+        RGBA if to_rgb => Some(to_rgba),
+        BGRA if to_rgb => Some(to_bgra),
+        GRAY if to_gray => Some(to_gray8),
+        _ => None,
+    }
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn copy(
+    _resolution: Resolution,
+    _src: FourCC,
+    _color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    Ok(data.to_vec())
+}
+
+fn to_rgb24(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    to_rgb(resolution, src, color_space, data, RgbFormat::Rgb24)
+}
+
+fn to_rgba(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    to_rgb(resolution, src, color_space, data, RgbFormat::Rgba)
+}
+
+fn to_bgra(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    to_rgb(resolution, src, color_space, data, RgbFormat::Bgra)
+}
+
+fn to_rgb(
+    resolution: Resolution,
+    src: FourCC,
+    color_space: ColorSpace,
+    data: &[u8],
+    format: RgbFormat,
+) -> Result<Vec<u8>, NokhwaError> {
+    let pixel_count = resolution.width() as usize * resolution.height() as usize;
+    let mut dest = vec![0; pixel_count * format.pixel_size()];
+    // `convert_into()` takes none of these, as cameras deliver them ready to use
+    let channels = match KnownFrameFormat::from(src) {
+        KnownFrameFormat::Gray8 => 1,
+        KnownFrameFormat::Gray16 | KnownFrameFormat::Depth16 => 2,
+        KnownFrameFormat::Rgb24 => 3,
+        _ => {
+            convert_into(
+                resolution,
+                src,
+                color_space,
+                data,
+                RgbDestination::new(&mut dest, format),
+            )?;
+            return Ok(dest);
+        }
+    };
+    let data =
+        data.get(..pixel_count * channels)
+            .ok_or_else(|| NokhwaError::ProcessFrameError {
+                src,
+                destination: format.to_string(),
+                error: "Buffer is smaller than a frame".to_string(),
+            })?;
+    for (pixel, dest) in data
+        .chunks_exact(channels)
+        .zip(dest.chunks_exact_mut(format.pixel_size()))
+    {
+        // 16-bit samples keep their most significant byte
+        if let [luma] | [_, luma] = pixel {
+            dest[..3].fill(*luma);
+        } else {
+            dest[..3].copy_from_slice(pixel);
+        }
+        if let Some(alpha) = dest.get_mut(3) {
+            *alpha = 255;
+        }
+    }
+    if format == RgbFormat::Bgra {
+        swap_red_blue(&mut dest);
+    }
+    Ok(dest)
+}
+
+fn to_gray8(
+    resolution: Resolution,
+    src: FourCC,
+    _color_space: ColorSpace,
+    data: &[u8],
+) -> Result<Vec<u8>, NokhwaError> {
+    let pixel_count = resolution.width() as usize * resolution.height() as usize;
+    let format = KnownFrameFormat::from(src);
+    // (bytes per pixel, offset of the luma or first color sample)
+    let (pixel_size, offset) = match format {
+        KnownFrameFormat::Yuyv => (2, 0),
+        // for 16-bit formats, the most significant byte of a little endian u16
+        KnownFrameFormat::Uyvy | KnownFrameFormat::Gray16 | KnownFrameFormat::Depth16 => (2, 1),
+        KnownFrameFormat::Rgb24 => (3, 0),
+        KnownFrameFormat::Bgra => (4, 0),
+        _ => (1, 0),
+    };
+    let Some(data) = data.get(..pixel_count * pixel_size) else {
+        return Err(NokhwaError::ProcessFrameError {
+            src,
+            destination: GRAY.to_string(),
+            error: "Buffer is smaller than a frame".to_string(),
+        });
+    };
+    let pixels = data.chunks_exact(pixel_size);
+    Ok(match format {
+        // BT.601 luma, in 8-bit fixed point
+        KnownFrameFormat::Rgb24 => pixels
+            .map(|pixel| luma(pixel[0], pixel[1], pixel[2]))
+            .collect(),
+        KnownFrameFormat::Bgra => pixels
+            .map(|pixel| luma(pixel[2], pixel[1], pixel[0]))
+            .collect(),
+        _ => pixels.map(|pixel| pixel[offset]).collect(),
+    })
+}
+
+fn luma(red: u8, green: u8, blue: u8) -> u8 {
+    let luma = (77 * u32::from(red) + 150 * u32::from(green) + 29 * u32::from(blue) + 128) >> 8;
+    u8::try_from(luma).unwrap_or(u8::MAX)
+}
@@ -0,0 +1,407 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! SIMD versions of the row converters, picked at runtime from what the CPU supports.
+//!
+//! Each function converts as many whole blocks of pixels from the start of the row as it can, and returns how many pixels it
+//! wrote. The caller converts the rest with the scalar path. The arithmetic is the same as [`yuv_to_rgb()`](super::yuv_to_rgb)
+//! for BT.601 limited range, so the output does not depend on which path ran. Other color spaces do not use them.
+
+/// Converts the start of a `YUYV` row, returning the number of pixels written.
+#[allow(unreachable_code)]
+pub(super) fn yuyv_row(src: &[u8], dest: &mut [u8], rgba: bool) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is available
+            return unsafe { x86::yuyv_row_avx2(src, dest, rgba) };
+        }
+        if is_x86_feature_detected!("sse2") {
+            // SAFETY: SSE2 is available
+            return unsafe { x86::yuyv_row_sse2(src, dest, rgba) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of the aarch64 baseline
+        return unsafe { neon::yuyv_row(src, dest, rgba) };
+    }
+    0
+}
+
+/// Converts the start of an `NV12` row, returning the number of pixels written.
+#[allow(unreachable_code)]
+pub(super) fn nv12_row(luma: &[u8], chroma: &[u8], dest: &mut [u8], rgba: bool) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is available
+            return unsafe { x86::nv12_row_avx2(luma, chroma, dest, rgba) };
+        }
+        if is_x86_feature_detected!("sse2") {
+            // SAFETY: SSE2 is available
+            return unsafe { x86::nv12_row_sse2(luma, chroma, dest, rgba) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of the aarch64 baseline
+        return unsafe { neon::nv12_row(luma, chroma, dest, rgba) };
+    }
+    0
+}
+
+/// How many whole blocks of `block` pixels fit into both the source and the destination.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+fn block_count(source_pixels: usize, dest: &[u8], rgba: bool, block: usize) -> usize {
+    let pixel_size = if rgba { 4 } else { 3 };
+    source_pixels.min(dest.len() / pixel_size) / block
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    use super::block_count;
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{
+        __m128i, __m256i, _mm256_adds_epi16, _mm256_and_si256, _mm256_cvtepu8_epi16,
+        _mm256_loadu_si256, _mm256_mullo_epi16, _mm256_or_si256, _mm256_packus_epi16,
+        _mm256_set1_epi16, _mm256_set1_epi32, _mm256_slli_epi32, _mm256_srai_epi16,
+        _mm256_srli_epi16, _mm256_srli_epi32, _mm256_storeu_si256, _mm256_sub_epi16,
+        _mm256_subs_epi16, _mm_adds_epi16, _mm_and_si128, _mm_loadl_epi64, _mm_loadu_si128,
+        _mm_mullo_epi16, _mm_or_si128, _mm_packus_epi16, _mm_set1_epi16, _mm_set1_epi32,
+        _mm_setzero_si128, _mm_slli_epi32, _mm_srai_epi16, _mm_srli_epi16, _mm_srli_epi32,
+        _mm_storel_epi64, _mm_sub_epi16, _mm_subs_epi16, _mm_unpacklo_epi8,
+    };
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{
+        __m128i, __m256i, _mm256_adds_epi16, _mm256_and_si256, _mm256_cvtepu8_epi16,
+        _mm256_loadu_si256, _mm256_mullo_epi16, _mm256_or_si256, _mm256_packus_epi16,
+        _mm256_set1_epi16, _mm256_set1_epi32, _mm256_slli_epi32, _mm256_srai_epi16,
+        _mm256_srli_epi16, _mm256_srli_epi32, _mm256_storeu_si256, _mm256_sub_epi16,
+        _mm256_subs_epi16, _mm_adds_epi16, _mm_and_si128, _mm_loadl_epi64, _mm_loadu_si128,
+        _mm_mullo_epi16, _mm_or_si128, _mm_packus_epi16, _mm_set1_epi16, _mm_set1_epi32,
+        _mm_setzero_si128, _mm_slli_epi32, _mm_srai_epi16, _mm_srli_epi16, _mm_srli_epi32,
+        _mm_storel_epi64, _mm_sub_epi16, _mm_subs_epi16, _mm_unpacklo_epi8,
+    };
+
+    /// Interleaves separate R, G and B channels into packed pixels.
+    #[inline]
+    fn interleave(red: &[u8], green: &[u8], blue: &[u8], dest: &mut [u8], rgba: bool) {
+        let pixel_size = if rgba { 4 } else { 3 };
+        for (((pixel, &red), &green), &blue) in dest
+            .chunks_exact_mut(pixel_size)
+            .zip(red)
+            .zip(green)
+            .zip(blue)
+        {
+            pixel[..3].copy_from_slice(&[red, green, blue]);
+            if rgba {
+                pixel[3] = u8::MAX;
+            }
+        }
+    }
+
+    /// Converts 8 pixels of 16 bit YUV, returning R, G and B in the low 8 bytes of each register.
+    ///
+    /// Saturating adds are fine here, since anything that saturates is clamped to 0 or 255 afterwards anyway.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn yuv_to_rgb_sse2(y: __m128i, u: __m128i, v: __m128i) -> [__m128i; 3] {
+        let luma = _mm_sub_epi16(y, _mm_set1_epi16(16));
+        let luma = _mm_adds_epi16(
+            _mm_adds_epi16(
+                _mm_mullo_epi16(luma, _mm_set1_epi16(74)),
+                _mm_srai_epi16(luma, 1),
+            ),
+            _mm_set1_epi16(32),
+        );
+        let cb = _mm_sub_epi16(u, _mm_set1_epi16(128));
+        let cr = _mm_sub_epi16(v, _mm_set1_epi16(128));
+        let red = _mm_adds_epi16(luma, _mm_mullo_epi16(cr, _mm_set1_epi16(102)));
+        let green = _mm_subs_epi16(
+            _mm_subs_epi16(luma, _mm_mullo_epi16(cb, _mm_set1_epi16(25))),
+            _mm_mullo_epi16(cr, _mm_set1_epi16(52)),
+        );
+        let blue = _mm_adds_epi16(luma, _mm_mullo_epi16(cb, _mm_set1_epi16(129)));
+        let red = _mm_srai_epi16(red, 6);
+        let green = _mm_srai_epi16(green, 6);
+        let blue = _mm_srai_epi16(blue, 6);
+        [
+            _mm_packus_epi16(red, red),
+            _mm_packus_epi16(green, green),
+            _mm_packus_epi16(blue, blue),
+        ]
+    }
+
+    /// Splits 16 bit `U0 V0 U1 V1 ...` lanes into U and V, each repeated for both pixels of the pair.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn split_chroma_sse2(uv: __m128i) -> (__m128i, __m128i) {
+        let u = _mm_and_si128(uv, _mm_set1_epi32(0xFFFF));
+        let v = _mm_srli_epi32(uv, 16);
+        (
+            _mm_or_si128(u, _mm_slli_epi32(u, 16)),
+            _mm_or_si128(v, _mm_slli_epi32(v, 16)),
+        )
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn store_sse2(rgb: [__m128i; 3], dest: &mut [u8], rgba: bool) {
+        let mut channels = [[0_u8; 8]; 3];
+        for (channel, register) in channels.iter_mut().zip(rgb) {
+            _mm_storel_epi64(channel.as_mut_ptr().cast(), register);
+        }
+        interleave(&channels[0], &channels[1], &channels[2], dest, rgba);
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn yuyv_row_sse2(src: &[u8], dest: &mut [u8], rgba: bool) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(src.len() / 2, dest, rgba, 8);
+        for (block, dest) in src
+            .chunks_exact(16)
+            .zip(dest.chunks_exact_mut(8 * pixel_size))
+            .take(blocks)
+        {
+            let block = _mm_loadu_si128(block.as_ptr().cast());
+            let y = _mm_and_si128(block, _mm_set1_epi16(0xFF));
+            let (u, v) = split_chroma_sse2(_mm_srli_epi16(block, 8));
+            store_sse2(yuv_to_rgb_sse2(y, u, v), dest, rgba);
+        }
+        blocks * 8
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn nv12_row_sse2(
+        luma: &[u8],
+        chroma: &[u8],
+        dest: &mut [u8],
+        rgba: bool,
+    ) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(luma.len().min(chroma.len()), dest, rgba, 8);
+        let zero = _mm_setzero_si128();
+        for ((luma, chroma), dest) in luma
+            .chunks_exact(8)
+            .zip(chroma.chunks_exact(8))
+            .zip(dest.chunks_exact_mut(8 * pixel_size))
+            .take(blocks)
+        {
+            let y = _mm_unpacklo_epi8(_mm_loadl_epi64(luma.as_ptr().cast()), zero);
+            let uv = _mm_unpacklo_epi8(_mm_loadl_epi64(chroma.as_ptr().cast()), zero);
+            let (u, v) = split_chroma_sse2(uv);
+            store_sse2(yuv_to_rgb_sse2(y, u, v), dest, rgba);
+        }
+        blocks * 8
+    }
+
+    /// The AVX2 version of [`yuv_to_rgb_sse2()`], for 16 pixels. Each 128 bit lane holds 8 of them in its low 8 bytes.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn yuv_to_rgb_avx2(y: __m256i, u: __m256i, v: __m256i) -> [__m256i; 3] {
+        let luma = _mm256_sub_epi16(y, _mm256_set1_epi16(16));
+        let luma = _mm256_adds_epi16(
+            _mm256_adds_epi16(
+                _mm256_mullo_epi16(luma, _mm256_set1_epi16(74)),
+                _mm256_srai_epi16(luma, 1),
+            ),
+            _mm256_set1_epi16(32),
+        );
+        let cb = _mm256_sub_epi16(u, _mm256_set1_epi16(128));
+        let cr = _mm256_sub_epi16(v, _mm256_set1_epi16(128));
+        let red = _mm256_adds_epi16(luma, _mm256_mullo_epi16(cr, _mm256_set1_epi16(102)));
+        let green = _mm256_subs_epi16(
+            _mm256_subs_epi16(luma, _mm256_mullo_epi16(cb, _mm256_set1_epi16(25))),
+            _mm256_mullo_epi16(cr, _mm256_set1_epi16(52)),
+        );
+        let blue = _mm256_adds_epi16(luma, _mm256_mullo_epi16(cb, _mm256_set1_epi16(129)));
+        let red = _mm256_srai_epi16(red, 6);
+        let green = _mm256_srai_epi16(green, 6);
+        let blue = _mm256_srai_epi16(blue, 6);
+        [
+            _mm256_packus_epi16(red, red),
+            _mm256_packus_epi16(green, green),
+            _mm256_packus_epi16(blue, blue),
+        ]
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn split_chroma_avx2(uv: __m256i) -> (__m256i, __m256i) {
+        let u = _mm256_and_si256(uv, _mm256_set1_epi32(0xFFFF));
+        let v = _mm256_srli_epi32(uv, 16);
+        (
+            _mm256_or_si256(u, _mm256_slli_epi32(u, 16)),
+            _mm256_or_si256(v, _mm256_slli_epi32(v, 16)),
+        )
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn store_avx2(rgb: [__m256i; 3], dest: &mut [u8], rgba: bool) {
+        let mut channels = [[0_u8; 32]; 3];
+        for (channel, register) in channels.iter_mut().zip(rgb) {
+            _mm256_storeu_si256(channel.as_mut_ptr().cast(), register);
+        }
+        let [red, green, blue] = channels;
+        let (first, second) = dest.split_at_mut(8 * if rgba { 4 } else { 3 });
+        interleave(&red[..8], &green[..8], &blue[..8], first, rgba);
+        interleave(&red[16..24], &green[16..24], &blue[16..24], second, rgba);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn yuyv_row_avx2(src: &[u8], dest: &mut [u8], rgba: bool) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(src.len() / 2, dest, rgba, 16);
+        for (block, dest) in src
+            .chunks_exact(32)
+            .zip(dest.chunks_exact_mut(16 * pixel_size))
+            .take(blocks)
+        {
+            let block = _mm256_loadu_si256(block.as_ptr().cast());
+            let y = _mm256_and_si256(block, _mm256_set1_epi16(0xFF));
+            let (u, v) = split_chroma_avx2(_mm256_srli_epi16(block, 8));
+            store_avx2(yuv_to_rgb_avx2(y, u, v), dest, rgba);
+        }
+        blocks * 16
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn nv12_row_avx2(
+        luma: &[u8],
+        chroma: &[u8],
+        dest: &mut [u8],
+        rgba: bool,
+    ) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(luma.len().min(chroma.len()), dest, rgba, 16);
+        for ((luma, chroma), dest) in luma
+            .chunks_exact(16)
+            .zip(chroma.chunks_exact(16))
+            .zip(dest.chunks_exact_mut(16 * pixel_size))
+            .take(blocks)
+        {
+            let y = _mm256_cvtepu8_epi16(_mm_loadu_si128(luma.as_ptr().cast()));
+            let uv = _mm256_cvtepu8_epi16(_mm_loadu_si128(chroma.as_ptr().cast()));
+            let (u, v) = split_chroma_avx2(uv);
+            store_avx2(yuv_to_rgb_avx2(y, u, v), dest, rgba);
+        }
+        blocks * 16
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::block_count;
+    use std::arch::aarch64::{
+        int16x8_t, uint8x16_t, uint8x16x3_t, uint8x16x4_t, uint8x8_t, vcombine_u8, vdupq_n_s16,
+        vdupq_n_u8, vld2_u8, vld4_u8, vmovl_u8, vmulq_n_s16, vqaddq_s16, vqshrun_n_s16, vqsubq_s16,
+        vreinterpretq_s16_u16, vshrq_n_s16, vst3q_u8, vst4q_u8, vsubq_s16, vzip_u8,
+    };
+
+    #[inline]
+    unsafe fn widen(channel: uint8x8_t) -> int16x8_t {
+        vreinterpretq_s16_u16(vmovl_u8(channel))
+    }
+
+    /// Converts 8 pixels. Saturating adds are fine here, since anything that saturates is clamped to 0 or 255 afterwards anyway.
+    #[inline]
+    unsafe fn yuv_to_rgb_neon(y: uint8x8_t, u: uint8x8_t, v: uint8x8_t) -> [uint8x8_t; 3] {
+        let luma = vsubq_s16(widen(y), vdupq_n_s16(16));
+        let luma = vqaddq_s16(
+            vqaddq_s16(vmulq_n_s16(luma, 74), vshrq_n_s16::<1>(luma)),
+            vdupq_n_s16(32),
+        );
+        let cb = vsubq_s16(widen(u), vdupq_n_s16(128));
+        let cr = vsubq_s16(widen(v), vdupq_n_s16(128));
+        let red = vqaddq_s16(luma, vmulq_n_s16(cr, 102));
+        let green = vqsubq_s16(vqsubq_s16(luma, vmulq_n_s16(cb, 25)), vmulq_n_s16(cr, 52));
+        let blue = vqaddq_s16(luma, vmulq_n_s16(cb, 129));
+        [
+            vqshrun_n_s16::<6>(red),
+            vqshrun_n_s16::<6>(green),
+            vqshrun_n_s16::<6>(blue),
+        ]
+    }
+
+    /// Interleaves the pixels of `even` and `odd`.
+    #[inline]
+    unsafe fn zip(even: uint8x8_t, odd: uint8x8_t) -> uint8x16_t {
+        let zipped = vzip_u8(even, odd);
+        vcombine_u8(zipped.0, zipped.1)
+    }
+
+    /// Converts 16 pixels, given the even and odd Y samples and the U and V shared by each pair, and stores them interleaved.
+    #[inline]
+    unsafe fn convert_and_store(
+        even: uint8x8_t,
+        odd: uint8x8_t,
+        u: uint8x8_t,
+        v: uint8x8_t,
+        dest: &mut [u8],
+        rgba: bool,
+    ) {
+        let [even_red, even_green, even_blue] = yuv_to_rgb_neon(even, u, v);
+        let [odd_red, odd_green, odd_blue] = yuv_to_rgb_neon(odd, u, v);
+        let red = zip(even_red, odd_red);
+        let green = zip(even_green, odd_green);
+        let blue = zip(even_blue, odd_blue);
+        if rgba {
+            vst4q_u8(
+                dest.as_mut_ptr(),
+                uint8x16x4_t(red, green, blue, vdupq_n_u8(u8::MAX)),
+            );
+        } else {
+            vst3q_u8(dest.as_mut_ptr(), uint8x16x3_t(red, green, blue));
+        }
+    }
+
+    pub(super) unsafe fn yuyv_row(src: &[u8], dest: &mut [u8], rgba: bool) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(src.len() / 2, dest, rgba, 16);
+        for (block, dest) in src
+            .chunks_exact(32)
+            .zip(dest.chunks_exact_mut(16 * pixel_size))
+            .take(blocks)
+        {
+            let yuyv = vld4_u8(block.as_ptr());
+            convert_and_store(yuyv.0, yuyv.2, yuyv.1, yuyv.3, dest, rgba);
+        }
+        blocks * 16
+    }
+
+    pub(super) unsafe fn nv12_row(
+        luma: &[u8],
+        chroma: &[u8],
+        dest: &mut [u8],
+        rgba: bool,
+    ) -> usize {
+        let pixel_size = if rgba { 4 } else { 3 };
+        let blocks = block_count(luma.len().min(chroma.len()), dest, rgba, 16);
+        for ((luma, chroma), dest) in luma
+            .chunks_exact(16)
+            .zip(chroma.chunks_exact(16))
+            .zip(dest.chunks_exact_mut(16 * pixel_size))
+            .take(blocks)
+        {
+            let y = vld2_u8(luma.as_ptr());
+            let uv = vld2_u8(chroma.as_ptr());
+            convert_and_store(y.0, y.1, uv.0, uv.1, dest, rgba);
+        }
+        blocks * 16
+    }
+}
@@ -0,0 +1,275 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions of 10-bit YUV into 16-bit RGB (RGB48) or floating point RGB, for HDR pipelines that need the extra precision.
+
+use super::for_each_row;
+use crate::{
+    error::NokhwaError,
+    pixel_format::{P010, Y210},
+    types::Resolution,
+};
+use four_cc::FourCC;
+
+/// Converts a bi-planar 4:2:0 10-bit frame ([`P010`](crate::pixel_format::P010)) into packed RGB48, three `u16`s per pixel
+/// using their full range.
+///
+/// Like [`NV12`](crate::pixel_format::NV12), but every sample is a little endian `u16` with the value in its upper 10 bits.
+/// BT.601 limited range is assumed.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn p010_to_rgb48(resolution: Resolution, data: &[u8]) -> Result<Vec<u16>, NokhwaError> {
+    let mut dest = vec![0; rgb48_size(resolution)];
+    buf_p010_to_rgb48(resolution, data, &mut dest)?;
+    Ok(dest)
+}
+
+/// Converts a bi-planar 4:2:0 10-bit frame into `dest`, see [`p010_to_rgb48()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_p010_to_rgb48(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u16],
+) -> Result<(), NokhwaError> {
+    let width = resolution.width() as usize;
+    let height = resolution.height() as usize;
+    // in bytes, odd sizes still get a chroma sample for the last column and row
+    let luma_stride = width * 2;
+    let chroma_stride = width.div_ceil(2) * 4;
+    let luma_size = luma_stride * height;
+    let source_size = luma_size + chroma_stride * height.div_ceil(2);
+    check_sizes(P010, resolution, data.len(), source_size, dest.len())?;
+    if width == 0 {
+        return Ok(());
+    }
+
+    let (luma, chroma) = data.split_at(luma_size);
+    for_each_row(
+        &mut dest[..rgb48_size(resolution)],
+        width,
+        3,
+        width * 3,
+        |row, dest_row| {
+            let luma_row = &luma[row * luma_stride..][..luma_stride];
+            let chroma_row = &chroma[(row / 2) * chroma_stride..][..chroma_stride];
+            for ((luma_pair, uv), dest_pair) in luma_row
+                .chunks(4)
+                .zip(chroma_row.chunks_exact(4))
+                .zip(dest_row.chunks_mut(6))
+            {
+                let (cb, cr) = (sample(&uv[..2]), sample(&uv[2..]));
+                for (luma, pixel) in luma_pair.chunks_exact(2).zip(dest_pair.chunks_exact_mut(3)) {
+                    pixel.copy_from_slice(&to_rgb48(yuv10_to_rgb(sample(luma), cb, cr)));
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Converts a packed 4:2:2 10-bit frame ([`Y210`](crate::pixel_format::Y210)) into packed RGB48, three `u16`s per pixel
+/// using their full range.
+///
+/// Like [`YUYV`](crate::pixel_format::YUYV), but every sample is a little endian `u16` with the value in its upper 10 bits.
+/// BT.601 limited range is assumed.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn y210_to_rgb48(resolution: Resolution, data: &[u8]) -> Result<Vec<u16>, NokhwaError> {
+    let mut dest = vec![0; rgb48_size(resolution)];
+    buf_y210_to_rgb48(resolution, data, &mut dest)?;
+    Ok(dest)
+}
+
+/// Converts a packed 4:2:2 10-bit frame into `dest`, see [`y210_to_rgb48()`].
+/// # Errors
+/// If `data` is too small for the `resolution`, or `dest` is too small for the converted frame, this will error.
+pub fn buf_y210_to_rgb48(
+    resolution: Resolution,
+    data: &[u8],
+    dest: &mut [u16],
+) -> Result<(), NokhwaError> {
+    let width = resolution.width() as usize;
+    // an odd width still stores a whole `Y0 U Y1 V` group for the last pixel
+    let stride = width.div_ceil(2) * 8;
+    let source_size = stride * resolution.height() as usize;
+    check_sizes(Y210, resolution, data.len(), source_size, dest.len())?;
+    if width == 0 {
+        return Ok(());
+    }
+
+    for_each_row(
+        &mut dest[..rgb48_size(resolution)],
+        width,
+        3,
+        width * 3,
+        |row, dest_row| {
+            let src_row = &data[row * stride..][..stride];
+            for (group, dest_pair) in src_row.chunks_exact(8).zip(dest_row.chunks_mut(6)) {
+                let (cb, cr) = (sample(&group[2..4]), sample(&group[6..]));
+                for (luma, pixel) in [&group[..2], &group[4..6]]
+                    .into_iter()
+                    .zip(dest_pair.chunks_exact_mut(3))
+                {
+                    pixel.copy_from_slice(&to_rgb48(yuv10_to_rgb(sample(luma), cb, cr)));
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Converts a [`P010`](crate::pixel_format::P010) frame into packed floating point RGB, each component from `0.0` to `1.0`.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn p010_to_rgb_f32(resolution: Resolution, data: &[u8]) -> Result<Vec<f32>, NokhwaError> {
+    p010_to_rgb48(resolution, data).map(|rgb48| rgb48_to_f32(&rgb48))
+}
+
+/// Converts a [`Y210`](crate::pixel_format::Y210) frame into packed floating point RGB, each component from `0.0` to `1.0`.
+/// # Errors
+/// If `data` is too small for the `resolution`, this will error.
+pub fn y210_to_rgb_f32(resolution: Resolution, data: &[u8]) -> Result<Vec<f32>, NokhwaError> {
+    y210_to_rgb48(resolution, data).map(|rgb48| rgb48_to_f32(&rgb48))
+}
+
+/// Scales 16-bit components, such as the output of [`p010_to_rgb48()`], to floating point from `0.0` to `1.0`.
+#[must_use]
+pub fn rgb48_to_f32(data: &[u16]) -> Vec<f32> {
+    data.iter()
+        .map(|&component| f32::from(component) / f32::from(u16::MAX))
+        .collect()
+}
+
+fn rgb48_size(resolution: Resolution) -> usize {
+    resolution.width() as usize * resolution.height() as usize * 3
+}
+
+fn check_sizes(
+    src: FourCC,
+    resolution: Resolution,
+    source_len: usize,
+    source_size: usize,
+    dest_len: usize,
+) -> Result<(), NokhwaError> {
+    let error = |error: String| NokhwaError::ProcessFrameError {
+        src,
+        destination: "RGB48".to_string(),
+        error,
+    };
+    if source_len < source_size {
+        return Err(error(format!(
+            "Expected at least {source_size} bytes, got {source_len}"
+        )));
+    }
+    let dest_size = rgb48_size(resolution);
+    if dest_len < dest_size {
+        return Err(error(format!(
+            "Destination needs {dest_size} components, has {dest_len}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reads a little endian sample with the value in its upper 10 bits.
+#[inline]
+fn sample(bytes: &[u8]) -> f32 {
+    f32::from(u16::from_le_bytes([bytes[0], bytes[1]]) >> 6)
+}
+
+/// Converts one BT.601 limited range 10-bit YUV sample to RGB, from `0.0` to `1.0`.
+#[inline]
+fn yuv10_to_rgb(luma: f32, cb: f32, cr: f32) -> [f32; 3] {
+    // limited range luma goes from 64 to 940, chroma from 64 to 960 centered on 512
+    let luma = (luma - 64.0) / 876.0;
+    let cb = (cb - 512.0) / 896.0;
+    let cr = (cr - 512.0) / 896.0;
+    [
+        luma + 1.402 * cr,
+        luma - 0.344_136 * cb - 0.714_136 * cr,
+        luma + 1.772 * cb,
+    ]
+    .map(|component| component.clamp(0.0, 1.0))
+}
+
+#[inline]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_rgb48(rgb: [f32; 3]) -> [u16; 3] {
+    // the components are clamped to 0.0..=1.0, so this cannot overflow or go negative
+    rgb.map(|component| (component * f32::from(u16::MAX)).round() as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stores 10-bit samples the way `P010` and `Y210` do, in the upper bits of little endian `u16`s.
+    fn samples(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| (value << 6).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn p010_limited_range_spans_rgb48() {
+        // black and white on the top row, grey below, with neutral chroma
+        let mut data = samples(&[64, 940, 502, 502]);
+        data.extend(samples(&[512, 512]));
+        let rgb = p010_to_rgb48(Resolution::new(2, 2), &data).unwrap();
+        assert_eq!(&rgb[..6], &[0, 0, 0, u16::MAX, u16::MAX, u16::MAX]);
+        assert!(rgb[6..].iter().all(|&component| component == rgb[6]));
+        assert!(rgb[6].abs_diff(u16::MAX / 2) <= 1);
+    }
+
+    #[test]
+    fn y210_matches_p010() {
+        let luma = [100, 300, 500, 700, 900, 64];
+        let chroma = [[200, 800], [700, 300], [512, 960]];
+        let mut p010 = samples(&luma);
+        p010.extend(samples(&chroma.concat()));
+        let y210 = samples(
+            &luma
+                .chunks_exact(2)
+                .zip(chroma)
+                .flat_map(|(luma, [cb, cr])| [luma[0], cb, luma[1], cr])
+                .collect::<Vec<_>>(),
+        );
+        let resolution = Resolution::new(6, 1);
+        assert_eq!(
+            p010_to_rgb48(resolution, &p010).unwrap(),
+            y210_to_rgb48(resolution, &y210).unwrap()
+        );
+    }
+
+    #[test]
+    fn floats_stay_in_range() {
+        let mut data = samples(&[0, 1023, 0, 1023]);
+        data.extend(samples(&[0, 1023]));
+        let rgb = p010_to_rgb_f32(Resolution::new(2, 2), &data).unwrap();
+        assert_eq!(rgb.len(), 12);
+        assert!(rgb.iter().all(|component| (0.0..=1.0).contains(component)));
+        assert_eq!(rgb48_to_f32(&[0, u16::MAX]), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn short_buffers_are_rejected() {
+        let resolution = Resolution::new(2, 2);
+        assert!(p010_to_rgb48(resolution, &[0; 11]).is_err());
+        assert!(y210_to_rgb48(resolution, &[0; 15]).is_err());
+        assert!(buf_p010_to_rgb48(resolution, &[0; 12], &mut [0; 11]).is_err());
+    }
+}
@@ -16,6 +16,10 @@
 
 use crate::types::ApiBackend;
 use four_cc::FourCC;
+use std::{
+    fmt::{Display, Formatter},
+    io,
+};
 use thiserror::Error;
 
 /// All errors in `nokhwa`.
@@ -46,6 +50,8 @@ pub enum NokhwaError {
     OpenStreamError(String),
     #[error("Could not capture frame: {0}")]
     ReadFrameError(String),
+    #[error("Could not write frame: {0}")]
+    WriteFrameError(String),
     #[error("Could not process frame {src} to {destination}: {error}")]
     ProcessFrameError {
         src: FourCC,
@@ -58,4 +64,174 @@ pub enum NokhwaError {
     UnsupportedOperationError(ApiBackend),
     #[error("This operation is not implemented yet: {0}")]
     NotImplementedError(String),
+    #[error("{error}{}", os_error.as_ref().map(|os_error| format!(" ({os_error})")).unwrap_or_default())]
+    Detailed {
+        error: Box<NokhwaError>,
+        kind: ErrorKind,
+        os_error: Option<PlatformError>,
+    },
+}
+
+impl NokhwaError {
+    /// The [`ErrorKind`] of this error. This is [`ErrorKind::Other`] unless the backend could classify the error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            NokhwaError::Detailed { kind, .. } => *kind,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// The raw error code the platform returned, if there was one.
+    #[must_use]
+    pub fn os_error(&self) -> Option<&PlatformError> {
+        match self {
+            NokhwaError::Detailed { os_error, .. } => os_error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The error without the details added by [`with_kind()`](NokhwaError::with_kind) or [`with_os_error()`](NokhwaError::with_os_error).
+    #[must_use]
+    pub fn inner(&self) -> &NokhwaError {
+        match self {
+            NokhwaError::Detailed { error, .. } => error.inner(),
+            _ => self,
+        }
+    }
+
+    /// Sets the [`ErrorKind`] of this error, keeping any [`PlatformError`] it already has.
+    #[must_use]
+    pub fn with_kind(self, kind: ErrorKind) -> Self {
+        match self {
+            NokhwaError::Detailed {
+                error, os_error, ..
+            } => NokhwaError::Detailed {
+                error,
+                kind,
+                os_error,
+            },
+            error => NokhwaError::Detailed {
+                error: Box::new(error),
+                kind,
+                os_error: None,
+            },
+        }
+    }
+
+    /// Attaches the raw platform error code to this error. The [`ErrorKind`] is derived from it.
+    #[must_use]
+    pub fn with_os_error(self, os_error: PlatformError) -> Self {
+        let error = match self {
+            NokhwaError::Detailed { error, .. } => error,
+            error => Box::new(error),
+        };
+        NokhwaError::Detailed {
+            error,
+            kind: os_error.kind(),
+            os_error: Some(os_error),
+        }
+    }
+
+    /// Attaches the `errno` of an [`io::Error`] to this error, if it has one.
+    #[must_use]
+    pub fn with_io_error(self, io_error: &io::Error) -> Self {
+        match io_error.raw_os_error() {
+            Some(errno) => self.with_os_error(PlatformError::Errno(errno)),
+            None => self,
+        }
+    }
+}
+
+/// A broad classification of a [`NokhwaError`] that can be matched on, regardless of the backend that produced it.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// The user or system has not allowed access to the camera.
+    PermissionDenied,
+    /// The camera is being used by another application or session.
+    DeviceBusy,
+    /// The camera does not support the requested format.
+    FormatUnsupported,
+    /// The camera was unplugged or otherwise went away.
+    Disconnected,
+    /// Anything else, or the error could not be classified.
+    Other,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            ErrorKind::PermissionDenied => "Permission Denied",
+            ErrorKind::DeviceBusy => "Device Busy",
+            ErrorKind::FormatUnsupported => "Format Unsupported",
+            ErrorKind::Disconnected => "Disconnected",
+            ErrorKind::Other => "Other",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+/// A raw error code, as returned by the platform's camera API.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum PlatformError {
+    /// An `errno` value, from `Video4Linux`.
+    Errno(i32),
+    /// A `HRESULT`, from `Media Foundation`.
+    HResult(i32),
+    /// The domain and code of an `NSError`, from `AVFoundation`.
+    NSError { domain: String, code: isize },
+}
+
+impl PlatformError {
+    /// Classifies this error code. Codes that are not known to mean one of the [`ErrorKind`]s are [`ErrorKind::Other`].
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            // Linux errno values
+            PlatformError::Errno(errno) => match errno {
+                // EPERM, EACCES
+                1 | 13 => ErrorKind::PermissionDenied,
+                // EBUSY
+                16 => ErrorKind::DeviceBusy,
+                // ENOENT, ENXIO, ENODEV
+                2 | 6 | 19 => ErrorKind::Disconnected,
+                _ => ErrorKind::Other,
+            },
+            PlatformError::HResult(hresult) => match u32::from_ne_bytes(hresult.to_ne_bytes()) {
+                // E_ACCESSDENIED
+                0x8007_0005 => ErrorKind::PermissionDenied,
+                // HRESULT_FROM_WIN32(ERROR_SHARING_VIOLATION), MF_E_HW_MFT_FAILED_START_STREAMING,
+                // MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED
+                0x8007_0020 | 0xC00D_3704 | 0xC00D_3EA3 => ErrorKind::DeviceBusy,
+                // MF_E_INVALIDMEDIATYPE, MF_E_UNSUPPORTED_FORMAT
+                0xC00D_36B4 | 0xC00D_3E98 => ErrorKind::FormatUnsupported,
+                // HRESULT_FROM_WIN32(ERROR_DEVICE_NOT_CONNECTED), MF_E_VIDEO_RECORDING_DEVICE_INVALIDATED
+                0x8007_048F | 0xC00D_3EA2 => ErrorKind::Disconnected,
+                _ => ErrorKind::Other,
+            },
+            PlatformError::NSError { domain, code } if domain == "AVFoundationErrorDomain" => {
+                match code {
+                    // AVErrorApplicationIsNotAuthorizedToUseDevice
+                    -11852 => ErrorKind::PermissionDenied,
+                    // AVErrorDeviceAlreadyUsedByAnotherSession, AVErrorDeviceInUseByAnotherApplication,
+                    // AVErrorDeviceLockedForConfigurationByAnotherProcess
+                    -11804 | -11815 | -11817 => ErrorKind::DeviceBusy,
+                    // AVErrorDeviceWasDisconnected, AVErrorDeviceNotConnected
+                    -11808 | -11814 => ErrorKind::Disconnected,
+                    _ => ErrorKind::Other,
+                }
+            }
+            PlatformError::NSError { .. } => ErrorKind::Other,
+        }
+    }
+}
+
+impl Display for PlatformError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlatformError::Errno(errno) => write!(f, "errno {errno}"),
+            PlatformError::HResult(hresult) => write!(f, "HRESULT {hresult:#010X}"),
+            PlatformError::NSError { domain, code } => write!(f, "{domain} code {code}"),
+        }
+    }
 }
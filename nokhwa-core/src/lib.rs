@@ -20,7 +20,9 @@
 
 //! Core type definitions for `nokhwa`
 pub mod buffer;
+pub mod conversion;
 pub mod error;
 pub mod pixel_format;
 pub mod traits;
+pub mod transform;
 pub mod types;
@@ -1,11 +1,251 @@
 use four_cc::FourCC;
+use std::fmt::{Display, Formatter};
 
 pub const YUV420: FourCC = FourCC(*b"420v");
 pub const MJPEG: FourCC = FourCC(*b"MJPG");
 pub const YUYV: FourCC = FourCC(*b"YUYV");
 pub const RAWRGB: FourCC = FourCC(*b"RGB3");
 pub const NV12: FourCC = FourCC(*b"nv12");
+// NV12 with each plane in its own buffer, from multi-planar Video4Linux devices
+pub const NV12M: FourCC = FourCC(*b"NM12");
+// Planar 4:2:0 YUV, Y then U then V
+pub const I420: FourCC = FourCC(*b"I420");
+// I420 as Video4Linux calls it
+pub const YU12: FourCC = FourCC(*b"YU12");
+// I420 with each plane in its own buffer, from multi-planar Video4Linux devices
+pub const YUV420M: FourCC = FourCC(*b"YM12");
+// Planar 4:2:0 YUV, Y then V then U
+pub const YV12: FourCC = FourCC(*b"YV12");
 pub const UYVY: FourCC = FourCC(*b"uyvy");
 // Also known as 2vuy
 pub const UYVY_APPLE: FourCC = FourCC(*b"2vuy");
 pub const GRAY: FourCC = FourCC(*b"GRAY");
+// 16-bit greyscale in little endian u16s, e.g. from infrared sensors
+pub const GRAY16: FourCC = FourCC(*b"Y16 ");
+// 16-bit depth in little endian u16s, usually in millimeters
+pub const DEPTH16: FourCC = FourCC(*b"D16 ");
+pub const BGRA: FourCC = FourCC(*b"BGRA");
+// Packed 8-bit RGBA, which cameras do not capture in, but frames can be converted into
+pub const RGBA: FourCC = FourCC(*b"RGBA");
+// 8-bit Bayer mosaics, named after their first 2x2 block
+pub const BAYER_BGGR8: FourCC = FourCC(*b"BA81");
+pub const BAYER_GBRG8: FourCC = FourCC(*b"GBRG");
+pub const BAYER_GRBG8: FourCC = FourCC(*b"GRBG");
+pub const BAYER_RGGB8: FourCC = FourCC(*b"RGGB");
+// 10-bit 4:2:2 YUV, packed 3 components per 32 bits
+pub const V210: FourCC = FourCC(*b"v210");
+// 10-bit bi-planar 4:2:0 YUV, laid out like NV12 with the samples in the upper 10 bits of little endian u16s
+pub const P010: FourCC = FourCC(*b"P010");
+// Also known as x420
+pub const P010_APPLE: FourCC = FourCC(*b"x420");
+// 10-bit packed 4:2:2 YUV, laid out like YUYV with the samples in the upper 10 bits of little endian u16s
+pub const Y210: FourCC = FourCC(*b"Y210");
+// H.264 / AVC elementary stream, from UVC cameras that encode on the device
+pub const H264: FourCC = FourCC(*b"H264");
+// H.265 / HEVC elementary stream
+pub const HEVC: FourCC = FourCC(*b"HEVC");
+
+/// A [`FourCC`] that `nokhwa` knows about, for matching exhaustively on the format of a [`CameraFormat`](crate::types::CameraFormat)
+/// or [`FrameBuffer`](crate::buffer::FrameBuffer).
+///
+/// Converting from a [`FourCC`] never fails, anything not listed here is kept as [`Unknown`](KnownFrameFormat::Unknown).
+/// Aliases (such as [`UYVY_APPLE`]) are folded into the same variant, and convert back into the first [`FourCC`] listed above.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KnownFrameFormat {
+    /// Bi-planar 4:2:0 YUV, video range ([`YUV420`]).
+    Yuv420,
+    /// Motion JPEG ([`MJPEG`]).
+    Mjpeg,
+    /// Packed 4:2:2 YUV, `Y0 U Y1 V` ([`YUYV`]).
+    Yuyv,
+    /// Packed 8-bit RGB ([`RAWRGB`]).
+    Rgb24,
+    /// Bi-planar 4:2:0 YUV ([`NV12`], [`NV12M`]).
+    Nv12,
+    /// Planar 4:2:0 YUV, with the U plane before the V plane ([`I420`], [`YU12`], [`YUV420M`]).
+    I420,
+    /// Planar 4:2:0 YUV, with the V plane before the U plane ([`YV12`]).
+    Yv12,
+    /// Packed 4:2:2 YUV, `U Y0 V Y1` ([`UYVY`], [`UYVY_APPLE`]).
+    Uyvy,
+    /// 8-bit greyscale ([`GRAY`]).
+    Gray8,
+    /// 16-bit greyscale ([`GRAY16`]).
+    Gray16,
+    /// 16-bit depth ([`DEPTH16`]).
+    Depth16,
+    /// Packed 8-bit BGRA ([`BGRA`]).
+    Bgra,
+    /// 8-bit Bayer mosaic, `BG/GR` ([`BAYER_BGGR8`]).
+    BayerBggr8,
+    /// 8-bit Bayer mosaic, `GB/RG` ([`BAYER_GBRG8`]).
+    BayerGbrg8,
+    /// 8-bit Bayer mosaic, `GR/BG` ([`BAYER_GRBG8`]).
+    BayerGrbg8,
+    /// 8-bit Bayer mosaic, `RG/GB` ([`BAYER_RGGB8`]).
+    BayerRggb8,
+    /// Packed 10-bit 4:2:2 YUV ([`V210`]).
+    V210,
+    /// Bi-planar 10-bit 4:2:0 YUV ([`P010`], [`P010_APPLE`]).
+    P010,
+    /// Packed 10-bit 4:2:2 YUV, `Y0 U Y1 V` ([`Y210`]).
+    Y210,
+    /// H.264 / AVC ([`H264`]).
+    H264,
+    /// H.265 / HEVC ([`HEVC`]).
+    Hevc,
+    /// Any other [`FourCC`].
+    Unknown(FourCC),
+}
+
+impl KnownFrameFormat {
+    /// Get the average amount of bits each pixel takes up. Returns `None` for compressed, unknown, or formats that don't
+    /// pack into a whole number of bits per pixel ([`V210`]).
+    #[must_use]
+    pub fn bits_per_pixel(self) -> Option<u32> {
+        match self {
+            KnownFrameFormat::Yuv420
+            | KnownFrameFormat::Nv12
+            | KnownFrameFormat::I420
+            | KnownFrameFormat::Yv12 => Some(12),
+            KnownFrameFormat::Yuyv
+            | KnownFrameFormat::Uyvy
+            | KnownFrameFormat::Gray16
+            | KnownFrameFormat::Depth16 => Some(16),
+            KnownFrameFormat::Rgb24 | KnownFrameFormat::P010 => Some(24),
+            KnownFrameFormat::Bgra | KnownFrameFormat::Y210 => Some(32),
+            KnownFrameFormat::Gray8
+            | KnownFrameFormat::BayerBggr8
+            | KnownFrameFormat::BayerGbrg8
+            | KnownFrameFormat::BayerGrbg8
+            | KnownFrameFormat::BayerRggb8 => Some(8),
+            KnownFrameFormat::Mjpeg
+            | KnownFrameFormat::H264
+            | KnownFrameFormat::Hevc
+            | KnownFrameFormat::V210
+            | KnownFrameFormat::Unknown(_) => None,
+        }
+    }
+
+    /// Get the size in bytes of an unpadded `width` by `height` frame. Returns `None` for compressed or unknown formats.
+    #[must_use]
+    pub fn frame_size(self, width: u32, height: u32) -> Option<usize> {
+        let (width, height) = (width as usize, height as usize);
+        match self {
+            // 6 pixels per 16 bytes, with rows padded to 48 pixels
+            KnownFrameFormat::V210 => Some(width.div_ceil(48) * 128 * height),
+            other => Some(width * height * other.bits_per_pixel()? as usize / 8),
+        }
+    }
+
+    /// Get the amount of bits each sample of a component holds, such as 8 for [`NV12`] and 10 for [`P010`]. Returns `None`
+    /// for compressed or unknown formats.
+    #[must_use]
+    pub fn bit_depth(self) -> Option<u32> {
+        match self {
+            KnownFrameFormat::V210 | KnownFrameFormat::P010 | KnownFrameFormat::Y210 => Some(10),
+            KnownFrameFormat::Gray16 | KnownFrameFormat::Depth16 => Some(16),
+            KnownFrameFormat::Mjpeg
+            | KnownFrameFormat::H264
+            | KnownFrameFormat::Hevc
+            | KnownFrameFormat::Unknown(_) => None,
+            _ => Some(8),
+        }
+    }
+
+    /// Checks if this is a compressed format, which has no fixed frame size.
+    #[must_use]
+    pub fn is_compressed(self) -> bool {
+        matches!(
+            self,
+            KnownFrameFormat::Mjpeg | KnownFrameFormat::H264 | KnownFrameFormat::Hevc
+        )
+    }
+}
+
+impl From<FourCC> for KnownFrameFormat {
+    fn from(fourcc: FourCC) -> Self {
+        match fourcc {
+            YUV420 => KnownFrameFormat::Yuv420,
+            MJPEG => KnownFrameFormat::Mjpeg,
+            YUYV => KnownFrameFormat::Yuyv,
+            RAWRGB => KnownFrameFormat::Rgb24,
+            NV12 | NV12M => KnownFrameFormat::Nv12,
+            I420 | YU12 | YUV420M => KnownFrameFormat::I420,
+            YV12 => KnownFrameFormat::Yv12,
+            UYVY | UYVY_APPLE => KnownFrameFormat::Uyvy,
+            GRAY => KnownFrameFormat::Gray8,
+            GRAY16 => KnownFrameFormat::Gray16,
+            DEPTH16 => KnownFrameFormat::Depth16,
+            BGRA => KnownFrameFormat::Bgra,
+            BAYER_BGGR8 => KnownFrameFormat::BayerBggr8,
+            BAYER_GBRG8 => KnownFrameFormat::BayerGbrg8,
+            BAYER_GRBG8 => KnownFrameFormat::BayerGrbg8,
+            BAYER_RGGB8 => KnownFrameFormat::BayerRggb8,
+            V210 => KnownFrameFormat::V210,
+            P010 | P010_APPLE => KnownFrameFormat::P010,
+            Y210 => KnownFrameFormat::Y210,
+            H264 => KnownFrameFormat::H264,
+            HEVC => KnownFrameFormat::Hevc,
+            other => KnownFrameFormat::Unknown(other),
+        }
+    }
+}
+
+impl From<KnownFrameFormat> for FourCC {
+    fn from(format: KnownFrameFormat) -> Self {
+        match format {
+            KnownFrameFormat::Yuv420 => YUV420,
+            KnownFrameFormat::Mjpeg => MJPEG,
+            KnownFrameFormat::Yuyv => YUYV,
+            KnownFrameFormat::Rgb24 => RAWRGB,
+            KnownFrameFormat::Nv12 => NV12,
+            KnownFrameFormat::I420 => I420,
+            KnownFrameFormat::Yv12 => YV12,
+            KnownFrameFormat::Uyvy => UYVY,
+            KnownFrameFormat::Gray8 => GRAY,
+            KnownFrameFormat::Gray16 => GRAY16,
+            KnownFrameFormat::Depth16 => DEPTH16,
+            KnownFrameFormat::Bgra => BGRA,
+            KnownFrameFormat::BayerBggr8 => BAYER_BGGR8,
+            KnownFrameFormat::BayerGbrg8 => BAYER_GBRG8,
+            KnownFrameFormat::BayerGrbg8 => BAYER_GRBG8,
+            KnownFrameFormat::BayerRggb8 => BAYER_RGGB8,
+            KnownFrameFormat::V210 => V210,
+            KnownFrameFormat::P010 => P010,
+            KnownFrameFormat::Y210 => Y210,
+            KnownFrameFormat::H264 => H264,
+            KnownFrameFormat::Hevc => HEVC,
+            KnownFrameFormat::Unknown(fourcc) => fourcc,
+        }
+    }
+}
+
+impl Display for KnownFrameFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KnownFrameFormat::Yuv420 => write!(f, "YUV 4:2:0 (420v)"),
+            KnownFrameFormat::Mjpeg => write!(f, "Motion JPEG"),
+            KnownFrameFormat::Yuyv => write!(f, "YUYV 4:2:2"),
+            KnownFrameFormat::Rgb24 => write!(f, "RGB24"),
+            KnownFrameFormat::Nv12 => write!(f, "NV12"),
+            KnownFrameFormat::I420 => write!(f, "I420"),
+            KnownFrameFormat::Yv12 => write!(f, "YV12"),
+            KnownFrameFormat::Uyvy => write!(f, "UYVY 4:2:2"),
+            KnownFrameFormat::Gray8 => write!(f, "Grayscale 8-bit"),
+            KnownFrameFormat::Gray16 => write!(f, "Grayscale 16-bit"),
+            KnownFrameFormat::Depth16 => write!(f, "Depth 16-bit"),
+            KnownFrameFormat::Bgra => write!(f, "BGRA"),
+            KnownFrameFormat::BayerBggr8 => write!(f, "Bayer BGGR 8-bit"),
+            KnownFrameFormat::BayerGbrg8 => write!(f, "Bayer GBRG 8-bit"),
+            KnownFrameFormat::BayerGrbg8 => write!(f, "Bayer GRBG 8-bit"),
+            KnownFrameFormat::BayerRggb8 => write!(f, "Bayer RGGB 8-bit"),
+            KnownFrameFormat::V210 => write!(f, "v210 10-bit 4:2:2"),
+            KnownFrameFormat::P010 => write!(f, "P010 10-bit 4:2:0"),
+            KnownFrameFormat::Y210 => write!(f, "Y210 10-bit 4:2:2"),
+            KnownFrameFormat::H264 => write!(f, "H.264"),
+            KnownFrameFormat::Hevc => write!(f, "H.265 / HEVC"),
+            KnownFrameFormat::Unknown(fourcc) => write!(f, "Unknown ({fourcc})"),
+        }
+    }
+}
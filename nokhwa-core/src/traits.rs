@@ -18,12 +18,13 @@ use crate::{
     buffer::FrameBuffer,
     error::NokhwaError,
     types::{
-        ApiBackend, CameraControl, CameraFormat, CameraInfo, ControlValueSetter,
-        KnownCameraControl, Resolution,
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
+        Exposure, Focus, KnownCameraControl, PtzCapabilities, Rect, RequestedFormat, Resolution,
+        SessionEvent, StreamStats, WhiteBalance, XuQuery,
     },
 };
 use four_cc::FourCC;
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, time::Duration};
 
 /// This trait is for any backend that allows you to grab and take frames from a camera.
 /// Many of the backends are **blocking**, if the camera is occupied the library will block while it waits for it to become available.
@@ -31,8 +32,10 @@ use std::{borrow::Cow, collections::HashMap};
 /// **Note**:
 /// - Backends, if not provided with a camera format, will be spawned with 640x480@15 FPS, MJPEG [`CameraFormat`].
 /// - Behaviour can differ from backend to backend. While the Camera struct abstracts most of this away, if you plan to use the raw backend structs please read the `Quirks` section of each backend.
-/// - If you call [`stop_stream()`](CaptureBackendTrait::stop_stream()), you will usually need to call [`open_stream()`](CaptureBackendTrait::open_stream()) to get more frames from the camera.
-pub trait CaptureBackendTrait {
+/// - If you call [`stop_stream()`](CaptureDevice::stop_stream()), you will usually need to call [`open_stream()`](CaptureDevice::open_stream()) to get more frames from the camera.
+/// - This trait is object safe, and `Camera` holds its backend as a `Box<dyn CaptureDevice>`. Code that works with any backend
+///   can take a `&mut dyn CaptureDevice` instead of being generic over it.
+pub trait CaptureDevice {
     /// Returns the current backend used.
     fn backend(&self) -> ApiBackend;
 
@@ -129,7 +132,7 @@ pub trait CaptureBackendTrait {
     fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError>;
 
     /// Sets the control to `control` in the camera.
-    /// Usually, the pipeline is calling [`camera_control()`](CaptureBackendTrait::camera_control), getting a camera control that way
+    /// Usually, the pipeline is calling [`camera_control()`](CaptureDevice::camera_control), getting a camera control that way
     /// then calling [`value()`](CameraControl::value()) to get a [`ControlValueSetter`] and setting the value that way.
     /// # Errors
     /// If the `control` is not supported, the value is invalid (less than min, greater than max, not in step), or there was an error setting the control,
@@ -140,7 +143,23 @@ pub trait CaptureBackendTrait {
         value: ControlValueSetter,
     ) -> Result<(), NokhwaError>;
 
-    /// Will open the camera stream with set parameters. This will be called internally if you try and call [`frame()`](CaptureBackendTrait::frame()) before you call [`open_stream()`](CaptureBackendTrait::open_stream()).
+    /// Sets several controls at once, returning the result of each, in the order of `controls`.
+    /// Backends that can apply them together do so, which is faster than calling
+    /// [`set_camera_control()`](CaptureDevice::set_camera_control) for each of them, and does not show them changing one after another in the stream.
+    /// The default implementation calls [`set_camera_control()`](CaptureDevice::set_camera_control) for each control.
+    /// # Errors
+    /// If none of the controls could be set, e.g. because the device could not be locked for configuration, this will error.
+    fn set_camera_controls(
+        &mut self,
+        controls: &[(KnownCameraControl, ControlValueSetter)],
+    ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+        Ok(controls
+            .iter()
+            .map(|(id, value)| self.set_camera_control(*id, value.clone()))
+            .collect())
+    }
+
+    /// Will open the camera stream with set parameters. This will be called internally if you try and call [`frame()`](CaptureDevice::frame()) before you call [`open_stream()`](CaptureDevice::open_stream()).
     /// # Errors
     /// If the specific backend fails to open the camera (e.g. already taken, busy, doesn't exist anymore) this will error.
     fn open_stream(&mut self) -> Result<(), NokhwaError>;
@@ -148,31 +167,264 @@ pub trait CaptureBackendTrait {
     /// Checks if stream if open. If it is, it will return true.
     fn is_stream_open(&self) -> bool;
 
-    /// Will get a frame from the camera as a [`Buffer`]. Depending on the backend, if you have not called [`open_stream()`](CaptureBackendTrait::open_stream()) before you called this,
+    /// Will get a frame from the camera as a [`Buffer`]. Depending on the backend, if you have not called [`open_stream()`](CaptureDevice::open_stream()) before you called this,
     /// it will either return an error.
     /// # Errors
-    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), the decoding fails (e.g. MJPEG -> u8), or [`open_stream()`](CaptureBackendTrait::open_stream()) has not been called yet,
+    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), the decoding fails (e.g. MJPEG -> u8), or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet,
     /// this will error.
     fn frame(&mut self) -> Result<FrameBuffer, NokhwaError>;
 
+    /// Like [`frame()`](CaptureDevice::frame), but gives up and returns `Ok(None)` if no frame arrives within `timeout`.
+    /// A `timeout` of zero only returns a frame that is already waiting.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the backend cannot wait for frames with a timeout, or fails to get the frame, this will error.
+    fn frame_timeout(&mut self, timeout: Duration) -> Result<Option<FrameBuffer>, NokhwaError> {
+        let _ = timeout;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
     /// Will get a frame from the camera **without** any processing applied, meaning you will usually get a frame you need to decode yourself.
     /// # Errors
-    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureBackendTrait::open_stream()) has not been called yet, this will error.
+    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet, this will error.
     fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError>;
 
+    /// Gets the [`StreamStats`] of the backend. Backends only need to fill in the dropped frame counters they can observe;
+    /// the default reports nothing dropped.
+    fn stream_stats(&self) -> StreamStats {
+        StreamStats::default()
+    }
+
+    /// Stops delivering frames, while keeping the device open and configured so that [`resume_stream()`](CaptureDevice::resume_stream)
+    /// is fast. The default implementation calls [`stop_stream()`](CaptureDevice::stop_stream).
+    /// # Errors
+    /// If the backend fails to pause the stream, this will error.
+    fn pause_stream(&mut self) -> Result<(), NokhwaError> {
+        self.stop_stream()
+    }
+
+    /// Resumes a stream paused with [`pause_stream()`](CaptureDevice::pause_stream). The default implementation calls
+    /// [`open_stream()`](CaptureDevice::open_stream).
+    /// # Errors
+    /// If the backend fails to resume the stream, this will error.
+    fn resume_stream(&mut self) -> Result<(), NokhwaError> {
+        self.open_stream()
+    }
+
+    /// Mirrors frames horizontally in the backend, before they are delivered.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`], which tells callers to flip frames themselves.
+    /// # Errors
+    /// If the backend cannot mirror frames, or fails to, this will error.
+    fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+        let _ = mirrored;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Crops frames to `crop` in the backend, or removes the crop if it is `None`. Frames, and the [`CameraFormat`], have the
+    /// size of the crop afterwards.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`], which tells callers to crop frames themselves.
+    /// # Errors
+    /// If the backend cannot crop frames, or fails to, this will error.
+    fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+        let _ = crop;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Sets the exposure to automatic, or to a fixed exposure time.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot set its exposure this way, or fails to, this will error.
+    fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+        let _ = exposure;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Sets the white balance to automatic, or to a fixed color temperature.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot set its white balance this way, or fails to, this will error.
+    fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+        let _ = white_balance;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Sets the focus to automatic, or to a fixed lens position.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot set its focus this way, or fails to, this will error.
+    fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+        let _ = focus;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Sets the brightness, from `0.0` (the lowest the camera supports) to `1.0` (the highest). It is clamped to that.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera has no brightness control, or fails to set it, this will error.
+    fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+        let _ = brightness;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Makes automatic focus focus on `roi`, a region of the frame in pixels of the current resolution, e.g. where
+    /// the user tapped.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot focus on a region, `roi` is not inside the frame, or setting it fails, this will error.
+    fn set_focus_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        let _ = roi;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Makes automatic exposure meter `roi`, a region of the frame in pixels of the current resolution.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot meter a region, `roi` is not inside the frame, or setting it fails, this will error.
+    fn set_exposure_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        let _ = roi;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Sends `query` to the control `selector` of the UVC extension unit `unit`, for vendor specific features that
+    /// have no [`KnownCameraControl`]. `data` is written to the control for [`XuQuery::SetCur`], and read into for the
+    /// other queries. It has to be exactly as long as the control, see [`XuQuery::GetLen`].
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the backend cannot talk to extension units, or the camera rejects the query, this will error.
+    fn xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: XuQuery,
+        data: &mut [u8],
+    ) -> Result<(), NokhwaError> {
+        let _ = (unit, selector, query, data);
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Gets what pan, tilt and zoom the camera can do, see [`PtzCapabilities`].
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the backend has no pan, tilt and zoom API, or fails to query the camera, this will error.
+    fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Zooms to `zoom`, in the units of [`PtzCapabilities::zoom()`].
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot zoom, or fails to, this will error.
+    fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+        let _ = zoom;
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Zooms to `zoom` smoothly, at `rate`. The unit of `rate` depends on the backend: `AVFoundation`, the only one
+    /// that ramps, takes powers of two per second.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot zoom at a rate, or fails to, this will error.
+    fn ramp_zoom(&mut self, zoom: f64, rate: f64) -> Result<(), NokhwaError> {
+        let _ = (zoom, rate);
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Points the camera at `pan` and `tilt` degrees.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot pan and tilt, or fails to, this will error.
+    fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        let _ = (pan, tilt);
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Moves the camera by `pan` and `tilt` degrees from where it is pointing.
+    /// The default implementation returns [`NokhwaError::UnsupportedOperationError`].
+    /// # Errors
+    /// If the camera cannot pan and tilt by an offset, or fails to, this will error.
+    fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        let _ = (pan, tilt);
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    /// Takes the next [`SessionEvent`] of the camera, such as [`SessionEvent::ControlChanged`] when a control was
+    /// changed by something other than this camera, or `None` if there is none. This never blocks.
+    /// The default implementation returns `None`, for backends that report no events.
+    fn poll_event(&mut self) -> Option<SessionEvent> {
+        None
+    }
+
     /// Will drop the stream.
     /// # Errors
     /// Please check the `Quirks` section of each backend.
     fn stop_stream(&mut self) -> Result<(), NokhwaError>;
 }
 
-impl<T> From<T> for Box<dyn CaptureBackendTrait>
+// `Camera` boxes its backend, so this fails to build if a change makes the trait not object safe.
+const _: Option<&dyn CaptureDevice> = None;
+
+/// The old name of [`CaptureDevice`]. Every [`CaptureDevice`] implements it, so backends should implement
+/// [`CaptureDevice`] instead.
+#[deprecated(since = "0.11.0", note = "please use `CaptureDevice` instead.")]
+pub trait CaptureBackendTrait: CaptureDevice {}
+
+#[allow(deprecated)]
+impl<T: CaptureDevice + ?Sized> CaptureBackendTrait for T {}
+
+impl<T> From<T> for Box<dyn CaptureDevice>
 where
-    T: CaptureBackendTrait + 'static,
+    T: CaptureDevice + 'static,
 {
     fn from(capbackend: T) -> Self {
         Box::new(capbackend)
     }
 }
 
-pub trait VirtualBackendTrait {}
+/// This trait is for creating [`CaptureDevice`]s of a backend that is not built into `nokhwa`, such as a
+/// machine-vision SDK. Register it under an [`ApiBackend::Custom`] name with `nokhwa::register_backend()`, and `nokhwa`'s
+/// `Camera` and `query()` will use it like any other backend.
+pub trait CaptureBackendFactory: Send + Sync {
+    /// Query the system for a list of devices this backend can open.
+    /// # Errors
+    /// If the devices cannot be listed, this will error.
+    fn query(&self) -> Result<Vec<CameraInfo>, NokhwaError>;
+
+    /// Open the device at `index`, using `format` to pick its [`CameraFormat`].
+    /// # Errors
+    /// If the device cannot be opened or the format cannot be fulfilled, this will error.
+    fn create(
+        &self,
+        index: &CameraIndex,
+        format: RequestedFormat,
+    ) -> Result<Box<dyn CaptureDevice>, NokhwaError>;
+}
+
+/// This trait is for any backend that allows you to push frames into a virtual camera device (e.g. `v4l2loopback`),
+/// so that other applications on the system can consume them as if they were a real webcam.
+pub trait VirtualBackendTrait {
+    /// Returns the current backend used.
+    fn backend(&self) -> ApiBackend;
+
+    /// Gets the [`CameraFormat`] that the virtual device is advertising to its consumers.
+    fn camera_format(&self) -> CameraFormat;
+
+    /// Will set the [`CameraFormat`] the virtual device advertises.
+    /// # Errors
+    /// If the device rejects the new format (e.g. a consumer is already attached), this will error.
+    fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError>;
+
+    /// Writes a single frame to the virtual device. The frame's [`Resolution`] and [`FourCC`] must match the
+    /// current [`CameraFormat`].
+    /// # Errors
+    /// If the frame does not match the current format or the device fails to accept the data, this will error.
+    fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError>;
+}
+
+impl<T> From<T> for Box<dyn VirtualBackendTrait>
+where
+    T: VirtualBackendTrait + 'static,
+{
+    fn from(virtbackend: T) -> Self {
+        Box::new(virtbackend)
+    }
+}
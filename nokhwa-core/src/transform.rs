@@ -0,0 +1,475 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rotating, flipping, cropping and scaling frames, see [`FrameTransformer`].
+
+use crate::{
+    buffer::FrameBuffer,
+    conversion::{convert_into, RgbDestination, RgbFormat},
+    error::NokhwaError,
+    pixel_format::{KnownFrameFormat, BGRA, GRAY, RAWRGB},
+    types::{Rect, Resolution, Rotation},
+};
+use four_cc::FourCC;
+use std::{
+    borrow::Cow,
+    f64::consts::PI,
+    fmt::{Display, Formatter},
+};
+
+/// How [`Transform::Scale`] samples the frame.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScaleFilter {
+    /// Takes the closest pixel. This is the fastest, but blocky, and skips pixels when shrinking.
+    Nearest,
+    /// Interpolates between the closest pixels, and averages all pixels that are covered when shrinking.
+    #[default]
+    Bilinear,
+    /// A windowed sinc over 3 pixels on either side. This is the sharpest, and the slowest.
+    Lanczos3,
+}
+
+impl ScaleFilter {
+    fn support(self) -> f64 {
+        match self {
+            ScaleFilter::Nearest => 0.5,
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, distance: f64) -> f64 {
+        let distance = distance.abs();
+        match self {
+            ScaleFilter::Nearest => f64::from(u8::from(distance < 0.5)),
+            ScaleFilter::Bilinear => (1.0 - distance).max(0.0),
+            ScaleFilter::Lanczos3 if distance < 3.0 => sinc(distance) * sinc(distance / 3.0),
+            ScaleFilter::Lanczos3 => 0.0,
+        }
+    }
+}
+
+impl Display for ScaleFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// One step of a [`FrameTransformer`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Transform {
+    /// Rotates the frame clockwise.
+    Rotate(Rotation),
+    /// Flips the frame horizontally, mirroring it.
+    FlipHorizontal,
+    /// Flips the frame vertically.
+    FlipVertical,
+    /// Crops the frame to the rectangle, which has to fit in the frame as it is at this step.
+    Crop(Rect),
+    /// Scales the frame to the resolution.
+    Scale(Resolution, ScaleFilter),
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transform::Rotate(rotation) => write!(f, "Rotate {rotation}"),
+            Transform::FlipHorizontal => write!(f, "Flip horizontally"),
+            Transform::FlipVertical => write!(f, "Flip vertically"),
+            Transform::Crop(rect) => write!(f, "Crop {rect}"),
+            Transform::Scale(resolution, filter) => write!(f, "Scale to {resolution} ({filter})"),
+        }
+    }
+}
+
+/// A list of [`Transform`]s that are applied to a frame in order, e.g. to turn the frames of a camera that is mounted
+/// sideways upright and scale them down.
+///
+/// [`GRAY`], [`RAWRGB`] and [`BGRA`] frames are transformed as they are. Frames in any other format are converted into
+/// [`RAWRGB`] with [`convert_into()`] first. If the first step is a crop, it is done before converting where
+/// [`FrameBuffer::cropped()`] can do it exactly, so the pixels that are cropped away are not converted.
+///
+/// A frame with a [pending mirror](FrameBuffer::mirror_pending) is flipped before the first step, so the steps see the frame
+/// the way it is shown. `nokhwa`'s `CallbackCamera::set_transformer()` applies a transformer to every frame of a camera.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct FrameTransformer {
+    steps: Vec<Transform>,
+}
+
+impl FrameTransformer {
+    /// Creates a transformer without any steps, which leaves frames as they are.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `step` after the existing steps.
+    #[must_use]
+    pub fn then(mut self, step: Transform) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Adds a [`Transform::Rotate`] step.
+    #[must_use]
+    pub fn rotate(self, rotation: Rotation) -> Self {
+        self.then(Transform::Rotate(rotation))
+    }
+
+    /// Adds a [`Transform::FlipHorizontal`] step.
+    #[must_use]
+    pub fn flip_horizontal(self) -> Self {
+        self.then(Transform::FlipHorizontal)
+    }
+
+    /// Adds a [`Transform::FlipVertical`] step.
+    #[must_use]
+    pub fn flip_vertical(self) -> Self {
+        self.then(Transform::FlipVertical)
+    }
+
+    /// Adds a [`Transform::Crop`] step.
+    #[must_use]
+    pub fn crop(self, rect: Rect) -> Self {
+        self.then(Transform::Crop(rect))
+    }
+
+    /// Adds a [`Transform::Scale`] step.
+    #[must_use]
+    pub fn scale(self, resolution: Resolution, filter: ScaleFilter) -> Self {
+        self.then(Transform::Scale(resolution, filter))
+    }
+
+    /// Get the steps, in the order they are applied.
+    #[must_use]
+    pub fn steps(&self) -> &[Transform] {
+        &self.steps
+    }
+
+    /// Checks if there are no steps.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Applies the steps to `frame`, returning a new [`FrameBuffer`] that keeps the timestamps and metadata of `frame`.
+    ///
+    /// The [`rotation()`](FrameBuffer::rotation) of the result includes any rotation steps, and it has no pending mirror.
+    /// If there is nothing to do, `frame` is cloned, which does not copy the data.
+    /// # Errors
+    /// This will error if the format of `frame` cannot be converted, its buffer is too small for its resolution, a crop does
+    /// not fit in the frame, or a scale is to an empty resolution.
+    pub fn apply(&self, frame: &FrameBuffer) -> Result<FrameBuffer, NokhwaError> {
+        if self.steps.is_empty() && !frame.mirror_pending() {
+            return Ok(frame.clone());
+        }
+
+        let mut steps = self.steps.as_slice();
+        let mut source = Cow::Borrowed(frame);
+        if let [Transform::Crop(rect), rest @ ..] = steps {
+            if let Some(cropped) = crop_unconverted(frame, *rect) {
+                source = Cow::Owned(cropped);
+                steps = rest;
+            }
+        }
+
+        let mut pixels = Pixels::from_frame(&source)?;
+        if frame.mirror_pending() {
+            pixels.flip_horizontal();
+        }
+        let mut rotation = frame.rotation();
+        for step in steps {
+            let error = |why: &str| NokhwaError::ProcessFrameError {
+                src: frame.source_frame_format(),
+                destination: step.to_string(),
+                error: why.to_string(),
+            };
+            match *step {
+                Transform::Rotate(by) => {
+                    pixels = pixels.rotated(by);
+                    rotation = rotation + by;
+                }
+                Transform::FlipHorizontal => pixels.flip_horizontal(),
+                Transform::FlipVertical => pixels.flip_vertical(),
+                Transform::Crop(rect) => {
+                    if !rect.fits_in(pixels.resolution) {
+                        return Err(error("Rectangle does not fit in the frame"));
+                    }
+                    pixels = pixels.cropped(rect);
+                }
+                Transform::Scale(resolution, filter) => {
+                    if resolution.width() == 0 || resolution.height() == 0 {
+                        return Err(error("Resolution is empty"));
+                    }
+                    pixels = pixels.scaled(resolution, filter);
+                }
+            }
+        }
+
+        Ok(frame
+            .with_pixels(pixels.resolution, pixels.data, pixels.format)
+            .with_rotation(rotation)
+            .with_pending_mirror(false))
+    }
+}
+
+/// Crops a frame that is not in a packed format with [`FrameBuffer::cropped()`], if that does not have to shrink `rect`.
+fn crop_unconverted(frame: &FrameBuffer, rect: Rect) -> Option<FrameBuffer> {
+    if packed_format(frame.known_frame_format()).is_some() || !rect.fits_in(frame.resolution()) {
+        return None;
+    }
+    // the crop is of the mirrored frame, the unconverted one is not mirrored yet
+    let rect = if frame.mirror_pending() {
+        Rect::new(
+            frame.width() - rect.x() - rect.width(),
+            rect.y(),
+            rect.width(),
+            rect.height(),
+        )
+    } else {
+        rect
+    };
+    frame
+        .cropped(rect)
+        .ok()
+        .filter(|cropped| cropped.resolution() == rect.resolution())
+}
+
+/// The [`FourCC`] and bytes per pixel of the formats that are transformed without converting them.
+fn packed_format(format: KnownFrameFormat) -> Option<(FourCC, usize)> {
+    match format {
+        KnownFrameFormat::Gray8 => Some((GRAY, 1)),
+        KnownFrameFormat::Rgb24 => Some((RAWRGB, 3)),
+        KnownFrameFormat::Bgra => Some((BGRA, 4)),
+        _ => None,
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// An unpadded frame in a packed 8-bit format.
+struct Pixels {
+    resolution: Resolution,
+    format: FourCC,
+    channels: usize,
+    data: Vec<u8>,
+}
+
+impl Pixels {
+    fn from_frame(frame: &FrameBuffer) -> Result<Self, NokhwaError> {
+        let resolution = frame.resolution();
+        let pixel_count = resolution.width() as usize * resolution.height() as usize;
+        let buffer = frame.unpadded_buffer()?;
+        if let Some((format, channels)) = packed_format(frame.known_frame_format()) {
+            let data = buffer.get(..pixel_count * channels).ok_or_else(|| {
+                NokhwaError::ProcessFrameError {
+                    src: frame.source_frame_format(),
+                    destination: "Transform".to_string(),
+                    error: "Buffer is smaller than an unpadded frame".to_string(),
+                }
+            })?;
+            return Ok(Pixels {
+                resolution,
+                format,
+                channels,
+                data: data.to_vec(),
+            });
+        }
+
+        let mut data = vec![0; pixel_count * 3];
+        convert_into(
+            resolution,
+            frame.source_frame_format(),
+            frame.color_space().unwrap_or_default(),
+            &buffer,
+            RgbDestination::new(&mut data, RgbFormat::Rgb24),
+        )?;
+        Ok(Pixels {
+            resolution,
+            format: RAWRGB,
+            channels: 3,
+            data,
+        })
+    }
+
+    fn width(&self) -> usize {
+        self.resolution.width() as usize
+    }
+
+    fn height(&self) -> usize {
+        self.resolution.height() as usize
+    }
+
+    fn rotated(&self, rotation: Rotation) -> Self {
+        let (width, height, channels) = (self.width(), self.height(), self.channels);
+        let resolution = match rotation {
+            Rotation::Deg0 | Rotation::Deg180 => self.resolution,
+            Rotation::Deg90 | Rotation::Deg270 => {
+                Resolution::new(self.resolution.height(), self.resolution.width())
+            }
+        };
+        let mut data = vec![0; self.data.len()];
+        let rotated_width = resolution.width() as usize;
+        for (y, row) in self.data.chunks_exact(width * channels).enumerate() {
+            for (x, pixel) in row.chunks_exact(channels).enumerate() {
+                let (to_x, to_y) = match rotation {
+                    Rotation::Deg0 => (x, y),
+                    Rotation::Deg90 => (height - 1 - y, x),
+                    Rotation::Deg180 => (width - 1 - x, height - 1 - y),
+                    Rotation::Deg270 => (y, width - 1 - x),
+                };
+                let start = (to_y * rotated_width + to_x) * channels;
+                data[start..start + channels].copy_from_slice(pixel);
+            }
+        }
+        Pixels {
+            resolution,
+            data,
+            ..*self
+        }
+    }
+
+    fn flip_horizontal(&mut self) {
+        let (row_len, channels) = (self.width() * self.channels, self.channels);
+        for row in self.data.chunks_exact_mut(row_len) {
+            // reversing the bytes of the row reverses the pixels, and the channels of each pixel with them
+            row.reverse();
+            for pixel in row.chunks_exact_mut(channels) {
+                pixel.reverse();
+            }
+        }
+    }
+
+    fn flip_vertical(&mut self) {
+        let row_len = self.width() * self.channels;
+        self.data = self
+            .data
+            .chunks_exact(row_len)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+    }
+
+    fn cropped(&self, rect: Rect) -> Self {
+        let channels = self.channels;
+        let columns = rect.x() as usize * channels..(rect.x() + rect.width()) as usize * channels;
+        let data = self
+            .data
+            .chunks_exact(self.width() * channels)
+            .skip(rect.y() as usize)
+            .take(rect.height() as usize)
+            .flat_map(|row| &row[columns.clone()])
+            .copied()
+            .collect();
+        Pixels {
+            resolution: rect.resolution(),
+            data,
+            ..*self
+        }
+    }
+
+    /// Scales with a separable filter, horizontally into floats and then vertically back into bytes.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn scaled(&self, resolution: Resolution, filter: ScaleFilter) -> Self {
+        let channels = self.channels;
+        let (width, to_width) = (self.width(), resolution.width() as usize);
+        let columns = taps(self.resolution.width(), resolution.width(), filter);
+        let rows = taps(self.resolution.height(), resolution.height(), filter);
+
+        let mut horizontal = vec![0.0_f32; to_width * self.height() * channels];
+        for (row, out) in self
+            .data
+            .chunks_exact(width * channels)
+            .zip(horizontal.chunks_exact_mut(to_width * channels))
+        {
+            for (tap, out) in columns.iter().zip(out.chunks_exact_mut(channels)) {
+                for (x, weight) in (tap.start..).zip(&tap.weights) {
+                    for (out, value) in out.iter_mut().zip(&row[x * channels..(x + 1) * channels]) {
+                        *out += f32::from(*value) * weight;
+                    }
+                }
+            }
+        }
+
+        let row_len = to_width * channels;
+        let mut data = vec![0; row_len * resolution.height() as usize];
+        for (tap, out) in rows.iter().zip(data.chunks_exact_mut(row_len)) {
+            for (i, out) in out.iter_mut().enumerate() {
+                let value: f32 = (tap.start..)
+                    .zip(&tap.weights)
+                    .map(|(y, weight)| horizontal[y * row_len + i] * weight)
+                    .sum();
+                *out = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        Pixels {
+            resolution,
+            data,
+            ..*self
+        }
+    }
+}
+
+/// The source pixels an output pixel is made of, starting at `start`, and how much each of them counts.
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Works out the [`Taps`] of each of the `to` output pixels along an axis of `from` pixels.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn taps(from: u32, to: u32, filter: ScaleFilter) -> Vec<Taps> {
+    let scale = f64::from(from) / f64::from(to);
+    // when shrinking, the filter is stretched to cover every source pixel
+    let stretch = if filter == ScaleFilter::Nearest {
+        1.0
+    } else {
+        scale.max(1.0)
+    };
+    let support = filter.support() * stretch;
+    (0..to)
+        .map(|i| {
+            let center = (f64::from(i) + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as u32;
+            let end = ((center + support).ceil() as u32).min(from);
+            let mut weights: Vec<f64> = (start..end)
+                .map(|j| filter.weight((f64::from(j) + 0.5 - center) / stretch))
+                .collect();
+            let total: f64 = weights.iter().sum();
+            if total > 0.0 {
+                for weight in &mut weights {
+                    *weight /= total;
+                }
+            } else {
+                // only when nearest lands exactly between two pixels, take the first
+                for (j, weight) in weights.iter_mut().enumerate() {
+                    *weight = f64::from(u8::from(j == 0));
+                }
+            }
+            Taps {
+                start: start as usize,
+                weights: weights.into_iter().map(|weight| weight as f32).collect(),
+            }
+        })
+        .collect()
+}
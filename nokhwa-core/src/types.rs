@@ -1,10 +1,15 @@
-use crate::{error::NokhwaError, pixel_format::MJPEG};
+use crate::{
+    error::NokhwaError,
+    pixel_format::{KnownFrameFormat, MJPEG},
+};
 use four_cc::FourCC;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     fmt::{Display, Formatter},
+    ops::{Add, Sub},
+    time::Duration,
 };
 
 /// Tells the init function what camera format to pick.
@@ -13,8 +18,16 @@ use std::{
 /// - `HighestResolution(Option<u32>)`: Pick the highest [`Resolution`] for the given framerate (the `Option<u32>`). If its `None`, it will pick the highest possible [`Resolution`]
 /// - `HighestFrameRate(Option<Resolution>)`: Pick the highest frame rate for the given [`Resolution`] (the `Option<Resolution>`). If it is `None`, it will pick the highest possinle framerate.
 /// - `Exact`: Pick the exact [`CameraFormat`] provided.
-/// - `Closest`: Pick the closest [`CameraFormat`] provided in order of [`FrameFormat`], [`Resolution`], and FPS. Note that if the [`FrameFormat`] does not exist, this will fail to resolve.
+/// - `Closest`: Pick the closest [`CameraFormat`] provided in order of [`FrameFormat`], [`Resolution`], and FPS. Resolutions with the same aspect ratio
+///   are preferred over closer ones with a different aspect ratio. Note that if the [`FrameFormat`] does not exist, this will fail to resolve.
+/// - `Range`: Pick the highest [`Resolution`] between `min_resolution` and `max_resolution` (inclusive, on both axes) running at `min_frame_rate`
+///   or more, then the highest frame rate of those.
 /// - `None`: Pick a random [`CameraFormat`]
+///
+/// Frame rates given as integers match formats by their rounded frame rate, so `30` also matches 29.97 FPS
+/// (30000/1001) formats. `Exact` and `Closest` use the exact [`FrameRate`] of their [`CameraFormat`].
+///
+/// If the request cannot be fulfilled, [`RequestedFormat::with_fallback`] can be used to try other requests.
 #[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[derive(Default)]
@@ -23,7 +36,13 @@ pub enum RequestedFormatType {
     AbsoluteHighestFrameRate,
     HighestResolution(Resolution),
     HighestFrameRate(u32),
+    Exact(CameraFormat),
     Closest(CameraFormat),
+    Range {
+        min_resolution: Resolution,
+        max_resolution: Resolution,
+        min_frame_rate: u32,
+    },
     #[default]
     None,
 }
@@ -37,34 +56,111 @@ impl Display for RequestedFormatType {
 /// A request to the camera for a valid [`CameraFormat`]
 #[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct RequestedFormat {
-    requested_format: RequestedFormatType,
+    requested: RequestedFormatType,
+    fallbacks: [Option<RequestedFormatType>; RequestedFormat::MAX_FALLBACKS],
+    fourccs: [Option<FourCC>; RequestedFormat::MAX_FOURCCS],
 }
 
 impl RequestedFormat {
+    /// The maximum amount of fallbacks a [`RequestedFormat`] can hold.
+    pub const MAX_FALLBACKS: usize = 4;
+
+    /// The maximum amount of [`FourCC`]s a [`RequestedFormat`] can be restricted to.
+    pub const MAX_FOURCCS: usize = 8;
+
     /// Creates a new [`RequestedFormat`] by using the [`RequestedFormatType`] and getting the [`FrameFormat`]
     /// constraints from a generic type.
     #[must_use]
     pub fn new(requested: RequestedFormatType) -> RequestedFormat {
         RequestedFormat {
-            requested_format: requested,
+            requested,
+            fallbacks: [None; RequestedFormat::MAX_FALLBACKS],
+            fourccs: [None; RequestedFormat::MAX_FOURCCS],
         }
     }
 
     /// Gets the [`RequestedFormatType`]
     #[must_use]
     pub fn from_camera_format(format: CameraFormat) -> RequestedFormat {
-        RequestedFormat {
-            requested_format: RequestedFormatType::Closest(format),
+        RequestedFormat::new(RequestedFormatType::Closest(format))
+    }
+
+    /// Adds a [`RequestedFormatType`] to try if the ones before it cannot be fulfilled. Fallbacks are tried in the order
+    /// they were added, e.g. `Exact`, then `Closest`, then `None` for anything at all.
+    ///
+    /// Fallbacks past [`MAX_FALLBACKS`](RequestedFormat::MAX_FALLBACKS) are ignored.
+    #[must_use]
+    pub fn with_fallback(mut self, fallback: RequestedFormatType) -> RequestedFormat {
+        if let Some(slot) = self.fallbacks.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(fallback);
+        }
+        self
+    }
+
+    /// Restricts the request to `fourccs`, in order of preference. For example, `[NV12, YUYV]` picks an `NV12` format if one fits
+    /// the request, otherwise a `YUYV` one, and never anything else (such as `MJPEG`).
+    ///
+    /// An empty list accepts any [`FourCC`], which is the default. [`FourCC`]s past [`MAX_FOURCCS`](RequestedFormat::MAX_FOURCCS) are ignored.
+    #[must_use]
+    pub fn with_fourccs(mut self, fourccs: &[FourCC]) -> RequestedFormat {
+        self.fourccs = [None; RequestedFormat::MAX_FOURCCS];
+        for (slot, fourcc) in self.fourccs.iter_mut().zip(fourccs) {
+            *slot = Some(*fourcc);
         }
+        self
     }
 
-    /// Fulfill the requested using a list of all available formats.
+    /// Gets the accepted [`FourCC`]s, in order of preference. If this is empty, any [`FourCC`] is accepted.
+    pub fn fourccs(&self) -> impl Iterator<Item = FourCC> + '_ {
+        self.fourccs.iter().flatten().copied()
+    }
+
+    /// Gets the [`RequestedFormatType`]
+    #[must_use]
+    pub fn requested_format(&self) -> RequestedFormatType {
+        self.requested
+    }
+
+    /// Gets the fallback [`RequestedFormatType`]s, in the order they are tried.
+    pub fn fallbacks(&self) -> impl Iterator<Item = RequestedFormatType> + '_ {
+        self.fallbacks.iter().flatten().copied()
+    }
+
+    /// Fulfill the requested using a list of all available formats. If the requested format cannot be fulfilled, the
+    /// [`fallbacks()`](RequestedFormat::fallbacks) are tried in order. Each of them tries the accepted [`fourccs()`](RequestedFormat::fourccs)
+    /// in order of preference.
     ///
     /// See [`RequestedFormatType`] for more details.
     #[must_use]
-    #[allow(clippy::too_many_lines)]
     pub fn fulfill(&self, all_formats: &[CameraFormat]) -> Option<CameraFormat> {
-        match self.requested_format {
+        let by_preference = if self.fourccs().next().is_none() {
+            vec![all_formats.to_vec()]
+        } else {
+            self.fourccs()
+                .map(|fourcc| {
+                    all_formats
+                        .iter()
+                        .filter(|x| x.format() == fourcc)
+                        .copied()
+                        .collect::<Vec<CameraFormat>>()
+                })
+                .collect()
+        };
+        std::iter::once(self.requested)
+            .chain(self.fallbacks())
+            .find_map(|requested| {
+                by_preference
+                    .iter()
+                    .find_map(|formats| Self::fulfill_one(requested, formats))
+            })
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn fulfill_one(
+        requested: RequestedFormatType,
+        all_formats: &[CameraFormat],
+    ) -> Option<CameraFormat> {
+        match requested {
             RequestedFormatType::AbsoluteHighestResolution => {
                 let mut formats = all_formats.to_vec();
                 formats.sort_by_key(CameraFormat::resolution);
@@ -73,16 +169,16 @@ impl RequestedFormat {
                     .into_iter()
                     .filter(|fmt| fmt.resolution() == resolution.resolution())
                     .collect::<Vec<CameraFormat>>();
-                format_resolutions.sort_by_key(CameraFormat::frame_rate);
+                format_resolutions.sort_by_key(CameraFormat::exact_frame_rate);
                 format_resolutions.last().copied()
             }
             RequestedFormatType::AbsoluteHighestFrameRate => {
                 let mut formats = all_formats.to_vec();
-                formats.sort_by_key(CameraFormat::frame_rate);
+                formats.sort_by_key(CameraFormat::exact_frame_rate);
                 let frame_rate = *formats.iter().last()?;
                 let mut format_framerates = formats
                     .into_iter()
-                    .filter(|fmt| fmt.frame_rate() == frame_rate.frame_rate())
+                    .filter(|fmt| fmt.exact_frame_rate() == frame_rate.exact_frame_rate())
                     .collect::<Vec<CameraFormat>>();
                 format_framerates.sort_by_key(CameraFormat::resolution);
                 format_framerates.last().copied()
@@ -106,7 +202,7 @@ impl RequestedFormat {
             RequestedFormatType::HighestFrameRate(fps) => {
                 let mut formats = all_formats
                     .iter()
-                    .filter(|x| x.frame_rate == fps)
+                    .filter(|x| x.frame_rate() == fps)
                     .copied()
                     .collect::<Vec<CameraFormat>>();
                 formats.sort_by(|a, b| a.resolution.cmp(&b.resolution));
@@ -119,6 +215,7 @@ impl RequestedFormat {
                     .filter(|x| x.resolution() == highest_res)
                     .last()
             }
+            RequestedFormatType::Exact(exact) => all_formats.iter().find(|x| **x == exact).copied(),
             #[allow(clippy::cast_possible_wrap)]
             RequestedFormatType::Closest(c) => {
                 let same_fourcc_formats = all_formats
@@ -133,34 +230,46 @@ impl RequestedFormat {
                         let x_diff = res.x() as i32 - c.resolution().x() as i32;
                         let y_diff = res.y() as i32 - c.resolution().y() as i32;
                         let dist_no_sqrt = (x_diff.abs()).pow(2) + (y_diff.abs()).pow(2);
-                        (dist_no_sqrt, res)
+                        (!res.same_aspect_ratio(c.resolution()), dist_no_sqrt, res)
                     })
-                    .collect::<Vec<(i32, Resolution)>>();
-                resolution_map.sort_by(|a, b| a.0.cmp(&b.0));
-                resolution_map.dedup_by(|a, b| a.0.eq(&b.0));
-                let resolution = resolution_map.first()?.1;
+                    .collect::<Vec<(bool, i32, Resolution)>>();
+                resolution_map
+                    .sort_by_key(|(mismatched_aspect, dist, _)| (*mismatched_aspect, *dist));
+                let resolution = resolution_map.first()?.2;
 
                 let frame_rates = all_formats
                     .iter()
                     .filter_map(|camera_format| {
-                        if camera_format.format() == c.format() && camera_format.resolution() == c.resolution() {
-                            return Some(camera_format.frame_rate());
+                        if camera_format.format() == c.format()
+                            && camera_format.resolution() == resolution
+                        {
+                            return Some(camera_format.exact_frame_rate());
                         }
                         None
                     })
-                    .collect::<Vec<u32>>();
+                    .collect::<Vec<FrameRate>>();
                 // sort FPSes
                 let mut framerate_map = frame_rates
                     .iter()
-                    .map(|x| {
-                        let abs = *x as i32 - c.frame_rate() as i32;
-                        (abs.unsigned_abs(), *x)
-                    })
-                    .collect::<Vec<(u32, u32)>>();
-                framerate_map.sort_by(|a, b| a.0.cmp(&b.0));
+                    .map(|x| ((x.as_f64() - c.exact_frame_rate().as_f64()).abs(), *x))
+                    .collect::<Vec<(f64, FrameRate)>>();
+                framerate_map.sort_by(|a, b| a.0.total_cmp(&b.0));
                 let frame_rate = framerate_map.first()?.1;
-                Some(CameraFormat::new(resolution, c.format(), frame_rate))
+                Some(CameraFormat::new_exact(resolution, c.format(), frame_rate))
             }
+            RequestedFormatType::Range {
+                min_resolution,
+                max_resolution,
+                min_frame_rate,
+            } => all_formats
+                .iter()
+                .filter(|x| {
+                    (min_resolution.width()..=max_resolution.width()).contains(&x.width())
+                        && (min_resolution.height()..=max_resolution.height()).contains(&x.height())
+                        && x.frame_rate() >= min_frame_rate
+                })
+                .max_by_key(|x| (x.resolution(), x.exact_frame_rate()))
+                .copied(),
             RequestedFormatType::None => all_formats.first().copied(),
         }
     }
@@ -310,6 +419,14 @@ impl Resolution {
     pub fn y(self) -> u32 {
         self.height_y
     }
+
+    /// Checks if this and `other` have the same aspect ratio, e.g. `1920x1080` and `1280x720`.
+    #[must_use]
+    #[cfg_attr(feature = "output-wasm", wasm_bindgen(skip))]
+    pub fn same_aspect_ratio(self, other: Resolution) -> bool {
+        u64::from(self.width_x) * u64::from(other.height_y)
+            == u64::from(other.width_x) * u64::from(self.height_y)
+    }
 }
 
 impl Display for Resolution {
@@ -334,20 +451,213 @@ impl Ord for Resolution {
     }
 }
 
+/// A rectangle of pixels in a frame, with its origin at the top left corner.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    /// Create a new [`Rect`] whose top left corner is at `x`, `y`.
+    #[must_use]
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Get the left edge of the rectangle.
+    #[must_use]
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// Get the top edge of the rectangle.
+    #[must_use]
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Get the width of the rectangle.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the rectangle.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the size of the rectangle as a [`Resolution`].
+    #[must_use]
+    pub fn resolution(&self) -> Resolution {
+        Resolution::new(self.width, self.height)
+    }
+
+    /// Checks if the rectangle is not empty and lies entirely inside a frame of `resolution`.
+    #[must_use]
+    pub fn fits_in(&self, resolution: Resolution) -> bool {
+        self.width > 0
+            && self.height > 0
+            && u64::from(self.x) + u64::from(self.width) <= u64::from(resolution.width())
+            && u64::from(self.y) + u64::from(self.height) <= u64::from(resolution.height())
+    }
+
+    /// Shrinks the rectangle so that every edge is on a multiple of `horizontal` and `vertical` pixels, as chroma
+    /// subsampled and Bayer formats need.
+    #[must_use]
+    pub fn aligned(&self, horizontal: u32, vertical: u32) -> Self {
+        let align = |start: u32, len: u32, step: u32| {
+            let step = step.max(1);
+            let aligned_start = start.div_ceil(step) * step;
+            let end = start.saturating_add(len) / step * step;
+            (aligned_start, end.saturating_sub(aligned_start))
+        };
+        let (x, width) = align(self.x, self.width, horizontal);
+        let (y, height) = align(self.y, self.height, vertical);
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl Display for Rect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {})",
+            self.width, self.height, self.x, self.y
+        )
+    }
+}
+
+/// A frame rate, as a number of frames per a number of seconds, e.g. 30000/1001 for 29.97 FPS.
+///
+/// It is kept reduced, so that 60/2 and 30/1 are equal.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct FrameRate {
+    numerator: u32,
+    denominator: u32,
+}
+
+impl FrameRate {
+    /// Creates a [`FrameRate`] of `numerator` frames every `denominator` seconds. A `denominator` of 0 is taken as 1.
+    #[must_use]
+    pub const fn new(numerator: u32, denominator: u32) -> Self {
+        let denominator = if denominator == 0 { 1 } else { denominator };
+        let (mut a, mut b) = (numerator, denominator);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        FrameRate {
+            numerator: numerator / a,
+            denominator: denominator / a,
+        }
+    }
+
+    /// Creates a [`FrameRate`] of a whole number of frames per second.
+    #[must_use]
+    pub const fn from_fps(fps: u32) -> Self {
+        FrameRate {
+            numerator: fps,
+            denominator: 1,
+        }
+    }
+
+    #[must_use]
+    pub const fn numerator(&self) -> u32 {
+        self.numerator
+    }
+
+    #[must_use]
+    pub const fn denominator(&self) -> u32 {
+        self.denominator
+    }
+
+    /// Gets the frame rate rounded to whole frames per second, e.g. 30 for 29.97.
+    #[must_use]
+    pub fn fps(&self) -> u32 {
+        let rounded = (u64::from(self.numerator) + u64::from(self.denominator) / 2)
+            / u64::from(self.denominator);
+        u32::try_from(rounded).unwrap_or(u32::MAX)
+    }
+
+    /// Gets the frame rate in frames per second.
+    #[must_use]
+    pub fn as_f64(&self) -> f64 {
+        f64::from(self.numerator) / f64::from(self.denominator)
+    }
+
+    /// Whether this is a whole number of frames per second.
+    #[must_use]
+    pub fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        FrameRate::from_fps(30)
+    }
+}
+
+impl From<u32> for FrameRate {
+    fn from(fps: u32) -> Self {
+        FrameRate::from_fps(fps)
+    }
+}
+
+impl PartialOrd for FrameRate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FrameRate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (u64::from(self.numerator) * u64::from(other.denominator))
+            .cmp(&(u64::from(other.numerator) * u64::from(self.denominator)))
+    }
+}
+
+impl Display for FrameRate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{:.2}", self.as_f64())
+        }
+    }
+}
+
 /// This is a convenience struct that holds all information about the format of a webcam stream.
-/// It consists of a [`Resolution`], [`FrameFormat`], and a frame rate(u8).
+/// It consists of a [`Resolution`], [`FrameFormat`], and a [`FrameRate`].
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct CameraFormat {
     resolution: Resolution,
     format: FourCC,
-    frame_rate: u32,
+    frame_rate: FrameRate,
 }
 
 impl CameraFormat {
-    /// Construct a new [`CameraFormat`]
+    /// Construct a new [`CameraFormat`] with a whole number of frames per second.
     #[must_use]
     pub fn new(resolution: Resolution, format: FourCC, frame_rate: u32) -> Self {
+        CameraFormat::new_exact(resolution, format, FrameRate::from_fps(frame_rate))
+    }
+
+    /// Construct a new [`CameraFormat`] with an exact [`FrameRate`], such as 29.97 FPS.
+    #[must_use]
+    pub fn new_exact(resolution: Resolution, format: FourCC, frame_rate: FrameRate) -> Self {
         CameraFormat {
             resolution,
             format,
@@ -358,14 +668,14 @@ impl CameraFormat {
     /// [`CameraFormat::new()`], but raw.
     #[must_use]
     pub fn new_from(res_x: u32, res_y: u32, format: FourCC, fps: u32) -> Self {
-        CameraFormat {
-            resolution: Resolution {
+        CameraFormat::new(
+            Resolution {
                 width_x: res_x,
                 height_y: res_y,
             },
             format,
-            frame_rate: fps,
-        }
+            fps,
+        )
     }
 
     /// Get the resolution of the current [`CameraFormat`]
@@ -391,14 +701,26 @@ impl CameraFormat {
         self.resolution = resolution;
     }
 
-    /// Get the frame rate of the current [`CameraFormat`]
+    /// Get the frame rate of the current [`CameraFormat`], rounded to whole frames per second. See
+    /// [`exact_frame_rate()`](CameraFormat::exact_frame_rate) for e.g. 29.97 FPS.
     #[must_use]
     pub fn frame_rate(&self) -> u32 {
+        self.frame_rate.fps()
+    }
+
+    /// Get the exact [`FrameRate`] of the current [`CameraFormat`].
+    #[must_use]
+    pub fn exact_frame_rate(&self) -> FrameRate {
         self.frame_rate
     }
 
-    /// Set the [`CameraFormat`]'s frame rate.
+    /// Set the [`CameraFormat`]'s frame rate, as a whole number of frames per second.
     pub fn set_frame_rate(&mut self, frame_rate: u32) {
+        self.frame_rate = FrameRate::from_fps(frame_rate);
+    }
+
+    /// Set the [`CameraFormat`]'s exact [`FrameRate`].
+    pub fn set_exact_frame_rate(&mut self, frame_rate: FrameRate) {
         self.frame_rate = frame_rate;
     }
 
@@ -408,6 +730,12 @@ impl CameraFormat {
         self.format
     }
 
+    /// Get the [`CameraFormat`]'s format as a [`KnownFrameFormat`].
+    #[must_use]
+    pub fn known_format(&self) -> KnownFrameFormat {
+        KnownFrameFormat::from(self.format)
+    }
+
     /// Set the [`CameraFormat`]'s format.
     pub fn set_format(&mut self, format: FourCC) {
         self.format = format;
@@ -419,7 +747,7 @@ impl Default for CameraFormat {
         CameraFormat {
             resolution: Resolution::new(640, 480),
             format: MJPEG,
-            frame_rate: 30,
+            frame_rate: FrameRate::from_fps(30),
         }
     }
 }
@@ -435,8 +763,16 @@ impl Display for CameraFormat {
 }
 
 /// Information about a Camera e.g. its name.
-/// `description` amd `misc` may contain information that may differ from backend to backend. Refer to each backend for details.
-/// `index` is a camera's index given to it by (usually) the OS usually in the order it is known to the system.
+/// `manufacturer`, `model`, `device_type` and `position` may contain information that may differ from backend to backend. Refer to each backend for details.
+///
+/// The `unique_id` stays the same across reboots (and, where the OS allows it, re-plugging), unlike indexes which change whenever
+/// the OS reorders devices. Pass it to [`CameraIndex::String`] to open the same camera again.
+/// # Unique IDs
+/// - `Video4Linux`: The `/dev/v4l/by-id` link of the device node, falling back to the `/dev/v4l/by-path` link and then the node itself (e.g. `/dev/video0`).
+///   The `by-id` link is derived from the USB serial number, so it follows the camera between ports; `by-path` follows the port instead.
+/// - `Media Foundation`: The device symbolic link, which is derived from the device instance path. For the infrared and
+///   depth streams of sensor cameras, this is followed by `|` and the index of the stream.
+/// - `AVFoundation`: The `uniqueID` of the `AVCaptureDevice`.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
 #[cfg_attr(feature = "output-wasm", wasm_bindgen)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -447,6 +783,14 @@ pub struct CameraInfo {
     model: Option<String>,
     device_type: Option<String>,
     position: Option<String>,
+    sensor_orientation: Rotation,
+    continuity_camera: Option<ContinuityCamera>,
+    sensor_type: SensorType,
+    availability: CameraAvailability,
+    usb_device: Option<UsbDevice>,
+    bus_path: Option<String>,
+    node_kind: NodeKind,
+    camera_position: CameraPosition,
 }
 
 #[cfg_attr(feature = "output-wasm", wasm_bindgen(js_class = CameraInfo))]
@@ -471,6 +815,14 @@ impl CameraInfo {
             model: Some(model.to_string()),
             device_type: Some(device_type.to_string()),
             position: Some(position.to_string()),
+            sensor_orientation: Rotation::Deg0,
+            continuity_camera: None,
+            sensor_type: SensorType::Color,
+            availability: CameraAvailability::Unknown,
+            usb_device: None,
+            bus_path: None,
+            node_kind: NodeKind::Capture,
+            camera_position: CameraPosition::Unknown,
         }
     }
 
@@ -487,6 +839,7 @@ impl CameraInfo {
         self.name.clone()
     }
 
+    /// Get the stable, backend specific unique ID of the device. See [`CameraInfo`] for what it is on each backend.
     #[must_use]
     pub fn unique_id(&self) -> String {
         self.unique_id.clone()
@@ -513,93 +866,417 @@ impl CameraInfo {
     }
 }
 
-impl Display for CameraInfo {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Name: {} ({}) Manufacturer: {:?}, Model: {:?}, {:?}",
-            self.name, self.unique_id, self.manufacturer, self.model, self.position
-        )
+impl CameraInfo {
+    /// Sets the [`sensor_orientation()`](CameraInfo::sensor_orientation) of the device.
+    #[must_use]
+    pub fn with_sensor_orientation(mut self, sensor_orientation: Rotation) -> Self {
+        self.sensor_orientation = sensor_orientation;
+        self
     }
-}
 
-/// The list of known camera controls to the library. <br>
-/// These can control the picture brightness, etc. <br>
-/// Note that not all backends/devices support all these. Run [`supported_camera_controls()`](crate::traits::CaptureBackendTrait::camera_controls) to see which ones can be set.
-#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
-#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-pub enum KnownCameraControl {
-    Brightness,
-    Contrast,
-    Hue,
-    Saturation,
-    Sharpness,
-    Gamma,
-    WhiteBalance,
-    BacklightComp,
-    Gain,
-    Pan,
-    Tilt,
-    Zoom,
-    Exposure,
-    Iris,
-    Focus,
-    /// Other camera control. Listed is the ID.
-    /// Wasteful, however is needed for a unified API across Windows, Linux, and `MacOSX` due to Microsoft's usage of GUIDs.
+    /// Get how far the image sensor is rotated clockwise from the natural orientation of the device it is built into.
+    /// Frames need to be rotated clockwise by this much to appear upright while the device is held in its natural orientation.
     ///
-    /// THIS SHOULD ONLY BE USED WHEN YOU KNOW THE PLATFORM THAT YOU ARE RUNNING ON.
-    Other(u128),
-}
+    /// This is [`Rotation::Deg0`] for external cameras and backends that do not report it.
+    /// # Quirks
+    /// - `Video4Linux`: The `V4L2_CID_CAMERA_SENSOR_ROTATION` control, usually only set for sensors described by the device tree.
+    /// - `Media Foundation`: The `EnclosureLocation` rotation of the device.
+    /// - `AVFoundation`: On iOS, built in cameras deliver landscape frames, so this is [`Rotation::Deg90`]. On macOS it is always [`Rotation::Deg0`].
+    #[must_use]
+    pub fn sensor_orientation(&self) -> Rotation {
+        self.sensor_orientation
+    }
 
-/// All camera controls in an array.
-#[must_use]
-pub const fn all_known_camera_controls() -> [KnownCameraControl; 15] {
-    [
-        KnownCameraControl::Brightness,
-        KnownCameraControl::Contrast,
-        KnownCameraControl::Hue,
-        KnownCameraControl::Saturation,
-        KnownCameraControl::Sharpness,
-        KnownCameraControl::Gamma,
-        KnownCameraControl::WhiteBalance,
-        KnownCameraControl::BacklightComp,
-        KnownCameraControl::Gain,
-        KnownCameraControl::Pan,
-        KnownCameraControl::Tilt,
-        KnownCameraControl::Zoom,
-        KnownCameraControl::Exposure,
-        KnownCameraControl::Iris,
-        KnownCameraControl::Focus,
-    ]
-}
+    /// Sets the [`continuity_camera()`](CameraInfo::continuity_camera) details of the device.
+    #[must_use]
+    pub fn with_continuity_camera(mut self, continuity_camera: ContinuityCamera) -> Self {
+        self.continuity_camera = Some(continuity_camera);
+        self
+    }
 
-impl Display for KnownCameraControl {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", &self)
+    /// Get the details of an iPhone used as a Continuity Camera, or `None` if the device is not one.
+    /// # Quirks
+    /// Only `AVFoundation` on macOS 13 and later reports these.
+    #[must_use]
+    pub fn continuity_camera(&self) -> Option<&ContinuityCamera> {
+        self.continuity_camera.as_ref()
+    }
+
+    /// Sets the [`sensor_type()`](CameraInfo::sensor_type) of the device.
+    #[must_use]
+    pub fn with_sensor_type(mut self, sensor_type: SensorType) -> Self {
+        self.sensor_type = sensor_type;
+        self
+    }
+
+    /// Get what the sensor of the device captures. This is [`SensorType::Color`] for regular cameras.
+    /// # Quirks
+    /// - `Media Foundation`: The infrared and depth streams of sensor groups, such as those of Windows Hello cameras, are
+    ///   listed as devices of their own.
+    /// - Other backends only list [`SensorType::Color`] devices.
+    #[must_use]
+    pub fn sensor_type(&self) -> SensorType {
+        self.sensor_type
+    }
+
+    /// Sets the [`availability()`](CameraInfo::availability) of the device.
+    #[must_use]
+    pub fn with_availability(mut self, availability: CameraAvailability) -> Self {
+        self.availability = availability;
+        self
+    }
+
+    /// Get whether the device could be opened when it was queried, so that e.g. a camera blocked by the privacy
+    /// settings can be shown as such before trying to open it.
+    /// # Quirks
+    /// - `Media Foundation`: Windows only tracks whether any camera is in use, so while one is, all of them are
+    ///   [`CameraAvailability::InUse`].
+    /// - Other backends report [`CameraAvailability::Unknown`].
+    #[must_use]
+    pub fn availability(&self) -> CameraAvailability {
+        self.availability
+    }
+
+    /// Sets the [`usb_device()`](CameraInfo::usb_device) details of the device.
+    #[must_use]
+    pub fn with_usb_device(mut self, usb_device: UsbDevice) -> Self {
+        self.usb_device = Some(usb_device);
+        self
+    }
+
+    /// Get the IDs of the USB device the camera is, or `None` if it is not a USB camera or the backend does not know.
+    /// # Quirks
+    /// Only `Video4Linux` reports these, read from `udev`.
+    #[must_use]
+    pub fn usb_device(&self) -> Option<&UsbDevice> {
+        self.usb_device.as_ref()
+    }
+
+    /// Sets the [`bus_path()`](CameraInfo::bus_path) of the device.
+    #[must_use]
+    pub fn with_bus_path(mut self, bus_path: &str) -> Self {
+        self.bus_path = Some(bus_path.to_string());
+        self
+    }
+
+    /// Get where the device is plugged in, which stays the same for a port as cameras come and go.
+    /// # Quirks
+    /// - `Video4Linux`: The `ID_PATH` of the device in `udev`, such as `pci-0000:00:14.0-usb-0:2:1.0`, falling back to the
+    ///   bus info the driver reports.
+    /// - Other backends report `None`.
+    #[must_use]
+    pub fn bus_path(&self) -> Option<&str> {
+        self.bus_path.as_deref()
+    }
+
+    /// Sets the [`node_kind()`](CameraInfo::node_kind) of the device.
+    #[must_use]
+    pub fn with_node_kind(mut self, node_kind: NodeKind) -> Self {
+        self.node_kind = node_kind;
+        self
+    }
+
+    /// Get what the device node delivers. This is [`NodeKind::Capture`] for everything `query()` lists.
+    /// # Quirks
+    /// `Video4Linux` gives UVC cameras a second node for their metadata, which is only listed by
+    /// `nokhwa_bindings_linux::query_all_nodes()`.
+    #[must_use]
+    pub fn node_kind(&self) -> NodeKind {
+        self.node_kind
+    }
+
+    /// Sets the [`camera_position()`](CameraInfo::camera_position) of the device.
+    #[must_use]
+    pub fn with_camera_position(mut self, camera_position: CameraPosition) -> Self {
+        self.camera_position = camera_position;
+        self
+    }
+
+    /// Get which way the camera faces, or whether it is an external one. Unlike [`position()`](CameraInfo::position),
+    /// this means the same on every backend.
+    /// # Quirks
+    /// - `Video4Linux`: The `V4L2_CID_CAMERA_ORIENTATION` control, usually only set for sensors described by the device tree.
+    /// - `Media Foundation`: The panel of the `EnclosureLocation` of the device. Devices without one are external.
+    /// - `AVFoundation`: The `position` of the device, or [`CameraPosition::External`] for external device types.
+    #[must_use]
+    pub fn camera_position(&self) -> CameraPosition {
+        self.camera_position
+    }
+
+    /// Get the clockwise rotation to apply to frames so they appear upright on a display that is itself rotated
+    /// clockwise by `display_rotation` from the natural orientation of the device.
+    ///
+    /// This is correct for cameras facing away from the user. For a front facing camera with a mirrored preview,
+    /// use `sensor_orientation() + display_rotation` instead.
+    #[must_use]
+    pub fn suggested_rotation(&self, display_rotation: Rotation) -> Rotation {
+        self.sensor_orientation - display_rotation
     }
 }
 
-/// This tells you weather a [`KnownCameraControl`] is automatically managed by the OS/Driver
-/// or manually managed by you, the programmer.
-#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+/// What the sensor of a device captures, see [`CameraInfo::sensor_type()`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-pub enum KnownCameraControlFlag {
-    Automatic,
-    Manual,
-    Continuous,
-    ReadOnly,
-    WriteOnly,
-    Volatile,
-    Disabled,
+pub enum SensorType {
+    /// Visible light, like any regular camera.
+    #[default]
+    Color,
+    /// Infrared light, usually in [`GRAY`](crate::pixel_format::GRAY) or [`GRAY16`](crate::pixel_format::GRAY16).
+    Infrared,
+    /// Distance from the sensor, usually in [`DEPTH16`](crate::pixel_format::DEPTH16).
+    Depth,
 }
 
-impl Display for KnownCameraControlFlag {
+impl Display for SensorType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
     }
 }
 
-/// The values for a [`CameraControl`].
+/// Whether a device could be opened, see [`CameraInfo::availability()`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum CameraAvailability {
+    /// The backend does not know.
+    #[default]
+    Unknown,
+    /// Nothing is known to stop the device from being opened.
+    Available,
+    /// Another application is using the device. Opening it may fail with [`ErrorKind::DeviceBusy`](crate::error::ErrorKind::DeviceBusy).
+    InUse,
+    /// The camera privacy settings of the system do not allow the application to use cameras. Opening it fails
+    /// with [`ErrorKind::PermissionDenied`](crate::error::ErrorKind::PermissionDenied).
+    PrivacyBlocked,
+}
+
+impl Display for CameraAvailability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// What a device node delivers, see [`CameraInfo::node_kind()`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum NodeKind {
+    /// Frames, which is what a camera is.
+    #[default]
+    Capture,
+    /// Metadata about the frames of another node of the same camera, such as the UVC payload headers. It cannot be
+    /// opened as a camera.
+    Metadata,
+}
+
+impl Display for NodeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Where a camera is, see [`CameraInfo::camera_position()`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum CameraPosition {
+    /// The backend does not know.
+    #[default]
+    Unknown,
+    /// Built in, facing the user, like the camera above a laptop screen.
+    Front,
+    /// Built in, facing away from the user, like the main camera of a phone.
+    Back,
+    /// Not built in, like a USB webcam.
+    External,
+}
+
+impl Display for CameraPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The IDs of the USB device a camera is, see [`CameraInfo::usb_device()`].
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UsbDevice {
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: Option<String>,
+}
+
+impl UsbDevice {
+    /// Creates the details of a USB device.
+    #[must_use]
+    pub fn new(vendor_id: u16, product_id: u16, serial_number: Option<String>) -> Self {
+        UsbDevice {
+            vendor_id,
+            product_id,
+            serial_number,
+        }
+    }
+
+    /// The USB vendor ID (`idVendor`), e.g. `0x046d` for Logitech.
+    #[must_use]
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    /// The USB product ID (`idProduct`).
+    #[must_use]
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    /// The serial number of the device, if it has one. Many cheap cameras do not, or all share the same one.
+    #[must_use]
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+}
+
+impl Display for UsbDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)?;
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, " ({serial_number})")?;
+        }
+        Ok(())
+    }
+}
+
+/// An iPhone used as a Continuity Camera, see [`CameraInfo::continuity_camera()`].
+/// - `connected` - Whether the phone is connected and can stream right now.
+/// - `desk_view_id` - The unique ID of the companion Desk View camera, which shows the desk in front of the phone. It is listed by `query()` as a separate camera.
+///
+/// The system does not report the battery level of the phone.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ContinuityCamera {
+    connected: bool,
+    desk_view_id: Option<String>,
+}
+
+impl ContinuityCamera {
+    /// Creates the details of a Continuity Camera.
+    #[must_use]
+    pub fn new(connected: bool, desk_view_id: Option<String>) -> Self {
+        ContinuityCamera {
+            connected,
+            desk_view_id,
+        }
+    }
+
+    /// Whether the phone is connected and can stream right now.
+    #[must_use]
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The unique ID of the companion Desk View camera, if the phone has one.
+    #[must_use]
+    pub fn desk_view_id(&self) -> Option<&str> {
+        self.desk_view_id.as_deref()
+    }
+}
+
+impl Display for CameraInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Name: {} ({}) Manufacturer: {:?}, Model: {:?}, {:?}, Sensor Orientation: {}",
+            self.name,
+            self.unique_id,
+            self.manufacturer,
+            self.model,
+            self.position,
+            self.sensor_orientation
+        )
+    }
+}
+
+/// The list of known camera controls to the library. <br>
+/// These can control the picture brightness, etc. <br>
+/// Note that not all backends/devices support all these. Run [`supported_camera_controls()`](crate::traits::CaptureDevice::camera_controls) to see which ones can be set.
+#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum KnownCameraControl {
+    Brightness,
+    Contrast,
+    Hue,
+    Saturation,
+    Sharpness,
+    Gamma,
+    WhiteBalance,
+    BacklightComp,
+    Gain,
+    Pan,
+    Tilt,
+    Zoom,
+    Exposure,
+    Iris,
+    Focus,
+    /// The torch (flash used as a continuous light) of the camera. Usually on phones and some external cameras.
+    Torch,
+    /// The frequency of the mains power, which the camera times its exposure to so lights powered by it do not show as
+    /// flickering bands. A menu of 0 (disabled), 1 (50 Hz), 2 (60 Hz) and, on some cameras, 3 (automatic).
+    PowerlineFrequency,
+    /// Other camera control. Listed is the ID.
+    /// Wasteful, however is needed for a unified API across Windows, Linux, and `MacOSX` due to Microsoft's usage of GUIDs.
+    ///
+    /// THIS SHOULD ONLY BE USED WHEN YOU KNOW THE PLATFORM THAT YOU ARE RUNNING ON.
+    Other(u128),
+}
+
+/// All camera controls in an array.
+#[must_use]
+pub const fn all_known_camera_controls() -> [KnownCameraControl; 17] {
+    [
+        KnownCameraControl::Brightness,
+        KnownCameraControl::Contrast,
+        KnownCameraControl::Hue,
+        KnownCameraControl::Saturation,
+        KnownCameraControl::Sharpness,
+        KnownCameraControl::Gamma,
+        KnownCameraControl::WhiteBalance,
+        KnownCameraControl::BacklightComp,
+        KnownCameraControl::Gain,
+        KnownCameraControl::Pan,
+        KnownCameraControl::Tilt,
+        KnownCameraControl::Zoom,
+        KnownCameraControl::Exposure,
+        KnownCameraControl::Iris,
+        KnownCameraControl::Focus,
+        KnownCameraControl::Torch,
+        KnownCameraControl::PowerlineFrequency,
+    ]
+}
+
+impl Display for KnownCameraControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", &self)
+    }
+}
+
+/// This tells you weather a [`KnownCameraControl`] is automatically managed by the OS/Driver
+/// or manually managed by you, the programmer.
+#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum KnownCameraControlFlag {
+    Automatic,
+    Manual,
+    Continuous,
+    ReadOnly,
+    WriteOnly,
+    Volatile,
+    Disabled,
+}
+
+impl Display for KnownCameraControlFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The values for a [`CameraControl`].
 ///
 /// This provides a wide range of values that can be used to control a camera.
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -655,6 +1332,9 @@ pub enum ControlValueDescription {
         value: isize,
         possible: Vec<isize>,
         default: isize,
+        /// Human-readable names for each entry of `possible` (e.g. "50 Hz"), in the same order. Empty if the backend
+        /// does not name its values.
+        labels: Vec<String>,
     },
     RGB {
         value: (f64, f64, f64),
@@ -937,11 +1617,19 @@ impl Display for ControlValueDescription {
                 value,
                 possible,
                 default,
+                labels,
             } => {
-                write!(
-                    f,
-                    "Current: {value}, Possible Values: {possible:?}, Default: {default}",
-                )
+                if labels.is_empty() {
+                    write!(
+                        f,
+                        "Current: {value}, Possible Values: {possible:?}, Default: {default}",
+                    )
+                } else {
+                    write!(
+                        f,
+                        "Current: {value}, Possible Values: {possible:?} {labels:?}, Default: {default}",
+                    )
+                }
             }
             ControlValueDescription::RGB {
                 value,
@@ -1203,6 +1891,245 @@ impl Display for ControlValueSetter {
     }
 }
 
+/// How the exposure of a camera is set, see [`set_exposure()`](crate::traits::CaptureDevice::set_exposure).
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Exposure {
+    /// The camera adjusts the exposure by itself.
+    #[default]
+    Auto,
+    /// A fixed exposure time. The camera rounds it to the nearest it supports.
+    Manual(Duration),
+}
+
+impl Display for Exposure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exposure::Auto => write!(f, "Auto"),
+            Exposure::Manual(duration) => write!(f, "Manual: {}s", duration.as_secs_f64()),
+        }
+    }
+}
+
+/// How the white balance of a camera is set, see
+/// [`set_white_balance()`](crate::traits::CaptureDevice::set_white_balance).
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum WhiteBalance {
+    /// The camera adjusts the white balance by itself.
+    #[default]
+    Auto,
+    /// A fixed color temperature in kelvin, e.g. 6500 for daylight.
+    Manual(u32),
+}
+
+impl Display for WhiteBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WhiteBalance::Auto => write!(f, "Auto"),
+            WhiteBalance::Manual(kelvin) => write!(f, "Manual: {kelvin}K"),
+        }
+    }
+}
+
+/// How the focus of a camera is set, see [`set_focus()`](crate::traits::CaptureDevice::set_focus).
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Focus {
+    /// The camera focuses by itself.
+    #[default]
+    Auto,
+    /// A fixed lens position, from `0.0` (the nearest the camera can focus) to `1.0` (the farthest). It is clamped to
+    /// that. Cameras do not agree on what distance a position is, so this is only comparable on the same camera.
+    Manual(f64),
+}
+
+impl Display for Focus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Focus::Auto => write!(f, "Auto"),
+            Focus::Manual(position) => write!(f, "Manual: {position}"),
+        }
+    }
+}
+
+/// The range of one pan, tilt or zoom axis, see [`PtzCapabilities`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PtzRange {
+    min: f64,
+    max: f64,
+    step: f64,
+    default: f64,
+}
+
+impl PtzRange {
+    /// Create a new [`PtzRange`].
+    #[must_use]
+    pub fn new(min: f64, max: f64, step: f64, default: f64) -> Self {
+        PtzRange {
+            min,
+            max,
+            step,
+            default,
+        }
+    }
+
+    /// Get the lowest value.
+    #[must_use]
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Get the highest value.
+    #[must_use]
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Get the smallest change the device makes, or `0.0` if it is continuous.
+    #[must_use]
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Get the value the device starts at.
+    #[must_use]
+    pub fn default(&self) -> f64 {
+        self.default
+    }
+
+    /// Clamps `value` into the range.
+    #[must_use]
+    pub fn clamp(&self, value: f64) -> f64 {
+        value.clamp(self.min, self.max)
+    }
+}
+
+impl Display for PtzRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} to {} (step {}, default {})",
+            self.min, self.max, self.step, self.default
+        )
+    }
+}
+
+/// What pan, tilt and zoom a camera can do.
+///
+/// Pan and tilt are in degrees, with positive values to the right and up. Zoom is in the units of the device: on
+/// `AVFoundation` it is the zoom factor, where `1.0` is no zoom, elsewhere it is whatever the driver reports,
+/// so use the [`PtzRange`] to scale it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PtzCapabilities {
+    pan: Option<PtzRange>,
+    tilt: Option<PtzRange>,
+    zoom: Option<PtzRange>,
+    relative_pan_tilt: bool,
+    zoom_ramp: bool,
+}
+
+impl PtzCapabilities {
+    /// Create new [`PtzCapabilities`], with `None` for the axes the camera does not have.
+    #[must_use]
+    pub fn new(pan: Option<PtzRange>, tilt: Option<PtzRange>, zoom: Option<PtzRange>) -> Self {
+        PtzCapabilities {
+            pan,
+            tilt,
+            zoom,
+            relative_pan_tilt: false,
+            zoom_ramp: false,
+        }
+    }
+
+    /// Sets whether the camera can pan and tilt by an offset from where it is.
+    #[must_use]
+    pub fn with_relative_pan_tilt(mut self, relative_pan_tilt: bool) -> Self {
+        self.relative_pan_tilt = relative_pan_tilt;
+        self
+    }
+
+    /// Sets whether the camera can zoom smoothly at a rate.
+    #[must_use]
+    pub fn with_zoom_ramp(mut self, zoom_ramp: bool) -> Self {
+        self.zoom_ramp = zoom_ramp;
+        self
+    }
+
+    /// Get the pan range in degrees, or `None` if the camera cannot pan.
+    #[must_use]
+    pub fn pan(&self) -> Option<PtzRange> {
+        self.pan
+    }
+
+    /// Get the tilt range in degrees, or `None` if the camera cannot tilt.
+    #[must_use]
+    pub fn tilt(&self) -> Option<PtzRange> {
+        self.tilt
+    }
+
+    /// Get the zoom range, or `None` if the camera cannot zoom.
+    #[must_use]
+    pub fn zoom(&self) -> Option<PtzRange> {
+        self.zoom
+    }
+
+    /// Checks if the camera can pan and tilt by an offset from where it is.
+    #[must_use]
+    pub fn relative_pan_tilt(&self) -> bool {
+        self.relative_pan_tilt
+    }
+
+    /// Checks if the camera can zoom smoothly at a rate.
+    #[must_use]
+    pub fn zoom_ramp(&self) -> bool {
+        self.zoom_ramp
+    }
+
+    /// Checks if the camera has none of pan, tilt and zoom.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pan.is_none() && self.tilt.is_none() && self.zoom.is_none()
+    }
+}
+
+/// A request to a control of a UVC extension unit, with the codes of the UVC specification (A.8). `SetCur` writes the
+/// data, all others read into it.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[repr(u8)]
+pub enum XuQuery {
+    SetCur = 0x01,
+    GetCur = 0x81,
+    GetMin = 0x82,
+    GetMax = 0x83,
+    /// The resolution, i.e. the step between values.
+    GetRes = 0x84,
+    /// The length of the control in bytes, as a little endian `u16`.
+    GetLen = 0x85,
+    /// Whether the control can be read and written, as a bitmask of one byte.
+    GetInfo = 0x86,
+    GetDef = 0x87,
+}
+
+impl Display for XuQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            XuQuery::SetCur => "SET_CUR",
+            XuQuery::GetCur => "GET_CUR",
+            XuQuery::GetMin => "GET_MIN",
+            XuQuery::GetMax => "GET_MAX",
+            XuQuery::GetRes => "GET_RES",
+            XuQuery::GetLen => "GET_LEN",
+            XuQuery::GetInfo => "GET_INFO",
+            XuQuery::GetDef => "GET_DEF",
+        };
+        write!(f, "{name}")
+    }
+}
+
 /// The list of known capture backends to the library. <br>
 /// - `AUTO` is special - it tells the Camera struct to automatically choose a backend most suited for the current platform.
 /// - `AVFoundation` - Uses `AVFoundation` on `MacOSX`
@@ -1211,6 +2138,11 @@ impl Display for ControlValueSetter {
 /// - `MediaFoundation` - Microsoft Media Foundation, Windows only,
 /// - `GStreamer` - ***DEPRECATED*** Uses `GStreamer` RTP to capture. Platform agnostic.
 /// - `Browser` - Uses browser APIs to capture from a webcam.
+/// - `FFmpeg` - Uses `FFmpeg`'s `avdevice` demuxers as a cross-platform fallback.
+/// - `DeckLink` - Blackmagic `DeckLink` SDI/HDMI capture cards, through `FFmpeg`'s `decklink` input device.
+/// - `GigEVision` - Industrial `GigE` Vision / USB3 Vision (`GenICam`) cameras through `aravis`.
+/// - `NDI` - Receives video from NDI senders on the local network.
+/// - `Custom` - A backend provided by another crate through a [`CaptureBackendFactory`](crate::traits::CaptureBackendFactory), identified by its name.
 #[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum ApiBackend {
@@ -1219,6 +2151,13 @@ pub enum ApiBackend {
     Video4Linux,
     MediaFoundation,
     Browser,
+    FFmpeg,
+    DeckLink,
+    GigEVision,
+    NDI,
+    /// Only serialized, as the name it borrows cannot be deserialized into a `&'static str`.
+    #[cfg_attr(feature = "serialize", serde(skip_deserializing))]
+    Custom(#[cfg_attr(feature = "serialize", serde(skip_deserializing))] &'static str),
 }
 
 impl Display for ApiBackend {
@@ -1226,3 +2165,603 @@ impl Display for ApiBackend {
         write!(f, "{self:?}")
     }
 }
+
+/// The connection state of a camera that has automatic reconnection enabled.
+/// - `Connected` - The device is present and frames are being read normally.
+/// - `Reconnecting` - The device has disappeared. The camera is waiting for a device with the same unique ID to return.
+#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// How the OS schedules a capture thread, e.g. the one of a `CallbackCamera`. Raising it keeps frames from being dropped
+/// while the rest of the application is busy.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ThreadPriority {
+    /// The priority threads start with.
+    #[default]
+    Normal,
+    /// Ahead of the other threads of the application.
+    /// - `Linux`: A nice value of -10. Lowering the nice value needs `CAP_SYS_NICE` or a high enough `RLIMIT_NICE`.
+    /// - `Windows`: `THREAD_PRIORITY_ABOVE_NORMAL`.
+    /// - `macOS`: The `QOS_CLASS_USER_INITIATED` quality of service class.
+    High,
+    /// Ahead of (almost) everything else on the system, for e.g. keeping recordings in sync with audio.
+    /// - `Linux`: The `SCHED_FIFO` real-time policy, which needs `CAP_SYS_NICE` or an `RLIMIT_RTPRIO`.
+    /// - `Windows`: `THREAD_PRIORITY_TIME_CRITICAL`.
+    /// - `macOS`: The `QOS_CLASS_USER_INTERACTIVE` quality of service class.
+    RealTime,
+}
+
+impl Display for ThreadPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Something that happened to the device or capture session of a backend while streaming, which would otherwise only show
+/// as frames no longer arriving.
+#[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SessionEvent {
+    /// The device was unplugged or otherwise went away.
+    Disconnected,
+    /// The device came back after being [`Disconnected`](SessionEvent::Disconnected). The stream has to be reopened.
+    Reconnected,
+    /// The capture session stopped because of an error, with the error of the platform.
+    RuntimeError(String),
+    /// The capture session was paused by the system, e.g. by a phone call or the application going to the background.
+    /// No frames arrive until it is [`Resumed`](SessionEvent::Resumed).
+    Interrupted(InterruptionReason),
+    /// The capture session is capturing again after being [`Interrupted`](SessionEvent::Interrupted), or restarted after
+    /// a [`RuntimeError`](SessionEvent::RuntimeError).
+    Resumed,
+    /// The thermal or power pressure on the device changed. At [`SystemPressureLevel::Shutdown`] the system stops the
+    /// session, so capture should get lighter (e.g. a lower frame rate) before that.
+    SystemPressure(SystemPressureLevel),
+    /// A control was changed by something other than this camera, such as another application, the automatic exposure of
+    /// the driver, or a button on the device. Read it again with
+    /// [`camera_control()`](crate::traits::CaptureDevice::camera_control) to get its new value.
+    ControlChanged(KnownCameraControl),
+    /// The source of the device (e.g. the input of an HDMI capture device) switched to another resolution. The format
+    /// has to be [refreshed](crate::traits::CaptureDevice::refresh_camera_format) and the stream reopened.
+    FormatChanged,
+}
+
+impl Display for SessionEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionEvent::Disconnected => write!(f, "Disconnected"),
+            SessionEvent::Reconnected => write!(f, "Reconnected"),
+            SessionEvent::RuntimeError(why) => write!(f, "Runtime error: {why}"),
+            SessionEvent::Interrupted(reason) => write!(f, "Interrupted: {reason}"),
+            SessionEvent::Resumed => write!(f, "Resumed"),
+            SessionEvent::SystemPressure(level) => write!(f, "System pressure: {level}"),
+            SessionEvent::ControlChanged(control) => write!(f, "Control changed: {control}"),
+            SessionEvent::FormatChanged => write!(f, "Format changed"),
+        }
+    }
+}
+
+/// Why the system interrupted a capture session, see [`SessionEvent::Interrupted`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum InterruptionReason {
+    /// The platform did not say.
+    #[default]
+    Unknown,
+    /// The application went to the background, where it cannot use the camera.
+    Backgrounded,
+    /// Another application (e.g. a phone call) took the audio device.
+    AudioDeviceInUse,
+    /// Another application took the camera.
+    VideoDeviceInUse,
+    /// Several applications are in the foreground (e.g. Split View), and the camera is only available to one of them.
+    MultipleForegroundApps,
+    /// The device is under too much thermal or power pressure.
+    SystemPressure,
+}
+
+impl Display for InterruptionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// How much thermal or power pressure the device is under, see [`SessionEvent::SystemPressure`].
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SystemPressureLevel {
+    /// Capture runs normally.
+    #[default]
+    Nominal,
+    /// Slightly elevated, capture is not affected.
+    Fair,
+    /// Highly elevated, the system may lower the quality of the capture.
+    Serious,
+    /// Critical, the frame rate should be lowered.
+    Critical,
+    /// The system is about to stop the capture session.
+    Shutdown,
+}
+
+impl Display for SystemPressureLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// A clockwise rotation in steps of 90 degrees.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Create a [`Rotation`] from clockwise degrees. Negative values rotate counter-clockwise.
+    /// Returns `None` if `degrees` is not a multiple of 90.
+    #[must_use]
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        if degrees % 90 != 0 {
+            return None;
+        }
+        Some(Self::from_quarter_turns(degrees / 90))
+    }
+
+    /// Get the clockwise rotation in degrees. This is one of 0, 90, 180 or 270.
+    #[must_use]
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 90,
+            Rotation::Deg180 => 180,
+            Rotation::Deg270 => 270,
+        }
+    }
+
+    /// Get the rotation that undoes this one.
+    #[must_use]
+    pub fn inverse(self) -> Self {
+        Rotation::Deg0 - self
+    }
+
+    fn from_quarter_turns(turns: i32) -> Self {
+        match turns.rem_euclid(4) {
+            0 => Rotation::Deg0,
+            1 => Rotation::Deg90,
+            2 => Rotation::Deg180,
+            _ => Rotation::Deg270,
+        }
+    }
+
+    fn quarter_turns(self) -> i32 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 1,
+            Rotation::Deg180 => 2,
+            Rotation::Deg270 => 3,
+        }
+    }
+}
+
+impl Add for Rotation {
+    type Output = Rotation;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::from_quarter_turns(self.quarter_turns() + rhs.quarter_turns())
+    }
+}
+
+impl Sub for Rotation {
+    type Output = Rotation;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::from_quarter_turns(self.quarter_turns() - rhs.quarter_turns())
+    }
+}
+
+impl Display for Rotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} degrees", self.degrees())
+    }
+}
+
+/// The file format a still photo is encoded in.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum PhotoCodec {
+    #[default]
+    Jpeg,
+    /// HEVC in a HEIF container, which is about half the size of a JPEG of the same quality.
+    Heif,
+}
+
+impl Display for PhotoCodec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhotoCodec::Jpeg => write!(f, "JPEG"),
+            PhotoCodec::Heif => write!(f, "HEIF"),
+        }
+    }
+}
+
+/// Whether the flash fires when a still photo is taken.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum FlashMode {
+    #[default]
+    Off,
+    On,
+    /// The device decides from how dark the scene is.
+    Auto,
+}
+
+impl Display for FlashMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// How a still photo is taken and encoded.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PhotoSettings {
+    codec: PhotoCodec,
+    flash: FlashMode,
+    quality: Option<u8>,
+}
+
+impl PhotoSettings {
+    /// Create [`PhotoSettings`] for a JPEG without flash, at the default quality of the device.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the file format of the photo.
+    #[must_use]
+    pub fn with_codec(mut self, codec: PhotoCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Sets whether the flash fires.
+    #[must_use]
+    pub fn with_flash(mut self, flash: FlashMode) -> Self {
+        self.flash = flash;
+        self
+    }
+
+    /// Sets the quality of the encoded photo, from 1 to 100. It is clamped to that.
+    #[must_use]
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality.clamp(1, 100));
+        self
+    }
+
+    /// Get the file format of the photo.
+    #[must_use]
+    pub fn codec(&self) -> PhotoCodec {
+        self.codec
+    }
+
+    /// Get whether the flash fires.
+    #[must_use]
+    pub fn flash(&self) -> FlashMode {
+        self.flash
+    }
+
+    /// Get the quality of the encoded photo, or `None` for the default of the device.
+    #[must_use]
+    pub fn quality(&self) -> Option<u8> {
+        self.quality
+    }
+}
+
+/// Whether the application is allowed to capture from cameras.
+/// - `Authorized` - Access has been granted.
+/// - `Denied` - The user explicitly denied access, or turned it off in the system privacy settings.
+/// - `Restricted` - Access is blocked by something the user cannot change, such as parental controls, device management policy or a missing capability declaration.
+/// - `NotDetermined` - The user has not been asked yet. Requesting permission will show the system prompt.
+#[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum PermissionStatus {
+    Authorized,
+    Denied,
+    Restricted,
+    NotDetermined,
+}
+
+impl PermissionStatus {
+    /// Returns `true` if cameras can be opened.
+    #[must_use]
+    pub fn is_authorized(&self) -> bool {
+        matches!(self, PermissionStatus::Authorized)
+    }
+}
+
+impl Display for PermissionStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Frame statistics for a stream, useful for diagnosing stutter.
+/// - `frames_delivered` - Frames handed to the user.
+/// - `frames_dropped_by_driver` - Frames the OS or driver reported as dropped before they reached `nokhwa`.
+/// - `frames_dropped_by_channel` - Frames `nokhwa` discarded because they were not read in time.
+#[derive(Copy, Clone, Debug, Default, Hash, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct StreamStats {
+    delivered: u64,
+    dropped_by_driver: u64,
+    dropped_by_channel: u64,
+}
+
+impl StreamStats {
+    /// Create a new [`StreamStats`].
+    #[must_use]
+    pub fn new(
+        frames_delivered: u64,
+        frames_dropped_by_driver: u64,
+        frames_dropped_by_channel: u64,
+    ) -> Self {
+        StreamStats {
+            delivered: frames_delivered,
+            dropped_by_driver: frames_dropped_by_driver,
+            dropped_by_channel: frames_dropped_by_channel,
+        }
+    }
+
+    /// Get the number of frames handed to the user.
+    #[must_use]
+    pub fn frames_delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Get the number of frames the OS or driver reported as dropped.
+    #[must_use]
+    pub fn frames_dropped_by_driver(&self) -> u64 {
+        self.dropped_by_driver
+    }
+
+    /// Get the number of frames discarded because they were not read in time.
+    #[must_use]
+    pub fn frames_dropped_by_channel(&self) -> u64 {
+        self.dropped_by_channel
+    }
+
+    /// Get the total number of dropped frames.
+    #[must_use]
+    pub fn frames_dropped(&self) -> u64 {
+        self.dropped_by_driver + self.dropped_by_channel
+    }
+}
+
+impl Display for StreamStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Delivered: {}, Dropped by driver: {}, Dropped by channel: {}",
+            self.delivered, self.dropped_by_driver, self.dropped_by_channel
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_format::{NV12, YUYV};
+
+    fn formats() -> Vec<CameraFormat> {
+        vec![
+            CameraFormat::new_from(640, 480, MJPEG, 30),
+            CameraFormat::new_from(1280, 720, MJPEG, 30),
+            CameraFormat::new_from(1280, 720, MJPEG, 60),
+            CameraFormat::new_from(1920, 1080, MJPEG, 30),
+            CameraFormat::new_from(640, 480, YUYV, 30),
+            CameraFormat::new_from(1280, 720, YUYV, 10),
+            CameraFormat::new_from(640, 480, NV12, 15),
+        ]
+    }
+
+    #[test]
+    fn exact_only_matches_an_available_format() {
+        let wanted = CameraFormat::new_from(1280, 720, YUYV, 10);
+        let request = RequestedFormat::new(RequestedFormatType::Exact(wanted));
+        assert_eq!(request.fulfill(&formats()), Some(wanted));
+
+        let missing = CameraFormat::new_from(1280, 720, YUYV, 30);
+        let request = RequestedFormat::new(RequestedFormatType::Exact(missing));
+        assert_eq!(request.fulfill(&formats()), None);
+    }
+
+    #[test]
+    fn range_picks_the_highest_resolution_then_frame_rate() {
+        let request = RequestedFormat::new(RequestedFormatType::Range {
+            min_resolution: Resolution::new(640, 480),
+            max_resolution: Resolution::new(1280, 720),
+            min_frame_rate: 30,
+        });
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(1280, 720, MJPEG, 60))
+        );
+
+        let request = RequestedFormat::new(RequestedFormatType::Range {
+            min_resolution: Resolution::new(320, 240),
+            max_resolution: Resolution::new(1280, 720),
+            min_frame_rate: 90,
+        });
+        assert_eq!(request.fulfill(&formats()), None);
+    }
+
+    #[test]
+    fn fallbacks_are_tried_in_order() {
+        let missing = CameraFormat::new_from(800, 600, MJPEG, 30);
+        let request = RequestedFormat::new(RequestedFormatType::Exact(missing))
+            .with_fallback(RequestedFormatType::HighestResolution(Resolution::new(
+                1280, 720,
+            )))
+            .with_fallback(RequestedFormatType::None);
+        assert_eq!(
+            request.fallbacks().collect::<Vec<_>>(),
+            [
+                RequestedFormatType::HighestResolution(Resolution::new(1280, 720)),
+                RequestedFormatType::None
+            ]
+        );
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(1280, 720, MJPEG, 60))
+        );
+
+        let request = RequestedFormat::new(RequestedFormatType::Exact(missing))
+            .with_fallback(RequestedFormatType::None);
+        assert_eq!(request.fulfill(&formats()), formats().first().copied());
+        assert_eq!(request.fulfill(&[]), None);
+    }
+
+    #[test]
+    fn fallbacks_past_the_maximum_are_ignored() {
+        let max = u32::try_from(RequestedFormat::MAX_FALLBACKS).unwrap();
+        let request = (0..max + 2).fold(
+            RequestedFormat::new(RequestedFormatType::None),
+            |request, fps| request.with_fallback(RequestedFormatType::HighestFrameRate(fps)),
+        );
+        assert_eq!(request.fallbacks().count(), RequestedFormat::MAX_FALLBACKS);
+        assert_eq!(
+            request.fallbacks().last(),
+            Some(RequestedFormatType::HighestFrameRate(max - 1))
+        );
+    }
+
+    #[test]
+    fn fourccs_are_tried_in_order_of_preference() {
+        let request = RequestedFormat::new(RequestedFormatType::AbsoluteHighestResolution)
+            .with_fourccs(&[NV12, YUYV]);
+        assert_eq!(request.fourccs().collect::<Vec<_>>(), [NV12, YUYV]);
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(640, 480, NV12, 15))
+        );
+
+        let request = request.with_fourccs(&[YUYV, NV12]);
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(1280, 720, YUYV, 10))
+        );
+    }
+
+    #[test]
+    fn fourccs_exclude_everything_else() {
+        let request =
+            RequestedFormat::new(RequestedFormatType::HighestFrameRate(60)).with_fourccs(&[YUYV]);
+        assert_eq!(request.fulfill(&formats()), None);
+
+        // the fallback still only gets YUYV formats
+        let request = request.with_fallback(RequestedFormatType::AbsoluteHighestFrameRate);
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(640, 480, YUYV, 30))
+        );
+
+        // an empty list accepts anything again
+        let request = request.with_fourccs(&[]);
+        assert_eq!(request.fourccs().count(), 0);
+        assert_eq!(
+            request.fulfill(&formats()),
+            Some(CameraFormat::new_from(1280, 720, MJPEG, 60))
+        );
+    }
+
+    #[test]
+    fn the_request_beats_a_fallback_in_a_preferred_fourcc() {
+        // every request is tried with every accepted FourCC before moving on to the next one
+        let wanted = CameraFormat::new_from(1920, 1080, MJPEG, 30);
+        let request = RequestedFormat::new(RequestedFormatType::Exact(wanted))
+            .with_fourccs(&[NV12, MJPEG])
+            .with_fallback(RequestedFormatType::None);
+        assert_eq!(request.fulfill(&formats()), Some(wanted));
+    }
+
+    const NTSC: FrameRate = FrameRate::new(30000, 1001);
+
+    #[test]
+    fn frame_rates_are_reduced() {
+        assert_eq!(FrameRate::new(60, 2), FrameRate::from_fps(30));
+        assert_eq!(FrameRate::new(60000, 2002), NTSC);
+        assert_eq!((NTSC.numerator(), NTSC.denominator()), (30000, 1001));
+        assert_eq!(FrameRate::new(15, 0), FrameRate::from_fps(15));
+        assert_eq!(FrameRate::new(0, 5), FrameRate::from_fps(0));
+        assert!(FrameRate::new(90, 3).is_integer());
+        assert!(!NTSC.is_integer());
+    }
+
+    #[test]
+    fn frame_rates_round_and_order_exactly() {
+        assert_eq!(NTSC.fps(), 30);
+        assert_eq!(FrameRate::new(25, 2).fps(), 13);
+        assert!((NTSC.as_f64() - 29.97).abs() < 0.001);
+        assert!(NTSC < FrameRate::from_fps(30));
+        assert!(NTSC > FrameRate::new(2997, 101));
+        assert_eq!(
+            FrameRate::new(1, 3).cmp(&FrameRate::new(2, 6)),
+            Ordering::Equal
+        );
+        assert_eq!(NTSC.to_string(), "29.97");
+        assert_eq!(FrameRate::new(120, 2).to_string(), "60");
+    }
+
+    #[test]
+    fn requests_negotiate_exact_frame_rates() {
+        let resolution = Resolution::new(1920, 1080);
+        let ntsc = CameraFormat::new_exact(resolution, NV12, NTSC);
+        let thirty = CameraFormat::new(resolution, NV12, 30);
+        let twenty_five = CameraFormat::new(resolution, NV12, 25);
+        let formats = [twenty_five, ntsc, thirty];
+
+        assert_eq!(ntsc.frame_rate(), 30);
+        assert_eq!(ntsc.exact_frame_rate(), NTSC);
+        assert_eq!(
+            RequestedFormat::new(RequestedFormatType::Exact(ntsc)).fulfill(&formats),
+            Some(ntsc)
+        );
+        assert_eq!(
+            RequestedFormat::new(RequestedFormatType::Exact(ntsc)).fulfill(&[thirty]),
+            None
+        );
+        assert_eq!(
+            RequestedFormat::new(RequestedFormatType::AbsoluteHighestFrameRate).fulfill(&formats),
+            Some(thirty)
+        );
+        // whole frame rates also match the rates they round to
+        assert_eq!(
+            RequestedFormat::new(RequestedFormatType::HighestFrameRate(30)).fulfill(&[ntsc]),
+            Some(ntsc)
+        );
+        let closest = |frame_rate| {
+            RequestedFormat::new(RequestedFormatType::Closest(CameraFormat::new_exact(
+                resolution, NV12, frame_rate,
+            )))
+            .fulfill(&formats)
+        };
+        assert_eq!(closest(FrameRate::new(2997, 100)), Some(ntsc));
+        assert_eq!(closest(FrameRate::from_fps(31)), Some(thirty));
+        assert_eq!(closest(FrameRate::from_fps(24)), Some(twenty_five));
+    }
+}
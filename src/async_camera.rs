@@ -0,0 +1,210 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::Camera;
+use flume::{Receiver, Sender, TryRecvError, TrySendError};
+use futures_core::Stream;
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    types::{CameraFormat, CameraIndex, CameraInfo, RequestedFormat},
+};
+
+/// How many frames may queue up before the capture thread starts dropping them.
+const FRAME_QUEUE_LEN: usize = 4;
+
+type Reply<T> = Sender<Result<T, NokhwaError>>;
+
+enum Command {
+    OpenStream(Reply<()>),
+    StopStream(Reply<()>),
+    CameraFormat(Reply<CameraFormat>),
+    SetCameraFormat(CameraFormat, Reply<CameraFormat>),
+    Shutdown,
+}
+
+/// A camera that can be driven from `async` code.
+///
+/// The underlying [`Camera`] lives on a dedicated capture thread, so none of the methods here block
+/// the executor. Frames are delivered through [`frame_stream()`](AsyncCamera::frame_stream), which is
+/// a [`Stream`] usable with any runtime.
+///
+/// If frames are not consumed fast enough, the oldest frames are kept and new ones are dropped
+/// rather than stalling the camera.
+pub struct AsyncCamera {
+    info: CameraInfo,
+    commands: Sender<Command>,
+    frames: Receiver<Result<FrameBuffer, NokhwaError>>,
+}
+
+impl AsyncCamera {
+    /// Creates a new [`AsyncCamera`]. The camera is opened on its capture thread.
+    /// # Errors
+    /// If the backend fails to open the camera (e.g. Device does not exist at specified index/path), this will error.
+    pub async fn new(index: CameraIndex, format: RequestedFormat) -> Result<Self, NokhwaError> {
+        let (commands, command_recv) = flume::unbounded();
+        let (frame_send, frames) = flume::bounded(FRAME_QUEUE_LEN);
+        let (ready_send, ready_recv) = flume::bounded(1);
+
+        std::thread::spawn(move || {
+            let camera = match Camera::new(index, format) {
+                Ok(camera) => {
+                    let _ = ready_send.send(Ok(camera.info().clone()));
+                    camera
+                }
+                Err(why) => {
+                    let _ = ready_send.send(Err(why));
+                    return;
+                }
+            };
+            camera_command_loop(camera, &command_recv, &frame_send);
+        });
+
+        let info = ready_recv
+            .recv_async()
+            .await
+            .map_err(|_| capture_thread_gone())??;
+
+        Ok(AsyncCamera {
+            info,
+            commands,
+            frames,
+        })
+    }
+
+    /// Gets the camera information such as Name and Index as a [`CameraInfo`].
+    #[must_use]
+    pub fn info(&self) -> &CameraInfo {
+        &self.info
+    }
+
+    /// Gets the current [`CameraFormat`].
+    /// # Errors
+    /// If the capture thread has died, this will error.
+    pub async fn camera_format(&self) -> Result<CameraFormat, NokhwaError> {
+        self.request(Command::CameraFormat).await
+    }
+
+    /// Will set the current [`CameraFormat`], returning the format that was actually applied.
+    /// This will reset the current stream if used while stream is opened.
+    /// # Errors
+    /// If the camera rejects the new camera format, this will return an error.
+    pub async fn set_camera_format(
+        &self,
+        new_fmt: CameraFormat,
+    ) -> Result<CameraFormat, NokhwaError> {
+        self.request(|reply| Command::SetCameraFormat(new_fmt, reply))
+            .await
+    }
+
+    /// Will open the camera stream. Frames will start arriving on [`frame_stream()`](AsyncCamera::frame_stream).
+    /// # Errors
+    /// If the specific backend fails to open the camera (e.g. already taken, busy, doesn't exist anymore) this will error.
+    pub async fn open_stream(&self) -> Result<(), NokhwaError> {
+        self.request(Command::OpenStream).await
+    }
+
+    /// Will drop the stream.
+    /// # Errors
+    /// Please check the `Quirks` section of each backend.
+    pub async fn stop_stream(&self) -> Result<(), NokhwaError> {
+        self.request(Command::StopStream).await
+    }
+
+    /// Waits for the next frame.
+    /// # Errors
+    /// If the backend fails to get a frame, or the capture thread has died, this will error.
+    pub async fn frame(&self) -> Result<FrameBuffer, NokhwaError> {
+        self.frames
+            .recv_async()
+            .await
+            .map_err(|_| capture_thread_gone())?
+    }
+
+    /// A [`Stream`] of captured frames. The stream ends when the capture thread exits.
+    pub fn frame_stream(&self) -> impl Stream<Item = Result<FrameBuffer, NokhwaError>> + '_ {
+        self.frames.stream()
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(Reply<T>) -> Command,
+    ) -> Result<T, NokhwaError> {
+        let (reply, response) = flume::bounded(1);
+        self.commands
+            .send(command(reply))
+            .map_err(|_| capture_thread_gone())?;
+        response
+            .recv_async()
+            .await
+            .map_err(|_| capture_thread_gone())?
+    }
+}
+
+impl Drop for AsyncCamera {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+    }
+}
+
+fn capture_thread_gone() -> NokhwaError {
+    NokhwaError::GeneralError("Capture thread has exited".to_string())
+}
+
+fn camera_command_loop(
+    mut camera: Camera,
+    commands: &Receiver<Command>,
+    frames: &Sender<Result<FrameBuffer, NokhwaError>>,
+) {
+    loop {
+        // only block on commands while idle, otherwise keep capturing between them
+        let command = if camera.is_stream_open() {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+
+        match command {
+            Some(Command::OpenStream(reply)) => {
+                let _ = reply.send(camera.open_stream());
+            }
+            Some(Command::StopStream(reply)) => {
+                let _ = reply.send(camera.stop_stream());
+            }
+            Some(Command::CameraFormat(reply)) => {
+                let _ = reply.send(Ok(camera.camera_format()));
+            }
+            Some(Command::SetCameraFormat(format, reply)) => {
+                let _ = reply
+                    .send(camera.set_camera_requset(RequestedFormat::from_camera_format(format)));
+            }
+            Some(Command::Shutdown) => break,
+            None => match frames.try_send(camera.frame()) {
+                Ok(()) | Err(TrySendError::Full(_)) => {}
+                Err(TrySendError::Disconnected(_)) => break,
+            },
+        }
+    }
+
+    let _ = camera.stop_stream();
+}
@@ -0,0 +1,572 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use aravis::{
+    glib::{Cast, Type},
+    Aravis, Buffer, BufferStatus, Camera, CameraExt, DeviceExt, GcEnumeration, GcFeatureNode,
+    GcFeatureNodeExt, Stream, StreamExt,
+};
+use four_cc::FourCC;
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    pixel_format::{BAYER_BGGR8, BAYER_GBRG8, BAYER_GRBG8, BAYER_RGGB8, GRAY, RAWRGB, UYVY, YUYV},
+    traits::CaptureDevice,
+    types::{
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueDescription,
+        ControlValueSetter, KnownCameraControl, KnownCameraControlFlag, RequestedFormat,
+        Resolution,
+    },
+};
+use std::{borrow::Cow, collections::HashMap, time::Instant};
+
+// Buffers queued on the stream at once.
+const BUFFER_COUNT: usize = 4;
+// How long to wait for a frame before giving up, in microseconds.
+const FRAME_TIMEOUT_US: u64 = 2_000_000;
+
+/// `GenICam` (PFNC) pixel format names and the [`FourCC`] they are delivered as.
+const PIXEL_FORMATS: [(&str, FourCC); 8] = [
+    ("Mono8", GRAY),
+    ("BayerBG8", BAYER_BGGR8),
+    ("BayerGB8", BAYER_GBRG8),
+    ("BayerGR8", BAYER_GRBG8),
+    ("BayerRG8", BAYER_RGGB8),
+    ("RGB8", RAWRGB),
+    ("YUV422_8", YUYV),
+    ("YUV422_8_UYVY", UYVY),
+];
+
+/// `GenICam` SFNC feature names and the [`KnownCameraControl`] they map to.
+const FEATURES: [(&str, KnownCameraControl); 7] = [
+    ("BlackLevel", KnownCameraControl::Brightness),
+    ("Gamma", KnownCameraControl::Gamma),
+    ("Sharpness", KnownCameraControl::Sharpness),
+    ("Hue", KnownCameraControl::Hue),
+    ("Saturation", KnownCameraControl::Saturation),
+    ("Gain", KnownCameraControl::Gain),
+    ("ExposureTime", KnownCameraControl::Exposure),
+];
+
+fn pfnc_to_fourcc(name: &str) -> Option<FourCC> {
+    PIXEL_FORMATS
+        .iter()
+        .find(|(pfnc, _)| *pfnc == name)
+        .map(|(_, fourcc)| *fourcc)
+}
+
+fn fourcc_to_pfnc(fourcc: FourCC) -> Option<&'static str> {
+    PIXEL_FORMATS
+        .iter()
+        .find(|(_, fcc)| *fcc == fourcc)
+        .map(|(pfnc, _)| *pfnc)
+}
+
+fn feature_name(control: KnownCameraControl) -> Option<&'static str> {
+    FEATURES
+        .iter()
+        .find(|(_, kcc)| *kcc == control)
+        .map(|(name, _)| *name)
+}
+
+/// The kinds of `GenICam` features that map to a [`ControlValueDescription`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FeatureKind {
+    Float,
+    Integer,
+    Boolean,
+    Enumeration,
+}
+
+fn initialize() -> Result<Aravis, NokhwaError> {
+    Aravis::initialize().map_err(|why| NokhwaError::InitializeError {
+        backend: ApiBackend::GigEVision,
+        error: why.to_string(),
+    })
+}
+
+/// Query the system for a list of available GigE Vision (and USB3 Vision) cameras through `aravis`.
+/// # Quirks
+/// - The `unique_id` of the [`CameraInfo`] is the `aravis` device ID (`<vendor>-<serial>`), which can be passed as a [`CameraIndex::String`].
+/// # Errors
+/// If `aravis` cannot be initialized, this will error.
+pub fn query_aravis() -> Result<Vec<CameraInfo>, NokhwaError> {
+    Ok(initialize()?
+        .get_device_list()
+        .into_iter()
+        .map(|device| {
+            CameraInfo::new(
+                &device.id.to_string_lossy(),
+                &device.model.to_string_lossy(),
+                &device.vendor.to_string_lossy(),
+                &device.model.to_string_lossy(),
+                &device.protocol.to_string_lossy(),
+                "",
+            )
+        })
+        .collect())
+}
+
+/// The backend struct that interfaces with industrial GigE Vision / `GenICam` cameras through [`aravis`](https://github.com/AravisProject/aravis).
+/// To see what this does, please see [`CaptureDevice`].
+/// # Quirks
+/// - Resolutions are reported as the sensor's full width and height only. Smaller regions can still be set with [`set_resolution()`](CaptureDevice::set_resolution).
+/// - Frame rates are whole numbers, even though `GenICam` cameras accept fractional rates.
+/// - Only the `GenICam` features `BlackLevel`, `Gamma`, `Sharpness`, `Hue`, `Saturation`, `Gain` and `ExposureTime` are mapped to [`KnownCameraControl`]s.
+///   Other float, integer, boolean and enumeration features can be reached with [`feature_control()`](AravisCaptureDevice::feature_control)
+///   and [`set_feature()`](AravisCaptureDevice::set_feature).
+/// - Enumeration features are reported as [`ControlValueDescription::Enum`]s, whose values are the indices of the entry names in `labels`.
+/// - Buffers `aravis` could not fill completely (e.g. due to dropped packets) are handed back and skipped.
+pub struct AravisCaptureDevice {
+    camera: Camera,
+    info: CameraInfo,
+    format: CameraFormat,
+    stream: Option<Stream>,
+}
+
+impl AravisCaptureDevice {
+    /// Creates a new capture device using `aravis`. A [`CameraIndex::Index`] refers to the order from [`query_aravis()`],
+    /// while a [`CameraIndex::String`] is the `aravis` device ID.
+    /// # Errors
+    /// This function will error if the camera cannot be found or opened, or if the [`RequestedFormat`] cannot be fulfilled.
+    pub fn new(index: &CameraIndex, cam_fmt: RequestedFormat) -> Result<Self, NokhwaError> {
+        let info = match index {
+            CameraIndex::Index(idx) => query_aravis()?.into_iter().nth(*idx as usize),
+            CameraIndex::String(id) => query_aravis()?
+                .into_iter()
+                .find(|info| &info.unique_id() == id),
+        }
+        .ok_or(NokhwaError::OpenDeviceError(
+            index.to_string(),
+            "No such device".to_string(),
+        ))?;
+
+        let camera = Camera::new(Some(&info.unique_id()))
+            .map_err(|why| NokhwaError::OpenDeviceError(index.to_string(), why.to_string()))?;
+
+        let mut device = AravisCaptureDevice {
+            camera,
+            info,
+            format: CameraFormat::default(),
+            stream: None,
+        };
+        let formats = device.compatible_camera_formats()?;
+        let format = cam_fmt
+            .fulfill(&formats)
+            .ok_or(NokhwaError::GetPropertyError {
+                property: "CameraFormat".to_string(),
+                error: "Failed to Fufill".to_string(),
+            })?;
+        device.set_camera_format(format)?;
+        Ok(device)
+    }
+
+    /// Gets an arbitrary `GenICam` feature (e.g. `AcquisitionFrameRate`) as a [`CameraControl`]. Float and integer features
+    /// are ranges, boolean features are booleans, and enumeration features are menus labelled with their entry names.
+    /// # Errors
+    /// If the feature does not exist or is of a kind that is not mapped (e.g. a command or string), this will error.
+    pub fn feature_control(&self, feature: &str) -> Result<CameraControl, NokhwaError> {
+        let control = FEATURES
+            .iter()
+            .find(|(name, _)| *name == feature)
+            .map_or(KnownCameraControl::Other(0), |(_, kcc)| *kcc);
+        let map_err = |why: aravis::glib::Error| NokhwaError::GetPropertyError {
+            property: feature.to_string(),
+            error: why.to_string(),
+        };
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        let description = match self.feature_kind(feature)? {
+            FeatureKind::Float => {
+                let value = self.camera.float(feature).map_err(map_err)?;
+                let (min, max) = self.camera.float_bounds(feature).map_err(map_err)?;
+                ControlValueDescription::FloatRange {
+                    min,
+                    max,
+                    value,
+                    step: 0.0,
+                    default: value,
+                }
+            }
+            FeatureKind::Integer => {
+                let value = self.camera.integer(feature).map_err(map_err)? as isize;
+                let (min, max) = self.camera.integer_bounds(feature).map_err(map_err)?;
+                let step = self.camera.integer_increment(feature).map_err(map_err)?;
+                ControlValueDescription::IntegerRange {
+                    min: min as isize,
+                    max: max as isize,
+                    value,
+                    step: step as isize,
+                    default: value,
+                }
+            }
+            FeatureKind::Boolean => {
+                let value = self.camera.boolean(feature).map_err(map_err)?;
+                ControlValueDescription::Boolean {
+                    value,
+                    default: value,
+                }
+            }
+            FeatureKind::Enumeration => {
+                let labels = self.enumeration_entries(feature)?;
+                let current = self.camera.string(feature).map_err(map_err)?;
+                let value = labels
+                    .iter()
+                    .position(|label| label.as_str() == current.as_str())
+                    .unwrap_or_default() as isize;
+                ControlValueDescription::Enum {
+                    value,
+                    possible: (0..labels.len() as isize).collect(),
+                    default: value,
+                    labels,
+                }
+            }
+        };
+        let available = self.camera.is_feature_available(feature).map_err(map_err)?;
+
+        let flags = if available {
+            vec![]
+        } else {
+            vec![KnownCameraControlFlag::Disabled]
+        };
+        Ok(CameraControl::new(
+            control,
+            feature.to_string(),
+            description,
+            flags,
+            available,
+        ))
+    }
+
+    /// Sets an arbitrary `GenICam` feature. Float and integer features take a [`ControlValueSetter::Float`] or
+    /// [`ControlValueSetter::Integer`], boolean features a [`ControlValueSetter::Boolean`], and enumeration features
+    /// either the index of the entry (as in [`feature_control()`](AravisCaptureDevice::feature_control)) as a
+    /// [`ControlValueSetter::EnumValue`] or its name as a [`ControlValueSetter::String`].
+    /// # Errors
+    /// If the feature does not exist, the value does not fit its kind, or the camera rejects it, this will error.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn set_feature(
+        &mut self,
+        feature: &str,
+        value: ControlValueSetter,
+    ) -> Result<(), NokhwaError> {
+        let set_err = |error: String| NokhwaError::SetPropertyError {
+            property: feature.to_string(),
+            value: value.to_string(),
+            error,
+        };
+        let result = match (self.feature_kind(feature)?, &value) {
+            (FeatureKind::Float, ControlValueSetter::Float(f)) => {
+                self.camera.set_float(feature, *f)
+            }
+            (FeatureKind::Float, ControlValueSetter::Integer(i)) => {
+                self.camera.set_float(feature, *i as f64)
+            }
+            (FeatureKind::Integer, ControlValueSetter::Integer(i)) => {
+                self.camera.set_integer(feature, *i as i64)
+            }
+            (FeatureKind::Boolean, ControlValueSetter::Boolean(b)) => {
+                self.camera.set_boolean(feature, *b)
+            }
+            (FeatureKind::Enumeration, ControlValueSetter::String(entry)) => {
+                self.camera.set_string(feature, entry)
+            }
+            (FeatureKind::Enumeration, ControlValueSetter::EnumValue(index)) => {
+                let entries = self.enumeration_entries(feature)?;
+                let entry = usize::try_from(*index)
+                    .ok()
+                    .and_then(|index| entries.get(index))
+                    .ok_or_else(|| set_err("No such enumeration entry".to_string()))?;
+                self.camera.set_string(feature, entry)
+            }
+            (kind, _) => {
+                return Err(set_err(format!(
+                    "Value does not fit a {kind:?} GenICam feature"
+                )))
+            }
+        };
+        result.map_err(|why| set_err(why.to_string()))
+    }
+
+    fn feature_kind(&self, feature: &str) -> Result<FeatureKind, NokhwaError> {
+        let node = self
+            .camera
+            .device()
+            .and_then(|device| device.feature(feature))
+            .and_then(|node| node.downcast::<GcFeatureNode>().ok())
+            .ok_or(NokhwaError::GetPropertyError {
+                property: feature.to_string(),
+                error: "No such feature".to_string(),
+            })?;
+        if node.downcast_ref::<GcEnumeration>().is_some() {
+            return Ok(FeatureKind::Enumeration);
+        }
+        match node.value_type() {
+            Type::F64 => Ok(FeatureKind::Float),
+            Type::I64 => Ok(FeatureKind::Integer),
+            Type::BOOL => Ok(FeatureKind::Boolean),
+            other => Err(NokhwaError::GetPropertyError {
+                property: feature.to_string(),
+                error: format!("Unsupported GenICam feature type {other}"),
+            }),
+        }
+    }
+
+    fn enumeration_entries(&self, feature: &str) -> Result<Vec<String>, NokhwaError> {
+        Ok(self
+            .camera
+            .dup_available_enumerations_as_strings(feature)
+            .map_err(|why| NokhwaError::GetPropertyError {
+                property: feature.to_string(),
+                error: why.to_string(),
+            })?
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    fn sensor_resolution(&self) -> Result<Resolution, NokhwaError> {
+        let map_err = |why: aravis::glib::Error| NokhwaError::GetPropertyError {
+            property: "Resolution".to_string(),
+            error: why.to_string(),
+        };
+        let (_, max_width) = self.camera.width_bounds().map_err(map_err)?;
+        let (_, max_height) = self.camera.height_bounds().map_err(map_err)?;
+        #[allow(clippy::cast_sign_loss)]
+        Ok(Resolution::new(max_width as u32, max_height as u32))
+    }
+}
+
+impl CaptureDevice for AravisCaptureDevice {
+    fn backend(&self) -> ApiBackend {
+        ApiBackend::GigEVision
+    }
+
+    fn camera_info(&self) -> &CameraInfo {
+        &self.info
+    }
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
+        let map_err = |why: aravis::glib::Error| NokhwaError::GetPropertyError {
+            property: "CameraFormat".to_string(),
+            error: why.to_string(),
+        };
+        let (_, _, width, height) = self.camera.region().map_err(map_err)?;
+        let pixel_format = self.camera.pixel_format_as_string().map_err(map_err)?;
+        let fourcc = pfnc_to_fourcc(&pixel_format).ok_or(NokhwaError::GetPropertyError {
+            property: "FourCC".to_string(),
+            error: format!("Unsupported GenICam pixel format {pixel_format}"),
+        })?;
+        let frame_rate = self.camera.frame_rate().map_err(map_err)?;
+        self.format = CameraFormat::new(
+            Resolution::new(width as u32, height as u32),
+            fourcc,
+            frame_rate.round() as u32,
+        );
+        Ok(())
+    }
+
+    fn camera_format(&self) -> CameraFormat {
+        self.format
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+        let pfnc = fourcc_to_pfnc(new_fmt.format()).ok_or(NokhwaError::SetPropertyError {
+            property: "FourCC".to_string(),
+            value: new_fmt.format().to_string(),
+            error: "No matching GenICam pixel format".to_string(),
+        })?;
+        let reopen = self.stream.is_some();
+        self.stop_stream()?;
+
+        let map_err = |why: aravis::glib::Error| NokhwaError::SetPropertyError {
+            property: "CameraFormat".to_string(),
+            value: new_fmt.to_string(),
+            error: why.to_string(),
+        };
+        self.camera
+            .set_pixel_format_from_string(pfnc)
+            .map_err(map_err)?;
+        self.camera
+            .set_region(0, 0, new_fmt.width() as i32, new_fmt.height() as i32)
+            .map_err(map_err)?;
+        self.camera
+            .set_frame_rate(f64::from(new_fmt.frame_rate()))
+            .map_err(map_err)?;
+        self.refresh_camera_format()?;
+
+        if reopen {
+            self.open_stream()?;
+        }
+        Ok(())
+    }
+
+    fn compatible_list_by_resolution(
+        &mut self,
+        fourcc: FourCC,
+    ) -> Result<HashMap<Resolution, Vec<u32>>, NokhwaError> {
+        if fourcc_to_pfnc(fourcc).is_none() {
+            return Ok(HashMap::new());
+        }
+        let (min, max) =
+            self.camera
+                .frame_rate_bounds()
+                .map_err(|why| NokhwaError::GetPropertyError {
+                    property: "Frame rate".to_string(),
+                    error: why.to_string(),
+                })?;
+        #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+        let frame_rates = (min.ceil().max(1.0) as u32..=max.floor() as u32).collect();
+        Ok(HashMap::from([(self.sensor_resolution()?, frame_rates)]))
+    }
+
+    fn compatible_fourcc(&mut self) -> Result<Vec<FourCC>, NokhwaError> {
+        let mut formats = self
+            .camera
+            .dup_available_pixel_formats_as_strings()
+            .map_err(|why| NokhwaError::GetPropertyError {
+                property: "FourCC".to_string(),
+                error: why.to_string(),
+            })?
+            .iter()
+            .filter_map(|name| pfnc_to_fourcc(name))
+            .collect::<Vec<FourCC>>();
+        formats.sort();
+        formats.dedup();
+        Ok(formats)
+    }
+
+    fn resolution(&self) -> Resolution {
+        self.format.resolution()
+    }
+
+    fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_resolution(new_res);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.format.frame_rate()
+    }
+
+    fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_frame_rate(new_fps);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_format(&self) -> FourCC {
+        self.format.format()
+    }
+
+    fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_format(fourcc);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn camera_control(&self, control: KnownCameraControl) -> Result<CameraControl, NokhwaError> {
+        let feature = feature_name(control).ok_or(NokhwaError::GetPropertyError {
+            property: control.to_string(),
+            error: "not found/not supported".to_string(),
+        })?;
+        self.feature_control(feature)
+    }
+
+    fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+        Ok(FEATURES
+            .iter()
+            .filter_map(|(feature, _)| self.feature_control(feature).ok())
+            .collect())
+    }
+
+    fn set_camera_control(
+        &mut self,
+        id: KnownCameraControl,
+        value: ControlValueSetter,
+    ) -> Result<(), NokhwaError> {
+        let feature = feature_name(id).ok_or(NokhwaError::SetPropertyError {
+            property: id.to_string(),
+            value: value.to_string(),
+            error: "not found/not supported".to_string(),
+        })?;
+        self.set_feature(feature, value)
+    }
+
+    fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.stream.is_some() {
+            return Ok(());
+        }
+        let map_err = |why: aravis::glib::Error| NokhwaError::OpenStreamError(why.to_string());
+        let payload = self.camera.payload().map_err(map_err)?;
+        let stream = self.camera.create_stream().map_err(map_err)?;
+        for _ in 0..BUFFER_COUNT {
+            stream.push_buffer(Buffer::new_allocate(payload as usize));
+        }
+        self.camera.start_acquisition().map_err(map_err)?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn is_stream_open(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
+        let cam_fmt = self.format;
+        let raw_frame = self.frame_raw()?;
+        Ok(FrameBuffer::new(
+            cam_fmt.resolution(),
+            &raw_frame,
+            cam_fmt.format(),
+            Instant::now(),
+        ))
+    }
+
+    fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+        let stream = self.stream.as_ref().ok_or(NokhwaError::ReadFrameError(
+            "Stream Not Started".to_string(),
+        ))?;
+        // every queued buffer may have failed, after which there is nothing left to wait for
+        for _ in 0..BUFFER_COUNT {
+            let buffer = stream
+                .timeout_pop_buffer(FRAME_TIMEOUT_US)
+                .ok_or(NokhwaError::ReadFrameError("Timed out".to_string()))?;
+            let status = buffer.status();
+            let data = (status == BufferStatus::Success).then(|| buffer.data().to_vec());
+            // hand the buffer back to aravis so it can be filled again
+            stream.push_buffer(buffer);
+            if let Some(data) = data {
+                return Ok(Cow::Owned(data));
+            }
+        }
+        Err(NokhwaError::ReadFrameError(
+            "aravis could not fill any buffer".to_string(),
+        ))
+    }
+
+    fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.stream.take().is_some() {
+            self.camera
+                .stop_acquisition()
+                .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?;
+        }
+        Ok(())
+    }
+}
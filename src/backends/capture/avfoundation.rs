@@ -14,30 +14,35 @@
  * limitations under the License.
  */
 #[cfg(target_os = "macos")]
-use flume::{Receiver, Sender};
+use flume::{Receiver, RecvTimeoutError, Sender};
 use four_cc::FourCC;
-use nokhwa_bindings_macos::AVCaptureVideoDataOutputSampleBufferDelegate;
 #[cfg(target_os = "macos")]
-use nokhwa_bindings_macos::{
-    AVCaptureDelegate, AVCaptureDeviceInput, AVCaptureDeviceWrapper, AVCaptureSession,
-    AVCaptureVideoDataOutput, ProtocolObject, Queue, QueueAttribute, Retained,
-};
+use nokhwa_bindings_macos::{AVCaptureDeviceWrapper, CaptureSession, SessionNotifier};
+#[cfg(target_os = "macos")]
+use nokhwa_core::error::ErrorKind;
 use nokhwa_core::{
-    buffer::FrameBuffer,
+    buffer::{AudioBuffer, FrameBuffer},
     error::NokhwaError,
-    traits::CaptureBackendTrait,
+    traits::CaptureDevice,
     types::{
         ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
-        KnownCameraControl, RequestedFormat, RequestedFormatType, Resolution,
+        Exposure, Focus, KnownCameraControl, PhotoSettings, PtzCapabilities, Rect, RequestedFormat,
+        RequestedFormatType, Resolution, SessionEvent, StreamStats, WhiteBalance,
     },
 };
 #[cfg(target_os = "macos")]
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
 
 use std::{borrow::Cow, collections::HashMap};
 
 /// The backend struct that interfaces with V4L2.
-/// To see what this does, please see [`CaptureBackendTrait`].
+/// To see what this does, please see [`CaptureDevice`].
 /// # Quirks
 /// - While working with `iOS` is allowed, it is not officially supported and may not work.
 /// - You **must** call [`nokhwa_initialize`](crate::nokhwa_initialize) **before** doing anything with `AVFoundation`.
@@ -48,15 +53,26 @@ use std::{borrow::Cow, collections::HashMap};
 #[cfg(target_os = "macos")]
 pub struct AVFoundationCaptureDevice {
     device: AVCaptureDeviceWrapper,
-    dev_input: Option<Retained<AVCaptureDeviceInput>>,
-    session: Option<Retained<AVCaptureSession>>,
-    data_out: Option<Retained<AVCaptureVideoDataOutput>>,
-    data_collect: Option<Retained<AVCaptureDelegate>>,
+    session: Option<CaptureSession>,
+    notifier: Option<SessionNotifier>,
+    event_sender: Sender<SessionEvent>,
+    event_receiver: Receiver<SessionEvent>,
+    event_callback: Arc<Mutex<Option<EventCallback>>>,
+    interrupted: Arc<AtomicBool>,
     info: CameraInfo,
     buffer_name: String,
     format: CameraFormat,
     frame_buffer_receiver: Arc<Receiver<FrameBuffer>>,
     frame_buffer_sender: Arc<Sender<FrameBuffer>>,
+    frames_dropped_by_channel: u64,
+    mirrored: bool,
+    output_pixel_format: Option<FourCC>,
+    zero_copy: bool,
+    exact_frame_rate: Option<f64>,
+    audio: bool,
+    throttle_on_pressure: bool,
+    audio_sender: Sender<AudioBuffer>,
+    audio_receiver: Receiver<AudioBuffer>,
 }
 
 #[cfg(target_os = "macos")]
@@ -80,17 +96,30 @@ impl AVFoundationCaptureDevice {
         let buffername = format!("{}_INDEX{}_", device_descriptor, index);
 
         let (send, recv) = flume::unbounded();
+        let (event_sender, event_receiver) = flume::unbounded();
+        let (audio_sender, audio_receiver) = flume::unbounded();
         Ok(AVFoundationCaptureDevice {
             device,
-            dev_input: None,
             session: None,
-            data_out: None,
-            data_collect: None,
+            notifier: None,
+            event_sender,
+            event_receiver,
+            event_callback: Arc::new(Mutex::new(None)),
+            interrupted: Arc::new(AtomicBool::new(false)),
             info: device_descriptor,
             buffer_name: buffername,
             format: camera_fmt,
             frame_buffer_receiver: Arc::new(recv),
             frame_buffer_sender: Arc::new(send),
+            frames_dropped_by_channel: 0,
+            mirrored: false,
+            output_pixel_format: None,
+            zero_copy: false,
+            exact_frame_rate: None,
+            audio: false,
+            throttle_on_pressure: false,
+            audio_sender,
+            audio_receiver,
         })
     }
 
@@ -113,10 +142,220 @@ impl AVFoundationCaptureDevice {
             RequestedFormat::new(RequestedFormatType::Closest(camera_format)),
         )
     }
+
+    /// Makes `AVFoundation` deliver frames in `pixel_format` (e.g. [`BGRA`](nokhwa_core::pixel_format::BGRA) or
+    /// [`NV12`](nokhwa_core::pixel_format::NV12)) instead of the format of the device, which saves converting them on the
+    /// CPU. `None` goes back to the format of the device. This also applies to a stream that is already open.
+    /// # Errors
+    /// If the stream is open and cannot deliver `pixel_format`, this will error and the format stays the same. Otherwise
+    /// this is checked once the stream is opened.
+    pub fn set_output_pixel_format(
+        &mut self,
+        pixel_format: Option<FourCC>,
+    ) -> Result<(), NokhwaError> {
+        if let Some(session) = &self.session {
+            if !session.set_pixel_format(pixel_format) {
+                return Err(output_pixel_format_error(pixel_format));
+            }
+        }
+        self.output_pixel_format = pixel_format;
+        Ok(())
+    }
+
+    /// Get the pixel format set with [`set_output_pixel_format()`](AVFoundationCaptureDevice::set_output_pixel_format).
+    #[must_use]
+    pub fn output_pixel_format(&self) -> Option<FourCC> {
+        self.output_pixel_format
+    }
+
+    /// Get the pixel formats `AVFoundation` can deliver frames in. This is empty if the stream is not open.
+    #[must_use]
+    pub fn output_pixel_formats(&self) -> Vec<FourCC> {
+        self.session
+            .as_ref()
+            .map_or_else(Vec::new, CaptureSession::pixel_formats)
+    }
+
+    /// Streams at exactly `fps` frames per second, which can be any rate within a frame rate range of the current format
+    /// (e.g. 24 or 29.97 from a format that supports 1 to 30), rather than only the top of a range like
+    /// [`set_frame_rate()`](CaptureDevice::set_frame_rate). Returns the frame rate the device reports afterwards,
+    /// which [`camera_format()`](CaptureDevice::camera_format) reports rounded. Setting another format goes back to
+    /// the frame rate of that format.
+    /// # Errors
+    /// If `fps` is not within a frame rate range of the current format, or the device cannot be configured, this will
+    /// error.
+    pub fn set_frame_rate_exact(&mut self, fps: f64) -> Result<f64, NokhwaError> {
+        let achieved = self.device.set_frame_rate_exact(fps)?;
+        self.exact_frame_rate = Some(fps);
+        self.refresh_camera_format()?;
+        Ok(achieved)
+    }
+
+    /// Gets the frame rate the device streams at, see
+    /// [`set_frame_rate_exact()`](AVFoundationCaptureDevice::set_frame_rate_exact).
+    #[must_use]
+    pub fn frame_rate_exact(&self) -> f64 {
+        self.device.frame_rate_exact()
+    }
+
+    /// Makes frames hold on to the `CVPixelBuffer` `AVFoundation` delivered them in, instead of copying them. The data is
+    /// only copied if it is taken out with [`FrameBuffer::into_owned()`], and the pixel buffer (and its `IOSurface`) can
+    /// be handed to Metal or Core Image by downcasting [`FrameBuffer::external_buffer()`] to a
+    /// [`PixelBuffer`](crate::backends::capture::PixelBuffer). This also applies to a stream that is already open.
+    ///
+    /// `AVFoundation` only has a few pixel buffers per stream, so frames must not be held on to for long, or it starts
+    /// dropping frames.
+    pub fn set_zero_copy(&mut self, zero_copy: bool) {
+        if let Some(session) = &self.session {
+            session.set_zero_copy(zero_copy);
+        }
+        self.zero_copy = zero_copy;
+    }
+
+    /// Checks if frames are delivered without being copied, see
+    /// [`set_zero_copy()`](AVFoundationCaptureDevice::set_zero_copy).
+    #[must_use]
+    pub fn zero_copy(&self) -> bool {
+        self.zero_copy
+    }
+
+    /// Captures the default microphone along with the video, in the same `AVCaptureSession`, so that audio and video
+    /// share one clock and do not drift apart. The samples are delivered on [`audio()`](AVFoundationCaptureDevice::audio).
+    /// This also applies to a stream that is already open.
+    ///
+    /// The user is asked for microphone access when audio is first captured. The application needs an
+    /// `NSMicrophoneUsageDescription` entry in its `Info.plist`.
+    /// # Errors
+    /// If the stream is open and microphone access is denied, there is no microphone, or the session does not accept it,
+    /// this will error and audio stays off. Otherwise this is checked once the stream is opened.
+    pub fn set_audio_enabled(&mut self, enabled: bool) -> Result<(), NokhwaError> {
+        if let Some(session) = &mut self.session {
+            if enabled {
+                session.add_audio(self.audio_sender.clone())?;
+            } else {
+                session.remove_audio();
+            }
+        }
+        self.audio = enabled;
+        Ok(())
+    }
+
+    /// Checks if audio is captured along with the video, see
+    /// [`set_audio_enabled()`](AVFoundationCaptureDevice::set_audio_enabled).
+    #[must_use]
+    pub fn audio_enabled(&self) -> bool {
+        self.audio
+    }
+
+    /// The channel [`AudioBuffer`]s from the microphone are delivered on while the stream is open, see
+    /// [`set_audio_enabled()`](AVFoundationCaptureDevice::set_audio_enabled). Their
+    /// [`capture_timestamp()`](AudioBuffer::capture_timestamp) is on the same clock as that of the video frames.
+    #[must_use]
+    pub fn audio(&self) -> &Receiver<AudioBuffer> {
+        &self.audio_receiver
+    }
+
+    /// The channel [`SessionEvent`]s are delivered on while the stream is open, such as the device being unplugged, the
+    /// session being interrupted, the session stopping with an error, or a control such as the zoom being changed by
+    /// something other than this device. Without these, either only shows as [`frame()`](CaptureDevice::frame) not
+    /// returning.
+    ///
+    /// Device connection events are posted on the main thread, so the main run loop must be running for them to arrive.
+    #[must_use]
+    pub fn events(&self) -> &Receiver<SessionEvent> {
+        &self.event_receiver
+    }
+
+    /// Calls `on_event` for every [`SessionEvent`], on the thread `AVFoundation` posts it on, in addition to sending it
+    /// to [`events()`](AVFoundationCaptureDevice::events). This replaces the previous callback.
+    pub fn set_event_callback(&mut self, on_event: impl FnMut(SessionEvent) + Send + 'static) {
+        *self
+            .event_callback
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Box::new(on_event));
+    }
+
+    /// Lowers the frame rate while the system reports critical thermal or power pressure, and restores it once the
+    /// pressure is back down, so that the system does not stop the session. The pressure is reported as
+    /// [`SessionEvent::SystemPressure`] either way. This also applies to a stream that is already open.
+    /// # Platform
+    /// Only iOS reports system pressure, on macOS this does nothing.
+    pub fn set_throttle_on_pressure(&mut self, throttle: bool) {
+        if let Some(notifier) = &self.notifier {
+            notifier.set_throttle_on_pressure(throttle);
+        }
+        self.throttle_on_pressure = throttle;
+    }
+
+    /// Checks if the frame rate is lowered under system pressure, see
+    /// [`set_throttle_on_pressure()`](AVFoundationCaptureDevice::set_throttle_on_pressure).
+    #[must_use]
+    pub fn throttle_on_pressure(&self) -> bool {
+        self.throttle_on_pressure
+    }
+
+    /// Checks if the system interrupted the session (e.g. by a phone call or going to the background on iOS). The
+    /// session resumes by itself once the interruption ends, until then [`frame()`](CaptureDevice::frame) errors
+    /// instead of waiting.
+    #[must_use]
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Takes a still photo through `AVCapturePhotoOutput`, at the full resolution of the device rather than that of the
+    /// stream, which keeps running. Returns the encoded JPEG or HEIF file, depending on [`PhotoSettings::codec()`].
+    /// # Errors
+    /// If the stream is not open, the device does not support `settings`, or the photo could not be taken, this will
+    /// error.
+    pub fn capture_photo(&mut self, settings: PhotoSettings) -> Result<Vec<u8>, NokhwaError> {
+        match &mut self.session {
+            Some(session) => session.capture_photo(settings),
+            None => Err(NokhwaError::ReadFrameError(
+                "Stream is not open, cannot take a photo".to_string(),
+            )),
+        }
+    }
+
+    /// Reads the controls of the device again. Controls are cached to check the values they are set to against, and
+    /// the cache is dropped by itself when the format changes, a mode is set, or (while the stream is open) a control
+    /// is changed elsewhere. This is only needed after changing the device outside of `nokhwa` with the stream closed.
+    /// # Errors
+    /// If the controls cannot be read, this will error.
+    pub fn refresh_controls(&self) -> Result<(), NokhwaError> {
+        self.device.refresh_controls()
+    }
+
+    // changes made here are not reported as `SessionEvent::ControlChanged`, only those made elsewhere
+    fn change_controls<T>(&mut self, change: impl FnOnce(&mut AVCaptureDeviceWrapper) -> T) -> T {
+        match &self.notifier {
+            Some(notifier) => notifier.without_control_events(|| change(&mut self.device)),
+            None => change(&mut self.device),
+        }
+    }
+
+    /// The center of `roi` as a point of interest, which `AVFoundation` takes from `(0.0, 0.0)` at the top left of
+    /// the frame to `(1.0, 1.0)` at the bottom right.
+    fn point_of_interest(&self, roi: Rect) -> Result<(f64, f64), NokhwaError> {
+        let resolution = self.format.resolution();
+        if !roi.fits_in(resolution) {
+            return Err(NokhwaError::SetPropertyError {
+                property: "Point of Interest".to_string(),
+                value: roi.to_string(),
+                error: format!("Not inside the {resolution} frame"),
+            });
+        }
+        let center = |start: u32, len: u32, size: u32| {
+            (f64::from(start) + f64::from(len) / 2.0) / f64::from(size)
+        };
+        Ok((
+            center(roi.x(), roi.width(), resolution.width()),
+            center(roi.y(), roi.height(), resolution.height()),
+        ))
+    }
 }
 
 #[cfg(target_os = "macos")]
-impl CaptureBackendTrait for AVFoundationCaptureDevice {
+impl CaptureDevice for AVFoundationCaptureDevice {
     fn backend(&self) -> ApiBackend {
         ApiBackend::AVFoundation
     }
@@ -137,6 +376,7 @@ impl CaptureBackendTrait for AVFoundationCaptureDevice {
     fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
         self.device.set_all(new_fmt)?;
         self.format = new_fmt;
+        self.exact_frame_rate = None;
         Ok(())
     }
 
@@ -227,84 +467,129 @@ impl CaptureBackendTrait for AVFoundationCaptureDevice {
         id: KnownCameraControl,
         value: ControlValueSetter,
     ) -> Result<(), NokhwaError> {
-        self.device.lock()?;
-        let res = self.device.set_control(id, value);
-        self.device.unlock();
-        res
+        self.change_controls(|device| {
+            device.lock()?;
+            let res = device.set_control(id, value);
+            device.unlock();
+            res
+        })
+    }
+
+    fn set_camera_controls(
+        &mut self,
+        controls: &[(KnownCameraControl, ControlValueSetter)],
+    ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+        // locked once for all of them, so they change together
+        self.change_controls(|device| {
+            device.lock()?;
+            let results = controls
+                .iter()
+                .map(|(id, value)| device.set_control(*id, value.clone()))
+                .collect();
+            device.unlock();
+            Ok(results)
+        })
     }
 
     fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.session.is_some() {
+            return Ok(());
+        }
         self.refresh_camera_format()?;
-
-        let input = AVCaptureDeviceInput::from_device(&self.device.raw_device());
-        match input {
-            Ok(_) => {}
-            Err(why) => {
-                return Err(NokhwaError::OpenDeviceError(
-                    "Cannot open device".to_string(),
-                    why.to_string(),
-                ))
-            }
+        self.frames_dropped_by_channel = 0;
+
+        let mut session = CaptureSession::new(
+            &mut self.device,
+            self.format,
+            &self.buffer_name,
+            self.frame_buffer_sender.clone(),
+        )?;
+        if self.mirrored {
+            session.set_mirrored(true);
+        }
+        session.set_zero_copy(self.zero_copy);
+        // adding the device to the session set the rounded frame rate of the format
+        if let Some(fps) = self.exact_frame_rate {
+            self.device.set_frame_rate_exact(fps)?;
+        }
+        if self.output_pixel_format.is_some() && !session.set_pixel_format(self.output_pixel_format)
+        {
+            return Err(output_pixel_format_error(self.output_pixel_format));
+        }
+        if self.audio {
+            session.add_audio(self.audio_sender.clone())?;
         }
-        let raw_device = input.unwrap();
-        let session = AVCaptureSession::new();
-        session.begin_configuration();
-        session.add_input(&raw_device);
-
-        self.device.set_all(self.format)?;
-
-        let bufname = &self.buffer_name;
-        let output = AVCaptureVideoDataOutput::new();
-        let mut capture_delegate = AVCaptureDelegate::new();
-        capture_delegate.set_sender(self.frame_buffer_sender.clone());
-        let delegate: &ProtocolObject<dyn AVCaptureVideoDataOutputSampleBufferDelegate> =
-            ProtocolObject::from_ref(&*capture_delegate);
-        let queue = Queue::new(bufname, QueueAttribute::Serial);
-        output.set_sample_buffer_delegate(delegate, &queue);
-        output.set_always_discards_late_video_frames(true);
-        session.add_output(&output);
-        session.commit_configuration();
-        session.start_running();
-
-        self.dev_input = Some(raw_device);
+        let event_sender = self.event_sender.clone();
+        let event_callback = self.event_callback.clone();
+        let interrupted = self.interrupted.clone();
+        interrupted.store(false, Ordering::Relaxed);
+        let notifier = SessionNotifier::new(&self.device, session.session(), move |event| {
+            match event {
+                SessionEvent::Interrupted(_) => interrupted.store(true, Ordering::Relaxed),
+                SessionEvent::Resumed => interrupted.store(false, Ordering::Relaxed),
+                _ => {}
+            }
+            if let Some(on_event) = event_callback
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+            {
+                on_event(event.clone());
+            }
+            let _ = event_sender.send(event);
+        });
+        notifier.set_throttle_on_pressure(self.throttle_on_pressure);
+        self.notifier = Some(notifier);
+        session.start();
         self.session = Some(session);
-        self.data_collect = Some(capture_delegate);
-        self.data_out = Some(output);
         Ok(())
     }
 
     fn is_stream_open(&self) -> bool {
-        if self.session.is_some()
-            && self.data_out.is_some()
-            && self.data_collect.is_some()
-            && self.dev_input.is_some()
-        {
-            return true;
-        }
-        match &self.session {
-            #[cfg(target_os = "ios")]
-            Some(session) => (!session.is_interrupted()) && session.is_running(),
-            #[cfg(target_os = "macos")]
-            Some(session) => session.is_running(),
-            None => false,
-        }
+        self.session.is_some()
     }
 
     fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
         self.refresh_camera_format()?;
-        let result = match self.frame_buffer_receiver.recv() {
+        // an interrupted session delivers no frames, so waiting for one would block until the interruption ends
+        let result = loop {
+            match self
+                .frame_buffer_receiver
+                .recv_timeout(INTERRUPTION_POLL_INTERVAL)
+            {
+                Ok(recv) => break recv,
+                Err(RecvTimeoutError::Timeout) if self.is_interrupted() => {
+                    return Err(NokhwaError::ReadFrameError(
+                        "The session is interrupted".to_string(),
+                    ));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(why) => {
+                    return Err(NokhwaError::ReadFrameError(why.to_string()));
+                }
+            }
+        };
+        // only the newest frame is handed out, anything older that queued up is skipped
+        self.frames_dropped_by_channel += self.frame_buffer_receiver.drain().count() as u64;
+        Ok(result)
+    }
+
+    fn frame_timeout(&mut self, timeout: Duration) -> Result<Option<FrameBuffer>, NokhwaError> {
+        self.refresh_camera_format()?;
+        let result = match self.frame_buffer_receiver.recv_timeout(timeout) {
             Ok(recv) => recv,
+            Err(RecvTimeoutError::Timeout) => return Ok(None),
             Err(why) => {
                 return Err(NokhwaError::ReadFrameError(why.to_string()));
             }
         };
-        let _ = self.frame_buffer_receiver.drain();
-        Ok(result)
+        self.frames_dropped_by_channel += self.frame_buffer_receiver.drain().count() as u64;
+        Ok(Some(result))
     }
 
     fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
         let result = match self.frame_buffer_receiver.recv() {
-            Ok(recv) => Cow::from(recv.buffer().to_vec()),
+            Ok(recv) => Cow::from(recv.into_owned()),
             Err(why) => {
                 return Err(NokhwaError::ReadFrameError(why.to_string()));
             }
@@ -312,65 +597,150 @@ impl CaptureBackendTrait for AVFoundationCaptureDevice {
         Ok(result)
     }
 
-    fn stop_stream(&mut self) -> Result<(), NokhwaError> {
-        if !self.is_stream_open() {
-            return Ok(());
+    fn stream_stats(&self) -> StreamStats {
+        StreamStats::new(
+            0,
+            self.session
+                .as_ref()
+                .map_or(0, |session| session.delegate().dropped_frames()),
+            self.frames_dropped_by_channel,
+        )
+    }
+
+    fn pause_stream(&mut self) -> Result<(), NokhwaError> {
+        match &self.session {
+            Some(session) => {
+                session.set_enabled(false);
+                Ok(())
+            }
+            None => Err(NokhwaError::GetPropertyError {
+                property: "AVCaptureSession".to_string(),
+                error: "Doesnt Exist".to_string(),
+            }),
         }
+    }
 
-        let session = match &self.session {
-            Some(session) => session,
-            None => {
-                return Err(NokhwaError::GetPropertyError {
-                    property: "AVCaptureSession".to_string(),
-                    error: "Doesnt Exist".to_string(),
-                })
+    fn resume_stream(&mut self) -> Result<(), NokhwaError> {
+        match &self.session {
+            Some(session) => {
+                // don't hand out a frame from before the pause
+                self.frame_buffer_receiver.drain();
+                session.set_enabled(true);
+                Ok(())
             }
-        };
+            None => Err(NokhwaError::GetPropertyError {
+                property: "AVCaptureSession".to_string(),
+                error: "Doesnt Exist".to_string(),
+            }),
+        }
+    }
 
-        let output = match &self.data_out {
-            Some(output) => output,
-            None => {
-                return Err(NokhwaError::GetPropertyError {
-                    property: "AVCaptureVideoDataOutput".to_string(),
-                    error: "Doesnt Exist".to_string(),
-                })
+    fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+        if let Some(session) = &self.session {
+            if !session.set_mirrored(mirrored) {
+                return Err(NokhwaError::UnsupportedOperationError(
+                    ApiBackend::AVFoundation,
+                ));
             }
-        };
+        }
+        self.mirrored = mirrored;
+        Ok(())
+    }
+
+    fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+        Ok(self.device.ptz_capabilities())
+    }
+
+    fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+        self.change_controls(|device| device.set_zoom(zoom, None))
+    }
+
+    fn ramp_zoom(&mut self, zoom: f64, rate: f64) -> Result<(), NokhwaError> {
+        self.change_controls(|device| device.set_zoom(zoom, Some(rate)))
+    }
+
+    fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+        match exposure {
+            Exposure::Auto => self
+                .change_controls(|device| device.set_continuous_auto(KnownCameraControl::Exposure)),
+            Exposure::Manual(_) => Err(NokhwaError::UnsupportedOperationError(self.backend())),
+        }
+    }
+
+    fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+        match white_balance {
+            WhiteBalance::Auto => self.change_controls(|device| {
+                device.set_continuous_auto(KnownCameraControl::WhiteBalance)
+            }),
+            WhiteBalance::Manual(_) => Err(NokhwaError::UnsupportedOperationError(self.backend())),
+        }
+    }
 
-        let input = match &self.dev_input {
-            Some(input) => input,
-            None => {
-                return Err(NokhwaError::GetPropertyError {
-                    property: "AVCaptureDeviceInput".to_string(),
-                    error: "Doesnt Exist".to_string(),
-                })
+    fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+        match focus {
+            Focus::Auto => {
+                self.change_controls(|device| device.set_continuous_auto(KnownCameraControl::Focus))
             }
-        };
+            Focus::Manual(_) => Err(NokhwaError::UnsupportedOperationError(self.backend())),
+        }
+    }
 
-        session.remove_output(output);
-        session.remove_input(input);
-        session.stop_running();
+    fn set_focus_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        let point = self.point_of_interest(roi)?;
+        self.change_controls(|device| {
+            device.set_point_of_interest(KnownCameraControl::Focus, point)
+        })
+    }
 
-        self.frame_buffer_receiver.try_iter();
-        self.dev_input = None;
-        self.session = None;
-        self.data_collect = None;
-        self.data_out = None;
+    fn set_exposure_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        let point = self.point_of_interest(roi)?;
+        self.change_controls(|device| {
+            device.set_point_of_interest(KnownCameraControl::Exposure, point)
+        })
+    }
 
+    fn poll_event(&mut self) -> Option<SessionEvent> {
+        self.event_receiver.try_recv().ok()
+    }
+
+    fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        // dropping the session stops it and detaches the device
+        self.notifier = None;
+        self.session = None;
+        self.frame_buffer_receiver.drain();
+        self.audio_receiver.drain();
         Ok(())
     }
 }
 
+#[cfg(target_os = "macos")]
+type EventCallback = Box<dyn FnMut(SessionEvent) + Send + 'static>;
+
+/// How often a blocking [`frame()`](CaptureDevice::frame) checks if the session was interrupted.
+#[cfg(target_os = "macos")]
+const INTERRUPTION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(target_os = "macos")]
+fn output_pixel_format_error(pixel_format: Option<FourCC>) -> NokhwaError {
+    NokhwaError::SetPropertyError {
+        property: "Output pixel format".to_string(),
+        value: pixel_format.map_or_else(|| "None".to_string(), |format| format.to_string()),
+        error: "The video data output cannot deliver this format".to_string(),
+    }
+    .with_kind(ErrorKind::FormatUnsupported)
+}
+
 #[cfg(target_os = "macos")]
 impl Drop for AVFoundationCaptureDevice {
     fn drop(&mut self) {
-        if self.stop_stream().is_err() {}
+        self.notifier = None;
+        self.session = None;
         self.device.unlock();
     }
 }
 
 /// The backend struct that interfaces with V4L2.
-/// To see what this does, please see [`CaptureBackendTrait`].
+/// To see what this does, please see [`CaptureDevice`].
 /// # Quirks
 /// - While working with `iOS` is allowed, it is not officially supported and may not work.
 /// - You **must** call [`nokhwa_initialize`](crate::nokhwa_initialize) **before** doing anything with `AVFoundation`.
@@ -413,7 +783,7 @@ impl AVFoundationCaptureDevice {
 
 #[cfg(not(target_os = "macos"))]
 #[allow(unreachable_code)]
-impl CaptureBackendTrait for AVFoundationCaptureDevice {
+impl CaptureDevice for AVFoundationCaptureDevice {
     fn backend(&self) -> ApiBackend {
         todo!()
     }
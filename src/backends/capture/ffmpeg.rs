@@ -0,0 +1,481 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use ffmpeg_next::{
+    codec, device, ffi,
+    format::{self, context::Input, Pixel},
+    media, Dictionary, Format, Packet,
+};
+use four_cc::FourCC;
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    pixel_format::{GRAY, MJPEG, NV12, RAWRGB, UYVY, UYVY_APPLE, V210, YUV420, YUYV},
+    traits::CaptureDevice,
+    types::{
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
+        KnownCameraControl, RequestedFormat, RequestedFormatType, Resolution,
+    },
+};
+use std::{borrow::Cow, collections::HashMap, ffi::CStr, ptr, time::Instant};
+
+#[cfg(target_os = "linux")]
+const DEMUXER: &str = "v4l2";
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const DEMUXER: &str = "avfoundation";
+#[cfg(target_os = "windows")]
+const DEMUXER: &str = "dshow";
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "windows"
+)))]
+const DEMUXER: &str = "";
+
+const DECKLINK_DEMUXER: &str = "decklink";
+
+fn demuxer_backend(demuxer: &str) -> ApiBackend {
+    if demuxer == DECKLINK_DEMUXER {
+        ApiBackend::DeckLink
+    } else {
+        ApiBackend::FFmpeg
+    }
+}
+
+fn demuxer(name: &str) -> Result<format::Input, NokhwaError> {
+    ffmpeg_next::init().map_err(|why| NokhwaError::InitializeError {
+        backend: demuxer_backend(name),
+        error: why.to_string(),
+    })?;
+    device::input::video()
+        .filter_map(|fmt| match fmt {
+            Format::Input(input) if unsafe { !input.as_ptr().is_null() } => Some(input),
+            _ => None,
+        })
+        .find(|input| input.name() == name)
+        .ok_or(NokhwaError::InitializeError {
+            backend: demuxer_backend(name),
+            error: format!("FFmpeg was built without the `{name}` input device"),
+        })
+}
+
+fn fourcc_to_ffmpeg(fourcc: FourCC) -> Option<&'static str> {
+    match fourcc {
+        YUYV => Some("yuyv422"),
+        UYVY | UYVY_APPLE => Some("uyvy422"),
+        NV12 | YUV420 => Some("nv12"),
+        GRAY => Some("gray"),
+        RAWRGB => Some("rgb24"),
+        MJPEG => Some("mjpeg"),
+        V210 => Some("yuv422p10"),
+        _ => None,
+    }
+}
+
+fn ffmpeg_to_fourcc(codec: codec::Id, pixel: Pixel) -> Option<FourCC> {
+    if codec == codec::Id::MJPEG {
+        return Some(MJPEG);
+    }
+    if codec == codec::Id::V210 {
+        return Some(V210);
+    }
+    match pixel {
+        Pixel::YUYV422 => Some(YUYV),
+        Pixel::UYVY422 => Some(UYVY),
+        Pixel::NV12 => Some(NV12),
+        Pixel::GRAY8 => Some(GRAY),
+        Pixel::RGB24 => Some(RAWRGB),
+        _ => None,
+    }
+}
+
+/// Query the system for a list of available devices through `avdevice`.
+/// # Quirks
+/// - The `unique_id` of the [`CameraInfo`] is the URL to pass to the demuxer (e.g. `/dev/video0` or `video=Integrated Camera`).
+/// - `avfoundation` does not implement device listing in `FFmpeg`, so this will return an empty list on Mac OS. Use a [`CameraIndex::Index`] instead.
+/// # Errors
+/// If `FFmpeg` cannot be initialized or was built without the platform's input device, this will error.
+pub fn query_ffmpeg() -> Result<Vec<CameraInfo>, NokhwaError> {
+    query_demuxer(DEMUXER)
+}
+
+/// Query the system for a list of Blackmagic `DeckLink` inputs through `FFmpeg`'s `decklink` input device.
+/// # Errors
+/// If `FFmpeg` cannot be initialized or was built without `--enable-decklink`, this will error.
+pub fn query_decklink() -> Result<Vec<CameraInfo>, NokhwaError> {
+    query_demuxer(DECKLINK_DEMUXER)
+}
+
+fn query_demuxer(name: &str) -> Result<Vec<CameraInfo>, NokhwaError> {
+    let input = demuxer(name)?;
+    let mut list: *mut ffi::AVDeviceInfoList = ptr::null_mut();
+    // SAFETY: `input` is a valid, static demuxer and `list` is freed below.
+    let result = unsafe {
+        ffi::avdevice_list_input_sources(
+            input.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+            &raw mut list,
+        )
+    };
+    if result < 0 || list.is_null() {
+        // ENOSYS: this demuxer cannot list devices.
+        return Ok(vec![]);
+    }
+
+    let mut devices = vec![];
+    unsafe {
+        let count = usize::try_from((*list).nb_devices).unwrap_or_default();
+        for idx in 0..count {
+            let info = *(*list).devices.add(idx);
+            let name = CStr::from_ptr((*info).device_name)
+                .to_string_lossy()
+                .to_string();
+            let description = CStr::from_ptr((*info).device_description)
+                .to_string_lossy()
+                .to_string();
+            let url = if name == "dshow" {
+                format!("video={name}")
+            } else {
+                name
+            };
+            devices.push(CameraInfo::new(
+                &url,
+                &description,
+                "",
+                "",
+                &format!("FFmpeg {name} Device"),
+                "",
+            ));
+        }
+        ffi::avdevice_free_list_devices(&raw mut list);
+    }
+    Ok(devices)
+}
+
+fn device_url(demuxer: &str, index: &CameraIndex) -> Result<String, NokhwaError> {
+    match index {
+        CameraIndex::String(url) => Ok(url.clone()),
+        CameraIndex::Index(idx) => match demuxer {
+            "v4l2" => Ok(format!("/dev/video{idx}")),
+            "avfoundation" => Ok(idx.to_string()),
+            _ => query_demuxer(demuxer)?
+                .into_iter()
+                .nth(*idx as usize)
+                .map(|info| info.unique_id())
+                .ok_or(NokhwaError::OpenDeviceError(
+                    index.to_string(),
+                    "No such device".to_string(),
+                )),
+        },
+    }
+}
+
+/// The backend struct that captures through `FFmpeg`'s `avdevice` demuxers (`v4l2`, `avfoundation`, `dshow`).
+/// This is meant as a fallback for platforms or configurations where no native backend is compiled in.
+/// To see what this does, please see [`CaptureDevice`].
+/// # Quirks
+/// - `FFmpeg` cannot list the formats a device supports, so the [`RequestedFormat`] is passed straight to the demuxer
+///   and compatibility lists will return an [`UnsupportedOperationError`](NokhwaError::UnsupportedOperationError).
+/// - Camera controls are not supported.
+/// - Changing the [`CameraFormat`] reopens the device.
+/// - When created with [`new_decklink()`](FFmpegCaptureDevice::new_decklink), the signal format is detected by the card,
+///   so only the [`FourCC`] of the [`RequestedFormat`] is used ([`UYVY`] for 8-bit or [`V210`] for 10-bit YUV).
+pub struct FFmpegCaptureDevice {
+    demuxer: &'static str,
+    url: String,
+    info: CameraInfo,
+    format: CameraFormat,
+    input: Option<Input>,
+    stream_index: usize,
+    packet: Packet,
+}
+
+impl FFmpegCaptureDevice {
+    /// Creates a new capture device using `FFmpeg`. A [`CameraIndex::String`] is passed to the demuxer as-is,
+    /// while a [`CameraIndex::Index`] is resolved to the platform's device naming.
+    /// # Errors
+    /// This function will error if `FFmpeg` cannot open the device with the requested format.
+    pub fn new(index: &CameraIndex, cam_fmt: RequestedFormat) -> Result<Self, NokhwaError> {
+        FFmpegCaptureDevice::with_demuxer(DEMUXER, index, cam_fmt)
+    }
+
+    /// Creates a new capture device for a Blackmagic `DeckLink` SDI/HDMI input, using `FFmpeg`'s `decklink` input device.
+    /// A [`CameraIndex::String`] should be the name of the input as returned by [`query_decklink()`].
+    /// # Errors
+    /// This function will error if `FFmpeg` was built without `DeckLink` support or the input cannot be opened.
+    pub fn new_decklink(
+        index: &CameraIndex,
+        cam_fmt: RequestedFormat,
+    ) -> Result<Self, NokhwaError> {
+        FFmpegCaptureDevice::with_demuxer(DECKLINK_DEMUXER, index, cam_fmt)
+    }
+
+    fn with_demuxer(
+        demuxer: &'static str,
+        index: &CameraIndex,
+        cam_fmt: RequestedFormat,
+    ) -> Result<Self, NokhwaError> {
+        let url = device_url(demuxer, index)?;
+        let format = match cam_fmt.requested_format() {
+            RequestedFormatType::Exact(format) | RequestedFormatType::Closest(format) => format,
+            RequestedFormatType::Range {
+                max_resolution,
+                min_frame_rate,
+                ..
+            } => CameraFormat::new(max_resolution, MJPEG, min_frame_rate),
+            RequestedFormatType::HighestResolution(resolution) => {
+                CameraFormat::new(resolution, MJPEG, 30)
+            }
+            RequestedFormatType::HighestFrameRate(frame_rate) => {
+                CameraFormat::new(Resolution::new(640, 480), MJPEG, frame_rate)
+            }
+            _ => CameraFormat::default(),
+        };
+
+        let mut device = FFmpegCaptureDevice {
+            demuxer,
+            info: CameraInfo::new(&url, &url, "", "", &format!("FFmpeg {demuxer} Device"), ""),
+            url,
+            format,
+            input: None,
+            stream_index: 0,
+            packet: Packet::empty(),
+        };
+        device.open_stream()?;
+        Ok(device)
+    }
+
+    fn options(&self) -> Dictionary<'static> {
+        let mut options = Dictionary::new();
+        if self.demuxer == DECKLINK_DEMUXER {
+            if let Some(pixel @ ("uyvy422" | "yuv422p10")) = fourcc_to_ffmpeg(self.format.format())
+            {
+                options.set("raw_format", pixel);
+            }
+            return options;
+        }
+        options.set(
+            "video_size",
+            &format!("{}x{}", self.format.width(), self.format.height()),
+        );
+        options.set("framerate", &self.format.frame_rate().to_string());
+        if let Some(pixel) = fourcc_to_ffmpeg(self.format.format()) {
+            match (self.demuxer, pixel) {
+                ("v4l2", _) => options.set("input_format", pixel),
+                ("dshow", "mjpeg") => options.set("vcodec", pixel),
+                (_, "mjpeg") => {}
+                _ => options.set("pixel_format", pixel),
+            }
+        }
+        options
+    }
+}
+
+impl CaptureDevice for FFmpegCaptureDevice {
+    fn backend(&self) -> ApiBackend {
+        demuxer_backend(self.demuxer)
+    }
+
+    fn camera_info(&self) -> &CameraInfo {
+        &self.info
+    }
+
+    fn refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
+        let input = self.input.as_ref().ok_or(NokhwaError::GetPropertyError {
+            property: "CameraFormat".to_string(),
+            error: "Stream Not Started".to_string(),
+        })?;
+        let stream = input
+            .stream(self.stream_index)
+            .ok_or(NokhwaError::GetPropertyError {
+                property: "CameraFormat".to_string(),
+                error: "Video stream disappeared".to_string(),
+            })?;
+        let decoder = codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|ctx| ctx.decoder().video())
+            .map_err(|why| NokhwaError::GetPropertyError {
+                property: "CameraFormat".to_string(),
+                error: why.to_string(),
+            })?;
+        let fourcc = ffmpeg_to_fourcc(stream.parameters().id(), decoder.format()).ok_or(
+            NokhwaError::GetPropertyError {
+                property: "FourCC".to_string(),
+                error: format!("Unknown FFmpeg pixel format {:?}", decoder.format()),
+            },
+        )?;
+        let rate = stream.avg_frame_rate();
+        #[allow(clippy::cast_sign_loss)]
+        let frame_rate = if rate.denominator() == 0 {
+            self.format.frame_rate()
+        } else {
+            (f64::from(rate).round() as u32).max(1)
+        };
+
+        self.format = CameraFormat::new(
+            Resolution::new(decoder.width(), decoder.height()),
+            fourcc,
+            frame_rate,
+        );
+        Ok(())
+    }
+
+    fn camera_format(&self) -> CameraFormat {
+        self.format
+    }
+
+    fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+        let prev_format = self.format;
+        let was_open = self.is_stream_open();
+        self.format = new_fmt;
+        self.stop_stream()?;
+        if let Err(why) = self.open_stream() {
+            self.format = prev_format;
+            if was_open {
+                self.open_stream()?;
+            }
+            return Err(NokhwaError::SetPropertyError {
+                property: "CameraFormat".to_string(),
+                value: new_fmt.to_string(),
+                error: why.to_string(),
+            });
+        }
+        if !was_open {
+            self.stop_stream()?;
+        }
+        Ok(())
+    }
+
+    fn compatible_list_by_resolution(
+        &mut self,
+        _fourcc: FourCC,
+    ) -> Result<HashMap<Resolution, Vec<u32>>, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    fn compatible_fourcc(&mut self) -> Result<Vec<FourCC>, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    fn resolution(&self) -> Resolution {
+        self.format.resolution()
+    }
+
+    fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_resolution(new_res);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.format.frame_rate()
+    }
+
+    fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_frame_rate(new_fps);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_format(&self) -> FourCC {
+        self.format.format()
+    }
+
+    fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_format(fourcc);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn camera_control(&self, _control: KnownCameraControl) -> Result<CameraControl, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    fn set_camera_control(
+        &mut self,
+        _id: KnownCameraControl,
+        _value: ControlValueSetter,
+    ) -> Result<(), NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(self.backend()))
+    }
+
+    fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.input.is_some() {
+            return Ok(());
+        }
+        let demuxer = demuxer(self.demuxer)?;
+        let input = format::open_with(&self.url, &Format::Input(demuxer), self.options())
+            .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?
+            .input();
+        self.stream_index = input
+            .streams()
+            .best(media::Type::Video)
+            .ok_or(NokhwaError::OpenStreamError(
+                "Device has no video stream".to_string(),
+            ))?
+            .index();
+        self.input = Some(input);
+        self.refresh_camera_format()
+    }
+
+    fn is_stream_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
+        let cam_fmt = self.format;
+        let raw_frame = self.frame_raw()?;
+        Ok(FrameBuffer::new(
+            cam_fmt.resolution(),
+            &raw_frame,
+            cam_fmt.format(),
+            Instant::now(),
+        ))
+    }
+
+    fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+        let input = match &mut self.input {
+            Some(input) => input,
+            None => {
+                return Err(NokhwaError::ReadFrameError(
+                    "Stream Not Started".to_string(),
+                ))
+            }
+        };
+        loop {
+            self.packet
+                .read(input)
+                .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?;
+            if self.packet.stream() == self.stream_index {
+                break;
+            }
+        }
+        match self.packet.data() {
+            Some(data) => Ok(Cow::Borrowed(data)),
+            None => Err(NokhwaError::ReadFrameError("Empty packet".to_string())),
+        }
+    }
+
+    fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        self.input = None;
+        Ok(())
+    }
+}
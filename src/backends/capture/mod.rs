@@ -28,6 +28,15 @@ mod msmf_backend;
 ))]
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-msmf")))]
 pub use msmf_backend::MediaFoundationCaptureDevice;
+#[cfg(all(feature = "input-msmf", target_os = "windows"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-msmf")))]
+pub use nokhwa_bindings_windows::extended::{
+    extended_camera_controls, BackgroundSegmentation, DigitalWindow, EyeGazeCorrection,
+    BACKGROUND_SEGMENTATION, DIGITAL_WINDOW, EYE_GAZE_CORRECTION,
+};
+#[cfg(all(feature = "d3d11", target_os = "windows"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "d3d11")))]
+pub use nokhwa_bindings_windows::wmf::{D3D11Texture, SHARED_TEXTURE_COUNT};
 #[cfg(any(
     all(
         feature = "input-avfoundation",
@@ -53,3 +62,24 @@ mod avfoundation;
 ))]
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-avfoundation")))]
 pub use avfoundation::AVFoundationCaptureDevice;
+#[cfg(all(feature = "input-avfoundation", target_os = "macos"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-avfoundation")))]
+pub use nokhwa_bindings_macos::PixelBuffer;
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "metal")))]
+pub use nokhwa_bindings_macos::{MetalTexture, MetalTextureCache};
+#[cfg(feature = "input-gige")]
+mod aravis;
+#[cfg(feature = "input-gige")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-gige")))]
+pub use self::aravis::{query_aravis, AravisCaptureDevice};
+#[cfg(feature = "input-ndi")]
+mod ndi;
+#[cfg(feature = "input-ndi")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-ndi")))]
+pub use ndi::{query_ndi, NDICaptureDevice};
+#[cfg(feature = "input-ffmpeg")]
+mod ffmpeg;
+#[cfg(feature = "input-ffmpeg")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-ffmpeg")))]
+pub use ffmpeg::{query_decklink, query_ffmpeg, FFmpegCaptureDevice};
@@ -14,21 +14,33 @@
  * limitations under the License.
  */
 use four_cc::FourCC;
-use nokhwa_bindings_windows::wmf::MediaFoundationDevice;
+#[cfg(feature = "d3d11")]
+use nokhwa_bindings_windows::wmf::D3D11Texture;
+use nokhwa_bindings_windows::wmf::{
+    extended_camera_controls, system_time, BackgroundSegmentation, DigitalWindow,
+    EyeGazeCorrection, MediaFoundationDevice,
+};
 use nokhwa_core::{
-    buffer::FrameBuffer,
+    buffer::{BufferPool, FrameBuffer},
+    conversion::{mjpeg_decoder, Decoder},
     error::NokhwaError,
-    traits::CaptureBackendTrait,
+    pixel_format::{MJPEG, NV12},
+    traits::CaptureDevice,
     types::{
         all_known_camera_controls, ApiBackend, CameraControl, CameraFormat, CameraIndex,
-        CameraInfo, ControlValueSetter, KnownCameraControl, RequestedFormat, RequestedFormatType,
-        Resolution,
+        CameraInfo, ControlValueSetter, Exposure, Focus, KnownCameraControl, PtzCapabilities, Rect,
+        RequestedFormat, RequestedFormatType, Resolution, SessionEvent, WhiteBalance,
     },
 };
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Instant,
+};
 
 /// The backend that deals with Media Foundation on Windows.
-/// To see what this does, please see [`CaptureBackendTrait`].
+/// To see what this does, please see [`CaptureDevice`].
 ///
 /// Note: This requires Windows 7 or newer to work.
 /// # Quirks
@@ -37,10 +49,27 @@ use std::{borrow::Cow, collections::HashMap};
 /// - The symbolic link for the device is listed in the `misc` attribute of the [`CameraInfo`].
 /// - The names may contain invalid characters since they were converted from UTF16.
 /// - When you call new or drop the struct, `initialize`/`de_initialize` will automatically be called.
+/// - If the MJPEG decoder is [`Decoder::Hardware`] when an MJPEG format is opened, Media Foundation decodes the frames
+///   into [`NV12`] instead, see [`set_decoded_output()`](MediaFoundationCaptureDevice::set_decoded_output).
+/// - Extended controls, such as those of Windows Studio Effects, are [`KnownCameraControl::Other`]s, see
+///   [`extended_camera_controls()`]. They also have typed setters, such as
+///   [`set_background_segmentation()`](MediaFoundationCaptureDevice::set_background_segmentation).
+/// - With the `d3d11` feature, frames can be read as Direct3D 11 textures instead, see
+///   [`set_d3d11_output()`](MediaFoundationCaptureDevice::set_d3d11_output).
+/// - Opening a camera that is blocked by the privacy settings or used by another application fails with an
+///   [`ErrorKind::PermissionDenied`](nokhwa_core::error::ErrorKind::PermissionDenied) or
+///   [`ErrorKind::DeviceBusy`](nokhwa_core::error::ErrorKind::DeviceBusy) error saying so. Querying reports this ahead
+///   of time as the [`availability()`](CameraInfo::availability) of the camera.
+/// - Unplugging the camera while streaming makes [`frame()`](CaptureDevice::frame) return an
+///   [`ErrorKind::Disconnected`](nokhwa_core::error::ErrorKind::Disconnected) error, and sends
+///   [`SessionEvent::Disconnected`] to [`events()`](MediaFoundationCaptureDevice::events).
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-msmf")))]
 pub struct MediaFoundationCaptureDevice {
     inner: MediaFoundationDevice,
     info: CameraInfo,
+    pool: BufferPool,
+    event_sender: Sender<SessionEvent>,
+    event_receiver: Receiver<SessionEvent>,
 }
 
 impl MediaFoundationCaptureDevice {
@@ -51,10 +80,12 @@ impl MediaFoundationCaptureDevice {
         let mut mf_device = MediaFoundationDevice::new(index.clone())?;
 
         let info = CameraInfo::new(
+            &mf_device.symlink(),
             &mf_device.name(),
+            "",
+            "",
             "MediaFoundation Camera Device",
-            &mf_device.symlink(),
-            index.clone(),
+            "",
         );
 
         let availible = mf_device.compatible_format_list()?;
@@ -67,11 +98,18 @@ impl MediaFoundationCaptureDevice {
             })?;
 
         println!("Desired format: {:?}", desired);
+        if desired.format() == MJPEG && mjpeg_decoder() == Decoder::Hardware {
+            mf_device.set_decoded_output(Some(NV12))?;
+        }
         mf_device.set_format(desired)?;
 
+        let (event_sender, event_receiver) = channel();
         let mut new_cam = MediaFoundationCaptureDevice {
             inner: mf_device,
             info,
+            pool: BufferPool::default(),
+            event_sender,
+            event_receiver,
         };
         new_cam.refresh_camera_format()?;
         Ok(new_cam)
@@ -100,16 +138,126 @@ impl MediaFoundationCaptureDevice {
     pub fn supported_camera_controls(&self) -> Vec<KnownCameraControl> {
         let mut supported_camera_controls: Vec<KnownCameraControl> = vec![];
 
-        for camera_control in all_known_camera_controls() {
+        for camera_control in all_known_camera_controls()
+            .into_iter()
+            .chain(extended_camera_controls())
+        {
             if let Ok(supported) = self.inner.control(camera_control) {
                 supported_camera_controls.push(supported.control());
             }
         }
         supported_camera_controls
     }
+
+    /// Lets Media Foundation decode frames into `output`, [`NV12`] or [`BGRA`](nokhwa_core::pixel_format::BGRA),
+    /// instead of returning them as the camera sends them. This is how MJPEG and H.264 cameras are decoded without
+    /// the CPU, on the GPU's decoder where there is one. `None` turns decoding off again.
+    ///
+    /// [`camera_format()`](CaptureDevice::camera_format) stays the format of the camera, while frames have
+    /// the decoded format.
+    /// # Errors
+    /// This errors if the stream is open, or Media Foundation cannot decode the current format into `output`.
+    pub fn set_decoded_output(&mut self, output: Option<FourCC>) -> Result<(), NokhwaError> {
+        self.inner.set_decoded_output(output)
+    }
+
+    /// Gets the format Media Foundation decodes frames into, if it does. See [`set_decoded_output()`](Self::set_decoded_output).
+    #[must_use]
+    pub fn decoded_output(&self) -> Option<FourCC> {
+        self.inner.decoded_output()
+    }
+
+    /// Lets Media Foundation decode frames on a Direct3D 11 device, so that [`frame_texture()`](Self::frame_texture)
+    /// returns them as textures without the copy into system memory, which is most of the cost of capturing 4K.
+    /// Frames are decoded into [`NV12`] unless [`set_decoded_output()`](Self::set_decoded_output) says otherwise.
+    /// # Errors
+    /// This errors if the stream is open, there is no hardware Direct3D 11 device, or Media Foundation cannot decode
+    /// the current format on it.
+    #[cfg(feature = "d3d11")]
+    #[cfg_attr(feature = "docs-features", doc(cfg(feature = "d3d11")))]
+    pub fn set_d3d11_output(&mut self, enabled: bool) -> Result<(), NokhwaError> {
+        self.inner.set_d3d11_output(enabled)
+    }
+
+    /// Gets whether frames are decoded on a Direct3D 11 device. See [`set_d3d11_output()`](Self::set_d3d11_output).
+    #[cfg(feature = "d3d11")]
+    #[cfg_attr(feature = "docs-features", doc(cfg(feature = "d3d11")))]
+    #[must_use]
+    pub fn d3d11_output(&self) -> bool {
+        self.inner.d3d11_output()
+    }
+
+    /// Reads the next frame as a [`D3D11Texture`], shared with an NT handle that other Direct3D devices, such as
+    /// the one of `wgpu`'s DX12 backend, can open. The texture is reused after
+    /// [`SHARED_TEXTURE_COUNT`](nokhwa_bindings_windows::wmf::SHARED_TEXTURE_COUNT) more frames.
+    /// # Errors
+    /// This errors if [`set_d3d11_output()`](Self::set_d3d11_output) is off, the stream is not open, or reading the
+    /// frame fails.
+    #[cfg(feature = "d3d11")]
+    #[cfg_attr(feature = "docs-features", doc(cfg(feature = "d3d11")))]
+    pub fn frame_texture(&mut self) -> Result<D3D11Texture, NokhwaError> {
+        self.inner.read_texture()
+    }
+
+    /// The channel [`SessionEvent`]s are delivered on while the stream is open. This is the camera being unplugged
+    /// ([`SessionEvent::Disconnected`]) or plugged back in ([`SessionEvent::Reconnected`]), after which it has to be
+    /// opened again, and controls changed by something else ([`SessionEvent::ControlChanged`]). Controls are checked for
+    /// changes every [`CONTROL_POLL_INTERVAL`](nokhwa_bindings_windows::wmf::CONTROL_POLL_INTERVAL) while frames are read.
+    #[must_use]
+    pub fn events(&self) -> &Receiver<SessionEvent> {
+        &self.event_receiver
+    }
+
+    /// Gets the eye gaze correction of the camera.
+    /// # Errors
+    /// This errors if the camera does not have the control.
+    pub fn eye_gaze_correction(&self) -> Result<EyeGazeCorrection, NokhwaError> {
+        self.inner.eye_gaze_correction()
+    }
+
+    /// Sets the eye gaze correction of the camera.
+    /// # Errors
+    /// This errors if the camera does not have the control, or does not support `correction`.
+    pub fn set_eye_gaze_correction(
+        &mut self,
+        correction: EyeGazeCorrection,
+    ) -> Result<(), NokhwaError> {
+        self.inner.set_eye_gaze_correction(correction)
+    }
+
+    /// Gets what the camera does with the background.
+    /// # Errors
+    /// This errors if the camera does not have the control.
+    pub fn background_segmentation(&self) -> Result<BackgroundSegmentation, NokhwaError> {
+        self.inner.background_segmentation()
+    }
+
+    /// Sets what the camera does with the background, e.g. blurring it.
+    /// # Errors
+    /// This errors if the camera does not have the control, or does not support `segmentation`.
+    pub fn set_background_segmentation(
+        &mut self,
+        segmentation: BackgroundSegmentation,
+    ) -> Result<(), NokhwaError> {
+        self.inner.set_background_segmentation(segmentation)
+    }
+
+    /// Gets the part of the sensor the camera streams.
+    /// # Errors
+    /// This errors if the camera does not have the control.
+    pub fn digital_window(&self) -> Result<DigitalWindow, NokhwaError> {
+        self.inner.digital_window()
+    }
+
+    /// Sets the part of the sensor the camera streams, or lets the camera follow faces.
+    /// # Errors
+    /// This errors if the camera does not have the control, or does not support `window`.
+    pub fn set_digital_window(&mut self, window: DigitalWindow) -> Result<(), NokhwaError> {
+        self.inner.set_digital_window(window)
+    }
 }
 
-impl CaptureBackendTrait for MediaFoundationCaptureDevice {
+impl CaptureDevice for MediaFoundationCaptureDevice {
     fn backend(&self) -> ApiBackend {
         ApiBackend::MediaFoundation
     }
@@ -214,7 +362,10 @@ impl CaptureBackendTrait for MediaFoundationCaptureDevice {
 
     fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
         let mut camera_ctrls = Vec::with_capacity(15);
-        for ctrl_id in all_known_camera_controls() {
+        for ctrl_id in all_known_camera_controls()
+            .into_iter()
+            .chain(extended_camera_controls())
+        {
             let ctrl = match self.camera_control(ctrl_id) {
                 Ok(v) => v,
                 Err(_) => continue,
@@ -234,7 +385,22 @@ impl CaptureBackendTrait for MediaFoundationCaptureDevice {
         self.inner.set_control(id, value)
     }
 
+    fn set_camera_controls(
+        &mut self,
+        controls: &[(KnownCameraControl, ControlValueSetter)],
+    ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+        self.inner.set_controls(controls)
+    }
+
     fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        let sender = self.event_sender.clone();
+        self.inner.watch_connection(move |event| {
+            let _ = sender.send(event);
+        })?;
+        let sender = self.event_sender.clone();
+        self.inner.watch_controls(move |event| {
+            let _ = sender.send(event);
+        });
         self.inner.start_stream()
     }
 
@@ -245,17 +411,70 @@ impl CaptureBackendTrait for MediaFoundationCaptureDevice {
     fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
         self.refresh_camera_format()?;
         let self_ctrl = self.camera_format();
-        Ok(FrameBuffer::new(
+        let frame = FrameBuffer::from_pooled(
             self_ctrl.resolution(),
-            &self.inner.raw_bytes()?,
-            self_ctrl.format(),
-        ))
+            self.pool.copy_from_slice(&self.inner.raw_bytes()?),
+            self.inner.output_format(),
+            Instant::now(),
+        );
+        let frame = match self.inner.last_row_stride() {
+            Some(row_stride) => frame.with_row_stride(row_stride),
+            None => frame,
+        };
+        Ok(match self.inner.last_sample_time() {
+            Some(sample_time) => frame.with_capture_timestamp(sample_time, system_time()),
+            None => frame,
+        })
     }
 
     fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
         self.inner.raw_bytes()
     }
 
+    fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+        self.inner.set_mirrored(mirrored)
+    }
+
+    fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+        self.inner.set_crop(crop)
+    }
+
+    fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+        self.inner.ptz_capabilities()
+    }
+
+    fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+        self.inner.set_zoom(zoom)
+    }
+
+    fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.inner.set_pan_tilt_absolute(pan, tilt)
+    }
+
+    fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.inner.set_pan_tilt_relative(pan, tilt)
+    }
+
+    fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+        self.inner.set_exposure(exposure)
+    }
+
+    fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+        self.inner.set_white_balance(white_balance)
+    }
+
+    fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+        self.inner.set_focus(focus)
+    }
+
+    fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+        self.inner.set_brightness(brightness)
+    }
+
+    fn poll_event(&mut self) -> Option<SessionEvent> {
+        self.event_receiver.try_recv().ok()
+    }
+
     fn stop_stream(&mut self) -> Result<(), NokhwaError> {
         self.inner.stop_stream();
         Ok(())
@@ -0,0 +1,468 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use four_cc::FourCC;
+use nokhwa_core::{
+    buffer::{FrameBuffer, PooledBuffer},
+    error::NokhwaError,
+    pixel_format::{BGRA, UYVY},
+    traits::CaptureDevice,
+    types::{
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
+        FrameRate, KnownCameraControl, RequestedFormat, Resolution,
+    },
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::{CStr, CString},
+    ptr, slice,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[allow(non_camel_case_types, non_snake_case, dead_code)]
+mod ffi {
+    use std::ffi::{c_char, c_float, c_int, c_void};
+
+    pub type NDIlib_find_instance_t = *mut c_void;
+    pub type NDIlib_recv_instance_t = *mut c_void;
+
+    pub const NDILIB_FRAME_TYPE_NONE: c_int = 0;
+    pub const NDILIB_FRAME_TYPE_VIDEO: c_int = 1;
+    pub const NDILIB_FRAME_TYPE_ERROR: c_int = 4;
+    pub const NDILIB_RECV_COLOR_FORMAT_UYVY_BGRA: c_int = 1;
+    pub const NDILIB_RECV_BANDWIDTH_HIGHEST: c_int = 100;
+    pub const NDILIB_TIMESTAMP_UNDEFINED: i64 = i64::MAX;
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct NDIlib_source_t {
+        pub p_ndi_name: *const c_char,
+        pub p_url_address: *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct NDIlib_find_create_t {
+        pub show_local_sources: bool,
+        pub p_groups: *const c_char,
+        pub p_extra_ips: *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct NDIlib_recv_create_v3_t {
+        pub source_to_connect_to: NDIlib_source_t,
+        pub color_format: c_int,
+        pub bandwidth: c_int,
+        pub allow_video_fields: bool,
+        pub p_ndi_recv_name: *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct NDIlib_video_frame_v2_t {
+        pub xres: c_int,
+        pub yres: c_int,
+        pub FourCC: u32,
+        pub frame_rate_N: c_int,
+        pub frame_rate_D: c_int,
+        pub picture_aspect_ratio: c_float,
+        pub frame_format_type: c_int,
+        pub timecode: i64,
+        pub p_data: *mut u8,
+        pub line_stride_in_bytes: c_int,
+        pub p_metadata: *const c_char,
+        pub timestamp: i64,
+    }
+
+    #[cfg_attr(windows, link(name = "Processing.NDI.Lib.x64"))]
+    #[cfg_attr(not(windows), link(name = "ndi"))]
+    extern "C" {
+        pub fn NDIlib_initialize() -> bool;
+        pub fn NDIlib_find_create_v2(
+            p_create_settings: *const NDIlib_find_create_t,
+        ) -> NDIlib_find_instance_t;
+        pub fn NDIlib_find_destroy(p_instance: NDIlib_find_instance_t);
+        pub fn NDIlib_find_wait_for_sources(
+            p_instance: NDIlib_find_instance_t,
+            timeout_in_ms: u32,
+        ) -> bool;
+        pub fn NDIlib_find_get_current_sources(
+            p_instance: NDIlib_find_instance_t,
+            p_no_sources: *mut u32,
+        ) -> *const NDIlib_source_t;
+        pub fn NDIlib_recv_create_v3(
+            p_create_settings: *const NDIlib_recv_create_v3_t,
+        ) -> NDIlib_recv_instance_t;
+        pub fn NDIlib_recv_destroy(p_instance: NDIlib_recv_instance_t);
+        pub fn NDIlib_recv_capture_v2(
+            p_instance: NDIlib_recv_instance_t,
+            p_video_data: *mut NDIlib_video_frame_v2_t,
+            p_audio_data: *mut c_void,
+            p_metadata: *mut c_void,
+            timeout_in_ms: u32,
+        ) -> c_int;
+        pub fn NDIlib_recv_free_video_v2(
+            p_instance: NDIlib_recv_instance_t,
+            p_video_data: *const NDIlib_video_frame_v2_t,
+        );
+    }
+}
+
+// How long to wait for senders to announce themselves when querying.
+const DISCOVERY_TIMEOUT_MS: u32 = 1000;
+// How long to wait for a frame before giving up.
+const FRAME_TIMEOUT_MS: u32 = 5000;
+
+fn initialize() -> Result<(), NokhwaError> {
+    // SAFETY: `NDIlib_initialize` may be called any number of times.
+    if unsafe { ffi::NDIlib_initialize() } {
+        Ok(())
+    } else {
+        Err(NokhwaError::InitializeError {
+            backend: ApiBackend::NDI,
+            error: "This CPU is not supported by the NDI runtime".to_string(),
+        })
+    }
+}
+
+fn ndi_fourcc(fourcc: u32) -> FourCC {
+    FourCC(fourcc.to_le_bytes())
+}
+
+/// NDI timestamps are in 100ns units since the Unix epoch. Turn one into an [`Instant`] on our clock.
+fn ndi_timestamp_to_instant(timestamp: i64) -> Instant {
+    let now = Instant::now();
+    if timestamp == ffi::NDILIB_TIMESTAMP_UNDEFINED {
+        return now;
+    }
+    let Ok(timestamp) = u64::try_from(timestamp) else {
+        return now;
+    };
+    let sent = UNIX_EPOCH + Duration::from_nanos(timestamp.saturating_mul(100));
+    match SystemTime::now().duration_since(sent) {
+        Ok(age) => now.checked_sub(age).unwrap_or(now),
+        Err(_) => now,
+    }
+}
+
+/// Query the LAN for NDI senders.
+/// # Quirks
+/// - The `unique_id` and `name` of the [`CameraInfo`] are the NDI source name (`MACHINE (Source)`), and the `model` is its URL.
+/// - Discovery waits up to one second for senders to announce themselves.
+/// # Errors
+/// If the NDI runtime cannot be initialized, this will error.
+pub fn query_ndi() -> Result<Vec<CameraInfo>, NokhwaError> {
+    initialize()?;
+    let settings = ffi::NDIlib_find_create_t {
+        show_local_sources: true,
+        p_groups: ptr::null(),
+        p_extra_ips: ptr::null(),
+    };
+    // SAFETY: `settings` outlives the call, and `finder` is destroyed before returning.
+    unsafe {
+        let finder = ffi::NDIlib_find_create_v2(&raw const settings);
+        if finder.is_null() {
+            return Err(NokhwaError::InitializeError {
+                backend: ApiBackend::NDI,
+                error: "Failed to create NDI finder".to_string(),
+            });
+        }
+        ffi::NDIlib_find_wait_for_sources(finder, DISCOVERY_TIMEOUT_MS);
+        let mut count = 0;
+        let sources = ffi::NDIlib_find_get_current_sources(finder, &raw mut count);
+        let cameras = if sources.is_null() {
+            vec![]
+        } else {
+            slice::from_raw_parts(sources, count as usize)
+                .iter()
+                .map(|source| {
+                    let name = CStr::from_ptr(source.p_ndi_name).to_string_lossy();
+                    let url = if source.p_url_address.is_null() {
+                        Cow::Borrowed("")
+                    } else {
+                        CStr::from_ptr(source.p_url_address).to_string_lossy()
+                    };
+                    CameraInfo::new(&name, &name, "NDI", &url, "NDI Source", "")
+                })
+                .collect()
+        };
+        ffi::NDIlib_find_destroy(finder);
+        Ok(cameras)
+    }
+}
+
+/// The backend struct that receives video from an NDI sender on the network.
+/// To see what this does, please see [`CaptureDevice`].
+/// # Quirks
+/// - The sender decides the [`Resolution`] and frame rate, and may change them at any time. The current [`CameraFormat`] is updated with each frame.
+/// - Frames arrive as [`UYVY`], or [`BGRA`] if the sender has an alpha channel. Changing the [`CameraFormat`] is not supported.
+/// - [`FrameBuffer`] timestamps are the sender's timestamps, converted to the local clock.
+/// - Rows are padded as the sender padded them, see [`FrameBuffer::row_stride()`]. [`frame_raw()`](CaptureDevice::frame_raw) keeps the padding.
+/// - Camera controls are not supported.
+pub struct NDICaptureDevice {
+    info: CameraInfo,
+    format: CameraFormat,
+    receiver: ffi::NDIlib_recv_instance_t,
+    last_timestamp: Instant,
+    last_row_stride: usize,
+}
+
+impl NDICaptureDevice {
+    /// Connects to an NDI sender. A [`CameraIndex::Index`] refers to the order from [`query_ndi()`],
+    /// while a [`CameraIndex::String`] is the NDI source name.
+    /// The [`RequestedFormat`] is ignored, as the sender decides the format.
+    /// # Errors
+    /// This function will error if the NDI runtime is missing, the sender cannot be found, or no frame arrives.
+    pub fn new(index: &CameraIndex, _cam_fmt: RequestedFormat) -> Result<Self, NokhwaError> {
+        let info = match index {
+            CameraIndex::Index(idx) => query_ndi()?.into_iter().nth(*idx as usize),
+            CameraIndex::String(name) => {
+                initialize()?;
+                Some(CameraInfo::new(name, name, "NDI", "", "NDI Source", ""))
+            }
+        }
+        .ok_or(NokhwaError::OpenDeviceError(
+            index.to_string(),
+            "No such NDI source".to_string(),
+        ))?;
+
+        let mut device = NDICaptureDevice {
+            info,
+            format: CameraFormat::new(Resolution::new(0, 0), UYVY, 0),
+            receiver: ptr::null_mut(),
+            last_timestamp: Instant::now(),
+            last_row_stride: 0,
+        };
+        device.open_stream()?;
+        // the format is only known once the first frame arrives
+        device.frame()?;
+        Ok(device)
+    }
+
+    /// Gets the sender's timestamp of the last received frame.
+    #[must_use]
+    pub fn last_timestamp(&self) -> Instant {
+        self.last_timestamp
+    }
+
+    /// Waits for the next video frame and copies it out of NDI, updating the format, timestamp and row stride.
+    #[allow(clippy::cast_sign_loss)]
+    fn receive(&mut self) -> Result<Vec<u8>, NokhwaError> {
+        if self.receiver.is_null() {
+            return Err(NokhwaError::ReadFrameError(
+                "Stream Not Started".to_string(),
+            ));
+        }
+        let started = Instant::now();
+        loop {
+            let remaining = Duration::from_millis(u64::from(FRAME_TIMEOUT_MS))
+                .checked_sub(started.elapsed())
+                .ok_or(NokhwaError::ReadFrameError("Timed out".to_string()))?;
+            // SAFETY: the receiver is valid, the frame is zeroed and freed right after copying.
+            unsafe {
+                let mut video: ffi::NDIlib_video_frame_v2_t = std::mem::zeroed();
+                let frame_type = ffi::NDIlib_recv_capture_v2(
+                    self.receiver,
+                    &raw mut video,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    u32::try_from(remaining.as_millis()).unwrap_or(FRAME_TIMEOUT_MS),
+                );
+                match frame_type {
+                    ffi::NDILIB_FRAME_TYPE_VIDEO => {
+                        let row_stride = video.line_stride_in_bytes as usize;
+                        let data =
+                            slice::from_raw_parts(video.p_data, row_stride * video.yres as usize)
+                                .to_vec();
+                        self.format = CameraFormat::new_exact(
+                            Resolution::new(video.xres as u32, video.yres as u32),
+                            match ndi_fourcc(video.FourCC) {
+                                BGRA => BGRA,
+                                _ => UYVY,
+                            },
+                            FrameRate::new(
+                                u32::try_from(video.frame_rate_N).unwrap_or_default(),
+                                u32::try_from(video.frame_rate_D).unwrap_or_default(),
+                            ),
+                        );
+                        self.last_row_stride = row_stride;
+                        self.last_timestamp = ndi_timestamp_to_instant(video.timestamp);
+                        ffi::NDIlib_recv_free_video_v2(self.receiver, &raw const video);
+                        return Ok(data);
+                    }
+                    ffi::NDILIB_FRAME_TYPE_ERROR => {
+                        return Err(NokhwaError::ReadFrameError(
+                            "NDI sender disconnected".to_string(),
+                        ))
+                    }
+                    ffi::NDILIB_FRAME_TYPE_NONE => {
+                        return Err(NokhwaError::ReadFrameError("Timed out".to_string()))
+                    }
+                    // audio, metadata and status changes are not requested, but may still be reported
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl CaptureDevice for NDICaptureDevice {
+    fn backend(&self) -> ApiBackend {
+        ApiBackend::NDI
+    }
+
+    fn camera_info(&self) -> &CameraInfo {
+        &self.info
+    }
+
+    fn refresh_camera_format(&mut self) -> Result<(), NokhwaError> {
+        Ok(())
+    }
+
+    fn camera_format(&self) -> CameraFormat {
+        self.format
+    }
+
+    fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+        if new_fmt == self.format {
+            return Ok(());
+        }
+        Err(NokhwaError::UnsupportedOperationError(ApiBackend::NDI))
+    }
+
+    fn compatible_list_by_resolution(
+        &mut self,
+        fourcc: FourCC,
+    ) -> Result<HashMap<Resolution, Vec<u32>>, NokhwaError> {
+        if fourcc != self.format.format() {
+            return Ok(HashMap::new());
+        }
+        Ok(HashMap::from([(
+            self.format.resolution(),
+            vec![self.format.frame_rate()],
+        )]))
+    }
+
+    fn compatible_fourcc(&mut self) -> Result<Vec<FourCC>, NokhwaError> {
+        Ok(vec![self.format.format()])
+    }
+
+    fn resolution(&self) -> Resolution {
+        self.format.resolution()
+    }
+
+    fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_resolution(new_res);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.format.frame_rate()
+    }
+
+    fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_frame_rate(new_fps);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn frame_format(&self) -> FourCC {
+        self.format.format()
+    }
+
+    fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
+        let mut new_fmt = self.format;
+        new_fmt.set_format(fourcc);
+        self.set_camera_format(new_fmt)
+    }
+
+    fn camera_control(&self, _control: KnownCameraControl) -> Result<CameraControl, NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(ApiBackend::NDI))
+    }
+
+    fn camera_controls(&self) -> Result<Vec<CameraControl>, NokhwaError> {
+        Ok(vec![])
+    }
+
+    fn set_camera_control(
+        &mut self,
+        _id: KnownCameraControl,
+        _value: ControlValueSetter,
+    ) -> Result<(), NokhwaError> {
+        Err(NokhwaError::UnsupportedOperationError(ApiBackend::NDI))
+    }
+
+    fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if !self.receiver.is_null() {
+            return Ok(());
+        }
+        let name = CString::new(self.info.unique_id())
+            .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?;
+        let settings = ffi::NDIlib_recv_create_v3_t {
+            source_to_connect_to: ffi::NDIlib_source_t {
+                p_ndi_name: name.as_ptr(),
+                p_url_address: ptr::null(),
+            },
+            color_format: ffi::NDILIB_RECV_COLOR_FORMAT_UYVY_BGRA,
+            bandwidth: ffi::NDILIB_RECV_BANDWIDTH_HIGHEST,
+            allow_video_fields: false,
+            p_ndi_recv_name: ptr::null(),
+        };
+        // SAFETY: `settings` and `name` outlive the call; NDI copies the strings.
+        let receiver = unsafe { ffi::NDIlib_recv_create_v3(&raw const settings) };
+        if receiver.is_null() {
+            return Err(NokhwaError::OpenStreamError(
+                "Failed to create NDI receiver".to_string(),
+            ));
+        }
+        self.receiver = receiver;
+        Ok(())
+    }
+
+    fn is_stream_open(&self) -> bool {
+        !self.receiver.is_null()
+    }
+
+    fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
+        let data = self.receive()?;
+        Ok(FrameBuffer::from_pooled(
+            self.format.resolution(),
+            PooledBuffer::unpooled(data),
+            self.format.format(),
+            self.last_timestamp,
+        )
+        .with_row_stride(self.last_row_stride))
+    }
+
+    fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+        Ok(Cow::Owned(self.receive()?))
+    }
+
+    fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        if !self.receiver.is_null() {
+            // SAFETY: the receiver was created by `NDIlib_recv_create_v3` and is not used after this.
+            unsafe { ffi::NDIlib_recv_destroy(self.receiver) };
+            self.receiver = ptr::null_mut();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NDICaptureDevice {
+    fn drop(&mut self) {
+        let _ = self.stop_stream();
+    }
+}
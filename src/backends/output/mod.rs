@@ -0,0 +1,25 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#[cfg(all(feature = "output-virtual-camera", target_os = "linux"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-virtual-camera")))]
+pub use nokhwa_bindings_linux::V4L2LoopbackDevice;
+#[cfg(all(feature = "output-virtual-camera", target_os = "macos"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-virtual-camera")))]
+pub use nokhwa_bindings_macos::CoreMediaIOVirtualCamera;
+#[cfg(all(feature = "output-virtual-camera", target_os = "windows"))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-virtual-camera")))]
+pub use nokhwa_bindings_windows::wmf::OBSVirtualCamera;
@@ -18,19 +18,43 @@ use four_cc::FourCC;
 use nokhwa_core::{
     buffer::FrameBuffer,
     error::NokhwaError,
-    traits::CaptureBackendTrait,
+    traits::CaptureDevice,
     types::{
-        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
-        KnownCameraControl, RequestedFormat, Resolution,
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ConnectionState,
+        ControlValueSetter, Exposure, Focus, KnownCameraControl, PtzCapabilities, Rect,
+        RequestedFormat, Resolution, SessionEvent, StreamStats, WhiteBalance, XuQuery,
     },
 };
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How long to wait between attempts to find a disconnected device again.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+type StateChangeCallback = Box<dyn FnMut(ConnectionState) + Send + 'static>;
+
+struct Reconnect {
+    state: ConnectionState,
+    on_state_change: StateChangeCallback,
+    last_attempt: Option<Instant>,
+}
 
 /// The main `Camera` struct. This is the struct that abstracts over all the backends, providing a simplified interface for use.
+#[allow(clippy::struct_excessive_bools)]
 pub struct Camera {
     idx: CameraIndex,
     api: ApiBackend,
-    device: Box<dyn CaptureBackendTrait>,
+    device: Box<dyn CaptureDevice>,
+    reconnect: Option<Reconnect>,
+    frames_delivered: u64,
+    paused: bool,
+    mirrored: bool,
+    mirror_in_software: bool,
+    crop: Option<Rect>,
+    crop_in_software: bool,
 }
 
 impl Camera {
@@ -41,6 +65,15 @@ impl Camera {
         Camera::with_backend(index, format, ApiBackend::Auto)
     }
 
+    /// Create a new camera from the [`unique_id`](crate::utils::CameraInfo::unique_id) returned by [`query()`](crate::query), and `format`.
+    ///
+    /// Unlike indexes, unique IDs do not change when the OS reorders devices.
+    /// # Errors
+    /// This will error if the camera cannot be found, you have a bad platform configuration (e.g. `input-v4l` but not on linux) or the backend cannot create the camera (e.g. permission denied).
+    pub fn from_unique_id(unique_id: &str, format: RequestedFormat) -> Result<Self, NokhwaError> {
+        Camera::new(CameraIndex::String(unique_id.to_string()), format)
+    }
+
     /// Create a new camera from an `index`, `format`, and `backend`. `format` can be `None`.
     /// # Errors
     /// This will error if you either have a bad platform configuration (e.g. `input-v4l` but not on linux) or the backend cannot create the camera (e.g. permission denied).
@@ -55,6 +88,13 @@ impl Camera {
             idx: index,
             api: backend,
             device: camera_backend,
+            reconnect: None,
+            frames_delivered: 0,
+            paused: false,
+            mirrored: false,
+            mirror_in_software: false,
+            crop: None,
+            crop_in_software: false,
         })
     }
 
@@ -79,15 +119,27 @@ impl Camera {
     }
 
     /// Allows creation of a [`Camera`] with a custom backend. This is useful if you are creating e.g. a custom module.
+    /// `device` can be any [`CaptureDevice`], or one that is already boxed.
     ///
     /// You **must** have set a format beforehand
     #[must_use]
     pub fn with_custom(
         idx: CameraIndex,
         api: ApiBackend,
-        device: Box<dyn CaptureBackendTrait>,
+        device: impl Into<Box<dyn CaptureDevice>>,
     ) -> Self {
-        Self { idx, api, device }
+        Self {
+            idx,
+            api,
+            device: device.into(),
+            reconnect: None,
+            frames_delivered: 0,
+            paused: false,
+            mirrored: false,
+            mirror_in_software: false,
+            crop: None,
+            crop_in_software: false,
+        }
     }
 
     /// Gets the current Camera's index.
@@ -119,6 +171,20 @@ impl Camera {
         self.api
     }
 
+    /// Gets the backend device of this camera, for use with code that works on any [`CaptureDevice`].
+    #[must_use]
+    pub fn device(&self) -> &dyn CaptureDevice {
+        self.device.as_ref()
+    }
+
+    /// Gets the backend device of this camera mutably.
+    ///
+    /// Settings made through the device directly bypass the `Camera`. For example, [`set_mirrored()`](CaptureDevice::set_mirrored)
+    /// called on the device is not re-applied after a reconnect, and is not reflected by [`is_mirrored()`](Camera::is_mirrored).
+    pub fn device_mut(&mut self) -> &mut dyn CaptureDevice {
+        self.device.as_mut()
+    }
+
     /// Sets the current Camera's backend. Note that this re-initializes the camera.
     /// # Errors
     /// The new backend may not exist or may fail to initialize the new camera.
@@ -340,7 +406,7 @@ impl Camera {
     }
 
     /// Sets the control to `control` in the camera.
-    /// Usually, the pipeline is calling [`camera_control()`](crate::camera_traits::CaptureBackendTrait::camera_control), getting a camera control that way
+    /// Usually, the pipeline is calling [`camera_control()`](crate::camera_traits::CaptureDevice::camera_control), getting a camera control that way
     /// then calling [`value()`](crate::utils::CameraControl::value()) to get a [`ControlValueSetter`](crate::utils::ControlValueSetter) and setting the value that way.
     /// # Errors
     /// If the `control` is not supported, the value is invalid (less than min, greater than max, not in step), or there was an error setting the control,
@@ -353,10 +419,28 @@ impl Camera {
         self.device.set_camera_control(id, value)
     }
 
-    /// Will open the camera stream with set parameters. This will be called internally if you try and call [`frame()`](CaptureBackendTrait::frame()) before you call [`open_stream()`](CaptureBackendTrait::open_stream()).
+    /// Sets several controls at once, returning the result of each, in the order of `controls`. This is faster than
+    /// calling [`set_camera_control()`](Camera::set_camera_control) for each of them.
+    /// # Errors
+    /// If none of the controls could be set, e.g. because the device could not be locked for configuration, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Controls of the same class (user or camera) are set atomically with one `VIDIOC_S_EXT_CTRLS`, so
+    ///   if one of them is rejected, none of the others in that class are set either.
+    /// - `Media Foundation`: Set one after another, but without looking up the control interfaces for each.
+    /// - `AVFoundation`: The device is locked for configuration once for all of them.
+    pub fn set_camera_controls(
+        &mut self,
+        controls: &[(KnownCameraControl, ControlValueSetter)],
+    ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+        self.device.set_camera_controls(controls)
+    }
+
+    /// Will open the camera stream with set parameters. This will be called internally if you try and call [`frame()`](CaptureDevice::frame()) before you call [`open_stream()`](CaptureDevice::open_stream()).
     /// # Errors
     /// If the specific backend fails to open the camera (e.g. already taken, busy, doesn't exist anymore) this will error.
     pub fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        self.frames_delivered = 0;
+        self.paused = false;
         self.device.open_stream()
     }
 
@@ -366,19 +450,409 @@ impl Camera {
         self.device.is_stream_open()
     }
 
-    /// Will get a frame from the camera as a Raw RGB image buffer. Depending on the backend, if you have not called [`open_stream()`](CaptureBackendTrait::open_stream()) before you called this,
+    /// Stops frame delivery while keeping the device open, configured and locked, so that [`resume_stream()`](Camera::resume_stream)
+    /// is much faster than re-opening the stream. [`frame()`](Camera::frame) errors while the stream is paused.
+    /// # Errors
+    /// If the stream is not open, or the backend fails to pause it, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: The buffers stay mapped, only streaming is turned off.
+    /// - `AVFoundation`: The capture session keeps running, only the video output is disabled.
+    /// - Other backends stop and re-open the stream.
+    pub fn pause_stream(&mut self) -> Result<(), NokhwaError> {
+        if !self.is_stream_open() {
+            return Err(NokhwaError::StreamShutdownError(
+                "Stream Not Open".to_string(),
+            ));
+        }
+        if !self.paused {
+            self.device.pause_stream()?;
+            self.paused = true;
+        }
+        Ok(())
+    }
+
+    /// Resumes a stream paused with [`pause_stream()`](Camera::pause_stream).
+    /// # Errors
+    /// If the backend fails to resume the stream, this will error.
+    pub fn resume_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.paused {
+            self.device.resume_stream()?;
+            self.paused = false;
+        }
+        Ok(())
+    }
+
+    /// Checks if the stream is paused.
+    #[must_use]
+    pub fn is_stream_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Will get a frame from the camera as a Raw RGB image buffer. Depending on the backend, if you have not called [`open_stream()`](CaptureDevice::open_stream()) before you called this,
     /// it will either return an error.
     /// # Errors
-    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), the decoding fails (e.g. MJPEG -> u8), or [`open_stream()`](CaptureBackendTrait::open_stream()) has not been called yet,
+    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), the decoding fails (e.g. MJPEG -> u8), or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet,
     /// this will error.
     pub fn frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
-        self.device.frame()
+        self.check_streaming()?;
+        let frame = self.device.frame();
+        self.process_frame(frame)
+    }
+
+    /// Like [`frame()`](Camera::frame), but returns `Ok(None)` if no frame arrived within `timeout` instead of blocking on a stalled device.
+    /// # Errors
+    /// If the backend cannot wait with a timeout, fails to get the frame, or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: The first frame after opening the stream is waited for without a timeout.
+    /// - `Media Foundation`, `OpenCV` and `JSCamera` do not support timeouts and return [`NokhwaError::UnsupportedOperationError`].
+    pub fn frame_timeout(&mut self, timeout: Duration) -> Result<Option<FrameBuffer>, NokhwaError> {
+        self.check_streaming()?;
+        self.device
+            .frame_timeout(timeout)
+            .transpose()
+            .map(|frame| self.process_frame(frame))
+            .transpose()
+    }
+
+    /// Gets a frame if one is ready, returning `Ok(None)` otherwise. This never blocks, making it suitable for GUI event loops.
+    /// This is [`frame_timeout()`](Camera::frame_timeout) with a timeout of zero.
+    /// # Errors
+    /// See [`frame_timeout()`](Camera::frame_timeout).
+    pub fn try_frame(&mut self) -> Result<Option<FrameBuffer>, NokhwaError> {
+        self.frame_timeout(Duration::ZERO)
+    }
+
+    fn check_streaming(&mut self) -> Result<(), NokhwaError> {
+        if self.paused {
+            return Err(NokhwaError::ReadFrameError("Stream Paused".to_string()));
+        }
+        if self.connection_state() == ConnectionState::Reconnecting {
+            self.try_reconnect()?;
+        }
+        Ok(())
+    }
+
+    fn process_frame(
+        &mut self,
+        frame: Result<FrameBuffer, NokhwaError>,
+    ) -> Result<FrameBuffer, NokhwaError> {
+        match frame {
+            Ok(frame) => {
+                // count drops into the sequence, so that they show up as gaps
+                let sequence = self.frames_delivered + self.device.stream_stats().frames_dropped();
+                self.frames_delivered += 1;
+                let frame = frame
+                    .with_sequence(sequence)
+                    .with_pending_mirror(self.mirror_in_software);
+                match self.crop {
+                    Some(crop) if self.crop_in_software => frame.cropped(crop),
+                    _ => Ok(frame),
+                }
+            }
+            Err(why) => {
+                if self.reconnect.is_some() && !self.device_present() {
+                    self.set_connection_state(ConnectionState::Reconnecting);
+                }
+                Err(why)
+            }
+        }
+    }
+
+    /// Mirrors frames horizontally, as expected of a selfie view.
+    ///
+    /// The backend mirrors frames itself where it can. Otherwise, frames from [`frame()`](Camera::frame) are marked with
+    /// [`mirror_pending()`](FrameBuffer::mirror_pending) and flipped when they are converted. The raw frame data is never flipped in that case.
+    /// # Errors
+    /// If the backend supports mirroring but fails to set it, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses the `V4L2_CID_HFLIP` control, which few UVC cameras have.
+    /// - `Media Foundation`: Uses the video processor of the source reader, if there is one.
+    /// - `AVFoundation`: Uses `videoMirrored` of the capture connection. If the stream is not open yet, this is applied when it opens.
+    pub fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+        match self.device.set_mirrored(mirrored) {
+            Ok(()) => self.mirror_in_software = false,
+            Err(NokhwaError::UnsupportedOperationError(_)) => self.mirror_in_software = mirrored,
+            Err(why) => return Err(why),
+        }
+        self.mirrored = mirrored;
+        Ok(())
+    }
+
+    /// Checks if frames are mirrored, see [`set_mirrored()`](Camera::set_mirrored).
+    #[must_use]
+    pub fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Crops frames to `crop`, or removes the crop if it is `None`.
+    ///
+    /// The backend crops frames itself where it can, which usually also saves bandwidth. Otherwise, frames from [`frame()`](Camera::frame)
+    /// are cropped with [`FrameBuffer::cropped()`], which does not work for compressed formats such as `MJPEG`.
+    /// Either way, the [`resolution()`](FrameBuffer::resolution) of frames is the size of the crop.
+    /// # Errors
+    /// If the backend supports cropping but fails to set it, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses the selection API, which most UVC cameras do not support. The stream must not be open.
+    /// - `Media Foundation`: Uses the video processor of the source reader, if there is one.
+    /// - `AVFoundation`: Always crops in software.
+    pub fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+        match self.device.set_crop(crop) {
+            Ok(()) => self.crop_in_software = false,
+            Err(NokhwaError::UnsupportedOperationError(_)) => self.crop_in_software = true,
+            Err(why) => return Err(why),
+        }
+        self.crop = crop;
+        Ok(())
+    }
+
+    /// Gets the crop set with [`set_crop()`](Camera::set_crop).
+    #[must_use]
+    pub fn crop(&self) -> Option<Rect> {
+        self.crop
+    }
+
+    /// Sets the exposure to automatic, or to a fixed exposure time.
+    /// # Errors
+    /// If the camera cannot set its exposure this way, or fails to, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Automatic is aperture priority, which is what UVC cameras call automatic exposure. The time is
+    ///   rounded to the 100µs steps of `V4L2_CID_EXPOSURE_ABSOLUTE` and clamped to its range.
+    /// - `Media Foundation`: `CameraControl_Exposure` is in powers of two of a second, so the time is rounded to the
+    ///   nearest one, e.g. 1/32s.
+    /// - `AVFoundation`: Only automatic exposure is supported on macOS.
+    pub fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+        self.device.set_exposure(exposure)
+    }
+
+    /// Sets the white balance to automatic, or to a fixed color temperature.
+    /// # Errors
+    /// If the camera cannot set its white balance this way, or fails to, this will error.
+    /// # Quirks
+    /// - `Video4Linux` and `Media Foundation`: The temperature is clamped to the range of the camera.
+    /// - `AVFoundation`: Only automatic white balance is supported on macOS.
+    pub fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+        self.device.set_white_balance(white_balance)
+    }
+
+    /// Sets the focus to automatic, or to a fixed lens position.
+    /// # Errors
+    /// If the camera cannot set its focus this way, or fails to, this will error.
+    /// # Quirks
+    /// - `Video4Linux` and `Media Foundation`: The lens position is mapped onto the range of the absolute focus
+    ///   control, which is not the same distance on every camera.
+    /// - `AVFoundation`: Only automatic focus is supported on macOS.
+    pub fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+        self.device.set_focus(focus)
+    }
+
+    /// Sets the brightness, from `0.0` (the lowest the camera supports) to `1.0` (the highest).
+    /// # Errors
+    /// If the camera has no brightness control, or fails to set it, this will error.
+    /// # Quirks
+    /// - `AVFoundation`: Not supported.
+    pub fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+        self.device.set_brightness(brightness)
+    }
+
+    /// Makes automatic focus focus on `roi`, a region of the frame in pixels of the current resolution, e.g. for tap
+    /// to focus.
+    /// # Errors
+    /// If the camera cannot focus on a region, `roi` is not inside the frame, or setting it fails, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses the UVC 1.5 region of interest controls (Linux 6.3 and later). UVC has one region for
+    ///   focus and exposure, so this also moves the region [`set_exposure_roi()`](Camera::set_exposure_roi) set.
+    /// - `Media Foundation`: Not supported.
+    /// - `AVFoundation`: Sets the focus point of interest to the center of `roi`, and switches to automatic focus.
+    pub fn set_focus_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        self.device.set_focus_roi(roi)
+    }
+
+    /// Makes automatic exposure meter `roi`, a region of the frame in pixels of the current resolution.
+    /// # Errors
+    /// If the camera cannot meter a region, `roi` is not inside the frame, or setting it fails, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses the UVC 1.5 region of interest controls (Linux 6.3 and later). UVC has one region for
+    ///   focus and exposure, so this also moves the region [`set_focus_roi()`](Camera::set_focus_roi) set.
+    /// - `Media Foundation`: Not supported.
+    /// - `AVFoundation`: Sets the exposure point of interest to the center of `roi`, and switches to automatic
+    ///   exposure.
+    pub fn set_exposure_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        self.device.set_exposure_roi(roi)
+    }
+
+    /// Sends `query` to the control `selector` of the UVC extension unit `unit`, to reach vendor specific features
+    /// such as Logitech `RightLight` or the palettes of thermal cameras. `data` is written for [`XuQuery::SetCur`] and
+    /// read into for the other queries, and has to be exactly as long as the control.
+    /// # Errors
+    /// If the backend cannot talk to extension units, or the camera rejects the query, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses `UVCIOC_CTRL_QUERY` of the `uvcvideo` driver, so only works with UVC cameras.
+    /// - Other backends do not support this.
+    pub fn xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: XuQuery,
+        data: &mut [u8],
+    ) -> Result<(), NokhwaError> {
+        self.device.xu_query(unit, selector, query, data)
+    }
+
+    /// Gets what pan, tilt and zoom the camera can do, see [`PtzCapabilities`].
+    /// # Errors
+    /// If the backend has no pan, tilt and zoom API, or fails to query the camera, this will error.
+    /// # Quirks
+    /// - `Video4Linux`: Uses the absolute and relative pan, tilt and zoom controls of UVC cameras.
+    /// - `Media Foundation`: Uses `IAMCameraControl`, which has no relative pan and tilt. Those are emulated by adding
+    ///   the offset to the current position.
+    /// - `AVFoundation`: Only has zoom, as `videoZoomFactor`, on macOS 14 and later.
+    pub fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+        self.device.ptz_capabilities()
+    }
+
+    /// Zooms to `zoom`, in the units of [`PtzCapabilities::zoom()`].
+    /// # Errors
+    /// If the camera cannot zoom, or fails to, this will error.
+    pub fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+        self.device.set_zoom(zoom)
+    }
+
+    /// Zooms to `zoom` smoothly, at `rate`. Only `AVFoundation` can do this, where `rate` is in powers of two per second.
+    /// # Errors
+    /// If the camera cannot zoom at a rate, or fails to, this will error.
+    pub fn ramp_zoom(&mut self, zoom: f64, rate: f64) -> Result<(), NokhwaError> {
+        self.device.ramp_zoom(zoom, rate)
+    }
+
+    /// Points the camera at `pan` and `tilt` degrees, where positive values are to the right and up.
+    /// # Errors
+    /// If the camera cannot pan and tilt, or fails to, this will error.
+    pub fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.device.set_pan_tilt_absolute(pan, tilt)
+    }
+
+    /// Moves the camera by `pan` and `tilt` degrees from where it is pointing.
+    /// # Errors
+    /// If the camera cannot pan and tilt by an offset, or fails to, this will error.
+    pub fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.device.set_pan_tilt_relative(pan, tilt)
+    }
+
+    /// Gets the [`StreamStats`] since the stream was opened: frames delivered by [`frame()`](Camera::frame), and the frames the backend saw being dropped.
+    #[must_use]
+    pub fn stream_stats(&self) -> StreamStats {
+        let backend = self.device.stream_stats();
+        StreamStats::new(
+            self.frames_delivered,
+            backend.frames_dropped_by_driver(),
+            backend.frames_dropped_by_channel(),
+        )
+    }
+
+    /// Takes the next [`SessionEvent`] of the camera, or `None` if there is none. This never blocks, so it can be
+    /// called from a GUI event loop, e.g. to read a control again after [`SessionEvent::ControlChanged`] and move its slider.
+    ///
+    /// Events are only reported while the stream is open.
+    /// # Quirks
+    /// - `Video4Linux`: Controls are reported through control events, which the driver sends when another process or the
+    ///   driver itself changes one. Drivers that do not send them report nothing.
+    /// - `Media Foundation`: Has no control events, so the controls are read again every half a second while frames are read.
+    /// - `AVFoundation`: Controls are observed with key-value observing.
+    /// - Events read here are not also delivered to the `events()` channel of the backend, and the other way around.
+    pub fn poll_event(&mut self) -> Option<SessionEvent> {
+        self.device.poll_event()
+    }
+
+    /// Enables automatic reconnection.
+    ///
+    /// If [`frame()`](Camera::frame) fails because the device has disappeared (e.g. it was unplugged), the camera enters
+    /// [`ConnectionState::Reconnecting`] instead of just erroring forever. While reconnecting, every call to `frame()` looks for
+    /// a device with the same [`unique_id`](CameraInfo::unique_id) and, once it returns, re-opens it with the current
+    /// [`CameraFormat`] and resumes the stream.
+    ///
+    /// `on_state_change` is called every time the [`ConnectionState`] changes.
+    pub fn enable_reconnect(
+        &mut self,
+        on_state_change: impl FnMut(ConnectionState) + Send + 'static,
+    ) {
+        self.reconnect = Some(Reconnect {
+            state: ConnectionState::Connected,
+            on_state_change: Box::new(on_state_change),
+            last_attempt: None,
+        });
+    }
+
+    /// Disables automatic reconnection.
+    pub fn disable_reconnect(&mut self) {
+        self.reconnect = None;
+    }
+
+    /// Gets the current [`ConnectionState`]. This is always [`ConnectionState::Connected`] if reconnection is not enabled.
+    #[must_use]
+    pub fn connection_state(&self) -> ConnectionState {
+        self.reconnect
+            .as_ref()
+            .map_or(ConnectionState::Connected, |reconnect| reconnect.state)
+    }
+
+    fn set_connection_state(&mut self, state: ConnectionState) {
+        if let Some(reconnect) = &mut self.reconnect {
+            if reconnect.state != state {
+                reconnect.state = state;
+                (reconnect.on_state_change)(state);
+            }
+        }
+    }
+
+    fn device_present(&self) -> bool {
+        let unique_id = self.device.camera_info().unique_id();
+        // if we cannot tell, assume the device is still there and let the error through
+        crate::query(self.api).map_or(true, |devices| {
+            devices.iter().any(|device| device.unique_id() == unique_id)
+        })
+    }
+
+    fn try_reconnect(&mut self) -> Result<(), NokhwaError> {
+        let disconnected = || {
+            NokhwaError::ReadFrameError("Device disconnected, waiting for it to return".to_string())
+        };
+        if let Some(reconnect) = &mut self.reconnect {
+            if reconnect
+                .last_attempt
+                .is_some_and(|last| last.elapsed() < RECONNECT_INTERVAL)
+            {
+                return Err(disconnected());
+            }
+            reconnect.last_attempt = Some(Instant::now());
+        }
+        if !self.device_present() {
+            return Err(disconnected());
+        }
+
+        let mut device = init_camera(
+            &CameraIndex::String(self.device.camera_info().unique_id()),
+            RequestedFormat::from_camera_format(self.device.camera_format()),
+            self.api,
+        )?;
+        device.open_stream()?;
+        self.device = device;
+        if self.mirrored {
+            self.set_mirrored(true)?;
+        }
+        if self.crop.is_some() {
+            self.set_crop(self.crop)?;
+        }
+        self.set_connection_state(ConnectionState::Connected);
+        Ok(())
     }
 
     /// Will get a frame from the camera **without** any processing applied, meaning you will usually get a frame you need to decode yourself.
     /// # Errors
-    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureBackendTrait::open_stream()) has not been called yet, this will error.
+    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet, this will error.
     pub fn frame_raw(&mut self) -> Result<Cow<[u8]>, NokhwaError> {
+        if self.paused {
+            return Err(NokhwaError::ReadFrameError("Stream Paused".to_string()));
+        }
         match self.device.frame_raw() {
             Ok(f) => Ok(f),
             Err(why) => Err(why),
@@ -387,10 +861,10 @@ impl Camera {
 
     /// Directly writes the current frame into said `buffer`.
     /// # Errors
-    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureBackendTrait::open_stream()) has not been called yet, this will error.
+    /// If the backend fails to get the frame (e.g. already taken, busy, doesn't exist anymore), or [`open_stream()`](CaptureDevice::open_stream()) has not been called yet, this will error.
     pub fn write_frame_to_buffer(&mut self, buffer: &mut [u8]) -> Result<(), NokhwaError> {
         let frame = self.frame()?;
-        buffer.copy_from_slice(&frame.buffer_bytes());
+        buffer.copy_from_slice(frame.buffer());
         Ok(())
     }
 
@@ -398,13 +872,26 @@ impl Camera {
     /// # Errors
     /// Please check the `Quirks` section of each backend.
     pub fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        self.paused = false;
         self.device.stop_stream()
     }
+
+    /// Stops the stream and releases the camera. Dropping the `Camera` does the same, but ignores any errors.
+    /// # Errors
+    /// If the backend fails to stop the stream, this will error. The camera is released regardless.
+    pub fn close(mut self) -> Result<(), NokhwaError> {
+        if self.is_stream_open() {
+            self.stop_stream()?;
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Camera {
     fn drop(&mut self) {
-        self.stop_stream().unwrap();
+        if self.is_stream_open() {
+            let _stop_stream_err = self.stop_stream();
+        }
     }
 }
 
@@ -418,6 +905,8 @@ fn figure_out_auto() -> Option<ApiBackend> {
         cap = ApiBackend::MediaFoundation;
     } else if cfg!(feature = "input-avfoundation") && (platform == "macos" || platform == "ios") {
         cap = ApiBackend::AVFoundation;
+    } else if cfg!(feature = "input-ffmpeg") {
+        cap = ApiBackend::FFmpeg;
     }
     if cap == ApiBackend::Auto {
         return None;
@@ -432,7 +921,7 @@ macro_rules! cap_impl_fn {
         $(
             paste::paste! {
                 #[cfg ($cfg) ]
-                fn [< init_ $backend_name>](idx: &CameraIndex, setting: RequestedFormat) -> Option<Result<Box<dyn CaptureBackendTrait>, NokhwaError>> {
+                fn [< init_ $backend_name>](idx: &CameraIndex, setting: RequestedFormat) -> Option<Result<Box<dyn CaptureDevice>, NokhwaError>> {
                     use crate::backends::capture::$backend;
                     match <$backend>::$init_fn(idx, setting) {
                         Ok(cap) => Some(Ok(cap.into())),
@@ -440,7 +929,7 @@ macro_rules! cap_impl_fn {
                     }
                 }
                 #[cfg(not( $cfg ))]
-                fn [< init_ $backend_name>](_idx: &CameraIndex, _setting: RequestedFormat) -> Option<Result<Box<dyn CaptureBackendTrait>, NokhwaError>> {
+                fn [< init_ $backend_name>](_idx: &CameraIndex, _setting: RequestedFormat) -> Option<Result<Box<dyn CaptureDevice>, NokhwaError>> {
                     None
                 }
             }
@@ -533,22 +1022,56 @@ macro_rules! cap_impl_matches {
 cap_impl_fn! {
     (V4LCaptureDevice, new, all(feature = "input-v4l", target_os = "linux"), v4l),
     (MediaFoundationCaptureDevice, new, all(feature = "input-msmf", target_os = "windows"), msmf),
-    (AVFoundationCaptureDevice, new, all(feature = "input-avfoundation", any(target_os = "macos", target_os = "ios")), avfoundation)
+    (AVFoundationCaptureDevice, new, all(feature = "input-avfoundation", any(target_os = "macos", target_os = "ios")), avfoundation),
+    (FFmpegCaptureDevice, new, feature = "input-ffmpeg", ffmpeg),
+    (FFmpegCaptureDevice, new_decklink, feature = "input-decklink", decklink),
+    (AravisCaptureDevice, new, feature = "input-gige", gige),
+    (NDICaptureDevice, new, feature = "input-ndi", ndi)
+}
+
+// Lets MJPEG conversion use the platform's hardware decoder. The decoder itself is only created on the first frame.
+fn register_platform_decoder() {
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+    REGISTER.call_once(|| {
+        #[cfg(all(feature = "input-v4l", target_os = "linux"))]
+        nokhwa_core::conversion::register_default_hardware_decoder(
+            nokhwa_bindings_linux::V4L2JpegDecoder::create,
+        );
+        #[cfg(all(feature = "input-msmf", target_os = "windows"))]
+        nokhwa_core::conversion::register_default_hardware_decoder(
+            nokhwa_bindings_windows::wmf::MediaFoundationJpegDecoder::create,
+        );
+        #[cfg(all(
+            feature = "input-avfoundation",
+            any(target_os = "macos", target_os = "ios")
+        ))]
+        nokhwa_core::conversion::register_default_hardware_decoder(
+            nokhwa_bindings_macos::VideoToolboxJpegDecoder::create,
+        );
+    });
 }
 
 fn init_camera(
     index: &CameraIndex,
     format: RequestedFormat,
     backend: ApiBackend,
-) -> Result<Box<dyn CaptureBackendTrait>, NokhwaError> {
+) -> Result<Box<dyn CaptureDevice>, NokhwaError> {
+    register_platform_decoder();
+    if let ApiBackend::Custom(_) = backend {
+        return crate::registry::backend_factory(backend)?.create(index, format);
+    }
     let camera_backend = cap_impl_matches! {
             backend, index, format,
             ("input-v4l", Video4Linux, init_v4l),
             ("input-msmf", MediaFoundation, init_msmf),
-            ("input-avfoundation", AVFoundation, init_avfoundation)
+            ("input-avfoundation", AVFoundation, init_avfoundation),
+            ("input-ffmpeg", FFmpeg, init_ffmpeg),
+            ("input-decklink", DeckLink, init_decklink),
+            ("input-gige", GigEVision, init_gige),
+            ("input-ndi", NDI, init_ndi)
     };
     Ok(camera_backend)
 }
 
-#[cfg(feature = "output-threaded")]
+#[cfg(any(feature = "output-threaded", feature = "output-tokio"))]
 unsafe impl Send for Camera {}
@@ -1,26 +1,105 @@
+#[cfg(feature = "output-convert-to-rgb")]
+pub use ffmpeg_next::format::Pixel;
+#[cfg(feature = "output-convert-to-rgb")]
 use ffmpeg_next::{
-    format::Pixel,
+    ffi,
     frame::Video,
     software::scaling::{Context, Flags},
 };
-use nokhwa_core::buffer::FrameBuffer;
-use nokhwa_core::pixel_format::{UYVY_APPLE, YUV420};
+#[cfg(feature = "output-convert-to-rgb")]
+use nokhwa_core::conversion::{convert_into, RgbDestination, RgbFormat};
+#[cfg(feature = "output-convert-to-rgb")]
+use nokhwa_core::pixel_format::{KnownFrameFormat, UYVY_APPLE, YUV420};
+use nokhwa_core::{buffer::FrameBuffer, error::NokhwaError};
+#[cfg(not(feature = "output-convert-to-rgb"))]
+use nokhwa_core::{
+    conversion::convert,
+    pixel_format::{BGRA, RAWRGB, RGBA},
+};
+
+/// The formats [`ConvertToRgb`] converts into without the `output-convert-to-rgb` feature, named like ffmpeg's `Pixel`
+/// so that code works with either feature.
+#[cfg(not(feature = "output-convert-to-rgb"))]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Pixel {
+    RGB24,
+    RGBA,
+    BGRA,
+}
 
 pub trait ConvertToRgb {
-    fn convert_to_rgb(&self, _output_format: Pixel) -> Vec<u8> {
-        todo!()
+    /// Converts the frame into tightly packed pixels of `output_format`, applying a pending mirror.
+    /// # Errors
+    /// If the frame cannot be converted into `output_format`, e.g. because its format is not supported or its buffer is
+    /// too small, this will error.
+    fn try_convert_to_rgb(&self, _output_format: Pixel) -> Result<Vec<u8>, NokhwaError> {
+        Err(NokhwaError::NotImplementedError(
+            "ConvertToRgb::try_convert_to_rgb".to_string(),
+        ))
+    }
+
+    /// Converts the frame into tightly packed pixels of `output_format`, see
+    /// [`try_convert_to_rgb()`](ConvertToRgb::try_convert_to_rgb).
+    /// # Panics
+    /// If [`try_convert_to_rgb()`](ConvertToRgb::try_convert_to_rgb) errors.
+    fn convert_to_rgb(&self, output_format: Pixel) -> Vec<u8> {
+        self.try_convert_to_rgb(output_format)
+            .unwrap_or_else(|why| panic!("{why}"))
     }
 }
 
+#[cfg(feature = "output-convert-to-rgb")]
 impl ConvertToRgb for FrameBuffer {
-    fn convert_to_rgb(&self, output_format: Pixel) -> Vec<u8> {
+    fn try_convert_to_rgb(&self, output_format: Pixel) -> Result<Vec<u8>, NokhwaError> {
+        let error = |error: String| NokhwaError::ProcessFrameError {
+            src: self.source_frame_format(),
+            destination: format!("{output_format:?}"),
+            error,
+        };
+
+        // 4:2:0, YUYV, MJPEG and Bayer mosaics are what most cameras deliver, so they do not go through ffmpeg
+        let native = matches!(
+            self.known_frame_format(),
+            KnownFrameFormat::Yuv420
+                | KnownFrameFormat::Nv12
+                | KnownFrameFormat::I420
+                | KnownFrameFormat::Yv12
+                | KnownFrameFormat::Yuyv
+                | KnownFrameFormat::Bgra
+                | KnownFrameFormat::Mjpeg
+                | KnownFrameFormat::BayerBggr8
+                | KnownFrameFormat::BayerGbrg8
+                | KnownFrameFormat::BayerGrbg8
+                | KnownFrameFormat::BayerRggb8
+        );
+        if let (true, Pixel::RGB24 | Pixel::RGBA) = (native, output_format) {
+            let format = if output_format == Pixel::RGBA {
+                RgbFormat::Rgba
+            } else {
+                RgbFormat::Rgb24
+            };
+            let row_len = self.width() as usize * format.pixel_size();
+            let mut output = vec![0; row_len * self.height() as usize];
+            convert_into(
+                self.resolution(),
+                self.source_frame_format(),
+                self.color_space().unwrap_or_default(),
+                &self.unpadded_buffer()?,
+                RgbDestination::new(&mut output, format),
+            )?;
+            if self.mirror_pending() {
+                mirror_rows(&mut output, row_len, row_len, format.pixel_size());
+            }
+            return Ok(output);
+        }
+
         let pixel_format = match self.source_frame_format() {
             YUV420 => Pixel::YUV420P,
             UYVY_APPLE => Pixel::UYVY422,
-            _ => panic!("Unsupported pixel format {}", self.source_frame_format()),
+            _ => return Err(error("Unsupported pixel format".to_string())),
         };
 
-        let scaler = Context::get(
+        let mut scaler = Context::get(
             pixel_format,
             self.width(),
             self.height(),
@@ -28,39 +107,93 @@ impl ConvertToRgb for FrameBuffer {
             self.width(),
             self.height(),
             Flags::BILINEAR,
-        );
-        match scaler {
-            Ok(mut scaler) => {
-                let buffer = self.buffer();
-                let width = self.width() as usize;
-                let height = self.height() as usize;
-
-                // let mut output_buffer = vec![0u8; self.width() as usize * self.height() as usize * 4];
-                let mut input_buffer = Video::new(pixel_format, self.width(), self.height());
-
-                // Calculate expected buffer size (4 bytes per 2 pixels)
-                let expected_size = (width * height * 2) as usize;
-
-                // Check if the buffer size matches the expected size
-                if buffer.len() != expected_size {
-                    panic!(
-                        "Buffer size does not match expected size of {}... It is {}",
-                        expected_size,
-                        buffer.len(),
-                    );
-                }
-
-                // Copy the buffer directly into the Video object
-                input_buffer.data_mut(0).copy_from_slice(buffer);
-
-                let mut output_buffer = Video::new(output_format, self.width(), self.height());
-                scaler.run(&input_buffer, &mut output_buffer).unwrap();
-
-                return output_buffer.data(0).to_vec();
-            }
-            Err(e) => {
-                panic!("Error creating scaler: {}", e);
-            }
+        )
+        .map_err(|why| error(format!("Error creating scaler: {why}")))?;
+        let buffer = self.unpadded_buffer()?;
+        let width = self.width() as usize;
+        let height = self.height() as usize;
+
+        let mut input_buffer = Video::new(pixel_format, self.width(), self.height());
+
+        // Calculate expected buffer size (4 bytes per 2 pixels)
+        let expected_size = width * height * 2;
+
+        // Check if the buffer size matches the expected size
+        if buffer.len() != expected_size {
+            return Err(error(format!(
+                "Buffer size does not match expected size of {}... It is {}",
+                expected_size,
+                buffer.len(),
+            )));
+        }
+
+        // Copy the buffer directly into the Video object
+        input_buffer.data_mut(0).copy_from_slice(&buffer);
+
+        let mut output_buffer = Video::new(output_format, self.width(), self.height());
+        scaler
+            .run(&input_buffer, &mut output_buffer)
+            .map_err(|why| error(why.to_string()))?;
+        if self.mirror_pending() {
+            mirror_first_plane(&mut output_buffer);
+        }
+
+        Ok(output_buffer.data(0).to_vec())
+    }
+}
+
+#[cfg(not(feature = "output-convert-to-rgb"))]
+impl ConvertToRgb for FrameBuffer {
+    fn try_convert_to_rgb(&self, output_format: Pixel) -> Result<Vec<u8>, NokhwaError> {
+        let (target, bytes_per_pixel) = match output_format {
+            Pixel::RGB24 => (RAWRGB, 3),
+            Pixel::RGBA => (RGBA, 4),
+            Pixel::BGRA => (BGRA, 4),
+        };
+        let mut output = convert(
+            self.resolution(),
+            self.source_frame_format(),
+            target,
+            self.color_space().unwrap_or_default(),
+            &self.unpadded_buffer()?,
+        )?;
+        if self.mirror_pending() {
+            let row_len = self.width() as usize * bytes_per_pixel;
+            mirror_rows(&mut output, row_len, row_len, bytes_per_pixel);
+        }
+        Ok(output)
+    }
+}
+
+/// Flips the first plane of `frame` horizontally. Planar formats are assumed to have 8-bit samples.
+#[cfg(feature = "output-convert-to-rgb")]
+fn mirror_first_plane(frame: &mut Video) {
+    let bytes_per_pixel = if frame.planes() > 1 {
+        1
+    } else {
+        frame.format().descriptor().map_or(0, |descriptor| {
+            let bits = unsafe { ffi::av_get_bits_per_pixel(descriptor.as_ptr()) };
+            usize::try_from(bits).unwrap_or(0) / 8
+        })
+    };
+    if bytes_per_pixel == 0 {
+        return;
+    }
+
+    let row_len = frame.plane_width(0) as usize * bytes_per_pixel;
+    let stride = frame.stride(0);
+    mirror_rows(frame.data_mut(0), stride, row_len, bytes_per_pixel);
+}
+
+/// Flips each `stride` long row of `data` horizontally, leaving any padding after the first `row_len` bytes alone.
+fn mirror_rows(data: &mut [u8], stride: usize, row_len: usize, bytes_per_pixel: usize) {
+    for row in data.chunks_mut(stride) {
+        if let Some(pixels) = row.get_mut(..row_len) {
+            // reversing the bytes reverses the pixels, then each pixel's bytes are put back in order
+            pixels.reverse();
+            pixels
+                .chunks_exact_mut(bytes_per_pixel)
+                .for_each(<[u8]>::reverse);
         }
     }
 }
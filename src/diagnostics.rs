@@ -0,0 +1,229 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{native_api_backend, permissions, query, registered_backends, Camera};
+use nokhwa_core::types::{
+    ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, PermissionStatus,
+    RequestedFormat, RequestedFormatType,
+};
+#[cfg(feature = "serialize")]
+use serde::Serialize;
+
+/// A snapshot of the cameras of the system and what they can do, made by [`diagnostics()`]. With the `serialize`
+/// feature it can be serialized (e.g. to JSON) and attached to a bug report, which gives you the formats a camera
+/// offered when format negotiation fails on a machine you cannot access.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct DiagnosticsReport {
+    nokhwa_version: String,
+    os: String,
+    arch: String,
+    permission: Result<PermissionStatus, String>,
+    backends: Vec<BackendReport>,
+}
+
+impl DiagnosticsReport {
+    /// Get the version of `nokhwa` that made the report.
+    #[must_use]
+    pub fn nokhwa_version(&self) -> &str {
+        &self.nokhwa_version
+    }
+
+    /// Get the operating system, as in [`std::env::consts::OS`].
+    #[must_use]
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    /// Get the CPU architecture, as in [`std::env::consts::ARCH`].
+    #[must_use]
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    /// Get whether the application could use cameras, see [`permissions::status()`].
+    /// # Errors
+    /// If the status could not be read, this is why.
+    pub fn permission(&self) -> Result<PermissionStatus, &str> {
+        self.permission.as_ref().copied().map_err(String::as_str)
+    }
+
+    /// Get the reports of each backend that was queried.
+    #[must_use]
+    pub fn backends(&self) -> &[BackendReport] {
+        &self.backends
+    }
+}
+
+/// The cameras one backend listed, see [`DiagnosticsReport`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct BackendReport {
+    backend: ApiBackend,
+    version: Option<String>,
+    devices: Result<Vec<DeviceReport>, String>,
+}
+
+impl BackendReport {
+    /// Get the backend that was queried.
+    #[must_use]
+    pub fn backend(&self) -> ApiBackend {
+        self.backend
+    }
+
+    /// Get the version of what the backend runs on, if it is known.
+    /// # Quirks
+    /// - `Video4Linux`: The release of the kernel, which the V4L2 API is part of.
+    /// - `FFmpeg`: The version of `libavformat`.
+    /// - Other backends report `None`.
+    #[must_use]
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Get the cameras the backend listed.
+    /// # Errors
+    /// If querying the backend failed, this is why.
+    pub fn devices(&self) -> Result<&[DeviceReport], &str> {
+        self.devices.as_deref().map_err(String::as_str)
+    }
+}
+
+/// One camera, see [`DiagnosticsReport`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct DeviceReport {
+    info: CameraInfo,
+    opened_format: Option<CameraFormat>,
+    formats: Result<Vec<CameraFormat>, String>,
+    controls: Result<Vec<CameraControl>, String>,
+    open_error: Option<String>,
+}
+
+impl DeviceReport {
+    /// Get the [`CameraInfo`] the backend listed the camera with.
+    #[must_use]
+    pub fn info(&self) -> &CameraInfo {
+        &self.info
+    }
+
+    /// Get the format the camera was opened with when it was not asked for any, or `None` if it could not be opened.
+    #[must_use]
+    pub fn opened_format(&self) -> Option<CameraFormat> {
+        self.opened_format
+    }
+
+    /// Get the formats the camera listed. This is empty if the camera could not be opened.
+    /// # Errors
+    /// If listing the formats failed, this is why.
+    pub fn formats(&self) -> Result<&[CameraFormat], &str> {
+        self.formats.as_deref().map_err(String::as_str)
+    }
+
+    /// Get the controls the camera listed. This is empty if the camera could not be opened.
+    /// # Errors
+    /// If listing the controls failed, this is why.
+    pub fn controls(&self) -> Result<&[CameraControl], &str> {
+        self.controls.as_deref().map_err(String::as_str)
+    }
+
+    /// Get why the camera could not be opened, if it could not.
+    #[must_use]
+    pub fn open_error(&self) -> Option<&str> {
+        self.open_error.as_deref()
+    }
+}
+
+/// Collects the cameras of the native backend, `FFmpeg` (with `input-ffmpeg`) and the [registered](crate::register_backend)
+/// backends, along with their formats and controls, into a [`DiagnosticsReport`].
+///
+/// Each camera is opened to read its formats and controls, without starting a stream. Errors along the way are
+/// recorded in the report rather than returned, as they are what a bug report needs most.
+#[must_use]
+pub fn diagnostics() -> DiagnosticsReport {
+    let mut backends = native_api_backend().into_iter().collect::<Vec<_>>();
+    if cfg!(feature = "input-ffmpeg") {
+        backends.push(ApiBackend::FFmpeg);
+    }
+    backends.extend(registered_backends().unwrap_or_default());
+
+    DiagnosticsReport {
+        nokhwa_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        permission: permissions::status().map_err(|why| why.to_string()),
+        backends: backends.into_iter().map(backend_report).collect(),
+    }
+}
+
+fn backend_report(backend: ApiBackend) -> BackendReport {
+    BackendReport {
+        backend,
+        version: backend_version(backend),
+        devices: query(backend)
+            .map(|cameras| {
+                cameras
+                    .into_iter()
+                    .map(|info| device_report(backend, info))
+                    .collect()
+            })
+            .map_err(|why| why.to_string()),
+    }
+}
+
+fn device_report(backend: ApiBackend, info: CameraInfo) -> DeviceReport {
+    match Camera::with_backend(
+        CameraIndex::String(info.unique_id()),
+        RequestedFormat::new(RequestedFormatType::None),
+        backend,
+    ) {
+        Ok(mut camera) => DeviceReport {
+            info,
+            opened_format: Some(camera.camera_format()),
+            formats: camera
+                .compatible_camera_formats()
+                .map_err(|why| why.to_string()),
+            controls: camera.camera_controls().map_err(|why| why.to_string()),
+            open_error: None,
+        },
+        Err(why) => DeviceReport {
+            info,
+            opened_format: None,
+            formats: Ok(vec![]),
+            controls: Ok(vec![]),
+            open_error: Some(why.to_string()),
+        },
+    }
+}
+
+fn backend_version(backend: ApiBackend) -> Option<String> {
+    match backend {
+        ApiBackend::Video4Linux => std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .ok()
+            .map(|release| release.trim().to_string()),
+        #[cfg(feature = "input-ffmpeg")]
+        ApiBackend::FFmpeg => {
+            let version = ffmpeg_next::format::version();
+            Some(format!(
+                "libavformat {}.{}.{}",
+                version >> 16,
+                (version >> 8) & 0xFF,
+                version & 0xFF
+            ))
+        }
+        _ => None,
+    }
+}
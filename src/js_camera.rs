@@ -21,10 +21,10 @@
 //! This assumes that you are running a modern browser on the desktop.
 
 use image::{buffer::ConvertBuffer, ImageBuffer, Rgb, RgbImage, Rgba};
-use js_sys::{Array, JsString, Map, Object, Promise};
+use js_sys::{Array, JsString, Map, Object, Promise, Reflect};
 use nokhwa_core::{
     error::NokhwaError,
-    types::{CameraIndex, CameraInfo, Resolution},
+    types::{CameraIndex, CameraInfo, PermissionStatus, Resolution},
 };
 use std::{
     borrow::{Borrow, Cow},
@@ -37,7 +37,8 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::{
     console::log_1, CanvasRenderingContext2d, Document, Element, HtmlCanvasElement,
     HtmlVideoElement, ImageData, MediaDeviceInfo, MediaDeviceKind, MediaDevices, MediaStream,
-    MediaStreamConstraints, MediaStreamTrack, MediaStreamTrackState, Navigator, Node, Window,
+    MediaStreamConstraints, MediaStreamTrack, MediaStreamTrackState, Navigator, Node,
+    PermissionState, Window,
 };
 
 // why no code completion
@@ -222,6 +223,48 @@ pub async fn request_permission() -> Result<(), NokhwaError> {
     }
 }
 
+/// Queries the Webcam permission state from the browser using [`Permissions::query()`](https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Permissions.html#method.query) [MDN](https://developer.mozilla.org/en-US/docs/Web/API/Permissions/query)
+/// # Errors
+/// This will error if there is no valid web context or the browser does not support querying the `camera` permission.
+pub async fn query_permission() -> Result<PermissionStatus, NokhwaError> {
+    let window: Window = window()?;
+    let permissions =
+        window
+            .navigator()
+            .permissions()
+            .map_err(|why| NokhwaError::StructureError {
+                structure: "Permissions".to_string(),
+                error: format!("{why:?}"),
+            })?;
+
+    let descriptor = Object::new();
+    if let Err(why) = Reflect::set(&descriptor, &jsv!("name"), &jsv!("camera")) {
+        return Err(NokhwaError::StructureError {
+            structure: "PermissionDescriptor".to_string(),
+            error: format!("{why:?}"),
+        });
+    }
+
+    let promise = permissions
+        .query(&descriptor)
+        .map_err(|why| NokhwaError::StructureError {
+            structure: "PermissionStatus".to_string(),
+            error: format!("{why:?}"),
+        })?;
+    let status = JsFuture::from(promise)
+        .await
+        .map_err(|why| NokhwaError::StructureError {
+            structure: "PermissionStatus".to_string(),
+            error: format!("{why:?}"),
+        })?;
+
+    match web_sys::PermissionStatus::from(status).state() {
+        PermissionState::Granted => Ok(PermissionStatus::Authorized),
+        PermissionState::Denied => Ok(PermissionStatus::Denied),
+        _ => Ok(PermissionStatus::NotDetermined),
+    }
+}
+
 /// Requests Webcam permissions from the browser using [`MediaDevices::get_user_media()`](https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.MediaDevices.html#method.get_user_media) [MDN](https://developer.mozilla.org/en-US/docs/Web/API/MediaDevices/getUserMedia)
 /// # Errors
 /// This will error if there is no valid web context or the web API is not supported
@@ -33,29 +33,77 @@
 /// Raw access to each of Nokhwa's backends.
 pub mod backends;
 mod camera;
+/// Snapshots of the cameras of the system, for bug reports.
+pub mod diagnostics;
 /// A camera that uses native browser APIs meant for WASM applications.
 #[cfg(feature = "input-jscam")]
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-jscam")))]
 pub mod js_camera;
 
+/// Several cameras streaming at once through one `AVFoundation` capture session.
+#[cfg(all(
+    feature = "input-avfoundation",
+    any(target_os = "macos", target_os = "ios")
+))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-avfoundation")))]
+pub mod multi_camera;
+
+/// Checking and requesting permission to use cameras.
+pub mod permissions;
 mod query;
+mod registry;
 /// A camera that runs in a different thread and can call your code based on callbacks.
 #[cfg(feature = "output-threaded")]
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-threaded")))]
 pub mod threaded;
 
-/// Convert to RGB using ffimage-yuv
-#[cfg(feature = "output-convert-to-rgb")]
-#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-convert-to-rgb")))]
+/// A camera driven from `async` code, delivering frames as a `Stream`.
+#[cfg(feature = "output-async")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-async")))]
+pub mod async_camera;
+
+/// A callback camera for `tokio` applications, delivering frames through `tokio::sync` channels.
+#[cfg(feature = "output-tokio")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-tokio")))]
+pub mod tokio_camera;
+
+/// Convert to RGB using ffmpeg, or only the converters of `nokhwa-core` with `convert-to-rgb-pure`
+#[cfg(any(feature = "output-convert-to-rgb", feature = "convert-to-rgb-pure"))]
+#[cfg_attr(
+    feature = "docs-features",
+    doc(cfg(any(feature = "output-convert-to-rgb", feature = "convert-to-rgb-pure")))
+)]
 pub mod convert_to_rgb;
 
+/// A virtual camera sink that republishes frames to other applications.
+#[cfg(feature = "output-virtual-camera")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-virtual-camera")))]
+pub mod virtual_camera;
+
+#[cfg(feature = "output-async")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-async")))]
+pub use async_camera::AsyncCamera;
 pub use camera::Camera;
+pub use diagnostics::diagnostics;
+#[cfg(all(
+    feature = "input-avfoundation",
+    any(target_os = "macos", target_os = "ios")
+))]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "input-avfoundation")))]
+pub use multi_camera::MultiCamera;
 pub use nokhwa_core::buffer::FrameBuffer;
 pub use nokhwa_core::error::NokhwaError;
 pub use query::*;
+pub use registry::{register_backend, registered_backends, unregister_backend};
 #[cfg(feature = "output-threaded")]
 #[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-threaded")))]
-pub use threaded::CallbackCamera;
+pub use threaded::{CallbackCamera, CallbackStats, PanicPolicy, SubscriptionMode, WatchdogAction};
+#[cfg(feature = "output-tokio")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-tokio")))]
+pub use tokio_camera::TokioCallbackCamera;
+#[cfg(feature = "output-virtual-camera")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-virtual-camera")))]
+pub use virtual_camera::VirtualCamera;
 
 pub mod utils {
     pub use nokhwa_core::types::*;
@@ -76,3 +124,11 @@ pub mod pixel_format {
 pub mod buffer {
     pub use nokhwa_core::buffer::*;
 }
+
+pub mod conversion {
+    pub use nokhwa_core::conversion::*;
+}
+
+pub mod transform {
+    pub use nokhwa_core::transform::*;
+}
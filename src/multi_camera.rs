@@ -0,0 +1,260 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use flume::{Receiver, RecvTimeoutError, Sender};
+use nokhwa_bindings_macos::{AVCaptureDeviceWrapper, MultiCamSession};
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    types::{ApiBackend, CameraFormat, CameraIndex, CameraInfo, RequestedFormat},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How long [`MultiCamera::frames()`] waits for a camera to deliver a frame.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Stream {
+    device: AVCaptureDeviceWrapper,
+    info: CameraInfo,
+    format: CameraFormat,
+    receiver: Receiver<FrameBuffer>,
+    sender: Arc<Sender<FrameBuffer>>,
+    latest: Option<FrameBuffer>,
+}
+
+/// Several cameras streaming at once through one capture session, such as the front and back camera of an iPhone, or two
+/// external cameras on a Mac.
+///
+/// Opening a second [`Camera`](crate::Camera) makes its session fight the first one over the capture hardware, which
+/// this avoids. [`frames()`](MultiCamera::frames) returns one frame per camera, matched up by capture time.
+/// # Quirks
+/// - This is only available on `AVFoundation`.
+/// - On iOS this needs a device that supports `AVCaptureMultiCamSession`, see [`is_supported()`](MultiCamera::is_supported),
+///   and the formats of the cameras have to fit within what its hardware can stream at once.
+pub struct MultiCamera {
+    session: Option<MultiCamSession>,
+    streams: Vec<Stream>,
+    sync_tolerance: Option<Duration>,
+}
+
+impl MultiCamera {
+    /// Checks if this device can stream several cameras at once.
+    #[must_use]
+    pub fn is_supported() -> bool {
+        MultiCamSession::is_supported()
+    }
+
+    /// Creates the cameras at `indices`, each in the format that best fulfills `format`. Frames are returned in the same
+    /// order as `indices`.
+    /// # Errors
+    /// If this device cannot stream several cameras at once, a camera cannot be found, or `format` cannot be fulfilled by
+    /// one of them, this will error.
+    pub fn new(indices: &[CameraIndex], format: RequestedFormat) -> Result<Self, NokhwaError> {
+        if !Self::is_supported() {
+            return Err(NokhwaError::UnsupportedOperationError(
+                ApiBackend::AVFoundation,
+            ));
+        }
+        let streams = indices
+            .iter()
+            .map(|index| {
+                let mut device = AVCaptureDeviceWrapper::new(index)?;
+                let formats = device.supported_formats()?;
+                let camera_format = format.fulfill(&formats).ok_or_else(|| {
+                    NokhwaError::OpenDeviceError(
+                        "Cannot fulfill request".to_string(),
+                        format.to_string(),
+                    )
+                })?;
+                device.set_all(camera_format)?;
+                let info = device.info().clone();
+                let (sender, receiver) = flume::unbounded();
+                Ok(Stream {
+                    device,
+                    info,
+                    format: camera_format,
+                    receiver,
+                    sender: Arc::new(sender),
+                    latest: None,
+                })
+            })
+            .collect::<Result<Vec<_>, NokhwaError>>()?;
+        Ok(MultiCamera {
+            session: None,
+            streams,
+            sync_tolerance: None,
+        })
+    }
+
+    /// Gets the number of cameras.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Checks if there are no cameras.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Gets the [`CameraInfo`] of the camera at `index`.
+    #[must_use]
+    pub fn camera_info(&self, index: usize) -> Option<&CameraInfo> {
+        self.streams.get(index).map(|stream| &stream.info)
+    }
+
+    /// Gets the [`CameraFormat`] of the camera at `index`.
+    #[must_use]
+    pub fn camera_format(&self, index: usize) -> Option<CameraFormat> {
+        self.streams.get(index).map(|stream| stream.format)
+    }
+
+    /// Sets how far apart the capture times of the frames returned together by [`frames()`](MultiCamera::frames) may be.
+    /// `None`, the default, is half the frame interval of the slowest camera.
+    pub fn set_sync_tolerance(&mut self, tolerance: Option<Duration>) {
+        self.sync_tolerance = tolerance;
+    }
+
+    /// Gets how far apart the capture times of the frames returned together may be, see
+    /// [`set_sync_tolerance()`](MultiCamera::set_sync_tolerance).
+    #[must_use]
+    pub fn sync_tolerance(&self) -> Duration {
+        self.sync_tolerance.unwrap_or_else(|| {
+            let slowest = self
+                .streams
+                .iter()
+                .map(|stream| stream.format.frame_rate())
+                .min()
+                .unwrap_or(0)
+                .max(1);
+            Duration::from_secs(1) / (2 * slowest)
+        })
+    }
+
+    /// Starts streaming all cameras. Does nothing if they already are.
+    /// # Errors
+    /// If a camera cannot be opened, or the hardware cannot stream all of them at once in their formats, this will error.
+    pub fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.session.is_some() {
+            return Ok(());
+        }
+        let mut session = MultiCamSession::new()?;
+        for (index, stream) in self.streams.iter_mut().enumerate() {
+            stream.latest = None;
+            let _ = stream.receiver.drain();
+            session.add_device(
+                &mut stream.device,
+                stream.format,
+                &format!("{}_MULTICAM{index}_", stream.info),
+                stream.sender.clone(),
+            )?;
+        }
+        session.start();
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Checks if the cameras are streaming.
+    #[must_use]
+    pub fn is_stream_open(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Gets one frame from every camera, in the order the cameras were given in, whose capture times are at most
+    /// [`sync_tolerance()`](MultiCamera::sync_tolerance) apart. Older frames of a camera that runs ahead are skipped.
+    /// # Errors
+    /// If the stream is not open, or a camera stops delivering frames, this will error.
+    pub fn frames(&mut self) -> Result<Vec<FrameBuffer>, NokhwaError> {
+        if self.session.is_none() {
+            return Err(NokhwaError::ReadFrameError(
+                "Stream is not open".to_string(),
+            ));
+        }
+        let tolerance = self.sync_tolerance();
+        let deadline = Instant::now() + FRAME_TIMEOUT;
+
+        for stream in &mut self.streams {
+            stream.next_frame(deadline)?;
+        }
+        loop {
+            let newest = self
+                .streams
+                .iter()
+                .filter_map(|stream| stream.latest.as_ref().map(FrameBuffer::timestamp))
+                .max();
+            let Some(newest) = newest else {
+                break;
+            };
+            let mut in_sync = true;
+            for stream in &mut self.streams {
+                let behind = stream.latest.as_ref().is_some_and(|frame| {
+                    newest.saturating_duration_since(frame.timestamp()) > tolerance
+                });
+                if behind {
+                    in_sync = false;
+                    stream.next_frame(deadline)?;
+                }
+            }
+            if in_sync {
+                break;
+            }
+        }
+
+        Ok(self
+            .streams
+            .iter_mut()
+            .filter_map(|stream| stream.latest.take())
+            .collect())
+    }
+
+    /// Stops streaming all cameras.
+    pub fn stop_stream(&mut self) {
+        // dropping the session stops it and detaches the devices
+        self.session = None;
+        for stream in &mut self.streams {
+            stream.latest = None;
+            let _ = stream.receiver.drain();
+        }
+    }
+}
+
+impl Stream {
+    /// Replaces the latest frame with the newest one that arrived, waiting for one until `deadline`.
+    fn next_frame(&mut self, deadline: Instant) -> Result<(), NokhwaError> {
+        let frame = match self.receiver.recv_deadline(deadline) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(NokhwaError::ReadFrameError(format!(
+                    "Timed out waiting for a frame from {}",
+                    self.info
+                )))
+            }
+            Err(why) => return Err(NokhwaError::ReadFrameError(why.to_string())),
+        };
+        self.latest = Some(self.receiver.drain().last().unwrap_or(frame));
+        Ok(())
+    }
+}
+
+impl Drop for MultiCamera {
+    fn drop(&mut self) {
+        self.stop_stream();
+    }
+}
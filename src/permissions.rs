@@ -0,0 +1,240 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use nokhwa_core::{error::NokhwaError, types::PermissionStatus};
+
+#[cfg(any(
+    all(
+        feature = "input-avfoundation",
+        any(target_os = "macos", target_os = "ios")
+    ),
+    all(feature = "input-msmf", target_os = "windows")
+))]
+use completion::completion;
+
+/// Gets whether the application may capture from cameras, without prompting the user.
+///
+/// Platforms without a permission model report [`PermissionStatus::Authorized`].
+/// # Platform
+/// - `AVFoundation`: The `AVCaptureDevice` authorization status for video.
+/// - `MediaFoundation`: The `webcam` app capability, which follows the camera privacy settings.
+/// - `Video4Linux`: Whether the current user can open the `/dev/video*` nodes. `Denied` usually means the user is not in the `video` group. This never reports `NotDetermined`.
+/// - `Browser`: The permission state is only available asynchronously, use [`request()`] or `js_camera::query_permission()` instead.
+/// # Errors
+/// This will error if the platform API fails, or on the browser.
+pub fn status() -> Result<PermissionStatus, NokhwaError> {
+    #[cfg(all(
+        feature = "input-avfoundation",
+        any(target_os = "macos", target_os = "ios")
+    ))]
+    {
+        Ok(nokhwa_bindings_macos::authorization_status())
+    }
+    #[cfg(all(feature = "input-msmf", target_os = "windows"))]
+    {
+        nokhwa_bindings_windows::wmf::webcam_capability_status()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(device_node_status())
+    }
+    #[cfg(all(feature = "input-jscam", target_arch = "wasm32"))]
+    {
+        Err(NokhwaError::NotImplementedError(
+            "The browser permission state must be queried with js_camera::query_permission()"
+                .to_string(),
+        ))
+    }
+    #[cfg(not(any(
+        all(
+            feature = "input-avfoundation",
+            any(target_os = "macos", target_os = "ios")
+        ),
+        all(feature = "input-msmf", target_os = "windows"),
+        target_os = "linux",
+        all(feature = "input-jscam", target_arch = "wasm32")
+    )))]
+    {
+        Ok(PermissionStatus::Authorized)
+    }
+}
+
+/// Asks the user for permission to use cameras, showing the system prompt if they have not been asked yet,
+/// and resolves to the resulting [`PermissionStatus`]. If the user has already answered, this resolves immediately
+/// without prompting again.
+///
+/// The returned future does not depend on any particular async runtime.
+/// # Platform
+/// - `AVFoundation`: Calls `requestAccessForMediaType:completionHandler:`. The application must have an `NSCameraUsageDescription` entry in its `Info.plist`, otherwise the process is terminated by the OS.
+/// - `MediaFoundation`: Requests the `webcam` app capability on a background thread.
+/// - `Video4Linux`: There is no prompt, this is the same as [`status()`].
+/// - `Browser`: Calls `getUserMedia()` to trigger the prompt, then queries `navigator.permissions`.
+/// # Errors
+/// This will error if the platform API fails.
+#[allow(clippy::unused_async)]
+pub async fn request() -> Result<PermissionStatus, NokhwaError> {
+    #[cfg(all(
+        feature = "input-avfoundation",
+        any(target_os = "macos", target_os = "ios")
+    ))]
+    {
+        let current = nokhwa_bindings_macos::authorization_status();
+        if current != PermissionStatus::NotDetermined {
+            return Ok(current);
+        }
+        let (completer, completion) = completion();
+        nokhwa_bindings_macos::request_authorization(move |_| completer.complete(()));
+        completion.await;
+        Ok(nokhwa_bindings_macos::authorization_status())
+    }
+    #[cfg(all(feature = "input-msmf", target_os = "windows"))]
+    {
+        let (completer, completion) = completion();
+        std::thread::spawn(move || {
+            completer.complete(nokhwa_bindings_windows::wmf::request_webcam_capability());
+        });
+        completion.await
+    }
+    #[cfg(all(feature = "input-jscam", target_arch = "wasm32"))]
+    {
+        let granted = crate::js_camera::request_permission().await.is_ok();
+        match crate::js_camera::query_permission().await {
+            Ok(status) => Ok(status),
+            // not every browser can query the camera permission
+            Err(_) if granted => Ok(PermissionStatus::Authorized),
+            Err(_) => Ok(PermissionStatus::Denied),
+        }
+    }
+    #[cfg(not(any(
+        all(
+            feature = "input-avfoundation",
+            any(target_os = "macos", target_os = "ios")
+        ),
+        all(feature = "input-msmf", target_os = "windows"),
+        all(feature = "input-jscam", target_arch = "wasm32")
+    )))]
+    {
+        status()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn device_node_status() -> PermissionStatus {
+    use std::{fs::OpenOptions, io::ErrorKind};
+
+    let Ok(entries) = std::fs::read_dir("/dev") else {
+        return PermissionStatus::Authorized;
+    };
+
+    let mut found = false;
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("video") {
+            continue;
+        }
+        found = true;
+        match OpenOptions::new().read(true).write(true).open(entry.path()) {
+            Err(why) if why.kind() == ErrorKind::PermissionDenied => {}
+            // anything else is not a permission problem
+            _ => return PermissionStatus::Authorized,
+        }
+    }
+
+    if found {
+        PermissionStatus::Denied
+    } else {
+        PermissionStatus::Authorized
+    }
+}
+
+/// A minimal runtime-agnostic oneshot, used to await platform callbacks.
+#[cfg(any(
+    all(
+        feature = "input-avfoundation",
+        any(target_os = "macos", target_os = "ios")
+    ),
+    all(feature = "input-msmf", target_os = "windows")
+))]
+mod completion {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex, MutexGuard},
+        task::{Context, Poll, Waker},
+    };
+
+    struct Shared<T> {
+        value: Option<T>,
+        waker: Option<Waker>,
+    }
+
+    fn lock<T>(shared: &Mutex<Shared<T>>) -> MutexGuard<'_, Shared<T>> {
+        match shared.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// The sending half of a [`Completion`], handed to a platform callback or thread.
+    pub(super) struct Completer<T> {
+        shared: Arc<Mutex<Shared<T>>>,
+    }
+
+    impl<T> Completer<T> {
+        pub(super) fn complete(self, value: T) {
+            let waker = {
+                let mut shared = lock(&self.shared);
+                shared.value = Some(value);
+                shared.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    /// A future that resolves once the matching [`Completer`] is completed.
+    pub(super) struct Completion<T> {
+        shared: Arc<Mutex<Shared<T>>>,
+    }
+
+    impl<T> Future for Completion<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut shared = lock(&self.shared);
+            match shared.value.take() {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    pub(super) fn completion<T>() -> (Completer<T>, Completion<T>) {
+        let shared = Arc::new(Mutex::new(Shared {
+            value: None,
+            waker: None,
+        }));
+        (
+            Completer {
+                shared: shared.clone(),
+            },
+            Completion { shared },
+        )
+    }
+}
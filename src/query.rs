@@ -14,9 +14,14 @@
  * limitations under the License.
  */
 
+use crate::Camera;
+use four_cc::FourCC;
 use nokhwa_core::{
     error::NokhwaError,
-    types::{ApiBackend, CameraInfo},
+    types::{
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, CameraPosition, NodeKind,
+        RequestedFormat, RequestedFormatType, Resolution, SensorType,
+    },
 };
 
 /// Gets the native [`ApiBackend`]
@@ -38,6 +43,11 @@ pub fn native_api_backend() -> Option<ApiBackend> {
 /// - `Media Foundation`: The names may contain invalid characters since they were converted from UTF16.
 /// - `AVFoundation`: The ID of the device is stored in the `misc` attribute of the [`CameraInfo`].
 /// - `AVFoundation`: There is lots of miscellaneous info in the `desc` attribute.
+/// - `Video4Linux`: Only nodes that capture frames are listed, not the metadata nodes of UVC cameras. The USB IDs and bus path
+///   of each camera are read from `udev`.
+/// - `FFmpeg`: The `unique_id` of the [`CameraInfo`] is the URL passed to the demuxer. Mac OS devices cannot be listed.
+/// - `DeckLink`, `GigEVision`, `NDI`: These are not included in `Auto`, query them with their own [`ApiBackend`].
+/// - `Custom`: The backend must have been registered with [`register_backend()`](crate::register_backend).
 /// - `WASM`: The `misc` field contains the device ID and group ID are seperated by a space (' ')
 /// # Errors
 /// If you use an unsupported API (check the README or crate root for more info), incompatible backend for current platform, incompatible platform, or insufficient permissions, etc
@@ -50,6 +60,8 @@ pub fn query(api: ApiBackend) -> Result<Vec<CameraInfo>, NokhwaError> {
                 "linux" => {
                     if cfg!(feature = "input-v4l") && cfg!(target_os = "linux") {
                         query(ApiBackend::Video4Linux)
+                    } else if cfg!(feature = "input-ffmpeg") {
+                        query(ApiBackend::FFmpeg)
                     } else {
                         dbg!("Error: No suitable Backends available. Perhaps you meant to enable one of the backends such as `input-v4l`? (Please read the docs.)");
                         Err(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))
@@ -58,6 +70,8 @@ pub fn query(api: ApiBackend) -> Result<Vec<CameraInfo>, NokhwaError> {
                 "windows" => {
                     if cfg!(feature = "input-msmf") && cfg!(target_os = "windows") {
                         query(ApiBackend::MediaFoundation)
+                    } else if cfg!(feature = "input-ffmpeg") {
+                        query(ApiBackend::FFmpeg)
                     } else {
                         dbg!("Error: No suitable Backends available. Perhaps you meant to enable one of the backends such as `input-msmf`? (Please read the docs.)");
                         Err(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))
@@ -66,6 +80,8 @@ pub fn query(api: ApiBackend) -> Result<Vec<CameraInfo>, NokhwaError> {
                 "macos" => {
                     if cfg!(feature = "input-avfoundation") {
                         query(ApiBackend::AVFoundation)
+                    } else if cfg!(feature = "input-ffmpeg") {
+                        query(ApiBackend::FFmpeg)
                     } else {
                         dbg!("Error: No suitable Backends available. Perhaps you meant to enable one of the backends such as `input-avfoundation`? (Please read the docs.)");
                         Err(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))
@@ -74,6 +90,8 @@ pub fn query(api: ApiBackend) -> Result<Vec<CameraInfo>, NokhwaError> {
                 "ios" => {
                     if cfg!(feature = "input-avfoundation") {
                         query(ApiBackend::AVFoundation)
+                    } else if cfg!(feature = "input-ffmpeg") {
+                        query(ApiBackend::FFmpeg)
                     } else {
                         dbg!("Error: No suitable Backends available. Perhaps you meant to enable one of the backends such as `input-avfoundation`? (Please read the docs.)");
                         Err(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))
@@ -91,9 +109,205 @@ pub fn query(api: ApiBackend) -> Result<Vec<CameraInfo>, NokhwaError> {
         ApiBackend::MediaFoundation => query_msmf(),
         #[allow(deprecated)]
         ApiBackend::Browser => query_wasm(),
+        ApiBackend::FFmpeg => query_ffmpeg(),
+        ApiBackend::DeckLink => query_decklink(),
+        ApiBackend::GigEVision => query_gige(),
+        ApiBackend::NDI => query_ndi(),
+        ApiBackend::Custom(_) => crate::registry::backend_factory(api)?.query(),
     }
 }
 
+/// Picks the camera to open when the user has not chosen one, rather than [`CameraIndex::Index(0)`](CameraIndex::Index),
+/// which often is a virtual camera.
+/// # Quirks
+/// - `AVFoundation`: The default video device of the system.
+/// - `Video4Linux`, `Media Foundation`: Neither has a default camera, so this is the first camera listed by [`query()`]
+///   (i.e. the lowest `/dev/video` node, or the first device `Media Foundation` enumerates) whose name does not mark it as
+///   virtual, like those of `v4l2loopback` and OBS. Infrared and depth streams are skipped.
+/// # Errors
+/// If there is no native backend, the [`query()`] fails, or there is no camera, this will error.
+pub fn default_camera() -> Result<CameraInfo, NokhwaError> {
+    let backend =
+        native_api_backend().ok_or(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))?;
+    if backend == ApiBackend::AVFoundation {
+        if let Some(info) = default_avfoundation_device() {
+            return Ok(info);
+        }
+    }
+    query(backend)?
+        .into_iter()
+        .filter(|info| {
+            info.node_kind() == NodeKind::Capture && info.sensor_type() == SensorType::Color
+        })
+        // the first camera that is not virtual, or else the first one
+        .min_by_key(|info| {
+            let name = info.name().to_lowercase();
+            ["virtual", "loopback", "dummy"]
+                .iter()
+                .any(|marker| name.contains(marker))
+        })
+        .ok_or_else(|| {
+            NokhwaError::OpenDeviceError("Default Camera".to_string(), "Not Found".to_string())
+        })
+}
+
+/// What a camera has to be capable of to be listed by [`query_devices_with()`]. Every requirement is optional, so the
+/// default filter lists every camera.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct QueryFilter {
+    min_resolution: Option<Resolution>,
+    fourcc: Option<FourCC>,
+    frame_rate: Option<u32>,
+    position: Option<CameraPosition>,
+}
+
+impl QueryFilter {
+    /// Creates a filter that lists every camera.
+    #[must_use]
+    pub fn new() -> Self {
+        QueryFilter::default()
+    }
+
+    /// Requires a format at least `resolution` wide and high.
+    #[must_use]
+    pub fn with_min_resolution(mut self, resolution: Resolution) -> Self {
+        self.min_resolution = Some(resolution);
+        self
+    }
+
+    /// Requires a format in `fourcc`.
+    #[must_use]
+    pub fn with_fourcc(mut self, fourcc: FourCC) -> Self {
+        self.fourcc = Some(fourcc);
+        self
+    }
+
+    /// Requires a format with at least `frame_rate` frames per second.
+    #[must_use]
+    pub fn with_frame_rate(mut self, frame_rate: u32) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Requires the camera to be at `position`, see [`CameraInfo::camera_position()`].
+    #[must_use]
+    pub fn with_position(mut self, position: CameraPosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Get the minimum resolution a format has to have, if any.
+    #[must_use]
+    pub fn min_resolution(&self) -> Option<Resolution> {
+        self.min_resolution
+    }
+
+    /// Get the `FourCC` a format has to be in, if any.
+    #[must_use]
+    pub fn fourcc(&self) -> Option<FourCC> {
+        self.fourcc
+    }
+
+    /// Get the minimum frame rate a format has to have, if any.
+    #[must_use]
+    pub fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    /// Get the position the camera has to be at, if any.
+    #[must_use]
+    pub fn position(&self) -> Option<CameraPosition> {
+        self.position
+    }
+
+    /// Checks if `format` meets the resolution, `FourCC` and frame rate requirements. A camera has to meet all of them
+    /// with one format, so one that only does 1080p at 5 FPS and 720p at 30 FPS is not listed for 1080p30.
+    #[must_use]
+    pub fn matches_format(&self, format: &CameraFormat) -> bool {
+        self.min_resolution.is_none_or(|min| {
+            format.resolution().width() >= min.width()
+                && format.resolution().height() >= min.height()
+        }) && self.fourcc.is_none_or(|fourcc| format.format() == fourcc)
+            && self
+                .frame_rate
+                .is_none_or(|frame_rate| format.frame_rate() >= frame_rate)
+    }
+
+    /// Checks if `info` is at the required position.
+    #[must_use]
+    pub fn matches_info(&self, info: &CameraInfo) -> bool {
+        self.position
+            .is_none_or(|position| info.camera_position() == position)
+    }
+
+    fn needs_formats(&self) -> bool {
+        self.min_resolution.is_some() || self.fourcc.is_some() || self.frame_rate.is_some()
+    }
+}
+
+/// Queries `api` like [`query()`], listing only the cameras that pass `filter`, e.g. so that an app can offer only the
+/// cameras that can do 1080p at 30 FPS.
+///
+/// To check the format requirements, each camera that passes the others is opened to list its formats, without
+/// starting a stream. Cameras that cannot be opened, e.g. because another application is using them, are left out.
+/// # Errors
+/// If the [`query()`] of `api` fails, this will error.
+pub fn query_devices_with(
+    api: ApiBackend,
+    filter: &QueryFilter,
+) -> Result<Vec<CameraInfo>, NokhwaError> {
+    Ok(query(api)?
+        .into_iter()
+        .filter(|info| filter.matches_info(info))
+        .filter(|info| {
+            !filter.needs_formats()
+                || supported_formats(api, info)
+                    .iter()
+                    .any(|format| filter.matches_format(format))
+        })
+        .collect())
+}
+
+/// A camera with its formats and controls, as listed by [`query_devices_detailed()`].
+pub type DeviceDetails = (CameraInfo, Vec<CameraFormat>, Vec<CameraControl>);
+
+/// Queries `api` like [`query()`], along with the formats and controls of each camera, e.g. for a device picker that
+/// shows what each camera can do.
+///
+/// Each camera is opened to read these, but no stream is started, so on most cameras the light stays off. Cameras that
+/// cannot be opened, e.g. because another application is using them, are listed without formats or controls.
+/// # Errors
+/// If the [`query()`] of `api` fails, this will error.
+pub fn query_devices_detailed(api: ApiBackend) -> Result<Vec<DeviceDetails>, NokhwaError> {
+    Ok(query(api)?
+        .into_iter()
+        .map(|info| match open_for_query(api, &info) {
+            Some(mut camera) => {
+                let formats = camera.compatible_camera_formats().unwrap_or_default();
+                let controls = camera.camera_controls().unwrap_or_default();
+                (info, formats, controls)
+            }
+            None => (info, vec![], vec![]),
+        })
+        .collect())
+}
+
+// errors are treated as no formats, as a camera that cannot be opened cannot be used either
+fn supported_formats(api: ApiBackend, info: &CameraInfo) -> Vec<CameraFormat> {
+    open_for_query(api, info)
+        .and_then(|mut camera| camera.compatible_camera_formats().ok())
+        .unwrap_or_default()
+}
+
+fn open_for_query(api: ApiBackend, info: &CameraInfo) -> Option<Camera> {
+    Camera::with_backend(
+        CameraIndex::String(info.unique_id()),
+        RequestedFormat::new(RequestedFormatType::None),
+        api,
+    )
+    .ok()
+}
+
 // TODO: More
 
 #[cfg(all(feature = "input-v4l", target_os = "linux"))]
@@ -143,6 +357,64 @@ fn query_avfoundation() -> Result<Vec<CameraInfo>, NokhwaError> {
     ))
 }
 
+#[cfg(all(
+    feature = "input-avfoundation",
+    any(target_os = "macos", target_os = "ios")
+))]
+fn default_avfoundation_device() -> Option<CameraInfo> {
+    nokhwa_bindings_macos::default_avfoundation_device()
+}
+
+#[cfg(not(all(
+    feature = "input-avfoundation",
+    any(target_os = "macos", target_os = "ios")
+)))]
+fn default_avfoundation_device() -> Option<CameraInfo> {
+    None
+}
+
+#[cfg(feature = "input-ffmpeg")]
+fn query_ffmpeg() -> Result<Vec<CameraInfo>, NokhwaError> {
+    crate::backends::capture::query_ffmpeg()
+}
+
+#[cfg(not(feature = "input-ffmpeg"))]
+fn query_ffmpeg() -> Result<Vec<CameraInfo>, NokhwaError> {
+    Err(NokhwaError::UnsupportedOperationError(ApiBackend::FFmpeg))
+}
+
+#[cfg(feature = "input-decklink")]
+fn query_decklink() -> Result<Vec<CameraInfo>, NokhwaError> {
+    crate::backends::capture::query_decklink()
+}
+
+#[cfg(not(feature = "input-decklink"))]
+fn query_decklink() -> Result<Vec<CameraInfo>, NokhwaError> {
+    Err(NokhwaError::UnsupportedOperationError(ApiBackend::DeckLink))
+}
+
+#[cfg(feature = "input-gige")]
+fn query_gige() -> Result<Vec<CameraInfo>, NokhwaError> {
+    crate::backends::capture::query_aravis()
+}
+
+#[cfg(not(feature = "input-gige"))]
+fn query_gige() -> Result<Vec<CameraInfo>, NokhwaError> {
+    Err(NokhwaError::UnsupportedOperationError(
+        ApiBackend::GigEVision,
+    ))
+}
+
+#[cfg(feature = "input-ndi")]
+fn query_ndi() -> Result<Vec<CameraInfo>, NokhwaError> {
+    crate::backends::capture::query_ndi()
+}
+
+#[cfg(not(feature = "input-ndi"))]
+fn query_ndi() -> Result<Vec<CameraInfo>, NokhwaError> {
+    Err(NokhwaError::UnsupportedOperationError(ApiBackend::NDI))
+}
+
 #[cfg(feature = "input-jscam")]
 fn query_wasm() -> Result<Vec<CameraInfo>, NokhwaError> {
     use crate::js_camera::query_js_cameras;
@@ -155,3 +427,138 @@ fn query_wasm() -> Result<Vec<CameraInfo>, NokhwaError> {
 fn query_wasm() -> Result<Vec<CameraInfo>, NokhwaError> {
     Err(NokhwaError::UnsupportedOperationError(ApiBackend::Browser))
 }
+
+/// A change to the cameras connected to the system, as reported by a [`DeviceMonitor`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// A camera was plugged in.
+    Added(CameraInfo),
+    /// The camera with this [`unique_id`](CameraInfo::unique_id) was removed.
+    Removed(String),
+}
+
+/// Watches the native backend for cameras being plugged in or removed, so you do not have to keep calling [`query()`].
+///
+/// Events are sent on the channel returned by [`events()`](DeviceMonitor::events) until the monitor is dropped.
+/// # Quirks
+/// - `Video4Linux`: Uses a `udev` monitor on the `video4linux` subsystem, which needs the `udev` feature.
+/// - `Media Foundation`: Listens for `WM_DEVICECHANGE` on a message-only window.
+/// - `AVFoundation`: Uses `AVCaptureDeviceWasConnectedNotification` / `AVCaptureDeviceWasDisconnectedNotification`. These are delivered on the main thread, so the main run loop must be running.
+#[cfg(feature = "flume")]
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "flume")))]
+pub struct DeviceMonitor {
+    events: flume::Receiver<DeviceEvent>,
+    _notifier: Box<dyn Send>,
+}
+
+#[cfg(feature = "flume")]
+impl DeviceMonitor {
+    /// Starts watching for device changes on the [native backend](native_api_backend).
+    /// # Errors
+    /// If there is no native backend enabled for this platform, the initial [`query()`] fails, or the platform notification could not be registered,
+    /// this will error.
+    pub fn new() -> Result<Self, NokhwaError> {
+        let backend =
+            native_api_backend().ok_or(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))?;
+        let mut known_devices = query(backend)?;
+        let (sender, events) = flume::unbounded();
+
+        let on_change = move || {
+            // notifications carry no usable payload on every platform, so re-query and diff instead
+            let Ok(devices) = query(backend) else {
+                return;
+            };
+            for removed in known_devices
+                .iter()
+                .filter(|known| !devices.iter().any(|d| d.unique_id() == known.unique_id()))
+            {
+                let _ = sender.send(DeviceEvent::Removed(removed.unique_id()));
+            }
+            for added in devices.iter().filter(|device| {
+                !known_devices
+                    .iter()
+                    .any(|k| k.unique_id() == device.unique_id())
+            }) {
+                let _ = sender.send(DeviceEvent::Added(added.clone()));
+            }
+            known_devices = devices;
+        };
+
+        Ok(DeviceMonitor {
+            events,
+            _notifier: native_device_notifier(std::sync::Mutex::new(on_change))?,
+        })
+    }
+
+    /// The channel [`DeviceEvent`]s are delivered on.
+    #[must_use]
+    pub fn events(&self) -> &flume::Receiver<DeviceEvent> {
+        &self.events
+    }
+}
+
+#[cfg(all(
+    feature = "flume",
+    feature = "input-v4l",
+    feature = "udev",
+    target_os = "linux"
+))]
+fn native_device_notifier(
+    on_change: std::sync::Mutex<impl FnMut() + Send + 'static>,
+) -> Result<Box<dyn Send>, NokhwaError> {
+    Ok(Box::new(nokhwa_bindings_linux::DeviceNotifier::new(
+        move || {
+            if let Ok(mut on_change) = on_change.lock() {
+                on_change();
+            }
+        },
+    )?))
+}
+
+#[cfg(all(feature = "flume", feature = "input-msmf", target_os = "windows"))]
+fn native_device_notifier(
+    on_change: std::sync::Mutex<impl FnMut() + Send + 'static>,
+) -> Result<Box<dyn Send>, NokhwaError> {
+    Ok(Box::new(nokhwa_bindings_windows::wmf::DeviceNotifier::new(
+        move || {
+            if let Ok(mut on_change) = on_change.lock() {
+                on_change();
+            }
+        },
+    )?))
+}
+
+#[cfg(all(
+    feature = "flume",
+    feature = "input-avfoundation",
+    any(target_os = "macos", target_os = "ios")
+))]
+fn native_device_notifier(
+    on_change: std::sync::Mutex<impl FnMut() + Send + 'static>,
+) -> Result<Box<dyn Send>, NokhwaError> {
+    Ok(Box::new(nokhwa_bindings_macos::DeviceNotifier::new(
+        move || {
+            if let Ok(mut on_change) = on_change.lock() {
+                on_change();
+            }
+        },
+    )?))
+}
+
+#[cfg(all(
+    feature = "flume",
+    not(any(
+        all(feature = "input-v4l", feature = "udev", target_os = "linux"),
+        all(feature = "input-msmf", target_os = "windows"),
+        all(
+            feature = "input-avfoundation",
+            any(target_os = "macos", target_os = "ios")
+        )
+    ))
+))]
+#[allow(clippy::needless_pass_by_value)]
+fn native_device_notifier(
+    _on_change: std::sync::Mutex<impl FnMut() + Send + 'static>,
+) -> Result<Box<dyn Send>, NokhwaError> {
+    Err(NokhwaError::UnsupportedOperationError(ApiBackend::Auto))
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use nokhwa_core::{error::NokhwaError, traits::CaptureBackendFactory, types::ApiBackend};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+type Registry = RwLock<HashMap<&'static str, Arc<dyn CaptureBackendFactory>>>;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Registers a [`CaptureBackendFactory`] under an [`ApiBackend::Custom`] name, so that [`query()`](crate::query) and
+/// [`Camera::with_backend()`](crate::Camera::with_backend) can use it. Registering the same name twice replaces the old factory.
+/// # Errors
+/// This will error if `backend` is not [`ApiBackend::Custom`].
+pub fn register_backend(
+    backend: ApiBackend,
+    factory: Box<dyn CaptureBackendFactory>,
+) -> Result<(), NokhwaError> {
+    let ApiBackend::Custom(name) = backend else {
+        return Err(NokhwaError::UnsupportedOperationError(backend));
+    };
+    registry()
+        .write()
+        .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+        .insert(name, Arc::from(factory));
+    Ok(())
+}
+
+/// Removes a previously registered [`ApiBackend::Custom`] backend. Returns `true` if it was registered.
+/// # Errors
+/// This will error if the registry lock is poisoned.
+pub fn unregister_backend(backend: ApiBackend) -> Result<bool, NokhwaError> {
+    let ApiBackend::Custom(name) = backend else {
+        return Ok(false);
+    };
+    Ok(registry()
+        .write()
+        .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+        .remove(name)
+        .is_some())
+}
+
+/// Gets the names of all registered [`ApiBackend::Custom`] backends.
+/// # Errors
+/// This will error if the registry lock is poisoned.
+pub fn registered_backends() -> Result<Vec<ApiBackend>, NokhwaError> {
+    Ok(registry()
+        .read()
+        .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+        .keys()
+        .map(|name| ApiBackend::Custom(name))
+        .collect())
+}
+
+pub(crate) fn backend_factory(
+    backend: ApiBackend,
+) -> Result<Arc<dyn CaptureBackendFactory>, NokhwaError> {
+    let ApiBackend::Custom(name) = backend else {
+        return Err(NokhwaError::UnsupportedOperationError(backend));
+    };
+    registry()
+        .read()
+        .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+        .get(name)
+        .cloned()
+        .ok_or(NokhwaError::UnsupportedOperationError(backend))
+}
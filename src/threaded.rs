@@ -15,24 +15,33 @@
  */
 
 use crate::Camera;
+use flume::{Receiver, SendTimeoutError, Sender, TrySendError};
 use four_cc::FourCC;
 use nokhwa_core::{
     buffer::FrameBuffer,
     error::NokhwaError,
     pixel_format::GRAY,
+    transform::FrameTransformer,
     types::{
-        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ControlValueSetter,
-        KnownCameraControl, RequestedFormat, RequestedFormatType, Resolution,
+        ApiBackend, CameraControl, CameraFormat, CameraIndex, CameraInfo, ConnectionState,
+        ControlValueSetter, Exposure, Focus, KnownCameraControl, PtzCapabilities, Rect,
+        RequestedFormat, RequestedFormatType, Resolution, SessionEvent, StreamStats,
+        ThreadPriority, WhiteBalance, XuQuery,
     },
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Formatter},
+    panic::{catch_unwind, AssertUnwindSafe},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError,
     },
 };
-use std::{thread::JoinHandle, time::Instant};
+use std::{
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
 
 type AtomicLock<T> = Arc<Mutex<T>>;
 pub type CallbackFn = fn(
@@ -42,6 +51,341 @@ pub type CallbackFn = fn(
     _die_bool: &Arc<AtomicBool>,
 );
 type HeldCallbackType = Arc<Mutex<Box<dyn FnMut(FrameBuffer) + Send + 'static>>>;
+type EventCallback = Box<dyn FnMut(SessionEvent) + Send + 'static>;
+
+// how far back the rolling frame rate and callback duration of `CallbackStats` look
+const STATS_WINDOW: Duration = Duration::from_secs(2);
+// the longest the capture thread waits for a frame with a watchdog, so it still notices being shut down
+const WATCHDOG_POLL: Duration = Duration::from_millis(100);
+
+/// How a [`CallbackCamera::subscribe()`] receiver is fed, and what happens when the subscriber falls behind.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SubscriptionMode {
+    /// The subscriber gets every frame. Frames queue up without limit if it falls behind, so a slow subscriber
+    /// grows the memory use of a long session.
+    EveryFrame,
+    /// The subscriber gets every frame, with at most this many queued. Once the queue is full, the callback thread
+    /// waits for the subscriber, which also holds up the callback and the other subscribers.
+    Bounded(usize),
+    /// The subscriber holds on to at most this many frames. Once the queue is full, the oldest frame it has not
+    /// received yet is dropped to make room.
+    DropOldest(usize),
+    /// The subscriber only holds on to the newest frame. Older frames it has not received yet are replaced.
+    LatestOnly,
+}
+
+struct Subscriber {
+    sender: Sender<FrameBuffer>,
+    // kept to evict the stale frames of a `DropOldest` or `LatestOnly` subscriber
+    receiver: Option<Receiver<FrameBuffer>>,
+    blocking: bool,
+}
+
+impl Subscriber {
+    fn new(mode: SubscriptionMode) -> (Self, Receiver<FrameBuffer>) {
+        let (sender, receiver) = match mode {
+            SubscriptionMode::EveryFrame => flume::unbounded(),
+            SubscriptionMode::Bounded(capacity) => flume::bounded(capacity),
+            SubscriptionMode::DropOldest(capacity) => flume::bounded(capacity.max(1)),
+            SubscriptionMode::LatestOnly => flume::bounded(1),
+        };
+        let evicting = matches!(
+            mode,
+            SubscriptionMode::DropOldest(_) | SubscriptionMode::LatestOnly
+        );
+        let subscriber = Subscriber {
+            sender,
+            receiver: evicting.then(|| receiver.clone()),
+            blocking: matches!(mode, SubscriptionMode::Bounded(_)),
+        };
+        (subscriber, receiver)
+    }
+
+    /// Whether the [`Receiver`] handed out for this subscriber was dropped. The one kept for evicting frames does not
+    /// count, or it would keep the subscriber and its frames around forever.
+    fn is_gone(&self) -> bool {
+        self.sender.receiver_count() <= usize::from(self.receiver.is_some())
+    }
+
+    /// Hands `frame` to the subscriber, waiting for room if it is [`SubscriptionMode::Bounded`] until `die_bool` is
+    /// set.
+    fn send(&self, frame: FrameBuffer, die_bool: &AtomicBool) -> Delivery {
+        if self.is_gone() {
+            return Delivery::Gone;
+        }
+        if self.blocking {
+            let mut frame = frame;
+            loop {
+                match self.sender.send_timeout(frame, Duration::from_millis(50)) {
+                    Ok(()) => return Delivery::Queued,
+                    Err(SendTimeoutError::Disconnected(_)) => return Delivery::Gone,
+                    Err(SendTimeoutError::Timeout(_)) if die_bool.load(Ordering::SeqCst) => {
+                        return Delivery::Dropped
+                    }
+                    Err(SendTimeoutError::Timeout(unsent)) => frame = unsent,
+                }
+            }
+        }
+        match (&self.receiver, self.sender.try_send(frame)) {
+            (Some(receiver), Err(TrySendError::Full(frame))) => {
+                let _stale = receiver.try_recv();
+                match self.sender.try_send(frame) {
+                    Err(TrySendError::Disconnected(_)) => Delivery::Gone,
+                    Ok(()) | Err(TrySendError::Full(_)) => Delivery::Dropped,
+                }
+            }
+            (_, Err(TrySendError::Disconnected(_))) => Delivery::Gone,
+            (_, Err(TrySendError::Full(_))) => Delivery::Dropped,
+            (_, Ok(())) => Delivery::Queued,
+        }
+    }
+}
+
+/// What became of a frame handed to a [`Subscriber`].
+enum Delivery {
+    Queued,
+    // either the frame or a stale one it replaced
+    Dropped,
+    Gone,
+}
+
+/// Delivery statistics of the callback thread, see [`CallbackCamera::stats()`].
+#[derive(Default)]
+struct DeliveryStats {
+    // when each frame in the last `STATS_WINDOW` was handed out, and how long the callback took with it
+    deliveries: VecDeque<(Instant, Duration)>,
+    dropped_by_subscribers: u64,
+}
+
+impl DeliveryStats {
+    fn record(&mut self, delivered: Instant, callback_duration: Duration) {
+        self.deliveries.push_back((delivered, callback_duration));
+        self.prune(delivered);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some((delivered, _)) = self.deliveries.front() {
+            if now.duration_since(*delivered) <= STATS_WINDOW {
+                break;
+            }
+            self.deliveries.pop_front();
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn frame_rate(&self) -> f64 {
+        match (self.deliveries.front(), self.deliveries.back()) {
+            (Some((first, _)), Some((last, _))) if last > first => {
+                (self.deliveries.len() - 1) as f64 / last.duration_since(*first).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn average_callback_duration(&self) -> Duration {
+        let total = self
+            .deliveries
+            .iter()
+            .map(|(_, duration)| *duration)
+            .sum::<Duration>();
+        u32::try_from(self.deliveries.len())
+            .ok()
+            .and_then(|count| total.checked_div(count))
+            .unwrap_or_default()
+    }
+}
+
+/// How well a [`CallbackCamera`] keeps up with its camera, see [`CallbackCamera::stats()`].
+///
+/// The frame rate and callback duration are rolling, over the frames of the last two seconds.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CallbackStats {
+    frame_rate: f64,
+    average_callback_duration: Duration,
+    queue_depth: usize,
+    dropped_by_subscribers: u64,
+    stream: StreamStats,
+}
+
+impl CallbackStats {
+    /// Gets the rate at which the callback thread hands out frames, in frames per second.
+    #[must_use]
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    /// Gets how long the callback takes with a frame on average.
+    #[must_use]
+    pub fn average_callback_duration(&self) -> Duration {
+        self.average_callback_duration
+    }
+
+    /// Gets the number of frames queued up for the subscriber that is furthest behind.
+    #[must_use]
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    /// Gets the number of frames subscribers missed because they were not read in time, see [`SubscriptionMode`].
+    #[must_use]
+    pub fn frames_dropped_by_subscribers(&self) -> u64 {
+        self.dropped_by_subscribers
+    }
+
+    /// Gets the total number of dropped frames, by the driver, the backend and the subscribers.
+    #[must_use]
+    pub fn frames_dropped(&self) -> u64 {
+        self.stream.frames_dropped() + self.dropped_by_subscribers
+    }
+
+    /// Gets the [`StreamStats`] of the camera.
+    #[must_use]
+    pub fn stream_stats(&self) -> StreamStats {
+        self.stream
+    }
+}
+
+impl Display for CallbackStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Frame rate: {:.1} FPS, Average callback duration: {:?}, Queue depth: {}, Dropped by subscribers: {}, {}",
+            self.frame_rate,
+            self.average_callback_duration,
+            self.queue_depth,
+            self.dropped_by_subscribers,
+            self.stream
+        )
+    }
+}
+
+/// What the [watchdog](CallbackCamera::enable_watchdog) of a [`CallbackCamera`] does when the stream stalls, besides calling
+/// its callback.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WatchdogAction {
+    /// Only call the callback.
+    Notify,
+    /// Call the callback, then stop and re-open the stream.
+    Restart,
+}
+
+/// What the capture thread of a [`CallbackCamera`] does after the frame callback panicked.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PanicPolicy {
+    /// Keep the stream going, and call the callback again with the next frame.
+    #[default]
+    KeepStreaming,
+    /// Stop the stream and the capture thread. The stream can be opened again with
+    /// [`open_stream()`](CallbackCamera::open_stream).
+    StopStream,
+}
+
+/// Errors of the capture thread, see [`CallbackCamera::last_error()`].
+#[derive(Default)]
+struct ErrorReporter {
+    on_error: Option<Box<dyn FnMut(NokhwaError) + Send + 'static>>,
+    last_error: Option<NokhwaError>,
+    panic_policy: PanicPolicy,
+}
+
+impl ErrorReporter {
+    fn report(&mut self, error: NokhwaError) {
+        self.last_error = Some(error.clone());
+        if let Some(on_error) = &mut self.on_error {
+            on_error(error);
+        }
+    }
+}
+
+struct Watchdog {
+    timeout: Duration,
+    action: WatchdogAction,
+    on_stall: Box<dyn FnMut(Duration) + Send + 'static>,
+    last_frame: Instant,
+}
+
+impl Watchdog {
+    fn deadline(&self) -> Instant {
+        self.last_frame + self.timeout
+    }
+
+    /// Fires if no frame has arrived within the timeout, and starts the next timeout.
+    fn check(&mut self, camera: &mut Camera) {
+        let stalled = self.last_frame.elapsed();
+        if stalled < self.timeout {
+            return;
+        }
+        (self.on_stall)(stalled);
+        if self.action == WatchdogAction::Restart {
+            if camera.is_stream_open() {
+                let _stop_err = camera.stop_stream();
+            }
+            let _open_err = camera.open_stream();
+        }
+        self.last_frame = Instant::now();
+    }
+}
+
+/// How the capture thread is scheduled, see [`CallbackCamera::set_thread_priority()`].
+#[derive(Default)]
+struct ThreadOptions {
+    priority: ThreadPriority,
+    affinity: Option<Vec<usize>>,
+    // set when the option changed since the capture thread last applied it
+    priority_pending: bool,
+    affinity_pending: bool,
+}
+
+/// Limits how often the capture thread delivers frames, see [`CallbackCamera::set_frame_rate_limit()`].
+struct FramePacer {
+    interval: Duration,
+    next_due: Option<Instant>,
+}
+
+impl FramePacer {
+    /// Whether a frame that arrived at `now` is delivered. Frames are expected at a steady rate, so one that arrives a
+    /// little early for its slot is still taken instead of waiting a whole frame for the next one.
+    fn admit(&mut self, now: Instant) -> bool {
+        if let Some(next_due) = self.next_due {
+            if now + self.interval / 8 < next_due {
+                return false;
+            }
+        }
+        // stay on the schedule unless delivery fell a whole interval behind it
+        self.next_due = Some(match self.next_due {
+            Some(next_due) if now.saturating_duration_since(next_due) < self.interval => {
+                next_due + self.interval
+            }
+            _ => now + self.interval,
+        });
+        true
+    }
+}
+
+/// Double buffer holding the newest frame. Writers fill the back slot and then flip it to the front, so readers only
+/// contend with a writer for as long as it takes to clone a frame.
+#[derive(Default)]
+struct LatestFrame {
+    slots: [Mutex<Option<FrameBuffer>>; 2],
+    front: AtomicUsize,
+}
+
+impl LatestFrame {
+    fn store(&self, frame: Option<FrameBuffer>) {
+        let back = 1 - self.front.load(Ordering::Acquire);
+        if let Ok(mut slot) = self.slots[back].lock() {
+            *slot = frame;
+        }
+        self.front.store(back, Ordering::Release);
+    }
+
+    fn load(&self) -> Option<FrameBuffer> {
+        self.slots[self.front.load(Ordering::Acquire)]
+            .lock()
+            .ok()?
+            .clone()
+    }
+}
 
 /// Creates a camera that runs in a different thread that you can use a callback to access the frames of.
 /// It uses a `Arc` and a `Mutex` to ensure that this feels like a normal camera, but callback based.
@@ -59,7 +403,15 @@ type HeldCallbackType = Arc<Mutex<Box<dyn FnMut(FrameBuffer) + Send + 'static>>>
 pub struct CallbackCamera {
     camera: AtomicLock<Camera>,
     frame_callback: HeldCallbackType,
-    last_frame_captured: AtomicLock<FrameBuffer>,
+    last_frame_captured: Arc<LatestFrame>,
+    subscribers: AtomicLock<Vec<Subscriber>>,
+    transformer: AtomicLock<Option<FrameTransformer>>,
+    stats: AtomicLock<DeliveryStats>,
+    watchdog: AtomicLock<Option<Watchdog>>,
+    errors: AtomicLock<ErrorReporter>,
+    on_event: AtomicLock<Option<EventCallback>>,
+    pacer: AtomicLock<Option<FramePacer>>,
+    thread_options: AtomicLock<ThreadOptions>,
     die_bool: Arc<AtomicBool>,
     current_camera: CameraInfo,
     handle: AtomicLock<Option<JoinHandle<()>>>,
@@ -87,12 +439,15 @@ impl CallbackCamera {
         Ok(CallbackCamera {
             camera: arc_camera,
             frame_callback: Arc::new(Mutex::new(Box::new(callback))),
-            last_frame_captured: Arc::new(Mutex::new(FrameBuffer::new(
-                Resolution::new(0, 0),
-                &vec![],
-                GRAY,
-                Instant::now(),
-            ))),
+            last_frame_captured: Arc::new(LatestFrame::default()),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            transformer: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(DeliveryStats::default())),
+            watchdog: Arc::new(Mutex::new(None)),
+            errors: Arc::new(Mutex::new(ErrorReporter::default())),
+            on_event: Arc::new(Mutex::new(None)),
+            pacer: Arc::new(Mutex::new(None)),
+            thread_options: Arc::new(Mutex::new(ThreadOptions::default())),
             die_bool: Arc::new(Default::default()),
             current_camera,
             handle: Arc::new(Mutex::new(None)),
@@ -107,12 +462,15 @@ impl CallbackCamera {
         CallbackCamera {
             camera: Arc::new(Mutex::new(camera)),
             frame_callback: Arc::new(Mutex::new(Box::new(callback))),
-            last_frame_captured: Arc::new(Mutex::new(FrameBuffer::new(
-                Resolution::new(0, 0),
-                &vec![],
-                GRAY,
-                Instant::now(),
-            ))),
+            last_frame_captured: Arc::new(LatestFrame::default()),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            transformer: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(DeliveryStats::default())),
+            watchdog: Arc::new(Mutex::new(None)),
+            errors: Arc::new(Mutex::new(ErrorReporter::default())),
+            on_event: Arc::new(Mutex::new(None)),
+            pacer: Arc::new(Mutex::new(None)),
+            thread_options: Arc::new(Mutex::new(ThreadOptions::default())),
             die_bool: Arc::new(Default::default()),
             current_camera,
             handle: Arc::new(Mutex::new(None)),
@@ -148,6 +506,250 @@ impl CallbackCamera {
         &self.current_camera
     }
 
+    /// Gets the [`StreamStats`] of the capture thread. See [`Camera::stream_stats`](crate::Camera::stream_stats).
+    /// # Errors
+    /// This will error if the camera lock is poisoned.
+    pub fn stream_stats(&self) -> Result<StreamStats, NokhwaError> {
+        Ok(self
+            .camera
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .stream_stats())
+    }
+
+    /// Gets how well the callback thread keeps up with the camera: the rolling frame rate, how long the callback takes,
+    /// how far behind the subscribers are and how many frames were dropped along the way.
+    /// # Errors
+    /// This will error if the camera, subscriber or statistics lock is poisoned.
+    pub fn stats(&self) -> Result<CallbackStats, NokhwaError> {
+        let stream = self.stream_stats()?;
+        let queue_depth = self
+            .subscribers
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .iter()
+            .filter(|subscriber| !subscriber.is_gone())
+            .map(|subscriber| subscriber.sender.len())
+            .max()
+            .unwrap_or_default();
+        let mut stats = self
+            .stats
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?;
+        stats.prune(Instant::now());
+        Ok(CallbackStats {
+            frame_rate: stats.frame_rate(),
+            average_callback_duration: stats.average_callback_duration(),
+            queue_depth,
+            dropped_by_subscribers: stats.dropped_by_subscribers,
+            stream,
+        })
+    }
+
+    /// Enables automatic reconnection. If the device disappears mid-stream, the capture thread keeps waiting for it to return
+    /// instead of failing forever. See [`Camera::enable_reconnect`](crate::Camera::enable_reconnect) for more details.
+    ///
+    /// `on_state_change` is called from the capture thread every time the [`ConnectionState`] changes.
+    /// # Errors
+    /// This will error if the camera lock is poisoned.
+    pub fn enable_reconnect(
+        &mut self,
+        on_state_change: impl FnMut(ConnectionState) + Send + 'static,
+    ) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .enable_reconnect(on_state_change);
+        Ok(())
+    }
+
+    /// Disables automatic reconnection.
+    /// # Errors
+    /// This will error if the camera lock is poisoned.
+    pub fn disable_reconnect(&mut self) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .disable_reconnect();
+        Ok(())
+    }
+
+    /// Enables the watchdog. If no frame arrives for `timeout`, e.g. because the driver wedged or the capture session was
+    /// interrupted, `on_stall` is called from the capture thread with how long the stream has been silent, and the stream
+    /// is restarted if `action` is [`WatchdogAction::Restart`]. The watchdog fires again every `timeout` for as long as the
+    /// stream stays silent. A paused stream, or a device that is being [reconnected](CallbackCamera::enable_reconnect), does
+    /// not count as stalled.
+    ///
+    /// `on_stall` is called while the capture thread holds the camera, so it must not call back into this `CallbackCamera`.
+    /// # Errors
+    /// This will error if the watchdog lock is poisoned.
+    /// # Quirks
+    /// Backends that cannot [wait with a timeout](crate::Camera::frame_timeout) (`Media Foundation`, `OpenCV` and `JSCamera`)
+    /// only notice a stall once the blocked read returns.
+    pub fn enable_watchdog(
+        &mut self,
+        timeout: Duration,
+        action: WatchdogAction,
+        on_stall: impl FnMut(Duration) + Send + 'static,
+    ) -> Result<(), NokhwaError> {
+        *self
+            .watchdog
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = Some(Watchdog {
+            timeout,
+            action,
+            on_stall: Box::new(on_stall),
+            last_frame: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Disables the watchdog.
+    /// # Errors
+    /// This will error if the watchdog lock is poisoned.
+    pub fn disable_watchdog(&mut self) -> Result<(), NokhwaError> {
+        *self
+            .watchdog
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = None;
+        Ok(())
+    }
+
+    /// Sets a callback that is called from the capture thread with every error it runs into: frames that fail to be captured
+    /// or [transformed](CallbackCamera::set_transformer), and panics of the frame callback. Errors of a paused stream, or
+    /// while the device is being [reconnected](CallbackCamera::enable_reconnect), are not reported.
+    ///
+    /// `on_error` must not call back into this `CallbackCamera`.
+    /// # Errors
+    /// This will error if the error lock is poisoned.
+    pub fn set_error_callback(
+        &mut self,
+        on_error: impl FnMut(NokhwaError) + Send + 'static,
+    ) -> Result<(), NokhwaError> {
+        self.errors
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .on_error = Some(Box::new(on_error));
+        Ok(())
+    }
+
+    /// Sets what happens after the frame callback panics. The panic is caught either way, and reported as a
+    /// [`NokhwaError::GeneralError`] through [`last_error()`](CallbackCamera::last_error) and the
+    /// [error callback](CallbackCamera::set_error_callback). The default is [`PanicPolicy::KeepStreaming`].
+    /// # Errors
+    /// This will error if the error lock is poisoned.
+    pub fn set_panic_policy(&mut self, policy: PanicPolicy) -> Result<(), NokhwaError> {
+        self.errors
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .panic_policy = policy;
+        Ok(())
+    }
+
+    /// Limits the rate at which frames are delivered to the callback, the subscribers and
+    /// [`latest_frame()`](CallbackCamera::latest_frame) to `max_fps`, or removes the limit with `None`. The camera keeps
+    /// capturing at its own frame rate, and the frames in between are skipped before they are
+    /// [transformed](CallbackCamera::set_transformer), so every delivered frame is the freshest one there is. This cuts
+    /// the work of e.g. a 60 FPS camera down to 15 FPS without changing its format.
+    /// # Errors
+    /// This will error if `max_fps` is zero, or the pacer lock is poisoned.
+    pub fn set_frame_rate_limit(&mut self, max_fps: Option<u32>) -> Result<(), NokhwaError> {
+        let pacer = match max_fps {
+            Some(0) => {
+                return Err(NokhwaError::SetPropertyError {
+                    property: "Frame rate limit".to_string(),
+                    value: "0".to_string(),
+                    error: "The frame rate limit must be above zero".to_string(),
+                })
+            }
+            Some(max_fps) => Some(FramePacer {
+                interval: Duration::from_secs(1) / max_fps,
+                next_due: None,
+            }),
+            None => None,
+        };
+        *self
+            .pacer
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = pacer;
+        Ok(())
+    }
+
+    /// Sets the [`ThreadPriority`] of the capture thread, which also runs the callback, so frames are not dropped while the
+    /// rest of the application is busy. The default is [`ThreadPriority::Normal`].
+    ///
+    /// The capture thread applies it before it captures its next frame. If that fails, e.g. because the process may not
+    /// raise its priority, the error is reported to the [error callback](CallbackCamera::set_error_callback).
+    /// # Errors
+    /// This will error if the thread option lock is poisoned.
+    pub fn set_thread_priority(&mut self, priority: ThreadPriority) -> Result<(), NokhwaError> {
+        let mut options = self
+            .thread_options
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?;
+        options.priority = priority;
+        options.priority_pending = true;
+        Ok(())
+    }
+
+    /// Pins the capture thread to the CPU cores with the indices in `cores`, or lets it run on any core again with `None`.
+    /// This is applied like [`set_thread_priority()`](CallbackCamera::set_thread_priority).
+    /// # Errors
+    /// This will error if `cores` is empty, or the thread option lock is poisoned.
+    /// # Quirks
+    /// - `Windows`: Only the cores of the first processor group can be used.
+    /// - `macOS` does not let threads be pinned to cores, which is reported to the error callback.
+    pub fn set_thread_affinity(&mut self, cores: Option<Vec<usize>>) -> Result<(), NokhwaError> {
+        if cores.as_ref().is_some_and(Vec::is_empty) {
+            return Err(NokhwaError::SetPropertyError {
+                property: "Thread affinity".to_string(),
+                value: "[]".to_string(),
+                error: "At least one core is needed".to_string(),
+            });
+        }
+        let mut options = self
+            .thread_options
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?;
+        options.affinity = cores;
+        options.affinity_pending = true;
+        Ok(())
+    }
+
+    /// Sets a callback for the [`SessionEvent`]s of this camera. It is called with [`SessionEvent::FormatChanged`] after
+    /// the format was switched, e.g. by [`set_camera_requset()`](CallbackCamera::set_camera_requset), and from the capture
+    /// thread with the events of the backend, such as [`SessionEvent::ControlChanged`], see
+    /// [`Camera::poll_event`](crate::Camera::poll_event).
+    /// # Errors
+    /// This will error if the event lock is poisoned.
+    pub fn set_event_callback(
+        &mut self,
+        on_event: impl FnMut(SessionEvent) + Send + 'static,
+    ) -> Result<(), NokhwaError> {
+        *self
+            .on_event
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = Some(Box::new(on_event));
+        Ok(())
+    }
+
+    /// Gets the last error the capture thread ran into, see [`set_error_callback()`](CallbackCamera::set_error_callback).
+    #[must_use]
+    pub fn last_error(&self) -> Option<NokhwaError> {
+        self.errors.lock().ok()?.last_error.clone()
+    }
+
+    /// Gets the current [`ConnectionState`].
+    /// # Errors
+    /// This will error if the camera lock is poisoned.
+    pub fn connection_state(&self) -> Result<ConnectionState, NokhwaError> {
+        Ok(self
+            .camera
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .connection_state())
+    }
+
     /// Gets the current [`CameraFormat`].
     pub fn camera_format(&self) -> Result<CameraFormat, NokhwaError> {
         Ok(self
@@ -158,26 +760,15 @@ impl CallbackCamera {
     }
 
     /// Will set the current [`CameraFormat`]
-    /// This will reset the current stream if used while stream is opened.
+    /// This will reset the current stream if used while stream is opened, see [`set_camera_requset()`](CallbackCamera::set_camera_requset).
     /// # Errors
     /// If you started the stream and the camera rejects the new camera format, this will return an error.
     #[deprecated(since = "0.10.0", note = "please use `set_camera_requset` instead.")]
     pub fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
-        *self
-            .last_frame_captured
-            .lock()
-            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = FrameBuffer::new(
-            new_fmt.resolution(),
-            &Vec::default(),
-            self.camera_format()?.format(),
-            Instant::now(),
-        );
         let request = RequestedFormat::new(RequestedFormatType::Closest(new_fmt));
-        let set_fmt = self
-            .camera
-            .lock()
-            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
-            .set_camera_requset(request)?;
+        let set_fmt = self.reconfigure(NokhwaError::GeneralError, |camera| {
+            camera.set_camera_requset(request)
+        })?;
         if new_fmt != set_fmt {
             return Err(NokhwaError::SetPropertyError {
                 property: "CameraFormat".to_string(),
@@ -189,7 +780,9 @@ impl CallbackCamera {
     }
 
     /// Will set the current [`CameraFormat`], using a [`RequestedFormat.`]
-    /// This will reset the current stream if used while stream is opened.
+    /// This will reset the current stream if used while stream is opened: the stream is stopped, reconfigured and opened
+    /// again, and frames keep going to the same callback and subscribers. [`SessionEvent::FormatChanged`] is sent to the
+    /// [event callback](CallbackCamera::set_event_callback) afterwards.
     ///
     /// This will also update the cache.
     ///
@@ -200,10 +793,9 @@ impl CallbackCamera {
         &mut self,
         request: RequestedFormat,
     ) -> Result<CameraFormat, NokhwaError> {
-        self.camera
-            .lock()
-            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
-            .set_camera_requset(request)
+        self.reconfigure(NokhwaError::GeneralError, |camera| {
+            camera.set_camera_requset(request)
+        })
     }
     /// A hashmap of [`Resolution`]s mapped to framerates
     /// # Errors
@@ -241,27 +833,18 @@ impl CallbackCamera {
     }
 
     /// Will set the current [`Resolution`]
-    /// This will reset the current stream if used while stream is opened.
+    /// This will reset the current stream if used while stream is opened, see [`set_camera_requset()`](CallbackCamera::set_camera_requset).
     /// # Errors
     /// If you started the stream and the camera rejects the new resolution, this will return an error.
     pub fn set_resolution(&mut self, new_res: Resolution) -> Result<(), NokhwaError> {
-        *self
-            .last_frame_captured
-            .lock()
-            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = FrameBuffer::new(
-            new_res,
-            &Vec::default(),
-            self.camera_format()?.format(),
-            Instant::now(),
-        );
-        self.camera
-            .lock()
-            .map_err(|why| NokhwaError::SetPropertyError {
+        self.reconfigure(
+            |error| NokhwaError::SetPropertyError {
                 property: "Resolution".to_string(),
                 value: new_res.to_string(),
-                error: why.to_string(),
-            })?
-            .set_resolution(new_res)
+                error,
+            },
+            |camera| camera.set_resolution(new_res),
+        )
     }
 
     /// Gets the current camera framerate (See: [`CameraFormat`]).
@@ -277,18 +860,18 @@ impl CallbackCamera {
     }
 
     /// Will set the current framerate
-    /// This will reset the current stream if used while stream is opened.
+    /// This will reset the current stream if used while stream is opened, see [`set_camera_requset()`](CallbackCamera::set_camera_requset).
     /// # Errors
     /// If you started the stream and the camera rejects the new framerate, this will return an error.
     pub fn set_frame_rate(&mut self, new_fps: u32) -> Result<(), NokhwaError> {
-        self.camera
-            .lock()
-            .map_err(|why| NokhwaError::SetPropertyError {
+        self.reconfigure(
+            |error| NokhwaError::SetPropertyError {
                 property: "Framerate".to_string(),
                 value: new_fps.to_string(),
-                error: why.to_string(),
-            })?
-            .set_frame_rate(new_fps)
+                error,
+            },
+            |camera| camera.set_frame_rate(new_fps),
+        )
     }
 
     /// Gets the current camera's frame format (See: [`CameraFormat`]).
@@ -304,18 +887,18 @@ impl CallbackCamera {
     }
 
     /// Will set the current [`FrameFormat`]
-    /// This will reset the current stream if used while stream is opened.
+    /// This will reset the current stream if used while stream is opened, see [`set_camera_requset()`](CallbackCamera::set_camera_requset).
     /// # Errors
     /// If you started the stream and the camera rejects the new frame format, this will return an error.
     pub fn set_frame_format(&mut self, fourcc: FourCC) -> Result<(), NokhwaError> {
-        self.camera
-            .lock()
-            .map_err(|why| NokhwaError::SetPropertyError {
+        self.reconfigure(
+            |error| NokhwaError::SetPropertyError {
                 property: "Framerate".to_string(),
                 value: fourcc.to_string(),
-                error: why.to_string(),
-            })?
-            .set_frame_format(fourcc)
+                error,
+            },
+            |camera| camera.set_frame_format(fourcc),
+        )
     }
 
     /// Gets the current supported list of [`KnownCameraControl`]
@@ -406,7 +989,7 @@ impl CallbackCamera {
     }
 
     /// Sets the control to `control` in the camera.
-    /// Usually, the pipeline is calling [`camera_control()`](crate::camera_traits::CaptureBackendTrait::camera_control), getting a camera control that way
+    /// Usually, the pipeline is calling [`camera_control()`](crate::camera_traits::CaptureDevice::camera_control), getting a camera control that way
     /// then calling [`value()`](crate::utils::CameraControl::value()) to get a [`ControlValueSetter`](crate::utils::ControlValueSetter) and setting the value that way.
     /// # Errors
     /// If the `control` is not supported, the value is invalid (less than min, greater than max, not in step), or there was an error setting the control,
@@ -426,6 +1009,17 @@ impl CallbackCamera {
             .set_camera_control(id, control)
     }
 
+    /// Sets several controls at once, see [`Camera::set_camera_controls`](crate::Camera::set_camera_controls).
+    /// # Errors
+    /// If none of the controls could be set, e.g. because the device could not be locked for configuration, this will error.
+    pub fn set_camera_controls(
+        &mut self,
+        controls: &[(KnownCameraControl, ControlValueSetter)],
+    ) -> Result<Vec<Result<(), NokhwaError>>, NokhwaError> {
+        self.control_camera("Camera Controls", format!("{controls:?}"))?
+            .set_camera_controls(controls)
+    }
+
     /// Will open the camera stream with set parameters. This will be called internally if you try and call [`frame()`](crate::Camera::frame()) before you call [`open_stream()`](crate::Camera::open_stream()).
     /// The callback will be called every frame.
     /// # Errors
@@ -438,6 +1032,12 @@ impl CallbackCamera {
                 property: "thread handle".to_string(),
                 error: why.to_string(),
             })?;
+        // the capture thread stops by itself after a panic with `PanicPolicy::StopStream`
+        if handle_lock.as_ref().is_some_and(JoinHandle::is_finished) {
+            if let Some(handle) = handle_lock.take() {
+                let _finished = handle.join();
+            }
+        }
         if handle_lock.is_none() {
             self.camera
                 .lock()
@@ -447,13 +1047,26 @@ impl CallbackCamera {
                     error: why.to_string(),
                 })?
                 .open_stream()?;
-            let die_bool_clone = self.die_bool.clone();
-            let camera_clone = self.camera.clone();
-            let last_frame = self.last_frame_captured.clone();
-            let callback = self.frame_callback.clone();
-            let handle = std::thread::spawn(move || {
-                camera_frame_thread_loop(camera_clone, callback, last_frame, die_bool_clone)
-            });
+            let thread = CaptureThread {
+                camera: self.camera.clone(),
+                frame_callback: self.frame_callback.clone(),
+                last_frame_captured: self.last_frame_captured.clone(),
+                subscribers: self.subscribers.clone(),
+                transformer: self.transformer.clone(),
+                stats: self.stats.clone(),
+                watchdog: self.watchdog.clone(),
+                errors: self.errors.clone(),
+                pacer: self.pacer.clone(),
+                thread_options: self.thread_options.clone(),
+                on_event: self.on_event.clone(),
+                die_bool: self.die_bool.clone(),
+            };
+            // a new thread starts out with the default scheduling
+            if let Ok(mut options) = self.thread_options.lock() {
+                options.priority_pending = options.priority != ThreadPriority::Normal;
+                options.affinity_pending = options.affinity.is_some();
+            }
+            let handle = std::thread::spawn(move || thread.run());
             *handle_lock = Some(handle);
             Ok(())
         } else {
@@ -478,29 +1091,126 @@ impl CallbackCamera {
         Ok(())
     }
 
+    /// Subscribes to the frames captured by the callback thread. Every subscriber gets its own copy of each frame
+    /// captured after it subscribed, in addition to the callback. Frames are reference counted, so this does not copy
+    /// the frame data.
+    ///
+    /// `mode` decides what happens once the subscriber falls behind, see [`SubscriptionMode`].
+    /// A subscriber is removed once its [`Receiver`] is dropped.
+    /// # Errors
+    /// This will error if the subscriber lock is poisoned.
+    pub fn subscribe(&self, mode: SubscriptionMode) -> Result<Receiver<FrameBuffer>, NokhwaError> {
+        let (subscriber, receiver) = Subscriber::new(mode);
+        self.subscribers
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .push(subscriber);
+        Ok(receiver)
+    }
+
+    /// Sets the [`FrameTransformer`] applied to every frame before it reaches the callback, the subscribers and
+    /// [`poll_frame()`](CallbackCamera::poll_frame), or removes it with `None`. Frames it fails to transform are dropped.
+    /// # Errors
+    /// This will error if the transformer lock is poisoned.
+    pub fn set_transformer(
+        &mut self,
+        transformer: Option<FrameTransformer>,
+    ) -> Result<(), NokhwaError> {
+        let mut current = self
+            .transformer
+            .lock()
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: "Transformer".to_string(),
+                value: format!("{transformer:?}"),
+                error: why.to_string(),
+            })?;
+        *current = transformer;
+        Ok(())
+    }
+
     /// Polls the camera for a frame, analogous to [`Camera::frame`](crate::Camera::frame)
     /// # Errors
-    /// This will error if the camera fails to capture a frame.
+    /// This will error if the camera fails to capture a frame, or the [transformer](CallbackCamera::set_transformer) fails
+    /// to transform it.
     pub fn poll_frame(&mut self) -> Result<FrameBuffer, NokhwaError> {
         let frame = self
             .camera
             .lock()
             .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?
             .frame()?;
-        *self
-            .last_frame_captured
-            .lock()
-            .map_err(|why| NokhwaError::GeneralError(why.to_string()))? = frame.clone();
+        let frame = transform(&self.transformer, frame)?;
+        self.last_frame_captured.store(Some(frame.clone()));
         Ok(frame)
     }
 
-    /// Gets the last frame captured by the camera.
+    /// Like [`poll_frame()`](CallbackCamera::poll_frame), but returns `Ok(None)` if no frame arrived within `timeout`, so
+    /// the caller stays responsive to e.g. shutdown signals. The time spent waiting for the callback thread to let go of the
+    /// camera counts towards `timeout`.
+    /// # Errors
+    /// This will error if the backend cannot wait with a timeout (see [`Camera::frame_timeout`](crate::Camera::frame_timeout)),
+    /// the camera fails to capture a frame, or the [transformer](CallbackCamera::set_transformer) fails to transform it.
+    pub fn poll_frame_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<FrameBuffer>, NokhwaError> {
+        let deadline = Instant::now() + timeout;
+        let Some(mut camera) = self.lock_camera_until(deadline)? else {
+            return Ok(None);
+        };
+        let Some(frame) =
+            camera.frame_timeout(deadline.saturating_duration_since(Instant::now()))?
+        else {
+            return Ok(None);
+        };
+        drop(camera);
+        let frame = transform(&self.transformer, frame)?;
+        self.last_frame_captured.store(Some(frame.clone()));
+        Ok(Some(frame))
+    }
+
+    /// Gets a frame if one is ready and the callback thread is not busy with the camera, returning `Ok(None)` otherwise.
+    /// This never blocks. It is [`poll_frame_timeout()`](CallbackCamera::poll_frame_timeout) with a timeout of zero.
+    /// # Errors
+    /// See [`poll_frame_timeout()`](CallbackCamera::poll_frame_timeout).
+    pub fn try_poll_frame(&mut self) -> Result<Option<FrameBuffer>, NokhwaError> {
+        self.poll_frame_timeout(Duration::ZERO)
+    }
+
+    /// Locks the camera, giving up with `Ok(None)` once `deadline` has passed.
+    fn lock_camera_until(
+        &self,
+        deadline: Instant,
+    ) -> Result<Option<MutexGuard<'_, Camera>>, NokhwaError> {
+        loop {
+            match self.camera.try_lock() {
+                Ok(camera) => return Ok(Some(camera)),
+                Err(TryLockError::Poisoned(why)) => {
+                    return Err(NokhwaError::ReadFrameError(why.to_string()))
+                }
+                Err(TryLockError::WouldBlock) if Instant::now() >= deadline => return Ok(None),
+                Err(TryLockError::WouldBlock) => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    /// Gets the last frame captured by the camera. This is an empty 0x0 frame if nothing was captured yet, or since
+    /// the resolution last changed. See [`latest_frame()`](CallbackCamera::latest_frame) to tell these cases apart.
+    /// # Errors
+    /// This does not error, the `Result` is kept for compatibility.
     pub fn last_frame(&self) -> Result<FrameBuffer, NokhwaError> {
         Ok(self
-            .last_frame_captured
-            .lock()
-            .map_err(|why| NokhwaError::ReadFrameError(why.to_string()))?
-            .clone())
+            .latest_frame()
+            .unwrap_or_else(|| FrameBuffer::new(Resolution::new(0, 0), &[], GRAY, Instant::now())))
+    }
+
+    /// Gets the most recent frame captured by the callback thread, or `None` if there is none yet.
+    ///
+    /// Unlike [`poll_frame()`](CallbackCamera::poll_frame), this neither blocks nor takes a frame from the stream, and
+    /// calling it repeatedly returns the same frame until a new one arrives. This suits renderers that draw at their own
+    /// rate. Frames are reference counted, so this does not copy the frame data.
+    #[must_use]
+    pub fn latest_frame(&self) -> Option<FrameBuffer> {
+        self.last_frame_captured.load()
     }
 
     /// Checks if stream if open. If it is, it will return true.
@@ -515,6 +1225,190 @@ impl CallbackCamera {
             .is_stream_open())
     }
 
+    /// Mirrors frames horizontally, see [`Camera::set_mirrored`](crate::Camera::set_mirrored).
+    /// # Errors
+    /// If the backend supports mirroring but fails to set it, this will error.
+    pub fn set_mirrored(&mut self, mirrored: bool) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: "Mirrored".to_string(),
+                value: mirrored.to_string(),
+                error: why.to_string(),
+            })?
+            .set_mirrored(mirrored)
+    }
+
+    /// Crops frames, see [`Camera::set_crop`](crate::Camera::set_crop).
+    /// # Errors
+    /// If the backend supports cropping but fails to set it, this will error.
+    pub fn set_crop(&mut self, crop: Option<Rect>) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: "Crop".to_string(),
+                value: format!("{crop:?}"),
+                error: why.to_string(),
+            })?
+            .set_crop(crop)
+    }
+
+    /// Sets the exposure, see [`Camera::set_exposure`](crate::Camera::set_exposure).
+    /// # Errors
+    /// If the camera cannot set its exposure this way, or fails to, this will error.
+    pub fn set_exposure(&mut self, exposure: Exposure) -> Result<(), NokhwaError> {
+        self.control_camera("Exposure", exposure.to_string())?
+            .set_exposure(exposure)
+    }
+
+    /// Sets the white balance, see [`Camera::set_white_balance`](crate::Camera::set_white_balance).
+    /// # Errors
+    /// If the camera cannot set its white balance this way, or fails to, this will error.
+    pub fn set_white_balance(&mut self, white_balance: WhiteBalance) -> Result<(), NokhwaError> {
+        self.control_camera("White Balance", white_balance.to_string())?
+            .set_white_balance(white_balance)
+    }
+
+    /// Sets the focus, see [`Camera::set_focus`](crate::Camera::set_focus).
+    /// # Errors
+    /// If the camera cannot set its focus this way, or fails to, this will error.
+    pub fn set_focus(&mut self, focus: Focus) -> Result<(), NokhwaError> {
+        self.control_camera("Focus", focus.to_string())?
+            .set_focus(focus)
+    }
+
+    /// Sets the brightness, see [`Camera::set_brightness`](crate::Camera::set_brightness).
+    /// # Errors
+    /// If the camera has no brightness control, or fails to set it, this will error.
+    pub fn set_brightness(&mut self, brightness: f64) -> Result<(), NokhwaError> {
+        self.control_camera("Brightness", brightness.to_string())?
+            .set_brightness(brightness)
+    }
+
+    /// Makes automatic focus focus on `roi`, see [`Camera::set_focus_roi`](crate::Camera::set_focus_roi).
+    /// # Errors
+    /// If the camera cannot focus on a region, `roi` is not inside the frame, or setting it fails, this will error.
+    pub fn set_focus_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        self.control_camera("Focus Region of Interest", roi.to_string())?
+            .set_focus_roi(roi)
+    }
+
+    /// Makes automatic exposure meter `roi`, see [`Camera::set_exposure_roi`](crate::Camera::set_exposure_roi).
+    /// # Errors
+    /// If the camera cannot meter a region, `roi` is not inside the frame, or setting it fails, this will error.
+    pub fn set_exposure_roi(&mut self, roi: Rect) -> Result<(), NokhwaError> {
+        self.control_camera("Exposure Region of Interest", roi.to_string())?
+            .set_exposure_roi(roi)
+    }
+
+    /// Sends `query` to a control of a UVC extension unit, see [`Camera::xu_query`](crate::Camera::xu_query).
+    /// # Errors
+    /// If the backend cannot talk to extension units, or the camera rejects the query, this will error.
+    pub fn xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: XuQuery,
+        data: &mut [u8],
+    ) -> Result<(), NokhwaError> {
+        self.control_camera(
+            &format!("Extension Unit {unit} Selector {selector}"),
+            query.to_string(),
+        )?
+        .xu_query(unit, selector, query, data)
+    }
+
+    /// Gets what pan, tilt and zoom the camera can do, see [`Camera::ptz_capabilities`](crate::Camera::ptz_capabilities).
+    /// # Errors
+    /// If the backend has no pan, tilt and zoom API, or fails to query the camera, this will error.
+    pub fn ptz_capabilities(&self) -> Result<PtzCapabilities, NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::GetPropertyError {
+                property: "PTZ Capabilities".to_string(),
+                error: why.to_string(),
+            })?
+            .ptz_capabilities()
+    }
+
+    /// Zooms to `zoom`, see [`Camera::set_zoom`](crate::Camera::set_zoom).
+    /// # Errors
+    /// If the camera cannot zoom, or fails to, this will error.
+    pub fn set_zoom(&mut self, zoom: f64) -> Result<(), NokhwaError> {
+        self.control_camera("Zoom", zoom.to_string())?
+            .set_zoom(zoom)
+    }
+
+    /// Zooms to `zoom` smoothly, see [`Camera::ramp_zoom`](crate::Camera::ramp_zoom).
+    /// # Errors
+    /// If the camera cannot zoom at a rate, or fails to, this will error.
+    pub fn ramp_zoom(&mut self, zoom: f64, rate: f64) -> Result<(), NokhwaError> {
+        self.control_camera("Zoom", zoom.to_string())?
+            .ramp_zoom(zoom, rate)
+    }
+
+    /// Points the camera at `pan` and `tilt` degrees, see [`Camera::set_pan_tilt_absolute`](crate::Camera::set_pan_tilt_absolute).
+    /// # Errors
+    /// If the camera cannot pan and tilt, or fails to, this will error.
+    pub fn set_pan_tilt_absolute(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.control_camera("Pan/Tilt", format!("{pan}, {tilt}"))?
+            .set_pan_tilt_absolute(pan, tilt)
+    }
+
+    /// Moves the camera by `pan` and `tilt` degrees, see [`Camera::set_pan_tilt_relative`](crate::Camera::set_pan_tilt_relative).
+    /// # Errors
+    /// If the camera cannot pan and tilt by an offset, or fails to, this will error.
+    pub fn set_pan_tilt_relative(&mut self, pan: f64, tilt: f64) -> Result<(), NokhwaError> {
+        self.control_camera("Relative Pan/Tilt", format!("{pan}, {tilt}"))?
+            .set_pan_tilt_relative(pan, tilt)
+    }
+
+    /// Locks the camera to change a control, turning a poisoned lock into a [`NokhwaError::SetPropertyError`].
+    fn control_camera(
+        &self,
+        property: &str,
+        value: String,
+    ) -> Result<MutexGuard<'_, Camera>, NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: property.to_string(),
+                value,
+                error: why.to_string(),
+            })
+    }
+
+    /// Pauses the stream, see [`Camera::pause_stream`](crate::Camera::pause_stream). The callback is not called while paused.
+    /// # Errors
+    /// If the stream is not open, or the backend fails to pause it, this will error.
+    pub fn pause_stream(&mut self) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?
+            .pause_stream()
+    }
+
+    /// Resumes a paused stream, see [`Camera::resume_stream`](crate::Camera::resume_stream).
+    /// # Errors
+    /// If the backend fails to resume the stream, this will error.
+    pub fn resume_stream(&mut self) -> Result<(), NokhwaError> {
+        self.camera
+            .lock()
+            .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))?
+            .resume_stream()
+    }
+
+    /// Checks if the stream is paused.
+    /// # Errors
+    /// This will error if the camera lock is poisoned.
+    pub fn is_stream_paused(&self) -> Result<bool, NokhwaError> {
+        Ok(self
+            .camera
+            .lock()
+            .map_err(|why| NokhwaError::GeneralError(why.to_string()))?
+            .is_stream_paused())
+    }
+
     /// Will drop the stream.
     /// # Errors
     /// Please check the `Quirks` section of each backend.
@@ -524,34 +1418,445 @@ impl CallbackCamera {
             .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?
             .stop_stream()
     }
+
+    /// Stops the callback thread and the stream, and releases the camera. Dropping the `CallbackCamera` does the same,
+    /// but ignores any errors.
+    ///
+    /// This blocks until the callback thread has finished with the frame it is capturing.
+    /// # Errors
+    /// If the backend fails to stop the stream, or the callback thread panicked, this will error.
+    pub fn close(mut self) -> Result<(), NokhwaError> {
+        self.shut_down()
+    }
+
+    /// Applies `change` to the camera, stopping the stream around it if it is open, so the capture thread picks up where
+    /// it left off with the new format.
+    fn reconfigure<T>(
+        &mut self,
+        lock_error: impl FnOnce(String) -> NokhwaError,
+        change: impl FnOnce(&mut Camera) -> Result<T, NokhwaError>,
+    ) -> Result<T, NokhwaError> {
+        let changed = {
+            let mut camera = self
+                .camera
+                .lock()
+                .map_err(|why| lock_error(why.to_string()))?;
+            let streaming = camera.is_stream_open();
+            if streaming {
+                camera.stop_stream()?;
+            }
+            let changed = change(&mut camera);
+            // reopened even if the change failed, to keep streaming in the old format
+            let reopened = if streaming {
+                camera.open_stream()
+            } else {
+                Ok(())
+            };
+            let changed = changed?;
+            reopened?;
+            changed
+        };
+        self.last_frame_captured.store(None);
+        // the camera lock is let go of first, so the callback may use this camera
+        if let Ok(mut on_event) = self.on_event.lock() {
+            if let Some(on_event) = on_event.as_mut() {
+                on_event(SessionEvent::FormatChanged);
+            }
+        }
+        Ok(changed)
+    }
+
+    fn shut_down(&mut self) -> Result<(), NokhwaError> {
+        self.die_bool.store(true, Ordering::SeqCst);
+        // the thread lets go of the camera lock between frames, and stops once it sees the die bool
+        let stopped = match self.camera.lock() {
+            Ok(mut camera) if camera.is_stream_open() => camera.stop_stream(),
+            Ok(_) => Ok(()),
+            Err(why) => Err(NokhwaError::StreamShutdownError(why.to_string())),
+        };
+        let handle = self
+            .handle
+            .lock()
+            .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?
+            .take();
+        if let Some(handle) = handle {
+            handle.join().map_err(|_| {
+                NokhwaError::StreamShutdownError("Callback thread panicked".to_string())
+            })?;
+        }
+        stopped
+    }
 }
 
 impl Drop for CallbackCamera {
     fn drop(&mut self) {
-        let _stop_stream_err = self.stop_stream();
-        self.die_bool.store(true, Ordering::SeqCst);
+        let _close_err = self.shut_down();
     }
 }
 
-fn camera_frame_thread_loop(
+struct CaptureThread {
     camera: AtomicLock<Camera>,
     frame_callback: HeldCallbackType,
-    last_frame_captured: AtomicLock<FrameBuffer>,
+    last_frame_captured: Arc<LatestFrame>,
+    subscribers: AtomicLock<Vec<Subscriber>>,
+    transformer: AtomicLock<Option<FrameTransformer>>,
+    stats: AtomicLock<DeliveryStats>,
+    watchdog: AtomicLock<Option<Watchdog>>,
+    errors: AtomicLock<ErrorReporter>,
+    pacer: AtomicLock<Option<FramePacer>>,
+    thread_options: AtomicLock<ThreadOptions>,
+    on_event: AtomicLock<Option<EventCallback>>,
     die_bool: Arc<AtomicBool>,
-) {
-    loop {
-        if let Ok(mut camera) = camera.lock() {
-            if let Ok(frame) = camera.frame() {
-                if let Ok(mut last_frame) = last_frame_captured.lock() {
-                    *last_frame = frame.clone();
-                    if let Ok(mut cb) = frame_callback.lock() {
-                        cb(frame);
+}
+
+impl CaptureThread {
+    fn run(self) {
+        loop {
+            self.apply_thread_options();
+            let mut idle = false;
+            // the camera lock is let go of before handing out the frame, so a bounded subscriber that holds up this
+            // thread does not also hold up the camera
+            let (frame, events) = if let Ok(mut camera) = self.camera.lock() {
+                let captured = self.capture(&mut camera);
+                // without a callback, the events are left for the backend's own channel
+                let events = if self.has_event_callback() {
+                    std::iter::from_fn(|| camera.poll_event()).collect()
+                } else {
+                    Vec::new()
+                };
+                let stopped = camera.is_stream_paused()
+                    || camera.connection_state() == ConnectionState::Reconnecting;
+                if let Some(watchdog) = self
+                    .watchdog
+                    .lock()
+                    .ok()
+                    .as_deref_mut()
+                    .and_then(Option::as_mut)
+                {
+                    // neither a paused stream nor a missing device is a stall
+                    if matches!(captured, Ok(Some(_))) || stopped {
+                        watchdog.last_frame = Instant::now();
+                    } else {
+                        watchdog.check(&mut camera);
                     }
                 }
+                idle = captured.is_err() && stopped;
+                // frames the pacer skips are not transformed, to save the work
+                let paced = captured.map(|frame| frame.filter(|_| self.admit()));
+                let frame = match paced.and_then(|frame| {
+                    frame
+                        .map(|frame| transform(&self.transformer, frame))
+                        .transpose()
+                }) {
+                    Ok(frame) => frame,
+                    Err(why) => {
+                        if !stopped {
+                            self.report(why);
+                        }
+                        None
+                    }
+                };
+                (frame, events)
+            } else {
+                (None, Vec::new())
+            };
+            self.emit(events);
+            if let Some(frame) = frame {
+                if !self.deliver(frame) {
+                    if let Ok(mut camera) = self.camera.lock() {
+                        if let Err(why) = camera.stop_stream() {
+                            self.report(why);
+                        }
+                    }
+                    break;
+                }
+            }
+            // don't spin on the camera lock while paused or the device is gone
+            if idle {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            if self.die_bool.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+    }
+
+    fn apply_thread_options(&self) {
+        let Ok(mut options) = self.thread_options.lock() else {
+            return;
+        };
+        let priority = std::mem::take(&mut options.priority_pending).then_some(options.priority);
+        let cores = std::mem::take(&mut options.affinity_pending)
+            .then(|| options.affinity.clone().unwrap_or_default());
+        drop(options);
+        if let Some(Err(why)) = priority.map(set_current_thread_priority) {
+            self.report(why);
+        }
+        if let Some(Err(why)) = cores.map(|cores| set_current_thread_affinity(&cores)) {
+            self.report(why);
+        }
+    }
+
+    /// Checks with the [`FramePacer`] whether a frame that arrived now is delivered.
+    fn admit(&self) -> bool {
+        match self.pacer.lock().as_deref_mut() {
+            Ok(Some(pacer)) => pacer.admit(Instant::now()),
+            Ok(None) | Err(_) => true,
+        }
+    }
+
+    /// Gets the next frame. With a watchdog, this gives up with `Ok(None)` at its deadline, if the backend can wait with a
+    /// timeout.
+    fn capture(&self, camera: &mut Camera) -> Result<Option<FrameBuffer>, NokhwaError> {
+        let deadline = self
+            .watchdog
+            .lock()
+            .ok()
+            .and_then(|watchdog| watchdog.as_ref().map(Watchdog::deadline));
+        let Some(deadline) = deadline else {
+            return camera.frame().map(Some);
+        };
+        let wait = deadline
+            .saturating_duration_since(Instant::now())
+            .min(WATCHDOG_POLL);
+        match camera.frame_timeout(wait) {
+            Err(NokhwaError::UnsupportedOperationError(_)) => camera.frame().map(Some),
+            captured => captured,
+        }
+    }
+
+    /// Hands `frame` to the subscribers and the callback. Returns `false` if the callback panicked and the stream should
+    /// stop.
+    fn deliver(&self, frame: FrameBuffer) -> bool {
+        let delivered = Instant::now();
+        let mut dropped = 0;
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(
+                |subscriber| match subscriber.send(frame.clone(), &self.die_bool) {
+                    Delivery::Queued => true,
+                    Delivery::Dropped => {
+                        dropped += 1;
+                        true
+                    }
+                    Delivery::Gone => false,
+                },
+            );
+        }
+        self.last_frame_captured.store(Some(frame.clone()));
+        let callback_start = Instant::now();
+        // the panic is caught inside the lock, so it does not poison the callback
+        let panicked = match self.frame_callback.lock() {
+            Ok(mut cb) => catch_unwind(AssertUnwindSafe(|| cb(frame))).err(),
+            Err(_) => None,
+        };
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.dropped_by_subscribers += dropped;
+            stats.record(delivered, callback_start.elapsed());
+        }
+        let Some(payload) = panicked else {
+            return true;
+        };
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        self.report(NokhwaError::GeneralError(format!(
+            "Frame callback panicked: {message}"
+        )));
+        self.errors.lock().map_or(true, |errors| {
+            errors.panic_policy == PanicPolicy::KeepStreaming
+        })
+    }
+
+    fn report(&self, error: NokhwaError) {
+        if let Ok(mut errors) = self.errors.lock() {
+            errors.report(error);
+        }
+    }
+
+    fn has_event_callback(&self) -> bool {
+        self.on_event
+            .lock()
+            .is_ok_and(|on_event| on_event.is_some())
+    }
+
+    fn emit(&self, events: Vec<SessionEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        if let Some(on_event) = self
+            .on_event
+            .lock()
+            .ok()
+            .as_deref_mut()
+            .and_then(Option::as_mut)
+        {
+            for event in events {
+                on_event(event);
             }
         }
-        if die_bool.load(Ordering::SeqCst) {
-            break;
+    }
+}
+
+fn transform(
+    transformer: &AtomicLock<Option<FrameTransformer>>,
+    frame: FrameBuffer,
+) -> Result<FrameBuffer, NokhwaError> {
+    match transformer
+        .lock()
+        .map_err(|why| NokhwaError::ProcessFrameError {
+            src: frame.source_frame_format(),
+            destination: "Transform".to_string(),
+            error: why.to_string(),
+        })?
+        .as_ref()
+    {
+        Some(transformer) => transformer.apply(&frame),
+        None => Ok(frame),
+    }
+}
+
+fn set_current_thread_priority(priority: ThreadPriority) -> Result<(), NokhwaError> {
+    #[cfg(all(
+        feature = "input-avfoundation",
+        any(target_os = "macos", target_os = "ios")
+    ))]
+    {
+        nokhwa_bindings_macos::set_current_thread_priority(priority)
+    }
+    #[cfg(all(feature = "input-msmf", target_os = "windows"))]
+    {
+        nokhwa_bindings_windows::wmf::set_current_thread_priority(priority)
+    }
+    #[cfg(all(feature = "input-v4l", target_os = "linux"))]
+    {
+        nokhwa_bindings_linux::set_current_thread_priority(priority)
+    }
+    #[cfg(not(any(
+        all(
+            feature = "input-avfoundation",
+            any(target_os = "macos", target_os = "ios")
+        ),
+        all(feature = "input-msmf", target_os = "windows"),
+        all(feature = "input-v4l", target_os = "linux")
+    )))]
+    {
+        Err(NokhwaError::NotImplementedError(format!(
+            "Setting the thread priority to {priority} needs a native backend"
+        )))
+    }
+}
+
+/// Pins the calling thread to `cores`, or unpins it if `cores` is empty.
+fn set_current_thread_affinity(cores: &[usize]) -> Result<(), NokhwaError> {
+    #[cfg(all(feature = "input-msmf", target_os = "windows"))]
+    {
+        nokhwa_bindings_windows::wmf::set_current_thread_affinity(cores)
+    }
+    #[cfg(all(feature = "input-v4l", target_os = "linux"))]
+    {
+        nokhwa_bindings_linux::set_current_thread_affinity(cores)
+    }
+    #[cfg(not(any(
+        all(feature = "input-msmf", target_os = "windows"),
+        all(feature = "input-v4l", target_os = "linux")
+    )))]
+    {
+        if cores.is_empty() {
+            // threads are not pinned to begin with
+            return Ok(());
+        }
+        Err(NokhwaError::NotImplementedError(
+            "Pinning threads to cores is not supported on this platform".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nokhwa_core::buffer::BufferPool;
+
+    fn frame(pool: &BufferPool) -> FrameBuffer {
+        FrameBuffer::from_pooled(
+            Resolution::new(2, 1),
+            pool.copy_from_slice(&[0; 2]),
+            GRAY,
+            Instant::now(),
+        )
+    }
+
+    #[test]
+    fn dropped_latest_only_subscribers_are_removed() {
+        let pool = BufferPool::new(4);
+        let die_bool = AtomicBool::new(false);
+        let (subscriber, receiver) = Subscriber::new(SubscriptionMode::LatestOnly);
+        assert!(matches!(
+            subscriber.send(frame(&pool), &die_bool),
+            Delivery::Queued
+        ));
+        // the first frame is evicted and goes back to the pool
+        assert!(matches!(
+            subscriber.send(frame(&pool), &die_bool),
+            Delivery::Dropped
+        ));
+        assert_eq!(pool.idle(), 1);
+        assert!(!subscriber.is_gone());
+
+        drop(receiver);
+        assert!(subscriber.is_gone());
+        assert!(matches!(
+            subscriber.send(frame(&pool), &die_bool),
+            Delivery::Gone
+        ));
+        let mut subscribers = vec![subscriber];
+        subscribers.retain(|subscriber| !subscriber.is_gone());
+        assert!(subscribers.is_empty());
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[test]
+    fn dropped_drop_oldest_subscribers_are_removed() {
+        let pool = BufferPool::new(4);
+        let die_bool = AtomicBool::new(false);
+        let (subscriber, receiver) = Subscriber::new(SubscriptionMode::DropOldest(2));
+        for _ in 0..3 {
+            subscriber.send(frame(&pool), &die_bool);
+        }
+        assert_eq!(receiver.len(), 2);
+        drop(receiver);
+        assert!(matches!(
+            subscriber.send(frame(&pool), &die_bool),
+            Delivery::Gone
+        ));
+        // all three frames that were ever allocated are back
+        drop(subscriber);
+        assert_eq!(pool.idle(), 3);
+    }
+
+    #[test]
+    fn live_subscribers_are_kept() {
+        let pool = BufferPool::new(4);
+        let die_bool = AtomicBool::new(false);
+        for mode in [
+            SubscriptionMode::EveryFrame,
+            SubscriptionMode::Bounded(1),
+            SubscriptionMode::DropOldest(1),
+            SubscriptionMode::LatestOnly,
+        ] {
+            let (subscriber, receiver) = Subscriber::new(mode);
+            assert!(!subscriber.is_gone(), "{mode:?}");
+            assert!(matches!(
+                subscriber.send(frame(&pool), &die_bool),
+                Delivery::Queued
+            ));
+            assert_eq!(
+                receiver.try_recv().map(|frame| frame.source_frame_format()),
+                Ok(GRAY)
+            );
+            drop(receiver);
+            assert!(subscriber.is_gone(), "{mode:?}");
         }
     }
 }
@@ -0,0 +1,261 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::Camera;
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    types::{ApiBackend, CameraFormat, CameraIndex, CameraInfo, RequestedFormat},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    sync::{broadcast, Mutex},
+    task::JoinHandle,
+};
+
+/// How many frames a subscriber may fall behind by before it starts missing frames.
+const FRAME_CHANNEL_CAPACITY: usize = 4;
+
+type AsyncCallback =
+    Box<dyn FnMut(FrameBuffer) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + 'static>;
+type HeldCallbackType = Arc<Mutex<AsyncCallback>>;
+
+/// A [`CallbackCamera`](crate::CallbackCamera) for `tokio` applications.
+///
+/// Capturing runs on `tokio`'s blocking thread pool, and every frame is published on a
+/// [`broadcast`] channel. Your `async` callback is awaited for each frame on a regular `tokio` task,
+/// and any number of extra consumers can [`subscribe()`](TokioCallbackCamera::subscribe).
+///
+/// A slow callback or subscriber does not stall the camera. Instead it will skip the frames it fell
+/// behind on.
+///
+/// All methods must be called from within a `tokio` runtime.
+#[cfg_attr(feature = "docs-features", doc(cfg(feature = "output-tokio")))]
+pub struct TokioCallbackCamera {
+    camera: Arc<Mutex<Camera>>,
+    frame_callback: HeldCallbackType,
+    frames: broadcast::Sender<FrameBuffer>,
+    die_bool: Arc<AtomicBool>,
+    current_camera: CameraInfo,
+    tasks: Option<(JoinHandle<()>, JoinHandle<()>)>,
+}
+
+impl TokioCallbackCamera {
+    /// Create a new `TokioCallbackCamera` from a [`CameraIndex`] and [`format`](RequestedFormat).
+    /// The camera is opened on the blocking thread pool.
+    ///
+    /// # Errors
+    /// This will error if you either have a bad platform configuration (e.g. `input-v4l` but not on linux) or the backend cannot create the camera (e.g. permission denied).
+    pub async fn new<F, Fut>(
+        index: CameraIndex,
+        format: RequestedFormat,
+        callback: F,
+    ) -> Result<Self, NokhwaError>
+    where
+        F: FnMut(FrameBuffer) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let camera = tokio::task::spawn_blocking(move || Camera::new(index, format))
+            .await
+            .map_err(|why| NokhwaError::OpenDeviceError("tokio".to_string(), why.to_string()))??;
+        Ok(Self::with_custom(camera, callback))
+    }
+
+    /// Allows creation of a [`TokioCallbackCamera`] with a custom backend. This is useful if you are creating e.g. a custom module.
+    ///
+    /// You **must** have set a format beforehand.
+    pub fn with_custom<F, Fut>(camera: Camera, callback: F) -> Self
+    where
+        F: FnMut(FrameBuffer) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let current_camera = camera.info().clone();
+        let (frames, _) = broadcast::channel(FRAME_CHANNEL_CAPACITY);
+        TokioCallbackCamera {
+            camera: Arc::new(Mutex::new(camera)),
+            frame_callback: Arc::new(Mutex::new(box_callback(callback))),
+            frames,
+            die_bool: Arc::new(AtomicBool::new(false)),
+            current_camera,
+            tasks: None,
+        }
+    }
+
+    /// Gets the current Camera's unique ID.
+    #[must_use]
+    pub fn index(&self) -> String {
+        self.current_camera.unique_id()
+    }
+
+    /// Gets the camera information such as Name and Index as a [`CameraInfo`].
+    #[must_use]
+    pub fn info(&self) -> &CameraInfo {
+        &self.current_camera
+    }
+
+    /// Gets the current Camera's backend
+    pub async fn backend(&self) -> ApiBackend {
+        self.camera.lock().await.backend()
+    }
+
+    /// Gets the current [`CameraFormat`].
+    pub async fn camera_format(&self) -> CameraFormat {
+        self.camera.lock().await.camera_format()
+    }
+
+    /// Will set the current [`CameraFormat`], using a [`RequestedFormat`].
+    /// This will reset the current stream if used while stream is opened.
+    ///
+    /// This will return the new [`CameraFormat`]
+    /// # Errors
+    /// If nothing fits the requested criteria, this will return an error.
+    pub async fn set_camera_requset(
+        &self,
+        request: RequestedFormat,
+    ) -> Result<CameraFormat, NokhwaError> {
+        let camera = self.camera.clone();
+        tokio::task::spawn_blocking(move || camera.blocking_lock().set_camera_requset(request))
+            .await
+            .map_err(|why| NokhwaError::SetPropertyError {
+                property: "Camera Format".to_string(),
+                value: request.to_string(),
+                error: why.to_string(),
+            })?
+    }
+
+    /// Subscribes to the frames of this camera. Every subscriber gets its own copy of each frame
+    /// captured after it subscribed.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<FrameBuffer> {
+        self.frames.subscribe()
+    }
+
+    /// Sets the frame callback to the new specified function. This function will be called instead of the previous one(s).
+    pub async fn set_callback<F, Fut>(&self, callback: F)
+    where
+        F: FnMut(FrameBuffer) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        *self.frame_callback.lock().await = box_callback(callback);
+    }
+
+    /// Will open the camera stream and start capturing. The callback will be called every frame.
+    /// # Errors
+    /// If the specific backend fails to open the camera (e.g. already taken, busy, doesn't exist anymore) this will error.
+    pub async fn open_stream(&mut self) -> Result<(), NokhwaError> {
+        if self.tasks.is_some() {
+            return Err(NokhwaError::OpenStreamError(
+                "Stream Already Open".to_string(),
+            ));
+        }
+
+        let camera = self.camera.clone();
+        tokio::task::spawn_blocking(move || camera.blocking_lock().open_stream())
+            .await
+            .map_err(|why| NokhwaError::OpenStreamError(why.to_string()))??;
+
+        self.die_bool.store(false, Ordering::SeqCst);
+        let camera = self.camera.clone();
+        let frames = self.frames.clone();
+        let die_bool = self.die_bool.clone();
+        let capture = tokio::task::spawn_blocking(move || {
+            camera_frame_task_loop(&camera, &frames, &die_bool);
+        });
+        let callback = tokio::spawn(frame_callback_task_loop(
+            self.frames.subscribe(),
+            self.frame_callback.clone(),
+        ));
+        self.tasks = Some((capture, callback));
+        Ok(())
+    }
+
+    /// Checks if stream if open. If it is, it will return true.
+    pub async fn is_stream_open(&self) -> bool {
+        self.camera.lock().await.is_stream_open()
+    }
+
+    /// Will drop the stream, waiting for the capture task to finish.
+    /// # Errors
+    /// Please check the `Quirks` section of each backend.
+    pub async fn stop_stream(&mut self) -> Result<(), NokhwaError> {
+        self.die_bool.store(true, Ordering::SeqCst);
+        if let Some((capture, callback)) = self.tasks.take() {
+            callback.abort();
+            capture
+                .await
+                .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?;
+        }
+
+        let camera = self.camera.clone();
+        tokio::task::spawn_blocking(move || camera.blocking_lock().stop_stream())
+            .await
+            .map_err(|why| NokhwaError::StreamShutdownError(why.to_string()))?
+    }
+}
+
+impl Drop for TokioCallbackCamera {
+    fn drop(&mut self) {
+        // the capture task holds its own reference to the camera, which stops the stream once it exits
+        self.die_bool.store(true, Ordering::SeqCst);
+        if let Some((_, callback)) = self.tasks.take() {
+            callback.abort();
+        }
+    }
+}
+
+fn box_callback<F, Fut>(mut callback: F) -> AsyncCallback
+where
+    F: FnMut(FrameBuffer) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Box::new(move |frame| Box::pin(callback(frame)))
+}
+
+fn camera_frame_task_loop(
+    camera: &Mutex<Camera>,
+    frames: &broadcast::Sender<FrameBuffer>,
+    die_bool: &AtomicBool,
+) {
+    while !die_bool.load(Ordering::SeqCst) {
+        if let Ok(frame) = camera.blocking_lock().frame() {
+            // no receivers is not an error, the frame is simply dropped
+            let _ = frames.send(frame);
+        }
+    }
+}
+
+async fn frame_callback_task_loop(
+    mut frames: broadcast::Receiver<FrameBuffer>,
+    frame_callback: HeldCallbackType,
+) {
+    loop {
+        match frames.recv().await {
+            Ok(frame) => {
+                let callback_future = (frame_callback.lock().await)(frame);
+                callback_future.await;
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
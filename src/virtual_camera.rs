@@ -0,0 +1,126 @@
+/*
+ * Copyright 2022 l1npengtul <l1npengtul@protonmail.com> / The Nokhwa Contributors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#[cfg(target_os = "macos")]
+use crate::backends::output::CoreMediaIOVirtualCamera;
+#[cfg(target_os = "windows")]
+use crate::backends::output::OBSVirtualCamera;
+#[cfg(target_os = "linux")]
+use crate::backends::output::V4L2LoopbackDevice;
+use nokhwa_core::{
+    buffer::FrameBuffer,
+    error::NokhwaError,
+    traits::VirtualBackendTrait,
+    types::{ApiBackend, CameraFormat, CameraIndex},
+};
+
+/// A virtual camera that republishes [`FrameBuffer`]s so that other applications on the system can open it like a regular webcam.
+/// This is the output counterpart of [`Camera`](crate::Camera), meant for filter/effects applications.
+///
+/// # Platform Support
+/// - Linux: `v4l2loopback` (see `V4L2LoopbackDevice`)
+/// - Windows: OBS Virtual Camera, which needs OBS Studio installed (see `OBSVirtualCamera`). The index is ignored, as there is only one.
+/// - Mac OS: The sink stream of a `CoreMediaIO` camera extension, such as the one of OBS (see `CoreMediaIOVirtualCamera`).
+///   The extension decides the resolution and `FourCC` of the frames.
+///
+/// Elsewhere, [`new()`](VirtualCamera::new) will return a [`NotImplementedError`](NokhwaError::NotImplementedError).
+/// You can still provide your own sink through [`with_custom()`](VirtualCamera::with_custom).
+pub struct VirtualCamera {
+    idx: CameraIndex,
+    device: Box<dyn VirtualBackendTrait>,
+}
+
+impl VirtualCamera {
+    /// Opens the virtual camera at `index` and makes it advertise `format` to its consumers.
+    /// # Errors
+    /// This will error if the platform has no supported virtual camera, or if the device cannot be opened or rejects the format.
+    pub fn new(index: CameraIndex, format: CameraFormat) -> Result<Self, NokhwaError> {
+        let device = init_virtual_camera(&index, format)?;
+        Ok(VirtualCamera { idx: index, device })
+    }
+
+    /// Create a new [`VirtualCamera`] from a custom [`VirtualBackendTrait`] sink.
+    #[must_use]
+    pub fn with_custom(index: CameraIndex, device: Box<dyn VirtualBackendTrait>) -> Self {
+        VirtualCamera { idx: index, device }
+    }
+
+    /// Gets the current virtual camera's index.
+    #[must_use]
+    pub fn index(&self) -> &CameraIndex {
+        &self.idx
+    }
+
+    /// Gets the current backend used by the sink.
+    #[must_use]
+    pub fn backend(&self) -> ApiBackend {
+        self.device.backend()
+    }
+
+    /// Gets the [`CameraFormat`] being advertised.
+    #[must_use]
+    pub fn camera_format(&self) -> CameraFormat {
+        self.device.camera_format()
+    }
+
+    /// Changes the [`CameraFormat`] being advertised.
+    /// # Errors
+    /// If the sink rejects the new format, this will error.
+    pub fn set_camera_format(&mut self, new_fmt: CameraFormat) -> Result<(), NokhwaError> {
+        self.device.set_camera_format(new_fmt)
+    }
+
+    /// Pushes a frame to the virtual camera. The frame must match the current [`CameraFormat`].
+    /// # Errors
+    /// If the frame does not match the current format, or the sink fails to accept it, this will error.
+    pub fn write_frame(&mut self, frame: &FrameBuffer) -> Result<(), NokhwaError> {
+        self.device.write_frame(frame)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn init_virtual_camera(
+    index: &CameraIndex,
+    format: CameraFormat,
+) -> Result<Box<dyn VirtualBackendTrait>, NokhwaError> {
+    Ok(V4L2LoopbackDevice::new(index, format)?.into())
+}
+
+#[cfg(target_os = "windows")]
+fn init_virtual_camera(
+    _index: &CameraIndex,
+    format: CameraFormat,
+) -> Result<Box<dyn VirtualBackendTrait>, NokhwaError> {
+    Ok(OBSVirtualCamera::new(format)?.into())
+}
+
+#[cfg(target_os = "macos")]
+fn init_virtual_camera(
+    index: &CameraIndex,
+    format: CameraFormat,
+) -> Result<Box<dyn VirtualBackendTrait>, NokhwaError> {
+    Ok(CoreMediaIOVirtualCamera::new(index, format)?.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn init_virtual_camera(
+    _index: &CameraIndex,
+    _format: CameraFormat,
+) -> Result<Box<dyn VirtualBackendTrait>, NokhwaError> {
+    Err(NokhwaError::NotImplementedError(
+        "Virtual cameras are only supported on Linux, Windows and Mac OS".to_string(),
+    ))
+}